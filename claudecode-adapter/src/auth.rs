@@ -0,0 +1,181 @@
+//! Login state detection for the Claude CLI.
+
+use crate::error::ClaudeError;
+use crate::types::{AuthState, AuthStatus, RunResult};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Command that starts the interactive Claude login flow.
+pub const LOGIN_COMMAND: &str = "claude auth login";
+
+/// Environment variable that authenticates the CLI without a stored login.
+const API_KEY_ENV_VAR: &str = "ANTHROPIC_API_KEY";
+
+/// How long `claude auth status` may take before the state is reported as unknown.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lowercase fragments the CLI prints when a run fails for lack of credentials.
+const AUTH_FAILURE_MARKERS: &[&str] = &[
+    "invalid api key",
+    "please run /login",
+    "not logged in",
+    "oauth token has expired",
+    "authentication_error",
+];
+
+/// Reports whether the Claude CLI at `path` has usable credentials.
+///
+/// An `ANTHROPIC_API_KEY` in the environment counts as logged in. Otherwise
+/// `claude auth status` is run and its output parsed; CLIs that predate the
+/// command, or that do not answer within 10 seconds, report
+/// [`AuthState::Unknown`].
+///
+/// # Errors
+///
+/// Returns `ClaudeError::SpawnFailed` if the status command cannot be executed.
+pub async fn auth_status(path: &Path) -> Result<AuthStatus, ClaudeError> {
+    if std::env::var_os(API_KEY_ENV_VAR).is_some_and(|v| !v.is_empty()) {
+        return Ok(status(
+            AuthState::LoggedIn,
+            Some(API_KEY_ENV_VAR.to_string()),
+        ));
+    }
+
+    let mut cmd = Command::new(path);
+    cmd.args(["auth", "status"])
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    cmd.creation_flags(0x0800_0000); // CREATE_NO_WINDOW
+
+    let Ok(output) = tokio::time::timeout(STATUS_TIMEOUT, cmd.output()).await else {
+        return Ok(status(AuthState::Unknown, None));
+    };
+    let output = output.map_err(|e| ClaudeError::SpawnFailed {
+        stage: "auth status".to_string(),
+        source: e,
+    })?;
+
+    Ok(parse_auth_status(
+        &String::from_utf8_lossy(&output.stdout),
+        &String::from_utf8_lossy(&output.stderr),
+    ))
+}
+
+/// Parses the output of `claude auth status`.
+///
+/// Newer CLIs print a JSON object with a `loggedIn` flag; older ones print
+/// a human-readable line, which is matched on "not logged in" / "logged in".
+pub(crate) fn parse_auth_status(stdout: &str, stderr: &str) -> AuthStatus {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(stdout.trim()) {
+        if let Some(logged_in) = json.get("loggedIn").and_then(serde_json::Value::as_bool) {
+            let method = json
+                .get("authMethod")
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string);
+            let state = if logged_in {
+                AuthState::LoggedIn
+            } else {
+                AuthState::LoggedOut
+            };
+            return status(state, method);
+        }
+    }
+
+    let text = format!("{stdout}\n{stderr}").to_lowercase();
+    if text.contains("not logged in") || text.contains("logged out") {
+        status(AuthState::LoggedOut, None)
+    } else if text.contains("logged in") {
+        status(AuthState::LoggedIn, None)
+    } else {
+        status(AuthState::Unknown, None)
+    }
+}
+
+/// Returns `true` if the output of a failed run indicates missing or rejected credentials.
+pub(crate) fn is_auth_failure(stdout: &str, stderr: &str) -> bool {
+    let stdout = stdout.to_lowercase();
+    let stderr = stderr.to_lowercase();
+    AUTH_FAILURE_MARKERS
+        .iter()
+        .any(|marker| stdout.contains(marker) || stderr.contains(marker))
+}
+
+/// Converts a failed run caused by missing credentials into `ClaudeError::AuthRequired`.
+pub(crate) fn ensure_authenticated(result: RunResult) -> Result<RunResult, ClaudeError> {
+    if result.exit_code != 0 && is_auth_failure(&result.stdout, &result.stderr) {
+        return Err(ClaudeError::AuthRequired {
+            login_command: LOGIN_COMMAND.to_string(),
+        });
+    }
+    Ok(result)
+}
+
+fn status(state: AuthState, method: Option<String>) -> AuthStatus {
+    AuthStatus {
+        state,
+        method,
+        login_command: LOGIN_COMMAND.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::types::LatencyBreakdown;
+
+    #[test]
+    fn test_parse_json_auth_status() {
+        let logged_in = parse_auth_status(r#"{"loggedIn":true,"authMethod":"claude.ai"}"#, "");
+        assert_eq!(logged_in.state, AuthState::LoggedIn);
+        assert_eq!(logged_in.method.as_deref(), Some("claude.ai"));
+
+        let logged_out = parse_auth_status(r#"{"loggedIn":false}"#, "");
+        assert_eq!(logged_out.state, AuthState::LoggedOut);
+        assert_eq!(logged_out.login_command, LOGIN_COMMAND);
+    }
+
+    #[test]
+    fn test_parse_text_auth_status() {
+        assert_eq!(
+            parse_auth_status("", "Not logged in. Run claude auth login.").state,
+            AuthState::LoggedOut
+        );
+        assert_eq!(
+            parse_auth_status("Logged in as user@example.com", "").state,
+            AuthState::LoggedIn
+        );
+        assert_eq!(
+            parse_auth_status("", "error: unknown command 'auth'").state,
+            AuthState::Unknown
+        );
+    }
+
+    #[test]
+    fn test_ensure_authenticated_maps_auth_failures() {
+        let result = |exit_code, stdout: &str| RunResult {
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            exit_code,
+            duration_ms: 0,
+            json: None,
+            stream_events: Vec::new(),
+            diagnostics: Vec::new(),
+            structured_output: None,
+            session_id: None,
+            result_text: None,
+            latency: LatencyBreakdown::default(),
+            event_offsets_ms: Vec::new(),
+        };
+
+        let err =
+            ensure_authenticated(result(1, "Invalid API key · Please run /login")).unwrap_err();
+        assert_eq!(err.error_code(), "auth_required");
+
+        assert!(ensure_authenticated(result(1, "some other failure")).is_ok());
+        assert!(ensure_authenticated(result(0, "not logged in is fine on success")).is_ok());
+    }
+}
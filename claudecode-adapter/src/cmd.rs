@@ -6,6 +6,7 @@
 //! - `--print`: Non-interactive mode, returns output directly
 //! - `--output-format <format>`: text | json | stream-json
 //! - `--model <model>`: Model selection (e.g., claude-sonnet-4)
+//! - `--max-turns <n>`: Stop after `n` agentic turns (print mode only)
 //!
 //! ### System Prompt Flags
 //! - `--system-prompt <prompt>`: Replace default system prompt entirely
@@ -24,6 +25,21 @@
 //! ### JSON Schema Flags
 //! - `--json-schema <schema>`: Force JSON output matching schema
 //!
+//! ### Settings Flags
+//! - `--setting-sources <sources>`: Which user, project and local settings files to load
+//! - `--settings <file-or-json>`: Settings layered over those files
+//!
+//! ### Permission Flags
+//! - `--permission-mode <mode>`: default | acceptEdits | plan
+//! - `--dangerously-skip-permissions`: Skip every permission check (bypass mode)
+//!
+//! ### Subagent Flags
+//! - `--agents <json>`: Subagent definitions (description, prompt, tools, model) keyed by name
+//!
+//! ### Session Flags
+//! - `--continue`: Continue the most recent session in the working directory
+//! - `--resume <id>`: Resume a specific session by ID
+//!
 //! ## Flag Combinations and Compatibility
 //!
 //! ### Valid Containment Combinations
@@ -46,6 +62,11 @@
 //! - `--disable-slash-commands`: Available in current versions
 //! - Flag availability varies by CLI version; check `claude --help`
 //!
+//! When [`RunConfig::capabilities`] is set, flags the installed CLI lacks
+//! are gated: `--disable-slash-commands`, `--no-session-persistence` and
+//! `--include-partial-messages` are left out with a warning, and
+//! [`check_features`] rejects configs that need any other missing flag.
+//!
 //! ## Known Limitations
 //! - `--strict-mcp-config` does not override `disabledMcpServers` in ~/.claude.json
 //!   ([GitHub #14490](https://github.com/anthropics/claude-code/issues/14490))
@@ -54,10 +75,92 @@
 //! ## External References
 //! - [Claude CLI Reference](https://docs.anthropic.com/en/docs/claude-code/cli-reference)
 
-use crate::types::{BuiltinToolSet, JsonSchema, OutputFormat, RunConfig, SystemPromptMode};
+use crate::error::ClaudeError;
+use crate::types::{
+    BuiltinToolSet, Feature, JsonSchema, OutputFormat, PermissionMode, RunConfig, SessionMode,
+    Settings, SystemPromptMode,
+};
+use std::collections::BTreeSet;
 use std::ffi::OsString;
 use std::path::Path;
 
+/// Checks that the installed CLI supports every flag `config` needs.
+///
+/// Flags [`build_args`] can leave out are not checked, and nothing is when
+/// [`RunConfig::capabilities`] is `None`.
+///
+/// # Errors
+///
+/// Returns `ClaudeError::UnsupportedFeature` listing every needed feature
+/// the CLI lacks.
+pub fn check_features(config: &RunConfig) -> Result<(), ClaudeError> {
+    let Some(capabilities) = &config.capabilities else {
+        return Ok(());
+    };
+    let features: Vec<Feature> = required_features(config)
+        .into_iter()
+        .filter(|feature| !capabilities.supports(*feature))
+        .collect();
+    if features.is_empty() {
+        Ok(())
+    } else {
+        Err(ClaudeError::UnsupportedFeature {
+            version: config.cli_version.clone(),
+            features,
+        })
+    }
+}
+
+/// Features behind the flags `config` cannot do without.
+fn required_features(config: &RunConfig) -> BTreeSet<Feature> {
+    let mut features = BTreeSet::new();
+    if config.output_format == Some(OutputFormat::StreamJson) {
+        features.insert(Feature::StreamJson);
+    }
+    match &config.system_prompt {
+        SystemPromptMode::None => {}
+        SystemPromptMode::Append(_) => {
+            features.insert(Feature::AppendSystemPrompt);
+        }
+        SystemPromptMode::Replace(_) => {
+            features.insert(Feature::SystemPrompt);
+        }
+    }
+    if let Some(mcp) = &config.mcp {
+        if !mcp.configs.is_empty() {
+            features.insert(Feature::Mcp);
+        }
+        if mcp.strict {
+            features.insert(Feature::StrictMcp);
+        }
+    }
+    if config.tools.builtin != BuiltinToolSet::Default {
+        features.insert(Feature::ToolsFlag);
+    }
+    if !matches!(config.json_schema, JsonSchema::None) {
+        features.insert(Feature::JsonSchema);
+    }
+    if config.setting_sources.is_some() {
+        features.insert(Feature::SettingSources);
+    }
+    if config.settings != Settings::None {
+        features.insert(Feature::Settings);
+    }
+    match config.permission_mode {
+        None => {}
+        Some(PermissionMode::BypassPermissions) => {
+            features.insert(Feature::SkipPermissions);
+        }
+        Some(_) => {
+            features.insert(Feature::PermissionMode);
+        }
+    }
+    if !config.agents.is_empty() {
+        features.insert(Feature::Agents);
+    }
+    features
+}
+
 /// Builds the argument list for a `claude --print` invocation from the given
 /// prompt and configuration.
 ///
@@ -65,12 +168,29 @@ use std::path::Path;
 /// or `Replace` mode) has already been written to the given path.  The
 /// corresponding `--*-file` CLI flag is emitted instead of the inline flag,
 /// keeping the OS argument list short and avoiding length limits on Windows.
+///
+/// Optional flags the CLI lacks according to [`RunConfig::capabilities`] are
+/// left out with a warning; see [`check_features`] for the others.
 #[must_use]
+#[allow(clippy::too_many_lines)]
 pub fn build_args(
     prompt: &str,
     config: &RunConfig,
     system_prompt_file: Option<&Path>,
 ) -> Vec<OsString> {
+    let optional = |feature: Feature| {
+        let supported = config
+            .capabilities
+            .as_ref()
+            .is_none_or(|capabilities| capabilities.supports(feature));
+        if !supported {
+            tracing::warn!(
+                flag = feature.flag(),
+                "Claude CLI does not support this flag; leaving it out"
+            );
+        }
+        supported
+    };
     let mut args = Vec::new();
 
     args.push(OsString::from("--print"));
@@ -80,6 +200,12 @@ pub fn build_args(
         args.push(OsString::from(model));
     }
 
+    // Hidden from `claude --help`, so it is not gated on capabilities.
+    if let Some(turns) = config.max_turns {
+        args.push(OsString::from("--max-turns"));
+        args.push(OsString::from(turns.to_string()));
+    }
+
     if let Some(format) = config.output_format {
         args.push(OsString::from("--output-format"));
         match format {
@@ -89,6 +215,9 @@ pub fn build_args(
                 args.push(OsString::from("stream-json"));
                 // Claude Code requires --verbose when using --print with stream-json
                 args.push(OsString::from("--verbose"));
+                if config.include_partial_messages && optional(Feature::IncludePartialMessages) {
+                    args.push(OsString::from("--include-partial-messages"));
+                }
             }
         }
     }
@@ -145,7 +274,7 @@ pub fn build_args(
         args.push(OsString::from(disallowed.join(",")));
     }
 
-    if config.tools.disable_slash_commands {
+    if config.tools.disable_slash_commands && optional(Feature::DisableSlashCommands) {
         args.push(OsString::from("--disable-slash-commands"));
     }
 
@@ -161,7 +290,7 @@ pub fn build_args(
         }
     }
 
-    if config.no_session_persistence {
+    if config.no_session_persistence && optional(Feature::NoSessionPersistence) {
         args.push(OsString::from("--no-session-persistence"));
     }
 
@@ -170,6 +299,48 @@ pub fn build_args(
         args.push(OsString::from(sources));
     }
 
+    match config.permission_mode {
+        None => {}
+        Some(PermissionMode::BypassPermissions) => {
+            tracing::warn!(
+                "running Claude CLI with --dangerously-skip-permissions; the agent may edit \
+                 files and run commands without confirmation"
+            );
+            args.push(OsString::from("--dangerously-skip-permissions"));
+        }
+        Some(mode) => {
+            args.push(OsString::from("--permission-mode"));
+            args.push(OsString::from(mode.as_str()));
+        }
+    }
+
+    match &config.settings {
+        Settings::None => {}
+        Settings::Override(settings) => {
+            args.push(OsString::from("--settings"));
+            args.push(OsString::from(settings.to_json().to_string()));
+        }
+        Settings::File(path) => {
+            args.push(OsString::from("--settings"));
+            args.push(OsString::from(path));
+        }
+    }
+
+    if !config.agents.is_empty() {
+        args.push(OsString::from("--agents"));
+        args.push(OsString::from(config.agents.to_json().to_string()));
+    }
+
+    match &config.session {
+        SessionMode::New => {}
+        SessionMode::Continue => args.push(OsString::from("--continue")),
+        SessionMode::Resume(id) => {
+            args.push(OsString::from("--resume"));
+            args.push(OsString::from(id));
+        }
+    }
+
+    args.extend(config.extra_args.iter().cloned());
     args.push(OsString::from(prompt));
 
     args
@@ -515,6 +686,20 @@ mod tests {
         assert_eq!(args_str[3], "test prompt");
     }
 
+    #[test]
+    fn test_max_turns_flag() {
+        let config = RunConfig {
+            max_turns: Some(5),
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &config, None);
+        let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
+        assert!(args_str.windows(2).any(|w| w == ["--max-turns", "5"]));
+
+        let args = build_args("test", &RunConfig::default(), None);
+        assert!(!args.iter().any(|arg| arg == "--max-turns"));
+    }
+
     #[test]
     fn test_no_session_persistence_flag() {
         let config = RunConfig {
@@ -571,4 +756,223 @@ mod tests {
             "Default config should NOT include --setting-sources"
         );
     }
+
+    #[test]
+    fn test_include_partial_messages_requires_stream_json() {
+        let config = RunConfig {
+            output_format: Some(OutputFormat::StreamJson),
+            include_partial_messages: true,
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &config, None);
+        assert!(args.iter().any(|a| a == "--include-partial-messages"));
+
+        let text = RunConfig {
+            output_format: Some(OutputFormat::Text),
+            ..config
+        };
+        let args = build_args("test", &text, None);
+        assert!(!args.iter().any(|a| a == "--include-partial-messages"));
+    }
+
+    #[test]
+    fn test_session_flags() {
+        let resume = RunConfig {
+            session: SessionMode::Resume("abc-123".to_string()),
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &resume, None);
+        let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
+        let idx = args_str.iter().position(|&s| s == "--resume").unwrap();
+        assert_eq!(args_str[idx + 1], "abc-123");
+        assert_eq!(args_str.last(), Some(&"test"));
+
+        let cont = RunConfig {
+            session: SessionMode::Continue,
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &cont, None);
+        assert!(args.iter().any(|a| a == "--continue"));
+
+        let args = build_args("test", &RunConfig::default(), None);
+        assert!(!args.iter().any(|a| a == "--continue" || a == "--resume"));
+    }
+
+    #[test]
+    fn test_agents_flag_serializes_definitions() {
+        let config = RunConfig {
+            agents: crate::types::AgentsConfig::new().agent(
+                "reviewer",
+                crate::types::AgentDefinition::new("Reviews code", "Be thorough.").tools(["Read"]),
+            ),
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &config, None);
+        let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
+        let idx = args_str.iter().position(|&s| s == "--agents").unwrap();
+        let agents: serde_json::Value = serde_json::from_str(args_str[idx + 1]).unwrap();
+        assert_eq!(
+            agents,
+            serde_json::json!({
+                "reviewer": {
+                    "description": "Reviews code",
+                    "prompt": "Be thorough.",
+                    "tools": ["Read"]
+                }
+            })
+        );
+
+        let args = build_args("test", &RunConfig::default(), None);
+        assert!(!args.iter().any(|a| a == "--agents"));
+    }
+
+    #[test]
+    fn test_extra_args_precede_prompt() {
+        let config = RunConfig {
+            extra_args: vec!["--betas".into(), "context-1m".into()],
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &config, None);
+        let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
+        assert_eq!(
+            args_str[args_str.len() - 3..],
+            ["--betas", "context-1m", "test"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_flags_are_gated_by_capabilities() {
+        let capabilities = crate::types::Capabilities {
+            features: [Feature::StreamJson, Feature::ToolsFlag]
+                .into_iter()
+                .collect(),
+        };
+        let config = RunConfig {
+            output_format: Some(OutputFormat::StreamJson),
+            include_partial_messages: true,
+            no_session_persistence: true,
+            tools: ToolPolicy {
+                builtin: BuiltinToolSet::None,
+                allowed: None,
+                disallowed: None,
+                disable_slash_commands: true,
+            },
+            capabilities: Some(capabilities),
+            ..RunConfig::default()
+        };
+        check_features(&config).unwrap();
+        let args = build_args("test", &config, None);
+        for flag in [
+            "--include-partial-messages",
+            "--no-session-persistence",
+            "--disable-slash-commands",
+        ] {
+            assert!(!args.iter().any(|a| a == flag), "{flag} was emitted");
+        }
+        assert!(args.iter().any(|a| a == "--tools"));
+
+        let config = RunConfig {
+            json_schema: JsonSchema::Inline("{}".to_string()),
+            mcp: Some(McpPolicy {
+                configs: vec!["mcp.json".to_string()],
+                strict: true,
+            }),
+            cli_version: Some(semver::Version::new(0, 2, 9)),
+            ..config
+        };
+        let err = check_features(&config).unwrap_err();
+        match &err {
+            ClaudeError::UnsupportedFeature { features, .. } => assert_eq!(
+                features,
+                &[Feature::JsonSchema, Feature::Mcp, Feature::StrictMcp]
+            ),
+            other => panic!("expected UnsupportedFeature, got {other:?}"),
+        }
+        assert_eq!(
+            err.to_string(),
+            "Claude CLI 0.2.9 does not support --json-schema, --mcp-config, --strict-mcp-config"
+        );
+
+        // Without capabilities every flag is emitted unchecked.
+        let config = RunConfig {
+            capabilities: None,
+            ..config
+        };
+        check_features(&config).unwrap();
+        assert!(build_args("test", &config, None)
+            .iter()
+            .any(|a| a == "--disable-slash-commands"));
+    }
+
+    #[test]
+    fn test_settings_flag_inline_and_file() {
+        let config = RunConfig {
+            settings: Settings::Override(crate::types::SettingsOverride {
+                permission_mode: Some(crate::types::PermissionMode::AcceptEdits),
+                trusted_directories: vec!["/srv/data".into()],
+                output_style: Some("default".to_string()),
+                ..Default::default()
+            }),
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &config, None);
+        let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
+        let idx = args_str.iter().position(|&s| s == "--settings").unwrap();
+        let settings: serde_json::Value = serde_json::from_str(args_str[idx + 1]).unwrap();
+        assert_eq!(
+            settings,
+            serde_json::json!({
+                "permissions": {
+                    "defaultMode": "acceptEdits",
+                    "additionalDirectories": ["/srv/data"]
+                },
+                "outputStyle": "default"
+            })
+        );
+
+        let config = RunConfig {
+            settings: Settings::File("ci-settings.json".into()),
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &config, None);
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "--settings" && w[1] == "ci-settings.json"));
+
+        let args = build_args("test", &RunConfig::default(), None);
+        assert!(!args.iter().any(|a| a == "--settings"));
+    }
+
+    #[test]
+    fn test_permission_mode_flags() {
+        let config = RunConfig {
+            permission_mode: Some(PermissionMode::AcceptEdits),
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &config, None);
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "--permission-mode" && w[1] == "acceptEdits"));
+        assert!(!args.iter().any(|a| a == "--dangerously-skip-permissions"));
+
+        let config = RunConfig {
+            permission_mode: Some(PermissionMode::BypassPermissions),
+            capabilities: Some(crate::types::Capabilities {
+                features: std::iter::once(Feature::PermissionMode).collect(),
+            }),
+            ..RunConfig::default()
+        };
+        let args = build_args("test", &config, None);
+        assert!(args.iter().any(|a| a == "--dangerously-skip-permissions"));
+        assert!(!args.iter().any(|a| a == "--permission-mode"));
+        match check_features(&config).unwrap_err() {
+            ClaudeError::UnsupportedFeature { features, .. } => {
+                assert_eq!(features, [Feature::SkipPermissions]);
+            }
+            other => panic!("expected UnsupportedFeature, got {other:?}"),
+        }
+
+        let args = build_args("test", &RunConfig::default(), None);
+        assert!(!args.iter().any(|a| a == "--permission-mode"));
+    }
 }
@@ -1,5 +1,7 @@
 //! Error types for the Claude Code adapter.
 
+use crate::types::Feature;
+use rig_cli_process_core::ProcessError;
 use std::process::ExitStatus;
 use thiserror::Error;
 
@@ -43,9 +45,11 @@ pub enum ClaudeError {
         source: std::io::Error,
     },
 
-    /// The subprocess exceeded its configured timeout.
-    #[error("Process timed out after {elapsed:?} (PID: {pid})\nPartial stdout: {partial_stdout}\nPartial stderr: {partial_stderr}")]
+    /// The subprocess exceeded one of its timeouts.
+    #[error("Process hit its {stage} timeout after {elapsed:?} (PID: {pid})\nPartial stdout: {}\nPartial stderr: {}", rig_cli_process_core::redact(.partial_stdout), rig_cli_process_core::redact(.partial_stderr))]
     Timeout {
+        /// Which timeout fired.
+        stage: crate::types::TimeoutStage,
         /// How long the process ran before being killed.
         elapsed: std::time::Duration,
         /// Operating-system PID of the timed-out process.
@@ -56,8 +60,37 @@ pub enum ClaudeError {
         partial_stderr: String,
     },
 
+    /// The subprocess printed nothing for its stall timeout and was stopped.
+    #[error("Process stalled: no output for {idle:?} (PID: {pid})\nPartial STDOUT: {}\nPartial STDERR: {}", rig_cli_process_core::redact(.partial_stdout), rig_cli_process_core::redact(.partial_stderr))]
+    Stalled {
+        /// How long the process had been silent.
+        idle: std::time::Duration,
+        /// When the process last printed a line, or was spawned if it never did.
+        last_activity: std::time::SystemTime,
+        /// OS process identifier.
+        pid: u32,
+        /// Stdout captured before the stall.
+        partial_stdout: String,
+        /// Stderr captured before the stall.
+        partial_stderr: String,
+    },
+
+    /// The host received SIGINT or SIGTERM during the run and passed it on to
+    /// the subprocess, which was stopped.
+    #[error("Process cancelled by {signal} (PID: {pid})\nPartial STDOUT: {}\nPartial STDERR: {}", rig_cli_process_core::redact(.partial_stdout), rig_cli_process_core::redact(.partial_stderr))]
+    Cancelled {
+        /// Name of the forwarded signal (e.g. `SIGINT`).
+        signal: String,
+        /// OS process identifier.
+        pid: u32,
+        /// Stdout captured before the signal.
+        partial_stdout: String,
+        /// Stderr captured before the signal.
+        partial_stderr: String,
+    },
+
     /// The subprocess exited with a non-zero status code.
-    #[error("Process exited with non-zero status: {exit_code} (PID: {pid}, elapsed: {elapsed:?})\nSTDOUT: {stdout}\nSTDERR: {stderr}")]
+    #[error("Process exited with non-zero status: {exit_code} (PID: {pid}, elapsed: {elapsed:?})\nSTDOUT: {}\nSTDERR: {}", rig_cli_process_core::redact(.stdout), rig_cli_process_core::redact(.stderr))]
     NonZeroExit {
         /// The non-zero exit code.
         exit_code: i32,
@@ -113,6 +146,46 @@ pub enum ClaudeError {
         limit_bytes: usize,
     },
 
+    /// The CLI is not logged in, or its credentials were rejected.
+    #[error("Claude CLI is not authenticated; run `{login_command}` to log in")]
+    AuthRequired {
+        /// Command the user should run to log in.
+        login_command: String,
+    },
+
+    /// The CLI stopped to ask an interactive question (e.g. a folder trust or
+    /// onboarding prompt) that no configured auto-answer matched.
+    #[error("Claude CLI is waiting on an interactive prompt: {question}")]
+    InteractivePromptDetected {
+        /// The prompt line printed by the CLI.
+        question: String,
+    },
+
+    /// A `claude mcp` subcommand that registers or removes a server failed.
+    #[error("`claude {command}` failed: {}", rig_cli_process_core::redact(.stderr))]
+    McpCommandFailed {
+        /// The subcommand, e.g. `mcp add-json`.
+        command: String,
+        /// Captured stderr, or why the subcommand was abandoned.
+        stderr: String,
+    },
+
+    /// An entry of `extra_args` sets a flag the adapter already passes.
+    #[error("Extra argument '{flag}' conflicts with a flag set from the config")]
+    ConflictingArg {
+        /// The conflicting flag, without any `=value`.
+        flag: String,
+    },
+
+    /// The installed CLI lacks flags the config needs.
+    #[error("Claude CLI {} does not support {}", version_label(.version.as_ref()), feature_flags(.features))]
+    UnsupportedFeature {
+        /// Version of the installed CLI, when known.
+        version: Option<semver::Version>,
+        /// Every needed feature the CLI lacks.
+        features: Vec<Feature>,
+    },
+
     /// An internal channel closed before the operation completed.
     #[error("Internal channel closed unexpectedly at stage '{stage}'")]
     ChannelClosed {
@@ -121,6 +194,18 @@ pub enum ClaudeError {
     },
 }
 
+fn version_label(version: Option<&semver::Version>) -> String {
+    version.map_or_else(|| "(unknown version)".to_string(), ToString::to_string)
+}
+
+fn feature_flags(features: &[Feature]) -> String {
+    features
+        .iter()
+        .map(|feature| feature.flag())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl From<std::io::Error> for ClaudeError {
     fn from(source: std::io::Error) -> Self {
         Self::SpawnFailed {
@@ -129,3 +214,101 @@ impl From<std::io::Error> for ClaudeError {
         }
     }
 }
+
+impl From<ProcessError> for ClaudeError {
+    fn from(error: ProcessError) -> Self {
+        match error {
+            ProcessError::Io { stage, source } => Self::SpawnFailed { stage, source },
+            ProcessError::Join { stage, source } => Self::StreamFailed { stage, source },
+            ProcessError::Signal {
+                signal,
+                pid,
+                reason,
+            } => Self::SignalFailed {
+                signal,
+                pid,
+                reason,
+            },
+            ProcessError::Timeout {
+                stage,
+                elapsed,
+                pid,
+                partial_stdout,
+                partial_stderr,
+            } => Self::Timeout {
+                stage,
+                elapsed,
+                pid,
+                partial_stdout,
+                partial_stderr,
+            },
+            ProcessError::Stalled {
+                idle,
+                last_activity,
+                pid,
+                partial_stdout,
+                partial_stderr,
+            } => Self::Stalled {
+                idle,
+                last_activity,
+                pid,
+                partial_stdout,
+                partial_stderr,
+            },
+            ProcessError::Cancelled {
+                signal,
+                pid,
+                partial_stdout,
+                partial_stderr,
+            } => Self::Cancelled {
+                signal,
+                pid,
+                partial_stdout,
+                partial_stderr,
+            },
+            ProcessError::OutputTruncated {
+                captured_bytes,
+                limit_bytes,
+            } => Self::OutputTruncated {
+                captured_bytes,
+                limit_bytes,
+            },
+            ProcessError::InteractivePromptDetected { question } => {
+                Self::InteractivePromptDetected { question }
+            }
+            ProcessError::NoStdout => Self::NoStdout,
+            ProcessError::NoStderr => Self::NoStderr,
+            ProcessError::NoPid => Self::NoPid,
+            ProcessError::ConflictingArg { flag } => Self::ConflictingArg { flag },
+        }
+    }
+}
+
+impl ClaudeError {
+    /// Returns a stable, machine-readable code identifying the error category.
+    ///
+    /// Codes are lowercase `snake_case` and shared across the adapter crates,
+    /// so callers can branch on them without matching adapter-specific variants.
+    #[must_use]
+    pub const fn error_code(&self) -> &'static str {
+        match self {
+            Self::ExecutableNotFound(_) => "discovery",
+            Self::VersionCheckFailed(_) | Self::DoctorFailed { .. } => "init",
+            Self::JsonParseError(_) => "parse",
+            Self::InvalidConfig(_) | Self::ConflictingArg { .. } => "validation",
+            Self::SpawnFailed { .. } | Self::NoStdout | Self::NoStderr | Self::NoPid => "spawn",
+            Self::Timeout { .. } => "timeout",
+            Self::Stalled { .. } => "stalled",
+            Self::Cancelled { .. } => "cancelled",
+            Self::NonZeroExit { .. } => "non_zero_exit",
+            Self::StreamFailed { .. } => "stream",
+            Self::SignalFailed { .. } => "signal",
+            Self::OutputTruncated { .. } => "output_truncated",
+            Self::AuthRequired { .. } => "auth_required",
+            Self::InteractivePromptDetected { .. } => "interactive_prompt",
+            Self::McpCommandFailed { .. } => "mcp_config",
+            Self::UnsupportedFeature { .. } => "unsupported_feature",
+            Self::ChannelClosed { .. } => "channel_closed",
+        }
+    }
+}
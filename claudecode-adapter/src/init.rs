@@ -78,6 +78,17 @@ pub async fn init(explicit_path: Option<PathBuf>) -> Result<InitReport, ClaudeEr
         (Feature::Mcp, "--mcp-config"),
         (Feature::StrictMcp, "--strict-mcp-config"),
         (Feature::ToolsFlag, "--tools"),
+        (Feature::Agents, "--agents"),
+        (Feature::SettingSources, "--setting-sources"),
+        (Feature::Settings, "--settings"),
+        (Feature::PermissionMode, "--permission-mode"),
+        (Feature::SkipPermissions, "--dangerously-skip-permissions"),
+        (Feature::DisableSlashCommands, "--disable-slash-commands"),
+        (Feature::NoSessionPersistence, "--no-session-persistence"),
+        (
+            Feature::IncludePartialMessages,
+            "--include-partial-messages",
+        ),
     ];
 
     let features = feature_checks
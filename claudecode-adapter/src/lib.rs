@@ -6,6 +6,8 @@
 
 #![warn(missing_docs)]
 
+/// Login state detection and auth-failure classification.
+pub mod auth;
 /// Command-line argument construction for Claude CLI invocations.
 pub mod cmd;
 /// Discovery and resolution of the Claude CLI executable path.
@@ -14,17 +16,22 @@ pub mod discovery;
 pub mod error;
 /// Initialization and capability probing of the Claude CLI.
 pub mod init;
+/// Registration of MCP servers in the CLI's own config.
+pub mod mcp;
 /// Subprocess execution with streaming, timeouts, and signal handling.
 pub mod process;
 /// Shared data types for configuration, results, and stream events.
 pub mod types;
 
+pub use auth::auth_status;
 pub use discovery::{discover_claude, CC_BIN_ENV_VAR};
 pub use error::ClaudeError;
 pub use init::init;
 pub use process::run_claude;
 pub use types::*;
 
+use std::borrow::Cow;
+
 /// High-level client for the Claude Code CLI.
 #[derive(Clone)]
 pub struct ClaudeCli {
@@ -32,13 +39,82 @@ pub struct ClaudeCli {
     pub path: std::path::PathBuf,
     /// Feature capabilities detected during initialization.
     pub capabilities: types::Capabilities,
+    /// CLI version detected during initialization, used to pick stream parsers.
+    pub version: Option<semver::Version>,
 }
 
 impl ClaudeCli {
     /// Creates a new `ClaudeCli` from a resolved path and detected capabilities.
     #[must_use]
     pub const fn new(path: std::path::PathBuf, capabilities: types::Capabilities) -> Self {
-        Self { path, capabilities }
+        Self {
+            path,
+            capabilities,
+            version: None,
+        }
+    }
+
+    /// Sets the CLI version runs select their stream parsers by, usually
+    /// [`InitReport::cli_version`](types::InitReport::cli_version).
+    #[must_use]
+    pub fn with_version(mut self, version: Option<semver::Version>) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Reports whether the CLI is logged in.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ClaudeError::SpawnFailed` if the status command cannot be executed.
+    pub async fn auth_status(&self) -> Result<types::AuthStatus, ClaudeError> {
+        auth_status(&self.path).await
+    }
+
+    /// Registers an MCP server for the directory `cwd`; see [`mcp::add_json`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `ClaudeError::McpCommandFailed` if the CLI rejects the server.
+    pub async fn mcp_add_json(
+        &self,
+        name: &str,
+        server: &serde_json::Value,
+        cwd: &std::path::Path,
+        env: &[(String, String)],
+    ) -> Result<(), ClaudeError> {
+        mcp::add_json(&self.path, name, server, cwd, env).await
+    }
+
+    /// Removes an MCP server registered for the directory `cwd`; see
+    /// [`mcp::remove`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `ClaudeError::McpCommandFailed` if the CLI cannot remove it.
+    pub async fn mcp_remove(
+        &self,
+        name: &str,
+        cwd: &std::path::Path,
+        env: &[(String, String)],
+    ) -> Result<(), ClaudeError> {
+        mcp::remove(&self.path, name, cwd, env).await
+    }
+
+    /// Lists the MCP servers the CLI loads with `config_file`; see
+    /// [`mcp::list`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `ClaudeError::McpCommandFailed` if the CLI cannot load the
+    /// config.
+    pub async fn mcp_list(
+        &self,
+        config_file: &std::path::Path,
+        cwd: &std::path::Path,
+        env: &[(String, String)],
+    ) -> Result<Vec<mcp::McpServerStatus>, ClaudeError> {
+        mcp::list(&self.path, config_file, cwd, env).await
     }
 
     /// Runs a prompt through the Claude CLI and returns the complete result.
@@ -46,13 +122,35 @@ impl ClaudeCli {
     /// # Errors
     ///
     /// Returns `ClaudeError` if the subprocess fails to spawn, times out,
-    /// or exits with an I/O error.
+    /// or exits with an I/O error, and `ClaudeError::AuthRequired` if the
+    /// CLI rejected the run for missing credentials.
     pub async fn run(
         &self,
         prompt: &str,
         config: &types::RunConfig,
     ) -> Result<types::RunResult, ClaudeError> {
-        run_claude(&self.path, prompt, config, None).await
+        run_claude(&self.path, prompt, &self.versioned(config), None).await
+    }
+
+    /// Synchronous [`run`](Self::run) on a runtime of its own, with the
+    /// `blocking` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`run`](Self::run), and
+    /// `ClaudeError::SpawnFailed` if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn run_blocking(
+        &self,
+        prompt: &str,
+        config: &types::RunConfig,
+    ) -> Result<types::RunResult, ClaudeError> {
+        rig_cli_process_core::blocking::block_on(self.run(prompt, config)).map_err(|source| {
+            ClaudeError::SpawnFailed {
+                stage: "blocking runtime".to_string(),
+                source,
+            }
+        })?
     }
 
     /// Runs a prompt with real-time streaming of events through the provided channel.
@@ -60,13 +158,46 @@ impl ClaudeCli {
     /// # Errors
     ///
     /// Returns `ClaudeError` if the subprocess fails to spawn, times out,
-    /// or exits with an I/O error.
+    /// or exits with an I/O error, and `ClaudeError::AuthRequired` if the
+    /// CLI rejected the run for missing credentials.
     pub async fn stream(
         &self,
         prompt: &str,
         config: &types::RunConfig,
         sender: tokio::sync::mpsc::Sender<types::StreamEvent>,
     ) -> Result<types::RunResult, ClaudeError> {
-        run_claude(&self.path, prompt, config, Some(sender)).await
+        run_claude(&self.path, prompt, &self.versioned(config), Some(sender)).await
+    }
+
+    /// Builds the command [`run`](Self::run) would spawn, without spawning it.
+    ///
+    /// # Errors
+    ///
+    /// See [`process::dry_run`].
+    pub fn dry_run(
+        &self,
+        prompt: &str,
+        config: &types::RunConfig,
+    ) -> Result<types::Invocation, ClaudeError> {
+        process::dry_run(&self.path, prompt, &self.versioned(config))
+    }
+
+    /// Fills in the detected CLI version and capabilities where `config`
+    /// does not set them.
+    fn versioned<'a>(&self, config: &'a types::RunConfig) -> Cow<'a, types::RunConfig> {
+        if config.capabilities.is_some() && (config.cli_version.is_some() || self.version.is_none())
+        {
+            return Cow::Borrowed(config);
+        }
+        Cow::Owned(types::RunConfig {
+            cli_version: config.cli_version.clone().or_else(|| self.version.clone()),
+            capabilities: Some(
+                config
+                    .capabilities
+                    .clone()
+                    .unwrap_or_else(|| self.capabilities.clone()),
+            ),
+            ..config.clone()
+        })
     }
 }
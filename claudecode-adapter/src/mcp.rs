@@ -0,0 +1,222 @@
+//! Registration of MCP servers through `claude mcp`.
+//!
+//! Runs normally pass their servers with `--mcp-config`. Registering a
+//! server with `claude mcp add-json --scope local` instead stores it in the
+//! CLI's own config for one directory, where user-level settings such as
+//! `disabledMcpServers` treat it like any other configured server.
+//!
+//! [`list`] asks the CLI which servers it would load and whether it can
+//! connect to them, so a config the CLI rejects is found before a run.
+
+use crate::error::ClaudeError;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long a `claude mcp` subcommand may take.
+const MCP_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Registers `server`, one entry of an `mcpServers` map, as `name` for the
+/// directory `cwd`.
+///
+/// `env` is added to the subcommand's environment, so it reads the same
+/// CLI config as the run that follows.
+///
+/// # Errors
+///
+/// Returns `ClaudeError::SpawnFailed` if the subcommand cannot be executed,
+/// and `ClaudeError::McpCommandFailed` if it fails or does not finish within
+/// 30 seconds.
+pub async fn add_json(
+    path: &Path,
+    name: &str,
+    server: &serde_json::Value,
+    cwd: &Path,
+    env: &[(String, String)],
+) -> Result<(), ClaudeError> {
+    let json = server.to_string();
+    mcp_command(
+        path,
+        &["add-json", "--scope", "local", name, &json],
+        cwd,
+        env,
+    )
+    .await
+    .map(drop)
+}
+
+/// Removes the server registered as `name` for the directory `cwd`.
+///
+/// # Errors
+///
+/// See [`add_json`].
+pub async fn remove(
+    path: &Path,
+    name: &str,
+    cwd: &Path,
+    env: &[(String, String)],
+) -> Result<(), ClaudeError> {
+    mcp_command(path, &["remove", "--scope", "local", name], cwd, env)
+        .await
+        .map(drop)
+}
+
+/// Connection state of one server, as reported by `claude mcp list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McpServerStatus {
+    /// Server name.
+    pub name: String,
+    /// Whether the CLI connected to the server.
+    pub connected: bool,
+    /// The CLI's status text, e.g. `✓ Connected` or `✗ Failed to connect`.
+    pub status: String,
+}
+
+/// Lists the servers the CLI loads in `cwd` with `config_file` passed as
+/// `--mcp-config`, and whether it could connect to each.
+///
+/// # Errors
+///
+/// See [`add_json`]. A config file the CLI cannot load makes the
+/// subcommand fail.
+pub async fn list(
+    path: &Path,
+    config_file: &Path,
+    cwd: &Path,
+    env: &[(String, String)],
+) -> Result<Vec<McpServerStatus>, ClaudeError> {
+    let config_file = config_file.to_string_lossy();
+    let stdout = mcp_command(path, &["list", "--mcp-config", &config_file], cwd, env).await?;
+    Ok(parse_list(&stdout))
+}
+
+/// Parses `claude mcp list` output, one `name: command - status` line per
+/// server. Other lines, such as the health check banner, are skipped.
+fn parse_list(stdout: &str) -> Vec<McpServerStatus> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(": ")?;
+            let (_, status) = rest.rsplit_once(" - ")?;
+            let status = status.trim();
+            let connected = status.starts_with('✓');
+            (connected || status.starts_with('✗')).then(|| McpServerStatus {
+                name: name.trim().to_string(),
+                connected,
+                status: status.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Runs `claude mcp <args>` in `cwd`, checks that it succeeded and returns
+/// its stdout.
+async fn mcp_command(
+    path: &Path,
+    args: &[&str],
+    cwd: &Path,
+    env: &[(String, String)],
+) -> Result<String, ClaudeError> {
+    let mut cmd = Command::new(path);
+    cmd.arg("mcp")
+        .args(args)
+        .current_dir(cwd)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    cmd.creation_flags(0x0800_0000); // CREATE_NO_WINDOW
+
+    let command = format!("mcp {}", args[0]);
+    let Ok(output) = tokio::time::timeout(MCP_COMMAND_TIMEOUT, cmd.output()).await else {
+        return Err(ClaudeError::McpCommandFailed {
+            command,
+            stderr: format!("no answer within {MCP_COMMAND_TIMEOUT:?}"),
+        });
+    };
+    let output = output.map_err(|e| ClaudeError::SpawnFailed {
+        stage: command.clone(),
+        source: e,
+    })?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(ClaudeError::McpCommandFailed {
+            command,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Writes a fake `claude` that logs its working directory and arguments,
+    /// and fails `remove`.
+    fn fake_cli(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("claude");
+        std::fs::write(
+            &path,
+            "#!/bin/sh\npwd >> calls.log\necho \"$@\" >> calls.log\n\
+             [ \"$2\" = remove ] && { echo 'No local server' >&2; exit 1; }\nexit 0\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_add_json_and_remove_run_in_cwd() {
+        let dir = tempfile::tempdir().unwrap();
+        let cli = fake_cli(dir.path());
+        let server = serde_json::json!({ "command": "server", "args": ["--stdio"] });
+
+        add_json(&cli, "rig_mcp", &server, dir.path(), &[])
+            .await
+            .unwrap();
+        let err = remove(&cli, "rig_mcp", dir.path(), &[]).await.unwrap_err();
+        assert!(
+            matches!(&err, ClaudeError::McpCommandFailed { command, stderr }
+                if command == "mcp remove" && stderr.contains("No local server")),
+            "{err}"
+        );
+
+        let log = std::fs::read_to_string(dir.path().join("calls.log")).unwrap();
+        let cwd = std::fs::canonicalize(dir.path()).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines[0], cwd.to_str().unwrap());
+        assert_eq!(
+            lines[1],
+            r#"mcp add-json --scope local rig_mcp {"args":["--stdio"],"command":"server"}"#
+        );
+        assert_eq!(lines[3], "mcp remove --scope local rig_mcp");
+    }
+
+    #[test]
+    fn test_parse_list_reads_server_lines() {
+        let stdout = "Checking MCP server health...\n\n\
+                      rig_mcp: /bin/rig-mcp --stdio - ✓ Connected\n\
+                      extra: npx -y some-server - ✗ Failed to connect\n";
+        let servers = parse_list(stdout);
+        assert_eq!(
+            servers,
+            [
+                McpServerStatus {
+                    name: "rig_mcp".to_string(),
+                    connected: true,
+                    status: "✓ Connected".to_string(),
+                },
+                McpServerStatus {
+                    name: "extra".to_string(),
+                    connected: false,
+                    status: "✗ Failed to connect".to_string(),
+                },
+            ]
+        );
+        assert!(parse_list("No MCP servers configured.\n").is_empty());
+    }
+}
@@ -1,31 +1,32 @@
 //! Subprocess execution with streaming, timeouts, and signal handling.
 
 use crate::error::ClaudeError;
-use crate::types::{OutputFormat, RunConfig, RunResult, SystemPromptMode};
-use std::process::Stdio;
-#[cfg(unix)]
-use std::time::Duration;
-use std::time::Instant;
+use crate::types::{
+    BuiltinToolSet, OutputFormat, RunConfig, RunResult, StderrKind, StreamEvent, StreamParsers,
+    SystemPromptMode,
+};
+use rig_cli_process_core::{
+    check_extra_args, hash::fnv1a, ConfigFile, EnvPolicy, EventSink, Invocation, LineParser,
+    ProcessRunner,
+};
 use tempfile::NamedTempFile;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::mpsc;
-use tokio::task::JoinSet;
-use tokio::time::timeout;
-
-/// Bounded channel capacity for internal stdout / stderr pipes.
-const CHANNEL_CAPACITY: usize = 100;
-/// Maximum bytes captured from a single pipe before truncation.
-const MAX_OUTPUT_BYTES: usize = 10 * 1024 * 1024; // 10 MB
-/// Time to wait for a graceful SIGTERM exit before sending SIGKILL.
-#[cfg(unix)]
-const GRACE_PERIOD: Duration = Duration::from_secs(5);
 
 /// Byte threshold above which prompts and system prompts are offloaded from
 /// CLI positional arguments.  Windows limits total arg length to ~32 KB; we
 /// use 30 KB as a safe cross-platform threshold.
 const ARG_THRESHOLD: usize = 30_000;
 
+/// Lowercase fragments of the trust and onboarding questions a fresh install
+/// asks before its first run.
+const INTERACTIVE_PROMPT_MARKERS: &[&str] = &[
+    "do you trust the files in this folder",
+    "is this a project you created or one you trust",
+    "choose the text style",
+    "select login method",
+];
+
 /// JavaScript preload script that monkey-patches Node.js `child_process` to
 /// inject `windowsHide: true` into every `spawn`, `exec`, `execFile`, and
 /// `fork` call.  This prevents Claude Code's internal subprocess spawns
@@ -110,18 +111,41 @@ fn windowshide_patch_path() -> Result<std::path::PathBuf, ClaudeError> {
 /// if the config uses `BuiltinToolSet::None`.
 ///
 /// System prompts exceeding the threshold are always handled via the
-/// official `--append-system-prompt-file` / `--system-prompt-file` flags.
+/// official `--append-system-prompt-file` / `--system-prompt-file` flags,
+/// from a temp file or, with [`RunConfig::preamble_cache_dir`], from a file
+/// named after the prompt's hash that later runs reuse.
+///
+/// [`RunConfig::images`] are staged before the run and listed after the
+/// prompt.
 ///
 /// # Errors
 ///
 /// Returns `ClaudeError` when the subprocess cannot be spawned, an I/O pipe
 /// fails, the configured timeout expires, or output exceeds the size limit.
+/// An image that is not a readable file is returned as
+/// `ClaudeError::InvalidConfig`.
+/// A non-zero exit whose output reports missing or rejected credentials is
+/// returned as `ClaudeError::AuthRequired`, and an entry of
+/// `config.extra_args` that repeats a generated flag as
+/// `ClaudeError::ConflictingArg` before anything is spawned. So is a config
+/// needing flags the CLI lacks, as `ClaudeError::UnsupportedFeature`.
 pub async fn run_claude(
     path: &std::path::Path,
     prompt: &str,
     config: &RunConfig,
     sender: Option<mpsc::Sender<crate::types::StreamEvent>>,
 ) -> Result<RunResult, ClaudeError> {
+    crate::cmd::check_features(config)?;
+    let staged = stage_images(config)?;
+    let image_prompt;
+    let image_config;
+    let (prompt, config) = if staged.paths.is_empty() {
+        (prompt, config)
+    } else {
+        image_prompt = prompt_with_images(prompt, &staged.paths);
+        image_config = with_read_granted(config);
+        (image_prompt.as_str(), &image_config)
+    };
     let use_stdin = prompt.len() > ARG_THRESHOLD;
 
     // --- System prompt: temp file if large, inline otherwise ---------------
@@ -131,8 +155,11 @@ pub async fn run_claude(
     };
     let needs_sys_file = sys_prompt_text.map_or(false, |t| t.len() > ARG_THRESHOLD);
 
-    let _sys_prompt_file: Option<NamedTempFile> = if needs_sys_file {
-        Some(write_temp_file("rig_sysprompt_", sys_prompt_text.unwrap_or_default())?)
+    let sys_prompt_file = if needs_sys_file {
+        Some(write_system_prompt_file(
+            config,
+            sys_prompt_text.unwrap_or_default(),
+        )?)
     } else {
         None
     };
@@ -143,10 +170,19 @@ pub async fn run_claude(
     let args = crate::cmd::build_args(
         effective_prompt,
         config,
-        _sys_prompt_file.as_ref().map(|f| f.path()),
+        sys_prompt_file.as_ref().map(SystemPromptFile::path),
     );
 
-    let result = execute_once(path, &args, config, use_stdin, prompt, sender.clone()).await?;
+    let result = crate::auth::ensure_authenticated(
+        execute_once(
+            path,
+            &args,
+            config,
+            use_stdin.then_some(prompt),
+            sender.clone(),
+        )
+        .await?,
+    )?;
 
     // --- Bug #7263 regression guard: empty stdout with stdin mode ----------
     // The bug signature is exit 0 + empty stdout when prompt was piped via
@@ -156,18 +192,97 @@ pub async fn run_claude(
             prompt_bytes = prompt.len(),
             "Empty stdout with stdin mode — possible Bug #7263 regression, retrying with temp file"
         );
-        return run_with_tempfile_fallback(path, prompt, config, &_sys_prompt_file, sender).await;
+        rig_cli_process_core::ProcessStats::global().record_restart();
+        let sys_prompt_path = sys_prompt_file.as_ref().map(SystemPromptFile::path);
+        return run_with_tempfile_fallback(path, prompt, config, sys_prompt_path, sender)
+            .await
+            .and_then(crate::auth::ensure_authenticated);
     }
 
     Ok(result)
 }
 
+/// Builds the command [`run_claude`] would spawn first, without spawning it.
+///
+/// A system prompt long enough to go through a file is reported in
+/// [`Invocation::config_files`], under a placeholder path unless it would go
+/// to the [preamble cache](RunConfig::preamble_cache_dir), and MCP configs
+/// and a settings file given as file paths are included with their
+/// contents. The temp-file retry for empty stdin runs is not shown, and
+/// `extra_args` are included without checking them for conflicts.
+/// [`RunConfig::images`] are listed at their own paths, since nothing is
+/// staged.
+///
+/// # Errors
+///
+/// Returns `ClaudeError::UnsupportedFeature` if the config needs flags the
+/// CLI lacks, and `ClaudeError::SpawnFailed` if building the command fails,
+/// which happens on Windows when the console-hiding patch cannot be written.
+pub fn dry_run(
+    path: &std::path::Path,
+    prompt: &str,
+    config: &RunConfig,
+) -> Result<Invocation, ClaudeError> {
+    crate::cmd::check_features(config)?;
+    let image_prompt;
+    let image_config;
+    let (prompt, config) = if config.images.is_empty() {
+        (prompt, config)
+    } else {
+        image_prompt = prompt_with_images(prompt, &config.images);
+        image_config = with_read_granted(config);
+        (image_prompt.as_str(), &image_config)
+    };
+    let use_stdin = prompt.len() > ARG_THRESHOLD;
+    let sys_prompt_file = match &config.system_prompt {
+        SystemPromptMode::Append(p) | SystemPromptMode::Replace(p) if p.len() > ARG_THRESHOLD => {
+            Some(ConfigFile {
+                path: config.preamble_cache_dir.as_deref().map_or_else(
+                    || std::env::temp_dir().join("rig_sysprompt_XXXXXX.txt"),
+                    |dir| cached_preamble_path(dir, p),
+                ),
+                contents: p.clone(),
+            })
+        }
+        _ => None,
+    };
+
+    let args = crate::cmd::build_args(
+        if use_stdin { "" } else { prompt },
+        config,
+        sys_prompt_file.as_ref().map(|f| f.path.as_path()),
+    );
+    let mut invocation = Invocation::from_command(
+        &build_command(path, &args, config)?,
+        config.env_policy == EnvPolicy::Inherit,
+    );
+    invocation.stdin = use_stdin.then(|| prompt.to_string());
+    invocation.config_files.extend(sys_prompt_file);
+    for mcp_config in config.mcp.iter().flat_map(|mcp| &mcp.configs) {
+        if let Ok(contents) = std::fs::read_to_string(mcp_config) {
+            invocation.config_files.push(ConfigFile {
+                path: mcp_config.into(),
+                contents,
+            });
+        }
+    }
+    if let crate::types::Settings::File(settings) = &config.settings {
+        if let Ok(contents) = std::fs::read_to_string(settings) {
+            invocation.config_files.push(ConfigFile {
+                path: settings.clone(),
+                contents,
+            });
+        }
+    }
+    Ok(invocation)
+}
+
 /// Fallback path: write prompt to temp file, grant Read tool if needed.
 async fn run_with_tempfile_fallback(
     path: &std::path::Path,
     prompt: &str,
     config: &RunConfig,
-    sys_prompt_file: &Option<NamedTempFile>,
+    sys_prompt_file: Option<&std::path::Path>,
     sender: Option<mpsc::Sender<crate::types::StreamEvent>>,
 ) -> Result<RunResult, ClaudeError> {
     let _prompt_file = write_temp_file("rig_prompt_", prompt)?;
@@ -193,13 +308,155 @@ async fn run_with_tempfile_fallback(
             config
         };
 
-    let args = crate::cmd::build_args(
-        &instruction,
-        effective_config,
-        sys_prompt_file.as_ref().map(|f| f.path()),
-    );
+    let args = crate::cmd::build_args(&instruction, effective_config, sys_prompt_file);
 
-    execute_once(path, &args, effective_config, false, "", sender).await
+    execute_once(path, &args, effective_config, None, sender).await
+}
+
+/// The [`RunConfig::images`] of a run, as the agent is shown them.
+struct StagedImages {
+    /// Temp directory in the run's cwd holding the copies; removed when
+    /// dropped.
+    _dir: Option<tempfile::TempDir>,
+    paths: Vec<std::path::PathBuf>,
+}
+
+/// Checks that every image is a file and copies them into a temp directory
+/// in the run's cwd, if it has one.
+fn stage_images(config: &RunConfig) -> Result<StagedImages, ClaudeError> {
+    for image in &config.images {
+        if !image.is_file() {
+            return Err(ClaudeError::InvalidConfig(format!(
+                "image {} is not a readable file",
+                image.display()
+            )));
+        }
+    }
+    let failed = |stage: &str, source| ClaudeError::SpawnFailed {
+        stage: format!("image {stage}"),
+        source,
+    };
+    let Some(cwd) = config.cwd.as_ref().filter(|_| !config.images.is_empty()) else {
+        let paths = config
+            .images
+            .iter()
+            .map(|image| std::fs::canonicalize(image).map_err(|e| failed("path resolution", e)))
+            .collect::<Result<_, _>>()?;
+        return Ok(StagedImages { _dir: None, paths });
+    };
+    let dir = tempfile::Builder::new()
+        .prefix(".rig_images_")
+        .tempdir_in(cwd)
+        .map_err(|e| failed("directory creation", e))?;
+    let mut paths = Vec::with_capacity(config.images.len());
+    for (i, image) in config.images.iter().enumerate() {
+        // Numbered so images with the same file name do not collide.
+        let name = image
+            .file_name()
+            .map_or_else(|| "image".into(), |name| name.to_string_lossy());
+        let target = dir.path().join(format!("{}-{name}", i + 1));
+        std::fs::copy(image, &target).map_err(|e| failed("copy", e))?;
+        paths.push(target);
+    }
+    Ok(StagedImages {
+        _dir: Some(dir),
+        paths,
+    })
+}
+
+/// `prompt` followed by the list of images for the agent to read.
+fn prompt_with_images(prompt: &str, images: &[std::path::PathBuf]) -> String {
+    let mut text = format!("{prompt}\n\nImages for this task; view each with the Read tool:");
+    for image in images {
+        text.push_str("\n- ");
+        text.push_str(&image.to_string_lossy());
+    }
+    text
+}
+
+/// `config` with the `Read` builtin enabled and allowed, so the agent can
+/// view images.
+fn with_read_granted(config: &RunConfig) -> RunConfig {
+    let add_read = |tools: &mut Vec<String>| {
+        if !tools.iter().any(|tool| tool == "Read") {
+            tools.push("Read".to_string());
+        }
+    };
+    let mut config = config.clone();
+    if matches!(config.tools.builtin, BuiltinToolSet::None) {
+        config.tools.builtin = BuiltinToolSet::Explicit(Vec::new());
+    }
+    if let BuiltinToolSet::Explicit(tools) = &mut config.tools.builtin {
+        add_read(tools);
+    }
+    if let Some(allowed) = &mut config.tools.allowed {
+        add_read(allowed);
+    }
+    config
+}
+
+/// A system prompt written out for `--append-system-prompt-file` /
+/// `--system-prompt-file`.
+enum SystemPromptFile {
+    /// Deleted when dropped.
+    Temp(NamedTempFile),
+    /// Kept in the preamble cache for later runs.
+    Cached(std::path::PathBuf),
+}
+
+impl SystemPromptFile {
+    fn path(&self) -> &std::path::Path {
+        match self {
+            Self::Temp(file) => file.path(),
+            Self::Cached(path) => path,
+        }
+    }
+}
+
+/// Writes a system prompt to the preamble cache, if the config has one, or
+/// to a temp file.
+fn write_system_prompt_file(
+    config: &RunConfig,
+    text: &str,
+) -> Result<SystemPromptFile, ClaudeError> {
+    config.preamble_cache_dir.as_deref().map_or_else(
+        || write_temp_file("rig_sysprompt_", text).map(SystemPromptFile::Temp),
+        |dir| cached_preamble_file(dir, text).map(SystemPromptFile::Cached),
+    )
+}
+
+/// Where the preamble cache in `dir` keeps `text`.
+fn cached_preamble_path(dir: &std::path::Path, text: &str) -> std::path::PathBuf {
+    dir.join(format!("rig_sysprompt_{:016x}.txt", fnv1a(text.as_bytes())))
+}
+
+/// Returns the preamble cache file holding `text`, writing it unless an
+/// earlier run already has.
+///
+/// The file is written under a temporary name and renamed into place, so a
+/// concurrent run never reads it half-written.
+fn cached_preamble_file(
+    dir: &std::path::Path,
+    text: &str,
+) -> Result<std::path::PathBuf, ClaudeError> {
+    let path = cached_preamble_path(dir, text);
+    if std::fs::read(&path).is_ok_and(|contents| contents == text.as_bytes()) {
+        return Ok(path);
+    }
+    let failed = |stage: &str, source| ClaudeError::SpawnFailed {
+        stage: format!("preamble cache {stage}"),
+        source,
+    };
+    std::fs::create_dir_all(dir).map_err(|e| failed("directory creation", e))?;
+    let file = tempfile::Builder::new()
+        .prefix("rig_sysprompt_")
+        .suffix(".tmp")
+        .tempfile_in(dir)
+        .map_err(|e| failed("file creation", e))?;
+    std::fs::write(file.path(), text).map_err(|e| failed("file write", e))?;
+    file.persist(&path)
+        .map_err(|e| failed("file rename", e.error))?;
+    Ok(path)
 }
 
 /// Creates a named temp file with the given prefix and content.
@@ -219,75 +476,157 @@ fn write_temp_file(prefix: &str, content: &str) -> Result<NamedTempFile, ClaudeE
     Ok(f)
 }
 
-/// Spawns a single Claude CLI subprocess, optionally piping the prompt via
-/// stdin, collects output, and returns the result.
+/// Runs a single Claude CLI subprocess, optionally piping the prompt via
+/// stdin, and assembles the result from its output.
 async fn execute_once(
     path: &std::path::Path,
     args: &[std::ffi::OsString],
     config: &RunConfig,
-    pipe_stdin: bool,
-    stdin_content: &str,
+    stdin_content: Option<&str>,
     sender: Option<mpsc::Sender<crate::types::StreamEvent>>,
 ) -> Result<RunResult, ClaudeError> {
-    let start_time = Instant::now();
-
-    let mut child = spawn_child(path, args, config, pipe_stdin)?;
-
-    // Write prompt to stdin and close the pipe so Claude sees EOF.
-    if pipe_stdin {
-        if let Some(mut stdin_pipe) = child.stdin.take() {
-            stdin_pipe
-                .write_all(stdin_content.as_bytes())
-                .await
-                .map_err(|e| ClaudeError::SpawnFailed {
-                    stage: "stdin write".to_string(),
-                    source: e,
-                })?;
-            drop(stdin_pipe);
-        }
+    check_extra_args(args, &config.extra_args)?;
+    let mut runner = ProcessRunner::new(build_command(path, args, config)?)
+        .limits(config.output_limits)
+        .timeout(config.timeout)
+        .spawn_timeout(config.spawn_timeout)
+        .first_output_timeout(config.first_output_timeout)
+        .log_sink(config.log_sink.as_deref())
+        .resource_limits(config.resource_limits)
+        .stall(config.stall_timeout, &config.on_stall)
+        .stderr_events(config.stderr_events.as_ref())
+        .forward_signals(config.forward_signals)
+        .prompts(&config.interactive_prompts, INTERACTIVE_PROMPT_MARKERS);
+    if let Some(content) = stdin_content {
+        runner = runner.stdin(content);
     }
 
-    let stdout = child.stdout.take().ok_or(ClaudeError::NoStdout)?;
-    let stderr = child.stderr.take().ok_or(ClaudeError::NoStderr)?;
-    let pid = child.id().ok_or(ClaudeError::NoPid)?;
-
-    let (stdout_tx, mut stdout_rx) = mpsc::channel::<String>(CHANNEL_CAPACITY);
-    let (stderr_tx, mut stderr_rx) = mpsc::channel::<String>(CHANNEL_CAPACITY);
-
-    let mut tasks = JoinSet::new();
-    let format = config.output_format;
-
-    tasks.spawn(async move {
-        drain_stdout_bounded(stdout, stdout_tx, sender, format, MAX_OUTPUT_BYTES).await
-    });
-    tasks.spawn(async move { drain_stderr_bounded(stderr, stderr_tx, MAX_OUTPUT_BYTES).await });
-
-    let execution = execute_and_collect(
-        &mut child,
-        &mut stdout_rx,
-        &mut stderr_rx,
-        &mut tasks,
-        format,
-        start_time,
-        config.output_format,
-    );
+    let mut parser = StreamJsonParser {
+        format: config.output_format,
+        parsers: &config.parsers,
+        version: config.cli_version.as_ref(),
+        stream_events: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    let events = sender.map(|tx| EventSink::new(tx, config.backpressure));
+    let output = runner.run(&mut parser, events).await?;
 
-    if let Ok(result) = timeout(config.timeout, execution).await {
-        result
+    let json = if config.output_format == Some(OutputFormat::Json) {
+        serde_json::from_str(&output.stdout).ok()
     } else {
-        handle_timeout(
-            &mut child,
-            pid,
-            &mut stdout_rx,
-            &mut stderr_rx,
-            &mut tasks,
-            config,
-        )
-        .await
+        None
+    };
+    let stream_events = parser.stream_events;
+    let diagnostics = parser.diagnostics;
+    let session_id = crate::types::parse_session_id(json.as_ref(), &stream_events)
+        .or_else(|| crate::types::parse_session_id(None, &diagnostics));
+    let result_text = crate::types::parse_result_text(json.as_ref(), &stream_events);
+
+    Ok(RunResult {
+        duration_ms: output.duration_ms(),
+        event_offsets_ms: output.event_offsets_ms(),
+        latency: output.latency,
+        stdout: if config.output_format == Some(OutputFormat::StreamJson) {
+            strip_diagnostics(&output.stdout)
+        } else {
+            output.stdout
+        },
+        stderr: output.stderr,
+        exit_code: output.exit_code,
+        json,
+        stream_events,
+        diagnostics,
+        structured_output: None,
+        session_id,
+        result_text,
+    })
+}
+
+/// Parses `stream-json` output into [`StreamEvent`]s with the configured
+/// parsers, keeping every raw event for session and result extraction and
+/// setting diagnostics aside.
+struct StreamJsonParser<'a> {
+    format: Option<OutputFormat>,
+    parsers: &'a StreamParsers,
+    version: Option<&'a semver::Version>,
+    stream_events: Vec<serde_json::Value>,
+    diagnostics: Vec<serde_json::Value>,
+}
+
+impl LineParser for StreamJsonParser<'_> {
+    type Event = StreamEvent;
+
+    fn parse(&mut self, line: &str) -> Vec<StreamEvent> {
+        if self.format != Some(OutputFormat::StreamJson) {
+            return Vec::new();
+        }
+        match classify_stream_line(line) {
+            StreamLine::Event(val) => {
+                let events = self.parsers.parse(self.version, &val).unwrap_or_default();
+                self.stream_events.push(val);
+                events
+            }
+            StreamLine::Diagnostic(val) => {
+                self.diagnostics.push(val);
+                Vec::new()
+            }
+            StreamLine::Blank => Vec::new(),
+        }
+    }
+
+    fn stalled(&mut self, idle: std::time::Duration) -> Vec<StreamEvent> {
+        vec![StreamEvent::Stalled {
+            idle_ms: u64::try_from(idle.as_millis()).unwrap_or(u64::MAX),
+        }]
+    }
+
+    fn stderr(&mut self, kind: StderrKind, message: &str) -> Vec<StreamEvent> {
+        let message = message.to_string();
+        vec![match kind {
+            StderrKind::Progress => StreamEvent::Progress { message },
+            StderrKind::Warning => StreamEvent::Warning { message },
+        }]
     }
 }
 
-/// Spawns the Claude CLI subprocess with the given arguments and config.
+/// One line of `stream-json` stdout.
+enum StreamLine {
+    /// A conversation or result event.
+    Event(serde_json::Value),
+    /// A `system` event, or a line that is not JSON (`--verbose` debug
+    /// output), kept as a string.
+    Diagnostic(serde_json::Value),
+    /// An empty line.
+    Blank,
+}
+
+fn classify_stream_line(line: &str) -> StreamLine {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return StreamLine::Blank;
+    }
+    match serde_json::from_str::<serde_json::Value>(trimmed) {
+        Ok(val) if val.get("type").and_then(serde_json::Value::as_str) == Some("system") => {
+            StreamLine::Diagnostic(val)
+        }
+        Ok(val) => StreamLine::Event(val),
+        Err(_) => StreamLine::Diagnostic(serde_json::Value::String(trimmed.to_string())),
+    }
+}
+
+/// `stdout` without the lines [`classify_stream_line`] takes for diagnostics.
+fn strip_diagnostics(stdout: &str) -> String {
+    stdout
+        .lines()
+        .filter(|line| !matches!(classify_stream_line(line), StreamLine::Diagnostic(_)))
+        .fold(String::with_capacity(stdout.len()), |mut out, line| {
+            out.push_str(line);
+            out.push('\n');
+            out
+        })
+}
+
+/// Builds the Claude CLI command with the given arguments and config.
 ///
 /// On Windows, applies two layers of console-window suppression:
 ///
@@ -302,14 +641,14 @@ async fn execute_once(
 /// Layer 1 affects only the **immediate child**.  Layer 2 patches the
 /// Node.js runtime itself, covering grandchild processes spawned by
 /// Claude Code.
-fn spawn_child(
+#[cfg_attr(not(windows), allow(clippy::unnecessary_wraps))]
+fn build_command(
     path: &std::path::Path,
     args: &[std::ffi::OsString],
     config: &RunConfig,
-    pipe_stdin: bool,
-) -> Result<tokio::process::Child, ClaudeError> {
+) -> Result<Command, ClaudeError> {
     let mut cmd = Command::new(path);
-    cmd.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+    cmd.args(args);
 
     // On Windows, prevent a visible console window from flashing when spawning
     // the CLI subprocess from a GUI application (windows_subsystem = "windows").
@@ -319,14 +658,11 @@ fn spawn_child(
         cmd.creation_flags(CREATE_NO_WINDOW);
     }
 
-    if pipe_stdin {
-        cmd.stdin(Stdio::piped());
-    }
-
     if let Some(cwd) = &config.cwd {
         cmd.current_dir(cwd);
     }
 
+    config.env_policy.apply(&mut cmd);
     for (k, v) in &config.env {
         cmd.env(k, v);
     }
@@ -354,255 +690,403 @@ fn spawn_child(
         cmd.env("NODE_OPTIONS", node_opts);
     }
 
-    cmd.spawn().map_err(|e| ClaudeError::SpawnFailed {
-        stage: "subprocess spawn".to_string(),
-        source: e,
-    })
+    Ok(cmd)
 }
 
-/// Drains both stdout/stderr channels, waits for the child to exit, then
-/// joins all reader tasks and assembles the final `RunResult`.
-async fn execute_and_collect(
-    child: &mut tokio::process::Child,
-    stdout_rx: &mut mpsc::Receiver<String>,
-    stderr_rx: &mut mpsc::Receiver<String>,
-    tasks: &mut JoinSet<Result<(), ClaudeError>>,
-    format: Option<OutputFormat>,
-    start_time: Instant,
-    output_format: Option<OutputFormat>,
-) -> Result<RunResult, ClaudeError> {
-    let mut stdout_lines = Vec::new();
-    let mut stderr_lines = Vec::new();
-    let mut stream_events = Vec::new();
-    let mut stdout_done = false;
-    let mut stderr_done = false;
-
-    while !stdout_done || !stderr_done {
-        tokio::select! {
-            result = stdout_rx.recv(), if !stdout_done => {
-                if let Some(line) = result {
-                    if format == Some(OutputFormat::StreamJson) {
-                        if let Ok(val) = serde_json::from_str::<serde_json::Value>(&line) {
-                            stream_events.push(val);
-                        }
-                    }
-                    stdout_lines.push(line);
-                } else {
-                    stdout_done = true;
-                }
-            }
-            result = stderr_rx.recv(), if !stderr_done => {
-                if let Some(line) = result {
-                    stderr_lines.push(line);
-                } else {
-                    stderr_done = true;
-                }
-            }
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::types::LatencyBreakdown;
+    use rig_cli_process_core::{classify_line, PromptAction};
+
+    #[test]
+    fn test_images_are_staged_into_cwd_and_read_is_granted() {
+        let images = tempfile::tempdir().unwrap();
+        let image = images.path().join("shot.png");
+        std::fs::write(&image, b"\x89PNG").unwrap();
+        let cwd = tempfile::tempdir().unwrap();
+        let config = RunConfig {
+            cwd: Some(cwd.path().to_path_buf()),
+            images: vec![image.clone(), image],
+            tools: crate::types::ToolPolicy {
+                builtin: BuiltinToolSet::None,
+                allowed: Some(vec!["mcp__rig__submit".to_string()]),
+                disallowed: None,
+                disable_slash_commands: false,
+            },
+            ..RunConfig::default()
+        };
+
+        let staged = stage_images(&config).unwrap();
+        assert_eq!(staged.paths.len(), 2);
+        for path in &staged.paths {
+            assert!(path.starts_with(cwd.path()));
+            assert_eq!(std::fs::read(path).unwrap(), b"\x89PNG");
         }
+        let prompt = prompt_with_images("Extract the totals", &staged.paths);
+        assert!(prompt.starts_with("Extract the totals\n\nImages for this task"));
+        assert!(prompt.ends_with("2-shot.png"));
+
+        let granted = with_read_granted(&config);
+        assert!(matches!(
+            granted.tools.builtin,
+            BuiltinToolSet::Explicit(ref tools) if tools == &["Read"]
+        ));
+        assert_eq!(granted.tools.allowed.unwrap(), ["mcp__rig__submit", "Read"]);
+
+        let dir = staged.paths[0].parent().unwrap().to_path_buf();
+        drop(staged);
+        assert!(!dir.exists());
+
+        let missing = RunConfig {
+            images: vec![cwd.path().join("missing.png")],
+            ..RunConfig::default()
+        };
+        assert!(matches!(
+            stage_images(&missing),
+            Err(ClaudeError::InvalidConfig(_))
+        ));
     }
 
-    let status = child.wait().await.map_err(|e| ClaudeError::SpawnFailed {
-        stage: "child.wait()".to_string(),
-        source: e,
-    })?;
-
-    while let Some(result) = tasks.join_next().await {
-        result.map_err(|e| ClaudeError::StreamFailed {
-            stage: "reader task join".to_string(),
-            source: e,
-        })??;
+    #[test]
+    fn test_markers_detect_trust_prompt() {
+        let policy = rig_cli_process_core::InteractivePromptPolicy::default();
+        assert_eq!(
+            classify_line(
+                "  Do you trust the files in this folder?",
+                &policy,
+                INTERACTIVE_PROMPT_MARKERS
+            ),
+            PromptAction::Reject
+        );
+        assert_eq!(
+            classify_line(
+                "Here is the answer you asked for.",
+                &policy,
+                INTERACTIVE_PROMPT_MARKERS
+            ),
+            PromptAction::Ignore
+        );
     }
 
-    let duration = start_time.elapsed();
-    let final_stdout = stdout_lines.join("\n");
-    let final_stderr = stderr_lines.join("\n");
-
-    let json = if output_format == Some(OutputFormat::Json) {
-        serde_json::from_str(&final_stdout).ok()
-    } else {
-        None
-    };
-
-    Ok(RunResult {
-        stdout: final_stdout,
-        stderr: final_stderr,
-        exit_code: status.code().unwrap_or(-1),
-        duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
-        json,
-        stream_events,
-        structured_output: None,
-    })
-}
-
-/// Handles a timeout by collecting remaining output, gracefully shutting down
-/// the child process, and returning a `ClaudeError::Timeout`.
-async fn handle_timeout(
-    child: &mut tokio::process::Child,
-    pid: u32,
-    stdout_rx: &mut mpsc::Receiver<String>,
-    stderr_rx: &mut mpsc::Receiver<String>,
-    tasks: &mut JoinSet<Result<(), ClaudeError>>,
-    config: &RunConfig,
-) -> Result<RunResult, ClaudeError> {
-    let partial_stdout = collect_remaining(stdout_rx);
-    let partial_stderr = collect_remaining(stderr_rx);
-
-    let _ = graceful_shutdown(child, pid).await;
-    tasks.abort_all();
+    #[test]
+    fn test_stream_json_parser_reads_v1_and_v2_events() {
+        let parsers = crate::types::builtin_parsers();
+        let mut parser = StreamJsonParser {
+            format: Some(OutputFormat::StreamJson),
+            parsers: &parsers,
+            version: None,
+            stream_events: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        let v1 = parser.parse(r#"{"type":"text","text":"flat"}"#);
+        assert!(matches!(&v1[..], [StreamEvent::Text { text }] if text == "flat"));
 
-    Err(ClaudeError::Timeout {
-        elapsed: config.timeout,
-        pid,
-        partial_stdout,
-        partial_stderr,
-    })
-}
+        let v2 = parser.parse(
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"envelope"}]}}"#,
+        );
+        assert!(matches!(&v2[..], [StreamEvent::Text { text }] if text == "envelope"));
 
-/// Drains stdout with bounded memory, parses JSONL, and forwards stream events.
-async fn drain_stdout_bounded(
-    stdout: impl tokio::io::AsyncRead + Unpin,
-    tx: mpsc::Sender<String>,
-    sender: Option<mpsc::Sender<crate::types::StreamEvent>>,
-    format: Option<OutputFormat>,
-    max_bytes: usize,
-) -> Result<(), ClaudeError> {
-    let mut reader = BufReader::new(stdout).lines();
-    let mut total_bytes = 0;
-
-    while let Some(line) = reader
-        .next_line()
-        .await
-        .map_err(|e| ClaudeError::SpawnFailed {
-            stage: "stdout read".to_string(),
-            source: e,
-        })?
-    {
-        total_bytes += line.len();
+        let delta = parser.parse(
+            r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"env"}}}"#,
+        );
+        assert!(matches!(&delta[..], [StreamEvent::TextDelta { text }] if text == "env"));
+        assert!(parser
+            .parse(r#"{"type":"stream_event","event":{"type":"message_start"}}"#)
+            .is_empty());
+
+        assert!(parser.parse("not json").is_empty());
+        assert_eq!(parser.stream_events.len(), 4);
+
+        let mut text_parser = StreamJsonParser {
+            format: Some(OutputFormat::Text),
+            parsers: &parsers,
+            version: None,
+            stream_events: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        assert!(text_parser
+            .parse(r#"{"type":"text","text":"flat"}"#)
+            .is_empty());
+    }
 
-        if total_bytes > max_bytes {
-            return Err(ClaudeError::OutputTruncated {
-                captured_bytes: total_bytes,
-                limit_bytes: max_bytes,
-            });
+    #[test]
+    fn test_stream_json_diagnostics_are_kept_out_of_stdout() {
+        let stdout = concat!(
+            r#"{"type":"system","subtype":"init","session_id":"s1","model":"m"}"#,
+            "\n",
+            "[DEBUG] Loaded settings\n",
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"hi"}]}}"#,
+            "\n",
+            r#"{"type":"result","result":"hi"}"#,
+            "\n",
+        );
+        let parsers = crate::types::builtin_parsers();
+        let mut parser = StreamJsonParser {
+            format: Some(OutputFormat::StreamJson),
+            parsers: &parsers,
+            version: None,
+            stream_events: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        for line in stdout.lines() {
+            parser.parse(line);
         }
 
-        if format == Some(OutputFormat::StreamJson) {
-            if let Ok(val) = serde_json::from_str::<serde_json::Value>(&line) {
-                if let Some(ref stream_tx) = sender {
-                    // Try v1.x flat format first, fall back to v2.x envelope format
-                    match serde_json::from_value::<crate::types::StreamEvent>(val.clone()) {
-                        Ok(event) => {
-                            let _ = stream_tx.send(event).await;
-                        }
-                        Err(_) => {
-                            for event in crate::types::extract_v2_events(&val) {
-                                let _ = stream_tx.send(event).await;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        assert_eq!(parser.stream_events.len(), 2);
+        assert_eq!(parser.diagnostics.len(), 2);
+        assert_eq!(parser.diagnostics[0]["subtype"], "init");
+        assert_eq!(parser.diagnostics[1], "[DEBUG] Loaded settings");
 
-        if tx.send(line).await.is_err() {
-            break;
-        }
+        let clean = strip_diagnostics(stdout);
+        assert!(
+            !clean.contains("DEBUG") && !clean.contains("init"),
+            "{clean}"
+        );
+        assert_eq!(clean.lines().count(), 2);
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_stream_json_parser_uses_registered_parsers_for_version() {
+        let parsers = crate::types::builtin_parsers().register(
+            semver::VersionReq::parse(">=9").unwrap(),
+            |val: &serde_json::Value| {
+                let text = val.pointer("/delta/text")?.as_str()?;
+                Some(vec![StreamEvent::Text {
+                    text: text.to_string(),
+                }])
+            },
+        );
+        let line = r#"{"type":"assistant","delta":{"text":"new shape"}}"#;
+
+        let v9 = semver::Version::new(9, 0, 0);
+        let mut parser = StreamJsonParser {
+            format: Some(OutputFormat::StreamJson),
+            parsers: &parsers,
+            version: Some(&v9),
+            stream_events: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        assert!(
+            matches!(&parser.parse(line)[..], [StreamEvent::Text { text }] if text == "new shape")
+        );
 
-/// Drains stderr with bounded memory.
-async fn drain_stderr_bounded(
-    stderr: impl tokio::io::AsyncRead + Unpin,
-    tx: mpsc::Sender<String>,
-    max_bytes: usize,
-) -> Result<(), ClaudeError> {
-    let mut reader = BufReader::new(stderr).lines();
-    let mut total_bytes = 0;
-
-    while let Some(line) = reader
-        .next_line()
-        .await
-        .map_err(|e| ClaudeError::SpawnFailed {
-            stage: "stderr read".to_string(),
-            source: e,
-        })?
-    {
-        total_bytes += line.len();
+        let v2 = semver::Version::new(2, 1, 0);
+        parser.version = Some(&v2);
+        assert!(parser.parse(line).is_empty());
+    }
 
-        if total_bytes > max_bytes {
-            return Err(ClaudeError::OutputTruncated {
-                captured_bytes: total_bytes,
-                limit_bytes: max_bytes,
-            });
-        }
+    #[test]
+    fn test_parse_session_id_from_json_and_stream() {
+        let json = serde_json::json!({ "type": "result", "result": "hi", "session_id": "s-json" });
+        assert_eq!(
+            crate::types::parse_session_id(Some(&json), &[]).as_deref(),
+            Some("s-json")
+        );
 
-        if tx.send(line).await.is_err() {
-            break;
-        }
+        let events = [
+            serde_json::json!({ "type": "system", "subtype": "hook" }),
+            serde_json::json!({ "type": "system", "subtype": "init", "session_id": "s-stream" }),
+        ];
+        assert_eq!(
+            crate::types::parse_session_id(None, &events).as_deref(),
+            Some("s-stream")
+        );
+        assert!(crate::types::parse_session_id(None, &[]).is_none());
+
+        let result = RunResult {
+            stdout: json.to_string(),
+            stderr: String::new(),
+            exit_code: 0,
+            duration_ms: 0,
+            json: Some(json),
+            stream_events: Vec::new(),
+            diagnostics: Vec::new(),
+            structured_output: None,
+            session_id: None,
+            result_text: None,
+            latency: LatencyBreakdown::default(),
+            event_offsets_ms: Vec::new(),
+        };
+        assert_eq!(result.final_text(), result.stdout);
+        assert!(result.usage().is_none());
+        assert!(result.model().is_none());
+        assert!(result.num_turns().is_none());
+        assert!(!result.hit_max_turns());
+
+        let with_models = RunResult {
+            json: Some(serde_json::json!({
+                "result": "hi",
+                "modelUsage": {
+                    "claude-haiku": { "outputTokens": 3 },
+                    "claude-sonnet": { "outputTokens": 40 }
+                }
+            })),
+            ..result.clone()
+        };
+        assert_eq!(with_models.model().as_deref(), Some("claude-sonnet"));
+
+        let events = vec![
+            serde_json::json!({ "type": "system", "subtype": "init", "model": "claude-opus" }),
+            serde_json::json!({ "type": "assistant", "message": { "usage": { "output_tokens": 1 } } }),
+            serde_json::json!({
+                "type": "result",
+                "result": "hi",
+                "num_turns": 3,
+                "usage": {
+                    "input_tokens": 10,
+                    "cache_read_input_tokens": 500,
+                    "output_tokens": 7
+                }
+            }),
+        ];
+        let streamed = RunResult {
+            json: None,
+            stream_events: events,
+            diagnostics: Vec::new(),
+            ..result
+        };
+        let usage = streamed.usage().unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (510, 7));
+        assert_eq!(streamed.model().as_deref(), Some("claude-opus"));
+        assert_eq!(streamed.num_turns(), Some(3));
+        assert!(!streamed.hit_max_turns());
+
+        let capped = RunResult {
+            json: Some(serde_json::json!({
+                "type": "result",
+                "subtype": "error_max_turns",
+                "num_turns": 6
+            })),
+            ..streamed
+        };
+        assert_eq!(capped.num_turns(), Some(6));
+        assert!(capped.hit_max_turns());
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_stream_json_parser_reports_subagent_activity() {
+        let parsers = crate::types::builtin_parsers();
+        let mut parser = StreamJsonParser {
+            format: Some(OutputFormat::StreamJson),
+            parsers: &parsers,
+            version: None,
+            stream_events: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        let start = parser.parse(
+            r#"{"type":"assistant","parent_tool_use_id":null,"message":{"content":[{"type":"tool_use","id":"tu_1","name":"Task","input":{"subagent_type":"reviewer","description":"Review","prompt":"Check the diff"}}]}}"#,
+        );
+        assert!(matches!(
+            &start[..],
+            [StreamEvent::SubagentStart { id, agent, prompt, .. }]
+                if id == "tu_1" && agent == "reviewer" && prompt == "Check the diff"
+        ));
+
+        let nested = parser.parse(
+            r#"{"type":"assistant","parent_tool_use_id":"tu_1","message":{"content":[{"type":"text","text":"looks fine"},{"type":"tool_use","id":"tu_2","name":"Read","input":{}}]}}"#,
+        );
+        assert!(matches!(
+            &nested[..],
+            [
+                StreamEvent::Subagent { parent_tool_use_id: a, event: text },
+                StreamEvent::Subagent { parent_tool_use_id: b, event: call },
+            ] if a == "tu_1" && b == "tu_1"
+                && matches!(text.as_ref(), StreamEvent::Text { text } if text == "looks fine")
+                && matches!(call.as_ref(), StreamEvent::ToolCall { name, .. } if name == "Read")
+        ));
+
+        // The subagent's text is not part of the main agent's answer.
+        assert!(crate::types::parse_result_text(None, &parser.stream_events).is_none());
+    }
 
-/// Sends SIGTERM, waits up to `GRACE_PERIOD`, then force-kills with SIGKILL.
-#[cfg(unix)]
-async fn graceful_shutdown(
-    child: &mut tokio::process::Child,
-    pid: u32,
-) -> Result<std::process::ExitStatus, ClaudeError> {
-    use nix::sys::signal::{self, Signal};
-    use nix::unistd::Pid;
-
-    let nix_pid = Pid::from_raw(pid.cast_signed());
-    signal::kill(nix_pid, Signal::SIGTERM).map_err(|e| ClaudeError::SignalFailed {
-        signal: "SIGTERM".to_string(),
-        pid,
-        reason: e.to_string(),
-    })?;
+    #[test]
+    fn test_parse_result_text_from_envelope_and_assistant_blocks() {
+        let json = serde_json::json!({ "type": "result", "result": "from json" });
+        assert_eq!(
+            crate::types::parse_result_text(Some(&json), &[]).as_deref(),
+            Some("from json")
+        );
 
-    match timeout(GRACE_PERIOD, child.wait()).await {
-        Ok(Ok(status)) => Ok(status),
-        Ok(Err(e)) => Err(ClaudeError::SpawnFailed {
-            stage: "graceful_shutdown wait".to_string(),
-            source: e,
-        }),
-        Err(_) => {
-            child.kill().await.map_err(|e| ClaudeError::SpawnFailed {
-                stage: "SIGKILL".to_string(),
-                source: e,
-            })?;
-            child.wait().await.map_err(|e| ClaudeError::SpawnFailed {
-                stage: "post-SIGKILL wait".to_string(),
-                source: e,
+        let assistant = |text: &str| {
+            serde_json::json!({
+                "type": "assistant",
+                "message": { "content": [
+                    { "type": "tool_use", "name": "Read", "input": {} },
+                    { "type": "text", "text": text }
+                ] }
             })
-        }
+        };
+        let mut events = vec![
+            serde_json::json!({ "type": "system", "subtype": "init" }),
+            assistant("Hello, "),
+            assistant("world"),
+        ];
+        assert_eq!(
+            crate::types::parse_result_text(None, &events).as_deref(),
+            Some("Hello, world")
+        );
+
+        events.push(serde_json::json!({ "type": "result", "result": "final" }));
+        assert_eq!(
+            crate::types::parse_result_text(None, &events).as_deref(),
+            Some("final")
+        );
+        assert!(crate::types::parse_result_text(None, &[]).is_none());
+
+        let partial = "{\"type\":\"system\",\"subtype\":\"init\"}\n\
+            {\"type\":\"assistant\",\"message\":{\"content\":[{\"type\":\"text\",\"text\":\"Hel\"}]}}\n\
+            {\"type\":\"assistant\",\"mess";
+        assert_eq!(
+            crate::types::partial_result_text(partial).as_deref(),
+            Some("Hel")
+        );
+        assert!(crate::types::partial_result_text("").is_none());
+
+        let result = RunResult {
+            stdout: "{\"type\":\"result\"}\n".to_string(),
+            stderr: String::new(),
+            exit_code: 0,
+            duration_ms: 0,
+            json: None,
+            stream_events: events,
+            diagnostics: Vec::new(),
+            structured_output: None,
+            session_id: None,
+            result_text: Some("final".to_string()),
+            latency: LatencyBreakdown::default(),
+            event_offsets_ms: Vec::new(),
+        };
+        assert_eq!(result.final_text(), "final");
     }
-}
 
-/// Windows: no graceful shutdown mechanism for console processes.
-/// Uses immediate TerminateProcess via Child::kill().
-#[cfg(windows)]
-async fn graceful_shutdown(
-    child: &mut tokio::process::Child,
-    _pid: u32,
-) -> Result<std::process::ExitStatus, ClaudeError> {
-    child.kill().await.map_err(|e| ClaudeError::SpawnFailed {
-        stage: "TerminateProcess".to_string(),
-        source: e,
-    })?;
-    child.wait().await.map_err(|e| ClaudeError::SpawnFailed {
-        stage: "post-kill wait".to_string(),
-        source: e,
-    })
-}
+    #[test]
+    fn test_preamble_cache_reuses_file_per_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("preambles");
+        let preamble = "x".repeat(ARG_THRESHOLD + 1);
+
+        let first = cached_preamble_file(&cache, &preamble).unwrap();
+        let modified = std::fs::metadata(&first).unwrap().modified().unwrap();
+        let second = cached_preamble_file(&cache, &preamble).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            std::fs::metadata(&second).unwrap().modified().unwrap(),
+            modified
+        );
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), preamble);
+
+        let other = cached_preamble_file(&cache, "y").unwrap();
+        assert_ne!(other, first);
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 2);
 
-/// Collects any remaining buffered lines from a channel without blocking.
-fn collect_remaining(rx: &mut mpsc::Receiver<String>) -> String {
-    let mut lines = Vec::new();
-    while let Ok(line) = rx.try_recv() {
-        lines.push(line);
+        let config = RunConfig {
+            system_prompt: SystemPromptMode::Append(preamble),
+            preamble_cache_dir: Some(cache),
+            ..RunConfig::default()
+        };
+        let invocation = dry_run(std::path::Path::new("claude"), "hi", &config).unwrap();
+        assert_eq!(invocation.config_files[0].path, first);
     }
-    lines.join("\n")
 }
@@ -1,10 +1,18 @@
 //! Shared data types for Claude CLI adapter configuration and results.
 
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
 use std::path::PathBuf;
 use std::time::Duration;
 
+pub use rig_cli_process_core::{
+    AuthState, AuthStatus, BackpressurePolicy, ConfigFile, EnvPolicy, InteractivePromptPolicy,
+    Invocation, LatencyBreakdown, OutputLimits, OverflowPolicy, ParserRegistry, PromptResponse,
+    RedactionPolicy, ResourceLimits, StallAction, StderrKind, StderrPatterns, StreamParser,
+    TimeoutStage,
+};
+
 /// Output format requested from the Claude CLI.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum OutputFormat {
@@ -27,6 +35,20 @@ pub enum SystemPromptMode {
     Replace(String),
 }
 
+/// Which conversation a CLI run belongs to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SessionMode {
+    /// Start a fresh session.
+    #[default]
+    New,
+    /// Continue the most recent session in the working directory (`--continue`).
+    Continue,
+    /// Resume the session with the given ID (`--resume <id>`).
+    ///
+    /// IDs are reported in [`RunResult::session_id`] of earlier runs.
+    Resume(String),
+}
+
 /// MCP server configuration policy.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpPolicy {
@@ -71,6 +93,215 @@ pub enum JsonSchema {
     Inline(String),
 }
 
+/// A subagent the main Claude agent can delegate to.
+///
+/// Serialized as one entry of the `--agents` JSON object.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AgentDefinition {
+    /// When the main agent should use this subagent.
+    pub description: String,
+    /// System prompt of the subagent.
+    pub prompt: String,
+    /// Tools the subagent may use. `None` inherits every tool of the main agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<String>>,
+    /// Model alias or name (e.g. `"sonnet"`). `None` uses the CLI default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl AgentDefinition {
+    /// Creates a subagent with the given description and system prompt.
+    #[must_use]
+    pub fn new(description: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            prompt: prompt.into(),
+            tools: None,
+            model: None,
+        }
+    }
+
+    /// Restricts the subagent to the given tools.
+    #[must_use]
+    pub fn tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Runs the subagent on the given model.
+    #[must_use]
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+}
+
+/// Subagent definitions passed to the CLI with `--agents`, keyed by name.
+///
+/// The main agent delegates to a subagent through its `Task` tool; that
+/// activity is reported as [`StreamEvent::SubagentStart`] and
+/// [`StreamEvent::Subagent`].
+///
+/// ```
+/// use rig_cli_claude::{AgentDefinition, AgentsConfig};
+///
+/// let agents = AgentsConfig::new().agent(
+///     "reviewer",
+///     AgentDefinition::new("Reviews diffs for bugs", "You are a careful code reviewer.")
+///         .tools(["Read", "Grep"])
+///         .model("sonnet"),
+/// );
+/// assert_eq!(agents.to_json()["reviewer"]["tools"][1], "Grep");
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct AgentsConfig {
+    /// Subagent definitions keyed by name.
+    pub agents: BTreeMap<String, AgentDefinition>,
+}
+
+impl AgentsConfig {
+    /// Creates an empty configuration.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds (or replaces) the subagent called `name`.
+    #[must_use]
+    pub fn agent(mut self, name: impl Into<String>, definition: AgentDefinition) -> Self {
+        self.agents.insert(name.into(), definition);
+        self
+    }
+
+    /// Returns `true` when no subagents are defined.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// The `--agents` JSON object.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+/// Permission mode a run starts in.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    /// Ask before each tool use that needs permission.
+    Default,
+    /// Accept file edits without asking.
+    AcceptEdits,
+    /// Only plan; make no changes.
+    Plan,
+    /// Skip all permission checks.
+    BypassPermissions,
+}
+
+impl PermissionMode {
+    /// The mode's name on the command line and in settings files.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::AcceptEdits => "acceptEdits",
+            Self::Plan => "plan",
+            Self::BypassPermissions => "bypassPermissions",
+        }
+    }
+}
+
+/// Typed settings layered over the user's with `--settings`.
+///
+/// Settings given this way take precedence over the user and project
+/// settings files, so a programmatic run can pin what it depends on even
+/// where [`RunConfig::setting_sources`] still loads some of them. Empty
+/// fields are left out and keep the user's value.
+///
+/// ```
+/// use rig_cli_claude::{PermissionMode, SettingsOverride};
+///
+/// let settings = SettingsOverride {
+///     permission_mode: Some(PermissionMode::Plan),
+///     disabled_mcp_servers: vec!["github".to_string()],
+///     ..SettingsOverride::default()
+/// };
+/// let json = settings.to_json();
+/// assert_eq!(json["permissions"]["defaultMode"], "plan");
+/// assert_eq!(json["disabledMcpjsonServers"][0], "github");
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SettingsOverride {
+    /// Permission mode the run starts in (`permissions.defaultMode`).
+    pub permission_mode: Option<PermissionMode>,
+    /// Servers from project `.mcp.json` files to keep disabled
+    /// (`disabledMcpjsonServers`).
+    pub disabled_mcp_servers: Vec<String>,
+    /// Directories besides the working directory the agent may work in
+    /// (`permissions.additionalDirectories`).
+    pub trusted_directories: Vec<PathBuf>,
+    /// Output style the agent answers in (`outputStyle`), e.g. `"default"`
+    /// to ignore a style the user selected.
+    pub output_style: Option<String>,
+}
+
+impl SettingsOverride {
+    /// The settings as the JSON object `--settings` takes.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut permissions = serde_json::Map::new();
+        if let Some(mode) = self.permission_mode {
+            permissions.insert("defaultMode".to_string(), serde_json::json!(mode));
+        }
+        if !self.trusted_directories.is_empty() {
+            let directories: Vec<_> = self
+                .trusted_directories
+                .iter()
+                .map(|dir| dir.to_string_lossy())
+                .collect();
+            permissions.insert(
+                "additionalDirectories".to_string(),
+                serde_json::json!(directories),
+            );
+        }
+
+        let mut settings = serde_json::Map::new();
+        if !permissions.is_empty() {
+            settings.insert("permissions".to_string(), permissions.into());
+        }
+        if !self.disabled_mcp_servers.is_empty() {
+            settings.insert(
+                "disabledMcpjsonServers".to_string(),
+                serde_json::json!(self.disabled_mcp_servers),
+            );
+        }
+        if let Some(style) = &self.output_style {
+            settings.insert("outputStyle".to_string(), style.clone().into());
+        }
+        settings.into()
+    }
+}
+
+/// Settings passed to the CLI with `--settings`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Settings {
+    /// No `--settings` flag.
+    #[default]
+    None,
+    /// Typed settings, passed as inline JSON.
+    Override(SettingsOverride),
+    /// A settings JSON file.
+    File(PathBuf),
+}
+
 /// Individual feature that the Claude CLI may support.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Feature {
@@ -88,6 +319,46 @@ pub enum Feature {
     StrictMcp,
     /// The `--tools` flag.
     ToolsFlag,
+    /// The `--agents` flag.
+    Agents,
+    /// The `--setting-sources` flag.
+    SettingSources,
+    /// The `--disable-slash-commands` flag.
+    DisableSlashCommands,
+    /// The `--no-session-persistence` flag.
+    NoSessionPersistence,
+    /// The `--include-partial-messages` flag.
+    IncludePartialMessages,
+    /// The `--settings` flag.
+    Settings,
+    /// The `--permission-mode` flag.
+    PermissionMode,
+    /// The `--dangerously-skip-permissions` flag.
+    SkipPermissions,
+}
+
+impl Feature {
+    /// The CLI flag the feature stands for.
+    #[must_use]
+    pub const fn flag(self) -> &'static str {
+        match self {
+            Self::StreamJson => "--output-format stream-json",
+            Self::JsonSchema => "--json-schema",
+            Self::SystemPrompt => "--system-prompt",
+            Self::AppendSystemPrompt => "--append-system-prompt",
+            Self::Mcp => "--mcp-config",
+            Self::StrictMcp => "--strict-mcp-config",
+            Self::ToolsFlag => "--tools",
+            Self::Agents => "--agents",
+            Self::SettingSources => "--setting-sources",
+            Self::DisableSlashCommands => "--disable-slash-commands",
+            Self::NoSessionPersistence => "--no-session-persistence",
+            Self::IncludePartialMessages => "--include-partial-messages",
+            Self::Settings => "--settings",
+            Self::PermissionMode => "--permission-mode",
+            Self::SkipPermissions => "--dangerously-skip-permissions",
+        }
+    }
 }
 
 /// Set of features detected from the Claude CLI help text.
@@ -122,6 +393,18 @@ pub struct InitReport {
     pub capabilities: Capabilities,
 }
 
+impl InitReport {
+    /// The version reported by `claude --version`, parsed as semver.
+    ///
+    /// Reads the first word (e.g. `2.0.14` of `2.0.14 (Claude Code)`);
+    /// `None` if it is not a valid version.
+    #[must_use]
+    pub fn cli_version(&self) -> Option<semver::Version> {
+        let word = self.version.split_whitespace().next()?;
+        semver::Version::parse(word.trim_start_matches('v')).ok()
+    }
+}
+
 /// Configuration for a single Claude CLI invocation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunConfig {
@@ -131,20 +414,64 @@ pub struct RunConfig {
     pub output_format: Option<OutputFormat>,
     /// System prompt mode.
     pub system_prompt: SystemPromptMode,
+    /// Directory a system prompt too long for the command line is written
+    /// to, under a name derived from its contents.
+    ///
+    /// A run whose prompt is already there reuses the file, so a stable
+    /// preamble goes to the CLI from the same path every time instead of a
+    /// fresh temp file per run. `None` writes a temp file that is deleted
+    /// after the run.
+    #[serde(default)]
+    pub preamble_cache_dir: Option<PathBuf>,
     /// Optional MCP server policy.
     pub mcp: Option<McpPolicy>,
     /// Tool access control policy.
     pub tools: ToolPolicy,
     /// Optional JSON schema constraint for structured output.
     pub json_schema: JsonSchema,
-    /// Whether to include partial / in-progress messages in stream output.
+    /// Whether to stream text as it is generated, with
+    /// `--include-partial-messages`.
+    ///
+    /// Only applies with [`OutputFormat::StreamJson`]. Each fragment arrives
+    /// as a [`StreamEvent::TextDelta`]; the complete
+    /// [`StreamEvent::Text`] still follows once the message is finished.
     pub include_partial_messages: bool,
-    /// Maximum wall-clock duration before the process is killed.
+    /// Maximum total wall-clock duration before the process is killed.
     pub timeout: Duration,
+    /// Maximum time spawning the process and writing its stdin may take.
+    /// `None` leaves startup bounded by `timeout` alone.
+    #[serde(default)]
+    pub spawn_timeout: Option<Duration>,
+    /// Maximum time from the start of the run until the process prints its
+    /// first stdout or stderr line, e.g. to catch a hung MCP handshake long
+    /// before `timeout`. `None` disables the check.
+    #[serde(default)]
+    pub first_output_timeout: Option<Duration>,
+    /// Maximum number of agentic turns, with `--max-turns`. `None` leaves
+    /// the CLI uncapped.
+    ///
+    /// Unlike [`timeout`](Self::timeout), hitting the cap ends the run
+    /// normally with a result envelope; see [`RunResult::hit_max_turns`].
+    #[serde(default)]
+    pub max_turns: Option<u32>,
     /// Working directory for the subprocess.
     pub cwd: Option<PathBuf>,
+    /// Image files shown to the model with the prompt, e.g. screenshots to
+    /// extract data from.
+    ///
+    /// Claude Code views images through its `Read` tool, so the prompt gets
+    /// a list of the images to read and `Read` is granted if the tool
+    /// policy leaves it out. With a [`cwd`](Self::cwd), the images are
+    /// copied into a temp directory inside it that is removed after the
+    /// run; otherwise they are read where they are.
+    #[serde(default)]
+    pub images: Vec<PathBuf>,
     /// Extra environment variables passed to the subprocess.
     pub env: Vec<(String, String)>,
+    /// Host environment variables the subprocess inherits. `env` is applied
+    /// on top.
+    #[serde(default)]
+    pub env_policy: EnvPolicy,
     /// Disable session persistence to avoid version-lock conflicts.
     ///
     /// When `true`, adds `--no-session-persistence` to the CLI invocation.
@@ -161,6 +488,86 @@ pub struct RunConfig {
     /// When `None` (default), the flag is omitted and the CLI uses its
     /// normal setting source resolution.
     pub setting_sources: Option<String>,
+    /// Permission mode the run starts in. `None` omits the flag, leaving
+    /// the mode to the settings files.
+    ///
+    /// [`PermissionMode::BypassPermissions`] passes
+    /// `--dangerously-skip-permissions`: the agent may edit files and run
+    /// commands with no confirmation, and every such run logs a warning.
+    /// Only use it in a sandbox the agent cannot escape. The other modes
+    /// pass `--permission-mode`.
+    pub permission_mode: Option<PermissionMode>,
+    /// Settings layered over the user's with `--settings`.
+    #[serde(default)]
+    pub settings: Settings,
+    /// Memory bounds and overflow behaviour for captured stdout / stderr.
+    pub output_limits: OutputLimits,
+    /// File that raw stdout / stderr lines are appended to as they arrive,
+    /// regardless of `output_limits`. Rotated by size.
+    #[serde(default)]
+    pub log_sink: Option<PathBuf>,
+    /// OS limits on the subprocess's memory, CPU time, open files and
+    /// priority.
+    #[serde(default)]
+    pub resource_limits: ResourceLimits,
+    /// How long the subprocess may print nothing on stdout or stderr
+    /// before it counts as stalled. `None` disables stall detection.
+    #[serde(default)]
+    pub stall_timeout: Option<Duration>,
+    /// What to do when the subprocess stalls.
+    #[serde(default)]
+    pub on_stall: StallAction,
+    /// Patterns classifying stderr lines as they arrive, streamed as
+    /// [`StreamEvent::Progress`] and [`StreamEvent::Warning`]. `None` only
+    /// captures stderr.
+    #[serde(default)]
+    pub stderr_events: Option<StderrPatterns>,
+    /// Whether SIGINT and SIGTERM received by the host are passed on to the
+    /// subprocess and its children, cancelling the run with `Cancelled`.
+    ///
+    /// The subprocess then runs in its own process group. Listening for the
+    /// signals stops them from terminating the host for the rest of its
+    /// life, so the host has to exit on its own after a cancelled run.
+    #[serde(default)]
+    pub forward_signals: bool,
+    /// Handling of interactive trust / onboarding prompts.
+    pub interactive_prompts: InteractivePromptPolicy,
+    /// Delivery of stream events when the receiver is slow.
+    pub backpressure: BackpressurePolicy,
+    /// Session to start, continue, or resume.
+    ///
+    /// Continuing or resuming requires the earlier run to have persisted its
+    /// session, i.e. not to have set
+    /// [`no_session_persistence`](Self::no_session_persistence).
+    pub session: SessionMode,
+    /// Subagents the main agent may delegate to. Empty omits `--agents`.
+    pub agents: AgentsConfig,
+    /// Arguments appended verbatim after the generated ones, before the
+    /// prompt, for CLI flags the config does not cover yet.
+    ///
+    /// Running fails with `ConflictingArg` if one of them is a flag the
+    /// config already sets.
+    #[serde(default)]
+    pub extra_args: Vec<OsString>,
+    /// Parsers for stream-JSON output. Defaults to [`builtin_parsers`];
+    /// register more to support a new CLI release.
+    #[serde(skip, default = "builtin_parsers")]
+    pub parsers: StreamParsers,
+    /// CLI version that selects among [`parsers`](Self::parsers).
+    ///
+    /// [`ClaudeCli`](crate::ClaudeCli) fills it in from the detected version
+    /// when unset; `None` applies every parser.
+    #[serde(default)]
+    pub cli_version: Option<semver::Version>,
+    /// Features of the installed CLI, which gate the flags emitted.
+    ///
+    /// Flags that only tune a run, such as `--disable-slash-commands`, are
+    /// left out with a warning when unsupported; running a config that needs
+    /// any other unsupported flag fails with `UnsupportedFeature`.
+    /// [`ClaudeCli`](crate::ClaudeCli) fills it in from the detected
+    /// capabilities when unset; `None` emits every flag unchecked.
+    #[serde(default)]
+    pub capabilities: Option<Capabilities>,
 }
 
 impl Default for RunConfig {
@@ -169,6 +576,7 @@ impl Default for RunConfig {
             model: None,
             output_format: Some(OutputFormat::Text),
             system_prompt: SystemPromptMode::None,
+            preamble_cache_dir: None,
             mcp: None,
             tools: ToolPolicy {
                 builtin: BuiltinToolSet::Default,
@@ -179,10 +587,32 @@ impl Default for RunConfig {
             json_schema: JsonSchema::None,
             include_partial_messages: false,
             timeout: Duration::from_secs(300),
+            spawn_timeout: None,
+            first_output_timeout: None,
+            max_turns: None,
             cwd: None,
+            images: Vec::new(),
             env: Vec::new(),
+            env_policy: EnvPolicy::default(),
             no_session_persistence: false,
             setting_sources: None,
+            permission_mode: None,
+            settings: Settings::None,
+            output_limits: OutputLimits::default(),
+            log_sink: None,
+            resource_limits: ResourceLimits::default(),
+            stall_timeout: None,
+            on_stall: StallAction::Warn,
+            stderr_events: None,
+            forward_signals: false,
+            interactive_prompts: InteractivePromptPolicy::default(),
+            backpressure: BackpressurePolicy::default(),
+            session: SessionMode::default(),
+            agents: AgentsConfig::default(),
+            extra_args: Vec::new(),
+            parsers: builtin_parsers(),
+            cli_version: None,
+            capabilities: None,
         }
     }
 }
@@ -201,9 +631,211 @@ pub struct RunResult {
     /// Parsed JSON output (when `OutputFormat::Json` was requested).
     pub json: Option<serde_json::Value>,
     /// Parsed JSONL stream events (when `OutputFormat::StreamJson` was used).
+    ///
+    /// Holds the conversation and result events; `system` events are kept
+    /// in [`diagnostics`](Self::diagnostics) instead.
     pub stream_events: Vec<serde_json::Value>,
+    /// Diagnostics the CLI printed on stdout in stream-JSON mode, in order:
+    /// `system` events (init, hooks, compaction) as parsed, and lines that
+    /// are not JSON, such as `--verbose` debug output, as strings.
+    ///
+    /// They are left out of [`stdout`](Self::stdout), which then holds only
+    /// the conversation and result events.
+    #[serde(default)]
+    pub diagnostics: Vec<serde_json::Value>,
     /// Optional structured output parsed against a JSON schema.
     pub structured_output: Option<serde_json::Value>,
+    /// Session ID reported by the CLI, when JSON or stream-JSON output was
+    /// requested. Pass it to [`SessionMode::Resume`] to continue the conversation.
+    pub session_id: Option<String>,
+    /// The assistant's final answer, extracted from JSON or stream-JSON output.
+    ///
+    /// `None` for text output, where stdout already is the answer.
+    pub result_text: Option<String>,
+    /// Where the run's time went: spawn, first text, first tool call, exit.
+    #[serde(default)]
+    pub latency: LatencyBreakdown,
+    /// Milliseconds from the start of the run at which each stream event was
+    /// parsed, in parse order. Under [`BackpressurePolicy::Block`] the n-th
+    /// offset belongs to the n-th event received.
+    #[serde(default)]
+    pub event_offsets_ms: Vec<u64>,
+}
+
+impl RunResult {
+    /// The assistant's final answer.
+    ///
+    /// This is [`result_text`](Self::result_text) when JSON or stream-JSON
+    /// output was requested, and the raw stdout otherwise.
+    #[must_use]
+    pub fn final_text(&self) -> &str {
+        self.result_text.as_deref().unwrap_or(&self.stdout)
+    }
+
+    /// Token usage reported by the CLI, when JSON or stream-JSON output was requested.
+    ///
+    /// Input tokens include prompt tokens written to and read from the cache.
+    #[must_use]
+    pub fn usage(&self) -> Option<TokenUsage> {
+        let usage = self
+            .json
+            .iter()
+            .chain(self.stream_events.iter().rev())
+            .find_map(|val| val.get("usage").filter(|_| val.get("result").is_some()))?;
+        let count = |key: &str| {
+            usage
+                .get(key)
+                .and_then(serde_json::Value::as_u64)
+                .unwrap_or(0)
+        };
+        Some(TokenUsage {
+            input_tokens: count("input_tokens")
+                + count("cache_creation_input_tokens")
+                + count("cache_read_input_tokens"),
+            output_tokens: count("output_tokens"),
+        })
+    }
+
+    /// The model that answered, when JSON or stream-JSON output was requested.
+    ///
+    /// Taken from the stream's `init` event, or else from the result
+    /// envelope's per-model usage, picking the model that generated the most
+    /// output (background calls may use a smaller model).
+    #[must_use]
+    pub fn model(&self) -> Option<String> {
+        let init = self
+            .diagnostics
+            .iter()
+            .chain(&self.stream_events)
+            .filter(|val| val.get("subtype").and_then(serde_json::Value::as_str) == Some("init"))
+            .find_map(|val| val.get("model").and_then(serde_json::Value::as_str));
+        if let Some(model) = init {
+            return Some(model.to_string());
+        }
+
+        let output_tokens = |usage: &serde_json::Value| {
+            usage
+                .get("outputTokens")
+                .and_then(serde_json::Value::as_u64)
+                .unwrap_or(0)
+        };
+        self.json
+            .iter()
+            .chain(self.stream_events.iter().rev())
+            .find_map(|val| val.get("modelUsage").and_then(serde_json::Value::as_object))?
+            .iter()
+            .max_by_key(|(_, usage)| output_tokens(usage))
+            .map(|(model, _)| model.clone())
+    }
+
+    /// Number of agentic turns the CLI took, from the result envelope, when
+    /// JSON or stream-JSON output was requested.
+    #[must_use]
+    pub fn num_turns(&self) -> Option<u32> {
+        self.result_envelope()?
+            .get("num_turns")
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|turns| u32::try_from(turns).ok())
+    }
+
+    /// Whether the CLI stopped because it reached
+    /// [`RunConfig::max_turns`] rather than finishing its answer.
+    #[must_use]
+    pub fn hit_max_turns(&self) -> bool {
+        self.result_envelope()
+            .and_then(|val| val.get("subtype"))
+            .and_then(serde_json::Value::as_str)
+            == Some("error_max_turns")
+    }
+
+    /// The JSON output, or else the last `result` event of the stream.
+    fn result_envelope(&self) -> Option<&serde_json::Value> {
+        self.json.as_ref().or_else(|| {
+            self.stream_events
+                .iter()
+                .rev()
+                .find(|val| val.get("type").and_then(serde_json::Value::as_str) == Some("result"))
+        })
+    }
+}
+
+/// Token counts reported by the CLI for a run.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// Tokens the model read, including cached prompt tokens.
+    pub input_tokens: u64,
+    /// Tokens the model generated.
+    pub output_tokens: u64,
+}
+
+/// Extracts the final answer from JSON output or stream events.
+///
+/// Prefers the `result` field of the result envelope. A stream that ended
+/// without one (e.g. it was truncated) falls back to the text blocks of its
+/// assistant messages, concatenated in order.
+pub(crate) fn parse_result_text(
+    json: Option<&serde_json::Value>,
+    stream_events: &[serde_json::Value],
+) -> Option<String> {
+    let is_type = |val: &serde_json::Value, ty: &str| {
+        val.get("type").and_then(serde_json::Value::as_str) == Some(ty)
+    };
+
+    let streamed = stream_events
+        .iter()
+        .rev()
+        .filter(|val| is_type(val, "result"));
+    let envelope = json
+        .into_iter()
+        .chain(streamed)
+        .find_map(|val| val.get("result").and_then(serde_json::Value::as_str));
+    if let Some(text) = envelope {
+        return Some(text.to_string());
+    }
+
+    // Subagent messages carry a `parent_tool_use_id`; only the main agent answers.
+    let text: String = stream_events
+        .iter()
+        .filter(|val| is_type(val, "assistant"))
+        .filter(|val| {
+            val.get("parent_tool_use_id")
+                .and_then(serde_json::Value::as_str)
+                .is_none()
+        })
+        .filter_map(|val| val.pointer("/message/content"))
+        .filter_map(serde_json::Value::as_array)
+        .flatten()
+        .filter(|block| is_type(block, "text"))
+        .filter_map(|block| block.get("text").and_then(serde_json::Value::as_str))
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Recovers whatever answer text a timed-out run had produced.
+///
+/// `partial_stdout` is the stdout captured before the timeout, e.g. the
+/// `partial_stdout` of [`ClaudeError::Timeout`](crate::ClaudeError::Timeout).
+/// Each line that parses as JSON is treated as a stream event; lines that do
+/// not (such as an unfinished final line) are skipped. Returns `None` when no
+/// assistant text was streamed before the timeout.
+#[must_use]
+pub fn partial_result_text(partial_stdout: &str) -> Option<String> {
+    let events: Vec<serde_json::Value> = partial_stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    parse_result_text(None, &events)
+}
+
+/// Finds the `session_id` in JSON output or, failing that, in stream events.
+pub(crate) fn parse_session_id(
+    json: Option<&serde_json::Value>,
+    stream_events: &[serde_json::Value],
+) -> Option<String> {
+    json.into_iter()
+        .chain(stream_events)
+        .find_map(|val| val.get("session_id").and_then(serde_json::Value::as_str))
+        .map(str::to_string)
 }
 
 /// A typed event received during a streaming Claude CLI run.
@@ -215,6 +847,13 @@ pub enum StreamEvent {
         /// The text content.
         text: String,
     },
+    /// A fragment of assistant text still being generated, reported when
+    /// [`RunConfig::include_partial_messages`] is set. The fragments of a
+    /// message are followed by its complete [`Text`](Self::Text).
+    TextDelta {
+        /// The new text.
+        text: String,
+    },
     /// The assistant invoked a tool.
     ToolCall {
         /// Name of the tool being called.
@@ -229,6 +868,43 @@ pub enum StreamEvent {
         /// Textual output from the tool.
         output: String,
     },
+    /// The main agent delegated a task to a subagent.
+    SubagentStart {
+        /// Tool use ID of the delegation, carried by the subagent's
+        /// [`Subagent`](Self::Subagent) events.
+        id: String,
+        /// Name of the subagent, as defined in [`AgentsConfig`].
+        agent: String,
+        /// Short description of the delegated task.
+        description: String,
+        /// Prompt the subagent was given.
+        prompt: String,
+    },
+    /// An event produced inside a subagent's run.
+    Subagent {
+        /// Tool use ID of the [`SubagentStart`](Self::SubagentStart) it belongs to.
+        parent_tool_use_id: String,
+        /// The subagent's event.
+        event: Box<Self>,
+    },
+    /// The subprocess has printed nothing for
+    /// [`RunConfig::stall_timeout`].
+    Stalled {
+        /// Milliseconds since the last output line.
+        idle_ms: u64,
+    },
+    /// A stderr line classified as progress by
+    /// [`RunConfig::stderr_events`], e.g. `Thinking…`.
+    Progress {
+        /// The line, without ANSI escapes.
+        message: String,
+    },
+    /// A stderr line classified as a warning by
+    /// [`RunConfig::stderr_events`]. The run carries on.
+    Warning {
+        /// The line, without ANSI escapes.
+        message: String,
+    },
     /// An error event emitted by the CLI.
     Error {
         /// Human-readable error message.
@@ -238,11 +914,53 @@ pub enum StreamEvent {
     Unknown(serde_json::Value),
 }
 
+impl rig_cli_process_core::TextEvent for StreamEvent {
+    fn text_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::Text { text } => Some(text),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> rig_cli_process_core::EventKind {
+        use rig_cli_process_core::EventKind;
+        match self {
+            Self::Text { .. } | Self::TextDelta { .. } => EventKind::Text,
+            Self::ToolCall { .. } | Self::SubagentStart { .. } => EventKind::ToolCall,
+            _ => EventKind::Other,
+        }
+    }
+}
+
+/// Parsers for Claude stream-JSON output, keyed by CLI version.
+pub type StreamParsers = ParserRegistry<StreamEvent>;
+
+/// The built-in stream-JSON parsers.
+///
+/// Claude Code v2.x envelopes are read by [`extract_v2_events`]; the flat
+/// [`StreamEvent`] format of v1.x is tried first. Both apply to every
+/// version, as a CLI may emit either.
+#[must_use]
+pub fn builtin_parsers() -> StreamParsers {
+    StreamParsers::new()
+        .register(semver::VersionReq::STAR, |val: &serde_json::Value| {
+            Some(extract_v2_events(val))
+        })
+        .register(semver::VersionReq::STAR, |val: &serde_json::Value| {
+            serde_json::from_value::<StreamEvent>(val.clone())
+                .ok()
+                .map(|event| vec![event])
+        })
+}
+
 /// Extracts [`StreamEvent`]s from Claude Code v2.x stream-json envelope format.
 ///
 /// Claude Code v2.x wraps content in message envelopes:
 /// - `{"type":"assistant","message":{"content":[{"type":"text",...},{"type":"tool_use",...}]}}`
 /// - `{"type":"result","result":"...","is_error":false}`
+/// - `{"type":"stream_event","event":{"type":"content_block_delta",...}}`
+///   (with `--include-partial-messages`; text deltas become
+///   [`StreamEvent::TextDelta`], other API events are skipped)
 /// - `{"type":"system",...}` (informational, skipped)
 ///
 /// This function unwraps those envelopes into the flat [`StreamEvent`] variants
@@ -272,10 +990,12 @@ pub fn extract_v2_events(val: &serde_json::Value) -> Vec<StreamEvent> {
                                 block.get("name").and_then(serde_json::Value::as_str),
                                 block.get("input"),
                             ) {
-                                events.push(StreamEvent::ToolCall {
-                                    name: name.to_string(),
-                                    input: input.clone(),
-                                });
+                                events.push(subagent_start(name, block, input).unwrap_or_else(
+                                    || StreamEvent::ToolCall {
+                                        name: name.to_string(),
+                                        input: input.clone(),
+                                    },
+                                ));
                             }
                         }
                         Some("tool_result") => {
@@ -298,6 +1018,22 @@ pub fn extract_v2_events(val: &serde_json::Value) -> Vec<StreamEvent> {
                 }
             }
         }
+        Some("stream_event") => {
+            if val
+                .pointer("/event/delta/type")
+                .and_then(serde_json::Value::as_str)
+                == Some("text_delta")
+            {
+                if let Some(text) = val
+                    .pointer("/event/delta/text")
+                    .and_then(serde_json::Value::as_str)
+                {
+                    events.push(StreamEvent::TextDelta {
+                        text: text.to_string(),
+                    });
+                }
+            }
+        }
         Some("result") => {
             if val.get("is_error") == Some(&serde_json::Value::Bool(true)) {
                 let msg = val
@@ -313,5 +1049,48 @@ pub fn extract_v2_events(val: &serde_json::Value) -> Vec<StreamEvent> {
         _ => {}
     }
 
-    events
+    // Messages from inside a subagent's run name the delegation they belong to.
+    match val
+        .get("parent_tool_use_id")
+        .and_then(serde_json::Value::as_str)
+    {
+        Some(parent) => events
+            .into_iter()
+            .map(|event| StreamEvent::Subagent {
+                parent_tool_use_id: parent.to_string(),
+                event: Box::new(event),
+            })
+            .collect(),
+        None => events,
+    }
+}
+
+/// Name of the tool the main agent delegates to subagents with.
+const SUBAGENT_TOOL: &str = "Task";
+
+/// Reads a `Task` tool call as a [`StreamEvent::SubagentStart`].
+fn subagent_start(
+    name: &str,
+    block: &serde_json::Value,
+    input: &serde_json::Value,
+) -> Option<StreamEvent> {
+    if name != SUBAGENT_TOOL {
+        return None;
+    }
+    let field = |key: &str| {
+        input
+            .get(key)
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    Some(StreamEvent::SubagentStart {
+        id: block
+            .get("id")
+            .and_then(serde_json::Value::as_str)?
+            .to_string(),
+        agent: input.get("subagent_type")?.as_str()?.to_string(),
+        description: field("description"),
+        prompt: field("prompt"),
+    })
 }
@@ -266,6 +266,7 @@ async fn e2e_timeout_graceful_shutdown() {
             pid,
             partial_stdout,
             partial_stderr,
+            ..
         }) => {
             // Expected: timeout with partial output captured
             assert!(
@@ -0,0 +1,72 @@
+//! Verbatim extra arguments passed through to a CLI.
+//!
+//! Each adapter's config has an `extra_args` list that is added to the
+//! arguments its builder generates, so a new CLI flag can be used before the
+//! typed config supports it. An extra flag the builder already emits would
+//! be passed twice, which CLIs either reject or resolve silently in favour
+//! of one of the values, so [`check_extra_args`] refuses it instead.
+
+use crate::error::ProcessError;
+use std::ffi::{OsStr, OsString};
+
+/// Checks that no flag in `extra` is also set by the generated arguments.
+///
+/// `args` is the full argument list, including `extra`. A flag is matched by
+/// its name, so `--model=x` conflicts with a generated `--model x`. Short
+/// flags are compared as written; an alias such as `-m` for `--model` is not
+/// detected.
+///
+/// # Errors
+/// Returns [`ProcessError::ConflictingArg`] for the first conflicting flag.
+pub fn check_extra_args(args: &[OsString], extra: &[OsString]) -> Result<(), ProcessError> {
+    let count = |list: &[OsString], name: &str| {
+        list.iter()
+            .filter(|arg| flag_name(arg) == Some(name))
+            .count()
+    };
+    for name in extra.iter().filter_map(|arg| flag_name(arg)) {
+        if count(args, name) > count(extra, name) {
+            return Err(ProcessError::ConflictingArg {
+                flag: name.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The name of a flag argument, without any `=value`. `None` for values and
+/// for the `-` and `--` markers.
+fn flag_name(arg: &OsStr) -> Option<&str> {
+    let arg = arg.to_str()?;
+    if !arg.starts_with('-') || arg == "-" || arg == "--" {
+        return None;
+    }
+    arg.split('=').next()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn os(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_check_extra_args_detects_generated_flags() {
+        let extra = os(&["--betas", "x", "-v"]);
+        let args = os(&["--print", "--model", "opus", "--betas", "x", "-v", "prompt"]);
+        check_extra_args(&args, &extra).unwrap();
+
+        let extra = os(&["--model=sonnet"]);
+        let args = os(&["--print", "--model", "opus", "--model=sonnet", "prompt"]);
+        let err = check_extra_args(&args, &extra).unwrap_err();
+        assert!(matches!(err, ProcessError::ConflictingArg { ref flag } if flag == "--model"));
+
+        // A value that looks like a flag is only a conflict if generated.
+        let extra = os(&["--add-dir", "a", "--add-dir", "b"]);
+        let args = os(&["exec", "--add-dir", "a", "--add-dir", "b", "prompt"]);
+        check_extra_args(&args, &extra).unwrap();
+    }
+}
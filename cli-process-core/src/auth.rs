@@ -0,0 +1,33 @@
+//! Login state of a CLI, as reported by each adapter's `auth_status`.
+
+use serde::{Deserialize, Serialize};
+
+/// Whether the CLI has usable credentials.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuthState {
+    /// The CLI reports an active login or API key.
+    LoggedIn,
+    /// The CLI reports no usable credentials.
+    LoggedOut,
+    /// Login state could not be determined.
+    Unknown,
+}
+
+/// Login state reported by an adapter's `auth_status`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuthStatus {
+    /// Detected login state.
+    pub state: AuthState,
+    /// How the CLI is authenticated (account type, API key, provider list), when known.
+    pub method: Option<String>,
+    /// Command the user should run to log in.
+    pub login_command: String,
+}
+
+impl AuthStatus {
+    /// Returns `true` when the CLI reported an active login.
+    #[must_use]
+    pub fn is_logged_in(&self) -> bool {
+        self.state == AuthState::LoggedIn
+    }
+}
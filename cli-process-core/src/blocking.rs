@@ -0,0 +1,55 @@
+//! Driving the async runners from synchronous code.
+//!
+//! The adapters' `run_blocking` methods and `rig-cli`'s blocking client
+//! methods go through [`block_on`], so callers without a Tokio runtime of
+//! their own (synchronous applications, FFI layers) never have to build one.
+
+use std::future::Future;
+use std::io;
+
+/// Runs `future` to completion on a current-thread runtime built for the call.
+///
+/// The runtime has the I/O and time drivers enabled, which subprocess
+/// spawning, pipe draining and timeouts need. Tasks the future spawns are
+/// dropped with the runtime once it returns.
+///
+/// # Errors
+/// Returns an [`io::Error`] if called from inside a Tokio runtime, where
+/// blocking the thread would stall the caller's runtime (await the async
+/// method instead), or if the runtime cannot be built.
+pub fn block_on<F: Future>(future: F) -> io::Result<F::Output> {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Err(io::Error::other(
+            "blocking call made from inside a Tokio runtime; await the async method instead",
+        ));
+    }
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    Ok(runtime.block_on(future))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_block_on_drives_timers_and_processes() {
+        let output = block_on(async {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            tokio::process::Command::new("true").output().await
+        })
+        .unwrap()
+        .unwrap();
+        assert!(output.status.success());
+    }
+
+    #[tokio::test]
+    async fn test_block_on_refuses_to_run_inside_a_runtime() {
+        let err = block_on(async {}).unwrap_err();
+        assert!(err.to_string().contains("await the async method"), "{err}");
+    }
+}
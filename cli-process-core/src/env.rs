@@ -0,0 +1,85 @@
+//! Control over the host environment a spawned CLI inherits.
+//!
+//! By default a CLI sees every variable of the host process, including API
+//! keys and tokens meant for other tools. [`EnvPolicy::Allowlist`] starts the
+//! child from an empty environment and copies over only the named host
+//! variables; variables the adapter sets explicitly (its `env` settings,
+//! credentials) are applied afterwards and always reach the child.
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// Host variables a CLI needs to start and find its own configuration.
+pub const BASE_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "TERM",
+    "TMPDIR",
+    "SYSTEMROOT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "TEMP",
+    "TMP",
+];
+
+/// Which host environment variables a spawned CLI inherits.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnvPolicy {
+    /// Inherit the whole host environment.
+    #[default]
+    Inherit,
+    /// Inherit only the listed variables.
+    Allowlist(Vec<String>),
+}
+
+impl EnvPolicy {
+    /// An allowlist of [`BASE_VARS`]: enough for a CLI to run and use a
+    /// login stored in its config directory, but no host secrets.
+    #[must_use]
+    pub fn isolated() -> Self {
+        Self::Allowlist(BASE_VARS.iter().map(|var| (*var).to_string()).collect())
+    }
+
+    /// Applies the policy to `cmd`. Call before setting explicit variables.
+    pub fn apply(&self, cmd: &mut Command) {
+        let Self::Allowlist(vars) = self else {
+            return;
+        };
+        cmd.env_clear();
+        for var in vars {
+            if let Some(value) = std::env::var_os(var) {
+                cmd.env(var, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_copies_only_listed_host_vars() {
+        let mut cmd = Command::new("true");
+        EnvPolicy::Allowlist(vec!["PATH".to_string()]).apply(&mut cmd);
+        cmd.env("EXPLICIT", "1");
+
+        let envs: Vec<_> = cmd
+            .as_std()
+            .get_envs()
+            .map(|(k, _)| k.to_string_lossy().into_owned())
+            .collect();
+        let expected_path = std::env::var_os("PATH").is_some();
+        assert_eq!(envs.iter().any(|k| k == "PATH"), expected_path);
+        assert!(envs.iter().any(|k| k == "EXPLICIT"));
+        assert_eq!(envs.len(), usize::from(expected_path) + 1);
+
+        let mut inherited = Command::new("true");
+        EnvPolicy::Inherit.apply(&mut inherited);
+        assert_eq!(inherited.as_std().get_envs().count(), 0);
+    }
+}
@@ -0,0 +1,161 @@
+//! Error type shared by the process runner.
+//!
+//! Adapters convert [`ProcessError`] into their own error enums, so callers
+//! of `rig-cli-claude`, `rig-cli-codex` and `rig-cli-opencode` never see it
+//! directly.
+
+use thiserror::Error;
+
+/// Errors raised while spawning, draining or stopping a CLI subprocess.
+#[derive(Debug, Error)]
+pub enum ProcessError {
+    /// Spawning, reading from, writing to or waiting on the child failed.
+    #[error("Process I/O failed at stage '{stage}': {source}")]
+    Io {
+        /// Human-readable label for the lifecycle stage that failed.
+        stage: String,
+        /// Underlying I/O error.
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A pipe reader task panicked or was cancelled.
+    #[error("Reader task failed at stage '{stage}': {source}")]
+    Join {
+        /// Lifecycle stage label.
+        stage: String,
+        /// The join error from the failed task.
+        #[source]
+        source: tokio::task::JoinError,
+    },
+
+    /// Sending a signal to the child process failed.
+    #[error("Failed to send signal {signal} to PID {pid}: {reason}")]
+    Signal {
+        /// Signal name (e.g. `SIGTERM`).
+        signal: String,
+        /// OS process identifier.
+        pid: u32,
+        /// Platform-specific error description.
+        reason: String,
+    },
+
+    /// The child process exceeded one of its timeouts.
+    #[error("Process hit its {stage} timeout after {elapsed:?} (PID: {pid})")]
+    Timeout {
+        /// Which timeout fired.
+        stage: TimeoutStage,
+        /// Wall-clock time elapsed before the timeout fired.
+        elapsed: std::time::Duration,
+        /// OS process identifier of the timed-out child.
+        pid: u32,
+        /// Stdout captured before the timeout.
+        partial_stdout: String,
+        /// Stderr captured before the timeout.
+        partial_stderr: String,
+    },
+
+    /// The child produced no output for its stall timeout under
+    /// [`StallAction::Abort`](crate::StallAction::Abort).
+    #[error("Process stalled: no output for {idle:?} (PID: {pid})")]
+    Stalled {
+        /// How long the child had been silent.
+        idle: std::time::Duration,
+        /// When the child last printed a line, or was spawned if it never did.
+        last_activity: std::time::SystemTime,
+        /// OS process identifier of the stalled child.
+        pid: u32,
+        /// Stdout captured before the stall.
+        partial_stdout: String,
+        /// Stderr captured before the stall.
+        partial_stderr: String,
+    },
+
+    /// The host received SIGINT or SIGTERM while the child ran, and passed
+    /// it on under
+    /// [`ProcessRunner::forward_signals`](crate::ProcessRunner::forward_signals).
+    #[error("Process cancelled by {signal} (PID: {pid})")]
+    Cancelled {
+        /// Name of the forwarded signal (e.g. `SIGINT`).
+        signal: String,
+        /// OS process identifier of the cancelled child.
+        pid: u32,
+        /// Stdout captured before the signal.
+        partial_stdout: String,
+        /// Stderr captured before the signal.
+        partial_stderr: String,
+    },
+
+    /// Output exceeded its [`OutputLimits`](crate::OutputLimits) under
+    /// [`OverflowPolicy::Error`](crate::OverflowPolicy::Error).
+    #[error("Output truncated: captured {captured_bytes} bytes (limit: {limit_bytes} bytes)")]
+    OutputTruncated {
+        /// Number of bytes captured so far.
+        captured_bytes: usize,
+        /// Maximum allowed bytes.
+        limit_bytes: usize,
+    },
+
+    /// The CLI stopped to ask an interactive question that no configured
+    /// auto-answer matched.
+    #[error("CLI is waiting on an interactive prompt: {question}")]
+    InteractivePromptDetected {
+        /// The prompt line printed by the CLI.
+        question: String,
+    },
+
+    /// Child stdout pipe was not captured.
+    #[error("Child process stdout was not captured")]
+    NoStdout,
+
+    /// Child stderr pipe was not captured.
+    #[error("Child process stderr was not captured")]
+    NoStderr,
+
+    /// Could not retrieve the PID from the spawned child.
+    #[error("Could not get PID from child process")]
+    NoPid,
+
+    /// An extra argument sets a flag the argument builder already emits.
+    #[error("Extra argument '{flag}' conflicts with a flag set from the config")]
+    ConflictingArg {
+        /// The conflicting flag, without any `=value`.
+        flag: String,
+    },
+}
+
+/// The stage of a run a [`ProcessError::Timeout`] fired in.
+///
+/// Tells a CLI that never got going, such as one hung on an MCP handshake,
+/// from one whose model is merely slow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutStage {
+    /// Spawning the child and writing its stdin took longer than the
+    /// spawn timeout.
+    Spawn,
+    /// The child printed no line on stdout or stderr within the
+    /// first-output timeout.
+    FirstOutput,
+    /// The whole run took longer than the total timeout.
+    Total,
+}
+
+impl std::fmt::Display for TimeoutStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Spawn => "spawn",
+            Self::FirstOutput => "first output",
+            Self::Total => "total",
+        })
+    }
+}
+
+impl ProcessError {
+    /// Wraps an I/O error with the stage it occurred in.
+    pub(crate) fn io(stage: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Io {
+            stage: stage.into(),
+            source,
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Delivery of parsed stream events to the caller's channel.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+/// How stream events are delivered when the receiver falls behind.
+///
+/// Applies to the `sender` passed to each adapter's run function. The
+/// non-blocking policies hold at most one channel's worth of extra events in
+/// an overflow queue, which is flushed as the receiver catches up and in
+/// full when the stream ends.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Wait for the receiver, pausing the stdout reader. Nothing is lost.
+    #[default]
+    Block,
+    /// Discard the oldest queued events, keeping the most recent ones.
+    DropOldest,
+    /// Discard new events while the channel is full.
+    DropNewest,
+    /// Merge consecutive queued text chunks into one event; other events
+    /// wait for the receiver as with [`Block`](Self::Block).
+    CoalesceText,
+}
+
+/// What a stream event marks in the run's
+/// [`LatencyBreakdown`](crate::LatencyBreakdown).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// Assistant text, complete or partial.
+    Text,
+    /// The assistant invoked a tool.
+    ToolCall,
+    /// Anything else.
+    Other,
+}
+
+/// A stream event that may carry a plain text chunk.
+///
+/// Implemented by each adapter's `StreamEvent` so [`EventSink`] can merge
+/// text under [`BackpressurePolicy::CoalesceText`], and the runner can time
+/// the first text and tool call.
+pub trait TextEvent {
+    /// Returns the text of a plain text chunk, or `None` for any other event.
+    fn text_mut(&mut self) -> Option<&mut String>;
+
+    /// What the event marks in the run's latency. Default:
+    /// [`EventKind::Other`].
+    fn kind(&self) -> EventKind {
+        EventKind::Other
+    }
+}
+
+/// Forwards stream events to the caller under a [`BackpressurePolicy`].
+#[derive(Debug)]
+pub struct EventSink<E> {
+    tx: mpsc::Sender<E>,
+    policy: BackpressurePolicy,
+    /// Events the channel had no room for, oldest first.
+    pending: VecDeque<E>,
+}
+
+impl<E: TextEvent> EventSink<E> {
+    /// Wraps `tx`, delivering events under `policy`.
+    #[must_use]
+    pub const fn new(tx: mpsc::Sender<E>, policy: BackpressurePolicy) -> Self {
+        Self {
+            tx,
+            policy,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Delivers `event`, queueing or dropping it if the receiver is behind.
+    pub async fn push(&mut self, event: E) {
+        match self.policy {
+            BackpressurePolicy::Block => {
+                let _ = self.tx.send(event).await;
+            }
+            BackpressurePolicy::DropNewest => {
+                let _ = self.tx.try_send(event);
+            }
+            BackpressurePolicy::DropOldest | BackpressurePolicy::CoalesceText => {
+                self.flush();
+                if self.pending.is_empty() {
+                    match self.tx.try_send(event) {
+                        Err(TrySendError::Full(event)) => self.pending.push_back(event),
+                        Ok(()) | Err(TrySendError::Closed(_)) => {}
+                    }
+                    return;
+                }
+                self.enqueue(event).await;
+            }
+        }
+    }
+
+    /// Adds `event` behind the queued events, keeping the queue within the
+    /// channel's capacity.
+    async fn enqueue(&mut self, mut event: E) {
+        if self.policy == BackpressurePolicy::CoalesceText {
+            if let (Some(queued), Some(text)) = (
+                self.pending.back_mut().and_then(TextEvent::text_mut),
+                event.text_mut(),
+            ) {
+                queued.push_str(text);
+                return;
+            }
+        }
+        self.pending.push_back(event);
+
+        while self.pending.len() > self.tx.max_capacity() {
+            let Some(oldest) = self.pending.pop_front() else {
+                break;
+            };
+            if self.policy == BackpressurePolicy::CoalesceText
+                && self.tx.send(oldest).await.is_err()
+            {
+                self.pending.clear();
+            }
+        }
+    }
+
+    /// Moves queued events into the channel while it has room.
+    fn flush(&mut self) {
+        while let Some(event) = self.pending.pop_front() {
+            match self.tx.try_send(event) {
+                Ok(()) => {}
+                Err(TrySendError::Full(event)) => {
+                    self.pending.push_front(event);
+                    return;
+                }
+                Err(TrySendError::Closed(_)) => {
+                    self.pending.clear();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Delivers every queued event once the stream has ended.
+    pub async fn finish(mut self) {
+        while let Some(event) = self.pending.pop_front() {
+            if self.tx.send(event).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Text(String),
+        Error(String),
+    }
+
+    impl TextEvent for Event {
+        fn text_mut(&mut self) -> Option<&mut String> {
+            match self {
+                Self::Text(text) => Some(text),
+                Self::Error(_) => None,
+            }
+        }
+    }
+
+    async fn push_digits(policy: BackpressurePolicy) -> Vec<Event> {
+        let (tx, mut rx) = mpsc::channel(2);
+        let mut sink = EventSink::new(tx, policy);
+        for i in 0..5 {
+            sink.push(Event::Text(i.to_string())).await;
+        }
+        let mut received = vec![rx.recv().await.unwrap(), rx.recv().await.unwrap()];
+        sink.finish().await;
+        while let Ok(event) = rx.try_recv() {
+            received.push(event);
+        }
+        received
+    }
+
+    fn texts(values: &[&str]) -> Vec<Event> {
+        values
+            .iter()
+            .map(|v| Event::Text((*v).to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_event_sink_drop_policies() {
+        assert_eq!(
+            push_digits(BackpressurePolicy::DropNewest).await,
+            texts(&["0", "1"])
+        );
+        assert_eq!(
+            push_digits(BackpressurePolicy::DropOldest).await,
+            texts(&["0", "1", "3", "4"])
+        );
+        assert_eq!(
+            push_digits(BackpressurePolicy::CoalesceText).await,
+            texts(&["0", "1", "234"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_sink_coalesces_only_text() {
+        let (tx, mut rx) = mpsc::channel(2);
+        let mut sink = EventSink::new(tx, BackpressurePolicy::CoalesceText);
+        for text in ["a", "b", "c", "d"] {
+            sink.push(Event::Text(text.to_string())).await;
+        }
+        sink.push(Event::Error("boom".to_string())).await;
+
+        let mut received = vec![rx.recv().await.unwrap(), rx.recv().await.unwrap()];
+        sink.finish().await;
+        while let Ok(event) = rx.try_recv() {
+            received.push(event);
+        }
+
+        let mut expected = texts(&["a", "b", "cd"]);
+        expected.push(Event::Error("boom".to_string()));
+        assert_eq!(received, expected);
+    }
+}
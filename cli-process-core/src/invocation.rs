@@ -0,0 +1,119 @@
+//! Resolved CLI invocations, as reported by dry runs.
+//!
+//! Each adapter's `dry_run` builds the command a run would spawn and returns
+//! it as an [`Invocation`] instead of spawning it, so containment flags and
+//! MCP wiring can be checked without spending tokens.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+/// A file the CLI reads its configuration from, with its contents.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigFile {
+    /// Where the run writes the file. Temporary files are already deleted
+    /// when a dry run returns.
+    pub path: PathBuf,
+    /// The file contents.
+    pub contents: String,
+}
+
+/// The command a run would spawn.
+///
+/// `env` holds variables set explicitly on the command (including any
+/// credentials, so treat it as sensitive); the rest of the host environment
+/// is inherited unless `inherits_env` is `false`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Invocation {
+    /// CLI binary.
+    pub program: PathBuf,
+    /// Arguments, lossily converted to UTF-8.
+    pub args: Vec<String>,
+    /// Variables set on the command.
+    pub env: Vec<(String, String)>,
+    /// Whether the CLI inherits the host environment.
+    pub inherits_env: bool,
+    /// Working directory, if not the caller's.
+    pub cwd: Option<PathBuf>,
+    /// Text piped to the CLI's stdin.
+    pub stdin: Option<String>,
+    /// Configuration files the run writes for the CLI.
+    pub config_files: Vec<ConfigFile>,
+}
+
+impl Invocation {
+    /// Captures the program, arguments, environment and working directory of
+    /// `cmd`.
+    #[must_use]
+    pub fn from_command(cmd: &Command, inherits_env: bool) -> Self {
+        let cmd = cmd.as_std();
+        Self {
+            program: PathBuf::from(cmd.get_program()),
+            args: cmd
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            env: cmd
+                .get_envs()
+                .filter_map(|(key, value)| {
+                    Some((
+                        key.to_string_lossy().into_owned(),
+                        value?.to_string_lossy().into_owned(),
+                    ))
+                })
+                .collect(),
+            inherits_env,
+            cwd: cmd.get_current_dir().map(PathBuf::from),
+            stdin: None,
+            config_files: Vec::new(),
+        }
+    }
+
+    /// The invocation as a single shell-quoted line, for logs and bug
+    /// reports. Omits the environment and stdin.
+    #[must_use]
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.program.to_string_lossy().into_owned())
+            .chain(self.args.iter().cloned())
+            .map(|word| shell_quote(&word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Quotes `word` for a POSIX shell when it holds anything but safe characters.
+fn shell_quote(word: &str) -> String {
+    let safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c));
+    if safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_command_captures_args_env_and_cwd() {
+        let mut cmd = Command::new("/usr/bin/claude");
+        cmd.args(["--print", "it's done"])
+            .env("KEY", "value")
+            .current_dir("/tmp/work");
+
+        let invocation = Invocation::from_command(&cmd, true);
+        assert_eq!(invocation.program, PathBuf::from("/usr/bin/claude"));
+        assert_eq!(invocation.args, ["--print", "it's done"]);
+        assert_eq!(invocation.env, [("KEY".to_string(), "value".to_string())]);
+        assert_eq!(invocation.cwd, Some(PathBuf::from("/tmp/work")));
+        assert_eq!(
+            invocation.command_line(),
+            r"/usr/bin/claude --print 'it'\''s done'"
+        );
+    }
+}
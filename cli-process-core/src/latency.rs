@@ -0,0 +1,116 @@
+//! Where the time of a CLI run went.
+
+use crate::events::{EventKind, TextEvent};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Milestones of one CLI run, in milliseconds since the run started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyBreakdown {
+    /// Until the child was spawned and any stdin input written.
+    pub spawn_ms: u64,
+    /// Until the first text event was parsed, if there was one.
+    pub first_token_ms: Option<u64>,
+    /// Until the first tool call event was parsed, if there was one.
+    pub first_tool_call_ms: Option<u64>,
+    /// Until the child exited.
+    pub total_ms: u64,
+}
+
+/// Records event timestamps as stdout lines are parsed.
+#[derive(Debug)]
+pub(crate) struct LatencyRecorder {
+    spawn: Duration,
+    first_token: Option<Duration>,
+    first_tool_call: Option<Duration>,
+    event_offsets: Vec<Duration>,
+}
+
+impl LatencyRecorder {
+    /// Starts recording for a child that took `spawn` to start.
+    pub(crate) const fn new(spawn: Duration) -> Self {
+        Self {
+            spawn,
+            first_token: None,
+            first_tool_call: None,
+            event_offsets: Vec::new(),
+        }
+    }
+
+    /// Records `events`, parsed from one line at `at`.
+    pub(crate) fn parsed<E: TextEvent>(&mut self, at: Duration, events: &[E]) {
+        for event in events {
+            self.event_offsets.push(at);
+            match event.kind() {
+                EventKind::Text => {
+                    self.first_token.get_or_insert(at);
+                }
+                EventKind::ToolCall => {
+                    self.first_tool_call.get_or_insert(at);
+                }
+                EventKind::Other => {}
+            }
+        }
+    }
+
+    /// The breakdown of a run that took `total`, and the offset of every
+    /// parsed event.
+    pub(crate) fn finish(self, total: Duration) -> (LatencyBreakdown, Vec<Duration>) {
+        let breakdown = LatencyBreakdown {
+            spawn_ms: millis(self.spawn),
+            first_token_ms: self.first_token.map(millis),
+            first_tool_call_ms: self.first_tool_call.map(millis),
+            total_ms: millis(total),
+        };
+        (breakdown, self.event_offsets)
+    }
+}
+
+/// `duration` in whole milliseconds, saturating on overflow.
+pub(crate) fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    struct Event(EventKind);
+
+    impl TextEvent for Event {
+        fn text_mut(&mut self) -> Option<&mut String> {
+            None
+        }
+
+        fn kind(&self) -> EventKind {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_recorder_keeps_the_first_of_each_milestone() {
+        let mut recorder = LatencyRecorder::new(Duration::from_millis(5));
+        recorder.parsed(Duration::from_millis(20), &[Event(EventKind::Other)]);
+        recorder.parsed(
+            Duration::from_millis(30),
+            &[Event(EventKind::Text), Event(EventKind::ToolCall)],
+        );
+        recorder.parsed(Duration::from_millis(40), &[Event(EventKind::Text)]);
+
+        let (breakdown, offsets) = recorder.finish(Duration::from_millis(50));
+        assert_eq!(
+            breakdown,
+            LatencyBreakdown {
+                spawn_ms: 5,
+                first_token_ms: Some(30),
+                first_tool_call_ms: Some(30),
+                total_ms: 50,
+            }
+        );
+        assert_eq!(
+            offsets,
+            [20, 30, 30, 40].map(Duration::from_millis).to_vec()
+        );
+    }
+}
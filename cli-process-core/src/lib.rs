@@ -0,0 +1,90 @@
+//! Shared subprocess runner for the `rig-cli` adapter crates.
+//!
+//! The Claude Code, Codex and `OpenCode` adapters all run their CLI the same
+//! way: spawn it with piped stdio, drain both pipes through bounded channels
+//! with per-pipe output caps, answer or reject interactive prompts, forward
+//! parsed stdout events to the caller, and stop the child with SIGTERM then
+//! SIGKILL when it overruns its timeout or, if asked to, pass on the
+//! SIGINT / SIGTERM the host receives. That loop lives here as
+//! [`ProcessRunner`]; each adapter only builds its [`tokio::process::Command`]
+//! and supplies a [`LineParser`] for its output format.
+//!
+//! This is an internal crate. The adapters re-export the configuration types
+//! ([`OutputLimits`], [`OverflowPolicy`], [`BackpressurePolicy`],
+//! [`InteractivePromptPolicy`], [`PromptResponse`], [`RedactionPolicy`],
+//! [`EnvPolicy`], [`ResourceLimits`], [`StallAction`], [`StderrPatterns`]),
+//! the [`AuthStatus`] their login checks report,
+//! the [`Invocation`] their dry runs report, and the [`ParserRegistry`] of
+//! their output parsers, and convert [`ProcessError`] into their own error enums. Their
+//! argument builders share [`check_extra_args`].
+
+#![warn(missing_docs)]
+
+/// Conflict checks for verbatim extra CLI arguments.
+pub mod args;
+/// Login state reported by the CLIs.
+pub mod auth;
+/// Synchronous entry points over the async runner, with the `blocking` feature.
+#[cfg(feature = "blocking")]
+pub mod blocking;
+/// Host environment inheritance for spawned CLIs.
+pub mod env;
+/// Error type shared by the process runner.
+pub mod error;
+/// Backpressure-aware delivery of stream events.
+pub mod events;
+/// Stable hashes for file names and cache keys.
+pub mod hash;
+/// Resolved CLI invocations reported by dry runs.
+pub mod invocation;
+/// Timing of CLI runs and their stream events.
+pub mod latency;
+/// Per-pipe output caps and overflow handling.
+pub mod limits;
+/// Buffered line scanning for subprocess output.
+pub mod lines;
+/// Size-rotated log files teeing raw subprocess output.
+pub mod log_sink;
+/// Version-keyed parsers for JSON stream output.
+pub mod parsers;
+/// Interactive prompt detection and auto-answers.
+pub mod prompts;
+/// Redaction of user-provided text in logs and error messages.
+pub mod redact;
+/// Memory, CPU, file and priority limits for spawned CLIs.
+pub mod resources;
+/// The shared spawn / drain / timeout loop.
+pub mod runner;
+/// SIGTERM-then-SIGKILL process shutdown.
+pub mod shutdown;
+/// Relaying host SIGINT / SIGTERM to the child.
+pub mod signals;
+/// Detection of CLIs that stop producing output.
+pub mod stall;
+/// Counters of supervised children, and leak checks.
+pub mod stats;
+/// Classification of stderr lines into progress and warnings.
+pub mod stderr;
+
+pub use args::check_extra_args;
+pub use auth::{AuthState, AuthStatus};
+pub use env::EnvPolicy;
+pub use error::{ProcessError, TimeoutStage};
+pub use events::{BackpressurePolicy, EventKind, EventSink, TextEvent};
+pub use invocation::{ConfigFile, Invocation};
+pub use latency::LatencyBreakdown;
+pub use limits::{OutputBuffer, OutputLimits, OverflowPolicy};
+pub use lines::{Line, LineReader};
+pub use log_sink::LogSink;
+pub use parsers::{ParserRegistry, StreamParser};
+pub use prompts::{
+    classify_line, InteractivePromptPolicy, PromptAction, PromptResponse, PromptWatcher,
+};
+pub use redact::{redact, RedactionPolicy};
+pub use resources::ResourceLimits;
+pub use runner::{LineParser, ProcessOutput, ProcessRunner};
+pub use shutdown::{graceful_shutdown, GRACE_PERIOD};
+pub use signals::{HostSignal, SignalListener};
+pub use stall::StallAction;
+pub use stats::{LeakCheck, LeakReport, ProcessCounts, ProcessStats};
+pub use stderr::{StderrKind, StderrPatterns};
@@ -0,0 +1,171 @@
+//! Output caps applied to each captured pipe.
+
+use crate::error::ProcessError;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// What to do when captured output exceeds its [`OutputLimits`].
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Fail the run with an `OutputTruncated` error.
+    #[default]
+    Error,
+    /// Discard the oldest lines, keeping the most recent output.
+    TruncateHead,
+    /// Stop capturing once a limit is reached, keeping the earliest output.
+    TruncateTail,
+}
+
+/// Bounds on how much subprocess output is held in memory per pipe.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OutputLimits {
+    /// Maximum bytes captured from a single pipe, newlines included.
+    pub max_bytes: usize,
+    /// Maximum lines captured from a single pipe. `None` means unlimited.
+    pub max_lines: Option<usize>,
+    /// Capacity of the internal line channels between the pipe readers and
+    /// the collector.
+    pub channel_capacity: usize,
+    /// Behaviour once either limit is exceeded.
+    pub overflow: OverflowPolicy,
+}
+
+impl OutputLimits {
+    /// Returns `true` if `bytes` and `lines` are both within the limits.
+    #[must_use]
+    pub const fn allows(&self, bytes: usize, lines: usize) -> bool {
+        if bytes > self.max_bytes {
+            return false;
+        }
+        match self.max_lines {
+            Some(max) => lines <= max,
+            None => true,
+        }
+    }
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024, // 10 MB
+            max_lines: None,
+            channel_capacity: 100,
+            overflow: OverflowPolicy::Error,
+        }
+    }
+}
+
+/// Line accumulator that applies the [`OverflowPolicy`] of its [`OutputLimits`].
+#[derive(Debug)]
+pub struct OutputBuffer {
+    lines: VecDeque<String>,
+    bytes: usize,
+    limits: OutputLimits,
+}
+
+impl OutputBuffer {
+    /// Creates an empty buffer bounded by `limits`.
+    #[must_use]
+    pub const fn new(limits: OutputLimits) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            bytes: 0,
+            limits,
+        }
+    }
+
+    /// Appends a line, dropping output according to the overflow policy once
+    /// a limit is exceeded.
+    ///
+    /// # Errors
+    /// Returns [`ProcessError::OutputTruncated`] under [`OverflowPolicy::Error`].
+    pub fn push(&mut self, line: String) -> Result<(), ProcessError> {
+        // Count the newline each line was read with, matching the pipe readers.
+        let bytes = self.bytes + line.len() + 1;
+        if self.limits.allows(bytes, self.lines.len() + 1) {
+            self.bytes = bytes;
+            self.lines.push_back(line);
+            return Ok(());
+        }
+
+        match self.limits.overflow {
+            OverflowPolicy::Error => Err(ProcessError::OutputTruncated {
+                captured_bytes: bytes,
+                limit_bytes: self.limits.max_bytes,
+            }),
+            OverflowPolicy::TruncateTail => Ok(()),
+            OverflowPolicy::TruncateHead => {
+                self.bytes = bytes;
+                self.lines.push_back(line);
+                while !self.limits.allows(self.bytes, self.lines.len()) {
+                    let Some(dropped) = self.lines.pop_front() else {
+                        break;
+                    };
+                    self.bytes -= dropped.len() + 1;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Joins the retained lines with newlines.
+    #[must_use]
+    pub fn join(&self) -> String {
+        self.lines
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn limits(
+        max_bytes: usize,
+        max_lines: Option<usize>,
+        overflow: OverflowPolicy,
+    ) -> OutputLimits {
+        OutputLimits {
+            max_bytes,
+            max_lines,
+            overflow,
+            ..OutputLimits::default()
+        }
+    }
+
+    #[test]
+    fn test_output_buffer_error_policy_rejects_overflow() {
+        let mut buf = OutputBuffer::new(limits(5, None, OverflowPolicy::Error));
+        buf.push("abc".to_string()).unwrap();
+        let err = buf.push("def".to_string()).unwrap_err();
+        assert!(matches!(
+            err,
+            ProcessError::OutputTruncated {
+                captured_bytes: 8,
+                limit_bytes: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn test_output_buffer_truncate_head_keeps_latest() {
+        let mut buf = OutputBuffer::new(limits(usize::MAX, Some(2), OverflowPolicy::TruncateHead));
+        for line in ["one", "two", "three"] {
+            buf.push(line.to_string()).unwrap();
+        }
+        assert_eq!(buf.join(), "two\nthree");
+    }
+
+    #[test]
+    fn test_output_buffer_truncate_tail_keeps_earliest() {
+        let mut buf = OutputBuffer::new(limits(8, None, OverflowPolicy::TruncateTail));
+        for line in ["one", "two", "three"] {
+            buf.push(line.to_string()).unwrap();
+        }
+        assert_eq!(buf.join(), "one\ntwo");
+    }
+}
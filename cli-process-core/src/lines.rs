@@ -0,0 +1,171 @@
+//! Buffered line scanning for subprocess output.
+//!
+//! [`LineReader`] replaces `BufReader::lines()` in the output drains. It
+//! reuses one line buffer for the whole stream instead of allocating a
+//! `String` per line, reports the exact number of bytes each line took on
+//! the wire (newline included), and keeps at most `max_line_bytes` of any
+//! single line in memory, so a runaway line without a newline cannot grow
+//! the buffer without bound. [`next_line`](LineReader::next_line) is cancel
+//! safe, and [`pending`](LineReader::pending) shows the start of a line
+//! whose newline has not arrived yet.
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+/// One line yielded by [`LineReader::next_line`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Line<'a> {
+    /// The line without its `\n` / `\r\n` terminator, clipped to the reader's
+    /// `max_line_bytes`. Invalid UTF-8 is replaced with U+FFFD.
+    pub text: &'a str,
+    /// Bytes the line occupied in the stream, including the terminator and
+    /// any clipped bytes.
+    pub raw_len: usize,
+}
+
+/// Reads newline-delimited lines into a reused buffer.
+pub struct LineReader<R> {
+    reader: BufReader<R>,
+    line: Vec<u8>,
+    /// Bytes of the line in `line` read so far, clipped ones included.
+    raw_len: usize,
+    /// Whether `line` holds a line already returned.
+    returned: bool,
+    max_line_bytes: usize,
+}
+
+impl<R: AsyncRead + Unpin> LineReader<R> {
+    /// Wraps `inner`, keeping at most `max_line_bytes` of each line.
+    pub fn new(inner: R, max_line_bytes: usize) -> Self {
+        Self {
+            reader: BufReader::new(inner),
+            line: Vec::new(),
+            raw_len: 0,
+            returned: false,
+            max_line_bytes,
+        }
+    }
+
+    /// The part of the next line read so far, if any; `None` once it has
+    /// been returned by [`next_line`](Self::next_line).
+    #[must_use]
+    pub fn pending(&self) -> Option<String> {
+        (!self.returned && !self.line.is_empty())
+            .then(|| String::from_utf8_lossy(&self.line).into_owned())
+    }
+
+    /// Reads the next line, or returns `None` at end of stream.
+    ///
+    /// The returned text borrows the reader's buffer and is overwritten by
+    /// the next call. Dropping the future before it completes keeps what was
+    /// read of the line for the next call.
+    pub async fn next_line(&mut self) -> std::io::Result<Option<Line<'_>>> {
+        if self.returned {
+            self.line.clear();
+            self.raw_len = 0;
+            self.returned = false;
+        }
+        let mut terminated = false;
+
+        while !terminated {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                if self.raw_len == 0 {
+                    return Ok(None);
+                }
+                break;
+            }
+
+            let newline = available.iter().position(|&b| b == b'\n');
+            terminated = newline.is_some();
+            let chunk_len = newline.map_or(available.len(), |i| i + 1);
+            let room = self.max_line_bytes.saturating_sub(self.line.len());
+            self.line
+                .extend_from_slice(&available[..chunk_len.min(room)]);
+            self.reader.consume(chunk_len);
+            self.raw_len += chunk_len;
+        }
+
+        if self.line.last() == Some(&b'\n') {
+            self.line.pop();
+            if self.line.last() == Some(&b'\r') {
+                self.line.pop();
+            }
+        }
+        if std::str::from_utf8(&self.line).is_err() {
+            self.line = String::from_utf8_lossy(&self.line)
+                .into_owned()
+                .into_bytes();
+        }
+
+        self.returned = true;
+        Ok(Some(Line {
+            text: std::str::from_utf8(&self.line).unwrap_or_default(),
+            raw_len: self.raw_len,
+        }))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn read_all(input: &[u8], max_line_bytes: usize) -> Vec<(String, usize)> {
+        let mut reader = LineReader::new(input, max_line_bytes);
+        let mut lines = Vec::new();
+        while let Some(line) = reader.next_line().await.unwrap() {
+            lines.push((line.text.to_string(), line.raw_len));
+        }
+        lines
+    }
+
+    #[tokio::test]
+    async fn test_line_reader_counts_terminators() {
+        let lines = read_all(b"one\r\ntwo\n\nlast", 1024).await;
+        assert_eq!(
+            lines,
+            [
+                ("one".to_string(), 5),
+                ("two".to_string(), 4),
+                (String::new(), 1),
+                ("last".to_string(), 4),
+            ]
+        );
+        assert_eq!(read_all(b"", 1024).await, []);
+    }
+
+    #[tokio::test]
+    async fn test_line_reader_clips_long_lines() {
+        let mut input = vec![b'x'; 20_000];
+        input.extend_from_slice(b"\nnext\n");
+        let lines = read_all(&input, 16).await;
+        assert_eq!(lines[0], ("x".repeat(16), 20_001));
+        assert_eq!(lines[1], ("next".to_string(), 5));
+    }
+
+    #[tokio::test]
+    async fn test_line_reader_resumes_after_cancelled_reads() {
+        let (mut writer, pipe) = tokio::io::duplex(64);
+        let mut reader = LineReader::new(pipe, 1024);
+        tokio::io::AsyncWriteExt::write_all(&mut writer, b"Continue? ")
+            .await
+            .unwrap();
+        let read = tokio::time::timeout(Duration::from_millis(50), reader.next_line()).await;
+        assert!(read.is_err());
+        assert_eq!(reader.pending().as_deref(), Some("Continue? "));
+
+        tokio::io::AsyncWriteExt::write_all(&mut writer, b"[y/n]\n")
+            .await
+            .unwrap();
+        let line = reader.next_line().await.unwrap().unwrap();
+        assert_eq!((line.text, line.raw_len), ("Continue? [y/n]", 16));
+        assert_eq!(reader.pending(), None);
+    }
+
+    #[tokio::test]
+    async fn test_line_reader_replaces_invalid_utf8() {
+        let lines = read_all(b"ok \xff\n", 1024).await;
+        assert_eq!(lines, [("ok \u{fffd}".to_string(), 5)]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Live tee of raw subprocess output to a log file.
+//!
+//! The in-memory capture is bounded and only reported once the CLI exits.
+//! A [`LogSink`] writes every stdout and stderr line to a file as it is
+//! read, before any limit applies, so a long run can be followed with
+//! `tail -f` and inspected after a timeout. Lines are prefixed with the pipe
+//! they came from. When the file would grow past its size limit it is
+//! rotated to `<path>.1` (shifting older files up to `<path>.<keep>`) and a
+//! fresh file is started.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Size at which the log file is rotated: 10MB.
+pub const DEFAULT_ROTATE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Number of rotated files kept next to the live one.
+pub const DEFAULT_KEEP_FILES: usize = 3;
+
+/// Appends output lines to a size-rotated log file.
+#[derive(Debug)]
+pub struct LogSink {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    rotate_bytes: u64,
+    keep_files: usize,
+}
+
+impl LogSink {
+    /// Opens `path` for appending with the default rotation.
+    ///
+    /// # Errors
+    /// Returns an I/O error if the file cannot be opened.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        Self::with_rotation(path, DEFAULT_ROTATE_BYTES, DEFAULT_KEEP_FILES)
+    }
+
+    /// Opens `path` for appending, rotating once it would exceed
+    /// `rotate_bytes` and keeping `keep_files` rotated files.
+    ///
+    /// # Errors
+    /// Returns an I/O error if the file cannot be opened.
+    pub fn with_rotation(
+        path: &Path,
+        rotate_bytes: u64,
+        keep_files: usize,
+    ) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            written,
+            rotate_bytes,
+            keep_files,
+        })
+    }
+
+    /// Writes `line` from the pipe `name`, rotating first if needed.
+    ///
+    /// # Errors
+    /// Returns an I/O error if the write or the rotation fails.
+    pub fn write_line(&mut self, name: &str, line: &str) -> std::io::Result<()> {
+        let entry = format!("[{name}] {line}\n");
+        let len = entry.len() as u64;
+        if self.written > 0 && self.written + len > self.rotate_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(entry.as_bytes())?;
+        self.written += len;
+        Ok(())
+    }
+
+    /// Shifts `<path>.N` to `<path>.N+1`, dropping the oldest, moves the
+    /// live file to `<path>.1` and reopens an empty one.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.keep_files == 0 {
+            self.file.set_len(0)?;
+        } else {
+            for n in (1..self.keep_files).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_sink_rotates_by_size() {
+        let dir = std::env::temp_dir().join(format!("rig_log_sink_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("run.log");
+
+        let mut sink = LogSink::with_rotation(&path, 20, 2).unwrap();
+        for line in ["one", "two", "three", "four"] {
+            sink.write_line("stdout", line).unwrap();
+        }
+
+        let read = |p: &Path| std::fs::read_to_string(p).unwrap();
+        assert_eq!(read(&path), "[stdout] four\n");
+        assert_eq!(read(&dir.join("run.log.1")), "[stdout] three\n");
+        assert_eq!(read(&dir.join("run.log.2")), "[stdout] two\n");
+        assert!(!dir.join("run.log.3").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
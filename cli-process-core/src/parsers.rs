@@ -0,0 +1,148 @@
+//! Version-keyed registry of parsers for JSON stream output.
+//!
+//! CLI releases change the shape of the JSON they stream. Each adapter
+//! registers its built-in parsers in a [`ParserRegistry`] under the CLI
+//! versions they understand; callers can register more, so a new release can
+//! be supported without patching the adapter.
+//!
+//! ```
+//! use rig_cli_process_core::ParserRegistry;
+//!
+//! let registry = ParserRegistry::<String>::new()
+//!     .register(semver::VersionReq::STAR, |val: &serde_json::Value| {
+//!         val.get("text")?.as_str().map(|t| vec![t.to_string()])
+//!     })
+//!     .register(semver::VersionReq::parse(">=3").unwrap(), |val: &serde_json::Value| {
+//!         val.pointer("/delta/text")?.as_str().map(|t| vec![t.to_string()])
+//!     });
+//!
+//! let v3 = semver::Version::new(3, 0, 0);
+//! let event = serde_json::json!({ "delta": { "text": "hi" } });
+//! assert_eq!(registry.parse(Some(&v3), &event), Some(vec!["hi".to_string()]));
+//! assert_eq!(registry.parse(Some(&semver::Version::new(2, 0, 0)), &event), None);
+//! ```
+
+use semver::{Version, VersionReq};
+use std::sync::Arc;
+
+/// Turns one JSON value from a CLI's output into events.
+///
+/// Returns `None` for values of a shape the parser does not recognise, so
+/// the registry can offer them to the next parser.
+pub trait StreamParser<E>: Send + Sync {
+    /// Parses `value`, or returns `None` if it is not a recognised shape.
+    fn parse(&self, value: &serde_json::Value) -> Option<Vec<E>>;
+}
+
+impl<E, F> StreamParser<E> for F
+where
+    F: Fn(&serde_json::Value) -> Option<Vec<E>> + Send + Sync,
+{
+    fn parse(&self, value: &serde_json::Value) -> Option<Vec<E>> {
+        self(value)
+    }
+}
+
+/// Parsers for one adapter's output, each registered for the CLI versions it
+/// understands.
+///
+/// Parsers registered later take precedence, so callers can override the
+/// adapter's built-in parsers.
+pub struct ParserRegistry<E> {
+    entries: Vec<(VersionReq, Arc<dyn StreamParser<E>>)>,
+}
+
+impl<E> ParserRegistry<E> {
+    /// Creates an empty registry.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Registers `parser` for the CLI versions matching `versions`.
+    #[must_use]
+    pub fn register(
+        mut self,
+        versions: VersionReq,
+        parser: impl StreamParser<E> + 'static,
+    ) -> Self {
+        self.entries.push((versions, Arc::new(parser)));
+        self
+    }
+
+    /// Parses `value` with the newest-registered parser that applies to
+    /// `version` and recognises it.
+    ///
+    /// With no detected `version`, every parser applies. Returns `None` when
+    /// no parser recognises the value.
+    #[must_use]
+    pub fn parse(&self, version: Option<&Version>, value: &serde_json::Value) -> Option<Vec<E>> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|(versions, _)| version.is_none_or(|v| versions.matches(v)))
+            .find_map(|(_, parser)| parser.parse(value))
+    }
+}
+
+impl<E> Default for ParserRegistry<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Clone for ParserRegistry<E> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<E> std::fmt::Debug for ParserRegistry<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.entries.iter().map(|(versions, _)| versions))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn tagged(tag: &'static str) -> impl Fn(&serde_json::Value) -> Option<Vec<&'static str>> {
+        move |val| (val.get("type")?.as_str()? == tag).then(|| vec![tag])
+    }
+
+    #[test]
+    fn test_later_registrations_take_precedence() {
+        let registry = ParserRegistry::new()
+            .register(VersionReq::STAR, |_: &serde_json::Value| {
+                Some(vec!["builtin"])
+            })
+            .register(VersionReq::STAR, tagged("custom"));
+
+        let custom = serde_json::json!({ "type": "custom" });
+        let other = serde_json::json!({ "type": "other" });
+        assert_eq!(registry.parse(None, &custom), Some(vec!["custom"]));
+        assert_eq!(registry.parse(None, &other), Some(vec!["builtin"]));
+    }
+
+    #[test]
+    fn test_parsers_apply_only_to_matching_versions() {
+        let registry =
+            ParserRegistry::new().register(VersionReq::parse("<2").unwrap(), tagged("text"));
+        let text = serde_json::json!({ "type": "text" });
+
+        assert_eq!(
+            registry.parse(Some(&Version::new(1, 4, 0)), &text),
+            Some(vec!["text"])
+        );
+        assert_eq!(registry.parse(Some(&Version::new(2, 0, 0)), &text), None);
+        assert_eq!(registry.parse(None, &text), Some(vec!["text"]));
+    }
+}
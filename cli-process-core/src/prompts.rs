@@ -0,0 +1,227 @@
+//! Detection and answering of interactive prompts printed by a CLI.
+
+use crate::error::ProcessError;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
+
+/// A canned answer for an interactive prompt printed by the CLI.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PromptResponse {
+    /// Case-insensitive fragment of the prompt line to match.
+    pub pattern: String,
+    /// Text written to the CLI's stdin, followed by a newline, when the prompt appears.
+    pub answer: String,
+}
+
+/// How trust and onboarding prompts printed by a fresh CLI install are handled.
+///
+/// Such prompts wait for keyboard input and would otherwise hang the run
+/// until its timeout. Lines matching an entry in
+/// [`auto_answers`](Self::auto_answers) are answered through stdin when it
+/// is [kept open](Self::keep_stdin_open); any other recognized prompt fails
+/// the run immediately with an `InteractivePromptDetected` error.
+///
+/// Prompts are looked for on stderr, and on stdout before the CLI's output
+/// stream starts. A question left without a newline is checked once the CLI
+/// stops writing after it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InteractivePromptPolicy {
+    /// Prompts to answer automatically, checked in order.
+    pub auto_answers: Vec<PromptResponse>,
+    /// Keeps the CLI's stdin open for the whole run, so prompts can be
+    /// answered and stalls nudged. Default: stdin is closed once any input
+    /// is written.
+    ///
+    /// Only for CLIs that do not wait for stdin to close before they start:
+    /// `claude -p` does, and hangs while stdin is open.
+    #[serde(default)]
+    pub keep_stdin_open: bool,
+}
+
+/// What to do about an output line that may be an interactive prompt.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PromptAction<'a> {
+    /// Ordinary output.
+    Ignore,
+    /// A configured prompt; write this answer to stdin.
+    Answer(&'a str),
+    /// A known prompt with no configured answer.
+    Reject,
+}
+
+/// Classifies a single output line against the policy and a CLI's built-in
+/// prompt `markers` (lowercase fragments of the questions it may ask).
+///
+/// JSON lines are never treated as prompts: they are stream events, and the
+/// model's own text inside them may quote anything.
+#[must_use]
+pub fn classify_line<'a>(
+    line: &str,
+    policy: &'a InteractivePromptPolicy,
+    markers: &[&str],
+) -> PromptAction<'a> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('{') {
+        return PromptAction::Ignore;
+    }
+
+    let lower = trimmed.to_lowercase();
+    if let Some(response) = policy
+        .auto_answers
+        .iter()
+        .find(|r| !r.pattern.is_empty() && lower.contains(&r.pattern.to_lowercase()))
+    {
+        return PromptAction::Answer(&response.answer);
+    }
+
+    if markers.iter().any(|marker| lower.contains(marker)) {
+        PromptAction::Reject
+    } else {
+        PromptAction::Ignore
+    }
+}
+
+/// Answers or rejects interactive prompts as output lines arrive.
+#[derive(Debug)]
+pub struct PromptWatcher<'a> {
+    policy: &'a InteractivePromptPolicy,
+    markers: &'a [&'a str],
+    /// Open stdin of the child, present only when the policy keeps it open
+    /// and no input was piped through stdin.
+    stdin: Option<ChildStdin>,
+}
+
+impl<'a> PromptWatcher<'a> {
+    /// Watches for `markers` and the prompts in `policy`, writing answers to `stdin`.
+    #[must_use]
+    pub const fn new(
+        policy: &'a InteractivePromptPolicy,
+        markers: &'a [&'a str],
+        stdin: Option<ChildStdin>,
+    ) -> Self {
+        Self {
+            policy,
+            markers,
+            stdin,
+        }
+    }
+
+    /// Answers `line` if it is a configured prompt.
+    ///
+    /// # Errors
+    /// Returns [`ProcessError::InteractivePromptDetected`] for a prompt that
+    /// cannot be answered, and [`ProcessError::Io`] if writing the answer fails.
+    pub async fn inspect(&mut self, line: &str) -> Result<(), ProcessError> {
+        let answer = match classify_line(line, self.policy, self.markers) {
+            PromptAction::Ignore => return Ok(()),
+            PromptAction::Answer(answer) => answer,
+            PromptAction::Reject => return Err(prompt_detected(line)),
+        };
+        if self.stdin.is_none() {
+            return Err(prompt_detected(line));
+        }
+
+        tracing::debug!(
+            prompt = line.trim(),
+            "Auto-answering interactive CLI prompt"
+        );
+        self.write_line(answer).await.map(drop)
+    }
+
+    /// Writes `text` and a newline to the child's stdin, if it is open.
+    /// Returns whether it was.
+    ///
+    /// # Errors
+    /// Returns [`ProcessError::Io`] if the write fails.
+    pub async fn write_line(&mut self, text: &str) -> Result<bool, ProcessError> {
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Ok(false);
+        };
+        stdin
+            .write_all(format!("{text}\n").as_bytes())
+            .await
+            .map_err(|e| ProcessError::io("stdin line write", e))?;
+        stdin
+            .flush()
+            .await
+            .map_err(|e| ProcessError::io("stdin line flush", e))?;
+        Ok(true)
+    }
+}
+
+fn prompt_detected(line: &str) -> ProcessError {
+    ProcessError::InteractivePromptDetected {
+        question: line.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    const MARKERS: &[&str] = &["do you trust the files in this folder"];
+
+    #[test]
+    fn test_classify_line_detects_known_prompts() {
+        let policy = InteractivePromptPolicy::default();
+        assert_eq!(
+            classify_line("  Do you trust the files in this folder?", &policy, MARKERS),
+            PromptAction::Reject
+        );
+        assert_eq!(
+            classify_line("Here is the answer you asked for.", &policy, MARKERS),
+            PromptAction::Ignore
+        );
+        assert_eq!(
+            classify_line(
+                r#"{"text":"do you trust the files in this folder"}"#,
+                &policy,
+                MARKERS
+            ),
+            PromptAction::Ignore
+        );
+        assert_eq!(
+            classify_line("Do you trust the files in this folder?", &policy, &[]),
+            PromptAction::Ignore
+        );
+    }
+
+    #[test]
+    fn test_classify_line_prefers_configured_answers() {
+        let policy = InteractivePromptPolicy {
+            auto_answers: vec![PromptResponse {
+                pattern: "Trust The Files".to_string(),
+                answer: "1".to_string(),
+            }],
+            keep_stdin_open: true,
+        };
+        assert_eq!(
+            classify_line("Do you trust the files in this folder?", &policy, MARKERS),
+            PromptAction::Answer("1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watcher_without_stdin_fails_fast() {
+        let policy = InteractivePromptPolicy {
+            auto_answers: vec![PromptResponse {
+                pattern: "press enter".to_string(),
+                answer: String::new(),
+            }],
+            keep_stdin_open: true,
+        };
+        let mut watcher = PromptWatcher::new(&policy, MARKERS, None);
+        watcher.inspect("regular output").await.unwrap();
+        let err = watcher
+            .inspect("Press Enter to continue")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ProcessError::InteractivePromptDetected { ref question }
+                if question == "Press Enter to continue"
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+//! Redaction of user-provided text before it reaches logs or error messages.
+//!
+//! Prompts, payloads and the CLI output derived from them can hold anything
+//! the caller passed in. Every place that embeds such text in a tracing event
+//! or an error string runs it through [`redact`], which applies the
+//! process-wide [`RedactionPolicy`]. Programmatic fields (e.g. the raw stdout
+//! kept on a timeout error) are left untouched; only their rendering is
+//! redacted.
+//!
+//! The default is [`RedactionPolicy::Truncate`] to
+//! [`DEFAULT_TRUNCATE_CHARS`] characters. Building with the `debug-output`
+//! feature switches the default to [`RedactionPolicy::Keep`], and
+//! [`RedactionPolicy::install`] overrides either at runtime.
+
+use crate::hash::fnv1a;
+use std::borrow::Cow;
+use std::sync::RwLock;
+
+/// Characters kept by the default [`RedactionPolicy::Truncate`].
+pub const DEFAULT_TRUNCATE_CHARS: usize = 200;
+
+/// How user-provided text is rendered in logs and error messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionPolicy {
+    /// Replace the text with its length and a stable 64-bit FNV-1a hash, so
+    /// repeated values can be correlated without being revealed.
+    Hash,
+    /// Keep the first `n` characters and note how many were cut.
+    Truncate(usize),
+    /// Replace the text with its length only.
+    Drop,
+    /// Leave the text as is.
+    Keep,
+}
+
+impl RedactionPolicy {
+    /// The policy in effect until [`install`](Self::install) is called.
+    #[cfg(not(feature = "debug-output"))]
+    pub const DEFAULT: Self = Self::Truncate(DEFAULT_TRUNCATE_CHARS);
+    /// The policy in effect until [`install`](Self::install) is called.
+    #[cfg(feature = "debug-output")]
+    pub const DEFAULT: Self = Self::Keep;
+
+    /// Makes this the process-wide policy used by [`redact`].
+    pub fn install(self) {
+        match GLOBAL.write() {
+            Ok(mut policy) => *policy = self,
+            Err(poisoned) => *poisoned.into_inner() = self,
+        }
+    }
+
+    /// The process-wide policy.
+    #[must_use]
+    pub fn current() -> Self {
+        GLOBAL
+            .read()
+            .map_or_else(|poisoned| *poisoned.into_inner(), |policy| *policy)
+    }
+
+    /// Renders `text` under this policy.
+    #[must_use]
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Self::Keep => Cow::Borrowed(text),
+            Self::Truncate(keep) => match text.char_indices().nth(keep) {
+                None => Cow::Borrowed(text),
+                Some((cut, _)) => {
+                    let removed = text[cut..].chars().count();
+                    Cow::Owned(format!("{}… [{removed} chars redacted]", &text[..cut]))
+                }
+            },
+            Self::Hash => Cow::Owned(format!(
+                "[redacted {} chars, fnv {:016x}]",
+                text.chars().count(),
+                fnv1a(text.as_bytes())
+            )),
+            Self::Drop => Cow::Owned(format!("[redacted {} chars]", text.chars().count())),
+        }
+    }
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static GLOBAL: RwLock<RedactionPolicy> = RwLock::new(RedactionPolicy::DEFAULT);
+
+/// Renders `text` under the process-wide [`RedactionPolicy`].
+#[must_use]
+pub fn redact(text: &str) -> Cow<'_, str> {
+    RedactionPolicy::current().apply(text)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_keeps_prefix_on_char_boundary() {
+        let policy = RedactionPolicy::Truncate(3);
+        assert_eq!(policy.apply("héllo wörld"), "hél… [8 chars redacted]");
+        assert!(matches!(policy.apply("abc"), Cow::Borrowed("abc")));
+    }
+
+    #[test]
+    fn test_hash_and_drop_hide_content() {
+        let hashed = RedactionPolicy::Hash.apply("secret prompt");
+        assert!(!hashed.contains("secret"));
+        assert_eq!(hashed, RedactionPolicy::Hash.apply("secret prompt"));
+        assert_ne!(hashed, RedactionPolicy::Hash.apply("other prompt"));
+
+        assert_eq!(
+            RedactionPolicy::Drop.apply("secret prompt"),
+            "[redacted 13 chars]"
+        );
+        assert_eq!(
+            RedactionPolicy::Keep.apply("secret prompt"),
+            "secret prompt"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Operating-system resource limits for spawned CLIs.
+//!
+//! The wall-clock timeout only stops a CLI once it expires; until then a
+//! runaway agent can allocate memory, spin the CPU or exhaust file
+//! descriptors on the host. [`ResourceLimits`] caps the child itself. On Unix
+//! the limits are rlimits set in the child between fork and exec, so they
+//! also bind every process it starts. On Windows the child is placed in a
+//! Job Object right after it is spawned.
+
+use serde::{Deserialize, Serialize};
+use tokio::process::{Child, Command};
+
+/// Limits applied to a spawned CLI. `None` leaves a resource unlimited.
+// The unsafe code only passes the values to system calls; no field value
+// can make it unsound.
+#[allow(clippy::unsafe_derive_deserialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Maximum address space in bytes (`RLIMIT_AS`). On Windows, the
+    /// maximum committed memory.
+    pub memory_bytes: Option<u64>,
+    /// Maximum CPU time in seconds (`RLIMIT_CPU`). On Windows, the maximum
+    /// user-mode time.
+    pub cpu_seconds: Option<u64>,
+    /// Maximum open file descriptors (`RLIMIT_NOFILE`). Ignored on Windows.
+    pub open_files: Option<u64>,
+    /// Scheduling niceness, from -20 (highest priority) to 19 (lowest).
+    /// Values below the current niceness usually need privileges. On
+    /// Windows, mapped to the nearest priority class.
+    pub niceness: Option<i32>,
+}
+
+impl ResourceLimits {
+    /// Returns `true` if no limit is set.
+    #[must_use]
+    pub const fn is_unlimited(&self) -> bool {
+        self.memory_bytes.is_none()
+            && self.cpu_seconds.is_none()
+            && self.open_files.is_none()
+            && self.niceness.is_none()
+    }
+
+    /// Arranges for the limits to be applied when `cmd` is spawned. A limit
+    /// that cannot be set fails the spawn.
+    #[cfg(unix)]
+    #[allow(unsafe_code)]
+    pub(crate) fn before_spawn(self, cmd: &mut Command) {
+        if self.is_unlimited() {
+            return;
+        }
+        // SAFETY: the closure runs in the forked child before exec. It only
+        // makes setrlimit and setpriority system calls, which are
+        // async-signal-safe, and neither allocates nor takes locks.
+        unsafe {
+            cmd.pre_exec(move || self.apply_to_current_process());
+        }
+    }
+
+    /// Windows limits are applied by [`after_spawn`](Self::after_spawn).
+    #[cfg(not(unix))]
+    pub(crate) const fn before_spawn(self, _cmd: &mut Command) {}
+
+    /// Places `child` in a Job Object carrying the limits.
+    ///
+    /// The child runs unconfined for the moment between spawn and
+    /// assignment.
+    #[cfg(windows)]
+    #[allow(unsafe_code)]
+    pub(crate) fn after_spawn(self, child: &Child) -> std::io::Result<()> {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+            SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+            JOB_OBJECT_LIMIT_PRIORITY_CLASS, JOB_OBJECT_LIMIT_PROCESS_MEMORY,
+            JOB_OBJECT_LIMIT_PROCESS_TIME,
+        };
+
+        if self.memory_bytes.is_none() && self.cpu_seconds.is_none() && self.niceness.is_none() {
+            return Ok(());
+        }
+        // The child has already exited.
+        let Some(process) = child.raw_handle() else {
+            return Ok(());
+        };
+
+        // SAFETY: the limit structure is plain data for which all-zero is
+        // valid, the job handle is checked before use and closed exactly
+        // once, and `process` is a live handle owned by `child`. Closing the
+        // job handle does not end the job while the child is assigned to it.
+        unsafe {
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            if let Some(bytes) = self.memory_bytes {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+                info.ProcessMemoryLimit = usize::try_from(bytes).unwrap_or(usize::MAX);
+            }
+            if let Some(seconds) = self.cpu_seconds {
+                // In 100-nanosecond ticks.
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_TIME;
+                info.BasicLimitInformation.PerProcessUserTimeLimit =
+                    i64::try_from(seconds.saturating_mul(10_000_000)).unwrap_or(i64::MAX);
+            }
+            if let Some(niceness) = self.niceness {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PRIORITY_CLASS;
+                info.BasicLimitInformation.PriorityClass = priority_class(niceness);
+            }
+
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return Err(std::io::Error::last_os_error());
+            }
+            let applied = SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                std::ptr::addr_of!(info).cast(),
+                u32::try_from(std::mem::size_of_val(&info)).unwrap_or(u32::MAX),
+            ) != 0
+                && AssignProcessToJobObject(job, process) != 0;
+            let result = if applied {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            };
+            CloseHandle(job);
+            result
+        }
+    }
+
+    /// Unix limits are applied by [`before_spawn`](Self::before_spawn).
+    #[cfg(not(windows))]
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    pub(crate) const fn after_spawn(self, _child: &Child) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Sets the limits on the calling process.
+    #[cfg(unix)]
+    #[allow(unsafe_code)]
+    fn apply_to_current_process(self) -> std::io::Result<()> {
+        use nix::sys::resource::{setrlimit, Resource};
+
+        let rlimits = [
+            (Resource::RLIMIT_AS, self.memory_bytes),
+            (Resource::RLIMIT_CPU, self.cpu_seconds),
+            (Resource::RLIMIT_NOFILE, self.open_files),
+        ];
+        for (resource, limit) in rlimits {
+            if let Some(limit) = limit {
+                setrlimit(resource, limit, limit)?;
+            }
+        }
+        if let Some(niceness) = self.niceness {
+            // SAFETY: setpriority only reads its arguments.
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, niceness) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The Windows priority class closest to a Unix niceness.
+#[cfg(windows)]
+const fn priority_class(niceness: i32) -> u32 {
+    use windows_sys::Win32::System::Threading::{
+        ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS,
+        IDLE_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+    };
+    match niceness {
+        i32::MIN..=-15 => HIGH_PRIORITY_CLASS,
+        -14..=-5 => ABOVE_NORMAL_PRIORITY_CLASS,
+        -4..=4 => NORMAL_PRIORITY_CLASS,
+        5..=14 => BELOW_NORMAL_PRIORITY_CLASS,
+        _ => IDLE_PRIORITY_CLASS,
+    }
+}
@@ -0,0 +1,1155 @@
+//! The spawn / drain / timeout / shutdown loop shared by every adapter.
+
+use crate::error::{ProcessError, TimeoutStage};
+use crate::events::{EventSink, TextEvent};
+use crate::latency::{millis, LatencyBreakdown, LatencyRecorder};
+use crate::limits::{OutputBuffer, OutputLimits, OverflowPolicy};
+use crate::lines::LineReader;
+use crate::log_sink::LogSink;
+use crate::prompts::{InteractivePromptPolicy, PromptWatcher};
+use crate::resources::ResourceLimits;
+use crate::shutdown::{graceful_shutdown, GRACE_PERIOD};
+use crate::signals::{forward_signal, HostSignal, SignalListener};
+use crate::stall::StallAction;
+use crate::stats::ProcessStats;
+use crate::stderr::{StderrKind, StderrPatterns};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+/// Log file shared by both pipe readers. Set to `None` after a write error.
+type SharedLog = Arc<Mutex<Option<LogSink>>>;
+
+/// Policy used when the adapter does not configure interactive prompts.
+static NO_PROMPTS: InteractivePromptPolicy = InteractivePromptPolicy {
+    auto_answers: Vec::new(),
+    keep_stdin_open: false,
+};
+
+/// How long a line may go without its newline before it is checked for an
+/// interactive prompt.
+const PROMPT_WAIT: Duration = Duration::from_millis(300);
+
+/// Action used when the adapter does not configure stall handling.
+static WARN_ON_STALL: StallAction = StallAction::Warn;
+
+/// Turns stdout lines into the adapter's stream events.
+pub trait LineParser {
+    /// Event type forwarded to the caller's channel.
+    type Event: TextEvent;
+
+    /// Parses one stdout line (without its terminator) into zero or more events.
+    fn parse(&mut self, line: &str) -> Vec<Self::Event>;
+
+    /// Events reporting that the CLI has been silent for `idle`. Default: none.
+    fn stalled(&mut self, idle: Duration) -> Vec<Self::Event> {
+        let _ = idle;
+        Vec::new()
+    }
+
+    /// Events for a stderr line the runner's
+    /// [`stderr_events`](ProcessRunner::stderr_events) patterns classified
+    /// as `kind`, with its cleaned-up `message`. Default: none.
+    fn stderr(&mut self, kind: StderrKind, message: &str) -> Vec<Self::Event> {
+        let _ = (kind, message);
+        Vec::new()
+    }
+}
+
+/// Captured output of a subprocess that ran to completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessOutput {
+    /// Retained stdout lines, joined with newlines.
+    pub stdout: String,
+    /// Retained stderr lines, joined with newlines.
+    pub stderr: String,
+    /// Exit code, or `-1` if the process was terminated by a signal.
+    pub exit_code: i32,
+    /// OS process identifier of the child.
+    pub pid: u32,
+    /// Wall-clock time from spawn to exit.
+    pub duration: Duration,
+    /// Time to spawn, to the first text and tool call events, and in total.
+    pub latency: LatencyBreakdown,
+    /// Time since the start of the run at which each stream event was
+    /// parsed, in the order the parser returned them.
+    pub event_offsets: Vec<Duration>,
+}
+
+impl ProcessOutput {
+    /// Returns [`duration`](Self::duration) in milliseconds, saturating on overflow.
+    #[must_use]
+    pub fn duration_ms(&self) -> u64 {
+        millis(self.duration)
+    }
+
+    /// Returns [`event_offsets`](Self::event_offsets) in milliseconds.
+    #[must_use]
+    pub fn event_offsets_ms(&self) -> Vec<u64> {
+        self.event_offsets.iter().copied().map(millis).collect()
+    }
+}
+
+/// Runs a CLI subprocess with bounded output capture and a hard timeout.
+///
+/// The adapter supplies a configured [`Command`] (binary, arguments, working
+/// directory, environment) and a [`LineParser`] for its stdout format; the
+/// runner owns everything else:
+///
+/// - stdout and stderr are read by two tasks in a [`JoinSet`] and handed to
+///   the collector over bounded channels of
+///   [`channel_capacity`](OutputLimits::channel_capacity) lines;
+/// - every line is parsed into events for the caller's [`EventSink`] and
+///   retained under the [`OutputLimits`]; stderr, stdout before the first
+///   event, and a line left without its newline are checked for
+///   interactive prompts;
+/// - on timeout, or when the run fails while the child is still alive, the
+///   child gets SIGTERM and then SIGKILL after the grace period, and the
+///   reader tasks are aborted;
+/// - with a [`log_sink`](Self::log_sink), every line is also appended to a
+///   file as soon as it is read, before the limits apply;
+/// - [`resource_limits`](Self::resource_limits) cap the child's memory, CPU
+///   time, open files and priority;
+/// - with a [`stall`](Self::stall) timeout, a child that prints nothing for
+///   that long is reported and handled before the timeout expires;
+/// - a [`spawn_timeout`](Self::spawn_timeout) and a
+///   [`first_output_timeout`](Self::first_output_timeout) stop a child that
+///   never gets going long before the total timeout would;
+/// - with [`forward_signals`](Self::forward_signals), SIGINT and SIGTERM
+///   sent to the host are passed on to the child, which cancels the run;
+/// - with [`stderr_events`](Self::stderr_events), stderr lines reporting
+///   progress or warnings are also parsed into events.
+///
+/// ```ignore
+/// let output = ProcessRunner::new(command)
+///     .limits(config.output_limits)
+///     .timeout(config.timeout)
+///     .prompts(&config.interactive_prompts, MARKERS)
+///     .run(&mut parser, events)
+///     .await?;
+/// ```
+#[derive(Debug)]
+pub struct ProcessRunner<'a> {
+    command: Command,
+    limits: OutputLimits,
+    timeout: Duration,
+    grace_period: Duration,
+    stdin: Option<&'a str>,
+    prompts: &'a InteractivePromptPolicy,
+    markers: &'a [&'a str],
+    log_sink: Option<&'a Path>,
+    resources: ResourceLimits,
+    stall_timeout: Option<Duration>,
+    on_stall: &'a StallAction,
+    forward_signals: bool,
+    spawn_timeout: Option<Duration>,
+    first_output_timeout: Option<Duration>,
+    stderr_patterns: Option<&'a StderrPatterns>,
+}
+
+impl<'a> ProcessRunner<'a> {
+    /// Wraps `command`. Its stdio is configured by the runner.
+    #[must_use]
+    pub fn new(command: Command) -> Self {
+        Self {
+            command,
+            limits: OutputLimits::default(),
+            timeout: Duration::from_secs(300),
+            grace_period: GRACE_PERIOD,
+            stdin: None,
+            prompts: &NO_PROMPTS,
+            markers: &[],
+            log_sink: None,
+            resources: ResourceLimits::default(),
+            stall_timeout: None,
+            on_stall: &WARN_ON_STALL,
+            forward_signals: false,
+            spawn_timeout: None,
+            first_output_timeout: None,
+            stderr_patterns: None,
+        }
+    }
+
+    /// Sets the per-pipe output limits. Default: [`OutputLimits::default`].
+    #[must_use]
+    pub const fn limits(mut self, limits: OutputLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets the total time the process may run before it is stopped.
+    /// Default: 300s.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the time spawning the process and writing its
+    /// [`stdin`](Self::stdin) may take. Default: no limit.
+    #[must_use]
+    pub const fn spawn_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.spawn_timeout = timeout;
+        self
+    }
+
+    /// Sets the time from the start of the run until the process prints its
+    /// first stdout or stderr line. Default: no limit.
+    #[must_use]
+    pub const fn first_output_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.first_output_timeout = timeout;
+        self
+    }
+
+    /// Sets how long the child may take to exit after SIGTERM before it is
+    /// killed. Default: [`GRACE_PERIOD`].
+    #[must_use]
+    pub const fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Writes `input` to the child's stdin and then closes it.
+    ///
+    /// Prompts cannot be auto-answered once stdin is closed, so matching
+    /// prompts fail the run instead, whatever the policy's
+    /// [`keep_stdin_open`](InteractivePromptPolicy::keep_stdin_open).
+    #[must_use]
+    pub const fn stdin(mut self, input: &'a str) -> Self {
+        self.stdin = Some(input);
+        self
+    }
+
+    /// Watches output for the CLI's built-in prompt `markers` and the
+    /// auto-answers in `policy`, keeping stdin open if the policy asks to.
+    /// Default: no prompt detection, stdin closed.
+    #[must_use]
+    pub const fn prompts(
+        mut self,
+        policy: &'a InteractivePromptPolicy,
+        markers: &'a [&'a str],
+    ) -> Self {
+        self.prompts = policy;
+        self.markers = markers;
+        self
+    }
+
+    /// Tees raw stdout and stderr lines to the file at `path`, rotated by
+    /// size (see [`LogSink`]). Default: no log file.
+    #[must_use]
+    pub const fn log_sink(mut self, path: Option<&'a Path>) -> Self {
+        self.log_sink = path;
+        self
+    }
+
+    /// Sets OS resource limits for the child. Default: unlimited.
+    #[must_use]
+    pub const fn resource_limits(mut self, resources: ResourceLimits) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Applies `action` whenever the child prints no stdout or stderr line
+    /// for `timeout`. Default: no stall detection.
+    ///
+    /// The stall is also reported through [`LineParser::stalled`].
+    #[must_use]
+    pub const fn stall(mut self, timeout: Option<Duration>, action: &'a StallAction) -> Self {
+        self.stall_timeout = timeout;
+        self.on_stall = action;
+        self
+    }
+
+    /// Passes SIGINT and SIGTERM received by the host on to the child's
+    /// process group, stops the child like a timeout would, and fails the run
+    /// with [`ProcessError::Cancelled`]. Default: off.
+    ///
+    /// On Unix the child is started in its own process group, so a Ctrl-C
+    /// typed at the terminal reaches it once, through the relay, along with
+    /// any process it spawned. On Windows the child is killed on Ctrl-C.
+    /// See [`SignalListener`] for the effect on the host.
+    #[must_use]
+    pub const fn forward_signals(mut self, enabled: bool) -> Self {
+        self.forward_signals = enabled;
+        self
+    }
+
+    /// Classifies each stderr line with `patterns` as it arrives and passes
+    /// progress and warnings to [`LineParser::stderr`]. Every line is still
+    /// captured. Default: stderr is only captured.
+    #[must_use]
+    pub const fn stderr_events(mut self, patterns: Option<&'a StderrPatterns>) -> Self {
+        self.stderr_patterns = patterns;
+        self
+    }
+
+    /// Sets up the child's stdio, process group and resource limits, and
+    /// starts listening for signals to forward, if enabled.
+    fn prepare_command(&mut self) -> Result<Option<SignalListener>, ProcessError> {
+        self.command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Dropping an in-flight run (e.g. an aborted stream) must not leak the CLI.
+            .kill_on_drop(true);
+        // A CLI reading its input from an open stdin would wait for EOF.
+        if self.stdin.is_some() || self.prompts.keep_stdin_open {
+            self.command.stdin(Stdio::piped());
+        } else {
+            self.command.stdin(Stdio::null());
+        }
+        self.resources.before_spawn(&mut self.command);
+        if !self.forward_signals {
+            return Ok(None);
+        }
+        #[cfg(unix)]
+        self.command.process_group(0);
+        SignalListener::new()
+            .map(Some)
+            .map_err(|e| ProcessError::io("signal handler", e))
+    }
+
+    /// Writes the [`stdin`](Self::stdin) input and closes the pipe so the CLI
+    /// sees EOF, within the spawn timeout. Otherwise stdin is only piped when
+    /// the prompt policy keeps it open, to answer prompts and nudge stalls.
+    async fn write_stdin(
+        &self,
+        child: &mut Child,
+        pid: u32,
+        start: Instant,
+    ) -> Result<(), ProcessError> {
+        let Some(input) = self.stdin else {
+            return Ok(());
+        };
+        let Some(mut pipe) = child.stdin.take() else {
+            return Ok(());
+        };
+        let write = pipe.write_all(input.as_bytes());
+        let written = match self.spawn_timeout {
+            Some(timeout) => {
+                tokio::time::timeout(timeout.saturating_sub(start.elapsed()), write).await
+            }
+            None => Ok(write.await),
+        };
+        if let Ok(result) = written {
+            return result.map_err(|e| ProcessError::io("stdin write", e));
+        }
+        drop(pipe);
+        let _ = graceful_shutdown(child, pid, self.grace_period).await;
+        Err(ProcessError::Timeout {
+            stage: TimeoutStage::Spawn,
+            elapsed: start.elapsed(),
+            pid,
+            partial_stdout: String::new(),
+            partial_stderr: String::new(),
+        })
+    }
+
+    /// Spawns the process and collects its output.
+    ///
+    /// Each stdout line is passed to `parser`; the events it returns are
+    /// delivered to `events`, if given. The exit code is reported as-is;
+    /// interpreting it is left to the adapter.
+    ///
+    /// # Errors
+    /// Returns [`ProcessError::Timeout`] with the output captured so far when
+    /// a timeout expires, naming it in its [`TimeoutStage`],
+    /// [`ProcessError::OutputTruncated`] when a limit is
+    /// exceeded under [`OverflowPolicy::Error`],
+    /// [`ProcessError::InteractivePromptDetected`] for an unanswerable
+    /// prompt, [`ProcessError::Stalled`] for a stall under
+    /// [`StallAction::Abort`], [`ProcessError::Cancelled`] for a forwarded
+    /// signal, and an I/O, join or signal error if the process cannot be
+    /// spawned, read or stopped, the log file cannot be opened, or a
+    /// resource limit cannot be applied. Write errors on the log file only
+    /// stop the tee.
+    pub async fn run<P: LineParser>(
+        mut self,
+        parser: &mut P,
+        events: Option<EventSink<P::Event>>,
+    ) -> Result<ProcessOutput, ProcessError> {
+        let start = Instant::now();
+        let log: SharedLog = Arc::new(Mutex::new(
+            self.log_sink
+                .map(LogSink::open)
+                .transpose()
+                .map_err(|e| ProcessError::io("log sink open", e))?,
+        ));
+
+        let mut signals = self.prepare_command()?;
+        let stats = ProcessStats::global();
+        let mut child = self.command.spawn().map_err(|e| {
+            stats.record_spawn_failure();
+            ProcessError::io("subprocess spawn", e)
+        })?;
+        // Counts the child as killed if the run is abandoned below.
+        let supervised = stats.supervise();
+        // On failure the child is killed when it is dropped.
+        self.resources
+            .after_spawn(&child)
+            .map_err(|e| ProcessError::io("resource limits", e))?;
+        let pid = child.id().ok_or(ProcessError::NoPid)?;
+
+        self.write_stdin(&mut child, pid, start).await?;
+        let watcher = PromptWatcher::new(self.prompts, self.markers, child.stdin.take());
+
+        let limits = self.limits;
+        let Drains {
+            mut tasks,
+            stdout_rx,
+            stderr_rx,
+            partial_rx,
+        } = Drains::spawn(&mut child, limits, log)?;
+
+        let mut collector = Collector {
+            stdout_rx,
+            stderr_rx,
+            partial_rx,
+            stdout: OutputBuffer::new(limits),
+            stderr: OutputBuffer::new(limits),
+            watcher,
+            stream_started: false,
+            pid,
+            stall_timeout: self.stall_timeout,
+            on_stall: self.on_stall,
+            started: start,
+            latency: LatencyRecorder::new(start.elapsed()),
+            first_output_at: self
+                .first_output_timeout
+                .map(|timeout| tokio::time::Instant::from_std(start) + timeout),
+            stderr_patterns: self.stderr_patterns,
+        };
+        let collected = tokio::select! {
+            collected = tokio::time::timeout(
+                self.timeout,
+                collector.collect(&mut child, &mut tasks, parser, events),
+            ) => collected,
+            signal = next_signal(&mut signals) => {
+                collector.drain_remaining();
+                let _ = forward_signal(&mut child, pid, signal, self.grace_period).await;
+                supervised.killed();
+                tasks.abort_all();
+                return Err(ProcessError::Cancelled {
+                    signal: signal.name().to_string(),
+                    pid,
+                    partial_stdout: collector.stdout.join(),
+                    partial_stderr: collector.stderr.join(),
+                });
+            }
+        };
+
+        match collected {
+            Ok(Ok(exit_code)) => {
+                supervised.exited();
+                Ok(collector.output(exit_code, start.elapsed()))
+            }
+            Ok(Err(e)) => {
+                // The CLI may be blocked on a prompt or a full pipe; stop it
+                // rather than leak it.
+                if matches!(child.try_wait(), Ok(None)) {
+                    let _ = graceful_shutdown(&mut child, pid, self.grace_period).await;
+                    supervised.killed();
+                } else {
+                    supervised.exited();
+                }
+                tasks.abort_all();
+                Err(e)
+            }
+            Err(_) => {
+                let elapsed = start.elapsed();
+                collector.drain_remaining();
+                let _ = graceful_shutdown(&mut child, pid, self.grace_period).await;
+                supervised.killed();
+                tasks.abort_all();
+                Err(ProcessError::Timeout {
+                    stage: TimeoutStage::Total,
+                    elapsed,
+                    pid,
+                    partial_stdout: collector.stdout.join(),
+                    partial_stderr: collector.stderr.join(),
+                })
+            }
+        }
+    }
+}
+
+/// The next signal `listener` receives; never resolves without one.
+async fn next_signal(listener: &mut Option<SignalListener>) -> HostSignal {
+    match listener {
+        Some(listener) => listener.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Receiving ends of the pipe readers and the output retained so far.
+///
+/// Kept outside the timed future so a timeout still reports partial output.
+struct Collector<'a> {
+    stdout_rx: mpsc::Receiver<String>,
+    stderr_rx: mpsc::Receiver<String>,
+    /// Lines still waiting for their newline, to check for prompts.
+    partial_rx: mpsc::Receiver<(Pipe, String)>,
+    stdout: OutputBuffer,
+    stderr: OutputBuffer,
+    watcher: PromptWatcher<'a>,
+    /// Whether a stdout line has been parsed into events; later stdout is
+    /// the CLI's output, not a prompt.
+    stream_started: bool,
+    pid: u32,
+    stall_timeout: Option<Duration>,
+    on_stall: &'a StallAction,
+    /// When the run started.
+    started: Instant,
+    /// Timestamps of the parsed events.
+    latency: LatencyRecorder,
+    /// When the child must have printed its first line; cleared once it has.
+    first_output_at: Option<tokio::time::Instant>,
+    /// Patterns turning stderr lines into events, if enabled.
+    stderr_patterns: Option<&'a StderrPatterns>,
+}
+
+impl Collector<'_> {
+    /// Drains both channels to EOF, waits for the child to exit, then joins
+    /// the reader tasks. Returns the exit code.
+    ///
+    /// Stderr lines, stdout lines before the output stream starts, and lines
+    /// left without a newline are shown to the [`PromptWatcher`], so an
+    /// interactive prompt is answered or fails the run as soon as it is
+    /// printed.
+    async fn collect<P: LineParser>(
+        &mut self,
+        child: &mut Child,
+        tasks: &mut JoinSet<Result<(), ProcessError>>,
+        parser: &mut P,
+        mut events: Option<EventSink<P::Event>>,
+    ) -> Result<i32, ProcessError> {
+        let mut stdout_done = false;
+        let mut stderr_done = false;
+        // The child counts as stalled once no line has arrived by `stall_at`.
+        let stall_timeout = self.stall_timeout;
+        let after = |instant| stall_timeout.map(|timeout| instant + timeout);
+        let mut last_line = tokio::time::Instant::now();
+        let mut stall_at = after(last_line);
+
+        while !stdout_done || !stderr_done {
+            let deadline = stall_at.unwrap_or(last_line);
+            tokio::select! {
+                () = tokio::time::sleep_until(self.first_output_at.unwrap_or(deadline)),
+                    if self.first_output_at.is_some() =>
+                {
+                    return Err(ProcessError::Timeout {
+                        stage: TimeoutStage::FirstOutput,
+                        elapsed: self.started.elapsed(),
+                        pid: self.pid,
+                        partial_stdout: self.stdout.join(),
+                        partial_stderr: self.stderr.join(),
+                    });
+                }
+                () = tokio::time::sleep_until(deadline), if stall_at.is_some() => {
+                    self.stalled(last_line.elapsed(), parser, &mut events).await?;
+                    // Act again only after another full timeout of silence.
+                    stall_at = after(deadline);
+                }
+                result = self.stdout_rx.recv(), if !stdout_done => {
+                    if let Some(line) = result {
+                        last_line = tokio::time::Instant::now();
+                        stall_at = after(last_line);
+                        self.first_output_at = None;
+                        let parsed = parser.parse(&line);
+                        if !self.stream_started {
+                            if parsed.is_empty() {
+                                self.watcher.inspect(&line).await?;
+                            } else {
+                                self.stream_started = true;
+                            }
+                        }
+                        self.latency.parsed(self.started.elapsed(), &parsed);
+                        if let Some(sink) = &mut events {
+                            for event in parsed {
+                                sink.push(event).await;
+                            }
+                        }
+                        self.stdout.push(line)?;
+                    } else {
+                        stdout_done = true;
+                    }
+                }
+                result = self.stderr_rx.recv(), if !stderr_done => {
+                    if let Some(line) = result {
+                        last_line = tokio::time::Instant::now();
+                        stall_at = after(last_line);
+                        self.first_output_at = None;
+                        self.watcher.inspect(&line).await?;
+                        if let (Some(patterns), Some(sink)) = (self.stderr_patterns, &mut events) {
+                            if let Some((kind, message)) = patterns.classify(&line) {
+                                for event in parser.stderr(kind, &message) {
+                                    sink.push(event).await;
+                                }
+                            }
+                        }
+                        self.stderr.push(line)?;
+                    } else {
+                        stderr_done = true;
+                    }
+                }
+                Some((pipe, line)) = self.partial_rx.recv() => {
+                    if pipe == Pipe::Stderr || !self.stream_started {
+                        self.watcher.inspect(&line).await?;
+                    }
+                }
+            }
+        }
+
+        if let Some(sink) = events {
+            sink.finish().await;
+        }
+
+        let status = child
+            .wait()
+            .await
+            .map_err(|e| ProcessError::io("child.wait()", e))?;
+
+        while let Some(result) = tasks.join_next().await {
+            result.map_err(|e| ProcessError::Join {
+                stage: "reader task join".to_string(),
+                source: e,
+            })??;
+        }
+
+        Ok(status.code().unwrap_or(-1))
+    }
+
+    /// Reports that the child has printed nothing for `idle`, then applies
+    /// the [`StallAction`].
+    async fn stalled<P: LineParser>(
+        &mut self,
+        idle: Duration,
+        parser: &mut P,
+        events: &mut Option<EventSink<P::Event>>,
+    ) -> Result<(), ProcessError> {
+        tracing::warn!(
+            pid = self.pid,
+            idle_ms = u64::try_from(idle.as_millis()).unwrap_or(u64::MAX),
+            "CLI has produced no output"
+        );
+        if let Some(sink) = events {
+            for event in parser.stalled(idle) {
+                sink.push(event).await;
+            }
+        }
+        match self.on_stall {
+            StallAction::Warn => Ok(()),
+            StallAction::Nudge(text) => {
+                if !self.watcher.write_line(text).await? {
+                    tracing::debug!(pid = self.pid, "CLI stdin is closed; cannot nudge");
+                }
+                Ok(())
+            }
+            StallAction::Abort => Err(ProcessError::Stalled {
+                idle,
+                last_activity: SystemTime::now()
+                    .checked_sub(idle)
+                    .unwrap_or_else(SystemTime::now),
+                pid: self.pid,
+                partial_stdout: self.stdout.join(),
+                partial_stderr: self.stderr.join(),
+            }),
+        }
+    }
+
+    /// The output of a child that exited with `exit_code` after `duration`.
+    fn output(&mut self, exit_code: i32, duration: Duration) -> ProcessOutput {
+        let latency = std::mem::replace(&mut self.latency, LatencyRecorder::new(Duration::ZERO));
+        let (latency, event_offsets) = latency.finish(duration);
+        ProcessOutput {
+            stdout: self.stdout.join(),
+            stderr: self.stderr.join(),
+            exit_code,
+            pid: self.pid,
+            duration,
+            latency,
+            event_offsets,
+        }
+    }
+
+    /// Moves lines still queued in the channels into the buffers, ignoring
+    /// overflow so a timeout reports as much output as the limits allow.
+    fn drain_remaining(&mut self) {
+        while let Ok(line) = self.stdout_rx.try_recv() {
+            let _ = self.stdout.push(line);
+        }
+        while let Ok(line) = self.stderr_rx.try_recv() {
+            let _ = self.stderr.push(line);
+        }
+    }
+}
+
+/// The child's output pipes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pipe {
+    Stdout,
+    Stderr,
+}
+
+impl Pipe {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// The tasks draining a child's stdout and stderr, and what they read.
+struct Drains {
+    tasks: JoinSet<Result<(), ProcessError>>,
+    stdout_rx: mpsc::Receiver<String>,
+    stderr_rx: mpsc::Receiver<String>,
+    /// Lines still waiting for their newline, to check for prompts.
+    partial_rx: mpsc::Receiver<(Pipe, String)>,
+}
+
+impl Drains {
+    /// Starts draining the child's stdout and stderr.
+    fn spawn(
+        child: &mut Child,
+        limits: OutputLimits,
+        log: SharedLog,
+    ) -> Result<Self, ProcessError> {
+        let stdout = child.stdout.take().ok_or(ProcessError::NoStdout)?;
+        let stderr = child.stderr.take().ok_or(ProcessError::NoStderr)?;
+        let (stdout_tx, stdout_rx) = mpsc::channel(limits.channel_capacity);
+        let (stderr_tx, stderr_rx) = mpsc::channel(limits.channel_capacity);
+        let (partial_tx, partial_rx) = mpsc::channel(2);
+        let mut tasks = JoinSet::new();
+        tasks.spawn(drain_pipe(
+            stdout,
+            Pipe::Stdout,
+            stdout_tx,
+            partial_tx.clone(),
+            limits,
+            Arc::clone(&log),
+        ));
+        tasks.spawn(drain_pipe(
+            stderr,
+            Pipe::Stderr,
+            stderr_tx,
+            partial_tx,
+            limits,
+            log,
+        ));
+        Ok(Self {
+            tasks,
+            stdout_rx,
+            stderr_rx,
+            partial_rx,
+        })
+    }
+}
+
+/// Reads one pipe line by line and forwards each line to the collector,
+/// teeing it to the log file first.
+///
+/// A line that goes [`PROMPT_WAIT`] without its newline, such as a question
+/// waiting for an answer, is also sent to `partial` once, as read so far.
+///
+/// Under [`OverflowPolicy::Error`] the reader stops as soon as a limit is
+/// exceeded; the truncating policies are applied by the collector's
+/// [`OutputBuffer`], so the pipe is always drained to EOF.
+async fn drain_pipe(
+    pipe: impl AsyncRead + Unpin,
+    kind: Pipe,
+    tx: mpsc::Sender<String>,
+    partial: mpsc::Sender<(Pipe, String)>,
+    limits: OutputLimits,
+    log: SharedLog,
+) -> Result<(), ProcessError> {
+    let name = kind.name();
+    let mut reader = LineReader::new(pipe, limits.max_bytes);
+    let mut total_bytes = 0;
+    let mut total_lines = 0;
+
+    // The partial line last sent, so it is checked once until it grows.
+    let mut checked = None;
+
+    loop {
+        let read = loop {
+            match tokio::time::timeout(PROMPT_WAIT, reader.next_line()).await {
+                Ok(read) => break read,
+                Err(_) => {
+                    if let Some(pending) = reader
+                        .pending()
+                        .filter(|pending| checked.as_ref() != Some(pending))
+                    {
+                        checked = Some(pending.clone());
+                        let _ = partial.send((kind, pending)).await;
+                    }
+                }
+            }
+        };
+        checked = None;
+        let Some(line) = read.map_err(|e| ProcessError::io(format!("{name} read"), e))? else {
+            break;
+        };
+        total_bytes += line.raw_len;
+        total_lines += 1;
+        tee(&log, name, line.text);
+
+        if limits.overflow == OverflowPolicy::Error && !limits.allows(total_bytes, total_lines) {
+            return Err(ProcessError::OutputTruncated {
+                captured_bytes: total_bytes,
+                limit_bytes: limits.max_bytes,
+            });
+        }
+
+        if tx.send(line.text.to_owned()).await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Appends `line` to the log file, if any. A failed write is logged and
+/// stops the tee; it does not fail the run.
+fn tee(log: &SharedLog, name: &str, line: &str) {
+    let Ok(mut guard) = log.lock() else {
+        return;
+    };
+    if let Some(sink) = guard.as_mut() {
+        if let Err(e) = sink.write_line(name, line) {
+            tracing::warn!(error = %e, "log sink write failed; no longer teeing output");
+            *guard = None;
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Upper(String);
+
+    impl TextEvent for Upper {
+        fn text_mut(&mut self) -> Option<&mut String> {
+            Some(&mut self.0)
+        }
+    }
+
+    struct UpperParser;
+
+    impl LineParser for UpperParser {
+        type Event = Upper;
+
+        fn parse(&mut self, line: &str) -> Vec<Upper> {
+            vec![Upper(line.to_uppercase())]
+        }
+
+        fn stderr(&mut self, kind: StderrKind, message: &str) -> Vec<Upper> {
+            vec![Upper(format!("{kind:?}: {message}"))]
+        }
+    }
+
+    fn sh(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[tokio::test]
+    async fn test_runner_collects_output_and_events() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let sink = EventSink::new(tx, crate::BackpressurePolicy::Block);
+        let output = ProcessRunner::new(sh("cat; echo oops >&2; exit 3"))
+            .stdin("one\ntwo\n")
+            .run(&mut UpperParser, Some(sink))
+            .await
+            .unwrap();
+
+        assert_eq!(output.stdout, "one\ntwo");
+        assert_eq!(output.stderr, "oops");
+        assert_eq!(output.exit_code, 3);
+        assert_eq!(rx.recv().await, Some(Upper("ONE".to_string())));
+        assert_eq!(rx.recv().await, Some(Upper("TWO".to_string())));
+        assert_eq!(output.event_offsets.len(), 2);
+        assert!(output.latency.spawn_ms <= output.latency.total_ms);
+        assert_eq!(output.latency.first_token_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_runner_streams_classified_stderr() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let sink = EventSink::new(tx, crate::BackpressurePolicy::Block);
+        let patterns = StderrPatterns::default();
+        let output = ProcessRunner::new(sh(
+            "echo 'Thinking...' >&2; echo boom >&2; echo 'Warning: slow' >&2",
+        ))
+        .stderr_events(Some(&patterns))
+        .run(&mut UpperParser, Some(sink))
+        .await
+        .unwrap();
+
+        assert_eq!(output.stderr, "Thinking...\nboom\nWarning: slow");
+        assert_eq!(
+            rx.recv().await,
+            Some(Upper("Progress: Thinking...".to_string()))
+        );
+        assert_eq!(
+            rx.recv().await,
+            Some(Upper("Warning: Warning: slow".to_string()))
+        );
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_runner_tees_output_to_log_sink() {
+        let path = std::env::temp_dir().join(format!("rig_runner_tee_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let err = ProcessRunner::new(sh("echo started; echo warn >&2; sleep 30"))
+            .limits(OutputLimits {
+                max_lines: Some(0),
+                overflow: OverflowPolicy::TruncateTail,
+                ..OutputLimits::default()
+            })
+            .log_sink(Some(&path))
+            .timeout(Duration::from_millis(300))
+            .grace_period(Duration::from_secs(1))
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            ProcessError::Timeout { ref partial_stdout, .. } if partial_stdout.is_empty()
+        ));
+        let mut lines: Vec<_> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        lines.sort();
+        assert_eq!(lines, ["[stderr] warn", "[stdout] started"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_runner_applies_resource_limits() {
+        let output = ProcessRunner::new(sh("ulimit -v; ulimit -t; ulimit -n; nice"))
+            .resource_limits(ResourceLimits {
+                memory_bytes: Some(1 << 30),
+                cpu_seconds: Some(60),
+                open_files: Some(64),
+                niceness: Some(19),
+            })
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap();
+
+        assert_eq!(output.stdout, "1048576\n60\n64\n19");
+    }
+
+    #[tokio::test]
+    async fn test_runner_timeout_reports_partial_output() {
+        let err = ProcessRunner::new(sh("echo started; sleep 30"))
+            .timeout(Duration::from_millis(300))
+            .grace_period(Duration::from_secs(1))
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap_err();
+
+        match err {
+            ProcessError::Timeout {
+                stage,
+                elapsed,
+                partial_stdout,
+                ..
+            } => {
+                assert_eq!(stage, TimeoutStage::Total);
+                assert!(elapsed >= Duration::from_millis(300));
+                assert_eq!(partial_stdout, "started");
+            }
+            other => panic!("expected timeout, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runner_first_output_timeout() {
+        let err = ProcessRunner::new(sh("sleep 30"))
+            .first_output_timeout(Some(Duration::from_millis(200)))
+            .grace_period(Duration::from_secs(1))
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ProcessError::Timeout { stage: TimeoutStage::FirstOutput, elapsed, .. }
+                    if elapsed >= Duration::from_millis(200)
+            ),
+            "{err:?}"
+        );
+
+        // Once the child has printed, only the total timeout applies.
+        let output = ProcessRunner::new(sh("echo started; sleep 0.4; echo done"))
+            .first_output_timeout(Some(Duration::from_millis(200)))
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "started\ndone");
+    }
+
+    #[tokio::test]
+    async fn test_runner_spawn_timeout_covers_stdin_write() {
+        // The child never reads, so writing more than a pipe holds blocks.
+        let input = "x".repeat(1 << 20);
+        let err = ProcessRunner::new(sh("sleep 30"))
+            .stdin(&input)
+            .spawn_timeout(Some(Duration::from_millis(200)))
+            .grace_period(Duration::from_secs(1))
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ProcessError::Timeout {
+                    stage: TimeoutStage::Spawn,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string().split(" after").next(),
+            Some("Process hit its spawn timeout")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_runner_aborts_stalled_process() {
+        let action = StallAction::Abort;
+        let err = ProcessRunner::new(sh("echo started; sleep 30"))
+            .stall(Some(Duration::from_millis(200)), &action)
+            .grace_period(Duration::from_secs(1))
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap_err();
+
+        match err {
+            ProcessError::Stalled {
+                idle,
+                last_activity,
+                partial_stdout,
+                ..
+            } => {
+                assert!(idle >= Duration::from_millis(200));
+                assert!(last_activity < SystemTime::now());
+                assert_eq!(partial_stdout, "started");
+            }
+            other => panic!("expected stall, got {other:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runner_forwards_host_signal_to_child() {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let (tx, mut rx) = mpsc::channel(8);
+        let sink = EventSink::new(tx, crate::BackpressurePolicy::Block);
+        let run = tokio::spawn(async move {
+            ProcessRunner::new(sh("echo started; sleep 30"))
+                .forward_signals(true)
+                .timeout(Duration::from_secs(10))
+                .grace_period(Duration::from_secs(1))
+                .run(&mut UpperParser, Some(sink))
+                .await
+        });
+
+        assert_eq!(rx.recv().await, Some(Upper("STARTED".to_string())));
+        kill(Pid::this(), Signal::SIGINT).unwrap();
+        match run.await.unwrap().unwrap_err() {
+            ProcessError::Cancelled {
+                signal,
+                partial_stdout,
+                ..
+            } => {
+                assert_eq!(signal, "SIGINT");
+                assert_eq!(partial_stdout, "started");
+            }
+            other => panic!("expected cancellation, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runner_nudges_stalled_process() {
+        let action = StallAction::Nudge("continue".to_string());
+        let policy = InteractivePromptPolicy {
+            keep_stdin_open: true,
+            ..InteractivePromptPolicy::default()
+        };
+        let output = ProcessRunner::new(sh("read line; echo \"got $line\""))
+            .prompts(&policy, &[])
+            .stall(Some(Duration::from_millis(100)), &action)
+            .timeout(Duration::from_secs(5))
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap();
+
+        assert_eq!(output.stdout, "got continue");
+    }
+
+    #[tokio::test]
+    async fn test_runner_stops_on_unanswered_prompt() {
+        let policy = InteractivePromptPolicy::default();
+        for script in [
+            "echo 'Trust this folder?' >&2; sleep 30",
+            // Left waiting for an answer on the same line.
+            "printf 'Trust this folder? [y/n] '; sleep 30",
+        ] {
+            let err = ProcessRunner::new(sh(script))
+                .prompts(&policy, &["trust this folder"])
+                .grace_period(Duration::from_secs(1))
+                .run(&mut UpperParser, None)
+                .await
+                .unwrap_err();
+
+            assert!(
+                matches!(
+                    err,
+                    ProcessError::InteractivePromptDetected { ref question }
+                        if question.starts_with("Trust this folder?")
+                ),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runner_closes_stdin_and_ignores_prompts_in_output() {
+        let policy = InteractivePromptPolicy {
+            auto_answers: vec![crate::PromptResponse {
+                pattern: "trust this folder".to_string(),
+                answer: "y".to_string(),
+            }],
+            keep_stdin_open: false,
+        };
+        // `cat` only returns once stdin is closed; the echoed line is output
+        // the parser turns into events, not a prompt.
+        let output = ProcessRunner::new(sh("cat; echo 'Trust this folder?'"))
+            .prompts(&policy, &["trust this folder"])
+            .timeout(Duration::from_secs(5))
+            .run(&mut UpperParser, None)
+            .await
+            .unwrap();
+
+        assert_eq!(output.stdout, "Trust this folder?");
+    }
+}
@@ -0,0 +1,66 @@
+//! Stopping a child process that overran its timeout or is stuck on a prompt.
+
+use crate::error::ProcessError;
+use std::time::Duration;
+
+/// Default time to wait for a graceful SIGTERM exit before sending SIGKILL.
+pub const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Sends SIGTERM, waits up to `grace`, then force-kills with SIGKILL.
+///
+/// # Errors
+/// Returns [`ProcessError::Signal`] if SIGTERM cannot be delivered and
+/// [`ProcessError::Io`] if killing or waiting on the child fails.
+#[cfg(unix)]
+pub async fn graceful_shutdown(
+    child: &mut tokio::process::Child,
+    pid: u32,
+    grace: Duration,
+) -> Result<std::process::ExitStatus, ProcessError> {
+    use nix::sys::signal::{self, Signal};
+    use nix::unistd::Pid;
+
+    let raw_pid = i32::try_from(pid).map_err(|_| ProcessError::Signal {
+        signal: "SIGTERM".to_string(),
+        pid,
+        reason: "PID value exceeds i32::MAX".to_string(),
+    })?;
+    signal::kill(Pid::from_raw(raw_pid), Signal::SIGTERM).map_err(|e| ProcessError::Signal {
+        signal: "SIGTERM".to_string(),
+        pid,
+        reason: e.to_string(),
+    })?;
+
+    match tokio::time::timeout(grace, child.wait()).await {
+        Ok(Ok(status)) => Ok(status),
+        Ok(Err(e)) => Err(ProcessError::io("graceful_shutdown wait", e)),
+        Err(_) => {
+            child
+                .kill()
+                .await
+                .map_err(|e| ProcessError::io("SIGKILL", e))?;
+            child
+                .wait()
+                .await
+                .map_err(|e| ProcessError::io("post-SIGKILL wait", e))
+        }
+    }
+}
+
+/// Windows: no graceful shutdown mechanism for console processes.
+/// Uses immediate TerminateProcess via Child::kill().
+#[cfg(windows)]
+pub async fn graceful_shutdown(
+    child: &mut tokio::process::Child,
+    _pid: u32,
+    _grace: Duration,
+) -> Result<std::process::ExitStatus, ProcessError> {
+    child
+        .kill()
+        .await
+        .map_err(|e| ProcessError::io("TerminateProcess", e))?;
+    child
+        .wait()
+        .await
+        .map_err(|e| ProcessError::io("post-kill wait", e))
+}
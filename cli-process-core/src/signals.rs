@@ -0,0 +1,148 @@
+//! Relaying SIGINT / SIGTERM received by the host to the child CLI.
+//!
+//! Without a relay, a program interrupted while a CLI runs exits and leaves
+//! the CLI behind whenever the signal does not reach the child directly, for
+//! example when it is sent with `kill` rather than typed at the terminal.
+//! [`ProcessRunner::forward_signals`](crate::ProcessRunner::forward_signals)
+//! listens with a [`SignalListener`] for the whole run and passes the first
+//! signal on with [`forward_signal`].
+
+use crate::error::ProcessError;
+use std::time::Duration;
+
+/// A termination signal received by the host process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostSignal {
+    /// SIGINT, or Ctrl-C on Windows.
+    Interrupt,
+    /// SIGTERM.
+    Terminate,
+}
+
+impl HostSignal {
+    /// The signal's conventional name, e.g. `"SIGINT"`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Interrupt => "SIGINT",
+            Self::Terminate => "SIGTERM",
+        }
+    }
+}
+
+/// Receives the SIGINT and SIGTERM signals sent to the host process.
+///
+/// Creating a listener replaces the default action of both signals, which
+/// is to terminate the process, for the rest of the process's life: a host
+/// that forwards signals has to exit on its own once the run reports
+/// [`ProcessError::Cancelled`].
+#[derive(Debug)]
+pub struct SignalListener {
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+}
+
+impl SignalListener {
+    /// Starts listening.
+    ///
+    /// # Errors
+    /// Returns an error if a signal handler cannot be installed.
+    #[cfg(unix)]
+    pub fn new() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    /// Starts listening for Ctrl-C.
+    ///
+    /// # Errors
+    /// Never fails on Windows; the handler is installed by
+    /// [`recv`](Self::recv).
+    #[cfg(windows)]
+    pub fn new() -> std::io::Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Waits for the next signal.
+    #[cfg(unix)]
+    pub async fn recv(&mut self) -> HostSignal {
+        tokio::select! {
+            _ = self.interrupt.recv() => HostSignal::Interrupt,
+            _ = self.terminate.recv() => HostSignal::Terminate,
+        }
+    }
+
+    /// Waits for the next Ctrl-C.
+    #[cfg(windows)]
+    pub async fn recv(&mut self) -> HostSignal {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        HostSignal::Interrupt
+    }
+}
+
+/// Sends `signal` to the process group led by `pid`, waits up to `grace`
+/// for the child to exit, then kills it with SIGKILL.
+///
+/// The child must have been started as the leader of its own process group,
+/// so the signal also reaches any process it spawned.
+///
+/// # Errors
+/// Returns [`ProcessError::Signal`] if the signal cannot be delivered and
+/// [`ProcessError::Io`] if killing or waiting on the child fails.
+#[cfg(unix)]
+pub async fn forward_signal(
+    child: &mut tokio::process::Child,
+    pid: u32,
+    signal: HostSignal,
+    grace: Duration,
+) -> Result<std::process::ExitStatus, ProcessError> {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+
+    let failed = |reason: String| ProcessError::Signal {
+        signal: signal.name().to_string(),
+        pid,
+        reason,
+    };
+    let raw_pid =
+        i32::try_from(pid).map_err(|_| failed("PID value exceeds i32::MAX".to_string()))?;
+    let kind = match signal {
+        HostSignal::Interrupt => Signal::SIGINT,
+        HostSignal::Terminate => Signal::SIGTERM,
+    };
+    killpg(Pid::from_raw(raw_pid), kind).map_err(|e| failed(e.to_string()))?;
+
+    match tokio::time::timeout(grace, child.wait()).await {
+        Ok(Ok(status)) => Ok(status),
+        Ok(Err(e)) => Err(ProcessError::io("forward_signal wait", e)),
+        Err(_) => {
+            child
+                .kill()
+                .await
+                .map_err(|e| ProcessError::io("SIGKILL", e))?;
+            child
+                .wait()
+                .await
+                .map_err(|e| ProcessError::io("post-SIGKILL wait", e))
+        }
+    }
+}
+
+/// Windows: console processes cannot be sent a signal, so the child is
+/// terminated immediately.
+#[cfg(windows)]
+pub async fn forward_signal(
+    child: &mut tokio::process::Child,
+    pid: u32,
+    _signal: HostSignal,
+    grace: Duration,
+) -> Result<std::process::ExitStatus, ProcessError> {
+    crate::shutdown::graceful_shutdown(child, pid, grace).await
+}
@@ -0,0 +1,38 @@
+//! Detection of CLIs that stop producing output.
+//!
+//! An agent waiting on a hung tool or a stuck network call can print
+//! nothing for minutes before the wall-clock timeout stops it. With a stall
+//! timeout, the runner notices as soon as neither stdout nor stderr has
+//! produced a line for that long, reports it as a stream event through
+//! [`LineParser::stalled`](crate::LineParser::stalled), and applies the
+//! configured [`StallAction`].
+
+use serde::{Deserialize, Serialize};
+
+/// What the runner does when a CLI stalls.
+///
+/// Each action is taken again whenever the CLI stays silent for another
+/// stall timeout.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StallAction {
+    /// Only report the stall; the run continues until the timeout.
+    #[default]
+    Warn,
+    /// Write this text, followed by a newline, to the CLI's stdin.
+    ///
+    /// For CLIs run in an interactive or stdin-driven mode. Has no effect
+    /// unless the prompt policy
+    /// [keeps stdin open](crate::InteractivePromptPolicy::keep_stdin_open)
+    /// and no input was piped through stdin.
+    Nudge(String),
+    /// Stop the CLI and fail the run with a `Stalled` error.
+    Abort,
+}
+
+impl StallAction {
+    /// Returns `true` if the action needs the CLI's stdin kept open.
+    #[must_use]
+    pub const fn needs_stdin(&self) -> bool {
+        matches!(self, Self::Nudge(_))
+    }
+}
@@ -0,0 +1,281 @@
+//! Process-wide counters of supervised children, and leak checks.
+//!
+//! Every child [`ProcessRunner`](crate::ProcessRunner) spawns is counted in
+//! [`ProcessStats::global`]: spawned, exited on its own, or killed (after a
+//! timeout, a failed run, a forwarded signal, or being dropped mid-run).
+//! A long-lived host whose [`running`](ProcessCounts::running) count keeps
+//! growing is leaking children; [`LeakCheck`] additionally looks for zombie
+//! children and file descriptors left open between two points in time.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters of the children spawned through this crate.
+///
+/// All counters only grow; take two [`snapshot`](Self::snapshot)s to get
+/// rates.
+#[derive(Debug, Default)]
+pub struct ProcessStats {
+    spawned: AtomicU64,
+    spawn_failures: AtomicU64,
+    exited: AtomicU64,
+    killed: AtomicU64,
+    restarts: AtomicU64,
+}
+
+/// A point-in-time copy of [`ProcessStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessCounts {
+    /// Children spawned.
+    pub spawned: u64,
+    /// Spawn attempts that failed.
+    pub spawn_failures: u64,
+    /// Children that exited on their own.
+    pub exited: u64,
+    /// Children stopped by a signal or by being dropped.
+    pub killed: u64,
+    /// Runs spawned again after their first child's output was unusable.
+    pub restarts: u64,
+}
+
+impl ProcessCounts {
+    /// Children spawned and not yet seen to exit or be killed.
+    #[must_use]
+    pub const fn running(&self) -> u64 {
+        self.spawned
+            .saturating_sub(self.exited)
+            .saturating_sub(self.killed)
+    }
+}
+
+static GLOBAL: ProcessStats = ProcessStats::new();
+
+impl ProcessStats {
+    /// Creates zeroed counters.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            spawned: AtomicU64::new(0),
+            spawn_failures: AtomicU64::new(0),
+            exited: AtomicU64::new(0),
+            killed: AtomicU64::new(0),
+            restarts: AtomicU64::new(0),
+        }
+    }
+
+    /// The counters [`ProcessRunner`](crate::ProcessRunner) records into.
+    #[must_use]
+    pub fn global() -> &'static Self {
+        &GLOBAL
+    }
+
+    /// Current values of every counter.
+    #[must_use]
+    pub fn snapshot(&self) -> ProcessCounts {
+        ProcessCounts {
+            spawned: self.spawned.load(Ordering::Relaxed),
+            spawn_failures: self.spawn_failures.load(Ordering::Relaxed),
+            exited: self.exited.load(Ordering::Relaxed),
+            killed: self.killed.load(Ordering::Relaxed),
+            restarts: self.restarts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Counts a failed spawn attempt.
+    pub fn record_spawn_failure(&self) {
+        self.spawn_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a run spawned again, e.g. with a fallback way of passing its
+    /// prompt.
+    pub fn record_restart(&self) {
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a spawned child; the returned guard counts how it ends.
+    pub(crate) fn supervise(&'static self) -> Supervised {
+        self.spawned.fetch_add(1, Ordering::Relaxed);
+        Supervised {
+            stats: self,
+            settled: false,
+        }
+    }
+}
+
+/// A spawned child not yet counted as exited or killed.
+///
+/// Dropping it unsettled counts the child as killed: runners spawn with
+/// `kill_on_drop`, so a run abandoned on an error takes its child with it.
+#[derive(Debug)]
+pub(crate) struct Supervised {
+    stats: &'static ProcessStats,
+    settled: bool,
+}
+
+impl Supervised {
+    /// Counts the child as having exited on its own.
+    pub(crate) fn exited(mut self) {
+        self.settled = true;
+        self.stats.exited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts the child as killed.
+    pub(crate) fn killed(self) {
+        drop(self);
+    }
+}
+
+impl Drop for Supervised {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.stats.killed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Open file descriptors and zombie children of this process, compared
+/// with a baseline taken by [`LeakCheck::start`].
+///
+/// Only available where `/proc` is (Linux); elsewhere
+/// [`start`](Self::start) returns an `Unsupported` error.
+#[derive(Debug, Clone, Copy)]
+pub struct LeakCheck {
+    open_fds: usize,
+}
+
+/// What [`LeakCheck::finish`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeakReport {
+    /// File descriptors open now that were not at the baseline. Negative
+    /// when more were closed than opened.
+    pub fd_delta: i64,
+    /// Children of this process that exited without being reaped.
+    pub zombies: Vec<u32>,
+}
+
+impl LeakReport {
+    /// Whether no descriptor was left open and no zombie found.
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.fd_delta <= 0 && self.zombies.is_empty()
+    }
+}
+
+impl LeakCheck {
+    /// Records the number of open file descriptors.
+    ///
+    /// # Errors
+    /// Returns an I/O error if `/proc/self/fd` cannot be read.
+    pub fn start() -> std::io::Result<Self> {
+        Ok(Self {
+            open_fds: open_fds()?,
+        })
+    }
+
+    /// Compares the open file descriptors with the baseline and lists
+    /// zombie children.
+    ///
+    /// Runs that are still in flight hold descriptors of their own, so
+    /// call it once they are done.
+    ///
+    /// # Errors
+    /// Returns an I/O error if `/proc` cannot be read.
+    pub fn finish(&self) -> std::io::Result<LeakReport> {
+        let now = i64::try_from(open_fds()?).unwrap_or(i64::MAX);
+        let before = i64::try_from(self.open_fds).unwrap_or(i64::MAX);
+        Ok(LeakReport {
+            fd_delta: now - before,
+            zombies: zombie_children()?,
+        })
+    }
+}
+
+/// Number of file descriptors this process has open.
+#[cfg(target_os = "linux")]
+fn open_fds() -> std::io::Result<usize> {
+    // The directory handle itself shows up in the listing.
+    Ok(std::fs::read_dir("/proc/self/fd")?
+        .count()
+        .saturating_sub(1))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_fds() -> std::io::Result<usize> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// PIDs of this process's children that are zombies.
+#[cfg(target_os = "linux")]
+fn zombie_children() -> std::io::Result<Vec<u32>> {
+    let parent = std::process::id();
+    let mut zombies = Vec::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let Ok(pid) = entry?.file_name().to_string_lossy().parse::<u32>() else {
+            continue;
+        };
+        // Processes may exit between the listing and the read.
+        let Ok(stat) = std::fs::read_to_string(format!("/proc/{pid}/stat")) else {
+            continue;
+        };
+        if parse_stat(&stat) == Some(('Z', parent)) {
+            zombies.push(pid);
+        }
+    }
+    Ok(zombies)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn zombie_children() -> std::io::Result<Vec<u32>> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// The state and parent PID in a `/proc/<pid>/stat` line.
+///
+/// The command name in parentheses may itself contain spaces and
+/// parentheses, so fields are counted from the last `)`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_stat(stat: &str) -> Option<(char, u32)> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    let mut fields = rest.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let parent = fields.next()?.parse().ok()?;
+    Some((state, parent))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supervised_children_settle_once() {
+        static STATS: ProcessStats = ProcessStats::new();
+        STATS.supervise().exited();
+        STATS.supervise().killed();
+        drop(STATS.supervise());
+        let _running = STATS.supervise();
+        STATS.record_restart();
+
+        let counts = STATS.snapshot();
+        assert_eq!(
+            counts,
+            ProcessCounts {
+                spawned: 4,
+                spawn_failures: 0,
+                exited: 1,
+                killed: 2,
+                restarts: 1,
+            }
+        );
+        assert_eq!(counts.running(), 1);
+    }
+
+    #[test]
+    fn test_parse_stat_reads_fields_after_the_command_name() {
+        assert_eq!(
+            parse_stat("4242 (node (worker) 1) Z 17 4242 17 0 -1"),
+            Some(('Z', 17))
+        );
+        assert_eq!(parse_stat("4242 (claude"), None);
+    }
+}
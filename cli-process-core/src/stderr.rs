@@ -0,0 +1,118 @@
+//! Classification of stderr lines into progress and warnings.
+//!
+//! CLIs print spinners and status lines such as `Thinking…` to stderr,
+//! alongside real errors. Left alone, stderr is only captured and returned
+//! once the run ends. With [`StderrPatterns`], the runner classifies each
+//! stderr line as it arrives and reports progress and warnings as stream
+//! events through [`LineParser::stderr`](crate::LineParser::stderr), so a UI
+//! can show live status. Lines matching no pattern are only captured.
+
+use serde::{Deserialize, Serialize};
+
+/// What a classified stderr line reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StderrKind {
+    /// Status of work in progress, e.g. a spinner or `Thinking…`.
+    Progress,
+    /// Something the user may want to know about that does not stop the run.
+    Warning,
+}
+
+/// Case-insensitive fragments that classify stderr lines.
+///
+/// Warning patterns are checked first, so `Warning: retrying request` is a
+/// warning even though `retrying` is a progress pattern.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StderrPatterns {
+    /// Fragments of progress lines.
+    pub progress: Vec<String>,
+    /// Fragments of warning lines.
+    pub warning: Vec<String>,
+}
+
+impl Default for StderrPatterns {
+    /// Status and warning wording shared by the Claude Code, Codex and
+    /// `OpenCode` CLIs.
+    fn default() -> Self {
+        let fragments = |fragments: &[&str]| fragments.iter().map(|f| (*f).to_string()).collect();
+        Self {
+            progress: fragments(&[
+                "thinking",
+                "working",
+                "running",
+                "loading",
+                "connecting",
+                "retrying",
+                "waiting",
+            ]),
+            warning: fragments(&["warn", "deprecated"]),
+        }
+    }
+}
+
+impl StderrPatterns {
+    /// Classifies `line`, returning its kind and its message: the last
+    /// frame of a carriage-return spinner, without ANSI escapes and
+    /// surrounding whitespace. Blank lines and lines matching no pattern
+    /// return `None`.
+    #[must_use]
+    pub fn classify(&self, line: &str) -> Option<(StderrKind, String)> {
+        let frame = line.rsplit('\r').find(|frame| !frame.trim().is_empty())?;
+        let message = strip_ansi(frame).trim().to_string();
+        let lower = message.to_lowercase();
+        let matches = |fragments: &[String]| {
+            fragments
+                .iter()
+                .any(|f| !f.is_empty() && lower.contains(&f.to_lowercase()))
+        };
+        if matches(&self.warning) {
+            Some((StderrKind::Warning, message))
+        } else if matches(&self.progress) {
+            Some((StderrKind::Progress, message))
+        } else {
+            None
+        }
+    }
+}
+
+/// Removes ANSI CSI escape sequences, such as colors and cursor moves.
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            plain.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            // Parameters and intermediates run up to a final byte in `@..=~`.
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_sorts_progress_warnings_and_other_lines() {
+        let patterns = StderrPatterns::default();
+        assert_eq!(
+            patterns.classify("\u{1b}[2K⠋ Thinking…\r⠙ Thinking…\r"),
+            Some((StderrKind::Progress, "⠙ Thinking…".to_string()))
+        );
+        assert_eq!(
+            patterns.classify("\u{1b}[33mWARNING\u{1b}[0m: retrying request"),
+            Some((StderrKind::Warning, "WARNING: retrying request".to_string()))
+        );
+        assert_eq!(patterns.classify("Error: invalid API key"), None);
+        assert_eq!(patterns.classify("  \r "), None);
+    }
+}
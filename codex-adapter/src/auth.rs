@@ -0,0 +1,147 @@
+//! Login state detection for the Codex CLI.
+
+use crate::error::CodexError;
+use crate::types::{AuthState, AuthStatus, RunResult};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Command that starts the interactive Codex login flow.
+pub const LOGIN_COMMAND: &str = "codex login";
+
+/// How long `codex login status` may take before the state is reported as unknown.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lowercase fragments the CLI prints when a run fails for lack of credentials.
+const AUTH_FAILURE_MARKERS: &[&str] = &[
+    "not logged in",
+    "401 unauthorized",
+    "invalid api key",
+    "incorrect api key",
+    "run `codex login`",
+];
+
+/// Reports whether the Codex CLI at `path` has usable credentials.
+///
+/// Runs `codex login status` and parses its output. CLIs that do not answer
+/// within 10 seconds report [`AuthState::Unknown`].
+///
+/// # Errors
+///
+/// Returns `CodexError::SpawnFailed` if the status command cannot be executed.
+pub async fn auth_status(path: &Path) -> Result<AuthStatus, CodexError> {
+    let mut cmd = Command::new(path);
+    cmd.args(["login", "status"])
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    #[cfg(windows)]
+    cmd.creation_flags(0x0800_0000); // CREATE_NO_WINDOW
+
+    let Ok(output) = tokio::time::timeout(STATUS_TIMEOUT, cmd.output()).await else {
+        return Ok(status(AuthState::Unknown, None));
+    };
+    let output = output.map_err(|e| CodexError::SpawnFailed {
+        stage: "login status".to_string(),
+        source: e,
+    })?;
+
+    Ok(parse_auth_status(
+        &String::from_utf8_lossy(&output.stdout),
+        &String::from_utf8_lossy(&output.stderr),
+    ))
+}
+
+/// Parses the output of `codex login status`.
+///
+/// The CLI prints `Logged in using <method>` (optionally followed by
+/// ` - <masked key>`) or `Not logged in`.
+pub(crate) fn parse_auth_status(stdout: &str, stderr: &str) -> AuthStatus {
+    let text = format!("{stdout}\n{stderr}");
+    let lower = text.to_lowercase();
+
+    if lower.contains("not logged in") {
+        return status(AuthState::LoggedOut, None);
+    }
+
+    let method = text.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Logged in using ")
+            .map(|rest| rest.split(" - ").next().unwrap_or(rest).trim().to_string())
+    });
+
+    if method.is_some() || lower.contains("logged in") {
+        status(AuthState::LoggedIn, method)
+    } else {
+        status(AuthState::Unknown, None)
+    }
+}
+
+/// Returns `true` if the output of a failed run indicates missing or rejected credentials.
+pub(crate) fn is_auth_failure(stdout: &str, stderr: &str) -> bool {
+    let stdout = stdout.to_lowercase();
+    let stderr = stderr.to_lowercase();
+    AUTH_FAILURE_MARKERS
+        .iter()
+        .any(|marker| stdout.contains(marker) || stderr.contains(marker))
+}
+
+/// Converts a failed run caused by missing credentials into `CodexError::AuthRequired`.
+pub(crate) fn ensure_authenticated(result: RunResult) -> Result<RunResult, CodexError> {
+    if result.exit_code != 0 && is_auth_failure(&result.stdout, &result.stderr) {
+        return Err(CodexError::AuthRequired {
+            login_command: LOGIN_COMMAND.to_string(),
+        });
+    }
+    Ok(result)
+}
+
+fn status(state: AuthState, method: Option<String>) -> AuthStatus {
+    AuthStatus {
+        state,
+        method,
+        login_command: LOGIN_COMMAND.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::types::LatencyBreakdown;
+
+    #[test]
+    fn test_parse_login_status() {
+        let chatgpt = parse_auth_status("", "Logged in using ChatGPT\n");
+        assert_eq!(chatgpt.state, AuthState::LoggedIn);
+        assert_eq!(chatgpt.method.as_deref(), Some("ChatGPT"));
+
+        let api_key = parse_auth_status("Logged in using an API key - sk-proj-***abcd", "");
+        assert_eq!(api_key.method.as_deref(), Some("an API key"));
+
+        let logged_out = parse_auth_status("", "Not logged in");
+        assert_eq!(logged_out.state, AuthState::LoggedOut);
+        assert_eq!(logged_out.login_command, LOGIN_COMMAND);
+
+        assert_eq!(parse_auth_status("", "").state, AuthState::Unknown);
+    }
+
+    #[test]
+    fn test_ensure_authenticated_maps_auth_failures() {
+        let result = |exit_code, stderr: &str| RunResult {
+            stdout: String::new(),
+            stderr: stderr.to_string(),
+            exit_code,
+            duration_ms: 0,
+            structured_output: None,
+            latency: LatencyBreakdown::default(),
+            event_offsets_ms: Vec::new(),
+        };
+
+        let err = ensure_authenticated(result(1, "error: 401 Unauthorized")).unwrap_err();
+        assert_eq!(err.error_code(), "auth_required");
+
+        assert!(ensure_authenticated(result(1, "sandbox denied")).is_ok());
+        assert!(ensure_authenticated(result(0, "401 Unauthorized")).is_ok());
+    }
+}
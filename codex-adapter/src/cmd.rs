@@ -17,6 +17,7 @@
 //! - `-m, --model <model>`: Model to use (e.g., o4-mini)
 //! - `--search`: Enable live web search capability
 //! - `-c, --config <key=value>`: Override config values
+//! - `--output-schema <file>`: JSON schema the final message must satisfy
 //!
 //! ## Flag Combinations and Compatibility
 //!
@@ -39,6 +40,13 @@
 //! - `--ask-for-approval`: Available in Codex CLI 0.92.0+
 //! - `--sandbox`: Uses Linux Landlock (may have reduced effect on other platforms)
 //! - `--full-auto`: Convenience flag since Codex 0.90.0
+//! - `--output-schema`: Missing from older releases; probe with
+//!   [`CodexCli::supports_output_schema`](crate::CodexCli::supports_output_schema)
+//!
+//! When [`CodexConfig::capabilities`] is set, flags the installed CLI lacks
+//! are gated: `--search` is left out with a warning, and [`check_features`]
+//! rejects configs that need any other missing flag. Capabilities come from
+//! [`probe_capabilities`](crate::probe::probe_capabilities).
 //!
 //! ## Known Limitations
 //! - MCP tools bypass Landlock sandbox restrictions (Codex Issue #4152)
@@ -49,12 +57,75 @@
 //! ## External References
 //! - [Codex CLI Reference](https://developers.openai.com/codex/cli/reference/)
 
-use crate::types::{ApprovalPolicy, CodexConfig, SandboxMode};
+use crate::error::CodexError;
+use crate::types::{ApprovalPolicy, CodexConfig, Feature, SandboxMode};
+use std::collections::BTreeSet;
 use std::ffi::OsString;
+use std::path::Path;
+
+/// Checks that the installed CLI supports every flag `config` needs.
+///
+/// `--search`, which [`build_args`] can leave out, is not checked, and
+/// nothing is when [`CodexConfig::capabilities`] is `None`.
+///
+/// # Errors
+///
+/// Returns `CodexError::UnsupportedFeature` listing every needed feature the
+/// CLI lacks.
+pub fn check_features(config: &CodexConfig) -> Result<(), CodexError> {
+    let Some(capabilities) = &config.capabilities else {
+        return Ok(());
+    };
+    let features: Vec<Feature> = required_features(config)
+        .into_iter()
+        .filter(|feature| !capabilities.supports(*feature))
+        .collect();
+    if features.is_empty() {
+        Ok(())
+    } else {
+        Err(CodexError::UnsupportedFeature {
+            version: config.cli_version.clone(),
+            features,
+        })
+    }
+}
+
+/// Features behind the flags `config` cannot do without.
+fn required_features(config: &CodexConfig) -> BTreeSet<Feature> {
+    let mut features = BTreeSet::new();
+    if let Some(sandbox) = &config.sandbox {
+        features.insert(sandbox.feature());
+    }
+    if config.ask_for_approval.is_some() {
+        features.insert(Feature::AskForApproval);
+    }
+    if config.full_auto {
+        features.insert(Feature::FullAuto);
+    }
+    if config.skip_git_repo_check {
+        features.insert(Feature::SkipGitRepoCheck);
+    }
+    if !config.add_dirs.is_empty() {
+        features.insert(Feature::AddDir);
+    }
+    if config.json_schema.is_some() {
+        features.insert(Feature::OutputSchema);
+    }
+    features
+}
 
 /// Builds the argument list for a Codex CLI invocation.
+///
+/// `output_schema_file` is the file [`CodexConfig::json_schema`] was written
+/// to, passed as `--output-schema`. `--search` is left out with a warning if
+/// the CLI lacks it according to [`CodexConfig::capabilities`]; see
+/// [`check_features`] for the other flags.
 #[must_use]
-pub fn build_args(prompt: &str, config: &CodexConfig) -> Vec<OsString> {
+pub fn build_args(
+    prompt: &str,
+    config: &CodexConfig,
+    output_schema_file: Option<&Path>,
+) -> Vec<OsString> {
     let mut args = Vec::new();
 
     args.push(OsString::from("exec"));
@@ -88,7 +159,18 @@ pub fn build_args(prompt: &str, config: &CodexConfig) -> Vec<OsString> {
     }
 
     if config.search {
-        args.push(OsString::from("--search"));
+        let supported = config
+            .capabilities
+            .as_ref()
+            .is_none_or(|capabilities| capabilities.supports(Feature::Search));
+        if supported {
+            args.push(OsString::from("--search"));
+        } else {
+            tracing::warn!(
+                flag = Feature::Search.flag(),
+                "Codex CLI does not support this flag; leaving it out"
+            );
+        }
     }
 
     if config.skip_git_repo_check {
@@ -110,11 +192,17 @@ pub fn build_args(prompt: &str, config: &CodexConfig) -> Vec<OsString> {
         args.push(OsString::from(format!("{k}={v}")));
     }
 
+    if let Some(file) = output_schema_file {
+        args.push(OsString::from("--output-schema"));
+        args.push(OsString::from(file));
+    }
+
     // Codex has no --system-prompt flag; prepend to the user prompt.
     let effective_prompt = config
         .system_prompt
         .as_ref()
         .map_or_else(|| prompt.to_string(), |sp| format!("{sp}\n\n{prompt}"));
+    args.extend(config.extra_args.iter().cloned());
     args.push(OsString::from(effective_prompt));
 
     args
@@ -138,7 +226,7 @@ mod tests {
             sandbox: Some(SandboxMode::ReadOnly),
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         // Assert --sandbox read-only is present (CONT-04)
@@ -156,7 +244,7 @@ mod tests {
             sandbox: Some(SandboxMode::WorkspaceWrite),
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         // Assert --sandbox workspace-write is present
@@ -174,7 +262,7 @@ mod tests {
             cd: Some(PathBuf::from("/tmp/sandbox")),
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         // Assert --cd is present (working directory isolation)
@@ -189,7 +277,7 @@ mod tests {
     #[test]
     fn test_full_auto_not_set_by_default() {
         let config = CodexConfig::default();
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         // Assert --full-auto is NOT present (CONT-03 audit: full_auto bypasses containment)
@@ -205,7 +293,7 @@ mod tests {
             skip_git_repo_check: true,
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         // Assert --skip-git-repo-check present (needed for temp dir containment)
@@ -225,7 +313,7 @@ mod tests {
             full_auto: false, // explicit false to document containment posture
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         // Assert all containment flags present and --full-auto absent
@@ -265,7 +353,7 @@ mod tests {
             ask_for_approval: Some(ApprovalPolicy::Untrusted),
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         assert!(
@@ -282,7 +370,7 @@ mod tests {
             ask_for_approval: Some(ApprovalPolicy::Never),
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         assert!(
@@ -301,7 +389,7 @@ mod tests {
             ask_for_approval: Some(ApprovalPolicy::Untrusted),
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         assert!(
@@ -329,7 +417,7 @@ mod tests {
             full_auto: false,
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         // Verify complete containment posture
@@ -372,7 +460,7 @@ mod tests {
             full_auto: true, // This overrides the above at CLI level!
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         // We still generate all flags (the API doesn't prevent conflicts)
@@ -402,7 +490,7 @@ mod tests {
             ask_for_approval: Some(ApprovalPolicy::OnFailure),
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         assert!(
@@ -419,7 +507,7 @@ mod tests {
             ask_for_approval: Some(ApprovalPolicy::OnRequest),
             ..CodexConfig::default()
         };
-        let args = build_args("test prompt", &config);
+        let args = build_args("test prompt", &config, None);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
 
         assert!(
@@ -435,4 +523,70 @@ mod tests {
         // Verify ApprovalPolicy::default() returns Untrusted (locked decision)
         assert_eq!(ApprovalPolicy::default(), ApprovalPolicy::Untrusted);
     }
+
+    #[test]
+    fn test_extra_args_precede_prompt() {
+        let config = CodexConfig {
+            model: Some("o4-mini".to_string()),
+            extra_args: vec!["--oss".into()],
+            ..CodexConfig::default()
+        };
+        let args = build_args("test prompt", &config, None);
+        let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
+        assert_eq!(args_str[args_str.len() - 2..], ["--oss", "test prompt"]);
+    }
+
+    #[test]
+    fn test_output_schema_flag() {
+        let config = CodexConfig::default();
+        let args = build_args("test prompt", &config, Some(Path::new("/tmp/schema.json")));
+        let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
+        assert!(
+            args_str
+                .windows(2)
+                .any(|w| w[0] == "--output-schema" && w[1] == "/tmp/schema.json"),
+            "Expected '--output-schema /tmp/schema.json' but got: {args_str:?}",
+        );
+
+        let args = build_args("test prompt", &config, None);
+        assert!(!args.iter().any(|a| a == "--output-schema"));
+    }
+
+    #[test]
+    fn test_unsupported_flags_are_gated_by_capabilities() {
+        let config = CodexConfig {
+            search: true,
+            sandbox: Some(SandboxMode::ReadOnly),
+            capabilities: Some(crate::types::Capabilities {
+                features: std::iter::once(Feature::SandboxReadOnly).collect(),
+            }),
+            ..CodexConfig::default()
+        };
+        check_features(&config).unwrap();
+        let args = build_args("test prompt", &config, None);
+        assert!(!args.iter().any(|a| a == "--search"));
+        assert!(args.iter().any(|a| a == "--sandbox"));
+
+        let config = CodexConfig {
+            sandbox: Some(SandboxMode::DangerFullAccess),
+            add_dirs: vec![PathBuf::from("/srv/data")],
+            cli_version: Some(semver::Version::new(0, 40, 0)),
+            ..config
+        };
+        let err = check_features(&config).unwrap_err();
+        assert_eq!(err.error_code(), "unsupported_feature");
+        assert_eq!(
+            err.to_string(),
+            "Codex CLI 0.40.0 does not support --sandbox danger-full-access, --add-dir"
+        );
+
+        let config = CodexConfig {
+            capabilities: None,
+            ..config
+        };
+        check_features(&config).unwrap();
+        assert!(build_args("test prompt", &config, None)
+            .iter()
+            .any(|a| a == "--search"));
+    }
 }
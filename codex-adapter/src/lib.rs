@@ -5,21 +5,28 @@
 
 #![warn(missing_docs)]
 
+/// Login state detection and auth-failure classification.
+pub mod auth;
 /// Command-line argument building utilities.
 pub mod cmd;
 /// Codex binary discovery on the host system.
 pub mod discovery;
 /// Error types for the adapter.
 pub mod error;
+/// Feature detection from the CLI's help text.
+pub mod probe;
 /// Subprocess execution and lifecycle management.
 pub mod process;
 /// Shared configuration and result types.
 pub mod types;
 
+use std::borrow::Cow;
 use tokio::process::Command;
 
+pub use auth::auth_status;
 pub use discovery::discover_codex;
 pub use error::CodexError;
+pub use probe::probe_capabilities;
 pub use process::run_codex;
 pub use types::*;
 
@@ -28,13 +35,35 @@ pub use types::*;
 pub struct CodexCli {
     /// Filesystem path to the Codex executable.
     pub path: std::path::PathBuf,
+    /// Features of the CLI, which runs gate their flags on. `None` emits
+    /// every flag unchecked.
+    pub capabilities: Option<types::Capabilities>,
 }
 
 impl CodexCli {
     /// Creates a new `CodexCli` pointing at the given binary path.
     #[must_use]
     pub const fn new(path: std::path::PathBuf) -> Self {
-        Self { path }
+        Self {
+            path,
+            capabilities: None,
+        }
+    }
+
+    /// Sets the capabilities runs gate their flags on, usually from
+    /// [`probe_capabilities`](Self::probe_capabilities).
+    #[must_use]
+    pub fn with_capabilities(mut self, capabilities: types::Capabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Detects the CLI's features from its help text.
+    ///
+    /// # Errors
+    /// Returns `CodexError::SpawnFailed` if a help command cannot be run.
+    pub async fn probe_capabilities(&self) -> Result<types::Capabilities, CodexError> {
+        probe_capabilities(&self.path).await
     }
 
     /// Checks if the Codex CLI is working correctly.
@@ -60,28 +89,92 @@ impl CodexCli {
         }
     }
 
+    /// Reports whether `codex exec` accepts `--output-schema`, which
+    /// [`CodexConfig::json_schema`](types::CodexConfig::json_schema) needs.
+    ///
+    /// Uses the capabilities set on the handle, and probes the help text
+    /// otherwise; returns `false` when it cannot be read.
+    pub async fn supports_output_schema(&self) -> bool {
+        match &self.capabilities {
+            Some(capabilities) => capabilities.supports(types::Feature::OutputSchema),
+            None => self
+                .probe_capabilities()
+                .await
+                .is_ok_and(|capabilities| capabilities.supports(types::Feature::OutputSchema)),
+        }
+    }
+
+    /// Reports whether the CLI is logged in.
+    ///
+    /// # Errors
+    /// Returns an error if the status command cannot be executed.
+    pub async fn auth_status(&self) -> Result<types::AuthStatus, CodexError> {
+        auth_status(&self.path).await
+    }
+
     /// Runs the Codex CLI to completion and returns the result.
     ///
     /// # Errors
-    /// Returns an error if the process fails to spawn, times out, or produces truncated output.
+    /// Returns an error if the process fails to spawn, times out, produces truncated output,
+    /// or is rejected for missing credentials.
     pub async fn run(
         &self,
         prompt: &str,
         config: &types::CodexConfig,
     ) -> Result<types::RunResult, CodexError> {
-        run_codex(&self.path, prompt, config, None).await
+        run_codex(&self.path, prompt, &self.gated(config), None).await
+    }
+
+    /// Synchronous [`run`](Self::run) on a runtime of its own, with the
+    /// `blocking` feature.
+    ///
+    /// # Errors
+    /// Returns the errors of [`run`](Self::run), and
+    /// `CodexError::SpawnFailed` if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn run_blocking(
+        &self,
+        prompt: &str,
+        config: &types::CodexConfig,
+    ) -> Result<types::RunResult, CodexError> {
+        rig_cli_process_core::blocking::block_on(self.run(prompt, config)).map_err(|source| {
+            CodexError::SpawnFailed {
+                stage: "blocking runtime".to_string(),
+                source,
+            }
+        })?
+    }
+
+    /// Builds the command [`run`](Self::run) would spawn, without spawning it.
+    #[must_use]
+    pub fn dry_run(&self, prompt: &str, config: &types::CodexConfig) -> types::Invocation {
+        process::dry_run(&self.path, prompt, &self.gated(config))
     }
 
     /// Runs the Codex CLI, streaming events through `sender` as they arrive.
     ///
     /// # Errors
-    /// Returns an error if the process fails to spawn, times out, or produces truncated output.
+    /// Returns an error if the process fails to spawn, times out, produces truncated output,
+    /// or is rejected for missing credentials.
     pub async fn stream(
         &self,
         prompt: &str,
         config: &types::CodexConfig,
         sender: tokio::sync::mpsc::Sender<types::StreamEvent>,
     ) -> Result<types::RunResult, CodexError> {
-        run_codex(&self.path, prompt, config, Some(sender)).await
+        run_codex(&self.path, prompt, &self.gated(config), Some(sender)).await
+    }
+
+    /// Fills in the handle's capabilities where `config` has none.
+    fn gated<'a>(&self, config: &'a types::CodexConfig) -> Cow<'a, types::CodexConfig> {
+        match &self.capabilities {
+            Some(capabilities) if config.capabilities.is_none() => {
+                Cow::Owned(types::CodexConfig {
+                    capabilities: Some(capabilities.clone()),
+                    ..config.clone()
+                })
+            }
+            _ => Cow::Borrowed(config),
+        }
     }
 }
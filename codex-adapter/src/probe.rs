@@ -0,0 +1,187 @@
+//! Feature detection from the Codex CLI's help text.
+//!
+//! `codex exec` flags come and go between releases, so
+//! [`probe_capabilities`] reads `codex --help` and `codex exec --help` and
+//! records which of the flags the adapter emits are there. Passing the
+//! result as [`CodexConfig::capabilities`](crate::types::CodexConfig::capabilities)
+//! gates [`build_args`](crate::cmd::build_args) on it.
+
+use crate::error::CodexError;
+use crate::types::{Capabilities, Feature};
+use std::collections::BTreeSet;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Help text fragments each feature is detected by.
+const FEATURE_PATTERNS: &[(Feature, &str)] = &[
+    (Feature::Json, "--json"),
+    (Feature::OutputSchema, "--output-schema"),
+    (Feature::Search, "--search"),
+    (Feature::AskForApproval, "--ask-for-approval"),
+    (Feature::FullAuto, "--full-auto"),
+    (Feature::AddDir, "--add-dir"),
+    (Feature::SkipGitRepoCheck, "--skip-git-repo-check"),
+];
+
+/// Sandbox modes by their name in `--sandbox`'s possible values.
+const SANDBOX_MODES: &[(Feature, &str)] = &[
+    (Feature::SandboxReadOnly, "read-only"),
+    (Feature::SandboxWorkspaceWrite, "workspace-write"),
+    (Feature::SandboxDangerFullAccess, "danger-full-access"),
+];
+
+/// Detects the features of the Codex CLI at `path` from its help text.
+///
+/// # Errors
+/// Returns `CodexError::SpawnFailed` if a help command cannot be run.
+pub async fn probe_capabilities(path: &Path) -> Result<Capabilities, CodexError> {
+    let help = help_text(path, &["--help"]).await?;
+    let exec_help = help_text(path, &["exec", "--help"]).await?;
+    Ok(parse_capabilities(&help, &exec_help))
+}
+
+/// Builds the capabilities described by the top-level and `exec` help.
+///
+/// Flags count wherever they appear, as some releases document shared
+/// options only at the top level. When `--sandbox` lists no possible
+/// values, every mode is assumed.
+#[must_use]
+pub fn parse_capabilities(help: &str, exec_help: &str) -> Capabilities {
+    let mut features: BTreeSet<Feature> = FEATURE_PATTERNS
+        .iter()
+        .filter(|(_, pattern)| help.contains(pattern) || exec_help.contains(pattern))
+        .map(|(feature, _)| *feature)
+        .collect();
+    if has_subcommand(exec_help, "resume") {
+        features.insert(Feature::Resume);
+    }
+    if let Some(values) = sandbox_values(exec_help).or_else(|| sandbox_values(help)) {
+        features.extend(
+            SANDBOX_MODES
+                .iter()
+                .filter(|(_, name)| values.is_empty() || values.contains(name))
+                .map(|(feature, _)| *feature),
+        );
+    }
+    Capabilities { features }
+}
+
+/// Runs the CLI with `args` and returns its stdout.
+async fn help_text(path: &Path, args: &[&str]) -> Result<String, CodexError> {
+    let output =
+        Command::new(path)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| CodexError::SpawnFailed {
+                stage: "capability probe".to_string(),
+                source: e,
+            })?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Whether `help` lists `name` under its commands.
+fn has_subcommand(help: &str, name: &str) -> bool {
+    help.lines()
+        .any(|line| line.split_whitespace().next() == Some(name) && line.starts_with("  "))
+}
+
+/// The possible values documented for `--sandbox`, empty if none are
+/// listed, or `None` if `help` has no `--sandbox` flag.
+fn sandbox_values(help: &str) -> Option<Vec<&str>> {
+    let mut lines = help.lines().skip_while(|line| !declares(line, "--sandbox"));
+    let mut section = vec![lines.next()?];
+    section.extend(lines.take_while(|line| !line.trim_start().starts_with('-')));
+    let values = section
+        .iter()
+        .find_map(|line| line.split_once("[possible values:"))
+        .map(|(_, rest)| {
+            rest.trim_end_matches(|c: char| c == ']' || c.is_whitespace())
+                .split(',')
+                .map(str::trim)
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(values)
+}
+
+/// Whether `line` is the entry of `flag` rather than prose mentioning it.
+fn declares(line: &str, flag: &str) -> bool {
+    line.split_whitespace()
+        .take_while(|token| token.starts_with('-') || token.starts_with('<'))
+        .any(|token| token.trim_end_matches(',') == flag)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    const HELP: &str = "\
+Codex CLI
+
+Usage: codex [OPTIONS] [PROMPT]
+
+Commands:
+  exec        Run Codex non-interactively
+  login       Manage login
+
+Options:
+  -a, --ask-for-approval <APPROVAL_POLICY>
+          Configure when the model requires human approval
+      --search
+          Enable web search
+";
+
+    const EXEC_HELP: &str = "\
+Run Codex non-interactively
+
+Usage: codex exec [OPTIONS] [PROMPT] [COMMAND]
+
+Commands:
+  resume  Resume a previous session by id or pick the most recent with --last
+
+Options:
+  -s, --sandbox <SANDBOX_MODE>
+          Select the sandbox policy to use when executing model-generated shell commands
+
+          [possible values: read-only, workspace-write]
+
+      --full-auto
+          Convenience alias for low-friction sandboxed automatic execution (-a on-request,
+          --sandbox workspace-write)
+      --skip-git-repo-check
+          Allow running Codex outside a Git repository
+      --json
+          Print events to stdout as JSONL
+";
+
+    #[test]
+    fn test_parse_capabilities_reads_both_help_texts() {
+        let capabilities = parse_capabilities(HELP, EXEC_HELP);
+        assert_eq!(
+            capabilities.features.into_iter().collect::<Vec<_>>(),
+            [
+                Feature::Json,
+                Feature::Resume,
+                Feature::Search,
+                Feature::SandboxReadOnly,
+                Feature::SandboxWorkspaceWrite,
+                Feature::AskForApproval,
+                Feature::FullAuto,
+                Feature::SkipGitRepoCheck,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sandbox_without_possible_values_allows_every_mode() {
+        let capabilities = parse_capabilities("", "  -s, --sandbox <MODE>\n      --json\n");
+        assert!(capabilities.supports(Feature::SandboxDangerFullAccess));
+        assert!(capabilities.supports(Feature::SandboxReadOnly));
+
+        let capabilities = parse_capabilities("", "      --json\n");
+        assert!(!capabilities.supports(Feature::SandboxReadOnly));
+        assert!(!capabilities.supports(Feature::Resume));
+    }
+}
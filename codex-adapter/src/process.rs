@@ -1,262 +1,318 @@
 //! Subprocess execution and lifecycle management for the Codex CLI.
 
 use crate::error::CodexError;
-use crate::types::{CodexConfig, RunResult};
-use std::process::Stdio;
-use std::time::{Duration, Instant};
-use tokio::io::{AsyncBufReadExt, BufReader};
+use crate::types::{CodexConfig, RunResult, StderrKind, StreamEvent, StreamParsers};
+use rig_cli_process_core::{
+    check_extra_args, ConfigFile, EnvPolicy, EventSink, Invocation, LineParser, ProcessRunner,
+};
 use tokio::process::Command;
-use tokio::sync::mpsc;
-use tokio::task::JoinSet;
-use tokio::time::timeout;
 
-const MAX_OUTPUT_BYTES: usize = 10 * 1024 * 1024; // 10 MB
-const GRACE_PERIOD: Duration = Duration::from_secs(5);
-
-/// Collected output lines, total byte count, and whether truncation occurred.
-type StreamOutput = (Vec<String>, usize, bool);
-
-/// Result of collecting all subprocess output: stdout lines, stderr lines, and exit status.
-type CollectedOutput = (Vec<String>, Vec<String>, std::process::ExitStatus);
-
-/// Timeout-wrapped result of the full collection phase.
-type TimedCollectionResult =
-    Result<Result<CollectedOutput, CodexError>, tokio::time::error::Elapsed>;
+/// Lowercase fragments of the trust and onboarding questions a fresh install
+/// asks before its first run.
+const INTERACTIVE_PROMPT_MARKERS: &[&str] = &[
+    "allow codex to work in this folder",
+    "do you trust the contents of this directory",
+    "sign in with chatgpt",
+];
 
 /// Spawns the Codex CLI and collects its output, optionally streaming events.
 ///
 /// # Errors
 /// Returns a [`CodexError`] if the process cannot be spawned, times out,
-/// produces truncated output, or encounters an I/O failure.
+/// produces truncated output, or encounters an I/O failure. A non-zero exit
+/// whose output reports missing or rejected credentials is returned as
+/// [`CodexError::AuthRequired`], and a trust or onboarding prompt with no
+/// configured answer as [`CodexError::InteractivePromptDetected`]. An entry
+/// of `config.extra_args` that repeats a generated flag fails the run with
+/// [`CodexError::ConflictingArg`] before anything is spawned. So does a
+/// config needing flags the CLI lacks, with [`CodexError::UnsupportedFeature`].
+///
+/// With [`CodexConfig::json_schema`] set, the schema is written to a temp
+/// file for `--output-schema` and the final message is parsed into
+/// [`RunResult::structured_output`].
 pub async fn run_codex(
     path: &std::path::Path,
     prompt: &str,
     config: &CodexConfig,
     sender: Option<tokio::sync::mpsc::Sender<crate::types::StreamEvent>>,
 ) -> Result<RunResult, CodexError> {
-    let args = crate::cmd::build_args(prompt, config);
-    let start_time = Instant::now();
-
-    let mut child = spawn_child(path, &args, config)?;
-
-    let stdout = child.stdout.take().ok_or(CodexError::NoStdout)?;
-    let stderr = child.stderr.take().ok_or(CodexError::NoStderr)?;
-    let pid = child.id().ok_or(CodexError::NoPid)?;
-
-    let mut tasks = JoinSet::new();
-
-    // Stdout reader task
-    tasks.spawn(async move { drain_stream_bounded(stdout, sender, "stdout").await });
+    crate::cmd::check_features(config)?;
+    let schema_file = config
+        .json_schema
+        .as_ref()
+        .map(write_schema_file)
+        .transpose()?;
+    let args = crate::cmd::build_args(
+        prompt,
+        config,
+        schema_file.as_ref().map(tempfile::NamedTempFile::path),
+    );
+    check_extra_args(&args, &config.extra_args)?;
+    let events = sender.map(|tx| EventSink::new(tx, config.backpressure));
+
+    let output = ProcessRunner::new(build_command(path, &args, config))
+        .limits(config.output_limits)
+        .timeout(config.timeout)
+        .spawn_timeout(config.spawn_timeout)
+        .first_output_timeout(config.first_output_timeout)
+        .log_sink(config.log_sink.as_deref())
+        .resource_limits(config.resource_limits)
+        .stall(config.stall_timeout, &config.on_stall)
+        .stderr_events(config.stderr_events.as_ref())
+        .forward_signals(config.forward_signals)
+        .prompts(&config.interactive_prompts, INTERACTIVE_PROMPT_MARKERS)
+        .run(
+            &mut JsonLinesParser {
+                parsers: &config.parsers,
+                version: config.cli_version.as_ref(),
+            },
+            events,
+        )
+        .await?;
+
+    let mut result = crate::auth::ensure_authenticated(RunResult {
+        duration_ms: output.duration_ms(),
+        event_offsets_ms: output.event_offsets_ms(),
+        latency: output.latency,
+        stdout: output.stdout,
+        stderr: output.stderr,
+        exit_code: output.exit_code,
+        structured_output: None,
+    })?;
+    if config.json_schema.is_some() {
+        result.structured_output = parse_structured_output(&result.stdout);
+    }
+    Ok(result)
+}
 
-    // Stderr reader task
-    tasks.spawn(async move { drain_stream_bounded(stderr, None, "stderr").await });
+/// Builds the command [`run_codex`] would spawn, without spawning it.
+///
+/// MCP servers are configured through `-c` overrides, so they show up in
+/// [`Invocation::args`] rather than as config files. A
+/// [`json_schema`](CodexConfig::json_schema) is reported in
+/// [`Invocation::config_files`] under a placeholder path. `extra_args` are
+/// included without checking them for conflicts.
+#[must_use]
+pub fn dry_run(path: &std::path::Path, prompt: &str, config: &CodexConfig) -> Invocation {
+    let schema_file = config.json_schema.as_ref().map(|schema| ConfigFile {
+        path: std::env::temp_dir().join("rig_codex_schema_XXXXXX.json"),
+        contents: schema.to_string(),
+    });
+    let args = crate::cmd::build_args(
+        prompt,
+        config,
+        schema_file.as_ref().map(|f| f.path.as_path()),
+    );
+    let mut invocation = Invocation::from_command(
+        &build_command(path, &args, config),
+        config.env_policy == EnvPolicy::Inherit,
+    );
+    invocation.config_files.extend(schema_file);
+    invocation
+}
 
-    let process_result = timeout(config.timeout, collect_output(&mut child, &mut tasks)).await;
-    let duration = start_time.elapsed();
+/// Writes `schema` to a temp file for `--output-schema`.
+fn write_schema_file(schema: &serde_json::Value) -> Result<tempfile::NamedTempFile, CodexError> {
+    let file = tempfile::Builder::new()
+        .prefix("rig_codex_schema_")
+        .suffix(".json")
+        .tempfile()
+        .map_err(|e| CodexError::SpawnFailed {
+            stage: "output schema temp file creation".to_string(),
+            source: e,
+        })?;
+    std::fs::write(file.path(), schema.to_string()).map_err(|e| CodexError::SpawnFailed {
+        stage: "output schema temp file write".to_string(),
+        source: e,
+    })?;
+    Ok(file)
+}
 
-    build_run_result(process_result, &mut child, pid, &mut tasks, duration).await
+/// Parses the final message `codex exec` printed on stdout as JSON, falling
+/// back to its last line when anything precedes it.
+fn parse_structured_output(stdout: &str) -> Option<serde_json::Value> {
+    let trimmed = stdout.trim();
+    serde_json::from_str(trimmed).ok().or_else(|| {
+        trimmed
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .and_then(|line| serde_json::from_str(line.trim()).ok())
+    })
 }
 
-/// Spawns the Codex child process with piped stdout/stderr.
-fn spawn_child(
+/// Builds the Codex command with its working directory and environment.
+fn build_command(
     path: &std::path::Path,
     args: &[std::ffi::OsString],
     config: &CodexConfig,
-) -> Result<tokio::process::Child, CodexError> {
+) -> Command {
     let mut cmd = Command::new(path);
-    cmd.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+    cmd.args(args);
 
     if let Some(ref dir) = config.cd {
         cmd.current_dir(dir);
     }
 
+    config.env_policy.apply(&mut cmd);
     for (k, v) in &config.env_vars {
         cmd.env(k, v);
     }
 
-    cmd.spawn().map_err(|e| CodexError::SpawnFailed {
-        stage: "spawn".to_string(),
-        source: e,
-    })
+    cmd
 }
 
-/// Collects stdout and stderr output from reader tasks and waits for the child.
-async fn collect_output(
-    child: &mut tokio::process::Child,
-    tasks: &mut JoinSet<StreamOutput>,
-) -> Result<CollectedOutput, CodexError> {
-    let mut stdout_lines = Vec::new();
-    let mut stderr_lines = Vec::new();
-    let mut results_received = 0u8;
-
-    while let Some(result) = tasks.join_next().await {
-        let (lines, _bytes, truncated) = result.map_err(|e| CodexError::StreamFailed {
-            stage: "join".to_string(),
-            source: e,
-        })?;
-
-        if truncated {
-            let captured: usize = lines.iter().map(String::len).sum();
-            return Err(CodexError::OutputTruncated {
-                captured_bytes: captured,
-                limit_bytes: MAX_OUTPUT_BYTES,
-            });
-        }
-
-        // First result is stdout (spawned first), second is stderr.
-        if results_received == 0 {
-            stdout_lines = lines;
-        } else {
-            stderr_lines = lines;
-        }
-        results_received += 1;
-    }
-
-    let status = child.wait().await.map_err(|e| CodexError::SpawnFailed {
-        stage: "wait".to_string(),
-        source: e,
-    })?;
-
-    Ok((stdout_lines, stderr_lines, status))
+/// Parses `--json` output lines into [`StreamEvent`]s. Lines that are not
+/// JSON are forwarded as text.
+struct JsonLinesParser<'a> {
+    parsers: &'a StreamParsers,
+    version: Option<&'a semver::Version>,
 }
 
-/// Converts the raw process outcome into a [`RunResult`] or an appropriate error.
-async fn build_run_result(
-    process_result: TimedCollectionResult,
-    child: &mut tokio::process::Child,
-    pid: u32,
-    tasks: &mut JoinSet<StreamOutput>,
-    duration: Duration,
-) -> Result<RunResult, CodexError> {
-    let duration_ms = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+impl LineParser for JsonLinesParser<'_> {
+    type Event = StreamEvent;
+
+    fn parse(&mut self, line: &str) -> Vec<StreamEvent> {
+        serde_json::from_str::<serde_json::Value>(line).map_or_else(
+            |_| {
+                vec![StreamEvent::Text {
+                    text: format!("{line}\n"),
+                }]
+            },
+            |val| self.parsers.parse(self.version, &val).unwrap_or_default(),
+        )
+    }
 
-    match process_result {
-        Ok(Ok((stdout_lines, stderr_lines, status))) => Ok(RunResult {
-            stdout: stdout_lines.join("\n"),
-            stderr: stderr_lines.join("\n"),
-            exit_code: status.code().unwrap_or(-1),
-            duration_ms,
-        }),
-        Ok(Err(e)) => Err(e),
-        Err(_) => {
-            // Timeout occurred -- graceful shutdown
-            let _ = graceful_shutdown(child, pid, tasks).await;
+    fn stalled(&mut self, idle: std::time::Duration) -> Vec<StreamEvent> {
+        vec![StreamEvent::Stalled {
+            idle_ms: u64::try_from(idle.as_millis()).unwrap_or(u64::MAX),
+        }]
+    }
 
-            Err(CodexError::Timeout {
-                elapsed: duration,
-                pid,
-                partial_stdout: String::new(),
-                partial_stderr: String::new(),
-            })
-        }
+    fn stderr(&mut self, kind: StderrKind, message: &str) -> Vec<StreamEvent> {
+        let message = message.to_string();
+        vec![match kind {
+            StderrKind::Progress => StreamEvent::Progress { message },
+            StderrKind::Warning => StreamEvent::Warning { message },
+        }]
     }
 }
 
-/// Drains a stream with bounded accumulation and optional JSONL parsing for `StreamEvent`.
-async fn drain_stream_bounded(
-    stream: impl tokio::io::AsyncRead + Unpin,
-    event_tx: Option<mpsc::Sender<crate::types::StreamEvent>>,
-    _stage: &str,
-) -> StreamOutput {
-    let mut reader = BufReader::new(stream).lines();
-    let mut lines = Vec::new();
-    let mut total_bytes = 0usize;
-    let mut truncated = false;
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use rig_cli_process_core::{classify_line, InteractivePromptPolicy, PromptAction};
+
+    #[test]
+    fn test_markers_detect_onboarding_prompts() {
+        let policy = InteractivePromptPolicy::default();
+        assert_eq!(
+            classify_line(
+                "Allow Codex to work in this folder without asking?",
+                &policy,
+                INTERACTIVE_PROMPT_MARKERS
+            ),
+            PromptAction::Reject
+        );
+        assert_eq!(
+            classify_line("done", &policy, INTERACTIVE_PROMPT_MARKERS),
+            PromptAction::Ignore
+        );
+    }
 
-    loop {
-        let Ok(Some(line)) = reader.next_line().await else {
-            break;
+    #[test]
+    fn test_json_lines_parser() {
+        let parsers = crate::types::builtin_parsers();
+        let mut parser = JsonLinesParser {
+            parsers: &parsers,
+            version: None,
         };
-
-        // Forward to event sender if configured.
-        if let Some(ref tx) = event_tx {
-            if let Ok(val) = serde_json::from_str::<serde_json::Value>(&line) {
-                if let Ok(event) = serde_json::from_value::<crate::types::StreamEvent>(val) {
-                    let _ = tx.send(event).await;
-                }
-            } else {
-                let _ = tx
-                    .send(crate::types::StreamEvent::Text {
-                        text: line.clone() + "\n",
-                    })
-                    .await;
-            }
-        }
-
-        let line_bytes = line.len();
-        if total_bytes + line_bytes <= MAX_OUTPUT_BYTES {
-            lines.push(line);
-            total_bytes += line_bytes;
-        } else {
-            truncated = true;
-        }
+        assert!(matches!(
+            &parser.parse("plain output")[..],
+            [StreamEvent::Text { text }] if text == "plain output\n"
+        ));
+        assert!(matches!(
+            &parser.parse(r#"{"type":"text","text":"hi"}"#)[..],
+            [StreamEvent::Text { text }] if text == "hi"
+        ));
+        assert!(parser.parse(r#"{"type":"turn.started"}"#).is_empty());
     }
 
-    (lines, total_bytes, truncated)
-}
-
-/// Graceful shutdown: `SIGTERM`, wait grace period, then `SIGKILL`.
-#[cfg(unix)]
-async fn graceful_shutdown(
-    child: &mut tokio::process::Child,
-    pid: u32,
-    tasks: &mut JoinSet<StreamOutput>,
-) -> Result<(), CodexError> {
-    use nix::sys::signal::{self, Signal};
-    use nix::unistd::Pid;
+    #[test]
+    fn test_dry_run_reports_args_and_cwd() {
+        let config = CodexConfig {
+            cd: Some("/tmp/work".into()),
+            env_policy: EnvPolicy::Allowlist(Vec::new()),
+            ..CodexConfig::default()
+        };
+        let invocation = dry_run(std::path::Path::new("/usr/bin/codex"), "hello", &config);
+
+        assert_eq!(
+            invocation.program,
+            std::path::PathBuf::from("/usr/bin/codex")
+        );
+        assert_eq!(invocation.args.first().map(String::as_str), Some("exec"));
+        assert_eq!(invocation.args.last().map(String::as_str), Some("hello"));
+        assert_eq!(invocation.cwd, Some("/tmp/work".into()));
+        assert!(!invocation.inherits_env);
+        assert_eq!(invocation.env, Vec::<(String, String)>::new());
+    }
 
-    let raw_pid = i32::try_from(pid).map_err(|_| CodexError::SignalFailed {
-        signal: "SIGTERM".to_string(),
-        pid,
-        reason: "PID value exceeds i32::MAX".to_string(),
-    })?;
-    let nix_pid = Pid::from_raw(raw_pid);
+    #[tokio::test]
+    async fn test_run_rejects_conflicting_extra_args() {
+        let config = CodexConfig {
+            model: Some("o4-mini".to_string()),
+            extra_args: vec!["--model=o3".into()],
+            ..CodexConfig::default()
+        };
+        // Rejected before spawning, so the executable need not exist.
+        let err = run_codex(
+            std::path::Path::new("/nonexistent/codex"),
+            "hi",
+            &config,
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(&err, CodexError::ConflictingArg { flag } if flag == "--model"),
+            "{err}"
+        );
+        assert_eq!(err.error_code(), "validation");
+    }
 
-    signal::kill(nix_pid, Signal::SIGTERM).map_err(|e| CodexError::SignalFailed {
-        signal: "SIGTERM".to_string(),
-        pid,
-        reason: e.to_string(),
-    })?;
+    #[test]
+    fn test_dry_run_reports_output_schema_file() {
+        let schema = serde_json::json!({"type": "object"});
+        let config = CodexConfig {
+            json_schema: Some(schema.clone()),
+            ..CodexConfig::default()
+        };
+        let invocation = dry_run(std::path::Path::new("/usr/bin/codex"), "hello", &config);
 
-    match timeout(GRACE_PERIOD, child.wait()).await {
-        Ok(Ok(_status)) => {}
-        Ok(Err(e)) => {
-            return Err(CodexError::SpawnFailed {
-                stage: "graceful_shutdown wait".to_string(),
-                source: e,
-            });
-        }
-        Err(_) => {
-            child.kill().await.map_err(|e| CodexError::SpawnFailed {
-                stage: "SIGKILL".to_string(),
-                source: e,
-            })?;
-            child.wait().await.map_err(|e| CodexError::SpawnFailed {
-                stage: "post-SIGKILL wait".to_string(),
-                source: e,
-            })?;
-        }
+        let [file] = invocation.config_files.as_slice() else {
+            panic!("expected one config file: {:?}", invocation.config_files);
+        };
+        assert_eq!(file.contents, schema.to_string());
+        let flag = invocation
+            .args
+            .iter()
+            .position(|a| a == "--output-schema")
+            .unwrap();
+        assert_eq!(invocation.args[flag + 1], file.path.display().to_string());
     }
 
-    tasks.abort_all();
-    Ok(())
-}
-
-/// Windows: immediate termination, no graceful shutdown for console processes.
-#[cfg(windows)]
-async fn graceful_shutdown(
-    child: &mut tokio::process::Child,
-    _pid: u32,
-    tasks: &mut JoinSet<StreamOutput>,
-) -> Result<(), CodexError> {
-    child.kill().await.map_err(|e| CodexError::SpawnFailed {
-        stage: "TerminateProcess".to_string(),
-        source: e,
-    })?;
-    child.wait().await.map_err(|e| CodexError::SpawnFailed {
-        stage: "post-kill wait".to_string(),
-        source: e,
-    })?;
-    tasks.abort_all();
-    Ok(())
+    #[test]
+    fn test_parse_structured_output() {
+        assert_eq!(
+            parse_structured_output("{\"total\": 3}\n"),
+            Some(serde_json::json!({"total": 3}))
+        );
+        assert_eq!(
+            parse_structured_output("Done.\n{\"total\": 3}\n\n"),
+            Some(serde_json::json!({"total": 3}))
+        );
+        assert_eq!(parse_structured_output("no json here"), None);
+    }
 }
@@ -1,9 +1,18 @@
 //! Shared configuration, result, and streaming types.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ffi::OsString;
 use std::path::PathBuf;
 use std::time::Duration;
 
+pub use rig_cli_process_core::{
+    AuthState, AuthStatus, BackpressurePolicy, ConfigFile, EnvPolicy, InteractivePromptPolicy,
+    Invocation, LatencyBreakdown, OutputLimits, OverflowPolicy, ParserRegistry, PromptResponse,
+    RedactionPolicy, ResourceLimits, StallAction, StderrKind, StderrPatterns, StreamParser,
+    TimeoutStage,
+};
+
 /// Sandbox isolation level for the Codex subprocess.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum SandboxMode {
@@ -36,8 +45,85 @@ pub enum ApprovalPolicy {
     Never,
 }
 
+/// A Codex CLI flag or subcommand that not every release has.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Feature {
+    /// JSON Lines event output.
+    Json,
+    /// The `--output-schema` flag.
+    OutputSchema,
+    /// The `exec resume` subcommand.
+    Resume,
+    /// The `--search` flag.
+    Search,
+    /// The `read-only` sandbox mode.
+    SandboxReadOnly,
+    /// The `workspace-write` sandbox mode.
+    SandboxWorkspaceWrite,
+    /// The `danger-full-access` sandbox mode.
+    SandboxDangerFullAccess,
+    /// The `--ask-for-approval` flag.
+    AskForApproval,
+    /// The `--full-auto` flag.
+    FullAuto,
+    /// The `--add-dir` flag.
+    AddDir,
+    /// The `--skip-git-repo-check` flag.
+    SkipGitRepoCheck,
+}
+
+impl Feature {
+    /// The CLI flag the feature stands for.
+    #[must_use]
+    pub const fn flag(self) -> &'static str {
+        match self {
+            Self::Json => "--json",
+            Self::OutputSchema => "--output-schema",
+            Self::Resume => "exec resume",
+            Self::Search => "--search",
+            Self::SandboxReadOnly => "--sandbox read-only",
+            Self::SandboxWorkspaceWrite => "--sandbox workspace-write",
+            Self::SandboxDangerFullAccess => "--sandbox danger-full-access",
+            Self::AskForApproval => "--ask-for-approval",
+            Self::FullAuto => "--full-auto",
+            Self::AddDir => "--add-dir",
+            Self::SkipGitRepoCheck => "--skip-git-repo-check",
+        }
+    }
+}
+
+impl SandboxMode {
+    /// The feature that stands for this mode.
+    #[must_use]
+    pub const fn feature(&self) -> Feature {
+        match self {
+            Self::ReadOnly => Feature::SandboxReadOnly,
+            Self::WorkspaceWrite => Feature::SandboxWorkspaceWrite,
+            Self::DangerFullAccess => Feature::SandboxDangerFullAccess,
+        }
+    }
+}
+
+/// Set of features detected from the Codex CLI help text, by
+/// [`probe_capabilities`](crate::probe::probe_capabilities).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Features present in this CLI build.
+    pub features: BTreeSet<Feature>,
+}
+
+impl Capabilities {
+    /// Returns `true` if the given feature is supported.
+    #[must_use]
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
 /// Configuration for a Codex CLI invocation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+// Independent switches, mostly mirroring CLI flags one to one.
+#[allow(clippy::struct_excessive_bools)]
 pub struct CodexConfig {
     /// Model identifier to use (e.g. `"o4-mini"`).
     pub model: Option<String>,
@@ -76,12 +162,88 @@ pub struct CodexConfig {
     pub overrides: Vec<(String, String)>,
     /// System prompt to append to the agent's instructions.
     pub system_prompt: Option<String>,
+    /// JSON schema the final message must satisfy, passed through a temp
+    /// file to `--output-schema`. The parsed message is returned in
+    /// [`RunResult::structured_output`]. Needs a CLI that has the flag; see
+    /// [`CodexCli::supports_output_schema`](crate::CodexCli::supports_output_schema).
+    #[serde(default)]
+    pub json_schema: Option<serde_json::Value>,
     /// Extra environment variables passed to the subprocess.
     pub env_vars: Vec<(String, String)>,
     /// Path to an MCP configuration file (TOML format for Codex).
     pub mcp_config_path: Option<std::path::PathBuf>,
-    /// Maximum wall-clock time before the subprocess is killed.
+    /// Maximum total wall-clock time before the subprocess is killed.
     pub timeout: Duration,
+    /// Maximum time spawning the process and writing its stdin may take.
+    /// `None` leaves startup bounded by `timeout` alone.
+    #[serde(default)]
+    pub spawn_timeout: Option<Duration>,
+    /// Maximum time from the start of the run until the process prints its
+    /// first stdout or stderr line, e.g. to catch a hung MCP handshake long
+    /// before `timeout`. `None` disables the check.
+    #[serde(default)]
+    pub first_output_timeout: Option<Duration>,
+    /// Memory bounds and overflow behaviour for captured stdout / stderr.
+    pub output_limits: OutputLimits,
+    /// File that raw stdout / stderr lines are appended to as they arrive,
+    /// regardless of `output_limits`. Rotated by size.
+    #[serde(default)]
+    pub log_sink: Option<PathBuf>,
+    /// OS limits on the subprocess's memory, CPU time, open files and
+    /// priority.
+    #[serde(default)]
+    pub resource_limits: ResourceLimits,
+    /// How long the subprocess may print nothing on stdout or stderr
+    /// before it counts as stalled. `None` disables stall detection.
+    #[serde(default)]
+    pub stall_timeout: Option<Duration>,
+    /// What to do when the subprocess stalls.
+    #[serde(default)]
+    pub on_stall: StallAction,
+    /// Patterns classifying stderr lines as they arrive, streamed as
+    /// [`StreamEvent::Progress`] and [`StreamEvent::Warning`]. `None` only
+    /// captures stderr.
+    #[serde(default)]
+    pub stderr_events: Option<StderrPatterns>,
+    /// Whether SIGINT and SIGTERM received by the host are passed on to the
+    /// subprocess and its children, cancelling the run with `Cancelled`.
+    ///
+    /// The subprocess then runs in its own process group. Listening for the
+    /// signals stops them from terminating the host for the rest of its
+    /// life, so the host has to exit on its own after a cancelled run.
+    #[serde(default)]
+    pub forward_signals: bool,
+    /// Handling of interactive trust / onboarding prompts.
+    pub interactive_prompts: InteractivePromptPolicy,
+    /// Delivery of stream events when the receiver is slow.
+    pub backpressure: BackpressurePolicy,
+    /// Host environment variables the subprocess inherits. `env_vars` is
+    /// applied on top.
+    #[serde(default)]
+    pub env_policy: EnvPolicy,
+    /// Arguments appended verbatim after the generated ones, before the
+    /// prompt, for CLI flags the config does not cover yet.
+    ///
+    /// Running fails with `ConflictingArg` if one of them is a flag the
+    /// config already sets.
+    #[serde(default)]
+    pub extra_args: Vec<OsString>,
+    /// Parsers for JSON output lines. Defaults to [`builtin_parsers`];
+    /// register more to support a new CLI release.
+    #[serde(skip, default = "builtin_parsers")]
+    pub parsers: StreamParsers,
+    /// CLI version that selects among [`parsers`](Self::parsers); `None`
+    /// applies every parser.
+    #[serde(default)]
+    pub cli_version: Option<semver::Version>,
+    /// Features of the installed CLI, which gate the flags emitted.
+    ///
+    /// `--search` is left out with a warning when unsupported; running a
+    /// config that needs any other unsupported flag fails with
+    /// `UnsupportedFeature`. [`CodexCli`](crate::CodexCli) fills it in from
+    /// its probed capabilities when unset; `None` emits every flag unchecked.
+    #[serde(default)]
+    pub capabilities: Option<Capabilities>,
 }
 
 impl Default for CodexConfig {
@@ -97,9 +259,26 @@ impl Default for CodexConfig {
             add_dirs: Vec::new(),
             overrides: Vec::new(),
             system_prompt: None,
+            json_schema: None,
             env_vars: Vec::new(),
             mcp_config_path: None,
             timeout: Duration::from_secs(300),
+            spawn_timeout: None,
+            first_output_timeout: None,
+            output_limits: OutputLimits::default(),
+            log_sink: None,
+            resource_limits: ResourceLimits::default(),
+            stall_timeout: None,
+            on_stall: StallAction::Warn,
+            stderr_events: None,
+            forward_signals: false,
+            interactive_prompts: InteractivePromptPolicy::default(),
+            backpressure: BackpressurePolicy::default(),
+            env_policy: EnvPolicy::default(),
+            extra_args: Vec::new(),
+            parsers: builtin_parsers(),
+            cli_version: None,
+            capabilities: None,
         }
     }
 }
@@ -115,6 +294,18 @@ pub struct RunResult {
     pub exit_code: i32,
     /// Wall-clock duration of the run in milliseconds.
     pub duration_ms: u64,
+    /// The final message parsed as JSON, when
+    /// [`CodexConfig::json_schema`] was set and the message parsed.
+    #[serde(default)]
+    pub structured_output: Option<serde_json::Value>,
+    /// Where the run's time went: spawn, first text, exit.
+    #[serde(default)]
+    pub latency: LatencyBreakdown,
+    /// Milliseconds from the start of the run at which each stream event was
+    /// parsed, in parse order. Under [`BackpressurePolicy::Block`] the n-th
+    /// offset belongs to the n-th event received.
+    #[serde(default)]
+    pub event_offsets_ms: Vec<u64>,
 }
 
 /// Incremental event emitted while streaming Codex output.
@@ -126,6 +317,24 @@ pub enum StreamEvent {
         /// The text content.
         text: String,
     },
+    /// The subprocess has printed nothing for
+    /// [`RunConfig::stall_timeout`].
+    Stalled {
+        /// Milliseconds since the last output line.
+        idle_ms: u64,
+    },
+    /// A stderr line classified as progress by
+    /// [`CodexConfig::stderr_events`], e.g. `Thinking…`.
+    Progress {
+        /// The line, without ANSI escapes.
+        message: String,
+    },
+    /// A stderr line classified as a warning by
+    /// [`CodexConfig::stderr_events`]. The run carries on.
+    Warning {
+        /// The line, without ANSI escapes.
+        message: String,
+    },
     /// An error message from the subprocess.
     Error {
         /// The error description.
@@ -134,3 +343,32 @@ pub enum StreamEvent {
     /// An unrecognised JSON value.
     Unknown(serde_json::Value),
 }
+
+impl rig_cli_process_core::TextEvent for StreamEvent {
+    fn text_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::Text { text } => Some(text),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> rig_cli_process_core::EventKind {
+        match self {
+            Self::Text { .. } => rig_cli_process_core::EventKind::Text,
+            _ => rig_cli_process_core::EventKind::Other,
+        }
+    }
+}
+
+/// Parsers for Codex JSON output, keyed by CLI version.
+pub type StreamParsers = ParserRegistry<StreamEvent>;
+
+/// The built-in parser, which reads each JSON line as a [`StreamEvent`].
+#[must_use]
+pub fn builtin_parsers() -> StreamParsers {
+    StreamParsers::new().register(semver::VersionReq::STAR, |val: &serde_json::Value| {
+        serde_json::from_value::<StreamEvent>(val.clone())
+            .ok()
+            .map(|event| vec![event])
+    })
+}
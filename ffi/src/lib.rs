@@ -0,0 +1,15 @@
+//! The C library of [`rig_cli_provider::ffi`].
+//!
+//! This crate only re-exports the `rig_cli_*` functions so that a normal
+//! build links them into a library C programs can load:
+//!
+//! ```text
+//! cargo build -p rig-cli-ffi --release
+//! ```
+//!
+//! produces `target/release/librig_cli_ffi.so` (`.dylib` on macOS) and
+//! `librig_cli_ffi.a`. See [`rig_cli_provider::ffi`] for the declarations
+//! and the calling conventions.
+
+#[cfg(unix)]
+pub use rig_cli_provider::ffi::*;
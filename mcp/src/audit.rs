@@ -0,0 +1,248 @@
+//! Audit log of tool calls served by [`RigMcpHandler`](crate::server::RigMcpHandler).
+//!
+//! An [`AuditLog`] receives one [`ToolCallRecord`] per `call_tool` request:
+//! the tool name, its arguments, the result, how long the call took, and
+//! which MCP client made it. Records go to a JSONL file or an in-process
+//! channel, after redaction rules have been applied.
+//!
+//! ```ignore
+//! let audit = AuditLog::file("/var/log/rig/tool-calls.jsonl")?
+//!     .redact_key("api_key")
+//!     .redact_key("password");
+//! let handler = RigMcpHandler::builder()
+//!     .toolset(toolset)
+//!     .audit(audit)
+//!     .build()
+//!     .await?;
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Placeholder written in place of redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// One audited `call_tool` request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    /// When the call started, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// Name of the MCP server that served the call.
+    pub server: String,
+    /// Name of the tool that was called.
+    pub tool: String,
+    /// Call arguments, after redaction.
+    pub arguments: Value,
+    /// Tool output or error message, after redaction.
+    pub result: String,
+    /// Whether the tool call failed.
+    pub is_error: bool,
+    /// Wall-clock duration of the call in milliseconds.
+    pub duration_ms: u64,
+    /// Name of the MCP client, as sent in its `initialize` request.
+    pub client_name: Option<String>,
+    /// Version of the MCP client, as sent in its `initialize` request.
+    pub client_version: Option<String>,
+}
+
+/// Where audit records are written.
+#[derive(Clone)]
+enum AuditSink {
+    /// Appends one JSON object per line.
+    File(Arc<Mutex<File>>),
+    /// Sends each record to a receiver in the same process.
+    Channel(UnboundedSender<ToolCallRecord>),
+}
+
+/// Records every tool call served through the MCP bridge.
+///
+/// Cloning is cheap; clones write to the same sink.
+#[derive(Clone)]
+pub struct AuditLog {
+    sink: AuditSink,
+    redact_keys: Vec<String>,
+    redact_results: bool,
+    max_result_chars: Option<usize>,
+}
+
+impl AuditLog {
+    /// Appends records as JSONL to the file at `path`, creating it if needed.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened for appending.
+    pub fn file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self::with_sink(AuditSink::File(Arc::new(Mutex::new(file)))))
+    }
+
+    /// Sends records to `sender`. Records are dropped once the receiver closes.
+    #[must_use]
+    pub const fn channel(sender: UnboundedSender<ToolCallRecord>) -> Self {
+        Self::with_sink(AuditSink::Channel(sender))
+    }
+
+    const fn with_sink(sink: AuditSink) -> Self {
+        Self {
+            sink,
+            redact_keys: Vec::new(),
+            redact_results: false,
+            max_result_chars: None,
+        }
+    }
+
+    /// Replaces the value of every argument field named `key` (at any depth,
+    /// case-insensitive) with [`REDACTED`].
+    #[must_use]
+    pub fn redact_key(mut self, key: impl Into<String>) -> Self {
+        self.redact_keys.push(key.into().to_lowercase());
+        self
+    }
+
+    /// Replaces every tool result with [`REDACTED`], keeping only its outcome.
+    #[must_use]
+    pub const fn redact_results(mut self, redact: bool) -> Self {
+        self.redact_results = redact;
+        self
+    }
+
+    /// Truncates recorded results to at most `max` characters.
+    #[must_use]
+    pub const fn max_result_chars(mut self, max: usize) -> Self {
+        self.max_result_chars = Some(max);
+        self
+    }
+
+    /// Applies the redaction rules to a record and writes it to the sink.
+    ///
+    /// Write failures are logged and otherwise ignored so auditing never
+    /// fails a tool call.
+    pub fn record(&self, mut record: ToolCallRecord) {
+        self.redact(&mut record);
+        match &self.sink {
+            AuditSink::File(file) => {
+                let line = match serde_json::to_string(&record) {
+                    Ok(line) => line,
+                    Err(e) => {
+                        tracing::warn!(target: "rig", error = %e, "Failed to serialize audit record");
+                        return;
+                    }
+                };
+                let mut file = file
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                if let Err(e) = writeln!(file, "{line}") {
+                    tracing::warn!(target: "rig", error = %e, "Failed to write audit record");
+                }
+            }
+            AuditSink::Channel(sender) => {
+                let _ = sender.send(record);
+            }
+        }
+    }
+
+    fn redact(&self, record: &mut ToolCallRecord) {
+        if !self.redact_keys.is_empty() {
+            redact_value(&mut record.arguments, &self.redact_keys);
+        }
+        if self.redact_results {
+            record.result = REDACTED.to_string();
+        } else if let Some(max) = self.max_result_chars
+            && let Some((cut, _)) = record.result.char_indices().nth(max)
+        {
+            record.result.truncate(cut);
+            record.result.push_str("...");
+        }
+    }
+}
+
+/// Recursively replaces the values of object fields whose lowercased name is in `keys`.
+fn redact_value(value: &mut Value, keys: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if keys.contains(&key.to_lowercase()) {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(field, keys);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact_value(item, keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(arguments: Value, result: &str) -> ToolCallRecord {
+        ToolCallRecord {
+            timestamp_ms: 0,
+            server: "rig-mcp-server".to_string(),
+            tool: "submit".to_string(),
+            arguments,
+            result: result.to_string(),
+            is_error: false,
+            duration_ms: 3,
+            client_name: Some("claude-code".to_string()),
+            client_version: None,
+        }
+    }
+
+    #[test]
+    fn test_channel_sink_redacts_nested_keys_and_truncates() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let audit = AuditLog::channel(tx)
+            .redact_key("API_KEY")
+            .max_result_chars(5);
+
+        audit.record(record(
+            json!({ "api_key": "sk-1", "items": [{ "Api_Key": "sk-2", "id": 7 }] }),
+            "résumé accepted",
+        ));
+
+        let got = rx.try_recv().unwrap();
+        assert_eq!(
+            got.arguments,
+            json!({ "api_key": REDACTED, "items": [{ "Api_Key": REDACTED, "id": 7 }] })
+        );
+        assert_eq!(got.result, "résum...");
+    }
+
+    #[test]
+    fn test_file_sink_appends_jsonl() {
+        let path = std::env::temp_dir().join(format!("rig-mcp-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let audit = AuditLog::file(&path).unwrap().redact_results(true);
+
+        let shared = audit.clone();
+        audit.record(record(json!({ "id": 1 }), "secret output"));
+        shared.record(record(json!({ "id": 2 }), "more"));
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<ToolCallRecord> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].arguments, json!({ "id": 1 }));
+        assert_eq!(records[0].result, REDACTED);
+        assert_eq!(records[1].arguments, json!({ "id": 2 }));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
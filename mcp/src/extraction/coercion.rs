@@ -0,0 +1,376 @@
+//! Schema-guided coercion of trivially mistyped submissions.
+//!
+//! Agents often submit values that are right in substance but wrong in form:
+//! a count as `"3"`, a flag as `"yes"`, an enum variant as `"High"` where the
+//! schema says `"high"`. With a [`CoercionPolicy`] set on the
+//! [`ExtractionConfig`](super::ExtractionConfig), such values are rewritten
+//! against the schema before validation instead of costing a retry. Every
+//! rewrite is recorded as a [`Coercion`] in
+//! [`ExtractionMetrics::coercions`](super::ExtractionMetrics::coercions), so
+//! prompts that keep needing them can be tuned.
+//!
+//! Only values the schema rejects as they are get rewritten, and only when
+//! the rewrite is unambiguous.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+
+/// Most `$ref` hops followed while resolving one schema, so a reference
+/// cycle cannot loop forever.
+const MAX_REF_HOPS: usize = 32;
+
+/// Which coercions are applied before validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoercionPolicy {
+    /// Parse strings where the schema expects a number or integer, e.g.
+    /// `"3"` to `3` (default: true).
+    pub numeric_strings: bool,
+    /// Read `"true"`, `"yes"`, `"false"` and `"no"`, in any case, where the
+    /// schema expects a boolean (default: true).
+    pub boolean_words: bool,
+    /// Replace a string that matches exactly one `enum` value ignoring ASCII
+    /// case with that value (default: true).
+    pub enum_case: bool,
+}
+
+impl Default for CoercionPolicy {
+    fn default() -> Self {
+        Self {
+            numeric_strings: true,
+            boolean_words: true,
+            enum_case: true,
+        }
+    }
+}
+
+/// The kind of rewrite a [`Coercion`] applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoercionKind {
+    /// A string parsed as a number or integer.
+    NumericString,
+    /// A yes/no or true/false string read as a boolean.
+    BooleanWord,
+    /// A string replaced by the `enum` value it matches ignoring case.
+    EnumCase,
+}
+
+/// One value rewritten before validation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coercion {
+    /// The attempt whose submission was rewritten, counting from 1.
+    pub attempt: usize,
+    /// JSON pointer to the rewritten value (empty for the root).
+    pub path: String,
+    /// The kind of rewrite.
+    pub kind: CoercionKind,
+    /// The submitted value.
+    pub from: Value,
+    /// The value it was replaced with.
+    pub to: Value,
+}
+
+impl std::fmt::Display for Coercion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "At path '{}': {} -> {}", self.path, self.from, self.to)
+    }
+}
+
+impl CoercionPolicy {
+    /// Rewrites the values of `instance` that `schema` rejects but this
+    /// policy can fix, returning what was rewritten for `attempt`.
+    pub fn apply(&self, schema: &Value, instance: &mut Value, attempt: usize) -> Vec<Coercion> {
+        let mut walker = Walker {
+            policy: *self,
+            root: schema,
+            attempt,
+            coercions: Vec::new(),
+        };
+        walker.coerce(schema, instance, String::new());
+        walker.coercions
+    }
+}
+
+/// State of one [`CoercionPolicy::apply`] pass.
+struct Walker<'a> {
+    policy: CoercionPolicy,
+    root: &'a Value,
+    attempt: usize,
+    coercions: Vec<Coercion>,
+}
+
+impl<'a> Walker<'a> {
+    fn coerce(&mut self, schema: &'a Value, instance: &mut Value, path: String) {
+        let Some(schema) = self.resolve(schema) else {
+            return;
+        };
+
+        if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+            for branch in all_of {
+                self.coerce(branch, instance, path.clone());
+            }
+        }
+        for keyword in ["anyOf", "oneOf"] {
+            if let Some(branches) = schema.get(keyword).and_then(Value::as_array) {
+                self.coerce_branches(branches, instance, &path);
+            }
+        }
+
+        match instance {
+            Value::Object(map) => {
+                let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                    return;
+                };
+                for (key, value) in map.iter_mut() {
+                    if let Some(property) = properties.get(key) {
+                        let token = key.replace('~', "~0").replace('/', "~1");
+                        self.coerce(property, value, format!("{path}/{token}"));
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let Some(item_schema) = schema.get("items").filter(|s| s.is_object()) else {
+                    return;
+                };
+                for (index, item) in items.iter_mut().enumerate() {
+                    self.coerce(item_schema, item, format!("{path}/{index}"));
+                }
+            }
+            Value::String(_) => {
+                if let Some((kind, to)) = self.coerce_string(schema, instance) {
+                    let from = std::mem::replace(instance, to.clone());
+                    self.coercions.push(Coercion {
+                        attempt: self.attempt,
+                        path,
+                        kind,
+                        from,
+                        to,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Coerces against the first branch that takes the instance as it is,
+    /// or else the first branch that can coerce it.
+    fn coerce_branches(&mut self, branches: &'a [Value], instance: &mut Value, path: &str) {
+        let resolved: Vec<&Value> = branches.iter().filter_map(|b| self.resolve(b)).collect();
+        if let Some(branch) = resolved.iter().find(|b| accepts_type(b, instance)) {
+            self.coerce(branch, instance, path.to_string());
+            return;
+        }
+        for branch in resolved {
+            let before = self.coercions.len();
+            self.coerce(branch, instance, path.to_string());
+            if self.coercions.len() > before {
+                return;
+            }
+        }
+    }
+
+    /// The rewrite of a string the schema rejects, if the policy allows one.
+    fn coerce_string(&self, schema: &Value, instance: &Value) -> Option<(CoercionKind, Value)> {
+        let text = instance.as_str()?;
+
+        if let Some(variants) = schema.get("enum").and_then(Value::as_array) {
+            if variants.contains(instance) {
+                return None;
+            }
+            if self.policy.enum_case {
+                let mut matches = variants
+                    .iter()
+                    .filter(|v| v.as_str().is_some_and(|v| v.eq_ignore_ascii_case(text)));
+                if let (Some(variant), None) = (matches.next(), matches.next()) {
+                    return Some((CoercionKind::EnumCase, variant.clone()));
+                }
+            }
+        }
+
+        if accepts_type(schema, instance) {
+            return None;
+        }
+        let trimmed = text.trim();
+        if self.policy.numeric_strings {
+            if allows(schema, "integer") || allows(schema, "number") {
+                if let Ok(n) = trimmed.parse::<i64>() {
+                    return Some((CoercionKind::NumericString, Value::from(n)));
+                }
+                if let Ok(n) = trimmed.parse::<u64>() {
+                    return Some((CoercionKind::NumericString, Value::from(n)));
+                }
+            }
+            if allows(schema, "number")
+                && let Some(n) = trimmed.parse::<f64>().ok().and_then(Number::from_f64)
+            {
+                return Some((CoercionKind::NumericString, Value::Number(n)));
+            }
+        }
+        if self.policy.boolean_words && allows(schema, "boolean") {
+            let word = trimmed.to_ascii_lowercase();
+            let flag = match word.as_str() {
+                "true" | "yes" => Some(true),
+                "false" | "no" => Some(false),
+                _ => None,
+            };
+            if let Some(flag) = flag {
+                return Some((CoercionKind::BooleanWord, Value::Bool(flag)));
+            }
+        }
+        None
+    }
+
+    /// Follows local `$ref`s from `schema` to the schema they point at.
+    fn resolve(&self, mut schema: &'a Value) -> Option<&'a Value> {
+        for _ in 0..MAX_REF_HOPS {
+            let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+                return Some(schema);
+            };
+            schema = self.root.pointer(reference.strip_prefix('#')?)?;
+        }
+        None
+    }
+}
+
+/// Whether `schema` names `ty` in its `type` keyword.
+fn allows(schema: &Value, ty: &str) -> bool {
+    match schema.get("type") {
+        Some(Value::String(t)) => t == ty,
+        Some(Value::Array(types)) => types.iter().any(|t| t == ty),
+        _ => false,
+    }
+}
+
+/// Whether `schema` takes `instance`'s JSON type as it is. A schema without
+/// a `type` keyword takes every type.
+fn accepts_type(schema: &Value, instance: &Value) -> bool {
+    if schema.get("type").is_none() {
+        return true;
+    }
+    match instance {
+        Value::Null => allows(schema, "null"),
+        Value::Bool(_) => allows(schema, "boolean"),
+        Value::Number(n) => {
+            allows(schema, "number") || (allows(schema, "integer") && (n.is_i64() || n.is_u64()))
+        }
+        Value::String(_) => allows(schema, "string"),
+        Value::Array(_) => allows(schema, "array"),
+        Value::Object(_) => allows(schema, "object"),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn coerce(schema: &Value, mut instance: Value) -> (Value, Vec<Coercion>) {
+        let coercions = CoercionPolicy::default().apply(schema, &mut instance, 1);
+        (instance, coercions)
+    }
+
+    #[test]
+    fn test_scalars_are_coerced_to_the_schema_type() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "count": {"type": "integer"},
+                "score": {"type": "number"},
+                "active": {"type": "boolean"},
+                "priority": {"type": "string", "enum": ["low", "high"]},
+                "name": {"type": "string"}
+            }
+        });
+        let (value, coercions) = coerce(
+            &schema,
+            json!({"count": " 3 ", "score": "2.5", "active": "Yes", "priority": "HIGH", "name": "42"}),
+        );
+
+        assert_eq!(
+            value,
+            json!({"count": 3, "score": 2.5, "active": true, "priority": "high", "name": "42"})
+        );
+        let mut kinds: Vec<_> = coercions
+            .iter()
+            .map(|c| (c.path.as_str(), c.kind))
+            .collect();
+        kinds.sort_unstable_by_key(|(path, _)| *path);
+        assert_eq!(
+            kinds,
+            [
+                ("/active", CoercionKind::BooleanWord),
+                ("/count", CoercionKind::NumericString),
+                ("/priority", CoercionKind::EnumCase),
+                ("/score", CoercionKind::NumericString),
+            ]
+        );
+        let count = coercions.iter().find(|c| c.path == "/count").unwrap();
+        assert_eq!(count.to_string(), "At path '/count': \" 3 \" -> 3");
+    }
+
+    #[test]
+    fn test_unfixable_and_ambiguous_values_are_left_alone() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "count": {"type": "integer"},
+                "ratio": {"type": "integer"},
+                "mode": {"enum": ["On", "ON"]},
+                "flag": {"type": "boolean"}
+            }
+        });
+        let submitted = json!({"count": "three", "ratio": "0.5", "mode": "on", "flag": "maybe"});
+        let (value, coercions) = coerce(&schema, submitted.clone());
+
+        assert_eq!(value, submitted);
+        assert_eq!(coercions, []);
+    }
+
+    #[test]
+    fn test_refs_arrays_and_optional_values_are_followed() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "items": {"type": "array", "items": {"$ref": "#/$defs/Item"}},
+                "limit": {"type": ["integer", "null"]},
+                "owner": {"anyOf": [{"$ref": "#/$defs/Item"}, {"type": "null"}]}
+            },
+            "$defs": {
+                "Item": {"type": "object", "properties": {"qty": {"type": "integer"}}}
+            }
+        });
+        let (value, coercions) = coerce(
+            &schema,
+            json!({"items": [{"qty": "1"}, {"qty": 2}], "limit": "10", "owner": {"qty": "4"}}),
+        );
+
+        assert_eq!(
+            value,
+            json!({"items": [{"qty": 1}, {"qty": 2}], "limit": 10, "owner": {"qty": 4}})
+        );
+        let mut paths: Vec<_> = coercions.iter().map(|c| c.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["/items/0/qty", "/limit", "/owner/qty"]);
+    }
+
+    #[test]
+    fn test_disabled_coercions_are_skipped() {
+        let schema = json!({"type": "object", "properties": {"count": {"type": "integer"}}});
+        let policy = CoercionPolicy {
+            numeric_strings: false,
+            ..CoercionPolicy::default()
+        };
+        let mut value = json!({"count": "3"});
+        assert_eq!(policy.apply(&schema, &mut value, 1), []);
+        assert_eq!(value, json!({"count": "3"}));
+    }
+
+    #[test]
+    fn test_reference_cycles_terminate() {
+        let schema = json!({"$ref": "#"});
+        let (value, coercions) = coerce(&schema, json!("1"));
+        assert_eq!(value, json!("1"));
+        assert_eq!(coercions, []);
+    }
+}
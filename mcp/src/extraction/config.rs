@@ -1,5 +1,27 @@
 //! Configuration for extraction retry behavior.
 
+use serde_json::Value;
+
+use super::coercion::CoercionPolicy;
+
+/// Default cap, in bytes, on the raw output and the submitted JSON each
+/// [`AttemptRecord`](super::error::AttemptRecord) keeps.
+pub const DEFAULT_MAX_STORED_BYTES: usize = 64 * 1024;
+
+/// How the prompt for a retry is built from the previous attempts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetryStrategy {
+    /// Append each attempt's feedback to the previous prompt. The agent sees
+    /// every earlier attempt in full, but the prompt grows with each retry.
+    #[default]
+    AppendToPrompt,
+    /// Send the initial prompt followed by the latest attempt's feedback only.
+    FreshPromptWithLastError,
+    /// Send the initial prompt, a one-line digest of each earlier attempt's
+    /// errors, and the latest attempt's full feedback.
+    SummarizedHistory,
+}
+
 /// Configuration for extraction retry behavior.
 #[derive(Debug, Clone)]
 pub struct ExtractionConfig {
@@ -7,6 +29,24 @@ pub struct ExtractionConfig {
     pub max_attempts: usize,
     /// Whether to include the full schema in validation feedback (default: true).
     pub include_schema_in_feedback: bool,
+    /// How retry prompts are built (default: [`RetryStrategy::AppendToPrompt`]).
+    pub retry_strategy: RetryStrategy,
+    /// Largest raw agent output an attempt record keeps, in bytes (default:
+    /// [`DEFAULT_MAX_STORED_BYTES`]). Longer output keeps its head and tail
+    /// around a truncation marker; `None` keeps it whole.
+    pub max_raw_output_bytes: Option<usize>,
+    /// Largest submitted JSON an attempt record keeps, in serialized bytes
+    /// (default: [`DEFAULT_MAX_STORED_BYTES`]). A longer submission is kept
+    /// as a string of its head and tail around a truncation marker; `None`
+    /// keeps it whole.
+    pub max_submitted_json_bytes: Option<usize>,
+    /// Whether attempt records keep the raw agent output at all (default:
+    /// true). Feedback for the next attempt is built from the full output
+    /// either way.
+    pub store_raw_output: bool,
+    /// Coercions applied to each submission before it is validated (default:
+    /// `None`, so submissions are validated as submitted).
+    pub coercion: Option<CoercionPolicy>,
 }
 
 impl Default for ExtractionConfig {
@@ -14,6 +54,11 @@ impl Default for ExtractionConfig {
         Self {
             max_attempts: 3,
             include_schema_in_feedback: true,
+            retry_strategy: RetryStrategy::AppendToPrompt,
+            max_raw_output_bytes: Some(DEFAULT_MAX_STORED_BYTES),
+            max_submitted_json_bytes: Some(DEFAULT_MAX_STORED_BYTES),
+            store_raw_output: true,
+            coercion: None,
         }
     }
 }
@@ -32,4 +77,154 @@ impl ExtractionConfig {
         self.include_schema_in_feedback = include;
         self
     }
+
+    /// Set how retry prompts are built.
+    #[must_use]
+    pub const fn with_retry_strategy(mut self, strategy: RetryStrategy) -> Self {
+        self.retry_strategy = strategy;
+        self
+    }
+
+    /// Set the largest raw agent output kept per attempt; `None` removes
+    /// the cap.
+    #[must_use]
+    pub const fn with_max_raw_output_bytes(mut self, max: Option<usize>) -> Self {
+        self.max_raw_output_bytes = max;
+        self
+    }
+
+    /// Set the largest submitted JSON kept per attempt; `None` removes the
+    /// cap.
+    #[must_use]
+    pub const fn with_max_submitted_json_bytes(mut self, max: Option<usize>) -> Self {
+        self.max_submitted_json_bytes = max;
+        self
+    }
+
+    /// Set whether attempt records keep the raw agent output.
+    #[must_use]
+    pub const fn with_raw_output_stored(mut self, store: bool) -> Self {
+        self.store_raw_output = store;
+        self
+    }
+
+    /// Set the coercions applied to submissions before validation.
+    #[must_use]
+    pub const fn with_coercion(mut self, policy: CoercionPolicy) -> Self {
+        self.coercion = Some(policy);
+        self
+    }
+
+    /// The raw output an attempt record keeps for `output`.
+    pub(crate) fn stored_raw_output(&self, output: &str) -> String {
+        if !self.store_raw_output {
+            return String::new();
+        }
+        self.max_raw_output_bytes
+            .map_or_else(|| output.to_string(), |max| truncate_middle(output, max))
+    }
+
+    /// The submission an attempt record keeps for `submitted`.
+    pub(crate) fn stored_submission(&self, submitted: &Value) -> Value {
+        let Some(max) = self.max_submitted_json_bytes else {
+            return submitted.clone();
+        };
+        let serialized = submitted.to_string();
+        if serialized.len() <= max {
+            submitted.clone()
+        } else {
+            Value::String(truncate_middle(&serialized, max))
+        }
+    }
+
+    /// The serialized submission an error keeps for `submitted`.
+    pub(crate) fn stored_submission_text(&self, submitted: &Value) -> String {
+        let serialized = submitted.to_string();
+        match self.max_submitted_json_bytes {
+            Some(max) => truncate_middle(&serialized, max),
+            None => serialized,
+        }
+    }
+}
+
+/// Cuts `text` down to at most its first and last `max / 2` bytes, split on
+/// character boundaries, around a marker naming how many bytes were dropped.
+pub(crate) fn truncate_middle(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut head = max / 2;
+    while !text.is_char_boundary(head) {
+        head -= 1;
+    }
+    let mut tail = text.len() - (max - max / 2);
+    while !text.is_char_boundary(tail) {
+        tail += 1;
+    }
+    format!(
+        "{}\n...[truncated {} bytes]...\n{}",
+        &text[..head],
+        tail - head,
+        &text[tail..]
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_truncate_middle_keeps_head_and_tail() {
+        let text = format!("{}{}{}", "a".repeat(10), "b".repeat(100), "c".repeat(10));
+        let cut = truncate_middle(&text, 20);
+        assert_eq!(
+            cut,
+            format!(
+                "{}\n...[truncated 100 bytes]...\n{}",
+                "a".repeat(10),
+                "c".repeat(10)
+            )
+        );
+        assert_eq!(truncate_middle("short", 20), "short");
+    }
+
+    #[test]
+    fn test_truncate_middle_respects_char_boundaries() {
+        let cut = truncate_middle("ééééé", 5);
+        assert!(cut.starts_with("é\n"));
+        assert!(cut.ends_with("\né"));
+        assert!(cut.contains("[truncated 6 bytes]"));
+    }
+
+    #[test]
+    fn test_stored_values_follow_config() {
+        let config = ExtractionConfig::default()
+            .with_max_raw_output_bytes(Some(4))
+            .with_max_submitted_json_bytes(Some(8));
+        assert!(
+            config
+                .stored_raw_output("0123456789")
+                .contains("[truncated 6 bytes]")
+        );
+
+        let small = json!({"a": 1});
+        assert_eq!(config.stored_submission(&small), small);
+        let large = json!({"name": "a long enough value"});
+        assert!(
+            config
+                .stored_submission(&large)
+                .as_str()
+                .unwrap()
+                .contains("[truncated")
+        );
+
+        let skipped = config.with_raw_output_stored(false);
+        assert_eq!(skipped.stored_raw_output("0123456789"), "");
+
+        let uncapped = ExtractionConfig::default().with_max_raw_output_bytes(None);
+        let long = "x".repeat(DEFAULT_MAX_STORED_BYTES + 1);
+        assert_eq!(uncapped.stored_raw_output(&long), long);
+    }
 }
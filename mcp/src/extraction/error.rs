@@ -1,20 +1,34 @@
 //! Error types for extraction operations with attempt history tracking.
 
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use thiserror::Error;
 
+use super::feedback::ValidationIssue;
 use super::metrics::ExtractionMetrics;
 
 /// Record of a single extraction attempt including submission and validation errors.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttemptRecord {
     /// The attempt number (1-indexed).
     pub attempt_number: usize,
-    /// The JSON submitted during this attempt.
+    /// The JSON submitted during this attempt, or a string of its head and
+    /// tail when it exceeded [`ExtractionConfig::max_submitted_json_bytes`].
+    ///
+    /// [`ExtractionConfig::max_submitted_json_bytes`]: super::ExtractionConfig::max_submitted_json_bytes
     pub submitted_json: serde_json::Value,
     /// Validation error messages from this attempt.
     pub validation_errors: Vec<String>,
-    /// Raw agent output text (not just parsed JSON).
+    /// Structured schema violations behind `validation_errors`.
+    ///
+    /// Empty when the attempt failed because its output was not valid JSON.
+    pub validation_issues: Vec<ValidationIssue>,
+    /// Raw agent output text (not just parsed JSON), capped at
+    /// [`ExtractionConfig::max_raw_output_bytes`] and empty unless
+    /// [`ExtractionConfig::store_raw_output`] is set.
+    ///
+    /// [`ExtractionConfig::max_raw_output_bytes`]: super::ExtractionConfig::max_raw_output_bytes
+    /// [`ExtractionConfig::store_raw_output`]: super::ExtractionConfig::store_raw_output
     pub raw_agent_output: String,
     /// Elapsed time at this attempt.
     pub elapsed: Duration,
@@ -66,3 +80,17 @@ pub enum ExtractionError {
         attempt: usize,
     },
 }
+
+impl ExtractionError {
+    /// Returns a stable, machine-readable code identifying the error category.
+    #[must_use]
+    pub const fn error_code(&self) -> &'static str {
+        match self {
+            Self::MaxRetriesExceeded { .. } => "max_retries_exceeded",
+            Self::ParseError { .. } => "parse",
+            Self::SchemaError(_) => "schema",
+            Self::AgentError(_) => "agent",
+            Self::CallbackRejection { .. } => "callback_rejection",
+        }
+    }
+}
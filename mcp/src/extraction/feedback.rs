@@ -1,6 +1,71 @@
 //! Validation feedback builders for agent retry loops.
 
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt::Write as _;
+
+use super::error::AttemptRecord;
+
+/// Errors listed per attempt in [`build_attempt_digest`].
+const DIGEST_ERRORS_PER_ATTEMPT: usize = 3;
+
+/// Characters kept of each error in [`build_attempt_digest`].
+const DIGEST_ERROR_CHARS: usize = 120;
+
+/// Most changes [`build_resubmission_feedback`] lists. A submission that
+/// differs from the previous one in more places is not shown a diff.
+const DIFF_MAX_CHANGES: usize = 8;
+
+/// A single schema violation in machine-readable form.
+///
+/// [`keyword`](Self::keyword) is the JSON Schema keyword that failed
+/// (`required`, `type`, `minimum`, `enum`, ...) and serves as a stable code
+/// for grouping failures. The [`Display`](std::fmt::Display) rendering is the
+/// line shown to the agent in retry feedback.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// JSON pointer to the offending value in the submission (empty for the root).
+    pub instance_path: String,
+    /// JSON pointer to the schema keyword that failed.
+    pub schema_path: String,
+    /// The failing keyword, i.e. the last segment of `schema_path`.
+    pub keyword: String,
+    /// The keyword's value in the schema (e.g. the `minimum` bound or the `required` list).
+    pub expected: Value,
+    /// The offending value from the submission.
+    pub actual: Value,
+    /// Human-readable description from the validator.
+    pub message: String,
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "At path '{}': {}", self.instance_path, self.message)
+    }
+}
+
+/// What a custom feedback formatter is given to describe a failed attempt.
+///
+/// See [`ExtractionOrchestratorBuilder::feedback_formatter`](super::orchestrator::ExtractionOrchestratorBuilder::feedback_formatter).
+#[derive(Debug, Clone, Copy)]
+pub struct FeedbackContext<'a> {
+    /// The schema submissions are validated against.
+    pub schema: &'a Value,
+    /// The failed attempt, counting from 1.
+    pub attempt: usize,
+    /// The configured maximum number of attempts.
+    pub max_attempts: usize,
+    /// The submission, or `None` if the output could not be parsed as JSON.
+    pub submission: Option<&'a Value>,
+    /// The agent's raw output.
+    pub raw_output: &'a str,
+    /// The parse error or validation errors, one line each.
+    pub errors: &'a [String],
+    /// The validation errors in structured form; empty for a parse error.
+    pub issues: &'a [ValidationIssue],
+    /// Every attempt so far, ending with this one.
+    pub history: &'a [AttemptRecord],
+}
 
 /// Build validation feedback message for the agent with complete error context.
 ///
@@ -59,10 +124,76 @@ pub fn build_validation_feedback(
     feedback
 }
 
-/// Collect all validation errors from jsonschema validation.
+/// Collect all validation failures as structured [`ValidationIssue`]s.
 ///
-/// Returns a vector of formatted error strings with instance paths.
 /// Uses `iter_errors()` to collect ALL validation failures, not just the first.
+/// If the schema itself does not compile, a single issue with keyword
+/// `"schema"` describing the compilation error is returned.
+///
+/// # Examples
+///
+/// ```
+/// use rig_cli_mcp::extraction::feedback::collect_validation_issues;
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "type": "object",
+///     "properties": {"age": {"type": "integer", "minimum": 0}},
+///     "required": ["age"]
+/// });
+///
+/// let issues = collect_validation_issues(&schema, &json!({"age": -5}));
+/// assert_eq!(issues[0].keyword, "minimum");
+/// assert_eq!(issues[0].instance_path, "/age");
+/// assert_eq!(issues[0].expected, json!(0));
+/// assert_eq!(issues[0].actual, json!(-5));
+/// ```
+#[must_use]
+pub fn collect_validation_issues(schema: &Value, instance: &Value) -> Vec<ValidationIssue> {
+    match jsonschema::Validator::new(schema) {
+        Ok(validator) => validator_issues(&validator, schema, instance),
+        Err(e) => vec![ValidationIssue {
+            instance_path: String::new(),
+            schema_path: String::new(),
+            keyword: "schema".to_string(),
+            expected: Value::Null,
+            actual: Value::Null,
+            message: format!("Schema compilation error: {e}"),
+        }],
+    }
+}
+
+/// The failures of `instance` against `validator`, compiled from `schema`.
+pub(crate) fn validator_issues(
+    validator: &jsonschema::Validator,
+    schema: &Value,
+    instance: &Value,
+) -> Vec<ValidationIssue> {
+    validator
+        .iter_errors(instance)
+        .map(|error| {
+            let schema_path = error.schema_path.to_string();
+            let keyword = schema_path
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            ValidationIssue {
+                instance_path: error.instance_path.to_string(),
+                expected: schema.pointer(&schema_path).cloned().unwrap_or(Value::Null),
+                actual: error.instance.clone().into_owned(),
+                message: error.to_string(),
+                schema_path,
+                keyword,
+            }
+        })
+        .collect()
+}
+
+/// Collect all validation errors from jsonschema validation.
+///
+/// Returns a vector of formatted error strings with instance paths; see
+/// [`collect_validation_issues`] for the structured form.
 ///
 /// # Examples
 ///
@@ -155,6 +286,230 @@ pub fn build_parse_error_feedback(
     feedback
 }
 
+/// Summarize failed attempts as one line each, for retry prompts that
+/// should not repeat every earlier submission in full.
+///
+/// Each line gives the attempt number, its error count and the first few
+/// errors, shortened. Returns an empty string for an empty history.
+///
+/// # Examples
+///
+/// ```
+/// use rig_cli_mcp::extraction::feedback::build_attempt_digest;
+/// use rig_cli_mcp::extraction::AttemptRecord;
+/// use serde_json::Value;
+/// use std::time::Duration;
+///
+/// let history = vec![AttemptRecord {
+///     attempt_number: 1,
+///     submitted_json: Value::Null,
+///     validation_errors: vec!["JSON parse error: expected value".to_string()],
+///     validation_issues: Vec::new(),
+///     raw_agent_output: "not json".to_string(),
+///     elapsed: Duration::ZERO,
+/// }];
+///
+/// let digest = build_attempt_digest(&history);
+/// assert!(digest.contains("Attempt 1: 1 error(s): JSON parse error: expected value"));
+/// ```
+#[must_use]
+pub fn build_attempt_digest(history: &[AttemptRecord]) -> String {
+    if history.is_empty() {
+        return String::new();
+    }
+
+    let mut digest = String::from("Earlier attempts (summary):\n");
+    for record in history {
+        let errors = &record.validation_errors;
+        let shown: Vec<String> = errors
+            .iter()
+            .take(DIGEST_ERRORS_PER_ATTEMPT)
+            .map(|error| shorten(error, DIGEST_ERROR_CHARS))
+            .collect();
+        let _ = write!(
+            digest,
+            "  - Attempt {}: {} error(s): {}",
+            record.attempt_number,
+            errors.len(),
+            shown.join("; ")
+        );
+        if errors.len() > shown.len() {
+            let _ = write!(digest, " (+{} more)", errors.len() - shown.len());
+        }
+        digest.push('\n');
+    }
+    digest
+}
+
+/// One difference between two submissions.
+///
+/// The [`Display`](std::fmt::Display) rendering is the line shown to the
+/// agent in retry feedback.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionChange {
+    /// JSON pointer to the value that differs (empty for the root).
+    pub path: String,
+    /// The value in the earlier submission; `None` if it was added.
+    pub before: Option<Value>,
+    /// The value in the later submission; `None` if it was removed.
+    pub after: Option<Value>,
+}
+
+impl std::fmt::Display for SubmissionChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "At path '{}': ", self.path)?;
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) => write!(f, "{before} -> {after}"),
+            (None, Some(after)) => write!(f, "added {after}"),
+            (Some(before), None) => write!(f, "removed {before}"),
+            (None, None) => f.write_str("unchanged"),
+        }
+    }
+}
+
+/// List the differences between two submissions.
+///
+/// Objects are compared key by key and arrays element by element, so a
+/// value inserted at the front of an array shows as every later element
+/// changing.
+///
+/// # Examples
+///
+/// ```
+/// use rig_cli_mcp::extraction::feedback::diff_submissions;
+/// use serde_json::json;
+///
+/// let changes = diff_submissions(
+///     &json!({"name": "Ada", "age": -5}),
+///     &json!({"name": "Ada", "age": "5", "tags": []}),
+/// );
+/// let lines: Vec<String> = changes.iter().map(ToString::to_string).collect();
+/// assert_eq!(lines, ["At path '/age': -5 -> \"5\"", "At path '/tags': added []"]);
+/// ```
+#[must_use]
+pub fn diff_submissions(before: &Value, after: &Value) -> Vec<SubmissionChange> {
+    let mut changes = Vec::new();
+    diff_values(String::new(), Some(before), Some(after), &mut changes);
+    changes
+}
+
+/// Appends the differences between `before` and `after`, found at `path`.
+fn diff_values(
+    path: String,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    changes: &mut Vec<SubmissionChange>,
+) {
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let token = key.replace('~', "~0").replace('/', "~1");
+                diff_values(
+                    format!("{path}/{token}"),
+                    before.get(key),
+                    after.get(key),
+                    changes,
+                );
+            }
+        }
+        (Some(Value::Array(before)), Some(Value::Array(after))) => {
+            for index in 0..before.len().max(after.len()) {
+                diff_values(
+                    format!("{path}/{index}"),
+                    before.get(index),
+                    after.get(index),
+                    changes,
+                );
+            }
+        }
+        _ if before == after => {}
+        _ => changes.push(SubmissionChange {
+            path,
+            before: before.cloned(),
+            after: after.cloned(),
+        }),
+    }
+}
+
+/// Build feedback comparing the latest failed submission with the earlier
+/// ones, so the agent does not spend its retries on the same value.
+///
+/// `history` ends with the attempt the feedback is for. Returns:
+/// - a warning naming the attempt, if the submission repeats an earlier one
+///   exactly;
+/// - the [changes](diff_submissions) since the previous submission, if there
+///   are only a few;
+/// - `None` otherwise, including when there is no earlier submission to
+///   compare with. Attempts that did not parse as JSON are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use rig_cli_mcp::extraction::feedback::build_resubmission_feedback;
+/// use rig_cli_mcp::extraction::AttemptRecord;
+/// use serde_json::json;
+/// use std::time::Duration;
+///
+/// let attempt = |number, submitted_json| AttemptRecord {
+///     attempt_number: number,
+///     submitted_json,
+///     validation_errors: vec!["At path '/age': -5 is less than the minimum of 0".to_string()],
+///     validation_issues: Vec::new(),
+///     raw_agent_output: String::new(),
+///     elapsed: Duration::ZERO,
+/// };
+///
+/// let history = vec![attempt(1, json!({"age": -5})), attempt(2, json!({"age": -5}))];
+/// let feedback = build_resubmission_feedback(&history).unwrap();
+/// assert!(feedback.contains("You already tried this exact value in attempt 1"));
+/// ```
+#[must_use]
+pub fn build_resubmission_feedback(history: &[AttemptRecord]) -> Option<String> {
+    let (current, earlier) = history.split_last()?;
+    if current.submitted_json.is_null() {
+        return None;
+    }
+    let mut submitted = earlier
+        .iter()
+        .filter(|record| !record.submitted_json.is_null());
+
+    if let Some(repeated) = submitted
+        .clone()
+        .find(|record| record.submitted_json == current.submitted_json)
+    {
+        return Some(format!(
+            "You already tried this exact value in attempt {}, and it failed the same way. \
+             Do not submit it again; change the values the errors point to.",
+            repeated.attempt_number
+        ));
+    }
+
+    let previous = submitted.next_back()?;
+    let changes = diff_submissions(&previous.submitted_json, &current.submitted_json);
+    if changes.len() > DIFF_MAX_CHANGES {
+        return None;
+    }
+    let mut feedback = format!(
+        "Changes since attempt {} (which also failed):\n",
+        previous.attempt_number
+    );
+    for change in changes {
+        let _ = writeln!(feedback, "  - {change}");
+    }
+    Some(feedback)
+}
+
+/// Cuts `text` to at most `max_chars` characters, marking the cut with `...`.
+fn shorten(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
 mod tests {
@@ -195,6 +550,100 @@ mod tests {
         assert!(errors.iter().any(|e| e.contains("name")));
     }
 
+    #[test]
+    fn test_collect_validation_issues() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "age": {"type": "integer", "minimum": 0}
+            },
+            "required": ["name", "age"]
+        });
+
+        let issues = collect_validation_issues(&schema, &json!({"age": -5}));
+
+        let required = issues.iter().find(|i| i.keyword == "required").unwrap();
+        assert_eq!(required.schema_path, "/required");
+        assert_eq!(required.expected, json!(["name", "age"]));
+        assert_eq!(required.actual, json!({"age": -5}));
+
+        let minimum = issues.iter().find(|i| i.keyword == "minimum").unwrap();
+        assert_eq!(minimum.instance_path, "/age");
+        assert_eq!(minimum.expected, json!(0));
+        assert_eq!(minimum.actual, json!(-5));
+        assert!(minimum.to_string().starts_with("At path '/age': "));
+
+        let broken = collect_validation_issues(&json!({"type": "no_such_type"}), &json!(1));
+        assert_eq!(broken[0].keyword, "schema");
+    }
+
+    fn failed(number: usize, submitted_json: Value) -> AttemptRecord {
+        AttemptRecord {
+            attempt_number: number,
+            submitted_json,
+            validation_errors: vec!["error".to_string()],
+            validation_issues: Vec::new(),
+            raw_agent_output: String::new(),
+            elapsed: std::time::Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_diff_submissions() {
+        let changes = diff_submissions(
+            &json!({"a/b": 1, "items": [1, 2, 3], "same": {"x": true}}),
+            &json!({"a/b": 2, "items": [1, 2], "same": {"x": true}, "new": null}),
+        );
+        let lines: Vec<String> = changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "At path '/a~1b': 1 -> 2",
+                "At path '/items/2': removed 3",
+                "At path '/new': added null",
+            ]
+        );
+        assert_eq!(diff_submissions(&json!(1), &json!("1"))[0].path, "");
+        assert_eq!(diff_submissions(&json!({"a": [1]}), &json!({"a": [1]})), []);
+    }
+
+    #[test]
+    fn test_build_resubmission_feedback() {
+        // Nothing to compare with yet, or the latest attempt did not parse.
+        assert!(build_resubmission_feedback(&[failed(1, json!({"age": -5}))]).is_none());
+        let unparsed = [failed(1, json!({"age": -5})), failed(2, Value::Null)];
+        assert!(build_resubmission_feedback(&unparsed).is_none());
+
+        // An exact repeat names the attempt it repeats, even past a parse failure.
+        let repeated = [
+            failed(1, json!({"age": -5})),
+            failed(2, json!({"age": -4})),
+            failed(3, Value::Null),
+            failed(4, json!({"age": -5})),
+        ];
+        let feedback = build_resubmission_feedback(&repeated).unwrap();
+        assert!(feedback.starts_with("You already tried this exact value in attempt 1,"));
+
+        // A near-identical submission gets a diff against the previous one.
+        let close = [failed(1, json!({"age": -5})), failed(2, json!({"age": -4}))];
+        assert_eq!(
+            build_resubmission_feedback(&close).unwrap(),
+            "Changes since attempt 1 (which also failed):\n  - At path '/age': -5 -> -4\n"
+        );
+
+        // A rewrite is not diffed.
+        let fields = |offset: usize| {
+            Value::Object(
+                (0..=DIFF_MAX_CHANGES)
+                    .map(|i| (format!("f{i}"), json!(i + offset)))
+                    .collect(),
+            )
+        };
+        let rewritten = [failed(1, fields(0)), failed(2, fields(100))];
+        assert!(build_resubmission_feedback(&rewritten).is_none());
+    }
+
     #[test]
     fn test_build_parse_error_feedback() {
         let schema = json!({"type": "object"});
@@ -0,0 +1,188 @@
+//! Saved attempt histories, for inspecting failed extractions offline.
+//!
+//! [`ExtractionError::MaxRetriesExceeded`] carries every attempt of the
+//! failed extraction. [`AttemptHistory::save`] writes them as JSON Lines,
+//! one [`AttemptRecord`] per line, so a production failure can be copied
+//! elsewhere and [loaded](AttemptHistory::load) again without rerunning
+//! the pipeline:
+//!
+//! ```ignore
+//! if let Some(history) = err.attempt_history() {
+//!     history.save("failed-extraction.jsonl")?;
+//! }
+//! // ... later, on another machine:
+//! println!("{}", AttemptHistory::load("failed-extraction.jsonl")?);
+//! ```
+//!
+//! The [`Display`](std::fmt::Display) rendering lists each attempt's
+//! validation errors as a diff against the previous attempt: which errors
+//! it fixed, which it introduced and which it kept, and how the submission
+//! changed.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
+
+use super::error::{AttemptRecord, ExtractionError};
+use super::feedback::diff_submissions;
+
+/// Submission changes shown per attempt by the rendering.
+const SHOWN_CHANGES: usize = 10;
+
+/// The attempts of one extraction, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct AttemptHistory {
+    /// The attempts, oldest first.
+    pub attempts: Vec<AttemptRecord>,
+}
+
+impl From<Vec<AttemptRecord>> for AttemptHistory {
+    fn from(attempts: Vec<AttemptRecord>) -> Self {
+        Self { attempts }
+    }
+}
+
+impl AttemptHistory {
+    /// Writes the attempts to `path` as JSON Lines, replacing the file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut file = BufWriter::new(std::fs::File::create(path)?);
+        for attempt in &self.attempts {
+            serde_json::to_writer(&mut file, attempt)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()
+    }
+
+    /// Reads attempts written by [`save`](Self::save). Blank lines are
+    /// skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or a line is not an
+    /// [`AttemptRecord`]; the error names the line.
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut attempts = Vec::new();
+        for (index, line) in file.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let attempt = serde_json::from_str(&line).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("line {}: {e}", index + 1),
+                )
+            })?;
+            attempts.push(attempt);
+        }
+        Ok(Self { attempts })
+    }
+}
+
+impl std::fmt::Display for AttemptHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut previous: Option<&AttemptRecord> = None;
+        for attempt in &self.attempts {
+            let errors = &attempt.validation_errors;
+            writeln!(
+                f,
+                "Attempt {} after {:.1?}: {} error(s)",
+                attempt.attempt_number,
+                attempt.elapsed,
+                errors.len()
+            )?;
+            let Some(previous) = previous.replace(attempt) else {
+                for error in errors {
+                    writeln!(f, "  - {error}")?;
+                }
+                continue;
+            };
+
+            let before = &previous.validation_errors;
+            let mut lines = String::new();
+            for error in before.iter().filter(|error| !errors.contains(error)) {
+                let _ = writeln!(lines, "  fixed: {error}");
+            }
+            for error in errors.iter().filter(|error| !before.contains(error)) {
+                let _ = writeln!(lines, "  new:   {error}");
+            }
+            for error in errors.iter().filter(|error| before.contains(error)) {
+                let _ = writeln!(lines, "  kept:  {error}");
+            }
+            f.write_str(&lines)?;
+
+            let changes = diff_submissions(&previous.submitted_json, &attempt.submitted_json);
+            if changes.is_empty() {
+                writeln!(f, "  submission unchanged")?;
+            }
+            for change in changes.iter().take(SHOWN_CHANGES) {
+                writeln!(f, "  changed: {change}")?;
+            }
+            if changes.len() > SHOWN_CHANGES {
+                writeln!(f, "  ... {} more changes", changes.len() - SHOWN_CHANGES)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ExtractionError {
+    /// The attempts of a [`MaxRetriesExceeded`](Self::MaxRetriesExceeded)
+    /// failure, ready to [save](AttemptHistory::save); `None` for other
+    /// errors.
+    #[must_use]
+    pub fn attempt_history(&self) -> Option<AttemptHistory> {
+        match self {
+            Self::MaxRetriesExceeded { history, .. } => Some(history.clone().into()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn attempt(number: usize, submitted_json: serde_json::Value, errors: &[&str]) -> AttemptRecord {
+        AttemptRecord {
+            attempt_number: number,
+            submitted_json,
+            validation_errors: errors.iter().map(ToString::to_string).collect(),
+            validation_issues: Vec::new(),
+            raw_agent_output: String::new(),
+            elapsed: Duration::from_millis(1500 * number as u64),
+        }
+    }
+
+    #[test]
+    fn test_history_round_trips_and_renders_error_diffs() {
+        let history = AttemptHistory::from(vec![
+            attempt(1, json!({"age": -5}), &["age below 0", "name missing"]),
+            attempt(2, json!({"age": 5}), &["name missing", "age not a string"]),
+        ]);
+        let path = std::env::temp_dir().join(format!("rig-attempts-{}.jsonl", std::process::id()));
+        history.save(&path).unwrap();
+        let loaded = AttemptHistory::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.attempts.len(), 2);
+        assert_eq!(loaded.attempts[1].elapsed, Duration::from_secs(3));
+        assert_eq!(
+            loaded.to_string(),
+            "Attempt 1 after 1.5s: 2 error(s)\n  \
+             - age below 0\n  \
+             - name missing\n\
+             Attempt 2 after 3.0s: 2 error(s)\n  \
+             fixed: age below 0\n  \
+             new:   age not a string\n  \
+             kept:  name missing\n  \
+             changed: At path '/age': -5 -> 5\n"
+        );
+    }
+}
@@ -1,7 +1,18 @@
 //! Metrics tracking and token estimation for extraction operations.
 
+use super::coercion::Coercion;
+use crate::harvest::ToolHarvest;
 use std::time::Duration;
 
+/// Token counts reported by a CLI for a single agent call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// Tokens the model read, including cached prompt tokens.
+    pub input_tokens: u64,
+    /// Tokens the model generated.
+    pub output_tokens: u64,
+}
+
 /// Metrics collected during an extraction operation.
 #[derive(Debug, Clone, Default)]
 pub struct ExtractionMetrics {
@@ -13,6 +24,52 @@ pub struct ExtractionMetrics {
     pub estimated_input_tokens: usize,
     /// Estimated output tokens received from agent.
     pub estimated_output_tokens: usize,
+    /// Input tokens summed over all attempts.
+    ///
+    /// Uses the usage reported by the adapter where available and
+    /// [`estimate_tokens`] for attempts that reported none.
+    pub input_tokens: u64,
+    /// Output tokens summed over all attempts, with the same fallback as
+    /// [`input_tokens`](Self::input_tokens).
+    pub output_tokens: u64,
+    /// Number of attempts whose token counts were reported by the adapter
+    /// rather than estimated.
+    pub reported_usage_attempts: usize,
+    /// Tool calls of every attempt, in order, for agents that return them
+    /// with [`AgentResponse::with_tool_harvest`](super::AgentResponse::with_tool_harvest).
+    /// Empty otherwise.
+    pub tool_harvest: ToolHarvest,
+    /// Values rewritten before validation, over all attempts, when the
+    /// config sets a [`CoercionPolicy`](super::CoercionPolicy). Empty
+    /// otherwise.
+    pub coercions: Vec<Coercion>,
+}
+
+impl ExtractionMetrics {
+    /// Returns `true` when every attempt reported its token usage, so
+    /// [`input_tokens`](Self::input_tokens) and
+    /// [`output_tokens`](Self::output_tokens) contain no estimates.
+    #[must_use]
+    pub const fn usage_is_exact(&self) -> bool {
+        self.total_attempts > 0 && self.reported_usage_attempts == self.total_attempts
+    }
+
+    /// Adds one attempt's token counts, estimating from `prompt` and `output`
+    /// when `usage` is `None`.
+    pub fn record_usage(&mut self, usage: Option<TokenUsage>, prompt: &str, output: &str) {
+        let usage = usage.map_or_else(
+            || TokenUsage {
+                input_tokens: estimate_tokens_u64(prompt),
+                output_tokens: estimate_tokens_u64(output),
+            },
+            |usage| {
+                self.reported_usage_attempts += 1;
+                usage
+            },
+        );
+        self.input_tokens = self.input_tokens.saturating_add(usage.input_tokens);
+        self.output_tokens = self.output_tokens.saturating_add(usage.output_tokens);
+    }
 }
 
 /// Estimate token count from text using the standard 4-chars-per-token heuristic.
@@ -34,6 +91,10 @@ pub fn estimate_tokens(text: &str) -> usize {
     text.chars().count().div_ceil(4)
 }
 
+fn estimate_tokens_u64(text: &str) -> u64 {
+    u64::try_from(estimate_tokens(text)).unwrap_or(u64::MAX)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,4 +114,24 @@ mod tests {
         assert_eq!(estimate_tokens("你好"), 1); // 2 chars / 4 = 0.5 -> 1
         assert_eq!(estimate_tokens("hello 世界"), 2); // 8 chars / 4 = 2
     }
+
+    #[test]
+    fn test_record_usage_falls_back_to_estimate() {
+        let mut metrics = ExtractionMetrics::default();
+        metrics.record_usage(
+            Some(TokenUsage {
+                input_tokens: 1200,
+                output_tokens: 40,
+            }),
+            "ignored",
+            "ignored",
+        );
+        metrics.record_usage(None, "abcdefgh", "abcd");
+        metrics.total_attempts = 2;
+
+        assert_eq!(metrics.input_tokens, 1202);
+        assert_eq!(metrics.output_tokens, 41);
+        assert_eq!(metrics.reported_usage_attempts, 1);
+        assert!(!metrics.usage_is_exact());
+    }
 }
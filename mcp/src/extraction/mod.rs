@@ -4,19 +4,33 @@
 //! loops for structured LLM extraction:
 //!
 //! - [`ExtractionOrchestrator`] - Async retry loop with validation feedback
+//! - [`ExtractionOrchestratorBuilder`] - Feedback, parsing and prompt hooks
 //! - [`ExtractionError`] - Typed error enum with attempt history
-//! - [`ExtractionMetrics`] - Token and timing metrics
+//! - [`AttemptHistory`] - Saves, loads and renders failed attempts for offline inspection
+//! - [`ExtractionMetrics`] - Token, timing and harvested tool call metrics
+//! - [`AgentResponse`] - Agent output with optional reported token usage
 //! - [`ExtractionConfig`] - Retry behavior configuration
+//! - [`RetryStrategy`] - How retry prompts carry earlier attempts
+//! - [`CoercionPolicy`] - Schema-guided fixes of mistyped values before validation
 //! - [`build_validation_feedback`] - Rich validation error formatting
+//! - [`WebhookNotifier`] - POSTs extraction events to a job tracker
 
+pub mod coercion;
 pub mod config;
 pub mod error;
 pub mod feedback;
+pub mod history;
 pub mod metrics;
 pub mod orchestrator;
+pub mod response;
+pub mod webhook;
 
-pub use config::ExtractionConfig;
+pub use coercion::{Coercion, CoercionKind, CoercionPolicy};
+pub use config::{DEFAULT_MAX_STORED_BYTES, ExtractionConfig, RetryStrategy};
 pub use error::{AttemptRecord, ExtractionError};
-pub use feedback::build_validation_feedback;
-pub use metrics::{ExtractionMetrics, estimate_tokens};
-pub use orchestrator::ExtractionOrchestrator;
+pub use feedback::{FeedbackContext, SubmissionChange, ValidationIssue, build_validation_feedback};
+pub use history::AttemptHistory;
+pub use metrics::{ExtractionMetrics, TokenUsage, estimate_tokens};
+pub use orchestrator::{ExtractionOrchestrator, ExtractionOrchestratorBuilder};
+pub use response::AgentResponse;
+pub use webhook::{ExtractionEvent, MetricsSummary, WebhookNotifier};
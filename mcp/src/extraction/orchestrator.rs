@@ -1,38 +1,79 @@
 //! Orchestration layer for retry/validation feedback loops in structured extraction.
 
+use rig_cli_process_core::redact;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use std::sync::Arc;
 use tokio::time::Instant;
 
-use super::config::ExtractionConfig;
+use super::coercion::CoercionPolicy;
+use super::config::{ExtractionConfig, RetryStrategy};
 use super::error::{AttemptRecord, ExtractionError};
 use super::feedback::{
-    build_parse_error_feedback, build_validation_feedback, collect_validation_errors,
+    FeedbackContext, ValidationIssue, build_attempt_digest, build_parse_error_feedback,
+    build_resubmission_feedback, build_validation_feedback, validator_issues,
 };
 use super::metrics::{ExtractionMetrics, estimate_tokens};
+use super::response::AgentResponse;
+use super::webhook::{self, ExtractionEvent, MetricsSummary, WebhookNotifier};
+use crate::tools::VersionedSchema;
+use crate::validation::SchemaValidation;
+
+/// Formats the feedback for a failed attempt.
+type FeedbackFormatter = Arc<dyn Fn(&FeedbackContext<'_>) -> String + Send + Sync>;
+
+/// Extracts the submission from an agent's output.
+type JsonExtractor = Arc<dyn Fn(&str) -> Result<Value, String> + Send + Sync>;
+
+/// Rewrites the prompt of an attempt before it is sent.
+type PromptTransform = Arc<dyn Fn(String, usize) -> String + Send + Sync>;
 
 /// Orchestrator for running bounded retry loops with validation feedback.
 ///
 /// The orchestrator validates agent output against a JSON schema, feeds back
 /// validation errors with full context, and tracks metrics across attempts.
+/// Use [`builder`](Self::builder) to replace how feedback is worded, how JSON
+/// is found in the output, or what each attempt's prompt looks like.
 pub struct ExtractionOrchestrator {
     schema: Value,
     config: ExtractionConfig,
+    feedback_formatter: Option<FeedbackFormatter>,
+    json_extractor: Option<JsonExtractor>,
+    prompt_transform: Option<PromptTransform>,
+    webhook: Option<WebhookNotifier>,
+    validation: SchemaValidation,
 }
 
 impl ExtractionOrchestrator {
     /// Creates a new orchestrator with the given schema and default configuration.
     #[must_use]
     pub fn new(schema: Value) -> Self {
+        Self::with_config(schema, ExtractionConfig::default())
+    }
+
+    /// Creates a new orchestrator with the given schema and configuration.
+    #[must_use]
+    pub const fn with_config(schema: Value, config: ExtractionConfig) -> Self {
         Self {
             schema,
-            config: ExtractionConfig::default(),
+            config,
+            feedback_formatter: None,
+            json_extractor: None,
+            prompt_transform: None,
+            webhook: None,
+            validation: SchemaValidation::new(),
         }
     }
 
-    /// Creates a new orchestrator with the given schema and configuration.
+    /// Returns a builder for an orchestrator with custom feedback, JSON
+    /// extraction or prompt hooks.
     #[must_use]
-    pub const fn with_config(schema: Value, config: ExtractionConfig) -> Self {
-        Self { schema, config }
+    pub fn builder(schema: Value) -> ExtractionOrchestratorBuilder {
+        ExtractionOrchestratorBuilder {
+            orchestrator: Self::new(schema),
+        }
     }
 
     /// Sets the maximum number of retry attempts (fluent builder pattern).
@@ -42,60 +83,128 @@ impl ExtractionOrchestrator {
         self
     }
 
+    /// Sets how retry prompts are built (fluent builder pattern).
+    #[must_use]
+    pub const fn retry_strategy(mut self, strategy: RetryStrategy) -> Self {
+        self.config.retry_strategy = strategy;
+        self
+    }
+
     /// Runs the extraction retry loop with the given agent function.
     ///
     /// The agent function receives a prompt string and returns the agent's text output
     /// (or error string). This abstraction allows any adapter to be used.
     ///
     /// Returns the validated JSON and metrics on success, or a typed error on failure.
+    /// Token usage in the metrics is estimated; use
+    /// [`extract_with_usage`](Self::extract_with_usage) when the adapter reports it.
     ///
     /// # Errors
     ///
     /// Returns `ExtractionError::MaxRetriesExceeded` if all retry attempts are exhausted.
     /// Returns `ExtractionError::SchemaError` if the schema is invalid.
     /// Returns `ExtractionError::AgentError` if the agent function returns an error.
+    // `ExtractionError` carries the full attempt history and metrics by design.
+    #[allow(clippy::result_large_err)]
+    pub async fn extract<F, Fut>(
+        &self,
+        agent_fn: F,
+        initial_prompt: String,
+    ) -> Result<(Value, ExtractionMetrics), ExtractionError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: std::future::Future<Output = Result<String, String>>,
+    {
+        self.run_attempts(agent_fn, initial_prompt).await
+    }
+
+    /// Runs the extraction retry loop with an agent function that reports token usage.
+    ///
+    /// Identical to [`extract`](Self::extract), except that the agent function
+    /// returns an [`AgentResponse`]. Usage reported by the adapter is summed into
+    /// [`ExtractionMetrics::input_tokens`] and [`ExtractionMetrics::output_tokens`];
+    /// attempts that report none are estimated from their text.
+    ///
+    /// # Errors
+    ///
+    /// See [`extract`](Self::extract).
+    #[allow(clippy::result_large_err)]
+    pub async fn extract_with_usage<F, Fut>(
+        &self,
+        agent_fn: F,
+        initial_prompt: String,
+    ) -> Result<(Value, ExtractionMetrics), ExtractionError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: std::future::Future<Output = Result<AgentResponse, String>>,
+    {
+        self.run_attempts(agent_fn, initial_prompt).await
+    }
+
     // Extraction retry loop is inherently complex with 5 stages (prompt, call, parse, validate, retry).
     // Splitting would fragment the state machine and reduce readability.
-    #[allow(clippy::too_many_lines)]
+    #[allow(clippy::too_many_lines, clippy::result_large_err)]
     #[tracing::instrument(
         name = "extraction_orchestrator_extract",
         skip_all,
         fields(max_attempts = self.config.max_attempts)
     )]
-    pub async fn extract<F, Fut>(
+    async fn run_attempts<F, Fut, R>(
         &self,
         agent_fn: F,
         initial_prompt: String,
     ) -> Result<(Value, ExtractionMetrics), ExtractionError>
     where
         F: Fn(String) -> Fut,
-        Fut: std::future::Future<Output = Result<String, String>>,
+        Fut: std::future::Future<Output = Result<R, String>>,
+        R: Into<AgentResponse>,
     {
         let start = Instant::now();
         let mut attempt_history: Vec<AttemptRecord> = Vec::new();
         let mut total_input_chars: usize = 0;
         let mut total_output_chars: usize = 0;
+        let mut usage_metrics = ExtractionMetrics::default();
         let mut current_prompt = initial_prompt.clone();
+        let max_attempts = self.config.max_attempts;
+        let run_id = webhook::run_id();
 
         // Validate schema compiles (early error if schema is invalid)
-        let _validator = jsonschema::Validator::new(&self.schema)
-            .map_err(|e| ExtractionError::SchemaError(e.to_string()))?;
+        let validator = self
+            .validation
+            .compile(&self.schema)
+            .map_err(ExtractionError::SchemaError)?;
 
         for attempt in 1..=self.config.max_attempts {
+            self.notify(
+                &run_id,
+                ExtractionEvent::AttemptStarted {
+                    attempt,
+                    max_attempts,
+                },
+            )
+            .await;
+
+            // The transform sees the untransformed prompt each time, so its
+            // changes do not pile up in appended retry prompts.
+            let sent_prompt = self.prompt_transform.as_ref().map_or_else(
+                || current_prompt.clone(),
+                |transform| transform(current_prompt.clone(), attempt),
+            );
+
             // Track input chars for this attempt
-            total_input_chars += current_prompt.chars().count();
+            total_input_chars += sent_prompt.chars().count();
 
             // Event 1: prompt_sent_to_agent
             tracing::debug!(
                 event = "prompt_sent_to_agent",
                 attempt = attempt,
-                prompt_chars = current_prompt.chars().count(),
+                prompt_chars = sent_prompt.chars().count(),
                 "prompt_sent_to_agent"
             );
 
             // Call agent with current prompt
-            let agent_output = match agent_fn(current_prompt.clone()).await {
-                Ok(output) => output,
+            let response: AgentResponse = match agent_fn(sent_prompt.clone()).await {
+                Ok(output) => output.into(),
                 Err(e) => {
                     tracing::warn!(
                         event = "extraction_outcome",
@@ -106,12 +215,22 @@ impl ExtractionOrchestrator {
                         error_kind = "agent_error",
                         "extraction_outcome"
                     );
+                    let event = ExtractionEvent::attempt_failed(
+                        attempt,
+                        max_attempts,
+                        std::slice::from_ref(&e),
+                    );
+                    self.notify(&run_id, event).await;
                     return Err(ExtractionError::AgentError(e));
                 }
             };
 
-            // Track output chars for this attempt
+            let agent_output = response.text;
+
+            // Track output chars and token usage for this attempt
             total_output_chars += agent_output.chars().count();
+            usage_metrics.record_usage(response.usage, &sent_prompt, &agent_output);
+            usage_metrics.tool_harvest.extend(response.tool_harvest);
 
             // Event 2: agent_response_received
             tracing::debug!(
@@ -122,11 +241,10 @@ impl ExtractionOrchestrator {
             );
 
             // Try to parse JSON from agent output
-            let parsed = match serde_json::from_str::<Value>(&agent_output) {
+            let mut parsed = match self.extract_json(&agent_output) {
                 Ok(value) => value,
-                Err(e) => {
+                Err(error_msg) => {
                     // Parse failure - record attempt with empty submitted_json
-                    let error_msg = e.to_string();
 
                     // Event 3: validation_result (parse failure)
                     tracing::debug!(
@@ -138,11 +256,16 @@ impl ExtractionOrchestrator {
                         "validation_result"
                     );
 
+                    let validation_errors = vec![format!("JSON parse error: {error_msg}")];
+                    let event =
+                        ExtractionEvent::attempt_failed(attempt, max_attempts, &validation_errors);
+                    self.notify(&run_id, event).await;
                     attempt_history.push(AttemptRecord {
                         attempt_number: attempt,
                         submitted_json: Value::Null,
-                        validation_errors: vec![format!("JSON parse error: {error_msg}")],
-                        raw_agent_output: agent_output.clone(),
+                        validation_errors,
+                        validation_issues: Vec::new(),
+                        raw_agent_output: self.config.stored_raw_output(&agent_output),
                         elapsed: start.elapsed(),
                     });
 
@@ -157,14 +280,23 @@ impl ExtractionOrchestrator {
                             "retry_decision"
                         );
 
-                        let feedback = build_parse_error_feedback(
-                            &agent_output,
-                            &error_msg,
-                            attempt,
-                            self.config.max_attempts,
-                            &self.schema,
+                        let feedback = self
+                            .custom_feedback(attempt, None, &agent_output, &[], &attempt_history)
+                            .unwrap_or_else(|| {
+                                build_parse_error_feedback(
+                                    &agent_output,
+                                    &error_msg,
+                                    attempt,
+                                    self.config.max_attempts,
+                                    &self.schema,
+                                )
+                            });
+                        current_prompt = self.retry_prompt(
+                            &initial_prompt,
+                            &current_prompt,
+                            &attempt_history,
+                            &feedback,
                         );
-                        current_prompt = format!("{current_prompt}\n\n{feedback}");
                         continue;
                     }
 
@@ -173,8 +305,23 @@ impl ExtractionOrchestrator {
                 }
             };
 
+            // Fix trivially mistyped values before validating them
+            if let Some(policy) = &self.config.coercion {
+                let coercions = policy.apply(&self.schema, &mut parsed, attempt);
+                if !coercions.is_empty() {
+                    tracing::debug!(
+                        event = "coercions_applied",
+                        attempt = attempt,
+                        coercion_count = coercions.len(),
+                        "coercions_applied"
+                    );
+                    usage_metrics.coercions.extend(coercions);
+                }
+            }
+
             // Validate parsed JSON against schema
-            let errors = collect_validation_errors(&self.schema, &parsed);
+            let issues = validator_issues(&validator, &self.schema, &parsed);
+            let errors: Vec<String> = issues.iter().map(ToString::to_string).collect();
 
             if errors.is_empty() {
                 // Event 3: validation_result (success)
@@ -200,9 +347,18 @@ impl ExtractionOrchestrator {
                 let metrics = ExtractionMetrics {
                     total_attempts: attempt,
                     wall_time: start.elapsed(),
-                    estimated_input_tokens: estimate_tokens(&current_prompt),
+                    estimated_input_tokens: estimate_tokens(&sent_prompt),
                     estimated_output_tokens: estimate_tokens(&agent_output),
+                    ..usage_metrics
                 };
+                self.notify(
+                    &run_id,
+                    ExtractionEvent::ExtractionSucceeded {
+                        attempts: attempt,
+                        metrics: MetricsSummary::from(&metrics),
+                    },
+                )
+                .await;
                 return Ok((parsed, metrics));
             }
 
@@ -216,11 +372,14 @@ impl ExtractionOrchestrator {
             );
 
             // Validation failed - record attempt
+            let event = ExtractionEvent::attempt_failed(attempt, max_attempts, &errors);
+            self.notify(&run_id, event).await;
             attempt_history.push(AttemptRecord {
                 attempt_number: attempt,
-                submitted_json: parsed.clone(),
+                submitted_json: self.config.stored_submission(&parsed),
                 validation_errors: errors.clone(),
-                raw_agent_output: agent_output.clone(),
+                validation_issues: issues.clone(),
+                raw_agent_output: self.config.stored_raw_output(&agent_output),
                 elapsed: start.elapsed(),
             });
 
@@ -235,15 +394,23 @@ impl ExtractionOrchestrator {
                     "retry_decision"
                 );
 
-                let feedback = build_validation_feedback(
-                    &self.schema,
-                    &parsed,
-                    &errors,
-                    attempt,
-                    self.config.max_attempts,
+                let feedback = self
+                    .custom_feedback(
+                        attempt,
+                        Some(&parsed),
+                        &agent_output,
+                        &issues,
+                        &attempt_history,
+                    )
+                    .unwrap_or_else(|| {
+                        self.validation_feedback(&parsed, &errors, attempt, &attempt_history)
+                    });
+                current_prompt = self.retry_prompt(
+                    &initial_prompt,
+                    &current_prompt,
+                    &attempt_history,
+                    &feedback,
                 );
-                // Conversation continuation strategy
-                current_prompt = format!("{current_prompt}\n\n{feedback}");
             }
         }
 
@@ -262,17 +429,122 @@ impl ExtractionOrchestrator {
             wall_time: start.elapsed(),
             estimated_input_tokens: total_input_chars.saturating_div(4),
             estimated_output_tokens: total_output_chars.saturating_div(4),
+            ..usage_metrics
         };
+        self.notify(
+            &run_id,
+            ExtractionEvent::BudgetExceeded {
+                attempts: max_attempts,
+                metrics: MetricsSummary::from(&metrics),
+            },
+        )
+        .await;
 
         Err(ExtractionError::MaxRetriesExceeded {
             attempts: self.config.max_attempts,
             max_attempts: self.config.max_attempts,
             history: attempt_history,
-            raw_output: current_prompt,
+            raw_output: self.config.stored_raw_output(&current_prompt),
             metrics,
         })
     }
 
+    /// Sends `event` to the webhook, if one is set.
+    async fn notify(&self, run_id: &str, event: ExtractionEvent) {
+        if let Some(webhook) = &self.webhook {
+            webhook.notify(run_id, &event).await;
+        }
+    }
+
+    /// Parses the submission out of `output` with the custom extractor, or
+    /// as plain JSON. Errors are the message shown to the agent.
+    fn extract_json(&self, output: &str) -> Result<Value, String> {
+        self.json_extractor.as_ref().map_or_else(
+            || serde_json::from_str(output).map_err(|e| e.to_string()),
+            |extractor| extractor(output),
+        )
+    }
+
+    /// Feedback from the custom formatter for the last attempt in `history`,
+    /// if one is set.
+    fn custom_feedback(
+        &self,
+        attempt: usize,
+        submission: Option<&Value>,
+        raw_output: &str,
+        issues: &[ValidationIssue],
+        history: &[AttemptRecord],
+    ) -> Option<String> {
+        let formatter = self.feedback_formatter.as_ref()?;
+        let errors = history
+            .last()
+            .map_or(&[][..], |record| &record.validation_errors[..]);
+        Some(formatter(&FeedbackContext {
+            schema: &self.schema,
+            attempt,
+            max_attempts: self.config.max_attempts,
+            submission,
+            raw_output,
+            errors,
+            issues,
+            history,
+        }))
+    }
+
+    /// The built-in feedback for a submission that failed validation.
+    fn validation_feedback(
+        &self,
+        parsed: &Value,
+        errors: &[String],
+        attempt: usize,
+        history: &[AttemptRecord],
+    ) -> String {
+        let mut feedback = build_validation_feedback(
+            &self.schema,
+            parsed,
+            errors,
+            attempt,
+            self.config.max_attempts,
+        );
+        // Point out a resubmitted value, or what little changed, so the agent
+        // stops retrying the same submission.
+        if let Some(resubmission) = build_resubmission_feedback(history) {
+            tracing::debug!(
+                event = "resubmission_feedback",
+                attempt = attempt,
+                "resubmission_feedback"
+            );
+            feedback.push_str("\n\n");
+            feedback.push_str(&resubmission);
+        }
+        feedback
+    }
+
+    /// Builds the next attempt's prompt according to the retry strategy.
+    ///
+    /// `history` ends with the attempt that `feedback` describes.
+    fn retry_prompt(
+        &self,
+        initial_prompt: &str,
+        current_prompt: &str,
+        history: &[AttemptRecord],
+        feedback: &str,
+    ) -> String {
+        match self.config.retry_strategy {
+            RetryStrategy::AppendToPrompt => format!("{current_prompt}\n\n{feedback}"),
+            RetryStrategy::FreshPromptWithLastError => format!("{initial_prompt}\n\n{feedback}"),
+            RetryStrategy::SummarizedHistory => {
+                let earlier = &history[..history.len().saturating_sub(1)];
+                if earlier.is_empty() {
+                    format!("{initial_prompt}\n\n{feedback}")
+                } else {
+                    let digest = build_attempt_digest(earlier);
+                    format!("{initial_prompt}\n\n{digest}\n{feedback}")
+                }
+            }
+        }
+    }
+
     /// Convenience method that extracts and deserializes to a typed value.
     ///
     /// This calls `extract()` and then deserializes the resulting `Value` to `T`.
@@ -286,6 +558,7 @@ impl ExtractionOrchestrator {
         skip_all,
         fields(max_attempts = self.config.max_attempts)
     )]
+    #[allow(clippy::result_large_err)]
     pub async fn extract_typed<T, F, Fut>(
         &self,
         agent_fn: F,
@@ -300,13 +573,173 @@ impl ExtractionOrchestrator {
 
         let typed =
             serde_json::from_value(value.clone()).map_err(|e| ExtractionError::ParseError {
-                message: format!("Deserialization to target type failed: {e}"),
-                raw_text: serde_json::to_string(&value).unwrap_or_else(|_| value.to_string()),
+                message: format!(
+                    "Deserialization to target type failed: {}",
+                    redact(&e.to_string())
+                ),
+                raw_text: self.config.stored_submission_text(&value),
                 attempt: metrics.total_attempts,
             })?;
 
         Ok((typed, metrics))
     }
+
+    /// Extracts a value submitted in any version of `versioned` and upgrades
+    /// it to the latest `T`.
+    ///
+    /// Build the orchestrator from [`VersionedSchema::schema`] so submissions
+    /// in older versions pass validation; the accepted JSON is then handed to
+    /// [`VersionedSchema::upgrade`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExtractionError::ParseError` if the validated JSON cannot be
+    /// upgraded to `T`, and otherwise the errors of [`extract`](Self::extract).
+    #[tracing::instrument(
+        name = "extraction_orchestrator_extract_versioned",
+        skip_all,
+        fields(max_attempts = self.config.max_attempts, schema_version = versioned.version())
+    )]
+    #[allow(clippy::result_large_err)]
+    pub async fn extract_versioned<T, F, Fut>(
+        &self,
+        versioned: &VersionedSchema<T>,
+        agent_fn: F,
+        initial_prompt: String,
+    ) -> Result<(T, ExtractionMetrics), ExtractionError>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+        F: Fn(String) -> Fut,
+        Fut: std::future::Future<Output = Result<String, String>>,
+    {
+        let (value, metrics) = self.extract(agent_fn, initial_prompt).await?;
+
+        let upgraded =
+            versioned
+                .upgrade(value.clone())
+                .map_err(|e| ExtractionError::ParseError {
+                    message: format!("Schema migration failed: {}", redact(&e.to_string())),
+                    raw_text: self.config.stored_submission_text(&value),
+                    attempt: metrics.total_attempts,
+                })?;
+
+        Ok((upgraded, metrics))
+    }
+}
+
+/// Builder for an [`ExtractionOrchestrator`] with custom hooks.
+///
+/// ```
+/// use rig_cli_mcp::extraction::ExtractionOrchestrator;
+/// use serde_json::json;
+///
+/// let orchestrator = ExtractionOrchestrator::builder(json!({"type": "object"}))
+///     .max_attempts(4)
+///     .feedback_formatter(|ctx| {
+///         format!("Try {} of {} failed:\n{}", ctx.attempt, ctx.max_attempts, ctx.errors.join("\n"))
+///     })
+///     .json_extractor(|output| {
+///         let start = output.find('{').ok_or("no JSON object found")?;
+///         serde_json::from_str(&output[start..]).map_err(|e| e.to_string())
+///     })
+///     .prompt_transform(|prompt, attempt| format!("[attempt {attempt}]\n{prompt}"))
+///     .build();
+/// ```
+pub struct ExtractionOrchestratorBuilder {
+    orchestrator: ExtractionOrchestrator,
+}
+
+impl ExtractionOrchestratorBuilder {
+    /// Replaces the retry configuration.
+    #[must_use]
+    pub const fn config(mut self, config: ExtractionConfig) -> Self {
+        self.orchestrator.config = config;
+        self
+    }
+
+    /// Sets the maximum number of attempts.
+    #[must_use]
+    pub const fn max_attempts(mut self, max: usize) -> Self {
+        self.orchestrator.config.max_attempts = max;
+        self
+    }
+
+    /// Sets how retry prompts are built.
+    #[must_use]
+    pub const fn retry_strategy(mut self, strategy: RetryStrategy) -> Self {
+        self.orchestrator.config.retry_strategy = strategy;
+        self
+    }
+
+    /// Applies `policy`'s coercions to each submission before validation.
+    #[must_use]
+    pub const fn coercion(mut self, policy: CoercionPolicy) -> Self {
+        self.orchestrator.config.coercion = Some(policy);
+        self
+    }
+
+    /// Words the feedback for failed attempts with `formatter` instead of
+    /// the built-in parse error and validation messages.
+    ///
+    /// The feedback is still combined with the prompt according to the
+    /// [`RetryStrategy`].
+    #[must_use]
+    pub fn feedback_formatter(
+        mut self,
+        formatter: impl Fn(&FeedbackContext<'_>) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.orchestrator.feedback_formatter = Some(Arc::new(formatter));
+        self
+    }
+
+    /// Finds the submission in the agent's output with `extractor` instead of
+    /// parsing the whole output as JSON, e.g. to strip code fences or prose.
+    ///
+    /// An `Err` counts as a parse error and its message is fed back.
+    #[must_use]
+    pub fn json_extractor(
+        mut self,
+        extractor: impl Fn(&str) -> Result<Value, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.orchestrator.json_extractor = Some(Arc::new(extractor));
+        self
+    }
+
+    /// Rewrites each attempt's prompt, given the attempt number counting
+    /// from 1, just before it is sent.
+    ///
+    /// The transform receives the prompt the retry strategy built, never its
+    /// own earlier output.
+    #[must_use]
+    pub fn prompt_transform(
+        mut self,
+        transform: impl Fn(String, usize) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.orchestrator.prompt_transform = Some(Arc::new(transform));
+        self
+    }
+
+    /// Reports each attempt and the outcome to `notifier`'s URL; see
+    /// [`webhook`](super::webhook).
+    #[must_use]
+    pub fn webhook(mut self, notifier: WebhookNotifier) -> Self {
+        self.orchestrator.webhook = Some(notifier);
+        self
+    }
+
+    /// Compiles the schema with `validation`'s draft, `format` checks and
+    /// `$ref` policy instead of the `jsonschema` defaults.
+    #[must_use]
+    pub fn schema_validation(mut self, validation: SchemaValidation) -> Self {
+        self.orchestrator.validation = validation;
+        self
+    }
+
+    /// Builds the orchestrator.
+    #[must_use]
+    pub fn build(self) -> ExtractionOrchestrator {
+        self.orchestrator
+    }
 }
 
 #[cfg(test)]
@@ -473,6 +906,46 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_attempt_records_cap_stored_output() {
+        let schema = json!({"type": "object", "properties": {"x": {"type": "number"}}});
+        let config = ExtractionConfig::default()
+            .with_max_attempts(2)
+            .with_max_raw_output_bytes(Some(64))
+            .with_max_submitted_json_bytes(Some(64));
+        let orchestrator = ExtractionOrchestrator::with_config(schema.clone(), config.clone());
+
+        let huge = "y".repeat(10_000);
+        let agent_fn = move |_prompt: String| {
+            let output = json!({"x": huge}).to_string();
+            async move { Ok(output) }
+        };
+        let Err(ExtractionError::MaxRetriesExceeded { history, .. }) =
+            orchestrator.extract(agent_fn, "initial".to_string()).await
+        else {
+            panic!("Expected MaxRetriesExceeded");
+        };
+        for record in &history {
+            assert!(record.raw_agent_output.len() < 200);
+            assert!(record.raw_agent_output.contains("[truncated"));
+            let submitted = record.submitted_json.as_str().unwrap();
+            assert!(submitted.starts_with(r#"{"x":"yyy"#));
+        }
+        assert_eq!(history.len(), 2);
+
+        let orchestrator = ExtractionOrchestrator::with_config(
+            schema,
+            config.with_max_attempts(1).with_raw_output_stored(false),
+        );
+        let agent_fn = |_prompt: String| async { Ok("not json".to_string()) };
+        let Err(ExtractionError::MaxRetriesExceeded { history, .. }) =
+            orchestrator.extract(agent_fn, "initial".to_string()).await
+        else {
+            panic!("Expected MaxRetriesExceeded");
+        };
+        assert_eq!(history[0].raw_agent_output, "");
+    }
+
     #[tokio::test]
     async fn test_extraction_parse_failure_counts_against_budget() {
         // Test that JSON parse failures count against retry budget
@@ -507,12 +980,15 @@ mod tests {
                     history[0].validation_errors
                 );
                 assert_eq!(history[0].submitted_json, serde_json::Value::Null);
+                assert_eq!(history[0].validation_issues, Vec::new());
 
                 // Second attempt: validation failure
                 assert!(
                     !history[1].validation_errors[0].contains("JSON parse error"),
                     "Second attempt should be validation error"
                 );
+                assert_eq!(history[1].validation_issues[0].keyword, "type");
+                assert_eq!(history[1].validation_issues[0].actual, json!("string"));
             }
             Ok(_) => panic!("Expected MaxRetriesExceeded"),
             Err(e) => panic!("Unexpected error: {e:?}"),
@@ -674,4 +1150,348 @@ mod tests {
             Err(e) => panic!("Unexpected error type: {e:?}"),
         }
     }
+
+    #[tokio::test]
+    async fn test_extraction_aggregates_reported_usage() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"name": {"type": "string"}},
+            "required": ["name"]
+        });
+
+        let orchestrator = ExtractionOrchestrator::new(schema).max_attempts(3);
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counter_clone = counter.clone();
+        let validate = crate::harvest::ToolHarvest {
+            calls: vec![crate::audit::ToolCallRecord {
+                timestamp_ms: 0,
+                server: "rig_mcp".to_string(),
+                tool: "validate_json".to_string(),
+                arguments: json!({"json": {"name": 1}}),
+                result: "JSON validation failed.".to_string(),
+                is_error: false,
+                duration_ms: 1,
+                client_name: None,
+                client_version: None,
+            }],
+        };
+
+        let agent_fn = move |_prompt: String| {
+            let counter = counter_clone.clone();
+            let validate = validate.clone();
+            async move {
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => Ok(AgentResponse::new(r#"{"name": 1}"#)
+                        .with_usage(900, 12)
+                        .with_tool_harvest(validate)),
+                    1 => Ok(AgentResponse::new("not json")),
+                    _ => Ok(AgentResponse::new(r#"{"name": "ok"}"#).with_usage(1100, 9)),
+                }
+            }
+        };
+
+        let (value, metrics) = orchestrator
+            .extract_with_usage(agent_fn, "initial".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(value["name"], "ok");
+        assert_eq!(metrics.total_attempts, 3);
+        assert_eq!(metrics.reported_usage_attempts, 2);
+        assert!(!metrics.usage_is_exact());
+        // The second attempt's estimate is added to the reported counts.
+        assert!(metrics.input_tokens > 2000);
+        assert_eq!(metrics.output_tokens, 12 + 2 + 9);
+        assert_eq!(metrics.tool_harvest.calls_to("validate_json"), 1);
+        assert_eq!(metrics.tool_harvest.validation_failures(), 1);
+    }
+
+    /// Runs three failing attempts under `strategy` and returns the prompts sent.
+    async fn retry_prompts(strategy: RetryStrategy) -> Vec<String> {
+        let schema = json!({
+            "type": "object",
+            "properties": {"name": {"type": "string"}},
+            "required": ["name"]
+        });
+        let orchestrator = ExtractionOrchestrator::new(schema)
+            .max_attempts(3)
+            .retry_strategy(strategy);
+
+        let prompts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&prompts);
+        let agent_fn = move |prompt: String| {
+            seen.lock().unwrap().push(prompt);
+            async { Ok(r#"{"name": 1}"#.to_string()) }
+        };
+
+        let result = orchestrator.extract(agent_fn, "initial".to_string()).await;
+        assert!(matches!(
+            result,
+            Err(ExtractionError::MaxRetriesExceeded { .. })
+        ));
+        prompts.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn test_retry_strategies_bound_prompt_growth() {
+        let appended = retry_prompts(RetryStrategy::AppendToPrompt).await;
+        assert!(appended[2].starts_with(&appended[1]));
+        assert!(appended[2].contains("Attempt 1/3"));
+
+        let fresh = retry_prompts(RetryStrategy::FreshPromptWithLastError).await;
+        assert!(fresh[2].starts_with("initial\n\nAttempt 2/3"));
+        assert!(!fresh[2].contains("Attempt 1/3"));
+        // Every attempt resubmits the same value.
+        assert!(!fresh[1].contains("You already tried"));
+        assert!(fresh[2].contains("You already tried this exact value in attempt 1"));
+
+        let summarized = retry_prompts(RetryStrategy::SummarizedHistory).await;
+        assert_eq!(summarized[1], fresh[1]);
+        assert!(summarized[2].starts_with("initial\n\nEarlier attempts (summary):\n"));
+        assert!(summarized[2].contains("  - Attempt 1: 1 error(s): At path '/name'"));
+        assert!(summarized[2].contains("Attempt 2/3: JSON validation failed."));
+        assert!(!summarized[2].contains("Attempt 1/3"));
+        assert!(summarized[2].len() < appended[2].len());
+    }
+
+    #[tokio::test]
+    async fn test_builder_hooks_shape_every_attempt() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"name": {"type": "string"}},
+            "required": ["name"]
+        });
+        let orchestrator = ExtractionOrchestrator::builder(schema)
+            .max_attempts(3)
+            .retry_strategy(RetryStrategy::AppendToPrompt)
+            .feedback_formatter(|ctx| {
+                format!(
+                    "FIX {}/{} ({}): {}",
+                    ctx.attempt,
+                    ctx.max_attempts,
+                    ctx.submission.map_or("unparsed", |_| "invalid"),
+                    ctx.issues
+                        .iter()
+                        .map(|issue| issue.keyword.as_str())
+                        .chain(ctx.submission.is_none().then_some("parse"))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+            })
+            .json_extractor(|output| {
+                let json = output
+                    .strip_prefix("```json\n")
+                    .and_then(|rest| rest.strip_suffix("\n```"))
+                    .ok_or("no fenced JSON block")?;
+                serde_json::from_str(json).map_err(|e| e.to_string())
+            })
+            .prompt_transform(|prompt, attempt| format!("#{attempt} {prompt}"))
+            .build();
+
+        let outputs = [
+            "not fenced",
+            "```json\n{\"name\": 1}\n```",
+            "```json\n{\"name\": \"Ada\"}\n```",
+        ];
+        let prompts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&prompts);
+        let agent_fn = move |prompt: String| {
+            let mut seen = seen.lock().unwrap();
+            let output = outputs[seen.len()].to_string();
+            seen.push(prompt);
+            drop(seen);
+            async move { Ok(output) }
+        };
+
+        let (value, metrics) = orchestrator
+            .extract(agent_fn, "initial".to_string())
+            .await
+            .unwrap();
+        assert_eq!(value, json!({"name": "Ada"}));
+        assert_eq!(metrics.total_attempts, 3);
+
+        let prompts = prompts.lock().unwrap();
+        assert_eq!(prompts[0], "#1 initial");
+        assert_eq!(prompts[1], "#2 initial\n\nFIX 1/3 (unparsed): parse");
+        // The transform is applied to the untransformed prompt only once.
+        assert_eq!(
+            prompts[2],
+            "#3 initial\n\nFIX 1/3 (unparsed): parse\n\nFIX 2/3 (invalid): type"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_extractor_error_is_fed_back_as_parse_error() {
+        let orchestrator = ExtractionOrchestrator::builder(json!({"type": "object"}))
+            .max_attempts(2)
+            .json_extractor(|_| Err("no JSON here".to_string()))
+            .build();
+
+        let err = orchestrator
+            .extract(|_| async { Ok("{}".to_string()) }, "initial".to_string())
+            .await
+            .unwrap_err();
+        let ExtractionError::MaxRetriesExceeded { history, .. } = err else {
+            panic!("expected MaxRetriesExceeded, got {err:?}");
+        };
+        assert_eq!(
+            history[0].validation_errors,
+            ["JSON parse error: no JSON here"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_coercion_fixes_submission_before_validation() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"count": {"type": "integer"}, "done": {"type": "boolean"}},
+            "required": ["count", "done"]
+        });
+        let agent_fn =
+            |_prompt: String| async { Ok(r#"{"count": "3", "done": "no"}"#.to_string()) };
+
+        let (value, metrics) = ExtractionOrchestrator::builder(schema.clone())
+            .max_attempts(1)
+            .coercion(CoercionPolicy::default())
+            .build()
+            .extract(agent_fn, "initial".to_string())
+            .await
+            .unwrap();
+        assert_eq!(value, json!({"count": 3, "done": false}));
+        let mut kinds: Vec<_> = metrics.coercions.iter().map(|c| c.kind).collect();
+        kinds.sort_unstable_by_key(|kind| *kind as u8);
+        assert_eq!(
+            kinds,
+            [
+                crate::extraction::CoercionKind::NumericString,
+                crate::extraction::CoercionKind::BooleanWord
+            ]
+        );
+        assert!(metrics.coercions.iter().all(|c| c.attempt == 1));
+
+        // Without a policy the same submission fails validation.
+        let err = ExtractionOrchestrator::new(schema)
+            .max_attempts(1)
+            .extract(agent_fn, "initial".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ExtractionError::MaxRetriesExceeded { .. }));
+    }
+
+    #[derive(JsonSchema, serde::Deserialize)]
+    struct TaskV1 {
+        title: String,
+    }
+
+    #[derive(JsonSchema, Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Task {
+        title: String,
+        done: bool,
+    }
+
+    #[tokio::test]
+    async fn test_extract_versioned_upgrades_old_submission() {
+        let versioned = VersionedSchema::<Task>::new(2).migration(1, |old: TaskV1| Task {
+            title: old.title,
+            done: false,
+        });
+        let orchestrator = ExtractionOrchestrator::new(versioned.schema()).max_attempts(1);
+
+        let agent_fn =
+            |_prompt: String| async { Ok(r#"{"schema_version": 1, "title": "ship"}"#.to_string()) };
+
+        let (task, metrics) = orchestrator
+            .extract_versioned(&versioned, agent_fn, "initial".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            task,
+            Task {
+                title: "ship".to_string(),
+                done: false,
+            }
+        );
+        assert_eq!(metrics.total_attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_receives_signed_lifecycle_events() {
+        type Received = Arc<std::sync::Mutex<Vec<(Option<String>, axum::body::Bytes)>>>;
+        let received: Received = Arc::default();
+        let app = axum::Router::new()
+            .route(
+                "/hook",
+                axum::routing::post(
+                    |axum::extract::State(received): axum::extract::State<Received>,
+                     headers: axum::http::HeaderMap,
+                     body: axum::body::Bytes| async move {
+                        let signature = headers
+                            .get(webhook::SIGNATURE_HEADER)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string);
+                        received.lock().unwrap().push((signature, body));
+                    },
+                ),
+            )
+            .with_state(Arc::clone(&received));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let schema = json!({
+            "type": "object",
+            "properties": {"name": {"type": "string"}},
+            "required": ["name"]
+        });
+        let orchestrator = ExtractionOrchestrator::builder(schema)
+            .max_attempts(2)
+            .webhook(WebhookNotifier::new(url).with_secret("s3cret"))
+            .build();
+        let counter = AtomicUsize::new(0);
+        let agent_fn = |_prompt: String| {
+            let output = if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                r#"{"age": 3}"#
+            } else {
+                r#"{"name": "Ada"}"#
+            };
+            async move { Ok(output.to_string()) }
+        };
+        orchestrator
+            .extract(agent_fn, "Extract".to_string())
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap().clone();
+        let events: Vec<Value> = received
+            .iter()
+            .map(|(signature, body)| {
+                assert_eq!(
+                    signature.as_deref(),
+                    Some(webhook::sign(b"s3cret", body).as_str())
+                );
+                serde_json::from_slice(body).unwrap()
+            })
+            .collect();
+        let names: Vec<&str> = events
+            .iter()
+            .map(|event| event["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "attempt_started",
+                "attempt_failed",
+                "attempt_started",
+                "extraction_succeeded"
+            ]
+        );
+        assert!(
+            events
+                .iter()
+                .all(|event| event["run_id"] == events[0]["run_id"])
+        );
+        assert_eq!(events[3]["attempts"], 2);
+    }
 }
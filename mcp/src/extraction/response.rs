@@ -0,0 +1,60 @@
+//! Agent response type passed back to the extraction orchestrator.
+
+use super::metrics::TokenUsage;
+use crate::harvest::ToolHarvest;
+
+/// Output of one agent call made by the [`ExtractionOrchestrator`](super::ExtractionOrchestrator).
+///
+/// Agent functions may return a plain `String`, which converts into a
+/// response without usage, or an `AgentResponse` carrying the token counts
+/// the CLI reported for the call and the tool calls it harvested.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgentResponse {
+    /// The agent's text output.
+    pub text: String,
+    /// Token usage reported by the adapter, if any.
+    pub usage: Option<TokenUsage>,
+    /// Tool calls the agent made during the call, if they were harvested.
+    pub tool_harvest: ToolHarvest,
+}
+
+impl AgentResponse {
+    /// Creates a response without usage information.
+    #[must_use]
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            usage: None,
+            tool_harvest: ToolHarvest::default(),
+        }
+    }
+
+    /// Attaches the token usage reported for this call.
+    #[must_use]
+    pub const fn with_usage(mut self, input_tokens: u64, output_tokens: u64) -> Self {
+        self.usage = Some(TokenUsage {
+            input_tokens,
+            output_tokens,
+        });
+        self
+    }
+
+    /// Attaches the tool calls harvested during this call.
+    #[must_use]
+    pub fn with_tool_harvest(mut self, harvest: ToolHarvest) -> Self {
+        self.tool_harvest = harvest;
+        self
+    }
+}
+
+impl From<String> for AgentResponse {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<&str> for AgentResponse {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
@@ -0,0 +1,291 @@
+//! Webhook notifications of an extraction's progress.
+//!
+//! An [`ExtractionOrchestrator`](super::ExtractionOrchestrator) given a
+//! [`WebhookNotifier`] POSTs one JSON document per [`ExtractionEvent`] to
+//! the notifier's URL, so a job tracker can follow extractions without
+//! polling:
+//!
+//! ```json
+//! {"run_id": "199e8f3a2c4-1f2a-1", "timestamp_ms": 1760000000000,
+//!  "event": "attempt_failed", "attempt": 1, "max_attempts": 3,
+//!  "errors": ["\"name\" is a required property"]}
+//! ```
+//!
+//! Every event of one extraction carries the same `run_id`. With a
+//! [secret](WebhookNotifier::with_secret), each request has an
+//! `X-Rig-Signature: sha256=<hex>` header holding the HMAC-SHA256 of the
+//! body; receivers recompute it with [`sign`] to reject forged events.
+//!
+//! Delivery is best effort: a failed or slow request is logged and the
+//! extraction carries on.
+
+use rig_cli_process_core::redact;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::metrics::ExtractionMetrics;
+
+/// Header naming the event of a request.
+pub const EVENT_HEADER: &str = "X-Rig-Event";
+
+/// Header carrying the body's signature, `sha256=<hex>`.
+pub const SIGNATURE_HEADER: &str = "X-Rig-Signature";
+
+/// How long a delivery may take by default.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One step of an extraction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExtractionEvent {
+    /// An attempt's prompt is about to be sent.
+    AttemptStarted {
+        /// The attempt, counting from 1.
+        attempt: usize,
+        /// Attempts the extraction may make.
+        max_attempts: usize,
+    },
+    /// An attempt's output did not parse or validate, or the agent failed.
+    AttemptFailed {
+        /// The attempt, counting from 1.
+        attempt: usize,
+        /// Attempts the extraction may make.
+        max_attempts: usize,
+        /// Why the attempt failed, redacted.
+        errors: Vec<String>,
+    },
+    /// An attempt produced a valid submission.
+    ExtractionSucceeded {
+        /// Attempts made.
+        attempts: usize,
+        /// Metrics of the whole extraction.
+        metrics: MetricsSummary,
+    },
+    /// Every attempt failed validation.
+    BudgetExceeded {
+        /// Attempts made.
+        attempts: usize,
+        /// Metrics of the whole extraction.
+        metrics: MetricsSummary,
+    },
+}
+
+impl ExtractionEvent {
+    /// The event's name, as in the body's `event` field.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::AttemptStarted { .. } => "attempt_started",
+            Self::AttemptFailed { .. } => "attempt_failed",
+            Self::ExtractionSucceeded { .. } => "extraction_succeeded",
+            Self::BudgetExceeded { .. } => "budget_exceeded",
+        }
+    }
+
+    /// An [`AttemptFailed`](Self::AttemptFailed) event with `errors`
+    /// redacted.
+    pub(crate) fn attempt_failed(attempt: usize, max_attempts: usize, errors: &[String]) -> Self {
+        Self::AttemptFailed {
+            attempt,
+            max_attempts,
+            errors: errors
+                .iter()
+                .map(|error| redact(error).into_owned())
+                .collect(),
+        }
+    }
+}
+
+/// The parts of [`ExtractionMetrics`] sent with an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MetricsSummary {
+    /// Wall-clock time of the extraction, in milliseconds.
+    pub wall_time_ms: u64,
+    /// Input tokens over all attempts.
+    pub input_tokens: u64,
+    /// Output tokens over all attempts.
+    pub output_tokens: u64,
+    /// Whether the token counts were all reported rather than estimated.
+    pub usage_is_exact: bool,
+    /// Tool calls made over all attempts, where the agent returned them.
+    pub tool_calls: usize,
+}
+
+impl From<&ExtractionMetrics> for MetricsSummary {
+    fn from(metrics: &ExtractionMetrics) -> Self {
+        Self {
+            wall_time_ms: u64::try_from(metrics.wall_time.as_millis()).unwrap_or(u64::MAX),
+            input_tokens: metrics.input_tokens,
+            output_tokens: metrics.output_tokens,
+            usage_is_exact: metrics.usage_is_exact(),
+            tool_calls: metrics.tool_harvest.calls.len(),
+        }
+    }
+}
+
+/// Body of a webhook request.
+#[derive(Serialize)]
+struct Delivery<'a> {
+    run_id: &'a str,
+    timestamp_ms: u64,
+    #[serde(flatten)]
+    event: &'a ExtractionEvent,
+}
+
+/// Sends [`ExtractionEvent`]s to a URL.
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    url: String,
+    secret: Option<Vec<u8>>,
+    timeout: Duration,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for WebhookNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookNotifier")
+            .field("url", &self.url)
+            .field("signed", &self.secret.is_some())
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebhookNotifier {
+    /// Creates a notifier sending unsigned events to `url`, giving up on
+    /// a delivery after 5 seconds.
+    #[must_use]
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+            timeout: DEFAULT_TIMEOUT,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Signs every request with `secret`; see [`SIGNATURE_HEADER`].
+    #[must_use]
+    pub fn with_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Sets how long a delivery may take.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends `event` of the extraction `run_id`. Failures are logged, not
+    /// returned.
+    pub async fn notify(&self, run_id: &str, event: &ExtractionEvent) {
+        let delivery = Delivery {
+            run_id,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| {
+                    u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+                }),
+            event,
+        };
+        let body = match serde_json::to_vec(&delivery) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!(target: "rig", error = %e, "Failed to serialize webhook event");
+                return;
+            }
+        };
+        let mut request = self
+            .client
+            .post(&self.url)
+            .timeout(self.timeout)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event.name());
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+        }
+        let sent = request
+            .body(body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(e) = sent {
+            tracing::warn!(
+                target: "rig",
+                event = event.name(),
+                error = %e,
+                "Failed to deliver webhook event"
+            );
+        }
+    }
+}
+
+/// The [`SIGNATURE_HEADER`] value of `body` under `secret`:
+/// `sha256=` and the hex HMAC-SHA256.
+#[must_use]
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mac = hmac_sha256::HMAC::mac(body, secret);
+    let mut signature = String::with_capacity(7 + 2 * mac.len());
+    signature.push_str("sha256=");
+    for byte in mac {
+        let _ = write!(signature, "{byte:02x}");
+    }
+    signature
+}
+
+/// An identifier for one extraction, unique within the process and
+/// unlikely to repeat across processes.
+pub(crate) fn run_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    format!(
+        "{started:x}-{:x}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_matches_rfc_4231() {
+        // Test case 2 of RFC 4231.
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_events_serialize_flat_with_their_name() {
+        let event = ExtractionEvent::attempt_failed(2, 3, &["missing name".to_string()]);
+        let body = serde_json::to_value(Delivery {
+            run_id: "run",
+            timestamp_ms: 7,
+            event: &event,
+        })
+        .unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "run_id": "run",
+                "timestamp_ms": 7,
+                "event": "attempt_failed",
+                "attempt": 2,
+                "max_attempts": 3,
+                "errors": ["missing name"],
+            })
+        );
+        assert_eq!(event.name(), body["event"]);
+        assert_ne!(run_id(), run_id());
+    }
+}
@@ -0,0 +1,329 @@
+//! Read-only file access for contained agents.
+//!
+//! MCP-enforced runs disable the CLI's builtin tools, so an agent cannot
+//! open the reference files it is pointed at. [`FileAccess`] gives it a
+//! `read_file` and `list_dir` tool pair instead, confined to one root
+//! directory. Paths are relative to the root; absolute paths, `..` and
+//! symlinks leading outside it are refused. Served through a
+//! [`RigMcpHandler`](crate::server::RigMcpHandler), every call lands in
+//! its audit log like any other tool call:
+//!
+//! ```ignore
+//! let access = FileAccess::new(&workspace)?;
+//! let (read_file, list_dir) = access.clone().build_tools();
+//! toolset.add_tool(read_file);
+//! toolset.add_tool(list_dir);
+//! // Only for agents that may change the workspace:
+//! toolset.add_tool(access.patch_tool());
+//! ```
+//!
+//! The optional [`patch_tool`](FileAccess::patch_tool) lets an agent edit
+//! files with unified diffs; see [`crate::patch`].
+
+use crate::patch::SubmitPatchTool;
+use crate::tools::ToolError;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Largest file `read_file` returns by default.
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 256 * 1024;
+
+/// Read-only access to the files below a root directory.
+#[derive(Debug, Clone)]
+pub struct FileAccess {
+    root: Arc<PathBuf>,
+    max_file_bytes: u64,
+}
+
+impl FileAccess {
+    /// Grants access to the files below `root`.
+    ///
+    /// # Errors
+    /// Returns an error if `root` does not exist or is not a directory.
+    pub fn new(root: impl AsRef<Path>) -> std::io::Result<Self> {
+        let root = root.as_ref().canonicalize()?;
+        if !root.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", root.display()),
+            ));
+        }
+        Ok(Self {
+            root: Arc::new(root),
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+        })
+    }
+
+    /// Sets the largest file `read_file` returns. Default:
+    /// [`DEFAULT_MAX_FILE_BYTES`].
+    #[must_use]
+    pub const fn max_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_file_bytes = bytes;
+        self
+    }
+
+    /// The directory access is confined to.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Builds the `read_file` and `list_dir` tools.
+    #[must_use]
+    pub fn build_tools(self) -> (ReadFileTool, ListDirTool) {
+        (
+            ReadFileTool {
+                access: self.clone(),
+            },
+            ListDirTool { access: self },
+        )
+    }
+
+    /// Builds the `submit_patch` tool, which writes below the root.
+    #[must_use]
+    pub const fn patch_tool(self) -> SubmitPatchTool {
+        SubmitPatchTool::new(self)
+    }
+
+    /// Resolves `path`, relative to the root, to an existing path below it.
+    fn resolve(&self, path: &str) -> Result<PathBuf, ToolError> {
+        let resolved = self
+            .root
+            .join(relative(path)?)
+            .canonicalize()
+            .map_err(|e| ToolError::Execution(format!("cannot open '{path}': {e}")))?;
+        self.contain(path, resolved)
+    }
+
+    /// Resolves `path`, relative to the root, to a path below it that may
+    /// not exist yet.
+    pub(crate) fn resolve_for_write(&self, path: &str) -> Result<PathBuf, ToolError> {
+        let target = self.root.join(relative(path)?);
+        if target.symlink_metadata().is_ok() {
+            return self.resolve(path);
+        }
+        // Resolve the deepest existing ancestor; the rest is created.
+        let mut missing = Vec::new();
+        let mut ancestor = target.as_path();
+        while ancestor.symlink_metadata().is_err() {
+            missing.push(ancestor.file_name().unwrap_or_default().to_owned());
+            ancestor = ancestor.parent().unwrap_or(self.root.as_path());
+        }
+        let mut resolved = ancestor
+            .canonicalize()
+            .map_err(|e| ToolError::Execution(format!("cannot open '{path}': {e}")))?;
+        resolved.extend(missing.iter().rev());
+        self.contain(path, resolved)
+    }
+
+    /// Checks that `resolved`, the resolution of `path`, is below the root.
+    fn contain(&self, path: &str, resolved: PathBuf) -> Result<PathBuf, ToolError> {
+        // A symlink may still lead out of the root.
+        if resolved.starts_with(&*self.root) {
+            Ok(resolved)
+        } else {
+            Err(ToolError::Execution(format!(
+                "'{path}' is outside the workspace"
+            )))
+        }
+    }
+}
+
+/// `path` as a relative path without `..`.
+fn relative(path: &str) -> Result<&Path, ToolError> {
+    let relative = Path::new(path);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(ToolError::Execution(format!(
+            "'{path}' must be relative to the workspace, without '..'"
+        )));
+    }
+    Ok(relative)
+}
+
+/// Arguments for the `ReadFileTool`.
+#[derive(Deserialize, Serialize)]
+pub struct ReadFileArgs {
+    /// Path of the file, relative to the root.
+    pub path: String,
+}
+
+/// Tool returning the text of a file below the [`FileAccess`] root.
+pub struct ReadFileTool {
+    access: FileAccess,
+}
+
+impl Tool for ReadFileTool {
+    const NAME: &'static str = "read_file";
+    type Error = ToolError;
+    type Args = ReadFileArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Read a UTF-8 text file in the workspace. Paths are relative to the \
+                          workspace root."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the file, relative to the workspace root"
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: ReadFileArgs) -> Result<String, ToolError> {
+        let path = self.access.resolve(&args.path)?;
+        let metadata = tokio::fs::metadata(&path)
+            .await
+            .map_err(|e| ToolError::Execution(format!("cannot read '{}': {e}", args.path)))?;
+        if !metadata.is_file() {
+            return Err(ToolError::Execution(format!(
+                "'{}' is not a file; use list_dir for directories",
+                args.path
+            )));
+        }
+        if metadata.len() > self.access.max_file_bytes {
+            return Err(ToolError::Execution(format!(
+                "'{}' is {} bytes, more than the {}-byte limit",
+                args.path,
+                metadata.len(),
+                self.access.max_file_bytes
+            )));
+        }
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| ToolError::Execution(format!("cannot read '{}': {e}", args.path)))?;
+        String::from_utf8(bytes)
+            .map_err(|_| ToolError::Execution(format!("'{}' is not UTF-8 text", args.path)))
+    }
+}
+
+/// Arguments for the `ListDirTool`.
+#[derive(Deserialize, Serialize)]
+pub struct ListDirArgs {
+    /// Path of the directory, relative to the root; the root when empty.
+    #[serde(default)]
+    pub path: String,
+}
+
+/// Tool listing a directory below the [`FileAccess`] root, one entry per
+/// line, directories ending in `/`.
+pub struct ListDirTool {
+    access: FileAccess,
+}
+
+impl Tool for ListDirTool {
+    const NAME: &'static str = "list_dir";
+    type Error = ToolError;
+    type Args = ListDirArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "List a directory in the workspace, one entry per line; directories \
+                          end in '/'. Paths are relative to the workspace root."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the directory, relative to the workspace root; omit for the root"
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: ListDirArgs) -> Result<String, ToolError> {
+        let path = self.access.resolve(&args.path)?;
+        let mut dir = tokio::fs::read_dir(&path)
+            .await
+            .map_err(|e| ToolError::Execution(format!("cannot list '{}': {e}", args.path)))?;
+        let mut entries = Vec::new();
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(|e| ToolError::Execution(format!("cannot list '{}': {e}", args.path)))?
+        {
+            let mut name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type().await.is_ok_and(|kind| kind.is_dir()) {
+                name.push('/');
+            }
+            entries.push(name);
+        }
+        entries.sort();
+        Ok(entries.join("\n"))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tools_stay_inside_the_root() {
+        let base = tempfile::tempdir().unwrap();
+        let base = base.path();
+        let root = base.join("workspace");
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/spec.md"), "# Spec").unwrap();
+        std::fs::write(base.join("secret.txt"), "hunter2").unwrap();
+        std::os::unix::fs::symlink(base.join("secret.txt"), root.join("leak")).unwrap();
+
+        let (read_file, list_dir) = FileAccess::new(&root).unwrap().build_tools();
+        let read = |path: &str| {
+            read_file.call(ReadFileArgs {
+                path: path.to_string(),
+            })
+        };
+        assert_eq!(read("docs/spec.md").await.unwrap(), "# Spec");
+        assert!(read("../secret.txt").await.is_err());
+        assert!(
+            read(&base.join("secret.txt").display().to_string())
+                .await
+                .is_err()
+        );
+        let escaped = read("leak").await.unwrap_err().to_string();
+        assert!(escaped.contains("outside the workspace"), "{escaped}");
+
+        let listing = list_dir
+            .call(ListDirArgs {
+                path: String::new(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(listing, "docs/\nleak");
+
+        let (small, _) = FileAccess::new(&root)
+            .unwrap()
+            .max_file_bytes(3)
+            .build_tools();
+        assert!(
+            small
+                .call(ReadFileArgs {
+                    path: "docs/spec.md".to_string()
+                })
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("3-byte limit")
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Harvest of the tool calls an MCP run made.
+//!
+//! When the server process is started with [`HARVEST_PATH_ENV`] set,
+//! [`RigMcpHandler`](crate::server::RigMcpHandler) appends a
+//! [`ToolCallRecord`] for every call to that file, unredacted. The parent
+//! process reads it back as a [`ToolHarvest`] once the run is over, to see
+//! how the agent got to its answer: how often it asked for an example, how
+//! many drafts it validated and how many of those failed.
+//!
+//! ```ignore
+//! let result = McpToolAgent::builder()
+//!     .toolset(toolset)
+//!     .harvest_tool_calls(true)
+//!     .run()
+//!     .await?;
+//! if let Some(harvest) = &result.tool_harvest {
+//!     println!(
+//!         "{} validate calls, {} failed",
+//!         harvest.calls_to("validate_json"),
+//!         harvest.validation_failures()
+//!     );
+//! }
+//! ```
+
+use crate::audit::ToolCallRecord;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Environment variable naming the file the MCP server appends harvested
+/// tool calls to.
+pub const HARVEST_PATH_ENV: &str = "RIG_MCP_HARVEST_PATH";
+
+/// The tool calls of one or more MCP runs, with their full arguments and
+/// results, in the order they were served.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolHarvest {
+    /// The harvested calls.
+    pub calls: Vec<ToolCallRecord>,
+}
+
+impl ToolHarvest {
+    /// Reads the JSONL file the MCP server wrote.
+    ///
+    /// A missing file is an empty harvest. Lines that do not parse, such as
+    /// one cut short by a server that was killed mid-write, are skipped with
+    /// a warning.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but cannot be read.
+    pub fn read(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let calls = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    tracing::warn!(target: "rig", error = %e, "Skipping malformed harvested tool call");
+                    None
+                }
+            })
+            .collect();
+        Ok(Self { calls })
+    }
+
+    /// Number of calls to `tool`.
+    #[must_use]
+    pub fn calls_to(&self, tool: &str) -> usize {
+        self.calls.iter().filter(|call| call.tool == tool).count()
+    }
+
+    /// Number of calls whose arguments failed schema validation: drafts
+    /// rejected by the validate tool, rejected submits, and arguments that
+    /// did not deserialize. Classified the same way as
+    /// [`ToolStats::validation_failures`](crate::stats::ToolStats::validation_failures).
+    #[must_use]
+    pub fn validation_failures(&self) -> usize {
+        self.calls
+            .iter()
+            .filter(|call| crate::stats::is_validation_failure(&call.result))
+            .count()
+    }
+
+    /// Number of calls that returned an error.
+    #[must_use]
+    pub fn errors(&self) -> usize {
+        self.calls.iter().filter(|call| call.is_error).count()
+    }
+
+    /// Appends the calls of a later run.
+    pub fn extend(&mut self, other: Self) {
+        self.calls.extend(other.calls);
+    }
+
+    /// Judges whether the agent followed the example → validate → submit
+    /// workflow on `server`, whose toolset has the tools named in `tools`.
+    ///
+    /// Steps whose tool the toolset does not have are not required.
+    #[must_use]
+    pub fn workflow_compliance(&self, server: &str, tools: &[String]) -> WorkflowCompliance {
+        let has = |tool: &str| tools.iter().any(|t| t == tool);
+        let calls: Vec<&ToolCallRecord> = self
+            .calls
+            .iter()
+            .filter(|call| call.server == server)
+            .collect();
+        let count = |tool: &str| calls.iter().filter(|call| call.tool == tool).count();
+        let passed = |call: &ToolCallRecord| {
+            !call.is_error && !crate::stats::is_validation_failure(&call.result)
+        };
+
+        let first_submit = calls
+            .iter()
+            .position(|call| call.tool == SUBMIT_TOOL && passed(call));
+        let mut violations = Vec::new();
+        if has(EXAMPLE_TOOL) && count(EXAMPLE_TOOL) == 0 {
+            violations.push(WorkflowViolation::MissingExample);
+        }
+        if let Some(submit) = first_submit {
+            let validated = calls[..submit]
+                .iter()
+                .any(|call| call.tool == VALIDATE_TOOL && passed(call));
+            if has(VALIDATE_TOOL) && !validated {
+                violations.push(WorkflowViolation::SubmitWithoutValidation);
+            }
+        } else if has(SUBMIT_TOOL) {
+            violations.push(WorkflowViolation::FreeformAnswer);
+        }
+
+        WorkflowCompliance {
+            tool_calls: calls.len(),
+            example_calls: count(EXAMPLE_TOOL),
+            validate_calls: count(VALIDATE_TOOL),
+            submit_calls: count(SUBMIT_TOOL),
+            violations,
+        }
+    }
+}
+
+/// Name of the toolkit tool returning an example.
+const EXAMPLE_TOOL: &str = "json_example";
+/// Name of the toolkit tool validating a draft.
+const VALIDATE_TOOL: &str = "validate_json";
+/// Name of the toolkit tool submitting the result.
+const SUBMIT_TOOL: &str = "submit";
+
+/// How closely a run followed the example → validate → submit workflow,
+/// from [`ToolHarvest::workflow_compliance`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowCompliance {
+    /// Calls the server served.
+    pub tool_calls: usize,
+    /// Calls to the example tool.
+    pub example_calls: usize,
+    /// Calls to the validate tool, passing or not.
+    pub validate_calls: usize,
+    /// Calls to the submit tool, accepted or not.
+    pub submit_calls: usize,
+    /// The workflow steps the agent skipped, in workflow order.
+    pub violations: Vec<WorkflowViolation>,
+}
+
+impl WorkflowCompliance {
+    /// Whether the agent skipped no step.
+    #[must_use]
+    pub const fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// A workflow step the agent skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowViolation {
+    /// The example tool was never called.
+    MissingExample,
+    /// The submit tool accepted a result without a passing validate call
+    /// before it.
+    SubmitWithoutValidation,
+    /// The submit tool never accepted a result, so the answer, if any, was
+    /// freeform text.
+    FreeformAnswer,
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(tool: &str, result: &str, is_error: bool) -> ToolCallRecord {
+        ToolCallRecord {
+            timestamp_ms: 0,
+            server: "rig_mcp".to_string(),
+            tool: tool.to_string(),
+            arguments: json!({ "json": { "name": "x" } }),
+            result: result.to_string(),
+            is_error,
+            duration_ms: 1,
+            client_name: None,
+            client_version: None,
+        }
+    }
+
+    #[test]
+    fn test_read_counts_calls_and_skips_partial_lines() {
+        let path =
+            std::env::temp_dir().join(format!("rig-mcp-harvest-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(ToolHarvest::read(&path).unwrap(), ToolHarvest::default());
+
+        let lines: Vec<String> = [
+            call("json_example", "{\"name\":\"example\"}", false),
+            call(
+                "validate_json",
+                "JSON validation failed.\n\nErrors: ...",
+                false,
+            ),
+            call("validate_json", "JSON is valid.", false),
+            call(
+                "submit",
+                "ToolCallError: Validation error: missing 'age'",
+                true,
+            ),
+        ]
+        .iter()
+        .map(|record| serde_json::to_string(record).unwrap())
+        .collect();
+        std::fs::write(&path, format!("{}\n{{\"timestamp_ms\":", lines.join("\n"))).unwrap();
+
+        let harvest = ToolHarvest::read(&path).unwrap();
+        assert_eq!(harvest.calls.len(), 4);
+        assert_eq!(harvest.calls_to("validate_json"), 2);
+        assert_eq!(harvest.validation_failures(), 2);
+        assert_eq!(harvest.errors(), 1);
+        assert_eq!(
+            harvest.calls[0].arguments,
+            json!({ "json": { "name": "x" } })
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_workflow_compliance_flags_skipped_steps() {
+        let tools: Vec<String> = ["json_example", "validate_json", "submit"]
+            .map(String::from)
+            .to_vec();
+        let harvest = |calls: Vec<ToolCallRecord>| ToolHarvest { calls };
+
+        let followed = harvest(vec![
+            call("json_example", "{}", false),
+            call(
+                "validate_json",
+                "JSON validation failed.\n\nErrors: ...",
+                false,
+            ),
+            call("validate_json", "JSON is valid.", false),
+            call("submit", "Submitted", false),
+        ])
+        .workflow_compliance("rig_mcp", &tools);
+        assert!(followed.is_compliant(), "{followed:?}");
+        assert_eq!(followed.tool_calls, 4);
+        assert_eq!(followed.validate_calls, 2);
+
+        let rushed = harvest(vec![
+            call(
+                "validate_json",
+                "JSON validation failed.\n\nErrors: ...",
+                false,
+            ),
+            call("submit", "Submitted", false),
+        ])
+        .workflow_compliance("rig_mcp", &tools);
+        assert_eq!(
+            rushed.violations,
+            [
+                WorkflowViolation::MissingExample,
+                WorkflowViolation::SubmitWithoutValidation
+            ]
+        );
+
+        let mut other_server = call("submit", "Submitted", false);
+        other_server.server = "domain".to_string();
+        let freeform = harvest(vec![
+            call("json_example", "{}", false),
+            call(
+                "submit",
+                "ToolCallError: Validation error: missing 'age'",
+                true,
+            ),
+            other_server,
+        ])
+        .workflow_compliance("rig_mcp", &tools);
+        assert_eq!(freeform.violations, [WorkflowViolation::FreeformAnswer]);
+        assert_eq!(freeform.submit_calls, 1);
+
+        let custom = harvest(Vec::new()).workflow_compliance("rig_mcp", &[]);
+        assert!(custom.is_compliant());
+    }
+}
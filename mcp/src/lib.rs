@@ -4,15 +4,37 @@
 
 #![warn(missing_docs)]
 
+pub mod audit;
 pub mod extraction;
+pub mod files;
+pub mod harvest;
+pub mod naming;
+pub mod pagination;
+pub mod patch;
+pub mod policy;
 pub mod server;
+pub mod stats;
+pub mod submissions;
 pub mod tools;
+pub mod validation;
+pub mod watchdog;
 
 /// Common traits and types for ergonomic usage of the Rig MCP server.
 pub mod prelude {
+    pub use crate::audit::{AuditLog, ToolCallRecord};
     pub use crate::extraction::{
-        ExtractionConfig, ExtractionError, ExtractionMetrics, ExtractionOrchestrator,
+        AgentResponse, ExtractionConfig, ExtractionError, ExtractionMetrics, ExtractionOrchestrator,
     };
-    pub use crate::server::{McpConfig, RigMcpHandler, ToolSetExt};
-    pub use crate::tools::{DynamicJsonSchemaToolkit, JsonSchemaToolkit};
+    pub use crate::files::FileAccess;
+    pub use crate::harvest::{ToolHarvest, WorkflowCompliance, WorkflowViolation};
+    pub use crate::policy::{PolicyViolation, ToolAccessPolicy};
+    pub use crate::server::{ConfigFormat, McpConfig, RemoteMcpConfig, RigMcpHandler, ToolSetExt};
+    pub use crate::stats::{ServerStats, ToolStats};
+    pub use crate::submissions::{SubmitOutcome, SubmitPolicy, SubmitRecord};
+    pub use crate::tools::{
+        DynamicJsonSchemaToolkit, JsonListToolkit, JsonSchemaToolkit, ListSubmission,
+        VersionedSchema,
+    };
+    pub use crate::validation::SchemaValidation;
+    pub use crate::watchdog::RestartPolicy;
 }
@@ -0,0 +1,191 @@
+//! Naming constraints for MCP servers and tools.
+//!
+//! A CLI exposes each MCP tool to the model under a qualified name built from
+//! the server and tool names, such as `mcp__<server>__<tool>`. Model APIs only
+//! accept tool names of up to [`MAX_TOOL_NAME_LEN`] characters drawn from
+//! `[A-Za-z0-9_-]`; a CLI handed anything else truncates the name or drops the
+//! tool without telling the caller. The checks here reject such names up
+//! front and suggest a legal replacement.
+
+use rig_cli_process_core::hash::fnv1a;
+use thiserror::Error;
+
+/// Longest qualified tool name model APIs accept.
+pub const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// An illegal server or tool name.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum NameError {
+    /// The name is empty.
+    #[error("{kind} name must not be empty")]
+    Empty {
+        /// `"server"` or `"tool"`.
+        kind: &'static str,
+    },
+    /// The name contains characters outside `[A-Za-z0-9_-]`, or a server
+    /// name contains the `__` separator.
+    #[error("{kind} name '{name}' is not allowed: {reason}; try '{suggestion}'")]
+    Invalid {
+        /// `"server"` or `"tool"`.
+        kind: &'static str,
+        /// The rejected name.
+        name: String,
+        /// What is wrong with it.
+        reason: &'static str,
+        /// A legal name derived from it.
+        suggestion: String,
+    },
+    /// The qualified name is longer than the limit.
+    #[error(
+        "tool name '{qualified}' is {len} characters, over the {max_len} character limit; \
+         rename tool '{tool}' to '{suggestion}' or use a shorter server name"
+    )]
+    TooLong {
+        /// The qualified name the CLI would use.
+        qualified: String,
+        /// Its length.
+        len: usize,
+        /// The limit.
+        max_len: usize,
+        /// The tool name within it.
+        tool: String,
+        /// A tool name that fits.
+        suggestion: String,
+    },
+}
+
+/// Checks that `name` is a legal MCP server name.
+///
+/// Server names may not contain `__`, which separates the parts of a
+/// qualified tool name.
+///
+/// # Errors
+/// Returns [`NameError::Empty`] or [`NameError::Invalid`].
+pub fn check_server_name(name: &str) -> Result<(), NameError> {
+    check_chars("server", name)?;
+    if name.contains("__") {
+        return Err(NameError::Invalid {
+            kind: "server",
+            name: name.to_string(),
+            reason: "'__' separates the parts of qualified tool names",
+            suggestion: sanitize(name),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `name` is a legal MCP tool name on its own.
+///
+/// # Errors
+/// Returns [`NameError::Empty`], [`NameError::Invalid`], or
+/// [`NameError::TooLong`] if the name alone exceeds [`MAX_TOOL_NAME_LEN`].
+pub fn check_tool_name(name: &str) -> Result<(), NameError> {
+    check_chars("tool", name)?;
+    check_qualified_len(name, name, MAX_TOOL_NAME_LEN)
+}
+
+/// Checks that `qualified`, the name a CLI builds for `tool`, fits in
+/// `max_len` characters.
+///
+/// # Errors
+/// Returns [`NameError::TooLong`] with a shortened tool name that fits.
+pub fn check_qualified_len(qualified: &str, tool: &str, max_len: usize) -> Result<(), NameError> {
+    let len = qualified.chars().count();
+    if len <= max_len {
+        return Ok(());
+    }
+    let budget = tool.chars().count().saturating_sub(len - max_len);
+    Err(NameError::TooLong {
+        qualified: qualified.to_string(),
+        len,
+        max_len,
+        tool: tool.to_string(),
+        suggestion: shorten(tool, budget),
+    })
+}
+
+/// Shortens `name` to at most `max_len` characters, keeping it distinct from
+/// other names with the same prefix by ending it in a short hash.
+///
+/// Names that already fit are returned unchanged.
+#[must_use]
+pub fn shorten(name: &str, max_len: usize) -> String {
+    if name.chars().count() <= max_len {
+        return name.to_string();
+    }
+    let hash = format!("{:04x}", fnv1a(name.as_bytes()) & 0xffff);
+    let keep = max_len.saturating_sub(hash.len() + 1);
+    let prefix: String = name.chars().take(keep).collect();
+    format!("{}_{hash}", prefix.trim_end_matches('_'))
+}
+
+/// Rejects empty names and characters outside `[A-Za-z0-9_-]`.
+fn check_chars(kind: &'static str, name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty { kind });
+    }
+    if !name.chars().all(is_name_char) {
+        return Err(NameError::Invalid {
+            kind,
+            name: name.to_string(),
+            reason: "only ASCII letters, digits, '_' and '-' are allowed",
+            suggestion: sanitize(name),
+        });
+    }
+    Ok(())
+}
+
+const fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Replaces illegal characters with `_` and collapses runs of `_`.
+fn sanitize(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = if is_name_char(c) { c } else { '_' };
+        if !(c == '_' && out.ends_with('_')) {
+            out.push(c);
+        }
+    }
+    let out = out.trim_matches('_');
+    if out.is_empty() {
+        "tool".to_string()
+    } else {
+        out.to_string()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_are_checked_and_suggestions_are_legal() {
+        assert!(check_server_name("rig_mcp").is_ok());
+        assert!(check_tool_name("submit").is_ok());
+        assert_eq!(check_tool_name(""), Err(NameError::Empty { kind: "tool" }));
+
+        match check_tool_name("get weather.v2") {
+            Err(NameError::Invalid { suggestion, .. }) => assert_eq!(suggestion, "get_weather_v2"),
+            other => panic!("expected invalid name, got {other:?}"),
+        }
+        match check_server_name("my__server") {
+            Err(NameError::Invalid { suggestion, .. }) => assert_eq!(suggestion, "my_server"),
+            other => panic!("expected invalid name, got {other:?}"),
+        }
+
+        let tool = "summarize_quarterly_financial_statements";
+        let qualified = format!("mcp__some_long_server_name__{tool}");
+        match check_qualified_len(&qualified, tool, MAX_TOOL_NAME_LEN) {
+            Err(NameError::TooLong { suggestion, .. }) => {
+                let fixed = format!("mcp__some_long_server_name__{suggestion}");
+                assert_eq!(fixed.len(), MAX_TOOL_NAME_LEN);
+                assert!(check_tool_name(&suggestion).is_ok());
+                assert_eq!(suggestion, shorten(tool, suggestion.len()));
+            }
+            other => panic!("expected too long, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Pagination of large tool results for [`RigMcpHandler`](crate::server::RigMcpHandler).
+//!
+//! A tool that returns a whole file or a long query result can fill the
+//! agent's context in one call. With pagination enabled, results longer
+//! than a page are cut after the page and end with a note naming a cursor;
+//! the agent calls [`FETCH_MORE_TOOL`] with it to read the next page:
+//!
+//! ```ignore
+//! let handler = RigMcpHandler::builder()
+//!     .toolset(toolset)
+//!     .paginate_results(16 * 1024)
+//!     .build()
+//!     .await?;
+//! ```
+//!
+//! The rest of a result is kept in memory until it is fetched. Only the
+//! [`MAX_PENDING`] most recent results are kept; fetching an older one
+//! fails with an error the agent can read.
+
+use rig::completion::ToolDefinition;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Name of the tool that returns the next page of a cut result.
+pub const FETCH_MORE_TOOL: &str = "fetch_more";
+
+/// Cut results whose rest is kept for [`FETCH_MORE_TOOL`].
+pub const MAX_PENDING: usize = 32;
+
+/// The rest of cut results, by cursor. Shared by clones.
+#[derive(Debug, Clone)]
+pub(crate) struct ResultPages {
+    page_bytes: usize,
+    pending: Arc<Mutex<Pending>>,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    next_cursor: u64,
+    /// Oldest first.
+    rests: VecDeque<(String, String)>,
+}
+
+impl ResultPages {
+    /// Pages of at most `page_bytes`, or one character when that is more.
+    pub(crate) fn new(page_bytes: usize) -> Self {
+        Self {
+            page_bytes,
+            pending: Arc::default(),
+        }
+    }
+
+    /// Definition of the [`FETCH_MORE_TOOL`].
+    pub(crate) fn definition() -> ToolDefinition {
+        ToolDefinition {
+            name: FETCH_MORE_TOOL.to_string(),
+            description: "Return the next page of a tool result that was cut short. Pass the \
+                          cursor named at the end of the cut result."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "cursor": {
+                        "type": "string",
+                        "description": "Cursor from the end of the cut result."
+                    }
+                },
+                "required": ["cursor"]
+            }),
+        }
+    }
+
+    /// The first page of `output`, followed by a note naming the cursor of
+    /// the rest when it does not fit. Short outputs are returned unchanged.
+    pub(crate) fn first_page(&self, mut output: String) -> String {
+        if output.len() <= self.page_bytes {
+            return output;
+        }
+        let end = page_end(&output, self.page_bytes);
+        let rest = output.split_off(end);
+        let remaining = rest.len();
+        let cursor = {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            let cursor = pending.next_cursor.to_string();
+            pending.next_cursor += 1;
+            if pending.rests.len() == MAX_PENDING {
+                pending.rests.pop_front();
+            }
+            pending.rests.push_back((cursor.clone(), rest));
+            cursor
+        };
+        let _ = write!(
+            output,
+            "\n\n[Result cut: {remaining} more bytes. Call {FETCH_MORE_TOOL} with \
+             {{\"cursor\": \"{cursor}\"}} to read the next page.]"
+        );
+        output
+    }
+
+    /// Answers a [`FETCH_MORE_TOOL`] call: the next page of the result
+    /// named by the `cursor` argument.
+    pub(crate) fn fetch(&self, arguments: &Value) -> Result<String, String> {
+        let cursor = arguments
+            .get("cursor")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("{FETCH_MORE_TOOL} needs a string \"cursor\" argument"))?;
+        let rest = {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            let index = pending
+                .rests
+                .iter()
+                .position(|(pending_cursor, _)| pending_cursor == cursor)
+                .ok_or_else(|| {
+                    format!("Unknown or expired cursor '{cursor}'; call the tool again instead")
+                })?;
+            pending.rests.remove(index).map(|(_, rest)| rest)
+        };
+        Ok(self.first_page(rest.unwrap_or_default()))
+    }
+}
+
+/// Where the page of `text` ends: at most `page_bytes` in, on a character
+/// boundary, and after at least one character.
+fn page_end(text: &str, page_bytes: usize) -> usize {
+    let mut end = page_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    if end == 0 {
+        end = text.chars().next().map_or(0, char::len_utf8);
+    }
+    end
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_results_are_read_page_by_page() {
+        let pages = ResultPages::new(4);
+        assert_eq!(pages.first_page("abc".to_string()), "abc");
+
+        // The page ends before `é` rather than inside it.
+        let first = pages.first_page("abcéfghij".to_string());
+        assert!(first.starts_with("abc\n\n[Result cut: 7 more bytes."));
+        assert!(first.contains(r#"{"cursor": "0"}"#));
+
+        let second = pages.fetch(&json!({ "cursor": "0" })).unwrap();
+        assert!(second.starts_with("éfg\n\n"));
+        assert!(second.contains(r#"{"cursor": "1"}"#));
+        assert_eq!(pages.fetch(&json!({ "cursor": "1" })).unwrap(), "hij");
+
+        assert!(
+            pages
+                .fetch(&json!({ "cursor": "1" }))
+                .unwrap_err()
+                .contains("Unknown or expired cursor")
+        );
+        assert!(pages.fetch(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_oldest_results_expire() {
+        let pages = ResultPages::new(1);
+        for _ in 0..=MAX_PENDING {
+            pages.first_page("ab".to_string());
+        }
+        assert!(pages.fetch(&json!({ "cursor": "0" })).is_err());
+        assert_eq!(pages.fetch(&json!({ "cursor": "1" })).unwrap(), "b");
+    }
+}
@@ -0,0 +1,675 @@
+//! Workspace edits through unified diffs.
+//!
+//! [`SubmitPatchTool`] lets an agent change files without the CLI's own
+//! `Edit` and `Write` builtins. It takes a unified diff, as `git diff` or
+//! `diff -u` print it, and applies it below a
+//! [`FileAccess`](crate::files::FileAccess) root:
+//!
+//! - Every path is checked against the root first; a patch touching
+//!   anything outside it is refused as a whole.
+//! - Every hunk must apply, at its line or shifted by earlier edits,
+//!   before any file is written.
+//! - Files are backed up before they change, and written through a
+//!   temporary file, so a failed write restores the files already written.
+//!   The backups live in a private temporary directory removed once the
+//!   patch is applied, unless [`backup_dir`](SubmitPatchTool::backup_dir)
+//!   asks to keep them.
+//!
+//! The agent gets a report of the hunks applied to each file. `/dev/null`
+//! as the old or new path creates or deletes a file.
+
+use crate::files::FileAccess;
+use crate::tools::ToolError;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Path standing for a missing file in a diff header.
+const DEV_NULL: &str = "/dev/null";
+
+/// Arguments for the `SubmitPatchTool`.
+#[derive(Deserialize, Serialize)]
+pub struct SubmitPatchArgs {
+    /// The unified diff to apply.
+    pub patch: String,
+}
+
+/// Tool applying unified diffs below a [`FileAccess`] root.
+pub struct SubmitPatchTool {
+    access: FileAccess,
+    backup_dir: Option<PathBuf>,
+    scratch_dir: Option<PathBuf>,
+}
+
+impl SubmitPatchTool {
+    /// Applies patches below `access`'s root. Backups are removed once each
+    /// patch is applied.
+    #[must_use]
+    pub const fn new(access: FileAccess) -> Self {
+        Self {
+            access,
+            backup_dir: None,
+            scratch_dir: None,
+        }
+    }
+
+    /// Keeps each patch's backups in a subdirectory of `dir`, named in the
+    /// agent's report. Keep it outside the root, so backups are not part of
+    /// the workspace.
+    #[must_use]
+    pub fn backup_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.backup_dir = Some(dir.into());
+        self
+    }
+
+    /// Puts the backups that are removed once the patch is applied in a
+    /// subdirectory of `dir` instead of the system temp directory.
+    #[must_use]
+    pub fn scratch_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.scratch_dir = Some(dir.into());
+        self
+    }
+
+    /// Applies the unified `diff`, returning what changed.
+    fn apply(&self, diff: &str) -> Result<String, ToolError> {
+        let file_patches = parse(diff)?;
+        if file_patches.is_empty() {
+            return Err(ToolError::Validation(
+                "the patch holds no file changes; send a unified diff".to_string(),
+            ));
+        }
+
+        // Work out every change before touching a file.
+        let mut changes = Vec::with_capacity(file_patches.len());
+        for (n, file_patch) in file_patches.iter().enumerate() {
+            let path = file_patch.path();
+            if matches!((&file_patch.old, &file_patch.new), (Some(old), Some(new)) if old != new) {
+                return Err(ToolError::Validation(format!(
+                    "renaming '{}' to '{path}' is not supported; delete and create instead",
+                    file_patch.old.as_deref().unwrap_or_default()
+                )));
+            }
+            if file_patches[..n]
+                .iter()
+                .any(|earlier| earlier.path() == path)
+            {
+                return Err(ToolError::Validation(format!(
+                    "'{path}' appears twice; send one diff per file"
+                )));
+            }
+            let target = self.access.resolve_for_write(path)?;
+            let original = match &file_patch.old {
+                Some(_) => Some(
+                    std::fs::read_to_string(&target)
+                        .map_err(|e| ToolError::Execution(format!("cannot read '{path}': {e}")))?,
+                ),
+                None if target.exists() => {
+                    return Err(ToolError::Validation(format!(
+                        "'{path}' already exists; patch it instead of creating it"
+                    )));
+                }
+                None => None,
+            };
+            let applied = file_patch.apply_to(original.as_deref().unwrap_or_default())?;
+            let updated = match file_patch.new {
+                Some(_) => Some(applied),
+                None if applied.is_empty() => None,
+                None => {
+                    return Err(ToolError::Validation(format!(
+                        "deleting '{path}' would drop lines the patch does not remove"
+                    )));
+                }
+            };
+            changes.push(Change {
+                path: path.to_string(),
+                target,
+                existed: original.is_some(),
+                updated,
+                hunks: file_patch.hunks.len(),
+            });
+        }
+
+        let backup = Backup::new(self.backup_dir.as_deref(), self.scratch_dir.as_deref());
+        for change in changes.iter().filter(|change| change.existed) {
+            backup
+                .save(change)
+                .map_err(|e| backup_failed(&change.path, &e))?;
+        }
+
+        for (written, change) in changes.iter().enumerate() {
+            if let Err(e) = change.write() {
+                for undone in changes[..written].iter().rev() {
+                    undone.undo(&backup.dir);
+                }
+                return Err(ToolError::Execution(format!(
+                    "cannot write '{}': {e}; the patch was rolled back",
+                    change.path
+                )));
+            }
+        }
+
+        let mut report = String::new();
+        for change in &changes {
+            let verb = match (change.existed, &change.updated) {
+                (false, _) => "Created",
+                (true, Some(_)) => "Patched",
+                (true, None) => "Deleted",
+            };
+            let _ = writeln!(
+                report,
+                "{verb} {} ({} hunk{})",
+                change.path,
+                change.hunks,
+                if change.hunks == 1 { "" } else { "s" }
+            );
+        }
+        if backup.keep && changes.iter().any(|change| change.existed) {
+            let _ = write!(report, "Backup: {}", backup.dir.display());
+        }
+        Ok(report.trim_end().to_string())
+    }
+}
+
+impl Tool for SubmitPatchTool {
+    const NAME: &'static str = "submit_patch";
+    type Error = ToolError;
+    type Args = SubmitPatchArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Apply a unified diff to files in the workspace, as `git diff` prints \
+                          it. Paths are relative to the workspace root. Either every hunk \
+                          applies or no file changes."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "patch": {
+                        "type": "string",
+                        "description": "The unified diff, with ---/+++ headers and @@ hunks"
+                    }
+                },
+                "required": ["patch"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: SubmitPatchArgs) -> Result<String, ToolError> {
+        self.apply(&args.patch)
+    }
+}
+
+/// One file's part of a patch.
+#[derive(Debug)]
+struct FilePatch {
+    /// Path before the change; `None` for a created file.
+    old: Option<String>,
+    /// Path after the change; `None` for a deleted file.
+    new: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// The path the patch changes.
+    fn path(&self) -> &str {
+        self.new
+            .as_deref()
+            .or(self.old.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Applies the hunks to `original`, in order. Lines keep the file's
+    /// line ending, `\r\n` if its first line has one.
+    fn apply_to(&self, original: &str) -> Result<String, ToolError> {
+        let line_ending = match original.split_once('\n') {
+            Some((first, _)) if first.ends_with('\r') => "\r\n",
+            _ => "\n",
+        };
+        let mut lines: Vec<&str> = original.lines().collect();
+        let mut trailing_newline = original.is_empty() || original.ends_with('\n');
+        let mut shift: isize = 0;
+        let mut from = 0;
+        for (n, hunk) in self.hunks.iter().enumerate() {
+            let old: Vec<&str> = hunk.old_lines().collect();
+            let new: Vec<&str> = hunk.new_lines().collect();
+            // A hunk without old lines inserts after line `old_start`.
+            let first = if old.is_empty() {
+                hunk.old_start
+            } else {
+                hunk.old_start.saturating_sub(1)
+            };
+            let expected = first.saturating_add_signed(shift);
+            let at = if matches_at(&lines, &old, expected) && expected >= from {
+                expected
+            } else {
+                (from..=lines.len().saturating_sub(old.len()))
+                    .find(|&at| matches_at(&lines, &old, at))
+                    .ok_or_else(|| {
+                        ToolError::Validation(format!(
+                            "hunk {} of '{}' does not match the file; read it again and resend \
+                             the patch",
+                            n + 1,
+                            self.path()
+                        ))
+                    })?
+            };
+            // A hunk reaching the end of the file says how the file ends.
+            if at + old.len() == lines.len() {
+                trailing_newline = !hunk.no_newline_at_end;
+            }
+            lines.splice(at..at + old.len(), new.iter().copied());
+            from = at + new.len();
+            shift += isize::try_from(new.len()).unwrap_or(isize::MAX)
+                - isize::try_from(old.len()).unwrap_or(isize::MAX);
+        }
+        let mut updated = lines.join(line_ending);
+        if trailing_newline && !updated.is_empty() {
+            updated.push_str(line_ending);
+        }
+        Ok(updated)
+    }
+}
+
+/// Whether `lines` holds `block` starting at `at`.
+fn matches_at(lines: &[&str], block: &[&str], at: usize) -> bool {
+    lines.get(at..at + block.len()) == Some(block)
+}
+
+/// One `@@` section of a file's patch.
+#[derive(Debug)]
+struct Hunk {
+    /// First line the hunk replaces, counting from 1.
+    old_start: usize,
+    lines: Vec<HunkLine>,
+    /// Whether the new file ends without a newline.
+    no_newline_at_end: bool,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+            HunkLine::Add(_) => None,
+        })
+    }
+
+    fn new_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Add(text) => Some(text.as_str()),
+            HunkLine::Remove(_) => None,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// A file change worked out and ready to write.
+struct Change {
+    path: String,
+    target: PathBuf,
+    existed: bool,
+    /// New contents; `None` deletes the file.
+    updated: Option<String>,
+    hunks: usize,
+}
+
+impl Change {
+    /// Writes the change: through a temporary file renamed over the
+    /// target, or by removing the target.
+    fn write(&self) -> std::io::Result<()> {
+        let Some(updated) = &self.updated else {
+            return std::fs::remove_file(&self.target);
+        };
+        if let Some(parent) = self.target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let staged = self.target.with_file_name(format!(
+            ".{}.rig-patch",
+            self.target
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        ));
+        std::fs::write(&staged, updated)?;
+        std::fs::rename(&staged, &self.target).inspect_err(|_| {
+            let _ = std::fs::remove_file(&staged);
+        })
+    }
+
+    /// Restores the file from `backup`, or removes it if it was created.
+    fn undo(&self, backup: &Path) {
+        let restored = if self.existed {
+            std::fs::copy(backup.join(&self.path), &self.target).map(|_| ())
+        } else {
+            std::fs::remove_file(&self.target)
+        };
+        if let Err(e) = restored {
+            tracing::warn!(target: "rig", path = %self.path, error = %e, "Failed to roll back patched file");
+        }
+    }
+}
+
+/// Where one patch's backups go.
+struct Backup {
+    dir: PathBuf,
+    /// Whether the backups outlive the patch.
+    keep: bool,
+}
+
+impl Backup {
+    /// A subdirectory of `kept` if given, or else of `scratch` or the
+    /// system temp directory, removed on drop.
+    fn new(kept: Option<&Path>, scratch: Option<&Path>) -> Self {
+        kept.map_or_else(
+            || Self {
+                dir: scratch
+                    .map_or_else(std::env::temp_dir, Path::to_path_buf)
+                    .join(format!("rig-patch-{}", patch_id())),
+                keep: false,
+            },
+            |dir| Self {
+                dir: dir.join(patch_id()),
+                keep: true,
+            },
+        )
+    }
+
+    /// Copies the file `change` replaces. The directory is created on the
+    /// first copy, readable only by its owner.
+    fn save(&self, change: &Change) -> std::io::Result<()> {
+        if !self.dir.exists() {
+            if let Some(parent) = self.dir.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut builder = std::fs::DirBuilder::new();
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            builder.create(&self.dir)?;
+        }
+        let copy = self.dir.join(&change.path);
+        if let Some(parent) = copy.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&change.target, &copy).map(|_| ())
+    }
+}
+
+impl Drop for Backup {
+    fn drop(&mut self) {
+        if !self.keep
+            && self.dir.exists()
+            && let Err(e) = std::fs::remove_dir_all(&self.dir)
+        {
+            tracing::warn!(target: "rig", dir = %self.dir.display(), error = %e, "Failed to remove patch backups");
+        }
+    }
+}
+
+fn backup_failed(path: &str, e: &std::io::Error) -> ToolError {
+    ToolError::Execution(format!("cannot back up '{path}': {e}; no file was changed"))
+}
+
+/// A name for one patch's backups, unique within the process and unlikely
+/// to repeat across processes.
+fn patch_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    format!(
+        "{started}-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Parses a unified diff into its file patches. Text outside them, such
+/// as `diff --git` and `index` lines, is skipped.
+fn parse(patch: &str) -> Result<Vec<FilePatch>, ToolError> {
+    let invalid = |message: String| ToolError::Validation(format!("invalid patch: {message}"));
+    let mut lines = patch.lines().peekable();
+    let mut file_patches = Vec::new();
+    while let Some(line) = lines.next() {
+        let Some(old) = line.strip_prefix("--- ") else {
+            continue;
+        };
+        let new = lines
+            .next()
+            .and_then(|line| line.strip_prefix("+++ "))
+            .ok_or_else(|| invalid(format!("'{line}' is not followed by a '+++' line")))?;
+        let mut file_patch = FilePatch {
+            old: header_path(old),
+            new: header_path(new),
+            hunks: Vec::new(),
+        };
+        if file_patch.old.is_none() && file_patch.new.is_none() {
+            return Err(invalid(
+                "a file goes from /dev/null to /dev/null".to_string(),
+            ));
+        }
+
+        while let Some(header) = lines.peek().and_then(|line| line.strip_prefix("@@ ")) {
+            let (old_start, mut old_left, mut new_left) = hunk_header(header)
+                .ok_or_else(|| invalid(format!("bad hunk header '@@ {header}'")))?;
+            lines.next();
+            let mut hunk = Hunk {
+                old_start,
+                lines: Vec::new(),
+                no_newline_at_end: false,
+            };
+            while old_left + new_left > 0 {
+                let line = lines.next().ok_or_else(|| {
+                    invalid(format!("a hunk of '{}' ends early", file_patch.path()))
+                })?;
+                let text = || line.get(1..).unwrap_or_default().to_string();
+                let (old_taken, new_taken, hunk_line) = match line.as_bytes().first() {
+                    // Some tools drop the space of an empty context line.
+                    Some(b' ') | None => (1, 1, HunkLine::Context(text())),
+                    Some(b'-') => (1, 0, HunkLine::Remove(text())),
+                    Some(b'+') => (0, 1, HunkLine::Add(text())),
+                    Some(b'\\') => continue,
+                    _ => return Err(invalid(format!("unexpected line '{line}' in a hunk"))),
+                };
+                if old_left < old_taken || new_left < new_taken {
+                    return Err(invalid(format!(
+                        "a hunk of '{}' is longer than its header says",
+                        file_patch.path()
+                    )));
+                }
+                old_left -= old_taken;
+                new_left -= new_taken;
+                hunk.lines.push(hunk_line);
+            }
+            // The marker after the last line applies to the new file when
+            // that line is in it.
+            if lines.peek().is_some_and(|line| line.starts_with('\\'))
+                && !matches!(hunk.lines.last(), Some(HunkLine::Remove(_)))
+            {
+                hunk.no_newline_at_end = true;
+                lines.next();
+            }
+            file_patch.hunks.push(hunk);
+        }
+        if file_patch.hunks.is_empty() && file_patch.new.is_some() {
+            return Err(invalid(format!("'{}' has no hunks", file_patch.path())));
+        }
+        file_patches.push(file_patch);
+    }
+    Ok(file_patches)
+}
+
+/// The path of a `---` or `+++` header, without its `a/` or `b/` prefix
+/// and timestamp; `None` for `/dev/null`.
+fn header_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or_default().trim_end();
+    if path == DEV_NULL {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// The old start and the old and new line counts of a hunk header,
+/// given after its leading `@@ `.
+fn hunk_header(header: &str) -> Option<(usize, usize, usize)> {
+    let mut ranges = header.split_whitespace();
+    let range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = range(ranges.next()?.strip_prefix('-')?)?;
+    let (_, new_count) = range(ranges.next()?.strip_prefix('+')?)?;
+    Some((old_start, old_count, new_count))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    println!(\"hi\");
++    println!(\"hello\");
+ }
+--- /dev/null
++++ b/docs/notes.md
+@@ -0,0 +1,2 @@
++# Notes
++Added by the agent.
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-stale
+";
+
+    fn workspace() -> (tempfile::TempDir, SubmitPatchTool) {
+        let base = tempfile::tempdir().unwrap();
+        let root = base.path().join("workspace");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src/lib.rs"),
+            "fn main() {\n    println!(\"hi\");\n}\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("old.txt"), "stale\n").unwrap();
+        let tool = FileAccess::new(&root)
+            .unwrap()
+            .patch_tool()
+            .backup_dir(base.path().join("backups"));
+        (base, tool)
+    }
+
+    #[test]
+    fn test_patch_modifies_creates_and_deletes_files() {
+        let (base, tool) = workspace();
+        let root = base.path().join("workspace");
+
+        let report = tool.apply(PATCH).unwrap();
+        assert!(report.starts_with(
+            "Patched src/lib.rs (1 hunk)\nCreated docs/notes.md (1 hunk)\nDeleted old.txt (1 hunk)\nBackup: "
+        ));
+        assert_eq!(
+            std::fs::read_to_string(root.join("src/lib.rs")).unwrap(),
+            "fn main() {\n    println!(\"hello\");\n}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("docs/notes.md")).unwrap(),
+            "# Notes\nAdded by the agent.\n"
+        );
+        assert!(!root.join("old.txt").exists());
+
+        let backup = PathBuf::from(report.rsplit("Backup: ").next().unwrap());
+        assert_eq!(
+            std::fs::read_to_string(backup.join("old.txt")).unwrap(),
+            "stale\n"
+        );
+    }
+
+    #[test]
+    fn test_default_backups_are_removed_after_the_patch() {
+        let (base, _) = workspace();
+        let root = base.path().join("workspace");
+        let scratch = base.path().join("scratch");
+        let tool = FileAccess::new(&root)
+            .unwrap()
+            .patch_tool()
+            .scratch_dir(&scratch);
+
+        let report = tool.apply(PATCH).unwrap();
+        assert!(!report.contains("Backup:"), "{report}");
+        assert_eq!(std::fs::read_dir(&scratch).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_rejected_patches_change_nothing() {
+        let (base, tool) = workspace();
+        let root = base.path().join("workspace");
+
+        let stale = PATCH.replace("-    println!(\"hi\")", "-    println!(\"bye\")");
+        let err = tool.apply(&stale).unwrap_err().to_string();
+        assert!(
+            err.contains("hunk 1 of 'src/lib.rs' does not match"),
+            "{err}"
+        );
+        assert!(!root.join("docs").exists());
+        assert!(root.join("old.txt").exists());
+
+        let escape = "--- a/../outside.txt\n+++ b/../outside.txt\n@@ -0,0 +1 @@\n+x\n";
+        assert!(tool.apply(escape).is_err());
+        assert!(!base.path().join("outside.txt").exists());
+        assert!(tool.apply("not a diff").is_err());
+    }
+
+    #[test]
+    fn test_hunks_apply_after_shifted_lines() {
+        let file_patch = &parse(
+            "--- a/f\n+++ b/f\n@@ -2,1 +2,2 @@\n b\n+c\n@@ -5 +6 @@\n-e\n+E\n\\ No newline at end of file\n",
+        )
+        .unwrap()[0];
+        assert_eq!(
+            file_patch.apply_to("a\nb\nx\nd\ne\n").unwrap(),
+            "a\nb\nc\nx\nd\nE"
+        );
+    }
+
+    #[test]
+    fn test_zero_context_hunks_insert_after_their_line() {
+        // As printed by `diff -U0`.
+        let file_patch = &parse(
+            "--- a/f\n+++ b/f\n@@ -0,0 +1 @@\n+top\n@@ -3,0 +5,2 @@\n+x\n+y\n@@ -4 +6,0 @@\n-d\n",
+        )
+        .unwrap()[0];
+        assert_eq!(
+            file_patch.apply_to("a\nb\nc\nd\ne\n").unwrap(),
+            "top\na\nb\nc\nx\ny\ne\n"
+        );
+    }
+
+    #[test]
+    fn test_crlf_files_keep_their_line_endings() {
+        let file_patch = &parse("--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n a\n-b\n+B\n").unwrap()[0];
+        assert_eq!(file_patch.apply_to("a\r\nb\r\n").unwrap(), "a\r\nB\r\n");
+    }
+}
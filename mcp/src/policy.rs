@@ -0,0 +1,341 @@
+//! Per-tool authorization for [`RigMcpHandler`](crate::server::RigMcpHandler).
+//!
+//! A [`ToolAccessPolicy`] is checked inside `call_tool` before a request is
+//! dispatched to the underlying `ToolSet` or `ToolServer`. It can restrict
+//! which tools are exposed (allow and deny lists), cap how often each tool
+//! may be called, and validate call arguments. Tools the policy hides are
+//! also left out of `list_tools`.
+//!
+//! Use [`RigMcpHandler::with_policy`](crate::server::RigMcpHandler::with_policy)
+//! to serve the same tools to different agents with different privileges:
+//!
+//! ```ignore
+//! let handler = RigMcpHandler::from_toolset(toolset).await?;
+//! let reviewer = handler.with_policy(
+//!     ToolAccessPolicy::new()
+//!         .allow("read_file")
+//!         .argument_pattern("read_file", "/path", Regex::new(r"^src/[\w/.-]+$")?)
+//!         .rate_limit("read_file", 30, Duration::from_secs(60)),
+//! );
+//! let writer = handler.with_policy(ToolAccessPolicy::new().deny("delete_file"));
+//! ```
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Custom argument check: receives the call arguments and returns a reason on rejection.
+type ArgumentCheck = Arc<dyn Fn(&Value) -> Result<(), String> + Send + Sync>;
+
+/// Reason a tool call was refused by a [`ToolAccessPolicy`].
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The tool is denied or not on the allow list.
+    #[error("Tool '{tool}' is not permitted by the access policy")]
+    NotAllowed {
+        /// Name of the refused tool.
+        tool: String,
+    },
+    /// The tool was called more often than its rate limit allows.
+    #[error("Tool '{tool}' exceeded its rate limit of {max_calls} calls per {per:?}")]
+    RateLimited {
+        /// Name of the refused tool.
+        tool: String,
+        /// Calls allowed per window.
+        max_calls: usize,
+        /// Length of the window.
+        per: Duration,
+    },
+    /// An argument failed validation.
+    #[error("Tool '{tool}' rejected argument '{argument}': {reason}")]
+    InvalidArgument {
+        /// Name of the refused tool.
+        tool: String,
+        /// JSON pointer of the argument, or `*` for custom validators.
+        argument: String,
+        /// Why the argument was rejected.
+        reason: String,
+    },
+}
+
+/// Sliding-window call limit for one tool.
+struct RateLimit {
+    max_calls: usize,
+    per: Duration,
+    calls: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimit {
+    /// Records a call at `now`, or returns `false` if the window is full.
+    fn try_acquire(&self, now: Instant) -> bool {
+        let mut calls = self
+            .calls
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        while calls
+            .front()
+            .is_some_and(|&call| now.duration_since(call) >= self.per)
+        {
+            calls.pop_front();
+        }
+        if calls.len() >= self.max_calls {
+            return false;
+        }
+        calls.push_back(now);
+        true
+    }
+}
+
+/// A check applied to the arguments of one tool.
+#[derive(Clone)]
+enum ArgumentValidator {
+    /// The string at a JSON pointer must match a regex.
+    Pattern { pointer: String, regex: Regex },
+    /// A user-supplied check over all arguments.
+    Custom(ArgumentCheck),
+}
+
+/// Allow/deny lists, rate limits, and argument validators for MCP tool calls.
+///
+/// An empty policy permits everything. Cloning is cheap; clones share rate
+/// limit counters.
+#[derive(Clone, Default)]
+pub struct ToolAccessPolicy {
+    allow: Option<HashSet<String>>,
+    deny: HashSet<String>,
+    rate_limits: HashMap<String, Arc<RateLimit>>,
+    validators: HashMap<String, Vec<ArgumentValidator>>,
+}
+
+impl std::fmt::Debug for ToolAccessPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolAccessPolicy")
+            .field("allow", &self.allow)
+            .field("deny", &self.deny)
+            .field("rate_limited", &self.rate_limits.keys().collect::<Vec<_>>())
+            .field("validated", &self.validators.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl ToolAccessPolicy {
+    /// Creates a policy that permits every tool.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `tool` to the allow list. Once any tool is allowed, every tool
+    /// not on the list is refused.
+    #[must_use]
+    pub fn allow(mut self, tool: impl Into<String>) -> Self {
+        self.allow
+            .get_or_insert_with(HashSet::new)
+            .insert(tool.into());
+        self
+    }
+
+    /// Refuses `tool`, even if it is on the allow list.
+    #[must_use]
+    pub fn deny(mut self, tool: impl Into<String>) -> Self {
+        self.deny.insert(tool.into());
+        self
+    }
+
+    /// Allows at most `max_calls` calls of `tool` in any window of length `per`.
+    #[must_use]
+    pub fn rate_limit(mut self, tool: impl Into<String>, max_calls: usize, per: Duration) -> Self {
+        self.rate_limits.insert(
+            tool.into(),
+            Arc::new(RateLimit {
+                max_calls,
+                per,
+                calls: Mutex::new(VecDeque::new()),
+            }),
+        );
+        self
+    }
+
+    /// Requires the string argument at JSON pointer `pointer` (e.g. `/path`)
+    /// of `tool` to match `regex`. Calls without the argument pass; calls
+    /// where it is not a string are refused.
+    #[must_use]
+    pub fn argument_pattern(
+        mut self,
+        tool: impl Into<String>,
+        pointer: impl Into<String>,
+        regex: Regex,
+    ) -> Self {
+        self.validators
+            .entry(tool.into())
+            .or_default()
+            .push(ArgumentValidator::Pattern {
+                pointer: pointer.into(),
+                regex,
+            });
+        self
+    }
+
+    /// Runs `check` over the arguments of every `tool` call. Returning `Err`
+    /// refuses the call with the given reason.
+    #[must_use]
+    pub fn validator<F>(mut self, tool: impl Into<String>, check: F) -> Self
+    where
+        F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validators
+            .entry(tool.into())
+            .or_default()
+            .push(ArgumentValidator::Custom(Arc::new(check)));
+        self
+    }
+
+    /// Returns whether `tool` passes the allow and deny lists.
+    #[must_use]
+    pub fn permits(&self, tool: &str) -> bool {
+        !self.deny.contains(tool) && self.allow.as_ref().is_none_or(|allow| allow.contains(tool))
+    }
+
+    /// Checks a call of `tool` with `arguments` against the policy.
+    ///
+    /// Validators run before the rate limit, so refused calls do not use up
+    /// the tool's budget.
+    ///
+    /// # Errors
+    /// Returns the first [`PolicyViolation`] the call triggers.
+    pub fn check(&self, tool: &str, arguments: &Value) -> Result<(), PolicyViolation> {
+        if !self.permits(tool) {
+            return Err(PolicyViolation::NotAllowed {
+                tool: tool.to_string(),
+            });
+        }
+
+        for validator in self.validators.get(tool).into_iter().flatten() {
+            let rejected = |argument: &str, reason: String| PolicyViolation::InvalidArgument {
+                tool: tool.to_string(),
+                argument: argument.to_string(),
+                reason,
+            };
+            match validator {
+                ArgumentValidator::Pattern { pointer, regex } => match arguments.pointer(pointer) {
+                    None => {}
+                    Some(Value::String(value)) if regex.is_match(value) => {}
+                    Some(Value::String(value)) => {
+                        return Err(rejected(
+                            pointer,
+                            format!("'{value}' does not match /{regex}/"),
+                        ));
+                    }
+                    Some(_) => return Err(rejected(pointer, "expected a string".to_string())),
+                },
+                ArgumentValidator::Custom(check) => {
+                    check(arguments).map_err(|reason| rejected("*", reason))?;
+                }
+            }
+        }
+
+        if let Some(limit) = self.rate_limits.get(tool)
+            && !limit.try_acquire(Instant::now())
+        {
+            return Err(PolicyViolation::RateLimited {
+                tool: tool.to_string(),
+                max_calls: limit.max_calls,
+                per: limit.per,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let policy = ToolAccessPolicy::new();
+        assert!(policy.permits("anything"));
+
+        let policy = policy
+            .allow("submit")
+            .allow("validate_json")
+            .deny("validate_json");
+        assert!(policy.permits("submit"));
+        assert!(!policy.permits("validate_json"));
+        assert!(!policy.permits("json_example"));
+        assert_eq!(
+            policy.check("json_example", &json!({})),
+            Err(PolicyViolation::NotAllowed {
+                tool: "json_example".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_argument_validators() {
+        let policy = ToolAccessPolicy::new()
+            .argument_pattern("read_file", "/path", Regex::new(r"^src/[\w/.-]+$").unwrap())
+            .validator("read_file", |args| {
+                if args.get("recursive") == Some(&json!(true)) {
+                    Err("recursive reads are disabled".to_string())
+                } else {
+                    Ok(())
+                }
+            });
+
+        assert_eq!(
+            policy.check("read_file", &json!({ "path": "src/lib.rs" })),
+            Ok(())
+        );
+        assert_eq!(policy.check("read_file", &json!({})), Ok(()));
+
+        let err = policy
+            .check("read_file", &json!({ "path": "/etc/passwd" }))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r"Tool 'read_file' rejected argument '/path': '/etc/passwd' does not match /^src/[\w/.-]+$/"
+        );
+        assert!(matches!(
+            policy.check("read_file", &json!({ "path": 7 })),
+            Err(PolicyViolation::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            policy.check("read_file", &json!({ "path": "src/a.rs", "recursive": true })),
+            Err(PolicyViolation::InvalidArgument { argument, .. }) if argument == "*"
+        ));
+    }
+
+    #[test]
+    fn test_rate_limit_is_shared_by_clones() {
+        let policy = ToolAccessPolicy::new().rate_limit("submit", 2, Duration::from_secs(60));
+        let shared = policy.clone();
+
+        assert_eq!(policy.check("submit", &json!({})), Ok(()));
+        assert_eq!(shared.check("submit", &json!({})), Ok(()));
+        assert!(matches!(
+            policy.check("submit", &json!({})),
+            Err(PolicyViolation::RateLimited { max_calls: 2, .. })
+        ));
+        assert_eq!(policy.check("validate_json", &json!({})), Ok(()));
+    }
+
+    #[test]
+    fn test_rate_limit_window_slides() {
+        let limit = RateLimit {
+            max_calls: 1,
+            per: Duration::from_secs(10),
+            calls: Mutex::new(VecDeque::new()),
+        };
+        let now = Instant::now();
+        assert!(limit.try_acquire(now));
+        assert!(!limit.try_acquire(now + Duration::from_secs(9)));
+        assert!(limit.try_acquire(now + Duration::from_secs(10)));
+    }
+}
@@ -1,5 +1,11 @@
 //! MCP server implementation bridging Rig and RMCP.
 
+use crate::audit::{AuditLog, ToolCallRecord};
+use crate::harvest::HARVEST_PATH_ENV;
+use crate::pagination::{FETCH_MORE_TOOL, ResultPages};
+use crate::policy::ToolAccessPolicy;
+use crate::stats::{CallOutcome, STATS_TOOL, ServerStats, StatsRecorder};
+use crate::watchdog::{RestartPolicy, SessionEnd, Watchdog};
 use rig::completion::ToolDefinition;
 use rig::tool::server::{ToolServerError, ToolServerHandle};
 use rig::tool::{ToolError, ToolSet, ToolSetError};
@@ -24,12 +30,40 @@ enum ToolSource {
 }
 
 /// MCP server handler that serves tools from a Rig `ToolSet` or `ToolServer`.
+///
+/// Cloning is cheap: clones share the underlying tool source, which is how
+/// [`serve_tcp`](Self::serve_tcp) and [`serve_sse`](Self::serve_sse) hand
+/// the same tools to every connected client.
+#[derive(Clone)]
 pub struct RigMcpHandler {
-    source: ToolSource,
+    source: Arc<ToolSource>,
     /// The name of the server (e.g. "rig-mcp-server").
     pub name: String,
     /// Pre-computed tool definitions.
     pub tool_definitions: Vec<McpTool>,
+    /// Audit log receiving every tool call, if configured.
+    audit: Option<AuditLog>,
+    /// Unredacted log of every tool call, when [`HARVEST_PATH_ENV`] is set.
+    harvest: Option<AuditLog>,
+    /// Access policy checked before every tool call.
+    policy: ToolAccessPolicy,
+    /// Per-tool call statistics, shared by clones.
+    stats: StatsRecorder,
+    /// Whether the [`STATS_TOOL`] is served.
+    stats_tool: bool,
+    /// Rest of cut results, when results are paginated.
+    pages: Option<ResultPages>,
+    /// When [`serve_stdio`](Self::serve_stdio) serves a failed session again.
+    restart_policy: RestartPolicy,
+    /// Whether [`run_stdio`](Self::run_stdio) skips its startup banner.
+    quiet: bool,
+    /// Format [`run_stdio`](Self::run_stdio) prints the client configs in
+    /// instead of serving, if any.
+    print_config: Option<ConfigFormat>,
+    /// Arguments clients start the executable with, in [`config`](Self::config).
+    launch_args: Vec<String>,
+    /// Token remote clients must present, if any.
+    auth_token: Option<Arc<str>>,
 }
 
 impl RigMcpHandler {
@@ -79,31 +113,79 @@ impl RigMcpHandler {
         }
     }
 
+    /// Returns a handler serving the same tools under a different access policy.
+    ///
+    /// The returned handler shares this handler's tool source, audit log and
+    /// statistics, so one `ToolSet` can be exposed to several agents with different
+    /// privileges, e.g. one handler per [`serve_tcp`](Self::serve_tcp) port.
+    #[must_use]
+    pub fn with_policy(&self, policy: ToolAccessPolicy) -> Self {
+        Self {
+            policy,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the call statistics of every tool served so far, by this
+    /// handler and every handler sharing its tools.
+    #[must_use]
+    pub fn stats(&self) -> ServerStats {
+        self.stats.snapshot(&self.name)
+    }
+
+    /// Definition of the [`STATS_TOOL`].
+    fn stats_tool_definition() -> McpTool {
+        Self::definition_to_mcp(ToolDefinition {
+            name: STATS_TOOL.to_string(),
+            description: "Report per-tool call counts, latencies, validation failures and recent \
+                          errors of this MCP server."
+                .to_string(),
+            parameters: serde_json::json!({ "type": "object", "properties": {} }),
+        })
+    }
+
     /// Returns the configuration details for this MCP server.
     ///
     /// This provides programmatic access to the executable path, server name, and arguments,
     /// allowing you to automate the creation or editing of configuration files (e.g. `~/.claude.json`).
     ///
     /// # Errors
-    /// Returns an error if the current executable path cannot be determined.
+    /// Returns an error if the current executable path cannot be determined or is
+    /// not valid Unicode.
     pub fn config(&self) -> Result<McpConfig, std::io::Error> {
-        let exe = std::env::current_exe()?;
-        Ok(McpConfig {
-            name: self.name.clone(),
-            command: exe.to_string_lossy().to_string(),
-            args: vec![],
-            env: std::collections::HashMap::new(),
-        })
+        let mut config = McpConfig::for_current_exe(&self.name)?;
+        config.args.clone_from(&self.launch_args);
+        Ok(config)
     }
 
     /// Starts the server over stdio and prints configuration details for popular MCP clients.
     ///
     /// This method outputs ready-to-use configuration snippets to `stderr` and then blocks
-    /// while serving the protocol.
+    /// while serving the protocol. A [`quiet`](RigMcpHandlerBuilder::quiet) handler
+    /// prints nothing.
+    ///
+    /// With [`print_config`](RigMcpHandlerBuilder::print_config) set, it
+    /// instead prints the snippets to `stdout` in that format and returns
+    /// without serving.
     ///
     /// # Errors
-    /// Returns an error if the executable path cannot be determined or if the server fails to start.
+    /// Returns an error if the executable path cannot be determined or the
+    /// server fails to start.
     pub async fn run_stdio(self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(format) = self.print_config {
+            let config = self.config()?;
+            match format {
+                ConfigFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&config.to_client_configs_json())?
+                ),
+            }
+            return Ok(());
+        }
+
+        if self.quiet {
+            return self.serve_stdio().await;
+        }
         let config = self.config()?;
 
         eprintln!("\n\x1b[1;36m🚀 Rig MCP Server Starting...\x1b[0m");
@@ -141,16 +223,253 @@ impl RigMcpHandler {
     /// This method is intended to be used when you want to run the server as part of a larger
     /// application, for example by spawning it in a background task.
     ///
+    /// A [`watchdog`](crate::watchdog) logs how each session ends. A session
+    /// that fails is served again as the handler's
+    /// [`RestartPolicy`](RigMcpHandlerBuilder::restart_policy) allows, with
+    /// the same tools and statistics; one the client closes ends serving.
+    ///
     /// # Errors
-    /// Returns an error if the server fails to initialize or if the connection is lost.
+    /// Returns an error if a session fails to initialize or dies, and the
+    /// restart policy allows no further restarts.
     pub async fn serve_stdio(self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut watchdog = Watchdog::new(&self.name, "stdio", self.restart_policy);
+        loop {
+            let end = self.serve_stdio_session().await;
+            let Some(backoff) = watchdog.on_session_end(&end) else {
+                return match end {
+                    SessionEnd::Failed(error) => Err(error.into()),
+                    SessionEnd::Closed | SessionEnd::Cancelled => Ok(()),
+                };
+            };
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    /// Serves one session over stdio and reports how it ended.
+    async fn serve_stdio_session(&self) -> SessionEnd {
         let (stdin, stdout) = rmcp::transport::io::stdio();
-        let service = rmcp::ServiceExt::serve(self, (stdin, stdout)).await?;
-        service.waiting().await?;
+        let service = match rmcp::ServiceExt::serve(self.clone(), (stdin, stdout)).await {
+            Ok(service) => service,
+            Err(e) => return SessionEnd::Failed(e.to_string()),
+        };
+        match service.waiting().await {
+            Ok(rmcp::service::QuitReason::Closed) => SessionEnd::Closed,
+            Ok(rmcp::service::QuitReason::Cancelled) => SessionEnd::Cancelled,
+            Ok(reason) => SessionEnd::Failed(format!("{reason:?}")),
+            Err(e) => SessionEnd::Failed(e.to_string()),
+        }
+    }
+
+    /// Serves the MCP protocol over raw TCP, one session per connection.
+    ///
+    /// Each accepted connection speaks newline-delimited JSON-RPC, exactly
+    /// like stdio, so any client that can pipe stdio to a socket (e.g.
+    /// `nc host port`) can reach a central tool server. All sessions share
+    /// this handler's tools. With an
+    /// [`auth_token`](RigMcpHandlerBuilder::auth_token), a connection must
+    /// first send the line `Bearer <token>`; any other is closed. A failed
+    /// accept is logged and serving goes on.
+    ///
+    /// # Errors
+    /// Returns an error if the address cannot be bound.
+    pub async fn serve_tcp(
+        self,
+        addr: impl tokio::net::ToSocketAddrs,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        self.serve_tcp_on(listener).await
+    }
+
+    /// Serves [`serve_tcp`](Self::serve_tcp) sessions on a bound listener.
+    async fn serve_tcp_on(
+        self,
+        listener: tokio::net::TcpListener,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let addr = listener.local_addr()?;
+        self.warn_if_exposed(addr);
+        tracing::info!(target: "rig", %addr, "Serving MCP over TCP");
+
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // Running out of file descriptors or a connection reset
+                    // before it was accepted passes; back off briefly so a
+                    // persistent error does not spin.
+                    tracing::warn!(target: "rig", error = %e, "Failed to accept MCP TCP connection");
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let handler = self.clone();
+            tokio::spawn(async move {
+                // Buffered so bytes the client sent after its token line
+                // reach the session.
+                let mut stream = tokio::io::BufReader::new(stream);
+                if let Some(ref token) = handler.auth_token
+                    && !tcp_token_accepted(&mut stream, token).await
+                {
+                    tracing::warn!(target: "rig", %peer, "MCP TCP connection rejected: missing or wrong token");
+                    return;
+                }
+                match rmcp::ServiceExt::serve(handler, stream).await {
+                    Ok(service) => {
+                        if let Err(e) = service.waiting().await {
+                            tracing::warn!(target: "rig", %peer, error = %e, "MCP TCP session ended abnormally");
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!(target: "rig", %peer, error = %e, "MCP TCP session failed to initialize");
+                    }
+                }
+            });
+        }
+    }
+
+    /// Serves the MCP protocol over HTTP with server-sent events.
+    ///
+    /// Uses rmcp's Streamable HTTP transport mounted at [`SSE_PATH`]: clients
+    /// POST JSON-RPC requests and receive responses and notifications as an
+    /// SSE stream. This is the remote transport Claude Code, Codex, and
+    /// `OpenCode` accept natively; point them at the server with
+    /// [`remote_config`](Self::remote_config). With an
+    /// [`auth_token`](RigMcpHandlerBuilder::auth_token), requests without
+    /// the header `Authorization: Bearer <token>` are refused with
+    /// `401 Unauthorized`. Runs until the server fails.
+    ///
+    /// # Errors
+    /// Returns an error if the address cannot be bound or the HTTP server fails.
+    pub async fn serve_sse(
+        self,
+        addr: impl tokio::net::ToSocketAddrs,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        self.serve_sse_on(listener).await
+    }
+
+    /// Serves [`serve_sse`](Self::serve_sse) on a bound listener.
+    async fn serve_sse_on(
+        self,
+        listener: tokio::net::TcpListener,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        use rmcp::transport::streamable_http_server::{
+            StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+        };
+
+        let addr = listener.local_addr()?;
+        self.warn_if_exposed(addr);
+        let token = self.auth_token.clone();
+        let service = StreamableHttpService::new(
+            move || Ok(self.clone()),
+            LocalSessionManager::default().into(),
+            StreamableHttpServerConfig::default(),
+        );
+        let mut router = axum::Router::new().nest_service(SSE_PATH, service);
+        if let Some(token) = token {
+            router = router.layer(axum::middleware::from_fn_with_state(token, require_bearer));
+        }
+
+        tracing::info!(target: "rig", %addr, path = SSE_PATH, "Serving MCP over HTTP/SSE");
+        axum::serve(listener, router).await?;
         Ok(())
     }
+
+    /// Warns when a listener without a token is reachable from other hosts.
+    fn warn_if_exposed(&self, addr: std::net::SocketAddr) {
+        if self.auth_token.is_none() && !addr.ip().is_loopback() {
+            tracing::warn!(target: "rig", %addr, "Serving MCP beyond loopback without an auth token; any host that can connect may call the tools");
+        }
+    }
+
+    /// Returns client configuration for reaching this server at `url`, as
+    /// served by [`serve_sse`](Self::serve_sse).
+    ///
+    /// `url` is the full endpoint including [`SSE_PATH`], e.g.
+    /// `http://tools.internal:8080/mcp`.
+    #[must_use]
+    pub fn remote_config(&self, url: impl Into<String>) -> RemoteMcpConfig {
+        RemoteMcpConfig {
+            name: self.name.clone(),
+            url: url.into(),
+            bearer_token: self.auth_token.as_deref().map(str::to_string),
+        }
+    }
+}
+
+/// Path the [`RigMcpHandler::serve_sse`] endpoint is mounted at.
+pub const SSE_PATH: &str = "/mcp";
+
+/// Pause after a failed accept before accepting again.
+const ACCEPT_BACKOFF: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Longest authentication line a TCP client may send.
+const MAX_AUTH_LINE: u64 = 1024;
+
+/// How long a TCP client has to send its authentication line.
+const AUTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Reads the `Bearer <token>` line a TCP client must open with, leaving
+/// the rest of the stream for the session. A client that does not finish
+/// the line within [`AUTH_TIMEOUT`] is refused.
+async fn tcp_token_accepted(
+    stream: &mut tokio::io::BufReader<tokio::net::TcpStream>,
+    token: &str,
+) -> bool {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+    let mut line = Vec::new();
+    let mut limited = (&mut *stream).take(MAX_AUTH_LINE + 1);
+    let read = limited.read_until(b'\n', &mut line);
+    match tokio::time::timeout(AUTH_TIMEOUT, read).await {
+        Ok(Ok(_)) if line.ends_with(b"\n") => {}
+        _ => return false,
+    }
+    let line = line.strip_suffix(b"\n").unwrap_or(&line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    line.strip_prefix(b"Bearer ")
+        .is_some_and(|given| token_matches(given, token))
+}
+
+/// Refuses HTTP requests without the handler's bearer token.
+async fn require_bearer(
+    axum::extract::State(token): axum::extract::State<Arc<str>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let accepted = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.as_bytes().strip_prefix(b"Bearer "))
+        .is_some_and(|given| token_matches(given, &token));
+    if accepted {
+        next.run(request).await
+    } else {
+        axum::response::IntoResponse::into_response(axum::http::StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Compares a presented token in constant time for tokens of its length.
+fn token_matches(given: &[u8], token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .iter()
+            .zip(token.as_bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Formats [`RigMcpHandler::run_stdio`] can print the client configs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// One JSON object, see [`McpConfig::to_client_configs_json`].
+    Json,
 }
 
+/// Environment variable naming the server an agent run started the current
+/// executable as, so one executable can serve a different toolset for each
+/// of several servers.
+pub const SERVER_NAME_ENV: &str = "RIG_MCP_SERVER_NAME";
+
 /// Programmatic configuration details for an MCP server.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct McpConfig {
@@ -165,6 +484,37 @@ pub struct McpConfig {
 }
 
 impl McpConfig {
+    /// Configuration launching the current executable, without arguments,
+    /// as the server `name`.
+    ///
+    /// # Errors
+    /// Returns an error if the current executable path cannot be determined
+    /// or is not valid Unicode.
+    pub fn for_current_exe(name: impl Into<String>) -> Result<Self, std::io::Error> {
+        let exe = std::env::current_exe()?;
+        Ok(Self {
+            name: name.into(),
+            command: config_path_string(&exe)?,
+            args: vec![],
+            env: std::collections::HashMap::new(),
+        })
+    }
+
+    /// Returns the three client configurations as one JSON object, for
+    /// tools that write config files: `claude` is
+    /// [`to_claude_json`](Self::to_claude_json), `codex` the table of
+    /// [`to_codex_toml`](Self::to_codex_toml) as JSON, and `opencode`
+    /// [`to_opencode_json`](Self::to_opencode_json).
+    #[must_use]
+    pub fn to_client_configs_json(&self) -> serde_json::Value {
+        let codex = codex_servers_table(&self.name, self.codex_server_table());
+        serde_json::json!({
+            "claude": self.to_claude_json(),
+            "codex": serde_json::to_value(codex).unwrap_or_default(),
+            "opencode": self.to_opencode_json(),
+        })
+    }
+
     /// Returns the configuration in `Claude` Code JSON format.
     /// This typically goes into `~/.claude.json` or `.mcp.json`.
     #[must_use]
@@ -182,21 +532,47 @@ impl McpConfig {
 
     /// Returns the configuration in `Codex` TOML format.
     /// This typically goes into `~/.codex/config.toml`.
+    ///
+    /// Strings and keys are escaped as TOML requires, so paths with spaces,
+    /// backslashes or quotes come through unchanged.
     #[must_use]
     pub fn to_codex_toml(&self) -> String {
-        let mut toml = format!(
-            "[mcp_servers.{}]\ncommand = \"{}\"\nargs = {:?}",
-            self.name, self.command, self.args
-        );
+        codex_servers_table(&self.name, self.codex_server_table()).to_string()
+    }
+
+    /// Returns the configuration as `Codex` `-c key=value` overrides, for
+    /// registering the server for a single `codex exec` run.
+    ///
+    /// Each value is a TOML literal, escaped as in
+    /// [`to_codex_toml`](Self::to_codex_toml). The environment is set as one
+    /// inline table, so variable names need no quoting in the key path.
+    #[must_use]
+    pub fn to_codex_overrides(&self) -> Vec<(String, String)> {
+        self.codex_server_table()
+            .into_iter()
+            .map(|(key, value)| {
+                (
+                    format!("mcp_servers.{}.{key}", self.name),
+                    value.to_string(),
+                )
+            })
+            .collect()
+    }
 
+    /// The server's entry under `mcp_servers` in Codex's config.
+    fn codex_server_table(&self) -> toml::Table {
+        let mut server = toml::Table::new();
+        server.insert("command".to_string(), self.command.clone().into());
+        server.insert("args".to_string(), self.args.clone().into());
         if !self.env.is_empty() {
-            use std::fmt::Write;
-            let _ = writeln!(toml, "\n[mcp_servers.{}.env]", self.name);
-            for (k, v) in &self.env {
-                let _ = writeln!(toml, "{k} = \"{v}\"");
-            }
+            let env: toml::Table = self
+                .env
+                .iter()
+                .map(|(k, v)| (k.clone(), toml::Value::from(v.clone())))
+                .collect();
+            server.insert("env".to_string(), env.into());
         }
-        toml
+        server
     }
 
     /// Returns the configuration in `OpenCode` JSON format.
@@ -216,11 +592,119 @@ impl McpConfig {
     }
 }
 
+/// Configuration details for a remote MCP server reached over HTTP/SSE.
+///
+/// The counterpart of [`McpConfig`] for servers started with
+/// [`RigMcpHandler::serve_sse`]: clients connect to `url` instead of
+/// spawning a process, so one tool server can serve agents on many machines.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RemoteMcpConfig {
+    /// The name of the server (e.g. "rig-mcp-server").
+    pub name: String,
+    /// The endpoint URL, e.g. `http://tools.internal:8080/mcp`.
+    pub url: String,
+    /// Token sent as `Authorization: Bearer <token>`, for servers with an
+    /// [`auth_token`](RigMcpHandlerBuilder::auth_token).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+}
+
+impl RemoteMcpConfig {
+    /// Returns the configuration in `Claude` Code JSON format.
+    /// This typically goes into `~/.claude.json` or `.mcp.json`.
+    #[must_use]
+    pub fn to_claude_json(&self) -> serde_json::Value {
+        let mut server = serde_json::json!({
+            "type": "http",
+            "url": &self.url
+        });
+        if let Some(headers) = self.headers_json() {
+            server["headers"] = headers;
+        }
+        serde_json::json!({ "mcpServers": { &self.name: server } })
+    }
+
+    /// Returns the configuration in `Codex` TOML format.
+    /// This typically goes into `~/.codex/config.toml`.
+    #[must_use]
+    pub fn to_codex_toml(&self) -> String {
+        let mut server = toml::Table::new();
+        server.insert("url".to_string(), self.url.clone().into());
+        if let Some(ref token) = self.bearer_token {
+            let mut headers = toml::Table::new();
+            headers.insert(
+                "Authorization".to_string(),
+                format!("Bearer {token}").into(),
+            );
+            server.insert("http_headers".to_string(), headers.into());
+        }
+        codex_servers_table(&self.name, server).to_string()
+    }
+
+    /// Returns the configuration in `OpenCode` JSON format.
+    /// This typically goes into `opencode.json`.
+    #[must_use]
+    pub fn to_opencode_json(&self) -> serde_json::Value {
+        let mut server = serde_json::json!({
+            "type": "remote",
+            "url": &self.url,
+            "enabled": true
+        });
+        if let Some(headers) = self.headers_json() {
+            server["headers"] = headers;
+        }
+        serde_json::json!({ "mcp": { &self.name: server } })
+    }
+
+    /// The `headers` object carrying the bearer token, if there is one.
+    fn headers_json(&self) -> Option<serde_json::Value> {
+        self.bearer_token
+            .as_ref()
+            .map(|token| serde_json::json!({ "Authorization": format!("Bearer {token}") }))
+    }
+}
+
+/// Wraps a server's entry as `mcp_servers.<name>` of a Codex config.
+fn codex_servers_table(name: &str, server: toml::Table) -> toml::Table {
+    let mut servers = toml::Table::new();
+    servers.insert(name.to_string(), server.into());
+    let mut root = toml::Table::new();
+    root.insert("mcp_servers".to_string(), servers.into());
+    root
+}
+
+/// Converts a path for use in an MCP config.
+///
+/// The configs are JSON or TOML text, which cannot carry a path that is not
+/// valid Unicode; rather than replace its bytes and launch the wrong file,
+/// such a path is an error.
+///
+/// # Errors
+/// Returns [`std::io::ErrorKind::InvalidData`] if the path is not valid
+/// Unicode.
+pub fn config_path_string(path: &std::path::Path) -> Result<String, std::io::Error> {
+    path.to_str().map(str::to_string).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("path is not valid Unicode: {}", path.display()),
+        )
+    })
+}
+
 /// Builder for `RigMcpHandler`.
 pub struct RigMcpHandlerBuilder {
     toolset: Option<ToolSet>,
     tool_server: Option<ToolServerHandle>,
     name: String,
+    audit: Option<AuditLog>,
+    policy: ToolAccessPolicy,
+    stats_tool: bool,
+    page_bytes: Option<usize>,
+    restart_policy: RestartPolicy,
+    quiet: bool,
+    print_config: Option<ConfigFormat>,
+    launch_args: Vec<String>,
+    auth_token: Option<Arc<str>>,
 }
 
 impl Default for RigMcpHandlerBuilder {
@@ -229,6 +713,15 @@ impl Default for RigMcpHandlerBuilder {
             toolset: None,
             tool_server: None,
             name: "rig-mcp-server".to_string(),
+            audit: None,
+            policy: ToolAccessPolicy::default(),
+            stats_tool: false,
+            page_bytes: None,
+            restart_policy: RestartPolicy::default(),
+            quiet: false,
+            print_config: None,
+            launch_args: Vec::new(),
+            auth_token: None,
         }
     }
 }
@@ -255,52 +748,170 @@ impl RigMcpHandlerBuilder {
         self
     }
 
+    /// Records every tool call served by the handler to `audit`.
+    #[must_use]
+    pub fn audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Restricts which tools can be called and with what arguments.
+    /// Defaults to a policy that permits everything.
+    #[must_use]
+    pub fn access_policy(mut self, policy: ToolAccessPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Also serves a [`STATS_TOOL`] returning [`RigMcpHandler::stats`] as
+    /// JSON, so MCP clients can see how their calls are faring. Ignored if
+    /// the tools already include one with that name. Default: off.
+    #[must_use]
+    pub const fn stats_tool(mut self, enabled: bool) -> Self {
+        self.stats_tool = enabled;
+        self
+    }
+
+    /// Cuts tool results longer than `page_bytes` and serves a
+    /// [`FETCH_MORE_TOOL`] returning the rest page by page, so one large
+    /// result cannot fill the agent's context; see [`crate::pagination`].
+    /// Ignored if the tools already include one with that name. Default:
+    /// results are returned whole.
+    #[must_use]
+    pub const fn paginate_results(mut self, page_bytes: usize) -> Self {
+        self.page_bytes = Some(page_bytes);
+        self
+    }
+
+    /// Sets when [`RigMcpHandler::serve_stdio`] serves a failed session
+    /// again. Default: never.
+    #[must_use]
+    pub const fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
+    /// Stops [`RigMcpHandler::run_stdio`] from printing its startup banner
+    /// and client config snippets to `stderr`, e.g. when stderr goes to a
+    /// log processor. Default: off.
+    #[must_use]
+    pub const fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Makes [`RigMcpHandler::run_stdio`] print the client configs to
+    /// `stdout` in `format` and return instead of serving, e.g. for a
+    /// `--print-config` flag. Default: serve.
+    #[must_use]
+    pub const fn print_config(mut self, format: Option<ConfigFormat>) -> Self {
+        self.print_config = format;
+        self
+    }
+
+    /// Sets the arguments MCP clients start the executable with, as they
+    /// appear in [`RigMcpHandler::config`] and the printed client configs.
+    /// Default: none.
+    #[must_use]
+    pub fn launch_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.launch_args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Requires clients of [`RigMcpHandler::serve_tcp`] and
+    /// [`RigMcpHandler::serve_sse`] to present `token`, which
+    /// [`RigMcpHandler::remote_config`] then carries. Default: any client
+    /// that can connect is served, so bind a loopback address.
+    #[must_use]
+    pub fn auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(token.into().into());
+        self
+    }
+
+    /// Assembles the handler from a tool source and its definitions.
+    fn finish(self, source: ToolSource, definitions: Vec<ToolDefinition>) -> RigMcpHandler {
+        let mut tool_definitions: Vec<McpTool> = definitions
+            .into_iter()
+            .map(RigMcpHandler::definition_to_mcp)
+            .collect();
+        let stats_tool =
+            self.stats_tool && !tool_definitions.iter().any(|tool| tool.name == STATS_TOOL);
+        if stats_tool {
+            tool_definitions.push(RigMcpHandler::stats_tool_definition());
+        } else if self.stats_tool {
+            tracing::warn!(target: "rig", tool_name = STATS_TOOL, "A tool already uses the stats tool name; not serving stats");
+        }
+        let page_bytes = self.page_bytes.filter(|_| {
+            !tool_definitions
+                .iter()
+                .any(|tool| tool.name == FETCH_MORE_TOOL)
+        });
+        if page_bytes.is_some() {
+            tool_definitions.push(RigMcpHandler::definition_to_mcp(ResultPages::definition()));
+        } else if self.page_bytes.is_some() {
+            tracing::warn!(target: "rig", tool_name = FETCH_MORE_TOOL, "A tool already uses the fetch_more tool name; returning results whole");
+        }
+        RigMcpHandler {
+            source: Arc::new(source),
+            name: self.name,
+            tool_definitions,
+            audit: self.audit,
+            harvest: harvest_log(),
+            policy: self.policy,
+            stats: StatsRecorder::default(),
+            stats_tool,
+            pages: page_bytes.map(ResultPages::new),
+            restart_policy: self.restart_policy,
+            quiet: self.quiet,
+            print_config: self.print_config,
+            launch_args: self.launch_args,
+            auth_token: self.auth_token,
+        }
+    }
+
     /// Builds the handler from a `ToolSet`.
     ///
     /// # Errors
     /// Returns `ToolSetError` if the toolset is missing or if fetching definitions fails.
-    pub async fn build(self) -> Result<RigMcpHandler, ToolSetError> {
-        let toolset = self.toolset.ok_or_else(|| {
+    pub async fn build(mut self) -> Result<RigMcpHandler, ToolSetError> {
+        let toolset = self.toolset.take().ok_or_else(|| {
             ToolSetError::ToolCallError(ToolError::ToolCallError(
                 "ToolSet is required for build(); call .toolset() first".into(),
             ))
         })?;
         let definitions = toolset.get_tool_definitions().await?;
-        let tool_definitions = definitions
-            .into_iter()
-            .map(RigMcpHandler::definition_to_mcp)
-            .collect();
-        Ok(RigMcpHandler {
-            source: ToolSource::Set(toolset),
-            name: self.name,
-            tool_definitions,
-        })
+        Ok(self.finish(ToolSource::Set(toolset), definitions))
     }
 
     /// Builds the handler from a `ToolServerHandle`.
     ///
     /// # Errors
     /// Returns `ToolServerError` if the server handle is missing or if fetching definitions fails.
-    pub async fn build_from_server(self) -> Result<RigMcpHandler, ToolServerError> {
-        let handle = self.tool_server.ok_or_else(|| {
+    pub async fn build_from_server(mut self) -> Result<RigMcpHandler, ToolServerError> {
+        let handle = self.tool_server.take().ok_or_else(|| {
             ToolServerError::ToolsetError(ToolSetError::ToolCallError(ToolError::ToolCallError(
                 "ToolServerHandle is required for build_from_server(); call .tool_server() first"
                     .into(),
             )))
         })?;
         let definitions = handle.get_tool_defs(None).await?;
-        let tool_definitions = definitions
-            .into_iter()
-            .map(RigMcpHandler::definition_to_mcp)
-            .collect();
-        Ok(RigMcpHandler {
-            source: ToolSource::Server(handle),
-            name: self.name,
-            tool_definitions,
-        })
+        Ok(self.finish(ToolSource::Server(handle), definitions))
     }
 }
 
+/// Opens the harvest file named by [`HARVEST_PATH_ENV`], if set.
+///
+/// A file that cannot be opened is logged and skipped, so a misconfigured
+/// harvest never stops the server from serving tools.
+fn harvest_log() -> Option<AuditLog> {
+    let path = std::env::var_os(HARVEST_PATH_ENV)?;
+    AuditLog::file(&path)
+        .inspect_err(|e| {
+            tracing::warn!(target: "rig", path = %std::path::Path::new(&path).display(), error = %e, "Failed to open tool call harvest file");
+        })
+        .ok()
+}
+
 /// Extension trait for `ToolSet` to provide MCP integration.
 #[async_trait::async_trait]
 pub trait ToolSetExt {
@@ -325,13 +936,7 @@ impl ToolSetExt for ToolSet {
     }
 
     async fn config(&self) -> Result<McpConfig, std::io::Error> {
-        let exe = std::env::current_exe()?;
-        Ok(McpConfig {
-            name: "rig-mcp-server".to_string(),
-            command: exe.to_string_lossy().to_string(),
-            args: vec![],
-            env: std::collections::HashMap::new(),
-        })
+        McpConfig::for_current_exe("rig-mcp-server")
     }
 }
 
@@ -355,9 +960,13 @@ impl ServerHandler for RigMcpHandler {
 
     async fn initialize(
         &self,
-        _request: rmcp::model::InitializeRequestParams,
-        _context: RequestContext<RoleServer>,
+        request: rmcp::model::InitializeRequestParams,
+        context: RequestContext<RoleServer>,
     ) -> Result<rmcp::model::InitializeResult, ErrorData> {
+        // Keep the client's identity so audit records can name the caller.
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
         Ok(self.get_info())
     }
 
@@ -368,17 +977,22 @@ impl ServerHandler for RigMcpHandler {
         _context: RequestContext<RoleServer>,
     ) -> Result<ListToolsResult, ErrorData> {
         Ok(ListToolsResult {
-            tools: self.tool_definitions.clone(),
+            tools: self
+                .tool_definitions
+                .iter()
+                .filter(|tool| self.policy.permits(&tool.name))
+                .cloned()
+                .collect(),
             next_cursor: None,
             meta: None,
         })
     }
 
-    #[tracing::instrument(skip(self, request, _context), fields(rpc.method = "call_tool", tool.name = %request.name))]
+    #[tracing::instrument(skip(self, request, context), fields(rpc.method = "call_tool", tool.name = %request.name))]
     async fn call_tool(
         &self,
         request: CallToolRequestParams,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, ErrorData> {
         let args_str = request
             .arguments
@@ -387,19 +1001,81 @@ impl ServerHandler for RigMcpHandler {
 
         tracing::debug!(target: "rig", tool_name = %request.name, "Calling tool via MCP bridge");
 
-        let result = match &self.source {
-            ToolSource::Set(set) => set
-                .call(&request.name, args_str)
-                .await
-                .map_err(|e| e.to_string()),
-            ToolSource::Server(server) => server
-                .call_tool(&request.name, &args_str)
-                .await
-                .map_err(|e| e.to_string()),
+        let started_at = std::time::SystemTime::now();
+        let start = std::time::Instant::now();
+        let arguments = request.arguments.clone().map_or(Value::Null, Value::Object);
+        let mut refused = false;
+        let is_stats_call = self.stats_tool && request.name == STATS_TOOL;
+        let fetch_more = self
+            .pages
+            .as_ref()
+            .filter(|_| request.name == FETCH_MORE_TOOL);
+        let result = if let Err(violation) = self.policy.check(&request.name, &arguments) {
+            tracing::warn!(target: "rig", tool_name = %request.name, %violation, "Tool call refused by access policy");
+            refused = true;
+            Err(violation.to_string())
+        } else if is_stats_call {
+            serde_json::to_string(&self.stats()).map_err(|e| e.to_string())
+        } else if let Some(pages) = fetch_more {
+            pages.fetch(&arguments)
+        } else {
+            match self.source.as_ref() {
+                ToolSource::Set(set) => set
+                    .call(&request.name, args_str)
+                    .await
+                    .map_err(|e| e.to_string()),
+                ToolSource::Server(server) => server
+                    .call_tool(&request.name, &args_str)
+                    .await
+                    .map_err(|e| e.to_string()),
+            }
         };
 
+        if !is_stats_call && fetch_more.is_none() {
+            let outcome = match &result {
+                Ok(output) => CallOutcome::Success(output),
+                Err(e) if refused => CallOutcome::Refused(e),
+                Err(e) => CallOutcome::Failed(e),
+            };
+            self.stats.record(&request.name, outcome, start.elapsed());
+        }
+
+        if self.audit.is_some() || self.harvest.is_some() {
+            let client = context.peer.peer_info().map(|info| &info.client_info);
+            let (output, is_error) = match &result {
+                Ok(output) => (output.clone(), false),
+                Err(e) => (e.clone(), true),
+            };
+            let record = ToolCallRecord {
+                timestamp_ms: started_at
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX)),
+                server: self.name.clone(),
+                tool: request.name.to_string(),
+                arguments,
+                result: output,
+                is_error,
+                duration_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+                client_name: client.map(|c| c.name.clone()),
+                client_version: client.map(|c| c.version.clone()),
+            };
+            if let Some(harvest) = &self.harvest {
+                harvest.record(record.clone());
+            }
+            if let Some(audit) = &self.audit {
+                audit.record(record);
+            }
+        }
+
         match result {
-            Ok(output) => Ok(CallToolResult::success(vec![Content::text(output)])),
+            Ok(output) => {
+                // A fetched page was cut when it was fetched.
+                let output = match &self.pages {
+                    Some(pages) if fetch_more.is_none() => pages.first_page(output),
+                    _ => output,
+                };
+                Ok(CallToolResult::success(vec![Content::text(output)]))
+            }
             Err(e) => {
                 tracing::error!(target: "rig", tool_name = %request.name, error = %e, "Tool call failed");
                 Ok(CallToolResult::error(vec![Content::text(e)]))
@@ -407,3 +1083,112 @@ impl ServerHandler for RigMcpHandler {
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_config_carries_launch_args() {
+        let handler = RigMcpHandler::builder()
+            .toolset(ToolSet::default())
+            .launch_args(["serve", "--schema", "/etc/schema.json"])
+            .build()
+            .await
+            .unwrap();
+        let config = handler.config().unwrap();
+        assert_eq!(config.args, ["serve", "--schema", "/etc/schema.json"]);
+        assert_eq!(
+            config.to_client_configs_json()["claude"]["mcpServers"]["rig-mcp-server"]["args"][0],
+            "serve"
+        );
+    }
+
+    async fn guarded_handler() -> RigMcpHandler {
+        RigMcpHandler::builder()
+            .toolset(ToolSet::default())
+            .auth_token("s3cret")
+            .build()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tcp_sessions_require_the_token() {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = guarded_handler().await.serve_tcp_on(listener).await;
+        });
+        let initialize = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26","#,
+            r#""capabilities":{},"clientInfo":{"name":"test","version":"1"}}}"#,
+            "\n"
+        );
+
+        let mut stranger = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stranger.write_all(initialize.as_bytes()).await.unwrap();
+        let mut rest = Vec::new();
+        stranger.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"Bearer s3cret\r\n").await.unwrap();
+        client.write_all(initialize.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        BufReader::new(client)
+            .read_line(&mut response)
+            .await
+            .unwrap();
+        assert!(response.contains(r#""id":1"#), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_silent_tcp_clients_are_disconnected() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = guarded_handler().await.serve_tcp_on(listener).await;
+        });
+
+        let mut silent = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut rest = Vec::new();
+        tokio::time::timeout(AUTH_TIMEOUT * 2, silent.read_to_end(&mut rest))
+            .await
+            .expect("server kept a silent client open")
+            .unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sse_requests_require_the_bearer_header() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}{SSE_PATH}", listener.local_addr().unwrap());
+        let handler = guarded_handler().await;
+        let remote = handler.remote_config(&url);
+        assert_eq!(remote.bearer_token.as_deref(), Some("s3cret"));
+        tokio::spawn(async move {
+            let _ = handler.serve_sse_on(listener).await;
+        });
+
+        let client = reqwest::Client::new();
+        let post = |token: Option<&str>| {
+            let mut request = client
+                .post(&url)
+                .header("Accept", "application/json, text/event-stream")
+                .body("{}");
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            request.send()
+        };
+        assert_eq!(post(None).await.unwrap().status(), 401);
+        assert_eq!(post(Some("guess")).await.unwrap().status(), 401);
+        assert_ne!(post(Some("s3cret")).await.unwrap().status(), 401);
+    }
+}
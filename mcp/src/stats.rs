@@ -0,0 +1,232 @@
+//! Per-tool call statistics for [`RigMcpHandler`](crate::server::RigMcpHandler).
+//!
+//! Every `call_tool` request is counted under its tool name with its
+//! latency and outcome. Calls whose arguments fail schema validation are
+//! counted separately, and the most recent failure messages are kept as
+//! samples, so an orchestrating process can tell an agent that struggles
+//! with a schema from a tool that is slow or broken.
+//!
+//! ```ignore
+//! let handler = RigMcpHandler::builder()
+//!     .toolset(toolset)
+//!     .stats_tool(true)
+//!     .build()
+//!     .await?;
+//! // ... after a run:
+//! for (tool, stats) in &handler.stats().tools {
+//!     println!("{tool}: {:.0}% invalid", stats.validation_failure_rate() * 100.0);
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Name of the optional tool that reports [`ServerStats`] to MCP clients.
+pub const STATS_TOOL: &str = "stats";
+
+/// Number of failure messages kept per tool.
+pub const ERROR_SAMPLES: usize = 5;
+
+/// Longest failure message kept, in characters.
+const MAX_SAMPLE_CHARS: usize = 300;
+
+/// Text that marks a schema validation failure in a tool's output or error:
+/// `validate_json` feedback, a rejected `submit`, and arguments that did not
+/// deserialize.
+const VALIDATION_MARKERS: &[&str] = &["JSON validation failed", "Validation error", "JsonError"];
+
+/// Counters for one tool.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolStats {
+    /// Calls received, including refused and failed ones.
+    pub calls: u64,
+    /// Calls that returned an error, including refusals.
+    pub errors: u64,
+    /// Calls refused by the access policy.
+    pub refused: u64,
+    /// Calls whose arguments failed schema validation.
+    pub validation_failures: u64,
+    /// Sum of call latencies in milliseconds.
+    pub total_latency_ms: u64,
+    /// Slowest call in milliseconds.
+    pub max_latency_ms: u64,
+    /// The last [`ERROR_SAMPLES`] error and validation messages, oldest
+    /// first, truncated.
+    pub recent_errors: Vec<String>,
+}
+
+impl ToolStats {
+    /// Mean call latency in milliseconds, or 0 before the first call.
+    #[must_use]
+    pub const fn mean_latency_ms(&self) -> u64 {
+        match self.total_latency_ms.checked_div(self.calls) {
+            Some(mean) => mean,
+            None => 0,
+        }
+    }
+
+    /// Fraction of calls that failed schema validation, from 0.0 to 1.0.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // Call counts stay far below 2^52.
+    pub fn validation_failure_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.validation_failures as f64 / self.calls as f64
+        }
+    }
+
+    /// Counts one call.
+    fn add(&mut self, outcome: CallOutcome<'_>, latency_ms: u64) {
+        let (message, failed) = match outcome {
+            CallOutcome::Success(output) => (output, false),
+            CallOutcome::Refused(message) => {
+                self.refused += 1;
+                (message, true)
+            }
+            CallOutcome::Failed(message) => (message, true),
+        };
+        let invalid = !matches!(outcome, CallOutcome::Refused(_)) && is_validation_failure(message);
+
+        self.calls += 1;
+        self.total_latency_ms = self.total_latency_ms.saturating_add(latency_ms);
+        self.max_latency_ms = self.max_latency_ms.max(latency_ms);
+        if failed {
+            self.errors += 1;
+        }
+        if invalid {
+            self.validation_failures += 1;
+        }
+        if failed || invalid {
+            if self.recent_errors.len() == ERROR_SAMPLES {
+                self.recent_errors.remove(0);
+            }
+            self.recent_errors.push(sample(message));
+        }
+    }
+}
+
+/// Snapshot of the statistics of every tool a handler has served.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerStats {
+    /// Name of the MCP server.
+    pub server: String,
+    /// Statistics by tool name. Tools never called are absent.
+    pub tools: BTreeMap<String, ToolStats>,
+}
+
+/// How a served call ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CallOutcome<'a> {
+    /// The tool returned this output.
+    Success(&'a str),
+    /// The access policy refused the call for this reason.
+    Refused(&'a str),
+    /// The tool failed with this message.
+    Failed(&'a str),
+}
+
+/// Shared, thread-safe statistics store. Clones update the same counters.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StatsRecorder {
+    tools: Arc<Mutex<BTreeMap<String, ToolStats>>>,
+}
+
+impl StatsRecorder {
+    /// Counts one call to `tool`.
+    pub(crate) fn record(&self, tool: &str, outcome: CallOutcome<'_>, latency: Duration) {
+        let latency_ms = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+        self.tools
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .entry(tool.to_string())
+            .or_default()
+            .add(outcome, latency_ms);
+    }
+
+    /// Copies the current counters.
+    pub(crate) fn snapshot(&self, server: &str) -> ServerStats {
+        let tools = self
+            .tools
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        ServerStats {
+            server: server.to_string(),
+            tools: tools.clone(),
+        }
+    }
+}
+
+/// Whether a tool's output or error reports a schema validation failure.
+pub(crate) fn is_validation_failure(message: &str) -> bool {
+    VALIDATION_MARKERS
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+/// Truncates `message` to [`MAX_SAMPLE_CHARS`].
+fn sample(message: &str) -> String {
+    match message.char_indices().nth(MAX_SAMPLE_CHARS) {
+        Some((cut, _)) => format!("{}...", &message[..cut]),
+        None => message.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_classifies_outcomes_and_keeps_recent_errors() {
+        let recorder = StatsRecorder::default();
+        let shared = recorder.clone();
+        let ms = Duration::from_millis;
+
+        recorder.record("submit", CallOutcome::Success("ok"), ms(10));
+        recorder.record(
+            "submit",
+            CallOutcome::Failed("ToolCallError: Validation error: missing 'name'"),
+            ms(30),
+        );
+        shared.record(
+            "validate_json",
+            CallOutcome::Success("JSON validation failed.\n\nErrors: ..."),
+            ms(2),
+        );
+        shared.record("delete", CallOutcome::Refused("not permitted"), ms(0));
+        for n in 0..ERROR_SAMPLES {
+            recorder.record("fetch", CallOutcome::Failed(&format!("timeout {n}")), ms(5));
+        }
+        recorder.record("fetch", CallOutcome::Failed(&"x".repeat(400)), ms(5));
+
+        let stats = recorder.snapshot("rig-mcp-server");
+        let submit = &stats.tools["submit"];
+        assert_eq!(
+            (submit.calls, submit.errors, submit.validation_failures),
+            (2, 1, 1)
+        );
+        assert_eq!((submit.mean_latency_ms(), submit.max_latency_ms), (20, 30));
+        assert!((submit.validation_failure_rate() - 0.5).abs() < f64::EPSILON);
+
+        let validate = &stats.tools["validate_json"];
+        assert_eq!((validate.errors, validate.validation_failures), (0, 1));
+        assert_eq!(validate.recent_errors.len(), 1);
+
+        let delete = &stats.tools["delete"];
+        assert_eq!(
+            (delete.errors, delete.refused, delete.validation_failures),
+            (1, 1, 0)
+        );
+
+        let fetch = &stats.tools["fetch"];
+        assert_eq!(fetch.recent_errors.len(), ERROR_SAMPLES);
+        assert_eq!(fetch.recent_errors[0], "timeout 1");
+        assert_eq!(
+            fetch.recent_errors[ERROR_SAMPLES - 1].len(),
+            MAX_SAMPLE_CHARS + 3
+        );
+    }
+}
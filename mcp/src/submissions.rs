@@ -0,0 +1,215 @@
+//! Every call of a submit tool, and which one counts.
+//!
+//! An agent may call `submit` more than once, e.g. to correct an answer.
+//! A toolkit's [`SubmitPolicy`] decides which submission becomes the run's
+//! result, and each call is appended as a [`SubmitRecord`] to the file
+//! named by [`SUBMISSIONS_PATH_ENV`], so the parent process can see every
+//! submission and when it was made.
+//!
+//! ```ignore
+//! let (submit, validate, example) = JsonSchemaToolkit::<Invoice>::builder()
+//!     .submit_policy(SubmitPolicy::FirstWins)
+//!     .build()
+//!     .build_tools();
+//! ```
+//!
+//! The policy is enforced per server process: a server restarted by its
+//! [`RestartPolicy`](crate::watchdog::RestartPolicy) starts over.
+
+use crate::tools::ToolError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Environment variable naming the file the MCP server appends submit
+/// records to.
+pub const SUBMISSIONS_PATH_ENV: &str = "RIG_MCP_SUBMISSIONS_PATH";
+
+/// Which of several submissions becomes the result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SubmitPolicy {
+    /// Every submission replaces the one before it.
+    #[default]
+    LastWins,
+    /// The first submission is kept; later ones are recorded and ignored.
+    FirstWins,
+    /// The first submission is kept; later ones are rejected with an error
+    /// the agent sees.
+    ErrorOnMultiple,
+}
+
+/// What happened to a submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmitOutcome {
+    /// Written as the result.
+    Accepted,
+    /// Left out under [`SubmitPolicy::FirstWins`].
+    Ignored,
+    /// Refused, for failing the schema or under
+    /// [`SubmitPolicy::ErrorOnMultiple`].
+    Rejected,
+}
+
+/// One call of a submit tool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmitRecord {
+    /// When the call was made, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// Name of the submit tool.
+    pub tool: String,
+    /// The submitted arguments.
+    pub submission: Value,
+    /// What happened to the submission.
+    pub outcome: SubmitOutcome,
+}
+
+impl SubmitRecord {
+    /// Reads the JSONL file the MCP server wrote.
+    ///
+    /// A missing file means no submission was made. Lines that do not
+    /// parse are skipped with a warning.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but cannot be read.
+    pub fn read_all(path: impl AsRef<Path>) -> std::io::Result<Vec<Self>> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    tracing::warn!(target: "rig", error = %e, "Skipping malformed submit record");
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Applies a [`SubmitPolicy`] to the submissions of one toolkit.
+#[derive(Debug)]
+pub(crate) struct SubmitGate {
+    policy: SubmitPolicy,
+    /// Submissions that passed validation so far.
+    submitted: AtomicUsize,
+}
+
+impl SubmitGate {
+    pub(crate) const fn new(policy: SubmitPolicy) -> Self {
+        Self {
+            policy,
+            submitted: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records a valid `submission` to `tool` and returns whether it becomes
+    /// the result.
+    ///
+    /// # Errors
+    /// Returns [`ToolError::Validation`] for a repeated submission under
+    /// [`SubmitPolicy::ErrorOnMultiple`].
+    pub(crate) fn admit(&self, tool: &str, submission: &Value) -> Result<bool, ToolError> {
+        let first = self.submitted.fetch_add(1, Ordering::SeqCst) == 0;
+        let outcome = match self.policy {
+            _ if first => SubmitOutcome::Accepted,
+            SubmitPolicy::LastWins => SubmitOutcome::Accepted,
+            SubmitPolicy::FirstWins => SubmitOutcome::Ignored,
+            SubmitPolicy::ErrorOnMultiple => SubmitOutcome::Rejected,
+        };
+        record(tool, submission, outcome);
+        match outcome {
+            SubmitOutcome::Accepted => Ok(true),
+            SubmitOutcome::Ignored => Ok(false),
+            SubmitOutcome::Rejected => Err(ToolError::Validation(
+                "a submission was already accepted; only one is allowed".to_string(),
+            )),
+        }
+    }
+
+    /// Records a `submission` to `tool` that failed validation.
+    pub(crate) fn reject(tool: &str, submission: &Value) {
+        record(tool, submission, SubmitOutcome::Rejected);
+    }
+}
+
+/// Appends a record to the [`SUBMISSIONS_PATH_ENV`] file, if set.
+fn record(tool: &str, submission: &Value, outcome: SubmitOutcome) {
+    let Ok(path) = std::env::var(SUBMISSIONS_PATH_ENV) else {
+        return;
+    };
+    let record = SubmitRecord {
+        timestamp_ms: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            }),
+        tool: tool.to_string(),
+        submission: submission.clone(),
+        outcome,
+    };
+    let appended = serde_json::to_string(&record)
+        .map_err(std::io::Error::other)
+        .and_then(|line| {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?;
+            writeln!(file, "{line}")
+        });
+    if let Err(e) = appended {
+        tracing::warn!(target: "rig", error = %e, "Failed to record submission");
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_policies_decide_repeated_submissions() {
+        let submission = json!({ "answer": 42 });
+        let admit = |policy| {
+            let gate = SubmitGate::new(policy);
+            (
+                gate.admit("submit", &submission).unwrap(),
+                gate.admit("submit", &submission).map_err(|e| e.to_string()),
+            )
+        };
+
+        assert_eq!(admit(SubmitPolicy::LastWins), (true, Ok(true)));
+        assert_eq!(admit(SubmitPolicy::FirstWins), (true, Ok(false)));
+        let (first, second) = admit(SubmitPolicy::ErrorOnMultiple);
+        assert!(first);
+        assert!(second.unwrap_err().contains("already accepted"));
+    }
+
+    #[test]
+    fn test_read_all_skips_malformed_lines() {
+        let path =
+            std::env::temp_dir().join(format!("rig-mcp-submissions-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert!(SubmitRecord::read_all(&path).unwrap().is_empty());
+
+        let record = SubmitRecord {
+            timestamp_ms: 1,
+            tool: "submit".to_string(),
+            submission: json!({}),
+            outcome: SubmitOutcome::Ignored,
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        std::fs::write(&path, format!("{line}\n{{\"timestamp_ms\n")).unwrap();
+
+        assert_eq!(SubmitRecord::read_all(&path).unwrap(), [record]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,15 +1,17 @@
 //! Built-in tools for the Rig MCP server with declarative configuration and Rig patterns.
 
-use jsonschema::Validator;
+use crate::submissions::{SubmitGate, SubmitPolicy};
+use crate::validation::SchemaValidation;
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::{JsonSchema, schema_for};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
+use std::collections::BTreeMap;
 use std::fmt::Write as _;
 use std::marker::PhantomData;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use thiserror::Error;
 
 /// Error type for Rig tools.
@@ -26,6 +28,11 @@ pub enum ToolError {
 /// Type alias for the submission callback.
 type SubmitCallback<T> = Arc<dyn Fn(T) -> String + Send + Sync>;
 
+/// Returned to the agent for a submission left out under
+/// [`SubmitPolicy::FirstWins`].
+const IGNORED_SUBMISSION: &str =
+    "A submission was already accepted; this one was recorded but not used.";
+
 /// A declarative toolkit for JSON-based workflows using Rig's `schemars` pattern.
 ///
 /// Consolidates a schema (derived from `T`), an example, and success behavior into
@@ -38,6 +45,8 @@ where
     example: String,
     on_submit: Option<SubmitCallback<T>>,
     success_message: String,
+    submit_policy: SubmitPolicy,
+    validation: Option<Arc<SchemaValidation>>,
     submit_tool_name: String,
     submit_tool_description: String,
     validate_tool_name: String,
@@ -47,6 +56,30 @@ where
     _marker: PhantomData<T>,
 }
 
+// Manual impl: every field is cheap to clone and `T` itself need not be `Clone`.
+impl<T> Clone for JsonSchemaToolkit<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            schema: Arc::clone(&self.schema),
+            example: self.example.clone(),
+            on_submit: self.on_submit.clone(),
+            success_message: self.success_message.clone(),
+            submit_policy: self.submit_policy,
+            validation: self.validation.clone(),
+            submit_tool_name: self.submit_tool_name.clone(),
+            submit_tool_description: self.submit_tool_description.clone(),
+            validate_tool_name: self.validate_tool_name.clone(),
+            validate_tool_description: self.validate_tool_description.clone(),
+            example_tool_name: self.example_tool_name.clone(),
+            example_tool_description: self.example_tool_description.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
 impl<T> JsonSchemaToolkit<T>
 where
     T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
@@ -70,12 +103,15 @@ where
                 schema: self.schema.clone(),
                 on_submit: self.on_submit,
                 success_message,
+                gate: Arc::new(SubmitGate::new(self.submit_policy)),
+                validation: self.validation.clone(),
                 _marker: PhantomData,
             },
             ValidateJsonTool {
                 name: self.validate_tool_name,
                 description: self.validate_tool_description,
                 schema: self.schema.clone(),
+                validation: self.validation.unwrap_or_default(),
             },
             JsonExampleTool {
                 name: self.example_tool_name,
@@ -94,6 +130,8 @@ where
     example: Option<Value>,
     on_submit: Option<SubmitCallback<T>>,
     success_message: Option<String>,
+    submit_policy: SubmitPolicy,
+    validation: Option<SchemaValidation>,
     submit_tool_name: Option<String>,
     submit_tool_description: Option<String>,
     validate_tool_name: Option<String>,
@@ -112,6 +150,8 @@ where
             example: None,
             on_submit: None,
             success_message: None,
+            submit_policy: SubmitPolicy::LastWins,
+            validation: None,
             submit_tool_name: None,
             submit_tool_description: None,
             validate_tool_name: None,
@@ -154,6 +194,23 @@ where
         self
     }
 
+    /// Sets which submission becomes the result when the agent submits
+    /// more than once (default: [`SubmitPolicy::LastWins`]).
+    #[must_use]
+    pub const fn submit_policy(mut self, policy: SubmitPolicy) -> Self {
+        self.submit_policy = policy;
+        self
+    }
+
+    /// Validates with `validation`'s draft, `format` checks and `$ref`
+    /// policy instead of the `jsonschema` defaults. The submit tool then
+    /// also validates submissions against the schema before accepting them.
+    #[must_use]
+    pub fn schema_validation(mut self, validation: SchemaValidation) -> Self {
+        self.validation = Some(validation);
+        self
+    }
+
     /// Customizes the submit tool name and description.
     #[must_use]
     pub fn customize_submit(
@@ -190,7 +247,23 @@ where
         self
     }
 
-    /// Builds the toolkit.
+    /// Builds the toolkit, checking the tool names against
+    /// [`crate::naming`].
+    ///
+    /// # Errors
+    /// Returns a [`NameError`](crate::naming::NameError) for the first
+    /// customized tool name a CLI would reject or truncate.
+    pub fn try_build(self) -> Result<JsonSchemaToolkit<T>, crate::naming::NameError> {
+        check_tool_names([
+            &self.submit_tool_name,
+            &self.validate_tool_name,
+            &self.example_tool_name,
+        ])?;
+        Ok(self.build())
+    }
+
+    /// Builds the toolkit. Tool names are not checked; see
+    /// [`try_build`](Self::try_build).
     #[must_use]
     pub fn build(self) -> JsonSchemaToolkit<T> {
         JsonSchemaToolkit {
@@ -203,6 +276,8 @@ where
             success_message: self
                 .success_message
                 .unwrap_or_else(|| "Successfully submitted.".to_string()),
+            submit_policy: self.submit_policy,
+            validation: self.validation.map(Arc::new),
             submit_tool_name: self.submit_tool_name.unwrap_or_else(|| "submit".to_string()),
             submit_tool_description: self.submit_tool_description.unwrap_or_else(|| {
                 "Submit the structured data. This will perform final validation and processing."
@@ -239,6 +314,8 @@ where
     schema: Arc<Value>,
     on_submit: Option<SubmitCallback<T>>,
     success_message: Arc<String>,
+    gate: Arc<SubmitGate>,
+    validation: Option<Arc<SchemaValidation>>,
     _marker: PhantomData<T>,
 }
 
@@ -264,12 +341,31 @@ where
     }
 
     async fn call(&self, args: T) -> Result<String, ToolError> {
+        let submission = serde_json::to_value(&args).unwrap_or_default();
+        if let Some(validation) = &self.validation {
+            let validator = validation
+                .compile(&self.schema)
+                .map_err(ToolError::Validation)?;
+            let errors: Vec<_> = validator.iter_errors(&submission).collect();
+            if !errors.is_empty() {
+                SubmitGate::reject(&self.name, &submission);
+                let mut feedback = String::from("Submission validation failed:\n");
+                for error in &errors {
+                    let _ = writeln!(feedback, "  - At '{}': {}", error.instance_path, error);
+                }
+                return Err(ToolError::Validation(feedback));
+            }
+        }
+        if !self.gate.admit(&self.name, &submission)? {
+            return Ok(IGNORED_SUBMISSION.to_string());
+        }
+
         // Write validated result to the file path specified by RIG_MCP_RESULT_PATH.
         // This is the primary result channel — the parent process reads this file
         // after the stream ends, rather than relying on stream ToolCall events.
         if let (Ok(result_path), Ok(json_str)) = (
             std::env::var("RIG_MCP_RESULT_PATH"),
-            serde_json::to_string(&args),
+            serde_json::to_string(&submission),
         ) {
             let _ = std::fs::write(&result_path, json_str.as_bytes());
         }
@@ -293,6 +389,7 @@ pub struct ValidateJsonTool {
     pub(crate) name: String,
     pub(crate) description: String,
     schema: Arc<Value>,
+    validation: Arc<SchemaValidation>,
 }
 
 impl Tool for ValidateJsonTool {
@@ -323,8 +420,10 @@ impl Tool for ValidateJsonTool {
     }
 
     async fn call(&self, args: ValidateJsonArgs) -> Result<String, ToolError> {
-        let validator =
-            Validator::new(&self.schema).map_err(|e| ToolError::Validation(e.to_string()))?;
+        let validator = self
+            .validation
+            .compile(&self.schema)
+            .map_err(ToolError::Validation)?;
 
         let errors: Vec<_> = validator.iter_errors(&args.json).collect();
         if errors.is_empty() {
@@ -391,6 +490,864 @@ impl Tool for JsonExampleTool {
     }
 }
 
+// ---------------------------------------------------------------------------
+// List mode
+// ---------------------------------------------------------------------------
+
+/// Property of a list submission holding its items.
+pub const LIST_ITEMS_FIELD: &str = "items";
+
+/// Type alias for the list submission callback.
+type ListSubmitCallback<T> = Arc<dyn Fn(Vec<T>) -> String + Send + Sync>;
+
+impl<T> JsonSchemaToolkit<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Returns a builder for a toolkit collecting a list of `T` instead of a
+    /// single one.
+    #[must_use]
+    pub fn list() -> JsonListToolkitBuilder<T> {
+        JsonListToolkitBuilder::default()
+    }
+}
+
+/// Arguments of the list submit tool, and what it writes to the result file.
+///
+/// Deserialize the run's submit result as `ListSubmission<T>` to get the
+/// items, however they were submitted.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ListSubmission<T> {
+    /// The submitted items.
+    pub items: Vec<T>,
+}
+
+/// A toolkit collecting a list of `T`, between a minimum and a maximum
+/// number of items.
+///
+/// Besides the submit, validate and example tools, it provides an
+/// `add_item`/`finalize` pair: an agent extracting a large collection adds
+/// the items one call at a time and then finalizes the list, instead of
+/// producing one JSON document that may exceed its output limit. Either way
+/// the result file holds a [`ListSubmission`].
+///
+/// The items added are kept by the tool server, so every run should use a
+/// server of its own.
+pub struct JsonListToolkit<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    item_schema: Arc<Value>,
+    list_schema: Arc<Value>,
+    example: String,
+    on_submit: Option<ListSubmitCallback<T>>,
+    success_message: String,
+    submit_policy: SubmitPolicy,
+    min_items: usize,
+    max_items: Option<usize>,
+    submit_tool_name: String,
+    submit_tool_description: String,
+    validate_tool_name: String,
+    validate_tool_description: String,
+    example_tool_name: String,
+    example_tool_description: String,
+    add_item_tool_name: String,
+    add_item_tool_description: String,
+    finalize_tool_name: String,
+    finalize_tool_description: String,
+}
+
+/// The tools of a [`JsonListToolkit`].
+pub struct JsonListTools<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Submits the whole list at once.
+    pub submit: ListSubmitTool<T>,
+    /// Validates one item.
+    pub validate: ValidateJsonTool,
+    /// Returns an example item.
+    pub example: JsonExampleTool,
+    /// Adds one item to the list.
+    pub add_item: AddItemTool<T>,
+    /// Submits the items added so far.
+    pub finalize: FinalizeListTool<T>,
+}
+
+impl<T> JsonListToolkit<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// The schema of the submit tool's arguments, a [`ListSubmission`] of
+    /// items matching `T`'s schema.
+    #[must_use]
+    pub fn schema(&self) -> &Value {
+        &self.list_schema
+    }
+
+    /// Consumes the toolkit and returns its five tools.
+    #[must_use]
+    pub fn build_tools(self) -> JsonListTools<T> {
+        let list = Arc::new(ListState {
+            items: Mutex::new(Vec::new()),
+            min_items: self.min_items,
+            max_items: self.max_items,
+            on_submit: self.on_submit,
+            success_message: self.success_message,
+            gate: SubmitGate::new(self.submit_policy),
+        });
+
+        JsonListTools {
+            submit: ListSubmitTool {
+                name: self.submit_tool_name,
+                description: self.submit_tool_description,
+                schema: self.list_schema,
+                list: Arc::clone(&list),
+            },
+            validate: ValidateJsonTool {
+                name: self.validate_tool_name,
+                description: self.validate_tool_description,
+                schema: Arc::clone(&self.item_schema),
+                validation: Arc::default(),
+            },
+            example: JsonExampleTool {
+                name: self.example_tool_name,
+                description: self.example_tool_description,
+                example: Arc::new(self.example),
+            },
+            add_item: AddItemTool {
+                name: self.add_item_tool_name,
+                description: self.add_item_tool_description,
+                schema: self.item_schema,
+                finalize_tool_name: self.finalize_tool_name.clone(),
+                list: Arc::clone(&list),
+            },
+            finalize: FinalizeListTool {
+                name: self.finalize_tool_name,
+                description: self.finalize_tool_description,
+                list,
+            },
+        }
+    }
+}
+
+/// Builder for [`JsonListToolkit`].
+pub struct JsonListToolkitBuilder<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    example: Option<Value>,
+    on_submit: Option<ListSubmitCallback<T>>,
+    success_message: Option<String>,
+    submit_policy: SubmitPolicy,
+    min_items: usize,
+    max_items: Option<usize>,
+    submit_tool_name: Option<String>,
+    submit_tool_description: Option<String>,
+    validate_tool_name: Option<String>,
+    validate_tool_description: Option<String>,
+    example_tool_name: Option<String>,
+    example_tool_description: Option<String>,
+    add_item_tool_name: Option<String>,
+    add_item_tool_description: Option<String>,
+    finalize_tool_name: Option<String>,
+    finalize_tool_description: Option<String>,
+}
+
+impl<T> Default for JsonListToolkitBuilder<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            example: None,
+            on_submit: None,
+            success_message: None,
+            submit_policy: SubmitPolicy::LastWins,
+            min_items: 0,
+            max_items: None,
+            submit_tool_name: None,
+            submit_tool_description: None,
+            validate_tool_name: None,
+            validate_tool_description: None,
+            example_tool_name: None,
+            example_tool_description: None,
+            add_item_tool_name: None,
+            add_item_tool_description: None,
+            finalize_tool_name: None,
+            finalize_tool_description: None,
+        }
+    }
+}
+
+impl<T> JsonListToolkitBuilder<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Sets the example of one item.
+    #[must_use]
+    pub fn example(mut self, example: T) -> Self {
+        self.example = Some(serde_json::to_value(example).unwrap_or_else(|_| json!({})));
+        self
+    }
+
+    /// Sets the fewest items a list may have. Default: 0.
+    #[must_use]
+    pub const fn min_items(mut self, min_items: usize) -> Self {
+        self.min_items = min_items;
+        self
+    }
+
+    /// Sets the most items a list may have. Default: no limit.
+    ///
+    /// A maximum below [`min_items`](Self::min_items) is raised to it.
+    #[must_use]
+    pub const fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// Sets the message returned once the list is submitted.
+    ///
+    /// Note: If `on_submit` is provided, its return value will be used instead.
+    #[must_use]
+    pub fn on_success(mut self, message: impl Into<String>) -> Self {
+        self.success_message = Some(message.into());
+        self
+    }
+
+    /// Sets a callback executed when a list is submitted, whole or by
+    /// `finalize`. The callback receives the items and returns a success
+    /// message.
+    #[must_use]
+    pub fn on_submit<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Vec<T>) -> String + Send + Sync + 'static,
+    {
+        self.on_submit = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets which submission becomes the result when the agent submits
+    /// more than once (default: [`SubmitPolicy::LastWins`]).
+    #[must_use]
+    pub const fn submit_policy(mut self, policy: SubmitPolicy) -> Self {
+        self.submit_policy = policy;
+        self
+    }
+
+    /// Customizes the submit tool name and description.
+    #[must_use]
+    pub fn customize_submit(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.submit_tool_name = Some(name.into());
+        self.submit_tool_description = Some(description.into());
+        self
+    }
+
+    /// Customizes the validate tool name and description.
+    #[must_use]
+    pub fn customize_validate(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.validate_tool_name = Some(name.into());
+        self.validate_tool_description = Some(description.into());
+        self
+    }
+
+    /// Customizes the example tool name and description.
+    #[must_use]
+    pub fn customize_example(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.example_tool_name = Some(name.into());
+        self.example_tool_description = Some(description.into());
+        self
+    }
+
+    /// Customizes the add-item tool name and description.
+    #[must_use]
+    pub fn customize_add_item(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.add_item_tool_name = Some(name.into());
+        self.add_item_tool_description = Some(description.into());
+        self
+    }
+
+    /// Customizes the finalize tool name and description.
+    #[must_use]
+    pub fn customize_finalize(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.finalize_tool_name = Some(name.into());
+        self.finalize_tool_description = Some(description.into());
+        self
+    }
+
+    /// Builds the toolkit, checking the tool names against
+    /// [`crate::naming`].
+    ///
+    /// # Errors
+    /// Returns a [`NameError`](crate::naming::NameError) for the first
+    /// customized tool name a CLI would reject or truncate.
+    pub fn try_build(self) -> Result<JsonListToolkit<T>, crate::naming::NameError> {
+        check_tool_names([
+            &self.submit_tool_name,
+            &self.validate_tool_name,
+            &self.example_tool_name,
+            &self.add_item_tool_name,
+            &self.finalize_tool_name,
+        ])?;
+        Ok(self.build())
+    }
+
+    /// Builds the toolkit. Tool names are not checked; see
+    /// [`try_build`](Self::try_build).
+    #[must_use]
+    pub fn build(self) -> JsonListToolkit<T> {
+        let item_schema = json!(schema_for!(T));
+        let max_items = self.max_items.map(|max| max.max(self.min_items));
+        let list_schema = list_schema(&item_schema, self.min_items, max_items);
+        let add_item_tool_name = self
+            .add_item_tool_name
+            .unwrap_or_else(|| "add_item".to_string());
+        let finalize_tool_name = self
+            .finalize_tool_name
+            .unwrap_or_else(|| "finalize".to_string());
+
+        JsonListToolkit {
+            item_schema: Arc::new(item_schema),
+            list_schema: Arc::new(list_schema),
+            example: self
+                .example
+                .as_ref()
+                .map_or_else(|| "{}".to_string(), std::string::ToString::to_string),
+            on_submit: self.on_submit,
+            success_message: self
+                .success_message
+                .unwrap_or_else(|| "Successfully submitted.".to_string()),
+            submit_policy: self.submit_policy,
+            min_items: self.min_items,
+            max_items,
+            submit_tool_name: self.submit_tool_name.unwrap_or_else(|| "submit".to_string()),
+            submit_tool_description: self.submit_tool_description.unwrap_or_else(|| {
+                format!(
+                    "Submit the whole list of items at once. For long lists, add the items one \
+                     at a time with {add_item_tool_name} and then call {finalize_tool_name} instead."
+                )
+            }),
+            validate_tool_name: self
+                .validate_tool_name
+                .unwrap_or_else(|| "validate_json".to_string()),
+            validate_tool_description: self.validate_tool_description.unwrap_or_else(|| {
+                "Validate one list item against the configured schema. Use this to check an item's format before adding it."
+                    .to_string()
+            }),
+            example_tool_name: self
+                .example_tool_name
+                .unwrap_or_else(|| "json_example".to_string()),
+            example_tool_description: self.example_tool_description.unwrap_or_else(|| {
+                "Get an example of one item of the expected list.".to_string()
+            }),
+            add_item_tool_description: self.add_item_tool_description.unwrap_or_else(|| {
+                format!("Add one item to the list. Call {finalize_tool_name} once every item is added.")
+            }),
+            finalize_tool_description: self.finalize_tool_description.unwrap_or_else(|| {
+                format!("Submit the items added with {add_item_tool_name} as the final list.")
+            }),
+            add_item_tool_name,
+            finalize_tool_name,
+        }
+    }
+}
+
+/// Schema of a [`ListSubmission`] of items matching `item`.
+///
+/// MCP tool arguments must be objects, so the list is wrapped in one. The
+/// item's definitions move to the root, where its `$ref`s point.
+fn list_schema(item: &Value, min_items: usize, max_items: Option<usize>) -> Value {
+    let mut item = item.clone();
+    let mut root = serde_json::Map::new();
+    if let Some(object) = item.as_object_mut() {
+        for key in ["$schema", "$defs", "definitions"] {
+            if let Some(value) = object.remove(key) {
+                root.insert(key.to_string(), value);
+            }
+        }
+    }
+    let mut items = json!({ "type": "array", "items": item, "minItems": min_items });
+    if let Some(max_items) = max_items {
+        items["maxItems"] = json!(max_items);
+    }
+    root.insert("type".to_string(), json!("object"));
+    root.insert("properties".to_string(), json!({ LIST_ITEMS_FIELD: items }));
+    root.insert("required".to_string(), json!([LIST_ITEMS_FIELD]));
+    root.insert("additionalProperties".to_string(), json!(false));
+    Value::Object(root)
+}
+
+/// The list the tools of a [`JsonListToolkit`] share.
+struct ListState<T> {
+    /// Items added with the add-item tool and not yet finalized.
+    items: Mutex<Vec<T>>,
+    min_items: usize,
+    max_items: Option<usize>,
+    on_submit: Option<ListSubmitCallback<T>>,
+    success_message: String,
+    gate: SubmitGate,
+}
+
+impl<T: Serialize> ListState<T> {
+    /// The items added so far, recovering them if a holder panicked.
+    fn items(&self) -> std::sync::MutexGuard<'_, Vec<T>> {
+        self.items.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Describes the allowed number of items.
+    fn bounds(&self) -> String {
+        match self.max_items {
+            Some(max) if max == self.min_items => format!("exactly {max}"),
+            Some(max) => format!("between {} and {max}", self.min_items),
+            None => format!("at least {}", self.min_items),
+        }
+    }
+
+    /// Checks that `count` items make a complete list.
+    fn check_count(&self, count: usize) -> Result<(), ToolError> {
+        if count < self.min_items || self.max_items.is_some_and(|max| count > max) {
+            return Err(ToolError::Validation(format!(
+                "the list must have {} items, got {count}",
+                self.bounds()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Writes `items`, submitted through `tool`, to the result file and runs
+    /// the callback, if the submit policy admits them.
+    fn deliver(&self, tool: &str, items: Vec<T>) -> Result<String, ToolError> {
+        let submission = ListSubmission { items };
+        let value = serde_json::to_value(&submission).unwrap_or_default();
+        if !self.gate.admit(tool, &value)? {
+            return Ok(IGNORED_SUBMISSION.to_string());
+        }
+
+        // Write validated result to the file path specified by RIG_MCP_RESULT_PATH.
+        // This is the primary result channel — the parent process reads this file
+        // after the stream ends, rather than relying on stream ToolCall events.
+        if let (Ok(result_path), Ok(json_str)) = (
+            std::env::var("RIG_MCP_RESULT_PATH"),
+            serde_json::to_string(&value),
+        ) {
+            let _ = std::fs::write(&result_path, json_str.as_bytes());
+        }
+
+        self.on_submit.as_ref().map_or_else(
+            || Ok(self.success_message.clone()),
+            |callback| Ok(callback(submission.items)),
+        )
+    }
+}
+
+/// Tool for submitting a whole list at once.
+///
+/// Items added with the add-item tool and not yet finalized are discarded.
+pub struct ListSubmitTool<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    pub(crate) name: String,
+    pub(crate) description: String,
+    schema: Arc<Value>,
+    list: Arc<ListState<T>>,
+}
+
+impl<T> Tool for ListSubmitTool<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    const NAME: &'static str = "submit";
+    type Error = ToolError;
+    type Args = ListSubmission<T>;
+    type Output = String;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: (*self.schema).clone(),
+        }
+    }
+
+    async fn call(&self, args: ListSubmission<T>) -> Result<String, ToolError> {
+        self.list.check_count(args.items.len())?;
+        self.list.items().clear();
+        self.list.deliver(&self.name, args.items)
+    }
+}
+
+/// Tool for adding one item to the list.
+pub struct AddItemTool<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    pub(crate) name: String,
+    pub(crate) description: String,
+    schema: Arc<Value>,
+    finalize_tool_name: String,
+    list: Arc<ListState<T>>,
+}
+
+impl<T> Tool for AddItemTool<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    const NAME: &'static str = "add_item";
+    type Error = ToolError;
+    type Args = T;
+    type Output = String;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: (*self.schema).clone(),
+        }
+    }
+
+    async fn call(&self, args: T) -> Result<String, ToolError> {
+        let count = {
+            let mut items = self.list.items();
+            if self.list.max_items.is_some_and(|max| items.len() >= max) {
+                return Err(ToolError::Validation(format!(
+                    "the list already has {} items, the most allowed; call {} to submit it",
+                    items.len(),
+                    self.finalize_tool_name
+                )));
+            }
+            items.push(args);
+            items.len()
+        };
+        Ok(format!(
+            "Added item {count} (the list must have {} items). Call {} once every item is added.",
+            self.list.bounds(),
+            self.finalize_tool_name
+        ))
+    }
+}
+
+/// Arguments for the `FinalizeListTool`.
+#[derive(Deserialize, Serialize)]
+pub struct FinalizeListArgs {}
+
+/// Tool for submitting the items added so far as the list.
+///
+/// A list with too few items is kept, so more can be added before
+/// finalizing again.
+pub struct FinalizeListTool<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    pub(crate) name: String,
+    pub(crate) description: String,
+    list: Arc<ListState<T>>,
+}
+
+impl<T> Tool for FinalizeListTool<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    const NAME: &'static str = "finalize";
+    type Error = ToolError;
+    type Args = FinalizeListArgs;
+    type Output = String;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        }
+    }
+
+    async fn call(&self, _args: FinalizeListArgs) -> Result<String, ToolError> {
+        let items = {
+            let mut items = self.list.items();
+            self.list.check_count(items.len())?;
+            std::mem::take(&mut *items)
+        };
+        self.list.deliver(&self.name, items)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Versioned schemas
+// ---------------------------------------------------------------------------
+
+/// Field carrying the schema version of a [`VersionedSchema`] submission.
+pub const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// Type alias for a migration from an older schema version to `T`.
+type MigrationFn<T> = Arc<dyn Fn(Value) -> Result<T, ToolError> + Send + Sync>;
+
+/// An older schema version registered on a [`VersionedSchema`].
+struct Migration<T> {
+    schema: Value,
+    upgrade: MigrationFn<T>,
+}
+
+/// A schema for `T` that also accepts submissions in older shapes.
+///
+/// Each version is identified by a [`SCHEMA_VERSION_FIELD`] property. The
+/// combined [`schema`](Self::schema) accepts the latest version (with or
+/// without the field) and every version registered with
+/// [`migration`](Self::migration) (with the field). [`upgrade`](Self::upgrade)
+/// reads the field and runs the matching migration, so long-running pipelines
+/// keep accepting data submitted before `T` changed.
+///
+/// `T` and the older versions must be structs; recursive types are not
+/// supported because each version's schema is inlined.
+///
+/// # Example
+///
+/// ```
+/// use rig_cli_mcp::tools::VersionedSchema;
+/// use schemars::JsonSchema;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(JsonSchema, Deserialize)]
+/// struct PersonV1 {
+///     name: String,
+/// }
+///
+/// #[derive(JsonSchema, Serialize, Deserialize)]
+/// struct Person {
+///     first_name: String,
+///     last_name: String,
+/// }
+///
+/// let schema = VersionedSchema::<Person>::new(2).migration(1, |old: PersonV1| {
+///     let (first, last) = old.name.split_once(' ').unwrap_or((&old.name, ""));
+///     Person {
+///         first_name: first.to_string(),
+///         last_name: last.to_string(),
+///     }
+/// });
+///
+/// let person = schema
+///     .upgrade(serde_json::json!({ "schema_version": 1, "name": "Ada Lovelace" }))
+///     .unwrap();
+/// assert_eq!(person.last_name, "Lovelace");
+/// ```
+pub struct VersionedSchema<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    version: u32,
+    schema: Value,
+    migrations: BTreeMap<u32, Migration<T>>,
+}
+
+// Manual impl: `T` itself need not be `Clone`.
+impl<T> Clone for VersionedSchema<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version,
+            schema: self.schema.clone(),
+            migrations: self
+                .migrations
+                .iter()
+                .map(|(version, migration)| {
+                    (
+                        *version,
+                        Migration {
+                            schema: migration.schema.clone(),
+                            upgrade: Arc::clone(&migration.upgrade),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T> VersionedSchema<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Creates a schema whose latest version, `version`, is `T`.
+    #[must_use]
+    pub fn new(version: u32) -> Self {
+        Self {
+            version,
+            schema: version_schema::<T>(version, false),
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Accepts submissions of an older `version`, deserialized as `Old` and
+    /// upgraded to `T` by `migrate`.
+    ///
+    /// `version` must be older than the latest version; registering the same
+    /// version twice keeps the last migration. To chain upgrades through
+    /// intermediate versions, compose them inside `migrate`.
+    #[must_use]
+    pub fn migration<Old, F>(mut self, version: u32, migrate: F) -> Self
+    where
+        Old: JsonSchema + DeserializeOwned + 'static,
+        F: Fn(Old) -> T + Send + Sync + 'static,
+    {
+        let upgrade = move |value: Value| {
+            serde_json::from_value::<Old>(value)
+                .map(&migrate)
+                .map_err(|e| {
+                    ToolError::Validation(format!(
+                        "submission does not match schema version {version}: {e}"
+                    ))
+                })
+        };
+        self.migrations.insert(
+            version,
+            Migration {
+                schema: version_schema::<Old>(version, true),
+                upgrade: Arc::new(upgrade),
+            },
+        );
+        self
+    }
+
+    /// The latest schema version.
+    #[must_use]
+    pub const fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns a JSON Schema accepting the latest version and every older
+    /// registered version.
+    ///
+    /// Use it for [`ExtractionOrchestrator`](crate::extraction::ExtractionOrchestrator)
+    /// or a [`DynamicJsonSchemaToolkit`] (see [`toolkit`](Self::toolkit)).
+    #[must_use]
+    pub fn schema(&self) -> Value {
+        if self.migrations.is_empty() {
+            return self.schema.clone();
+        }
+        let versions: Vec<Value> = std::iter::once(self.schema.clone())
+            .chain(self.migrations.values().rev().map(|m| m.schema.clone()))
+            .collect();
+        json!({ "anyOf": versions })
+    }
+
+    /// Returns a dynamic toolkit builder validating against [`schema`](Self::schema).
+    ///
+    /// Pass submitted values through [`upgrade`](Self::upgrade) to get a `T`.
+    #[must_use]
+    pub fn toolkit(&self) -> DynamicJsonSchemaToolkitBuilder {
+        DynamicJsonSchemaToolkit::builder().schema(self.schema())
+    }
+
+    /// Deserializes a submission of any registered version and upgrades it to `T`.
+    ///
+    /// A submission without [`SCHEMA_VERSION_FIELD`] is read as the latest version.
+    ///
+    /// # Errors
+    /// Returns [`ToolError::Validation`] if the version is unknown or the
+    /// submission does not deserialize into that version's type.
+    pub fn upgrade(&self, mut value: Value) -> Result<T, ToolError> {
+        let version = match value
+            .as_object_mut()
+            .and_then(|object| object.remove(SCHEMA_VERSION_FIELD))
+        {
+            None => self.version,
+            Some(raw) => raw
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| {
+                    ToolError::Validation(format!(
+                        "`{SCHEMA_VERSION_FIELD}` must be a non-negative integer, got {raw}"
+                    ))
+                })?,
+        };
+
+        if version == self.version {
+            return serde_json::from_value(value).map_err(|e| {
+                ToolError::Validation(format!(
+                    "submission does not match schema version {version}: {e}"
+                ))
+            });
+        }
+        let migration = self.migrations.get(&version).ok_or_else(|| {
+            ToolError::Validation(format!(
+                "unsupported schema version {version} (latest is {})",
+                self.version
+            ))
+        })?;
+        (migration.upgrade)(value)
+    }
+}
+
+/// Inlined schema for `S` with a [`SCHEMA_VERSION_FIELD`] fixed to `version`.
+fn version_schema<S: JsonSchema>(version: u32, required: bool) -> Value {
+    let generator = schemars::generate::SchemaSettings::draft2020_12()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    let mut schema = generator.into_root_schema_for::<S>().to_value();
+
+    if let Some(object) = schema.as_object_mut() {
+        // Each version becomes a subschema of `anyOf`; only the root may declare a dialect.
+        object.remove("$schema");
+        if let Some(properties) = object
+            .entry("properties")
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+        {
+            properties.insert(
+                SCHEMA_VERSION_FIELD.to_string(),
+                json!({ "const": version }),
+            );
+        }
+        if required
+            && let Some(fields) = object
+                .entry("required")
+                .or_insert_with(|| json!([]))
+                .as_array_mut()
+        {
+            fields.push(json!(SCHEMA_VERSION_FIELD));
+        }
+    }
+    schema
+}
+
 // ---------------------------------------------------------------------------
 // Dynamic (runtime-schema) toolkit
 // ---------------------------------------------------------------------------
@@ -412,6 +1369,8 @@ pub struct DynamicJsonSchemaToolkit {
     example: String,
     on_submit: Option<DynamicSubmitCallback>,
     success_message: String,
+    submit_policy: SubmitPolicy,
+    validation: Arc<SchemaValidation>,
     submit_tool_name: String,
     submit_tool_description: String,
     validate_tool_name: String,
@@ -440,11 +1399,14 @@ impl DynamicJsonSchemaToolkit {
                 schema: self.schema.clone(),
                 on_submit: self.on_submit,
                 success_message,
+                gate: Arc::new(SubmitGate::new(self.submit_policy)),
+                validation: Arc::clone(&self.validation),
             },
             ValidateJsonTool {
                 name: self.validate_tool_name,
                 description: self.validate_tool_description,
                 schema: self.schema.clone(),
+                validation: self.validation,
             },
             JsonExampleTool {
                 name: self.example_tool_name,
@@ -462,6 +1424,8 @@ pub struct DynamicJsonSchemaToolkitBuilder {
     example: Option<Value>,
     on_submit: Option<DynamicSubmitCallback>,
     success_message: Option<String>,
+    submit_policy: SubmitPolicy,
+    validation: SchemaValidation,
     submit_tool_name: Option<String>,
     submit_tool_description: Option<String>,
     validate_tool_name: Option<String>,
@@ -506,6 +1470,22 @@ impl DynamicJsonSchemaToolkitBuilder {
         self
     }
 
+    /// Sets which submission becomes the result when the agent submits
+    /// more than once (default: [`SubmitPolicy::LastWins`]).
+    #[must_use]
+    pub const fn submit_policy(mut self, policy: SubmitPolicy) -> Self {
+        self.submit_policy = policy;
+        self
+    }
+
+    /// Validates with `validation`'s draft, `format` checks and `$ref`
+    /// policy instead of the `jsonschema` defaults.
+    #[must_use]
+    pub fn schema_validation(mut self, validation: SchemaValidation) -> Self {
+        self.validation = validation;
+        self
+    }
+
     /// Customizes the submit tool name and description.
     #[must_use]
     pub fn customize_submit(
@@ -545,11 +1525,18 @@ impl DynamicJsonSchemaToolkitBuilder {
     /// Builds the toolkit.
     ///
     /// # Errors
-    /// Returns an error if `schema` was not provided.
+    /// Returns an error if `schema` was not provided, or a customized tool
+    /// name breaks the rules in [`crate::naming`].
     pub fn build(self) -> Result<DynamicJsonSchemaToolkit, String> {
         let schema = self
             .schema
             .ok_or("schema is required for DynamicJsonSchemaToolkit")?;
+        check_tool_names([
+            &self.submit_tool_name,
+            &self.validate_tool_name,
+            &self.example_tool_name,
+        ])
+        .map_err(|e| e.to_string())?;
         Ok(DynamicJsonSchemaToolkit {
             schema: Arc::new(schema),
             example: self
@@ -561,6 +1548,8 @@ impl DynamicJsonSchemaToolkitBuilder {
             success_message: self
                 .success_message
                 .unwrap_or_else(|| "Successfully submitted.".to_string()),
+            submit_policy: self.submit_policy,
+            validation: Arc::new(self.validation),
             submit_tool_name: self.submit_tool_name.unwrap_or_else(|| "submit".to_string()),
             submit_tool_description: self.submit_tool_description.unwrap_or_else(|| {
                 "Submit the structured data. This will perform final validation and processing."
@@ -583,6 +1572,16 @@ impl DynamicJsonSchemaToolkitBuilder {
     }
 }
 
+/// Checks the customized names of a toolkit's tools.
+fn check_tool_names<'a>(
+    names: impl IntoIterator<Item = &'a Option<String>>,
+) -> Result<(), crate::naming::NameError> {
+    names
+        .into_iter()
+        .flatten()
+        .try_for_each(|name| crate::naming::check_tool_name(name))
+}
+
 /// Tool for submitting work in JSON format with runtime schema validation.
 ///
 /// Unlike [`SubmitTool`] which deserializes into a concrete type `T`,
@@ -594,6 +1593,8 @@ pub struct DynamicSubmitTool {
     schema: Arc<Value>,
     on_submit: Option<DynamicSubmitCallback>,
     success_message: Arc<String>,
+    gate: Arc<SubmitGate>,
+    validation: Arc<SchemaValidation>,
 }
 
 impl Tool for DynamicSubmitTool {
@@ -616,17 +1617,23 @@ impl Tool for DynamicSubmitTool {
 
     async fn call(&self, args: Value) -> Result<String, ToolError> {
         // Validate against the runtime schema before accepting
-        let validator =
-            Validator::new(&self.schema).map_err(|e| ToolError::Validation(e.to_string()))?;
+        let validator = self
+            .validation
+            .compile(&self.schema)
+            .map_err(ToolError::Validation)?;
 
         let errors: Vec<_> = validator.iter_errors(&args).collect();
         if !errors.is_empty() {
+            SubmitGate::reject(&self.name, &args);
             let mut feedback = String::from("Submission validation failed:\n");
             for error in &errors {
                 let _ = writeln!(feedback, "  - At '{}': {}", error.instance_path, error);
             }
             return Err(ToolError::Validation(feedback));
         }
+        if !self.gate.admit(&self.name, &args)? {
+            return Ok(IGNORED_SUBMISSION.to_string());
+        }
 
         // Write validated result to the file path specified by RIG_MCP_RESULT_PATH.
         // This is the primary result channel — the parent process reads this file
@@ -0,0 +1,248 @@
+//! How submissions are validated against JSON schemas.
+//!
+//! By default schemas are compiled with the `jsonschema` defaults: the draft
+//! named by `$schema` (2020-12 when absent), `format` checked only where the
+//! draft asserts it, and external `$ref`s fetched over HTTP or from files.
+//! [`SchemaValidation`] changes these so that a domain schema validates the
+//! same way here as in the service that defined it:
+//!
+//! ```
+//! use rig_cli_mcp::validation::{ExternalRefs, SchemaDraft, SchemaValidation};
+//! use serde_json::json;
+//!
+//! let validation = SchemaValidation::new()
+//!     .draft(SchemaDraft::Draft7)
+//!     .format("sku", |value| value.starts_with("SKU-"))
+//!     .external_refs(ExternalRefs::Registered)
+//!     .resource("https://example.com/money.json", json!({"type": "number"}));
+//!
+//! let validator = validation
+//!     .compile(&json!({
+//!         "type": "object",
+//!         "properties": {
+//!             "sku": {"type": "string", "format": "sku"},
+//!             "price": {"$ref": "https://example.com/money.json"}
+//!         }
+//!     }))
+//!     .unwrap();
+//! assert!(validator.is_valid(&json!({"sku": "SKU-1", "price": 3})));
+//! assert!(!validator.is_valid(&json!({"sku": "1"})));
+//! ```
+//!
+//! [`JsonSchemaToolkitBuilder::schema_validation`](crate::tools::JsonSchemaToolkitBuilder::schema_validation)
+//! and [`ExtractionOrchestratorBuilder::schema_validation`](crate::extraction::ExtractionOrchestratorBuilder::schema_validation)
+//! apply it to the toolkit's tools and the orchestrator's retry loop.
+
+use jsonschema::{Draft, Resource, Retrieve, Uri, Validator};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Checks a string against a custom `format`.
+type FormatCheck = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// JSON Schema drafts a schema can be compiled as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaDraft {
+    /// Draft 4.
+    Draft4,
+    /// Draft 6.
+    Draft6,
+    /// Draft 7.
+    Draft7,
+    /// Draft 2019-09.
+    Draft201909,
+    /// Draft 2020-12.
+    Draft202012,
+}
+
+impl From<SchemaDraft> for Draft {
+    fn from(draft: SchemaDraft) -> Self {
+        match draft {
+            SchemaDraft::Draft4 => Self::Draft4,
+            SchemaDraft::Draft6 => Self::Draft6,
+            SchemaDraft::Draft7 => Self::Draft7,
+            SchemaDraft::Draft201909 => Self::Draft201909,
+            SchemaDraft::Draft202012 => Self::Draft202012,
+        }
+    }
+}
+
+/// How `$ref`s to documents outside the schema are resolved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExternalRefs {
+    /// Fetch `http(s)://` and `file://` references.
+    #[default]
+    Fetch,
+    /// Resolve only documents registered with
+    /// [`SchemaValidation::resource`]; a schema referring to any other
+    /// document does not compile.
+    Registered,
+}
+
+/// Options for compiling JSON schemas; see the [module docs](self).
+#[derive(Clone, Default)]
+pub struct SchemaValidation {
+    draft: Option<SchemaDraft>,
+    formats: BTreeMap<String, FormatCheck>,
+    validate_formats: Option<bool>,
+    external_refs: ExternalRefs,
+    resources: BTreeMap<String, Value>,
+}
+
+impl std::fmt::Debug for SchemaValidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SchemaValidation")
+            .field("draft", &self.draft)
+            .field("formats", &self.formats.keys().collect::<Vec<_>>())
+            .field("validate_formats", &self.validate_formats)
+            .field("external_refs", &self.external_refs)
+            .field("resources", &self.resources.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl SchemaValidation {
+    /// The `jsonschema` defaults.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            draft: None,
+            formats: BTreeMap::new(),
+            validate_formats: None,
+            external_refs: ExternalRefs::Fetch,
+            resources: BTreeMap::new(),
+        }
+    }
+
+    /// Compiles schemas as `draft`, whatever their `$schema` says.
+    #[must_use]
+    pub const fn draft(mut self, draft: SchemaDraft) -> Self {
+        self.draft = Some(draft);
+        self
+    }
+
+    /// Checks strings with `"format": name` using `check`.
+    ///
+    /// Registering a format turns on format validation, including the
+    /// built-in formats such as `email`, unless
+    /// [`validate_formats`](Self::validate_formats) turns it off.
+    #[must_use]
+    pub fn format(
+        mut self,
+        name: impl Into<String>,
+        check: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.formats.insert(name.into(), Arc::new(check));
+        self
+    }
+
+    /// Whether `format` is checked. Default: only where the draft asserts
+    /// it, or always once a custom [`format`](Self::format) is registered.
+    #[must_use]
+    pub const fn validate_formats(mut self, validate: bool) -> Self {
+        self.validate_formats = Some(validate);
+        self
+    }
+
+    /// Sets how external `$ref`s are resolved. Default:
+    /// [`ExternalRefs::Fetch`].
+    #[must_use]
+    pub const fn external_refs(mut self, policy: ExternalRefs) -> Self {
+        self.external_refs = policy;
+        self
+    }
+
+    /// Makes `schema` the document `$ref`s to `uri` resolve to, under
+    /// either [`ExternalRefs`] policy.
+    #[must_use]
+    pub fn resource(mut self, uri: impl Into<String>, schema: Value) -> Self {
+        self.resources.insert(uri.into(), schema);
+        self
+    }
+
+    /// Compiles `schema` with these options.
+    ///
+    /// # Errors
+    /// Returns the compilation error if `schema` or a registered resource
+    /// is not a valid schema, or a reference cannot be resolved.
+    pub fn compile(&self, schema: &Value) -> Result<Validator, String> {
+        let mut options = jsonschema::options();
+        if let Some(draft) = self.draft {
+            options.with_draft(draft.into());
+        }
+        for (name, check) in &self.formats {
+            let check = Arc::clone(check);
+            options.with_format(name.clone(), move |value: &str| check(value));
+        }
+        if let Some(validate) = self
+            .validate_formats
+            .or_else(|| (!self.formats.is_empty()).then_some(true))
+        {
+            options.should_validate_formats(validate);
+        }
+        if self.external_refs == ExternalRefs::Registered {
+            options.with_retriever(RegisteredOnly);
+        }
+        for (uri, contents) in &self.resources {
+            let resource = Resource::from_contents(contents.clone())
+                .map_err(|e| format!("resource '{uri}': {e}"))?;
+            options.with_resource(uri.clone(), resource);
+        }
+        options.build(schema).map_err(|e| e.to_string())
+    }
+}
+
+/// Refuses every document that was not registered up front.
+struct RegisteredOnly;
+
+impl Retrieve for RegisteredOnly {
+    fn retrieve(&self, uri: &Uri<&str>) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        Err(format!(
+            "external reference '{}' is not a registered resource",
+            uri.as_str()
+        )
+        .into())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_options_change_how_schemas_validate() {
+        // Draft 7 ignores `prefixItems`; 2020-12 checks it.
+        let tuple = json!({"type": "array", "prefixItems": [{"type": "string"}]});
+        let draft7 = SchemaValidation::new().draft(SchemaDraft::Draft7);
+        assert!(draft7.compile(&tuple).unwrap().is_valid(&json!([1])));
+        assert!(
+            !SchemaValidation::new()
+                .compile(&tuple)
+                .unwrap()
+                .is_valid(&json!([1]))
+        );
+
+        let iban = json!({"type": "string", "format": "iban"});
+        assert!(
+            SchemaValidation::new()
+                .compile(&iban)
+                .unwrap()
+                .is_valid(&json!("x"))
+        );
+        let checked = SchemaValidation::new().format("iban", |value| value.starts_with("DE"));
+        assert!(!checked.compile(&iban).unwrap().is_valid(&json!("x")));
+        assert!(checked.compile(&iban).unwrap().is_valid(&json!("DE89")));
+        let unchecked = checked.validate_formats(false);
+        assert!(unchecked.compile(&iban).unwrap().is_valid(&json!("x")));
+
+        let external = json!({"$ref": "https://example.com/money.json"});
+        let registered = SchemaValidation::new().external_refs(ExternalRefs::Registered);
+        let error = registered.compile(&external).unwrap_err();
+        assert!(error.contains("not a registered resource"), "{error}");
+        let money = registered.resource("https://example.com/money.json", json!({"minimum": 0}));
+        assert!(!money.compile(&external).unwrap().is_valid(&json!(-1)));
+    }
+}
@@ -0,0 +1,173 @@
+//! Liveness watchdog for MCP sessions served over stdio.
+//!
+//! When a session of [`RigMcpHandler::serve_stdio`](crate::server::RigMcpHandler::serve_stdio)
+//! ends, the watchdog logs how it ended as a structured `tracing` event under
+//! the `rig` target, with the server name, transport, reason and restart
+//! count as fields. A session that failed, rather than one the client closed,
+//! is served again under the handler's [`RestartPolicy`]. Restarts reuse the
+//! same handler, so tools, the audit log and [`stats`](crate::stats) carry
+//! over into the new session.
+//!
+//! ```ignore
+//! RigMcpHandler::builder()
+//!     .toolset(toolset)
+//!     .restart_policy(RestartPolicy {
+//!         max_restarts: 5,
+//!         ..RestartPolicy::default()
+//!     })
+//!     .build()
+//!     .await?
+//!     .serve_stdio()
+//!     .await?;
+//! ```
+
+use std::time::Duration;
+
+/// When a failed session is served again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Restarts allowed over the server's lifetime. Default: 0, so the first
+    /// failure ends serving.
+    pub max_restarts: u32,
+    /// Pause before each restart. Default: one second.
+    pub backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 0,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// How a served session ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SessionEnd {
+    /// The client closed the transport.
+    Closed,
+    /// The session was cancelled by the server.
+    Cancelled,
+    /// The session failed to initialize or its task died.
+    Failed(String),
+}
+
+impl SessionEnd {
+    /// Short name of the reason, logged as the event's `reason` field.
+    const fn reason(&self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Cancelled => "cancelled",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+/// Tracks the sessions of one server and decides whether to restart it.
+pub(crate) struct Watchdog<'a> {
+    server: &'a str,
+    transport: &'static str,
+    policy: RestartPolicy,
+    restarts: u32,
+}
+
+impl<'a> Watchdog<'a> {
+    pub(crate) const fn new(
+        server: &'a str,
+        transport: &'static str,
+        policy: RestartPolicy,
+    ) -> Self {
+        Self {
+            server,
+            transport,
+            policy,
+            restarts: 0,
+        }
+    }
+
+    /// Logs how a session ended and returns the pause before serving
+    /// another, or `None` when serving should stop.
+    pub(crate) fn on_session_end(&mut self, end: &SessionEnd) -> Option<Duration> {
+        let SessionEnd::Failed(error) = end else {
+            tracing::info!(
+                target: "rig",
+                event = "mcp_session_ended",
+                server = self.server,
+                transport = self.transport,
+                reason = end.reason(),
+                restarts = self.restarts,
+                "MCP session ended"
+            );
+            return None;
+        };
+        if self.restarts >= self.policy.max_restarts {
+            tracing::error!(
+                target: "rig",
+                event = "mcp_session_failed",
+                server = self.server,
+                transport = self.transport,
+                reason = end.reason(),
+                error = %error,
+                restarts = self.restarts,
+                "MCP session failed; not restarting"
+            );
+            return None;
+        }
+        self.restarts += 1;
+        tracing::warn!(
+            target: "rig",
+            event = "mcp_session_restarting",
+            server = self.server,
+            transport = self.transport,
+            reason = end.reason(),
+            error = %error,
+            restarts = self.restarts,
+            backoff_ms = u64::try_from(self.policy.backoff.as_millis()).unwrap_or(u64::MAX),
+            "MCP session failed; restarting"
+        );
+        Some(self.policy.backoff)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy_never_restarts() {
+        let mut watchdog = Watchdog::new("tools", "stdio", RestartPolicy::default());
+        assert_eq!(
+            watchdog.on_session_end(&SessionEnd::Failed("boom".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_failures_restart_until_the_budget_runs_out() {
+        let policy = RestartPolicy {
+            max_restarts: 2,
+            backoff: Duration::from_millis(10),
+        };
+        let mut watchdog = Watchdog::new("tools", "stdio", policy);
+        let failed = SessionEnd::Failed("task panicked".to_string());
+
+        assert_eq!(watchdog.on_session_end(&failed), Some(policy.backoff));
+        assert_eq!(watchdog.on_session_end(&failed), Some(policy.backoff));
+        assert_eq!(watchdog.on_session_end(&failed), None);
+        assert_eq!(watchdog.restarts, 2);
+    }
+
+    #[test]
+    fn test_closed_and_cancelled_sessions_are_not_restarted() {
+        let policy = RestartPolicy {
+            max_restarts: 3,
+            ..RestartPolicy::default()
+        };
+        let mut watchdog = Watchdog::new("tools", "stdio", policy);
+        assert_eq!(watchdog.on_session_end(&SessionEnd::Closed), None);
+        assert_eq!(watchdog.on_session_end(&SessionEnd::Cancelled), None);
+        assert_eq!(watchdog.restarts, 0);
+    }
+}
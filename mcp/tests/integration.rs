@@ -51,6 +51,123 @@ async fn test_mcp_config_formats() {
         opencode["mcpServers"]["test-server"]["command"],
         "/path/to/exe"
     );
+
+    // Verify all three as one JSON object
+    let all = config.to_client_configs_json();
+    assert_eq!(all["claude"], claude);
+    assert_eq!(all["opencode"], opencode);
+    assert_eq!(
+        all["codex"]["mcp_servers"]["test-server"]["env"]["API_KEY"],
+        "secret"
+    );
+}
+
+#[test]
+fn test_mcp_config_escapes_adversarial_paths() {
+    let command = r#"C:\Program Files\My "Tools"\rig-mcp.exe"#;
+    let result_path = r"C:\Users\o'brien\AppData\Local\Temp\result [1].json";
+    let mut env = HashMap::new();
+    env.insert("RIG_MCP_RESULT_PATH".to_string(), result_path.to_string());
+    env.insert("ODD KEY.NAME".to_string(), "line\nbreak".to_string());
+    let config = McpConfig {
+        name: "rig_mcp".to_string(),
+        command: command.to_string(),
+        args: vec![r"--dir=D:\a b\".to_string(), "\"quoted\"".to_string()],
+        env,
+    };
+
+    // Codex TOML parses back to the same values
+    let codex: toml::Table = config.to_codex_toml().parse().unwrap();
+    let server = &codex["mcp_servers"]["rig_mcp"];
+    assert_eq!(server["command"].as_str(), Some(command));
+    assert_eq!(server["args"][0].as_str(), Some(r"--dir=D:\a b\"));
+    assert_eq!(server["args"][1].as_str(), Some("\"quoted\""));
+    assert_eq!(
+        server["env"]["RIG_MCP_RESULT_PATH"].as_str(),
+        Some(result_path)
+    );
+    assert_eq!(server["env"]["ODD KEY.NAME"].as_str(), Some("line\nbreak"));
+
+    // So does each -c override value
+    let overrides: HashMap<String, String> = config.to_codex_overrides().into_iter().collect();
+    let value = |key: &str| -> toml::Value {
+        let doc: toml::Table = format!("v = {}", overrides[key]).parse().unwrap();
+        doc["v"].clone()
+    };
+    assert_eq!(value("mcp_servers.rig_mcp.command").as_str(), Some(command));
+    assert_eq!(value("mcp_servers.rig_mcp.args"), server["args"]);
+    assert_eq!(value("mcp_servers.rig_mcp.env"), server["env"]);
+
+    // Claude and OpenCode JSON survive a serialize/parse round trip
+    for json in [config.to_claude_json(), config.to_opencode_json()] {
+        let text = serde_json::to_string(&json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+        let server = &parsed["mcpServers"]["rig_mcp"];
+        assert_eq!(server["command"], command);
+        assert_eq!(server["env"]["RIG_MCP_RESULT_PATH"], result_path);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_config_path_string_rejects_non_unicode() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::path::Path::new(std::ffi::OsStr::from_bytes(b"/tmp/r\xffig"));
+    let err = rig_cli_mcp::server::config_path_string(path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        rig_cli_mcp::server::config_path_string(std::path::Path::new(r"C:\a b\x.exe")).unwrap(),
+        r"C:\a b\x.exe"
+    );
+}
+
+#[test]
+fn test_remote_mcp_config_formats() {
+    let config = RemoteMcpConfig {
+        name: "central".to_string(),
+        url: "http://tools.internal:8080/mcp".to_string(),
+        bearer_token: None,
+    };
+
+    let claude = config.to_claude_json();
+    assert_eq!(claude["mcpServers"]["central"]["type"], "http");
+    assert_eq!(
+        claude["mcpServers"]["central"]["url"],
+        "http://tools.internal:8080/mcp"
+    );
+
+    assert_eq!(
+        config.to_codex_toml(),
+        "[mcp_servers.central]\nurl = \"http://tools.internal:8080/mcp\"\n"
+    );
+
+    let opencode = config.to_opencode_json();
+    assert_eq!(opencode["mcp"]["central"]["type"], "remote");
+    assert_eq!(
+        opencode["mcp"]["central"]["url"],
+        "http://tools.internal:8080/mcp"
+    );
+
+    assert!(claude["mcpServers"]["central"].get("headers").is_none());
+
+    let config = RemoteMcpConfig {
+        bearer_token: Some("s3cret".to_string()),
+        ..config
+    };
+    assert_eq!(
+        config.to_claude_json()["mcpServers"]["central"]["headers"]["Authorization"],
+        "Bearer s3cret"
+    );
+    assert!(
+        config
+            .to_codex_toml()
+            .contains("[mcp_servers.central.http_headers]\nAuthorization = \"Bearer s3cret\"")
+    );
+    assert_eq!(
+        config.to_opencode_json()["mcp"]["central"]["headers"]["Authorization"],
+        "Bearer s3cret"
+    );
 }
 
 #[tokio::test]
@@ -95,3 +212,191 @@ async fn test_toolkit_validation() {
     assert!(err_result.contains("validation failed"));
     assert!(err_result.contains("value"));
 }
+
+#[tokio::test]
+async fn test_toolkit_checks_custom_formats() {
+    use rig::tool::Tool;
+    use rig_cli_mcp::tools::ValidateJsonArgs;
+
+    let (submit, validate, _) = DynamicJsonSchemaToolkit::builder()
+        .schema(json!({
+            "type": "object",
+            "properties": {"sku": {"type": "string", "format": "sku"}}
+        }))
+        .schema_validation(SchemaValidation::new().format("sku", |v| v.starts_with("SKU-")))
+        .build()
+        .unwrap()
+        .build_tools();
+
+    let feedback = validate
+        .call(ValidateJsonArgs {
+            json: json!({"sku": "42"}),
+        })
+        .await
+        .unwrap();
+    assert!(feedback.contains("validation failed"), "{feedback}");
+    assert!(submit.call(json!({"sku": "42"})).await.is_err());
+    assert!(submit.call(json!({"sku": "SKU-42"})).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_stats_tool_is_listed_when_enabled() {
+    let (submit, validate, example) = JsonSchemaToolkit::<TestModel>::builder()
+        .build()
+        .build_tools();
+    let mut toolset = rig::tool::ToolSet::default();
+    toolset.add_tool(submit);
+    toolset.add_tool(validate);
+    toolset.add_tool(example);
+
+    let handler = RigMcpHandler::builder()
+        .toolset(toolset)
+        .stats_tool(true)
+        .build()
+        .await
+        .unwrap();
+
+    assert!(
+        handler
+            .tool_definitions
+            .iter()
+            .any(|tool| tool.name == rig_cli_mcp::stats::STATS_TOOL)
+    );
+    let stats = handler.stats();
+    assert_eq!(stats.server, "rig-mcp-server");
+    assert_eq!(stats.tools.len(), 0);
+}
+
+#[tokio::test]
+async fn test_fetch_more_tool_is_listed_when_paginating() {
+    let (submit, validate, example) = JsonSchemaToolkit::<TestModel>::builder()
+        .build()
+        .build_tools();
+    let mut toolset = rig::tool::ToolSet::default();
+    toolset.add_tool(submit);
+    toolset.add_tool(validate);
+    toolset.add_tool(example);
+
+    let handler = RigMcpHandler::builder()
+        .toolset(toolset)
+        .paginate_results(16 * 1024)
+        .build()
+        .await
+        .unwrap();
+
+    let fetch_more = handler
+        .tool_definitions
+        .iter()
+        .find(|tool| tool.name == rig_cli_mcp::pagination::FETCH_MORE_TOOL)
+        .unwrap();
+    assert_eq!(fetch_more.input_schema["required"], json!(["cursor"]));
+}
+
+#[derive(JsonSchema, Deserialize)]
+struct TestModelV1 {
+    id: String,
+}
+
+#[test]
+fn test_versioned_schema_upgrades_submissions() {
+    let versioned =
+        VersionedSchema::<TestModel>::new(2).migration(1, |old: TestModelV1| TestModel {
+            id: old.id,
+            value: 0,
+        });
+
+    let schema = versioned.schema();
+    let versions = schema["anyOf"].as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0]["properties"]["schema_version"]["const"], 2);
+    assert_eq!(versions[1]["properties"]["schema_version"]["const"], 1);
+    assert!(
+        versions[1]["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("schema_version"))
+    );
+
+    let old = versioned
+        .upgrade(json!({ "schema_version": 1, "id": "a" }))
+        .unwrap();
+    assert_eq!(
+        old,
+        TestModel {
+            id: "a".to_string(),
+            value: 0
+        }
+    );
+
+    // Without a version field the submission is read as the latest version.
+    let latest = versioned.upgrade(json!({ "id": "b", "value": 7 })).unwrap();
+    assert_eq!(latest.value, 7);
+
+    assert!(
+        versioned
+            .upgrade(json!({ "schema_version": 9, "id": "c" }))
+            .is_err()
+    );
+    assert!(versioned.upgrade(json!({ "schema_version": 1 })).is_err());
+}
+
+#[tokio::test]
+async fn test_list_toolkit_collects_items_incrementally() {
+    use rig::tool::Tool;
+    use rig_cli_mcp::tools::{FinalizeListArgs, ListSubmission};
+
+    let toolkit = JsonSchemaToolkit::<TestModel>::list()
+        .min_items(2)
+        .max_items(2)
+        .on_submit(|items| format!("Handled {} items", items.len()))
+        .build();
+    let schema = toolkit.schema().clone();
+    assert_eq!(schema["properties"]["items"]["minItems"], 2);
+    assert_eq!(schema["properties"]["items"]["maxItems"], 2);
+    assert_eq!(schema["required"], json!(["items"]));
+    let tools = toolkit.build_tools();
+
+    let item = |id: &str| TestModel {
+        id: id.to_string(),
+        value: 1,
+    };
+    let added = tools.add_item.call(item("a")).await.unwrap();
+    assert!(added.contains("Added item 1"), "{added}");
+    // Too few items: the list is kept so more can be added.
+    assert!(tools.finalize.call(FinalizeListArgs {}).await.is_err());
+    tools.add_item.call(item("b")).await.unwrap();
+    assert!(tools.add_item.call(item("c")).await.is_err());
+    let done = tools.finalize.call(FinalizeListArgs {}).await.unwrap();
+    assert_eq!(done, "Handled 2 items");
+
+    let whole = ListSubmission {
+        items: vec![item("d")],
+    };
+    assert!(tools.submit.call(whole).await.is_err());
+}
+
+#[tokio::test]
+async fn test_first_submission_wins_under_first_wins_policy() {
+    use rig::tool::Tool;
+    use std::sync::{Arc, Mutex};
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&received);
+    let (submit, _, _) = JsonSchemaToolkit::<TestModel>::builder()
+        .submit_policy(SubmitPolicy::FirstWins)
+        .on_submit(move |model| {
+            sink.lock().unwrap().push(model.id);
+            "Handled".to_string()
+        })
+        .build()
+        .build_tools();
+
+    let model = |id: &str| TestModel {
+        id: id.to_string(),
+        value: 1,
+    };
+    assert_eq!(submit.call(model("first")).await.unwrap(), "Handled");
+    let second = submit.call(model("second")).await.unwrap();
+    assert!(second.contains("not used"), "{second}");
+    assert_eq!(*received.lock().unwrap(), ["first"]);
+}
@@ -0,0 +1,131 @@
+//! Login state detection for the `OpenCode` CLI.
+//!
+//! `OpenCode` has no status subcommand with stable output, so the stored
+//! credentials file (`opencode auth login` writes one entry per provider) is
+//! inspected directly, falling back to well-known provider API key variables.
+
+use crate::types::{AuthState, AuthStatus};
+use std::path::PathBuf;
+
+/// Command that starts the interactive `OpenCode` login flow.
+pub const LOGIN_COMMAND: &str = "opencode auth login";
+
+/// Provider API key variables `OpenCode` picks up without a stored login.
+const API_KEY_ENV_VARS: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "OPENAI_API_KEY",
+    "OPENROUTER_API_KEY",
+    "GEMINI_API_KEY",
+];
+
+/// Lowercase fragments the CLI prints when a run fails for lack of credentials.
+const AUTH_FAILURE_MARKERS: &[&str] = &[
+    "providerautherror",
+    "no credentials",
+    "invalid api key",
+    "401 unauthorized",
+    "run `opencode auth login`",
+];
+
+/// Returns the path of the `OpenCode` credentials file.
+///
+/// Resolves to `$XDG_DATA_HOME/opencode/auth.json`, or
+/// `~/.local/share/opencode/auth.json` when `XDG_DATA_HOME` is unset.
+#[must_use]
+pub fn credentials_path() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")))?;
+    Some(data_dir.join("opencode").join("auth.json"))
+}
+
+/// Reports whether `OpenCode` has credentials for at least one provider.
+///
+/// Providers with stored credentials are listed in [`AuthStatus::method`].
+/// A missing credentials file counts as logged out unless a provider API key
+/// variable is set; an unreadable or malformed file reports
+/// [`AuthState::Unknown`].
+pub async fn auth_status() -> AuthStatus {
+    let stored = match credentials_path() {
+        Some(path) => match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => parse_credentials(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                status(AuthState::LoggedOut, None)
+            }
+            Err(_) => status(AuthState::Unknown, None),
+        },
+        None => status(AuthState::Unknown, None),
+    };
+
+    if stored.state == AuthState::LoggedIn {
+        return stored;
+    }
+
+    API_KEY_ENV_VARS
+        .iter()
+        .find(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()))
+        .map_or(stored, |var| {
+            status(AuthState::LoggedIn, Some((*var).to_string()))
+        })
+}
+
+/// Parses the credentials file: a JSON object keyed by provider ID.
+pub(crate) fn parse_credentials(contents: &str) -> AuthStatus {
+    let Ok(serde_json::Value::Object(providers)) = serde_json::from_str(contents) else {
+        return status(AuthState::Unknown, None);
+    };
+
+    if providers.is_empty() {
+        return status(AuthState::LoggedOut, None);
+    }
+
+    let mut names: Vec<&str> = providers.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    status(AuthState::LoggedIn, Some(names.join(", ")))
+}
+
+/// Returns `true` if the output of a failed run indicates missing or rejected credentials.
+pub(crate) fn is_auth_failure(stdout: &str, stderr: &str) -> bool {
+    let stdout = stdout.to_lowercase();
+    let stderr = stderr.to_lowercase();
+    AUTH_FAILURE_MARKERS
+        .iter()
+        .any(|marker| stdout.contains(marker) || stderr.contains(marker))
+}
+
+fn status(state: AuthState, method: Option<String>) -> AuthStatus {
+    AuthStatus {
+        state,
+        method,
+        login_command: LOGIN_COMMAND.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_credentials() {
+        let stored = parse_credentials(
+            r#"{"openai":{"type":"oauth"},"anthropic":{"type":"api","key":"sk-ant"}}"#,
+        );
+        assert_eq!(stored.state, AuthState::LoggedIn);
+        assert_eq!(stored.method.as_deref(), Some("anthropic, openai"));
+
+        assert_eq!(parse_credentials("{}").state, AuthState::LoggedOut);
+        assert_eq!(parse_credentials("not json").state, AuthState::Unknown);
+        assert_eq!(parse_credentials("{}").login_command, LOGIN_COMMAND);
+    }
+
+    #[test]
+    fn test_is_auth_failure() {
+        assert!(is_auth_failure(
+            "",
+            "ProviderAuthError: no credentials for anthropic"
+        ));
+        assert!(!is_auth_failure("", "Error: model not found"));
+    }
+}
@@ -34,6 +34,12 @@
 //! - `--model`: Supports opencode/big-pickle and other available models
 //! - No version-specific flags known; `OpenCode` CLI has minimal flag surface
 //!
+//! When [`OpenCodeConfig::capabilities`] is set, flags the installed CLI
+//! lacks are gated: `--print-logs` and `--log-level` are left out with a
+//! warning, and [`check_features`] rejects configs that need any other
+//! missing flag. Capabilities come from
+//! [`probe_capabilities`](crate::probe::probe_capabilities).
+//!
 //! ## Known Limitations
 //! - No filesystem sandbox mechanism (containment relies on process isolation)
 //! - No tool restriction flags (all configured tools are available)
@@ -43,12 +49,73 @@
 //! - [OpenCode Documentation](https://opencode.ai/docs/)
 //! - [OpenCode MCP Servers](https://opencode.ai/docs/mcp-servers/)
 
-use crate::types::OpenCodeConfig;
+use crate::error::OpenCodeError;
+use crate::types::{Feature, OpenCodeConfig};
+use std::collections::BTreeSet;
 use std::ffi::OsString;
 
+/// Checks that the installed CLI supports every flag `config` needs.
+///
+/// Flags [`build_args`] can leave out are not checked, and nothing is when
+/// [`OpenCodeConfig::capabilities`] is `None`.
+///
+/// # Errors
+///
+/// Returns `OpenCodeError::UnsupportedFeature` listing every needed feature
+/// the CLI lacks.
+pub fn check_features(config: &OpenCodeConfig) -> Result<(), OpenCodeError> {
+    let Some(capabilities) = &config.capabilities else {
+        return Ok(());
+    };
+    let features: Vec<Feature> = required_features(config)
+        .into_iter()
+        .filter(|feature| !capabilities.supports(*feature))
+        .collect();
+    if features.is_empty() {
+        Ok(())
+    } else {
+        Err(OpenCodeError::UnsupportedFeature {
+            version: config.cli_version.clone(),
+            features,
+        })
+    }
+}
+
+/// Features behind the flags `config` cannot do without.
+fn required_features(config: &OpenCodeConfig) -> BTreeSet<Feature> {
+    let mut features = BTreeSet::new();
+    if config.model.is_some() {
+        features.insert(Feature::Model);
+    }
+    if config.port.is_some() {
+        features.insert(Feature::Port);
+    }
+    if config.hostname.is_some() {
+        features.insert(Feature::Hostname);
+    }
+    features
+}
+
 /// Builds the argument list for an `OpenCode` subprocess invocation.
+///
+/// Optional flags the CLI lacks according to
+/// [`OpenCodeConfig::capabilities`] are left out with a warning; see
+/// [`check_features`] for the others.
 #[must_use]
 pub fn build_args(message: &str, config: &OpenCodeConfig) -> Vec<OsString> {
+    let optional = |feature: Feature| {
+        let supported = config
+            .capabilities
+            .as_ref()
+            .is_none_or(|capabilities| capabilities.supports(feature));
+        if !supported {
+            tracing::warn!(
+                flag = feature.flag(),
+                "OpenCode CLI does not support this flag; leaving it out"
+            );
+        }
+        supported
+    };
     let mut args = Vec::new();
 
     args.push(OsString::from("run"));
@@ -58,13 +125,15 @@ pub fn build_args(message: &str, config: &OpenCodeConfig) -> Vec<OsString> {
         args.push(OsString::from(model));
     }
 
-    if config.print_logs {
+    if config.print_logs && optional(Feature::PrintLogs) {
         args.push(OsString::from("--print-logs"));
     }
 
     if let Some(ref level) = config.log_level {
-        args.push(OsString::from("--log-level"));
-        args.push(OsString::from(level));
+        if optional(Feature::LogLevel) {
+            args.push(OsString::from("--log-level"));
+            args.push(OsString::from(level));
+        }
     }
 
     if let Some(port) = config.port {
@@ -82,6 +151,7 @@ pub fn build_args(message: &str, config: &OpenCodeConfig) -> Vec<OsString> {
         .prompt
         .as_ref()
         .map_or_else(|| message.to_string(), |sp| format!("{sp}\n\n{message}"));
+    args.extend(config.extra_args.iter().cloned());
     args.push(OsString::from(effective_message));
 
     args
@@ -231,6 +301,21 @@ mod tests {
             mcp_config_path: Some(std::path::PathBuf::from("/tmp/mcp.json")),
             env_vars: vec![],
             timeout: std::time::Duration::from_secs(60),
+            spawn_timeout: None,
+            first_output_timeout: None,
+            output_limits: crate::types::OutputLimits::default(),
+            log_sink: None,
+            resource_limits: crate::types::ResourceLimits::default(),
+            stall_timeout: None,
+            on_stall: crate::types::StallAction::Warn,
+            stderr_events: None,
+            forward_signals: false,
+            backpressure: crate::types::BackpressurePolicy::default(),
+            env_policy: crate::types::EnvPolicy::default(),
+            extra_args: vec![],
+            parsers: crate::types::builtin_parsers(),
+            cli_version: None,
+            capabilities: None,
         };
         let args = build_args("test prompt", &config);
         let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
@@ -351,4 +436,49 @@ mod tests {
             "User message should follow"
         );
     }
+
+    #[test]
+    fn test_extra_args_precede_message() {
+        let config = OpenCodeConfig {
+            extra_args: vec!["--agent".into(), "plan".into()],
+            ..OpenCodeConfig::default()
+        };
+        let args = build_args("test prompt", &config);
+        let args_str: Vec<&str> = args.iter().filter_map(|s| s.to_str()).collect();
+        assert_eq!(
+            args_str[args_str.len() - 3..],
+            ["--agent", "plan", "test prompt"]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_flags_are_gated_by_capabilities() {
+        let config = OpenCodeConfig {
+            model: Some("opencode/big-pickle".to_string()),
+            print_logs: true,
+            log_level: Some("DEBUG".to_string()),
+            capabilities: Some(crate::types::Capabilities {
+                features: std::iter::once(Feature::Model).collect(),
+            }),
+            ..OpenCodeConfig::default()
+        };
+        check_features(&config).unwrap();
+        let args = build_args("test prompt", &config);
+        assert!(!args
+            .iter()
+            .any(|a| a == "--print-logs" || a == "--log-level"));
+        assert!(args.iter().any(|a| a == "--model"));
+
+        let config = OpenCodeConfig {
+            port: Some(4096),
+            cli_version: Some(semver::Version::new(0, 3, 0)),
+            ..config
+        };
+        let err = check_features(&config).unwrap_err();
+        assert_eq!(err.error_code(), "unsupported_feature");
+        assert_eq!(
+            err.to_string(),
+            "OpenCode CLI 0.3.0 does not support --port"
+        );
+    }
 }
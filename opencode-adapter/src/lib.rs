@@ -37,6 +37,7 @@
 //! - **Configuration** ([`OpenCodeConfig`]): Typed config for model, timeout, working directory
 //! - **Execution** ([`run_opencode`]): Spawns subprocess with bounded output and timeout
 //! - **Streaming** ([`OpenCodeCli::stream`]): Real-time event streaming via channels
+//! - **Server mode** ([`OpenCodeServer`]): Prompts sent over HTTP to one long-lived `opencode serve`
 //! - **Errors** ([`OpenCodeError`]): Rich error types with context (PID, elapsed time, partial output)
 //!
 //! ## Containment
@@ -69,17 +70,24 @@
 
 #![warn(missing_docs)]
 
+pub mod auth;
 pub mod cmd;
 pub mod discovery;
 pub mod error;
+pub mod probe;
 pub mod process;
+pub mod server;
 pub mod types;
 
+use std::borrow::Cow;
 use tokio::process::Command;
 
+pub use auth::auth_status;
 pub use discovery::discover_opencode;
 pub use error::OpenCodeError;
+pub use probe::probe_capabilities;
 pub use process::run_opencode;
+pub use server::OpenCodeServer;
 pub use types::*;
 
 /// High-level handle for the `OpenCode` CLI binary.
@@ -87,13 +95,36 @@ pub use types::*;
 pub struct OpenCodeCli {
     /// Filesystem path to the `OpenCode` executable.
     pub path: std::path::PathBuf,
+    /// Features of the CLI, which runs gate their flags on. `None` emits
+    /// every flag unchecked.
+    pub capabilities: Option<types::Capabilities>,
 }
 
 impl OpenCodeCli {
     /// Creates a new handle pointing at the given binary path.
     #[must_use]
     pub const fn new(path: std::path::PathBuf) -> Self {
-        Self { path }
+        Self {
+            path,
+            capabilities: None,
+        }
+    }
+
+    /// Sets the capabilities runs gate their flags on, usually from
+    /// [`probe_capabilities`](Self::probe_capabilities).
+    #[must_use]
+    pub fn with_capabilities(mut self, capabilities: types::Capabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Detects the CLI's features from its help text.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OpenCodeError::SpawnFailed` if a help command cannot be run.
+    pub async fn probe_capabilities(&self) -> Result<types::Capabilities, OpenCodeError> {
+        probe_capabilities(&self.path).await
     }
 
     /// Runs `--version` to verify the binary is functional.
@@ -122,6 +153,13 @@ impl OpenCodeCli {
         }
     }
 
+    /// Reports whether `OpenCode` has stored or environment credentials.
+    ///
+    /// See [`auth::auth_status`] for how the state is detected.
+    pub async fn auth_status(&self) -> types::AuthStatus {
+        auth_status().await
+    }
+
     /// Runs `OpenCode` to completion and returns the full result.
     ///
     /// # Errors
@@ -133,7 +171,34 @@ impl OpenCodeCli {
         message: &str,
         config: &types::OpenCodeConfig,
     ) -> Result<types::RunResult, OpenCodeError> {
-        run_opencode(&self.path, message, config, None).await
+        run_opencode(&self.path, message, &self.gated(config), None).await
+    }
+
+    /// Synchronous [`run`](Self::run) on a runtime of its own, with the
+    /// `blocking` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`run`](Self::run), and
+    /// `OpenCodeError::SpawnFailed` if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn run_blocking(
+        &self,
+        message: &str,
+        config: &types::OpenCodeConfig,
+    ) -> Result<types::RunResult, OpenCodeError> {
+        rig_cli_process_core::blocking::block_on(self.run(message, config)).map_err(|source| {
+            OpenCodeError::SpawnFailed {
+                stage: "blocking runtime".to_string(),
+                source,
+            }
+        })?
+    }
+
+    /// Builds the command [`run`](Self::run) would spawn, without spawning it.
+    #[must_use]
+    pub fn dry_run(&self, message: &str, config: &types::OpenCodeConfig) -> types::Invocation {
+        process::dry_run(&self.path, message, &self.gated(config))
     }
 
     /// Runs `OpenCode` while streaming events through `sender`.
@@ -148,6 +213,19 @@ impl OpenCodeCli {
         config: &types::OpenCodeConfig,
         sender: tokio::sync::mpsc::Sender<types::StreamEvent>,
     ) -> Result<types::RunResult, OpenCodeError> {
-        run_opencode(&self.path, message, config, Some(sender)).await
+        run_opencode(&self.path, message, &self.gated(config), Some(sender)).await
+    }
+
+    /// Fills in the handle's capabilities where `config` has none.
+    fn gated<'a>(&self, config: &'a types::OpenCodeConfig) -> Cow<'a, types::OpenCodeConfig> {
+        match &self.capabilities {
+            Some(capabilities) if config.capabilities.is_none() => {
+                Cow::Owned(types::OpenCodeConfig {
+                    capabilities: Some(capabilities.clone()),
+                    ..config.clone()
+                })
+            }
+            _ => Cow::Borrowed(config),
+        }
     }
 }
@@ -0,0 +1,147 @@
+//! Feature detection from the `OpenCode` CLI's help text.
+//!
+//! [`probe_capabilities`] reads `opencode --help` and `opencode run --help`
+//! and records which of the flags and subcommands the adapter uses are
+//! there. Passing the result as
+//! [`OpenCodeConfig::capabilities`](crate::types::OpenCodeConfig::capabilities)
+//! gates [`build_args`](crate::cmd::build_args) on it.
+
+use crate::error::OpenCodeError;
+use crate::types::{Capabilities, Feature};
+use std::collections::BTreeSet;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Flags each feature is detected by.
+const FEATURE_FLAGS: &[(Feature, &str)] = &[
+    (Feature::Resume, "--session"),
+    (Feature::Model, "--model"),
+    (Feature::PrintLogs, "--print-logs"),
+    (Feature::LogLevel, "--log-level"),
+    (Feature::Port, "--port"),
+    (Feature::Hostname, "--hostname"),
+];
+
+/// Detects the features of the `OpenCode` CLI at `path` from its help text.
+///
+/// # Errors
+///
+/// Returns `OpenCodeError::SpawnFailed` if a help command cannot be run.
+pub async fn probe_capabilities(path: &Path) -> Result<Capabilities, OpenCodeError> {
+    let help = help_text(path, &["--help"]).await?;
+    let run_help = help_text(path, &["run", "--help"]).await?;
+    Ok(parse_capabilities(&help, &run_help))
+}
+
+/// Builds the capabilities described by the top-level and `run` help.
+///
+/// Global options such as `--print-logs` are listed in both, depending on
+/// the release, so flags count wherever they appear.
+#[must_use]
+pub fn parse_capabilities(help: &str, run_help: &str) -> Capabilities {
+    let lines = || help.lines().chain(run_help.lines());
+    let mut features: BTreeSet<Feature> = FEATURE_FLAGS
+        .iter()
+        .filter(|(_, flag)| lines().any(|line| declares(line, flag)))
+        .map(|(feature, _)| *feature)
+        .collect();
+    if lines().any(|line| declares(line, "--format") && line.contains("json")) {
+        features.insert(Feature::Json);
+    }
+    if help.lines().any(|line| lists_command(line, "serve")) {
+        features.insert(Feature::Serve);
+    }
+    Capabilities { features }
+}
+
+/// Runs the CLI with `args` and returns its help text.
+///
+/// Some releases print help on stderr, so both streams are read.
+async fn help_text(path: &Path, args: &[&str]) -> Result<String, OpenCodeError> {
+    let output =
+        Command::new(path)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| OpenCodeError::SpawnFailed {
+                stage: "capability probe".to_string(),
+                source: e,
+            })?;
+    Ok(format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    ))
+}
+
+/// Whether `line` is the entry of `flag` rather than prose mentioning it.
+fn declares(line: &str, flag: &str) -> bool {
+    line.split_whitespace()
+        .take_while(|token| token.starts_with('-'))
+        .any(|token| token.trim_end_matches(',') == flag)
+}
+
+/// Whether `line` lists the `name` subcommand, as `opencode name ...` or
+/// `name ...`.
+fn lists_command(line: &str, name: &str) -> bool {
+    let mut tokens = line.split_whitespace();
+    match tokens.next() {
+        Some("opencode") => tokens.next() == Some(name),
+        first => line.starts_with("  ") && first == Some(name),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    const HELP: &str = r#"
+Commands:
+  opencode [project]        start opencode tui                        [default]
+  opencode run [message..]  run opencode with a message
+  opencode serve            starts a headless opencode server
+
+Options:
+  -h, --help        show help                                          [boolean]
+      --print-logs  print logs to stderr                               [boolean]
+      --log-level   log level [string] [choices: "DEBUG", "INFO", "WARN", "ERROR"]
+"#;
+
+    const RUN_HELP: &str = r#"
+Positionals:
+  message  message to send                                 [array] [default: []]
+
+Options:
+  -m, --model     model to use in the format of provider/model          [string]
+  -c, --continue  continue the last session                            [boolean]
+  -s, --session   session id to continue                                [string]
+      --format    format: default (formatted) or json (raw JSON events)
+                                  [string] [choices: "default", "json"]
+"#;
+
+    #[test]
+    fn test_parse_capabilities_reads_both_help_texts() {
+        let capabilities = parse_capabilities(HELP, RUN_HELP);
+        assert_eq!(
+            capabilities.features.into_iter().collect::<Vec<_>>(),
+            [
+                Feature::Json,
+                Feature::Resume,
+                Feature::Model,
+                Feature::PrintLogs,
+                Feature::LogLevel,
+                Feature::Serve,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prose_mentions_do_not_count() {
+        let capabilities = parse_capabilities(
+            "  run  pass --port through to serve\n",
+            "      --format  output format  [choices: \"default\"]\n",
+        );
+        assert!(capabilities.features.is_empty(), "{capabilities:?}");
+    }
+}
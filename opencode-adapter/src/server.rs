@@ -0,0 +1,630 @@
+//! HTTP backend for `opencode serve`.
+//!
+//! Every [`run_opencode`](crate::process::run_opencode) call starts a fresh
+//! `OpenCode` process, which pays the CLI's startup cost on each prompt.
+//! [`OpenCodeServer`] instead starts one long-lived `opencode serve` process
+//! (or attaches to one that is already running) and sends each prompt to it
+//! over HTTP, streaming progress from the server's `/event` Server-Sent Events
+//! endpoint.
+//!
+//! Each prompt runs in a new server session, so runs do not share history.
+//! Results are returned as the same [`RunResult`] / [`StreamEvent`] types as
+//! the subprocess path.
+
+use crate::error::OpenCodeError;
+use crate::types::{LatencyBreakdown, OpenCodeConfig, RunResult, StreamEvent};
+use rig_cli_process_core::EventSink;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+
+/// Maximum time to wait for `opencode serve` to report its listening address.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to keep reading events after the prompt request has returned.
+const EVENT_DRAIN_GRACE: Duration = Duration::from_secs(2);
+
+/// Hostname the server binds to when [`OpenCodeConfig::hostname`] is unset.
+const DEFAULT_HOSTNAME: &str = "127.0.0.1";
+
+/// Handle to a running `opencode serve` instance.
+///
+/// Cloning is cheap; clones share the same server. A server started with
+/// [`start`](Self::start) is killed when the last clone is dropped.
+#[derive(Clone)]
+pub struct OpenCodeServer {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    base_url: String,
+    http: reqwest::Client,
+    pid: u32,
+    /// Owned server process; `None` when attached to an external server.
+    child: Option<Child>,
+}
+
+impl std::fmt::Debug for OpenCodeServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenCodeServer")
+            .field("base_url", &self.inner.base_url)
+            .field("owned", &self.inner.child.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl OpenCodeServer {
+    /// Starts `opencode serve` from the binary at `path` and waits until it listens.
+    ///
+    /// The server binds to [`OpenCodeConfig::hostname`] (default `127.0.0.1`)
+    /// and [`OpenCodeConfig::port`] (default: any free port), runs in
+    /// [`OpenCodeConfig::cwd`], and receives [`OpenCodeConfig::env_vars`] and
+    /// [`OpenCodeConfig::mcp_config_path`] exactly as a subprocess run would.
+    /// The remaining fields are applied per prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OpenCodeError::SpawnFailed`] if the process cannot be
+    /// started, or [`OpenCodeError::Server`] if it exits or stays silent for
+    /// 30 seconds without reporting a listening address.
+    pub async fn start(
+        path: &std::path::Path,
+        config: &OpenCodeConfig,
+    ) -> Result<Self, OpenCodeError> {
+        let mut cmd = Command::new(path);
+        cmd.arg("serve")
+            .arg("--port")
+            .arg(config.port.unwrap_or(0).to_string())
+            .arg("--hostname")
+            .arg(config.hostname.as_deref().unwrap_or(DEFAULT_HOSTNAME))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+
+        if let Some(cwd) = &config.cwd {
+            cmd.current_dir(cwd);
+        }
+        for (k, v) in &config.env_vars {
+            cmd.env(k, v);
+        }
+        if let Some(ref mcp_path) = config.mcp_config_path {
+            cmd.env("OPENCODE_CONFIG", mcp_path);
+        }
+
+        let mut child = cmd.spawn().map_err(|e| OpenCodeError::SpawnFailed {
+            stage: "spawn server".to_string(),
+            source: e,
+        })?;
+        let pid = child.id().ok_or(OpenCodeError::NoPid)?;
+        let stdout = child.stdout.take().ok_or(OpenCodeError::NoStdout)?;
+        let mut lines = BufReader::new(stdout).lines();
+
+        let listening = tokio::time::timeout(STARTUP_TIMEOUT, async {
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Some(url) = listening_url(&line) {
+                    return Some(url);
+                }
+            }
+            None
+        })
+        .await;
+
+        let base_url = match listening {
+            Ok(Some(url)) => url,
+            Ok(None) => {
+                return Err(server_error(
+                    "start server",
+                    "server exited before reporting its address",
+                ))
+            }
+            Err(_) => {
+                return Err(server_error(
+                    "start server",
+                    format!("no listening address after {STARTUP_TIMEOUT:?}"),
+                ))
+            }
+        };
+
+        // Keep draining stdout so server logging never blocks on a full pipe.
+        tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                base_url,
+                http: reqwest::Client::new(),
+                pid,
+                child: Some(child),
+            }),
+        })
+    }
+
+    /// Attaches to an already running server at `base_url` (e.g. `http://127.0.0.1:4096`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OpenCodeError::Server`] if the server does not answer.
+    pub async fn attach(base_url: impl Into<String>) -> Result<Self, OpenCodeError> {
+        let server = Self {
+            inner: Arc::new(Inner {
+                base_url: base_url.into().trim_end_matches('/').to_string(),
+                http: reqwest::Client::new(),
+                pid: 0,
+                child: None,
+            }),
+        };
+        server.check_health().await?;
+        Ok(server)
+    }
+
+    /// Base URL of the server, without a trailing slash.
+    #[must_use]
+    pub fn base_url(&self) -> &str {
+        &self.inner.base_url
+    }
+
+    /// Verifies that the server answers HTTP requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OpenCodeError::Server`] if the request fails or is rejected.
+    pub async fn check_health(&self) -> Result<(), OpenCodeError> {
+        let response = self
+            .inner
+            .http
+            .get(self.url("/config"))
+            .send()
+            .await
+            .map_err(|e| server_error("health check", e.to_string()))?;
+        ensure_success("health check", &response)
+    }
+
+    /// Sends `message` in a new session and waits for the reply.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OpenCodeError::Server`] if a request fails or the server
+    /// reports an error for the run, and [`OpenCodeError::Timeout`] if no
+    /// reply arrives within [`OpenCodeConfig::timeout`].
+    pub async fn run(
+        &self,
+        message: &str,
+        config: &OpenCodeConfig,
+    ) -> Result<RunResult, OpenCodeError> {
+        self.prompt(message, config, None).await
+    }
+
+    /// Sends `message` in a new session, streaming events through `sender`.
+    ///
+    /// Text is forwarded as it is generated, subject to
+    /// [`OpenCodeConfig::backpressure`].
+    ///
+    /// # Errors
+    ///
+    /// See [`run`](Self::run).
+    pub async fn stream(
+        &self,
+        message: &str,
+        config: &OpenCodeConfig,
+        sender: mpsc::Sender<StreamEvent>,
+    ) -> Result<RunResult, OpenCodeError> {
+        self.prompt(message, config, Some(sender)).await
+    }
+
+    async fn prompt(
+        &self,
+        message: &str,
+        config: &OpenCodeConfig,
+        sender: Option<mpsc::Sender<StreamEvent>>,
+    ) -> Result<RunResult, OpenCodeError> {
+        let start_time = Instant::now();
+        let session_id = self.create_session(config).await?;
+
+        // Subscribe before prompting so no early events are missed.
+        let events = match sender {
+            Some(tx) => {
+                let response = self
+                    .inner
+                    .http
+                    .get(self.url("/event"))
+                    .send()
+                    .await
+                    .map_err(|e| server_error("subscribe to events", e.to_string()))?;
+                ensure_success("subscribe to events", &response)?;
+                let sink = EventSink::new(tx, config.backpressure);
+                Some(tokio::spawn(forward_events(
+                    response,
+                    session_id.clone(),
+                    sink,
+                )))
+            }
+            None => None,
+        };
+
+        let reply = tokio::time::timeout(
+            config.timeout,
+            self.send_message(&session_id, message, config),
+        )
+        .await;
+
+        let result = match reply {
+            Ok(result) => result,
+            Err(_elapsed) => {
+                // Best effort: stop the session so the server frees the model.
+                let _ = self
+                    .inner
+                    .http
+                    .post(self.url(&format!("/session/{session_id}/abort")))
+                    .send()
+                    .await;
+                Err(OpenCodeError::Timeout {
+                    stage: crate::types::TimeoutStage::Total,
+                    elapsed: start_time.elapsed(),
+                    pid: self.inner.pid,
+                    partial_stdout: String::new(),
+                    partial_stderr: String::new(),
+                })
+            }
+        };
+
+        if let Some(mut task) = events {
+            if tokio::time::timeout(EVENT_DRAIN_GRACE, &mut task)
+                .await
+                .is_err()
+            {
+                task.abort();
+            }
+        }
+
+        let text = result?;
+        let duration_ms = u64::try_from(start_time.elapsed().as_millis()).unwrap_or(u64::MAX);
+        Ok(RunResult {
+            stdout: text,
+            stderr: String::new(),
+            exit_code: 0,
+            duration_ms,
+            latency: LatencyBreakdown {
+                total_ms: duration_ms,
+                ..LatencyBreakdown::default()
+            },
+            event_offsets_ms: Vec::new(),
+        })
+    }
+
+    /// Creates a session and returns its ID.
+    async fn create_session(&self, config: &OpenCodeConfig) -> Result<String, OpenCodeError> {
+        let response = with_directory(self.inner.http.post(self.url("/session")), config)
+            .json(&json!({}))
+            .send()
+            .await
+            .map_err(|e| server_error("create session", e.to_string()))?;
+        ensure_success("create session", &response)?;
+        let session: Value = response
+            .json()
+            .await
+            .map_err(|e| server_error("create session", e.to_string()))?;
+        session
+            .get("id")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| server_error("create session", "response has no session id"))
+    }
+
+    /// Sends the prompt and returns the reply text once the run completes.
+    async fn send_message(
+        &self,
+        session_id: &str,
+        message: &str,
+        config: &OpenCodeConfig,
+    ) -> Result<String, OpenCodeError> {
+        let response = with_directory(
+            self.inner
+                .http
+                .post(self.url(&format!("/session/{session_id}/message"))),
+            config,
+        )
+        .json(&message_body(message, config))
+        .send()
+        .await
+        .map_err(|e| server_error("send message", e.to_string()))?;
+        ensure_success("send message", &response)?;
+        let reply: Value = response
+            .json()
+            .await
+            .map_err(|e| server_error("send message", e.to_string()))?;
+        reply_text(&reply)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.inner.base_url)
+    }
+}
+
+/// Scopes a request to [`OpenCodeConfig::cwd`], if set.
+fn with_directory(
+    request: reqwest::RequestBuilder,
+    config: &OpenCodeConfig,
+) -> reqwest::RequestBuilder {
+    match &config.cwd {
+        Some(cwd) => request.query(&[("directory", cwd.to_string_lossy())]),
+        None => request,
+    }
+}
+
+/// Reads the SSE stream and forwards this session's events until it goes idle.
+async fn forward_events(
+    mut response: reqwest::Response,
+    session_id: String,
+    mut sink: EventSink<StreamEvent>,
+) {
+    let mut parser = SseParser::default();
+    let mut tracker = SessionTracker::new(session_id);
+
+    'stream: while let Ok(Some(chunk)) = response.chunk().await {
+        for data in parser.push(&String::from_utf8_lossy(&chunk)) {
+            let Ok(event) = serde_json::from_str::<Value>(&data) else {
+                continue;
+            };
+            match tracker.interpret(&event) {
+                Some(ServerEvent::Emit(event)) => sink.push(event).await,
+                Some(ServerEvent::Idle) => break 'stream,
+                None => {}
+            }
+        }
+    }
+
+    sink.finish().await;
+}
+
+/// Accumulates SSE bytes and yields the `data` payload of each complete event.
+#[derive(Default)]
+struct SseParser {
+    buffer: String,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.buffer.push_str(&chunk.replace("\r\n", "\n"));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.find("\n\n") {
+            let block: String = self.buffer.drain(..end + 2).collect();
+            let data: Vec<&str> = block
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect();
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+        events
+    }
+}
+
+/// What a server event means for the tracked session.
+#[derive(Debug)]
+enum ServerEvent {
+    /// Forward this event to the caller.
+    Emit(StreamEvent),
+    /// The session finished processing the prompt.
+    Idle,
+}
+
+/// Filters the server-wide event bus down to one session's assistant output.
+struct SessionTracker {
+    session_id: String,
+    /// Assistant message IDs; parts of other messages (the prompt) are skipped.
+    assistant_messages: HashSet<String>,
+    /// Text length already forwarded, per part ID.
+    forwarded: HashMap<String, usize>,
+}
+
+impl SessionTracker {
+    fn new(session_id: String) -> Self {
+        Self {
+            session_id,
+            assistant_messages: HashSet::new(),
+            forwarded: HashMap::new(),
+        }
+    }
+
+    fn interpret(&mut self, event: &Value) -> Option<ServerEvent> {
+        let props = event.get("properties")?;
+        match event.get("type").and_then(Value::as_str)? {
+            "message.updated" => {
+                let info = props.get("info")?;
+                if self.is_ours(info)
+                    && info.get("role").and_then(Value::as_str) == Some("assistant")
+                {
+                    let id = info.get("id").and_then(Value::as_str)?;
+                    self.assistant_messages.insert(id.to_string());
+                }
+                None
+            }
+            "message.part.updated" => {
+                let part = props.get("part")?;
+                let message_id = part.get("messageID").and_then(Value::as_str)?;
+                if !self.is_ours(part)
+                    || !self.assistant_messages.contains(message_id)
+                    || part.get("type").and_then(Value::as_str) != Some("text")
+                {
+                    return None;
+                }
+                let part_id = part.get("id").and_then(Value::as_str)?;
+                let full = part.get("text").and_then(Value::as_str).unwrap_or_default();
+                let sent = self.forwarded.entry(part_id.to_string()).or_default();
+                // Prefer the explicit delta; fall back to diffing the full text.
+                let text = props
+                    .get("delta")
+                    .and_then(Value::as_str)
+                    .unwrap_or_else(|| full.get(*sent..).unwrap_or_default())
+                    .to_string();
+                *sent = full.len().max(*sent + text.len());
+                (!text.is_empty()).then_some(ServerEvent::Emit(StreamEvent::Text { text }))
+            }
+            "session.error" if self.is_ours(props) || props.get("sessionID").is_none() => {
+                let error = props.get("error")?;
+                let message = error
+                    .pointer("/data/message")
+                    .or_else(|| error.get("name"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("session error")
+                    .to_string();
+                Some(ServerEvent::Emit(StreamEvent::Error { message }))
+            }
+            "session.idle" if self.is_ours(props) => Some(ServerEvent::Idle),
+            _ => None,
+        }
+    }
+
+    fn is_ours(&self, value: &Value) -> bool {
+        value.get("sessionID").and_then(Value::as_str) == Some(self.session_id.as_str())
+    }
+}
+
+/// Extracts the server URL from an `opencode serve` startup line.
+fn listening_url(line: &str) -> Option<String> {
+    line.split_whitespace()
+        .find(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|url| url.trim_end_matches('/').to_string())
+}
+
+/// Builds the `/session/{id}/message` request body.
+fn message_body(message: &str, config: &OpenCodeConfig) -> Value {
+    let mut body = json!({ "parts": [{ "type": "text", "text": message }] });
+    if let Some((provider, model)) = config.model.as_deref().and_then(|m| m.split_once('/')) {
+        body["model"] = json!({ "providerID": provider, "modelID": model });
+    }
+    if let Some(ref system) = config.prompt {
+        body["system"] = json!(system);
+    }
+    body
+}
+
+/// Joins the text parts of a prompt reply, or reports the run's error.
+fn reply_text(reply: &Value) -> Result<String, OpenCodeError> {
+    if let Some(error) = reply.pointer("/info/error") {
+        let message = error
+            .pointer("/data/message")
+            .or_else(|| error.get("name"))
+            .and_then(Value::as_str)
+            .map_or_else(|| error.to_string(), str::to_string);
+        return Err(server_error("run prompt", message));
+    }
+
+    Ok(reply
+        .get("parts")
+        .and_then(Value::as_array)
+        .map(|parts| {
+            parts
+                .iter()
+                .filter(|part| part.get("type").and_then(Value::as_str) == Some("text"))
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("")
+        })
+        .unwrap_or_default())
+}
+
+fn ensure_success(stage: &str, response: &reqwest::Response) -> Result<(), OpenCodeError> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(server_error(stage, format!("HTTP {status}")))
+    }
+}
+
+fn server_error(stage: &str, message: impl Into<String>) -> OpenCodeError {
+    OpenCodeError::Server {
+        stage: stage.to_string(),
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.push("data: {\"a\":"), Vec::<String>::new());
+        assert_eq!(parser.push("1}\r\n\r\n: ping\n\ndata: x\n"), ["{\"a\":1}"]);
+        assert_eq!(parser.push("data: y\n\n"), ["x\ny"]);
+    }
+
+    #[test]
+    fn test_tracker_forwards_only_assistant_text_of_session() {
+        let mut tracker = SessionTracker::new("ses_1".to_string());
+        let part = |message: &str, text: &str| {
+            json!({
+                "type": "message.part.updated",
+                "properties": { "part": {
+                    "id": format!("prt_{message}"), "sessionID": "ses_1",
+                    "messageID": message, "type": "text", "text": text,
+                }},
+            })
+        };
+
+        // The user's own prompt is echoed back as a part; it must be skipped.
+        assert!(tracker.interpret(&part("msg_user", "hi")).is_none());
+
+        tracker.interpret(&json!({
+            "type": "message.updated",
+            "properties": { "info": { "id": "msg_a", "sessionID": "ses_1", "role": "assistant" } },
+        }));
+        let texts: Vec<String> = ["Hel", "Hello", "Hello!"]
+            .iter()
+            .filter_map(|text| match tracker.interpret(&part("msg_a", text)) {
+                Some(ServerEvent::Emit(StreamEvent::Text { text })) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, ["Hel", "lo", "!"]);
+
+        let other = json!({ "type": "session.idle", "properties": { "sessionID": "ses_2" } });
+        assert!(tracker.interpret(&other).is_none());
+        let idle = json!({ "type": "session.idle", "properties": { "sessionID": "ses_1" } });
+        assert!(matches!(tracker.interpret(&idle), Some(ServerEvent::Idle)));
+    }
+
+    #[test]
+    fn test_message_body_and_reply() {
+        let config = OpenCodeConfig {
+            model: Some("anthropic/claude-sonnet-4".to_string()),
+            prompt: Some("Be brief.".to_string()),
+            ..OpenCodeConfig::default()
+        };
+        let body = message_body("2+2?", &config);
+        assert_eq!(body["model"]["providerID"], "anthropic");
+        assert_eq!(body["model"]["modelID"], "claude-sonnet-4");
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["parts"][0]["text"], "2+2?");
+
+        let reply = json!({ "info": {}, "parts": [
+            { "type": "step-start" },
+            { "type": "text", "text": "4" },
+        ]});
+        assert_eq!(reply_text(&reply).unwrap(), "4");
+
+        let failed = json!({ "info": { "error": { "name": "ProviderAuthError", "data": { "message": "no key" } } } });
+        assert_eq!(reply_text(&failed).unwrap_err().error_code(), "server");
+    }
+
+    #[test]
+    fn test_listening_url() {
+        assert_eq!(
+            listening_url("opencode server listening on http://127.0.0.1:4096").as_deref(),
+            Some("http://127.0.0.1:4096")
+        );
+        assert!(listening_url("starting up").is_none());
+    }
+}
@@ -1,9 +1,75 @@
 //! Shared types for `OpenCode` adapter configuration and results.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ffi::OsString;
 use std::path::PathBuf;
 use std::time::Duration;
 
+pub use rig_cli_process_core::{
+    AuthState, AuthStatus, BackpressurePolicy, ConfigFile, EnvPolicy, Invocation, LatencyBreakdown,
+    OutputLimits, OverflowPolicy, ParserRegistry, RedactionPolicy, ResourceLimits, StallAction,
+    StderrKind, StderrPatterns, StreamParser, TimeoutStage,
+};
+
+/// An `OpenCode` CLI flag or subcommand that not every release has.
+///
+/// `OpenCode` has no sandbox or web search flags, so unlike Claude Code and
+/// Codex there are no such features to detect.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Feature {
+    /// JSON event output, `run --format json`.
+    Json,
+    /// Resuming an earlier session with `run --session`.
+    Resume,
+    /// The `--model` flag.
+    Model,
+    /// The `--print-logs` flag.
+    PrintLogs,
+    /// The `--log-level` flag.
+    LogLevel,
+    /// The `--port` flag.
+    Port,
+    /// The `--hostname` flag.
+    Hostname,
+    /// The `serve` subcommand behind
+    /// [`OpenCodeServer`](crate::server::OpenCodeServer).
+    Serve,
+}
+
+impl Feature {
+    /// The CLI flag the feature stands for.
+    #[must_use]
+    pub const fn flag(self) -> &'static str {
+        match self {
+            Self::Json => "--format json",
+            Self::Resume => "--session",
+            Self::Model => "--model",
+            Self::PrintLogs => "--print-logs",
+            Self::LogLevel => "--log-level",
+            Self::Port => "--port",
+            Self::Hostname => "--hostname",
+            Self::Serve => "serve",
+        }
+    }
+}
+
+/// Set of features detected from the `OpenCode` CLI help text, by
+/// [`probe_capabilities`](crate::probe::probe_capabilities).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Features present in this CLI build.
+    pub features: BTreeSet<Feature>,
+}
+
+impl Capabilities {
+    /// Returns `true` if the given feature is supported.
+    #[must_use]
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
 /// Configuration for an `OpenCode` CLI invocation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenCodeConfig {
@@ -23,10 +89,79 @@ pub struct OpenCodeConfig {
     pub env_vars: Vec<(String, String)>,
     /// Path to an MCP configuration JSON file (`OpenCode` format).
     pub mcp_config_path: Option<PathBuf>,
-    /// Maximum wall-clock time before the process is killed.
+    /// Maximum total wall-clock time before the process is killed.
     pub timeout: Duration,
+    /// Maximum time spawning the process and writing its stdin may take.
+    /// `None` leaves startup bounded by `timeout` alone.
+    #[serde(default)]
+    pub spawn_timeout: Option<Duration>,
+    /// Maximum time from the start of the run until the process prints its
+    /// first stdout or stderr line, e.g. to catch a hung MCP handshake long
+    /// before `timeout`. `None` disables the check.
+    #[serde(default)]
+    pub first_output_timeout: Option<Duration>,
     /// Working directory for the child process.
     pub cwd: Option<PathBuf>,
+    /// Memory bounds and overflow behaviour for captured stdout / stderr.
+    pub output_limits: OutputLimits,
+    /// File that raw stdout / stderr lines are appended to as they arrive,
+    /// regardless of `output_limits`. Rotated by size.
+    #[serde(default)]
+    pub log_sink: Option<PathBuf>,
+    /// OS limits on the subprocess's memory, CPU time, open files and
+    /// priority.
+    #[serde(default)]
+    pub resource_limits: ResourceLimits,
+    /// How long the subprocess may print nothing on stdout or stderr
+    /// before it counts as stalled. `None` disables stall detection.
+    #[serde(default)]
+    pub stall_timeout: Option<Duration>,
+    /// What to do when the subprocess stalls.
+    #[serde(default)]
+    pub on_stall: StallAction,
+    /// Patterns classifying stderr lines as they arrive, streamed as
+    /// [`StreamEvent::Progress`] and [`StreamEvent::Warning`]. `None` only
+    /// captures stderr.
+    #[serde(default)]
+    pub stderr_events: Option<StderrPatterns>,
+    /// Whether SIGINT and SIGTERM received by the host are passed on to the
+    /// subprocess and its children, cancelling the run with `Cancelled`.
+    ///
+    /// The subprocess then runs in its own process group. Listening for the
+    /// signals stops them from terminating the host for the rest of its
+    /// life, so the host has to exit on its own after a cancelled run.
+    #[serde(default)]
+    pub forward_signals: bool,
+    /// Delivery of stream events when the receiver is slow.
+    pub backpressure: BackpressurePolicy,
+    /// Host environment variables the subprocess inherits. `env_vars` is
+    /// applied on top.
+    #[serde(default)]
+    pub env_policy: EnvPolicy,
+    /// Arguments appended verbatim after the generated ones, before the
+    /// prompt, for CLI flags the config does not cover yet.
+    ///
+    /// Running fails with `ConflictingArg` if one of them is a flag the
+    /// config already sets.
+    #[serde(default)]
+    pub extra_args: Vec<OsString>,
+    /// Parsers for JSON output lines. Defaults to [`builtin_parsers`];
+    /// register more to support a new CLI release.
+    #[serde(skip, default = "builtin_parsers")]
+    pub parsers: StreamParsers,
+    /// CLI version that selects among [`parsers`](Self::parsers); `None`
+    /// applies every parser.
+    #[serde(default)]
+    pub cli_version: Option<semver::Version>,
+    /// Features of the installed CLI, which gate the flags emitted.
+    ///
+    /// `--print-logs` and `--log-level` are left out with a warning when
+    /// unsupported; running a config that needs any other unsupported flag
+    /// fails with `UnsupportedFeature`. [`OpenCodeCli`](crate::OpenCodeCli)
+    /// fills it in from its probed capabilities when unset; `None` emits
+    /// every flag unchecked.
+    #[serde(default)]
+    pub capabilities: Option<Capabilities>,
 }
 
 impl Default for OpenCodeConfig {
@@ -41,7 +176,22 @@ impl Default for OpenCodeConfig {
             env_vars: Vec::new(),
             mcp_config_path: None,
             timeout: Duration::from_secs(300),
+            spawn_timeout: None,
+            first_output_timeout: None,
             cwd: None,
+            output_limits: OutputLimits::default(),
+            log_sink: None,
+            resource_limits: ResourceLimits::default(),
+            stall_timeout: None,
+            on_stall: StallAction::Warn,
+            stderr_events: None,
+            forward_signals: false,
+            backpressure: BackpressurePolicy::default(),
+            env_policy: EnvPolicy::default(),
+            extra_args: Vec::new(),
+            parsers: builtin_parsers(),
+            cli_version: None,
+            capabilities: None,
         }
     }
 }
@@ -57,6 +207,15 @@ pub struct RunResult {
     pub exit_code: i32,
     /// Wall-clock duration in milliseconds.
     pub duration_ms: u64,
+    /// Where the run's time went: spawn, first text, exit. Runs through
+    /// [`OpenCodeServer`](crate::server::OpenCodeServer) only report the total.
+    #[serde(default)]
+    pub latency: LatencyBreakdown,
+    /// Milliseconds from the start of the run at which each stream event was
+    /// parsed, in parse order. Under [`BackpressurePolicy::Block`] the n-th
+    /// offset belongs to the n-th event received. Empty for server runs.
+    #[serde(default)]
+    pub event_offsets_ms: Vec<u64>,
 }
 
 /// Events streamed from the `OpenCode` CLI.
@@ -67,6 +226,24 @@ pub enum StreamEvent {
         /// The text content.
         text: String,
     },
+    /// The subprocess has printed nothing for
+    /// [`RunConfig::stall_timeout`].
+    Stalled {
+        /// Milliseconds since the last output line.
+        idle_ms: u64,
+    },
+    /// A stderr line classified as progress by
+    /// [`OpenCodeConfig::stderr_events`], e.g. `Thinking…`.
+    Progress {
+        /// The line, without ANSI escapes.
+        message: String,
+    },
+    /// A stderr line classified as a warning by
+    /// [`OpenCodeConfig::stderr_events`]. The run carries on.
+    Warning {
+        /// The line, without ANSI escapes.
+        message: String,
+    },
     /// An error event.
     Error {
         /// The error message.
@@ -78,3 +255,32 @@ pub enum StreamEvent {
         serde_json::Value,
     ),
 }
+
+impl rig_cli_process_core::TextEvent for StreamEvent {
+    fn text_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::Text { text } => Some(text),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> rig_cli_process_core::EventKind {
+        match self {
+            Self::Text { .. } => rig_cli_process_core::EventKind::Text,
+            _ => rig_cli_process_core::EventKind::Other,
+        }
+    }
+}
+
+/// Parsers for `OpenCode` JSON output, keyed by CLI version.
+pub type StreamParsers = ParserRegistry<StreamEvent>;
+
+/// The built-in parser, which reads each JSON line as a [`StreamEvent`].
+#[must_use]
+pub fn builtin_parsers() -> StreamParsers {
+    StreamParsers::new().register(semver::VersionReq::STAR, |val: &serde_json::Value| {
+        serde_json::from_value::<StreamEvent>(val.clone())
+            .ok()
+            .map(|event| vec![event])
+    })
+}
@@ -9,7 +9,6 @@
 use rig::completion::ToolDefinition;
 use rig::tool::{Tool, ToolSet};
 use rig_cli::prelude::*;
-use rig_cli::tools::ToolSetExt;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -141,10 +140,7 @@ fn build_toolset() -> ToolSet {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // MCP server mode: serves tools over stdio when RIG_MCP_SERVER=1
-    if std::env::var("RIG_MCP_SERVER").is_ok() {
-        build_toolset().into_handler().await?.serve_stdio().await?;
-        return Ok(());
-    }
+    rig_cli::mcp_child::maybe_serve(build_toolset).await;
 
     let client = rig_cli::claude::Client::new().await?;
 
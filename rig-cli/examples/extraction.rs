@@ -8,7 +8,7 @@
 //! Run: `cargo run -p rig-cli --example extraction`
 
 use rig::tool::ToolSet;
-use rig_cli::tools::{JsonSchemaToolkit, ToolSetExt};
+use rig_cli::tools::JsonSchemaToolkit;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -45,9 +45,7 @@ fn build_toolset() -> ToolSet {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    if std::env::var("RIG_MCP_SERVER").is_ok() {
-        return build_toolset().into_handler().await?.serve_stdio().await;
-    }
+    rig_cli::mcp_child::maybe_serve(build_toolset).await;
 
     let client = rig_cli::claude::Client::new().await?;
 
@@ -11,7 +11,7 @@
 use chrono::{DateTime, Utc};
 use rig::completion::ToolDefinition;
 use rig::tool::{Tool, ToolSet};
-use rig_cli::tools::{JsonSchemaToolkit, ToolSetExt};
+use rig_cli::tools::JsonSchemaToolkit;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -113,10 +113,7 @@ fn build_toolset() -> ToolSet {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    if std::env::var("RIG_MCP_SERVER").is_ok() {
-        build_toolset().into_handler().await?.serve_stdio().await?;
-        return Ok(());
-    }
+    rig_cli::mcp_child::maybe_serve(build_toolset).await;
 
     let client = rig_cli::claude::Client::new().await?;
 
@@ -10,7 +10,6 @@
 
 use rig::tool::ToolSet;
 use rig_cli::prelude::*;
-use rig_cli::tools::ToolSetExt;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -45,9 +44,7 @@ fn build_toolset() -> ToolSet {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // MCP server mode: serves tools over stdio when RIG_MCP_SERVER=1
-    if std::env::var("RIG_MCP_SERVER").is_ok() {
-        return build_toolset().into_handler().await?.serve_stdio().await;
-    }
+    rig_cli::mcp_child::maybe_serve(build_toolset).await;
 
     // --- KEY CODE: One-shot MCP extraction ---
     let client = rig_cli::claude::Client::new().await?;
@@ -0,0 +1,211 @@
+//! Batch structured extraction across many inputs.
+//!
+//! `extract_batch::<T>()` on each provider client runs one MCP tool agent per
+//! input item and deserializes each submission into `T`. The
+//! [`JsonSchemaToolkit`] for `T` is built once and cloned into every run, and
+//! every run reuses the client's already discovered CLI binary instead of
+//! searching for it again. At most
+//! [`ClientConfig::batch_concurrency`](crate::config::ClientConfig::batch_concurrency)
+//! runs are in flight at a time.
+//!
+//! Each run still gets its own MCP result file, so concurrent runs never see
+//! each other's submissions.
+//!
+//! As with any MCP agent, the current executable must serve the same toolkit
+//! when started with `RIG_MCP_SERVER=1`, which
+//! [`maybe_serve`](crate::mcp_child::maybe_serve) takes care of:
+//!
+//! ```ignore
+//! rig_cli::mcp_child::maybe_serve(|| {
+//!     let (submit, validate, example) = JsonSchemaToolkit::<Invoice>::builder()
+//!         .build()
+//!         .build_tools();
+//!     let mut toolset = ToolSet::default();
+//!     toolset.add_tool(submit);
+//!     toolset.add_tool(validate);
+//!     toolset.add_tool(example);
+//!     toolset
+//! })
+//! .await;
+//!
+//! let client = rig_cli::claude::Client::new().await?;
+//! let batch = client.extract_batch::<Invoice>(documents).await;
+//! println!("{}/{} extracted", batch.succeeded(), batch.results.len());
+//! ```
+
+use crate::config::ClientConfig;
+use futures::StreamExt;
+use rig::tool::ToolSet;
+use rig_cli_mcp::extraction::{estimate_tokens, ExtractionError, ExtractionMetrics};
+use rig_cli_mcp::tools::JsonSchemaToolkit;
+use rig_cli_provider::mcp_agent::{CliAdapter, McpToolAgent};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Outcome of an `extract_batch` call.
+#[derive(Debug)]
+pub struct BatchExtraction<T> {
+    /// One result per input item, in input order.
+    pub results: Vec<Result<T, ExtractionError>>,
+    /// Metrics aggregated over every item.
+    ///
+    /// `total_attempts` counts one run per item and `wall_time` covers the
+    /// whole batch. Token counts are estimated from each item and the
+    /// agent's submission.
+    pub metrics: ExtractionMetrics,
+}
+
+impl<T> BatchExtraction<T> {
+    /// Number of items that extracted successfully.
+    #[must_use]
+    pub fn succeeded(&self) -> usize {
+        self.results.iter().filter(|result| result.is_ok()).count()
+    }
+}
+
+/// Settings shared by every run of a batch.
+pub(crate) struct BatchRun<'a> {
+    pub(crate) adapter: CliAdapter,
+    pub(crate) cli_path: &'a Path,
+    pub(crate) config: &'a ClientConfig,
+    pub(crate) payload: Option<&'a str>,
+    pub(crate) images: &'a [PathBuf],
+    pub(crate) preamble: Option<&'a str>,
+}
+
+/// A finished item: the prompt sent, the agent output, and the typed result.
+type ItemOutcome<T> = (String, String, Result<T, ExtractionError>);
+
+impl BatchRun<'_> {
+    /// Runs every item and collects the results in input order.
+    pub(crate) async fn extract<T>(&self, items: Vec<String>) -> BatchExtraction<T>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let start = Instant::now();
+        let toolkit = JsonSchemaToolkit::<T>::builder().build();
+
+        let outcomes: Vec<ItemOutcome<T>> = futures::stream::iter(items)
+            .map(|item| self.extract_one(toolkit.clone(), item))
+            .buffered(self.config.batch_concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut batch = aggregate(outcomes);
+        batch.metrics.wall_time = start.elapsed();
+        batch
+    }
+
+    /// Runs a single item through an MCP tool agent.
+    pub(crate) async fn extract_one<T>(
+        &self,
+        toolkit: JsonSchemaToolkit<T>,
+        item: String,
+    ) -> ItemOutcome<T>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let (submit, validate, example) = toolkit.build_tools();
+        let mut toolset = ToolSet::default();
+        toolset.add_tool(submit);
+        toolset.add_tool(validate);
+        toolset.add_tool(example);
+
+        let mut builder = McpToolAgent::builder()
+            .toolset(toolset)
+            .adapter(self.adapter)
+            .prompt(&item)
+            .timeout(self.config.timeout)
+            .cli_path(self.cli_path);
+        if let Some(payload) = self.payload {
+            builder = builder.payload(payload);
+        }
+        for image in self.images {
+            builder = builder.image(image);
+        }
+        if let Some(preamble) = self.preamble {
+            builder = builder.system_prompt(preamble);
+        }
+        if let Some(ref mode) = self.config.sandbox {
+            builder = builder.sandbox_mode(mode.clone());
+        }
+        if let Some(ref tools) = self.config.builtin_tools {
+            builder = builder.allow_builtins(tools.clone());
+        }
+
+        match builder.run().await {
+            Ok(result) => {
+                let typed = match result.typed_result::<T>() {
+                    Ok(Some(value)) => Ok(value),
+                    Ok(None) => Err(ExtractionError::AgentError(
+                        "agent finished without calling submit".to_string(),
+                    )),
+                    Err(e) => Err(ExtractionError::ParseError {
+                        message: e.to_string(),
+                        raw_text: result.submit_result.clone().unwrap_or_default(),
+                        attempt: 1,
+                    }),
+                };
+                let output = result.submit_result.unwrap_or(result.stdout);
+                (item, output, typed)
+            }
+            Err(e) => (
+                item,
+                String::new(),
+                Err(ExtractionError::AgentError(e.to_string())),
+            ),
+        }
+    }
+}
+
+/// Folds per-item outcomes into a [`BatchExtraction`], leaving `wall_time` unset.
+#[allow(clippy::result_large_err)]
+fn aggregate<T>(outcomes: Vec<ItemOutcome<T>>) -> BatchExtraction<T> {
+    let mut metrics = ExtractionMetrics::default();
+    let results = outcomes
+        .into_iter()
+        .map(|(prompt, output, result)| {
+            metrics.total_attempts += 1;
+            metrics.estimated_input_tokens += estimate_tokens(&prompt);
+            metrics.estimated_output_tokens += estimate_tokens(&output);
+            metrics.record_usage(None, &prompt, &output);
+            result
+        })
+        .collect();
+
+    BatchExtraction { results, metrics }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate_keeps_order_and_sums_metrics() {
+        let batch = aggregate(vec![
+            ("abcdefgh".to_string(), "{\"n\":1}".to_string(), Ok(1)),
+            (
+                "abcd".to_string(),
+                String::new(),
+                Err(ExtractionError::AgentError("timed out".to_string())),
+            ),
+            ("abcd".to_string(), "abcd".to_string(), Ok(3)),
+        ]);
+
+        assert_eq!(batch.succeeded(), 2);
+        assert_eq!(batch.results[0].as_ref().unwrap(), &1);
+        assert_eq!(batch.results[1].as_ref().unwrap_err().error_code(), "agent");
+        assert_eq!(batch.results[2].as_ref().unwrap(), &3);
+
+        assert_eq!(batch.metrics.total_attempts, 3);
+        assert_eq!(batch.metrics.estimated_input_tokens, 4);
+        assert_eq!(batch.metrics.estimated_output_tokens, 3);
+        assert_eq!(batch.metrics.input_tokens, 4);
+        assert_eq!(batch.metrics.output_tokens, 3);
+        assert!(!batch.metrics.usage_is_exact());
+    }
+}
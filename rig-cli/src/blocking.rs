@@ -0,0 +1,32 @@
+//! Synchronous client entry points, with the `blocking` feature.
+//!
+//! Each provider client gets `new_blocking` and `prompt_blocking`, which run
+//! their async counterparts on a current-thread runtime built for the call,
+//! so synchronous applications and FFI layers need no Tokio runtime of their
+//! own:
+//!
+//! ```ignore
+//! let client = rig_cli::claude::Client::new_blocking()?;
+//! let answer = client.prompt_blocking("Summarize this file")?;
+//! ```
+//!
+//! Calling them from inside a Tokio runtime fails with
+//! [`Error::ExecutionFailed`]; await the async methods there instead.
+
+use crate::errors::Error;
+use rig::completion::{Prompt, PromptError};
+use std::future::Future;
+
+/// Runs `future` to completion on a runtime of its own.
+pub fn block_on<F: Future>(future: F) -> Result<F::Output, Error> {
+    rig_cli_process_core::blocking::block_on(future)
+        .map_err(|e| Error::ExecutionFailed(format!("blocking runtime: {e}")))
+}
+
+/// Sends one prompt through `agent` and waits for the reply.
+pub fn prompt(agent: &impl Prompt, prompt: &str) -> Result<String, Error> {
+    block_on(async { agent.prompt(prompt).await })?.map_err(|e| match e {
+        PromptError::CompletionError(e) => Error::Completion(e),
+        other => Error::ExecutionFailed(other.to_string()),
+    })
+}
@@ -0,0 +1,329 @@
+//! Response caching for direct CLI completions.
+//!
+//! A [`CacheLayer`] set on [`ClientConfig::cache`](crate::config::ClientConfig::cache)
+//! short-circuits `completion()` calls whose inputs match an earlier
+//! successful call: the same model, system prompt, chat history (whitespace
+//! normalized), tool schemas, and payload. Entries live in an in-memory LRU
+//! and, optionally, in a [`CacheBackend`] such as [`DiskCache`] so they
+//! survive restarts. This makes idempotent extraction pipelines and test
+//! suites cheap to re-run.
+//!
+//! Streaming calls and MCP agents are never cached.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rig_cli::cache::{CacheLayer, DiskCache};
+//! # use rig_cli::config::ClientConfig;
+//! # use std::time::Duration;
+//! let config = ClientConfig {
+//!     cache: Some(
+//!         CacheLayer::new(256, Duration::from_secs(3600))
+//!             .with_backend(DiskCache::new(".rig-cache")),
+//!     ),
+//!     ..ClientConfig::default()
+//! };
+//! ```
+
+use crate::response::CliResponse;
+use rig::completion::{message::AssistantContent, CompletionRequest, CompletionResponse, Usage};
+use rig::OneOrMany;
+use rig_cli_process_core::hash::fnv1a_128;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A cached response and when it was stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// The cached CLI response.
+    pub response: CliResponse,
+    /// Seconds since the Unix epoch at which the entry was stored.
+    pub stored_at: u64,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        now_secs().saturating_sub(self.stored_at) <= ttl.as_secs()
+    }
+}
+
+/// Persistent storage behind the in-memory cache.
+///
+/// Implementations should treat I/O failures as cache misses rather than
+/// errors; a broken cache must never fail a completion.
+pub trait CacheBackend: Send + Sync {
+    /// Returns the entry stored under `key`, if any.
+    fn get(&self, key: &str) -> Option<CacheEntry>;
+
+    /// Stores `entry` under `key`, replacing any previous entry.
+    fn put(&self, key: &str, entry: &CacheEntry);
+}
+
+/// [`CacheBackend`] that stores one JSON file per entry in a directory.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Creates a disk cache rooted at `dir`. The directory is created on first write.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+impl CacheBackend for DiskCache {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        let contents = std::fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    fn put(&self, key: &str, entry: &CacheEntry) {
+        let write = std::fs::create_dir_all(&self.dir).and_then(|()| {
+            let json = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+            std::fs::write(self.path(key), json)
+        });
+        if let Err(e) = write {
+            tracing::warn!(dir = %self.dir.display(), error = %e, "failed to write cache entry");
+        }
+    }
+}
+
+/// Least-recently-used map of cache entries.
+struct MemoryLru {
+    capacity: usize,
+    entries: HashMap<String, CacheEntry>,
+    order: VecDeque<String>,
+}
+
+impl MemoryLru {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            if let Some(k) = self.order.remove(pos) {
+                self.order.push_back(k);
+            }
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<CacheEntry> {
+        let entry = self.entries.get(key).cloned()?;
+        self.touch(key);
+        Some(entry)
+    }
+
+    fn put(&mut self, key: &str, entry: CacheEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key.to_string(), entry).is_some() {
+            self.touch(key);
+            return;
+        }
+        self.order.push_back(key.to_string());
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if self.entries.remove(key).is_some() {
+            self.order.retain(|k| k != key);
+        }
+    }
+}
+
+/// Response cache shared by every model created from a client.
+///
+/// Cloning is cheap; clones share the same entries.
+#[derive(Clone)]
+pub struct CacheLayer {
+    ttl: Duration,
+    memory: Arc<Mutex<MemoryLru>>,
+    backend: Option<Arc<dyn CacheBackend>>,
+}
+
+impl std::fmt::Debug for CacheLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheLayer")
+            .field("ttl", &self.ttl)
+            .field("backend", &self.backend.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CacheLayer {
+    /// Creates an in-memory cache holding up to `capacity` responses for `ttl` each.
+    #[must_use]
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            ttl,
+            memory: Arc::new(Mutex::new(MemoryLru::new(capacity))),
+            backend: None,
+        }
+    }
+
+    /// Adds a persistent backend consulted on in-memory misses.
+    #[must_use]
+    pub fn with_backend(mut self, backend: impl CacheBackend + 'static) -> Self {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Returns the cached response for `key` if it is within the TTL.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<CliResponse> {
+        {
+            let mut memory = self.memory.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(entry) = memory.get(key) {
+                if entry.is_fresh(self.ttl) {
+                    return Some(entry.response);
+                }
+                memory.remove(key);
+            }
+        }
+
+        // The backend may do I/O, so it is read without holding the lock.
+        let entry = self.backend.as_ref()?.get(key)?;
+        if !entry.is_fresh(self.ttl) {
+            return None;
+        }
+        let response = entry.response.clone();
+        self.memory
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .put(key, entry);
+        Some(response)
+    }
+
+    /// Stores `response` under `key` in memory and in the backend.
+    pub fn put(&self, key: &str, response: &CliResponse) {
+        let entry = CacheEntry {
+            response: response.clone(),
+            stored_at: now_secs(),
+        };
+        if let Some(ref backend) = self.backend {
+            backend.put(key, &entry);
+        }
+        self.memory
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .put(key, entry);
+    }
+
+    /// Computes the cache key for a completion request.
+    ///
+    /// The key is a stable 128-bit FNV-1a hash (hex encoded) of the model,
+    /// system prompt, whitespace-normalized chat history, tool schemas, and
+    /// payload, so it is safe to persist across processes.
+    #[must_use]
+    pub fn key(model: &str, request: &CompletionRequest, payload: Option<&str>) -> String {
+        let history = rig_cli_provider::utils::format_chat_history(request);
+        let normalized = history.split_whitespace().collect::<Vec<_>>().join(" ");
+        let tools = serde_json::to_string(&request.tools).unwrap_or_default();
+
+        let parts = [
+            model,
+            request.preamble.as_deref().unwrap_or(""),
+            &normalized,
+            &tools,
+            payload.unwrap_or(""),
+        ];
+        format!("{:032x}", hash_parts(&parts))
+    }
+}
+
+/// Builds the completion response returned for a cache hit.
+pub(crate) fn completion_response(response: CliResponse) -> CompletionResponse<CliResponse> {
+    CompletionResponse {
+        choice: OneOrMany::one(AssistantContent::text(response.text.clone())),
+        usage: Usage::default(),
+        raw_response: response,
+    }
+}
+
+/// 128-bit FNV-1a over `parts`, each terminated by a NUL separator.
+fn hash_parts(parts: &[&str]) -> u128 {
+    let bytes: Vec<u8> = parts
+        .iter()
+        .flat_map(|part| part.bytes().chain(std::iter::once(0)))
+        .collect();
+    fnv1a_128(&bytes)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn response(text: &str) -> CliResponse {
+        CliResponse::from_run_result(text.to_string(), String::new(), 0, 10)
+    }
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let cache = CacheLayer::new(2, Duration::from_secs(60));
+        cache.put("a", &response("a"));
+        cache.put("b", &response("b"));
+        assert!(cache.get("a").is_some());
+
+        cache.put("c", &response("c"));
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.get("a").unwrap().text, "a");
+        assert_eq!(cache.get("c").unwrap().text, "c");
+    }
+
+    #[test]
+    fn test_expired_entries_are_misses() {
+        let cache = CacheLayer::new(4, Duration::from_secs(60));
+        cache.memory.lock().unwrap().put(
+            "old",
+            CacheEntry {
+                response: response("stale"),
+                stored_at: now_secs() - 120,
+            },
+        );
+        assert!(cache.get("old").is_none());
+    }
+
+    #[test]
+    fn test_disk_backend_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache =
+            CacheLayer::new(4, Duration::from_secs(60)).with_backend(DiskCache::new(dir.path()));
+        cache.put("k", &response("persisted"));
+
+        let fresh =
+            CacheLayer::new(4, Duration::from_secs(60)).with_backend(DiskCache::new(dir.path()));
+        assert_eq!(fresh.get("k").unwrap().text, "persisted");
+    }
+
+    #[test]
+    fn test_fnv_is_stable_and_separates_parts() {
+        assert_eq!(hash_parts(&["ab", "c"]), hash_parts(&["ab", "c"]));
+        assert_ne!(hash_parts(&["ab", "c"]), hash_parts(&["a", "bc"]));
+    }
+}
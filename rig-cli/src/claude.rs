@@ -39,22 +39,51 @@
 //!
 //! For MCP enforcement, the agent is constrained to submit responses ONLY via
 //! MCP tool calls, preventing freeform text responses and ensuring schema compliance.
+//!
+//! # Continuing a Session
+//!
+//! Every completion reports the CLI session it ran in as
+//! [`CliResponse::session_id`]. [`Client::resume_agent`] builds an agent whose
+//! prompts continue that session, so earlier turns need not be replayed in
+//! the prompt.
+//!
+//! # Subagents
+//!
+//! [`Client::with_agents`] defines specialized subagents the model may
+//! delegate to. Delegations stream as `Task` tool calls.
+//!
+//! # Partial Messages
+//!
+//! [`Client::with_partial_messages`] streams text as the model generates it
+//! instead of one message at a time.
+//!
+//! # Permission Modes
+//!
+//! [`Client::with_permission_mode`] sets whether the agent may edit files
+//! without asking. Bypassing permission checks altogether must first be
+//! acknowledged with [`Client::i_understand_the_risk`].
 
-use crate::config::ClientConfig;
+use crate::batch::{BatchExtraction, BatchRun};
+use crate::cache::CacheLayer;
+use crate::config::{ClientConfig, TimeoutBehavior};
 use crate::errors::Error;
+use crate::extractor::McpExtractorBuilder;
 use crate::response::CliResponse;
-use futures::StreamExt;
 use rig::completion::{
     message::AssistantContent, CompletionError, CompletionModel, CompletionRequest,
     CompletionResponse, Usage,
 };
-use rig::streaming::{RawStreamingChoice, RawStreamingToolCall, StreamingCompletionResponse};
+use rig::streaming::StreamingCompletionResponse;
 use rig::OneOrMany;
 use rig_cli_claude;
 use rig_cli_provider::mcp_agent::{CliAdapter, CliAgentBuilder};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
 use std::time::Instant;
-use tokio_stream::wrappers::ReceiverStream;
-use uuid::Uuid;
+
+pub use rig_cli_claude::{AgentDefinition, AgentsConfig, PermissionMode};
 
 /// Claude Code provider client.
 ///
@@ -85,18 +114,31 @@ pub struct Client {
     config: ClientConfig,
     /// Optional payload data for context injection.
     payload: Option<String>,
+    /// Image files shown to the model with every prompt.
+    images: Vec<PathBuf>,
+    /// Subagents prompts may delegate to.
+    agents: rig_cli_claude::AgentsConfig,
+    /// Whether streams carry partial text deltas.
+    partial_messages: bool,
+    /// Permission mode direct CLI runs start in.
+    permission_mode: Option<PermissionMode>,
+    /// Whether [`PermissionMode::BypassPermissions`] was acknowledged.
+    bypass_acknowledged: bool,
 }
 
 impl Client {
     /// Creates a new Claude Code client with auto-discovery.
     ///
     /// Discovers the Claude CLI binary and validates it's executable.
-    /// Uses default configuration (300s timeout, 100 message channel capacity).
+    /// Uses default configuration (300s timeout, 100 message channel capacity),
+    /// with the profile named by
+    /// [`PROFILE_ENV_VAR`](crate::config::PROFILE_ENV_VAR) applied if set.
     ///
     /// # Errors
     ///
     /// Returns `Error::ClaudeNotFound` if the CLI binary cannot be found,
-    /// or `Error::Provider` if initialization fails.
+    /// `Error::Provider` if initialization fails, or `Error::Config` if the
+    /// environment names an unknown profile.
     ///
     /// # Example
     ///
@@ -108,7 +150,31 @@ impl Client {
     /// # }
     /// ```
     pub async fn new() -> Result<Self, Error> {
-        Self::from_config(ClientConfig::default()).await
+        Self::from_config(ClientConfig::from_env()?).await
+    }
+
+    /// Synchronous [`new`](Self::new), with the `blocking` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`new`](Self::new), and `Error::ExecutionFailed`
+    /// if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn new_blocking() -> Result<Self, Error> {
+        crate::blocking::block_on(Self::new())?
+    }
+
+    /// Sends `prompt` through a default agent and waits for the reply, with
+    /// the `blocking` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Completion` if the CLI run fails, and
+    /// `Error::ExecutionFailed` if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn prompt_blocking(&self, prompt: &str) -> Result<String, Error> {
+        let agent = rig::agent::AgentBuilder::new(Model::make(self, String::new())).build();
+        crate::blocking::prompt(&agent, prompt)
     }
 
     /// Creates a new Claude Code client with custom configuration.
@@ -142,12 +208,19 @@ impl Client {
             .await
             .map_err(|_| Error::ClaudeNotFound)?;
 
-        let cli = rig_cli_claude::ClaudeCli::new(report.claude_path, report.capabilities);
+        let version = report.cli_version();
+        let cli = rig_cli_claude::ClaudeCli::new(report.claude_path, report.capabilities)
+            .with_version(version);
 
         Ok(Self {
             cli,
             config,
             payload: None,
+            images: Vec::new(),
+            agents: rig_cli_claude::AgentsConfig::default(),
+            partial_messages: false,
+            permission_mode: None,
+            bypass_acknowledged: false,
         })
     }
 
@@ -176,6 +249,135 @@ impl Client {
         self
     }
 
+    /// Shows the model image files with every prompt, e.g. screenshots to
+    /// extract structured data from.
+    ///
+    /// The images go to agents, [`mcp_agent`](Self::mcp_agent) runs and
+    /// [`mcp_extractor`](Self::mcp_extractor)s alike. Claude Code views them
+    /// through its `Read` tool, which is granted for the run; see
+    /// [`rig_cli_claude::RunConfig::images`]. Completions with images bypass
+    /// the response cache.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rig_cli::claude::Client;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new().await?.with_images(["receipt.png"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_images<I, P>(mut self, images: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.images = images.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Defines subagents the model may delegate to on the direct CLI path.
+    ///
+    /// The definitions are passed with `--agents`. Streams report each
+    /// delegation as a `Task` tool call; the subagent's own output is not
+    /// part of the answer. Completions with subagents bypass the response
+    /// cache.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rig_cli::claude::{AgentDefinition, AgentsConfig, Client};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new().await?.with_agents(AgentsConfig::new().agent(
+    ///     "reviewer",
+    ///     AgentDefinition::new("Reviews diffs for bugs", "You are a careful reviewer.")
+    ///         .tools(["Read", "Grep"]),
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_agents(mut self, agents: rig_cli_claude::AgentsConfig) -> Self {
+        self.agents = agents;
+        self
+    }
+
+    /// Streams text deltas as the model generates them.
+    ///
+    /// Passes `--include-partial-messages`, so streamed agents emit text in
+    /// small chunks rather than one message per assistant turn. Completions
+    /// are unaffected.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rig_cli::claude::Client;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new().await?.with_partial_messages(true);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn with_partial_messages(mut self, enabled: bool) -> Self {
+        self.partial_messages = enabled;
+        self
+    }
+
+    /// Sets the permission mode direct CLI runs start in.
+    ///
+    /// [`PermissionMode::AcceptEdits`] lets the agent edit files without
+    /// asking, and [`PermissionMode::Plan`] keeps it from changing anything.
+    /// [`PermissionMode::BypassPermissions`] skips every permission check,
+    /// so the agent may also run any command; it is refused unless
+    /// [`i_understand_the_risk`](Self::i_understand_the_risk) was called
+    /// first, and every run in it logs a warning. Completions in a
+    /// permission mode bypass the response cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Config` for [`PermissionMode::BypassPermissions`]
+    /// without the acknowledgement.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rig_cli::claude::{Client, PermissionMode};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new()
+    ///     .await?
+    ///     .i_understand_the_risk()
+    ///     .with_permission_mode(PermissionMode::BypassPermissions)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_permission_mode(mut self, mode: PermissionMode) -> Result<Self, Error> {
+        if mode == PermissionMode::BypassPermissions {
+            if !self.bypass_acknowledged {
+                return Err(Error::Config(
+                    "PermissionMode::BypassPermissions lets the agent edit files and run \
+                     commands unchecked; call i_understand_the_risk() first"
+                        .to_string(),
+                ));
+            }
+            tracing::warn!(
+                "Claude client permission checks bypassed; only run it in a sandbox the agent \
+                 cannot escape"
+            );
+        }
+        self.permission_mode = Some(mode);
+        Ok(self)
+    }
+
+    /// Acknowledges that [`PermissionMode::BypassPermissions`] lets the
+    /// agent modify the machine it runs on without confirmation, allowing
+    /// [`with_permission_mode`](Self::with_permission_mode) to set it.
+    #[must_use]
+    pub const fn i_understand_the_risk(mut self) -> Self {
+        self.bypass_acknowledged = true;
+        self
+    }
+
     /// Access the underlying CLI handle for advanced use cases.
     ///
     /// This is an escape hatch for developers who need access to adapter-specific
@@ -191,6 +393,47 @@ impl Client {
         &self.config
     }
 
+    /// Creates an agent builder whose prompts continue an earlier CLI session.
+    ///
+    /// Each prompt is sent with `--resume <session_id>`, so the CLI already
+    /// holds the earlier turns; only the new messages need to be in the
+    /// prompt. Session IDs come from [`CliResponse::session_id`]. Resumed
+    /// completions bypass the response cache.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rig_cli::claude::Client;
+    /// # use rig::client::CompletionClient;
+    /// # use rig::completion::Completion;
+    /// # use rig::completion::Prompt;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new().await?;
+    /// let first = client
+    ///     .agent("claude-sonnet-4")
+    ///     .build()
+    ///     .completion("My name is Ada.", vec![])
+    ///     .await?
+    ///     .send()
+    ///     .await?;
+    ///
+    /// if let Some(session_id) = first.raw_response.session_id {
+    ///     let agent = client.resume_agent(session_id).build();
+    ///     let answer = agent.prompt("What is my name?").await?;
+    ///     println!("{answer}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn resume_agent(&self, session_id: impl Into<String>) -> rig::agent::AgentBuilder<Model> {
+        let model = Model {
+            session: rig_cli_claude::SessionMode::Resume(session_id.into()),
+            ..Model::make(self, String::new())
+        };
+        rig::agent::AgentBuilder::new(model)
+    }
+
     /// Creates an MCP-enforced agent builder for structured extraction.
     ///
     /// Unlike [`agent()`](rig::client::CompletionClient::agent) which uses direct CLI execution,
@@ -222,17 +465,66 @@ impl Client {
     /// ```
     #[must_use]
     pub fn mcp_agent(&self, _model: impl Into<String>) -> CliAgentBuilder {
-        let mut builder = rig_cli_provider::mcp_agent::CliAgent::builder()
-            .adapter(CliAdapter::ClaudeCode)
-            .timeout(self.config.timeout);
+        let mut builder = self.config.contain(
+            rig_cli_provider::mcp_agent::CliAgent::builder()
+                .adapter(CliAdapter::ClaudeCode)
+                .timeout(self.config.timeout),
+        );
 
         // Transfer payload if set on client
         if let Some(ref payload) = self.payload {
             builder = builder.payload(payload.clone());
         }
+        for image in &self.images {
+            builder = builder.image(image);
+        }
 
         builder
     }
+
+    /// Extracts a `T` from each item with MCP-enforced runs.
+    ///
+    /// Each item is sent as the prompt of its own run, with at most
+    /// [`ClientConfig::batch_concurrency`] runs in flight. The toolkit for `T`
+    /// is built once and the client's CLI binary is reused, so nothing is
+    /// rediscovered per item. Results come back in input order; a failed item
+    /// does not stop the others. See [`crate::batch`] for the MCP server the
+    /// current executable must provide.
+    pub async fn extract_batch<T>(&self, items: Vec<String>) -> BatchExtraction<T>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        BatchRun {
+            adapter: CliAdapter::ClaudeCode,
+            cli_path: &self.cli.path,
+            config: &self.config,
+            payload: self.payload.as_deref(),
+            images: &self.images,
+            preamble: None,
+        }
+        .extract(items)
+        .await
+    }
+
+    /// Creates an extractor returning a `T` from one MCP-enforced run per
+    /// call, the MCP counterpart of Rig's `extractor::<T>()`.
+    ///
+    /// The toolkit for `T` is built once and the client's CLI binary and
+    /// payload are reused. See [`crate::extractor`] for the MCP server the
+    /// current executable must provide.
+    #[must_use]
+    pub fn mcp_extractor<T>(&self, _model: impl Into<String>) -> McpExtractorBuilder<T>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        McpExtractorBuilder::new(
+            CliAdapter::ClaudeCode,
+            self.cli.path.clone(),
+            self.config.clone(),
+            self.payload.clone(),
+        )
+        .images(self.images.clone())
+    }
 }
 
 impl rig::client::CompletionClient for Client {
@@ -259,15 +551,26 @@ pub struct Model {
     config: ClientConfig,
     /// Optional payload for context injection.
     payload: Option<String>,
-    /// Model identifier (stored for API consistency, CLI agents don't use per-request model selection).
+    /// Image files shown to the model with every prompt.
+    images: Vec<PathBuf>,
+    /// CLI session prompts run in.
+    session: rig_cli_claude::SessionMode,
+    /// Subagents prompts may delegate to.
+    agents: rig_cli_claude::AgentsConfig,
+    /// Whether streams carry partial text deltas.
+    partial_messages: bool,
+    /// Permission mode runs start in.
+    permission_mode: Option<PermissionMode>,
+    /// Model identifier. CLI agents don't use per-request model selection;
+    /// it keys the response cache.
     // Matches Rig's CompletionModel pattern where Model has a model identifier field
-    #[allow(dead_code, clippy::struct_field_names)]
+    #[allow(clippy::struct_field_names)]
     model_name: String,
 }
 
 impl CompletionModel for Model {
     type Response = CliResponse;
-    type StreamingResponse = ();
+    type StreamingResponse = CliResponse;
     type Client = Client;
 
     fn make(client: &Self::Client, model: impl Into<String>) -> Self {
@@ -275,6 +578,11 @@ impl CompletionModel for Model {
             cli: client.cli.clone(),
             config: client.config.clone(),
             payload: client.payload.clone(),
+            images: client.images.clone(),
+            session: rig_cli_claude::SessionMode::New,
+            agents: client.agents.clone(),
+            partial_messages: client.partial_messages,
+            permission_mode: client.permission_mode,
             model_name: model.into(),
         }
     }
@@ -283,6 +591,32 @@ impl CompletionModel for Model {
         &self,
         request: CompletionRequest,
     ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        // A resumed session's reply depends on history the cache key cannot
+        // see, a reply with subagents on their definitions, one with images
+        // on their contents, and one in a permission mode on the edits the
+        // run makes.
+        let cached = self
+            .config
+            .cache
+            .as_ref()
+            .filter(|_| {
+                self.session == rig_cli_claude::SessionMode::New
+                    && self.agents.is_empty()
+                    && self.images.is_empty()
+                    && self.permission_mode.is_none()
+            })
+            .map(|cache| {
+                let key = CacheLayer::key(&self.model_name, &request, self.payload.as_deref());
+                (cache, key)
+            });
+        if let Some((cache, ref key)) = cached {
+            if let Some(hit) = cache.get(key) {
+                return Ok(crate::cache::completion_response(hit));
+            }
+        }
+
+        self.config.charge_run(CliAdapter::ClaudeCode)?;
+
         // Extract prompt from chat history using the utility function
         let prompt_text = rig_cli_provider::utils::format_chat_history(&request);
 
@@ -307,8 +641,24 @@ impl CompletionModel for Model {
         // Direct CLI execution path
         let start = Instant::now();
 
+        // Stream-JSON carries the answer, session ID, model, and usage, keeps
+        // every event for the raw response, and still holds the answer so far
+        // if the run times out.
+        let return_partial = self.config.on_timeout == TimeoutBehavior::ReturnPartial;
         let mut config = rig_cli_claude::RunConfig {
+            model: self.config.model(CliAdapter::ClaudeCode),
+            output_format: Some(rig_cli_claude::OutputFormat::StreamJson),
             timeout: self.config.timeout,
+            output_limits: (&self.config).into(),
+            session: self.session.clone(),
+            agents: self.agents.clone(),
+            env_policy: self.config.env_policy.clone(),
+            extra_args: self.config.extra_args.clone(),
+            forward_signals: self.config.forward_signals,
+            preamble_cache_dir: self.config.preamble_cache_dir.clone(),
+            max_turns: self.config.max_turns,
+            permission_mode: self.permission_mode,
+            images: self.images.clone(),
             ..rig_cli_claude::RunConfig::default()
         };
 
@@ -318,11 +668,20 @@ impl CompletionModel for Model {
         }
 
         // Run the CLI
-        let result = self
-            .cli
-            .run(&final_prompt, &config)
-            .await
-            .map_err(|e| {
+        let result = match self.cli.run(&final_prompt, &config).await {
+            Err(rig_cli_claude::ClaudeError::Timeout { partial_stdout, .. }) if return_partial => {
+                let text =
+                    rig_cli_claude::partial_result_text(&partial_stdout).unwrap_or_default();
+                let duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+                return Ok(CompletionResponse {
+                    choice: OneOrMany::one(AssistantContent::text(text.clone())),
+                    usage: Usage::default(),
+                    raw_response: CliResponse::from_partial(text, duration_ms),
+                });
+            }
+            result => result,
+        }
+        .map_err(|e| {
                 #[cfg(feature = "debug-output")]
                 {
                     CompletionError::ProviderError(format!("{e}\n--- raw debug output ---\nError occurred during CLI execution. Enable tracing for detailed output."))
@@ -335,11 +694,19 @@ impl CompletionModel for Model {
 
         let duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
 
-        let cli_response =
-            CliResponse::from_run_result(result.stdout.clone(), result.exit_code, duration_ms);
+        let cli_response = cli_response(result, duration_ms);
+        let text = cli_response.text.clone();
+        self.config
+            .record_tokens(CliAdapter::ClaudeCode, cli_response.usage);
+
+        if let Some((cache, ref key)) = cached {
+            if cli_response.exit_code == 0 {
+                cache.put(key, &cli_response);
+            }
+        }
 
         Ok(CompletionResponse {
-            choice: OneOrMany::one(AssistantContent::text(result.stdout)),
+            choice: OneOrMany::one(AssistantContent::text(text)),
             usage: Usage::default(),
             raw_response: cli_response,
         })
@@ -350,6 +717,7 @@ impl CompletionModel for Model {
         request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
         // Streaming always uses direct CLI (MCP enforcement only on completion path)
+        self.config.charge_run(CliAdapter::ClaudeCode)?;
         let prompt_text = rig_cli_provider::utils::format_chat_history(&request);
 
         // If payload is set, wrap prompt in XML context structure
@@ -372,8 +740,22 @@ impl CompletionModel for Model {
         let timeout = self.config.timeout;
 
         let mut config = rig_cli_claude::RunConfig {
+            model: self.config.model(CliAdapter::ClaudeCode),
             output_format: Some(rig_cli_claude::OutputFormat::StreamJson),
             timeout,
+            output_limits: (&self.config).into(),
+            backpressure: self.config.backpressure.into(),
+            session: self.session.clone(),
+            agents: self.agents.clone(),
+            env_policy: self.config.env_policy.clone(),
+            extra_args: self.config.extra_args.clone(),
+            forward_signals: self.config.forward_signals,
+            stderr_events: self.config.stderr_events.clone(),
+            preamble_cache_dir: self.config.preamble_cache_dir.clone(),
+            max_turns: self.config.max_turns,
+            include_partial_messages: self.partial_messages,
+            permission_mode: self.permission_mode,
+            images: self.images.clone(),
             ..rig_cli_claude::RunConfig::default()
         };
 
@@ -388,37 +770,40 @@ impl CompletionModel for Model {
             config.tools.allowed = Some(allowed_tools);
         }
 
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        let client = self.config.clone();
         tokio::spawn(async move {
-            // Error from CLI stream is intentionally dropped;
-            // the receiver will see the channel close and handle accordingly
-            let _ = cli.stream(&final_prompt, &config, tx).await;
-        });
-
-        // Convert the receiver into a stream
-        let stream = ReceiverStream::new(rx).map(|event| {
-            match event {
-                rig_cli_claude::StreamEvent::Text { text } => Ok(RawStreamingChoice::Message(text)),
-                rig_cli_claude::StreamEvent::ToolCall { name, input } => {
-                    let id = Uuid::new_v4().to_string();
-                    let tool_call = RawStreamingToolCall::new(id, name, input);
-                    Ok(RawStreamingChoice::ToolCall(tool_call))
-                }
-                rig_cli_claude::StreamEvent::ToolResult { .. } => {
-                    // For now we ignore tool results in the assistant output stream
-                    // They are usually input for the next turn
-                    // Empty message acts as a no-op heartbeat
-                    Ok(RawStreamingChoice::Message(String::new()))
-                }
-                rig_cli_claude::StreamEvent::Error { message } => {
-                    Err(CompletionError::ProviderError(message))
-                }
-                rig_cli_claude::StreamEvent::Unknown(_) => {
-                    Ok(RawStreamingChoice::Message(String::new()))
-                }
+            let result = cli.stream(&final_prompt, &config, tx).await;
+            if let Ok(ref result) = result {
+                client.record_tokens(CliAdapter::ClaudeCode, result.usage().map(Into::into));
             }
+            // The receiver is gone only if the stream was dropped
+            let _ = done_tx.send(result);
         });
 
-        Ok(StreamingCompletionResponse::stream(Box::pin(stream)))
+        Ok(crate::streaming::assemble(
+            rx,
+            done_rx,
+            self.partial_messages,
+            |result| {
+                let duration_ms = result.duration_ms;
+                cli_response(result, duration_ms)
+            },
+        ))
+    }
+}
+
+/// Builds the raw response for a finished run.
+fn cli_response(result: rig_cli_claude::RunResult, duration_ms: u64) -> CliResponse {
+    let text = result.final_text().to_string();
+    CliResponse {
+        session_id: result.session_id.clone(),
+        model: result.model(),
+        usage: result.usage().map(Into::into),
+        num_turns: result.num_turns(),
+        latency: Some(result.latency),
+        stream_events: result.stream_events,
+        ..CliResponse::from_run_result(text, result.stderr, result.exit_code, duration_ms)
     }
 }
 
@@ -433,6 +818,25 @@ mod tests {
             text: "Hello, world!".to_string(),
             exit_code: 0,
             duration_ms: 1234,
+            session_id: Some("abc".to_string()),
+            truncated: false,
+            stderr: "warning".to_string(),
+            stream_events: vec![serde_json::json!({ "type": "result", "result": "Hello" })],
+            events: vec![rig_cli_provider::events::StreamEvent::Text(
+                "Hello".to_string(),
+            )],
+            model: Some("claude-sonnet".to_string()),
+            usage: Some(crate::response::TokenUsage {
+                input_tokens: 10,
+                output_tokens: 5,
+            }),
+            num_turns: Some(2),
+            latency: Some(crate::response::LatencyBreakdown {
+                spawn_ms: 12,
+                first_token_ms: Some(900),
+                first_tool_call_ms: None,
+                total_ms: 1234,
+            }),
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -441,6 +845,65 @@ mod tests {
         assert_eq!(deserialized.text, "Hello, world!");
         assert_eq!(deserialized.exit_code, 0);
         assert_eq!(deserialized.duration_ms, 1234);
+        assert_eq!(deserialized.session_id.as_deref(), Some("abc"));
+        assert_eq!(deserialized.stderr, "warning");
+        assert_eq!(deserialized.stream_events.len(), 1);
+        assert_eq!(deserialized.events, response.events);
+        assert_eq!(deserialized.model.as_deref(), Some("claude-sonnet"));
+        assert_eq!(deserialized.usage.map(|u| u.output_tokens), Some(5));
+        assert_eq!(deserialized.num_turns, Some(2));
+        assert_eq!(deserialized.latency, response.latency);
+
+        let legacy: CliResponse =
+            serde_json::from_str(r#"{"text":"hi","exit_code":0,"duration_ms":1}"#).unwrap();
+        assert!(legacy.session_id.is_none());
+        assert!(!legacy.truncated);
+        assert!(legacy.stream_events.is_empty());
+        assert!(legacy.usage.is_none());
+        assert!(legacy.num_turns.is_none());
+        assert!(legacy.latency.is_none());
+    }
+
+    #[test]
+    fn test_bypass_permissions_requires_acknowledgement() {
+        let client = Client {
+            cli: rig_cli_claude::ClaudeCli::new(
+                "claude".into(),
+                rig_cli_claude::Capabilities {
+                    features: std::collections::BTreeSet::new(),
+                },
+            ),
+            config: ClientConfig::default(),
+            payload: None,
+            images: Vec::new(),
+            agents: rig_cli_claude::AgentsConfig::default(),
+            partial_messages: false,
+            permission_mode: None,
+            bypass_acknowledged: false,
+        };
+
+        let client = client
+            .with_permission_mode(PermissionMode::AcceptEdits)
+            .unwrap();
+        assert_eq!(client.permission_mode, Some(PermissionMode::AcceptEdits));
+
+        let err = client
+            .clone()
+            .with_permission_mode(PermissionMode::BypassPermissions)
+            .err()
+            .unwrap();
+        assert_eq!(err.error_code(), "config");
+        assert!(err.to_string().contains("i_understand_the_risk"), "{err}");
+
+        let client = client
+            .i_understand_the_risk()
+            .with_permission_mode(PermissionMode::BypassPermissions)
+            .unwrap();
+        let model = Model::make(&client, "claude-sonnet");
+        assert_eq!(
+            model.permission_mode,
+            Some(PermissionMode::BypassPermissions)
+        );
     }
 
     #[test]
@@ -449,5 +912,26 @@ mod tests {
         assert!(config.binary_path.is_none());
         assert_eq!(config.timeout.as_secs(), 300);
         assert_eq!(config.channel_capacity, 100);
+        assert_eq!(config.max_output_bytes, 10 * 1024 * 1024);
+        assert!(config.max_output_lines.is_none());
+        assert_eq!(config.on_timeout, crate::config::TimeoutBehavior::Error);
+        assert_eq!(config.batch_concurrency, 4);
+    }
+
+    #[test]
+    fn test_client_config_maps_to_output_limits() {
+        let config = ClientConfig {
+            max_output_lines: Some(50),
+            overflow_policy: crate::config::OverflowPolicy::TruncateHead,
+            ..ClientConfig::default()
+        };
+        let limits = rig_cli_claude::OutputLimits::from(&config);
+        assert_eq!(limits.max_bytes, config.max_output_bytes);
+        assert_eq!(limits.max_lines, Some(50));
+        assert_eq!(limits.channel_capacity, 100);
+        assert_eq!(
+            limits.overflow,
+            rig_cli_claude::OverflowPolicy::TruncateHead
+        );
     }
 }
@@ -19,21 +19,33 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # Workspace access
+//!
+//! [`Client::with_workspace`] sets the directory Codex runs in and grants it
+//! write access to further roots, such as a repository checkout.
 
-use crate::config::ClientConfig;
+use crate::batch::{BatchExtraction, BatchRun};
+use crate::cache::CacheLayer;
+use crate::config::{ClientConfig, TimeoutBehavior};
 use crate::errors::Error;
+use crate::extractor::McpExtractorBuilder;
 use crate::response::CliResponse;
-use futures::StreamExt;
 use rig::completion::{
     message::AssistantContent, CompletionError, CompletionModel, CompletionRequest,
     CompletionResponse, Usage,
 };
-use rig::streaming::{RawStreamingChoice, StreamingCompletionResponse};
+use rig::streaming::StreamingCompletionResponse;
 use rig::OneOrMany;
 use rig_cli_codex::{discover_codex, CodexCli, CodexConfig};
+use rig_cli_mcp::extraction::ExtractionError;
 use rig_cli_provider::mcp_agent::{CliAdapter, CliAgentBuilder};
 use rig_cli_provider::utils::format_chat_history;
-use tokio_stream::wrappers::ReceiverStream;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Instant;
 
 /// Codex CLI provider client.
 ///
@@ -44,6 +56,72 @@ pub struct Client {
     cli: CodexCli,
     config: ClientConfig,
     payload: Option<String>,
+    workspace: Workspace,
+}
+
+/// Directories a Codex run works in and may write to.
+///
+/// The default leaves Codex in the current directory under the sandbox of
+/// [`ClientConfig::sandbox`].
+///
+/// ```
+/// # use rig_cli::codex::Workspace;
+/// let workspace = Workspace::new()
+///     .cd("/src/my-repo")
+///     .writable_root("/src/shared-fixtures")
+///     .skip_git_repo_check(true);
+/// assert_eq!(workspace.writable_roots.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Workspace {
+    /// Working directory for the run (`--cd`).
+    pub cd: Option<PathBuf>,
+    /// Directories writable alongside the working directory (`--add-dir`).
+    ///
+    /// When non-empty, runs use the `workspace-write` sandbox, which is the
+    /// mode these roots apply to.
+    pub writable_roots: Vec<PathBuf>,
+    /// Allow running outside a git repository (`--skip-git-repo-check`).
+    pub skip_git_repo_check: bool,
+}
+
+impl Workspace {
+    /// Creates a workspace with no overrides.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the working directory.
+    #[must_use]
+    pub fn cd(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cd = Some(dir.into());
+        self
+    }
+
+    /// Adds a directory Codex may write to.
+    #[must_use]
+    pub fn writable_root(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.writable_roots.push(dir.into());
+        self
+    }
+
+    /// Sets whether Codex may run outside a git repository.
+    #[must_use]
+    pub const fn skip_git_repo_check(mut self, skip: bool) -> Self {
+        self.skip_git_repo_check = skip;
+        self
+    }
+
+    /// Applies the workspace to a run config.
+    fn apply(&self, config: &mut CodexConfig) {
+        config.cd.clone_from(&self.cd);
+        config.add_dirs.clone_from(&self.writable_roots);
+        config.skip_git_repo_check = self.skip_git_repo_check;
+        if !self.writable_roots.is_empty() {
+            config.sandbox = Some(rig_cli_codex::SandboxMode::WorkspaceWrite);
+        }
+    }
 }
 
 impl Client {
@@ -56,18 +134,34 @@ impl Client {
     ///
     /// Returns `Error::CodexNotFound` if the CLI binary cannot be found.
     /// Returns `Error::Provider` if the health check fails.
+    /// Returns `Error::Config` if [`PROFILE_ENV_VAR`](crate::config::PROFILE_ENV_VAR)
+    /// names an unknown profile.
     pub async fn new() -> Result<Self, Error> {
-        let path = discover_codex(None).map_err(|_| Error::CodexNotFound)?;
-        let cli = CodexCli::new(path);
-        cli.check_health()
-            .await
-            .map_err(|e| Error::Provider(e.into()))?;
+        Self::from_config(ClientConfig::from_env()?).await
+    }
 
-        Ok(Self {
-            cli,
-            config: ClientConfig::default(),
-            payload: None,
-        })
+    /// Synchronous [`new`](Self::new), with the `blocking` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`new`](Self::new), and `Error::ExecutionFailed`
+    /// if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn new_blocking() -> Result<Self, Error> {
+        crate::blocking::block_on(Self::new())?
+    }
+
+    /// Sends `prompt` through a default agent and waits for the reply, with
+    /// the `blocking` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Completion` if the CLI run fails, and
+    /// `Error::ExecutionFailed` if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn prompt_blocking(&self, prompt: &str) -> Result<String, Error> {
+        let agent = rig::agent::AgentBuilder::new(Model::make(self, String::new())).build();
+        crate::blocking::prompt(&agent, prompt)
     }
 
     /// Creates a new Codex client from the given configuration.
@@ -79,17 +173,25 @@ impl Client {
     ///
     /// Returns `Error::CodexNotFound` if the CLI binary cannot be found.
     /// Returns `Error::Provider` if the health check fails.
+    ///
+    /// The CLI's help text is probed for the flags it supports, and runs
+    /// needing a missing one fail before spawning; if the probe fails, every
+    /// flag is passed unchecked.
     pub async fn from_config(config: ClientConfig) -> Result<Self, Error> {
         let path = discover_codex(config.binary_path.clone()).map_err(|_| Error::CodexNotFound)?;
-        let cli = CodexCli::new(path);
+        let mut cli = CodexCli::new(path);
         cli.check_health()
             .await
             .map_err(|e| Error::Provider(e.into()))?;
+        if let Ok(capabilities) = cli.probe_capabilities().await {
+            cli = cli.with_capabilities(capabilities);
+        }
 
         Ok(Self {
             cli,
             config,
             payload: None,
+            workspace: Workspace::default(),
         })
     }
 
@@ -103,6 +205,29 @@ impl Client {
         self
     }
 
+    /// Sets the directories direct CLI runs work in and may write to.
+    ///
+    /// MCP agents also start in [`Workspace::cd`]. Completions with a
+    /// workspace set bypass the response cache, whose keys do not include
+    /// it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rig_cli::codex::{Client, Workspace};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new()
+    ///     .await?
+    ///     .with_workspace(Workspace::new().cd("/src/my-repo").writable_root("/src/my-repo"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_workspace(mut self, workspace: Workspace) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
     /// Access the underlying CLI handle for advanced use cases.
     ///
     /// This is an escape hatch for developers who need access to adapter-specific
@@ -123,16 +248,117 @@ impl Client {
     /// See module docs for the difference between `agent()` and `mcp_agent()`.
     #[must_use]
     pub fn mcp_agent(&self, _model: impl Into<String>) -> CliAgentBuilder {
-        let mut builder = rig_cli_provider::mcp_agent::CliAgent::builder()
-            .adapter(CliAdapter::Codex)
-            .timeout(self.config.timeout);
+        let mut builder = self.config.contain(
+            rig_cli_provider::mcp_agent::CliAgent::builder()
+                .adapter(CliAdapter::Codex)
+                .timeout(self.config.timeout),
+        );
 
         if let Some(ref payload) = self.payload {
             builder = builder.payload(payload.clone());
         }
+        if let Some(ref dir) = self.workspace.cd {
+            builder = builder.working_dir(dir.clone());
+        }
 
         builder
     }
+
+    /// Extracts a `T` from each item with MCP-enforced runs.
+    ///
+    /// Items run concurrently, up to [`ClientConfig::batch_concurrency`], and
+    /// results come back in input order. See [`crate::batch`] for the MCP
+    /// server the current executable must provide.
+    pub async fn extract_batch<T>(&self, items: Vec<String>) -> BatchExtraction<T>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        BatchRun {
+            adapter: CliAdapter::Codex,
+            cli_path: &self.cli.path,
+            config: &self.config,
+            payload: self.payload.as_deref(),
+            images: &[],
+            preamble: None,
+        }
+        .extract(items)
+        .await
+    }
+
+    /// Creates an extractor returning a `T` from one MCP-enforced run per
+    /// call, the MCP counterpart of Rig's `extractor::<T>()`.
+    ///
+    /// The toolkit for `T` is built once and the client's CLI binary and
+    /// payload are reused. See [`crate::extractor`] for the MCP server the
+    /// current executable must provide.
+    #[must_use]
+    pub fn mcp_extractor<T>(&self, _model: impl Into<String>) -> McpExtractorBuilder<T>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        McpExtractorBuilder::new(
+            CliAdapter::Codex,
+            self.cli.path.clone(),
+            self.config.clone(),
+            self.payload.clone(),
+        )
+    }
+
+    /// Extracts a `T` from a single prompt.
+    ///
+    /// When the CLI accepts `--output-schema`, the run's final message is
+    /// constrained to the schema of `T` directly, as `--json-schema` does for
+    /// Claude. `OpenAI`'s strict structured outputs reject objects that allow
+    /// unknown fields, so derive `T` with `#[serde(deny_unknown_fields)]`.
+    /// Older CLIs fall back to an MCP-enforced run, as in
+    /// [`extract_batch`](Self::extract_batch).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExtractionError::AgentError` if the run fails or the run
+    /// budget is spent, and `ExtractionError::ParseError` if the final
+    /// message does not deserialize into `T`.
+    #[allow(clippy::result_large_err)]
+    pub async fn extract<T>(&self, prompt: impl Into<String>) -> Result<T, ExtractionError>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let prompt = prompt.into();
+        if !self.cli.supports_output_schema().await {
+            let mut batch = self.extract_batch(vec![prompt]).await;
+            return batch.results.pop().unwrap_or_else(|| {
+                Err(ExtractionError::AgentError(
+                    "batch returned no result".to_string(),
+                ))
+            });
+        }
+
+        self.config
+            .charge_run(CliAdapter::Codex)
+            .map_err(|e| ExtractionError::AgentError(e.to_string()))?;
+        let schema = serde_json::to_value(schemars::schema_for!(T))
+            .map_err(|e| ExtractionError::SchemaError(e.to_string()))?;
+        let mut config = run_config(&self.config, &self.workspace);
+        config.json_schema = Some(schema);
+
+        let result = self
+            .cli
+            .run(&wrap_payload(self.payload.as_deref(), prompt), &config)
+            .await
+            .map_err(|e| ExtractionError::AgentError(e.to_string()))?;
+        let Some(output) = result.structured_output else {
+            return Err(ExtractionError::ParseError {
+                message: "final message is not JSON".to_string(),
+                raw_text: result.stdout,
+                attempt: 1,
+            });
+        };
+        serde_json::from_value(output.clone()).map_err(|e| ExtractionError::ParseError {
+            message: e.to_string(),
+            raw_text: output.to_string(),
+            attempt: 1,
+        })
+    }
 }
 
 /// Codex completion model.
@@ -144,18 +370,24 @@ pub struct Model {
     cli: CodexCli,
     config: ClientConfig,
     payload: Option<String>,
+    workspace: Workspace,
+    /// Model identifier; CLI agents ignore it, but it keys the response cache.
+    #[allow(clippy::struct_field_names)]
+    model_name: String,
 }
 
 impl CompletionModel for Model {
     type Response = CliResponse;
-    type StreamingResponse = ();
+    type StreamingResponse = CliResponse;
     type Client = Client;
 
-    fn make(client: &Self::Client, _model: impl Into<String>) -> Self {
+    fn make(client: &Self::Client, model: impl Into<String>) -> Self {
         Self {
             cli: client.cli.clone(),
             config: client.config.clone(),
             payload: client.payload.clone(),
+            workspace: client.workspace.clone(),
+            model_name: model.into(),
         }
     }
 
@@ -163,38 +395,48 @@ impl CompletionModel for Model {
         &self,
         request: CompletionRequest,
     ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
-        let prompt_text = format_chat_history(&request);
+        let cached = self
+            .config
+            .cache
+            .as_ref()
+            .filter(|_| self.workspace == Workspace::default())
+            .map(|cache| {
+                let key = CacheLayer::key(&self.model_name, &request, self.payload.as_deref());
+                (cache, key)
+            });
+        if let Some((cache, ref key)) = cached {
+            if let Some(hit) = cache.get(key) {
+                return Ok(crate::cache::completion_response(hit));
+            }
+        }
 
-        // If payload is set, wrap prompt in XML context structure
-        let final_prompt = if let Some(ref payload) = self.payload {
-            format!(
-                r"<context>
-{payload}
-</context>
+        self.config.charge_run(CliAdapter::Codex)?;
+        let prompt_text = format_chat_history(&request);
 
-<task>
-{prompt_text}
-</task>"
-            )
-        } else {
-            prompt_text
-        };
+        let final_prompt = wrap_payload(self.payload.as_deref(), prompt_text);
 
-        let mut config = CodexConfig {
-            timeout: self.config.timeout,
-            ..CodexConfig::default()
-        };
+        let mut config = run_config(&self.config, &self.workspace);
 
         // Wire preamble into system_prompt if present
         if let Some(ref preamble) = request.preamble {
             config.system_prompt = Some(preamble.clone());
         }
 
-        let result = self
-            .cli
-            .run(&final_prompt, &config)
-            .await
-            .map_err(|e| {
+        let start = Instant::now();
+        let result = match self.cli.run(&final_prompt, &config).await {
+            Err(rig_cli_codex::CodexError::Timeout { partial_stdout, .. })
+                if self.config.on_timeout == TimeoutBehavior::ReturnPartial =>
+            {
+                let duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+                return Ok(CompletionResponse {
+                    choice: OneOrMany::one(AssistantContent::text(partial_stdout.clone())),
+                    usage: Usage::default(),
+                    raw_response: CliResponse::from_partial(partial_stdout, duration_ms),
+                });
+            }
+            result => result,
+        }
+        .map_err(|e| {
                 #[cfg(feature = "debug-output")]
                 {
                     CompletionError::ProviderError(format!("{e}\n--- raw debug output ---\nError occurred during CLI execution. Enable tracing for detailed output."))
@@ -205,11 +447,21 @@ impl CompletionModel for Model {
                 }
             })?;
 
-        let cli_response = CliResponse::from_run_result(
-            result.stdout.clone(),
-            result.exit_code,
-            result.duration_ms,
-        );
+        let cli_response = CliResponse {
+            latency: Some(result.latency),
+            ..CliResponse::from_run_result(
+                result.stdout.clone(),
+                result.stderr,
+                result.exit_code,
+                result.duration_ms,
+            )
+        };
+
+        if let Some((cache, ref key)) = cached {
+            if cli_response.exit_code == 0 {
+                cache.put(key, &cli_response);
+            }
+        }
 
         Ok(CompletionResponse {
             choice: OneOrMany::one(AssistantContent::text(result.stdout)),
@@ -222,55 +474,73 @@ impl CompletionModel for Model {
         &self,
         request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        self.config.charge_run(CliAdapter::Codex)?;
         let prompt_text = format_chat_history(&request);
 
-        // If payload is set, wrap prompt in XML context structure
-        let final_prompt = if let Some(ref payload) = self.payload {
-            format!(
-                r"<context>
-{payload}
-</context>
-
-<task>
-{prompt_text}
-</task>"
-            )
-        } else {
-            prompt_text
-        };
+        let final_prompt = wrap_payload(self.payload.as_deref(), prompt_text);
 
         let (tx, rx) = tokio::sync::mpsc::channel(self.config.channel_capacity);
         let cli = self.cli.clone();
 
         // Spawn the CLI process in the background
-        let mut config = CodexConfig {
-            timeout: self.config.timeout,
-            ..CodexConfig::default()
-        };
+        let mut config = run_config(&self.config, &self.workspace);
 
         // Wire preamble into system_prompt if present
         if let Some(preamble) = request.preamble {
             config.system_prompt = Some(preamble);
         }
 
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
         tokio::spawn(async move {
-            // Error from CLI stream is intentionally dropped here;
-            // the receiver will see the channel close and handle accordingly
-            let _ = cli.stream(&final_prompt, &config, tx).await;
+            // The receiver is gone only if the stream was dropped
+            let _ = done_tx.send(cli.stream(&final_prompt, &config, tx).await);
         });
 
-        // Convert the receiver into a stream
-        let stream = ReceiverStream::new(rx).map(|event| match event {
-            rig_cli_codex::StreamEvent::Text { text } => Ok(RawStreamingChoice::Message(text)),
-            rig_cli_codex::StreamEvent::Error { message } => {
-                Err(CompletionError::ProviderError(message))
-            }
-            rig_cli_codex::StreamEvent::Unknown(_) => {
-                Ok(RawStreamingChoice::Message(String::new()))
+        Ok(crate::streaming::assemble(rx, done_rx, false, |result| {
+            CliResponse {
+                latency: Some(result.latency),
+                ..CliResponse::from_run_result(
+                    result.stdout,
+                    result.stderr,
+                    result.exit_code,
+                    result.duration_ms,
+                )
             }
-        });
+        }))
+    }
+}
 
-        Ok(StreamingCompletionResponse::stream(Box::pin(stream)))
+/// Builds the config for a direct CLI run from the client settings.
+fn run_config(client: &ClientConfig, workspace: &Workspace) -> CodexConfig {
+    let mut config = CodexConfig {
+        model: client.model(CliAdapter::Codex),
+        timeout: client.timeout,
+        output_limits: client.into(),
+        backpressure: client.backpressure.into(),
+        sandbox: client.sandbox.clone(),
+        env_policy: client.env_policy.clone(),
+        extra_args: client.extra_args.clone(),
+        forward_signals: client.forward_signals,
+        stderr_events: client.stderr_events.clone(),
+        ..CodexConfig::default()
+    };
+    workspace.apply(&mut config);
+    config
+}
+
+/// Wraps `prompt` in an XML context structure when a payload is set.
+fn wrap_payload(payload: Option<&str>, prompt: String) -> String {
+    match payload {
+        Some(payload) => format!(
+            r"<context>
+{payload}
+</context>
+
+<task>
+{prompt}
+</task>"
+        ),
+        None => prompt,
     }
 }
 
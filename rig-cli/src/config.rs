@@ -1,8 +1,40 @@
 //! Shared client configuration for CLI-based providers.
+//!
+//! # Profiles
+//!
+//! A [`Profile`] bundles the containment settings (timeout, sandbox, builtin
+//! tools, environment, run budget) a team wants every client in a given
+//! environment to use. Select a built-in one with [`ClientConfig::profile`],
+//! or set [`PROFILE_ENV_VAR`] and every `Client::new()` picks it up:
+//!
+//! ```
+//! # use rig_cli::config::{ClientConfig, SandboxMode};
+//! let config = ClientConfig::profile("ci")?;
+//! assert_eq!(config.sandbox, Some(SandboxMode::ReadOnly));
+//! # Ok::<(), rig_cli::errors::Error>(())
+//! ```
 
+use crate::cache::CacheLayer;
+use crate::errors::Error;
+use crate::quota::{QuotaMiddleware, QuotaStore, QuotaUsage};
+use crate::response::TokenUsage;
+use rig::completion::CompletionError;
+use rig_cli_provider::errors::ProviderError;
+use rig_cli_provider::mcp_agent::{BackpressurePolicy, CliAdapter, CliAgentBuilder};
+use std::ffi::OsString;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
+pub use rig_cli_codex::SandboxMode;
+pub use rig_cli_process_core::{EnvPolicy, StderrPatterns};
+pub use rig_cli_provider::models::DefaultModels;
+
+/// Environment variable naming the [`Profile`] that
+/// [`ClientConfig::from_env`] (and so every `Client::new()`) applies.
+pub const PROFILE_ENV_VAR: &str = "RIG_CLI_PROFILE";
+
 /// Configuration for CLI-based provider clients.
 ///
 /// This configuration is shared across all agents created from a client,
@@ -24,8 +56,152 @@ pub struct ClientConfig {
     /// Bounded channel size for streaming responses.
     ///
     /// Controls how many messages can be buffered when streaming
-    /// CLI output. Default: 100 messages.
+    /// CLI output, and sizes the adapters' internal stdout / stderr line
+    /// channels. Default: 100 messages.
     pub channel_capacity: usize,
+
+    /// Maximum bytes captured from each of the CLI's stdout and stderr.
+    ///
+    /// Default: 10 MB.
+    pub max_output_bytes: usize,
+
+    /// Maximum lines captured from each of the CLI's stdout and stderr.
+    ///
+    /// Default: `None` (unlimited).
+    pub max_output_lines: Option<usize>,
+
+    /// What to do when captured output exceeds the limits above.
+    ///
+    /// Default: [`OverflowPolicy::Error`]. Long-running agents that log
+    /// heavily can use a truncating policy instead of failing the run.
+    pub overflow_policy: OverflowPolicy,
+
+    /// Response cache for direct CLI completions.
+    ///
+    /// Default: `None` (every call runs the CLI). See [`CacheLayer`].
+    pub cache: Option<CacheLayer>,
+
+    /// How streamed events are delivered when the consumer falls behind.
+    ///
+    /// Default: [`BackpressurePolicy::Block`], which never loses events but
+    /// pauses the CLI output reader. UIs that prefer fresh output over
+    /// completeness can drop or coalesce events instead.
+    pub backpressure: BackpressurePolicy,
+
+    /// What a completion returns when the CLI exceeds [`timeout`](Self::timeout).
+    ///
+    /// Default: [`TimeoutBehavior::Error`].
+    pub on_timeout: TimeoutBehavior,
+
+    /// Maximum number of MCP runs `extract_batch` keeps in flight.
+    ///
+    /// Each run spawns its own CLI and MCP server process. Default: 4.
+    pub batch_concurrency: usize,
+
+    /// Codex sandbox for direct runs and MCP agents.
+    ///
+    /// Default: `None`, leaving direct runs to the CLI's own default and MCP
+    /// agents read-only. Claude Code and `OpenCode` ignore it.
+    pub sandbox: Option<SandboxMode>,
+
+    /// Builtin tools MCP agents may use alongside their MCP tools.
+    ///
+    /// Default: `None` (all builtins disabled).
+    pub builtin_tools: Option<Vec<String>>,
+
+    /// Host environment variables direct CLI runs inherit.
+    ///
+    /// Default: [`EnvPolicy::Inherit`].
+    pub env_policy: EnvPolicy,
+
+    /// Arguments appended verbatim to every direct CLI run, for flags the
+    /// adapters do not support yet.
+    ///
+    /// They go to whichever CLI the client runs, so a config shared between
+    /// providers should only hold flags they all accept. A flag the adapter
+    /// already sets fails the run. Default: empty.
+    pub extra_args: Vec<OsString>,
+
+    /// Whether SIGINT and SIGTERM received by the program are passed on to
+    /// the CLI of a direct run, failing it with the adapter's `Cancelled`
+    /// error and the output so far.
+    ///
+    /// Once a run has listened for them, the signals no longer terminate the
+    /// program, which has to exit on its own. Default: `false`.
+    pub forward_signals: bool,
+
+    /// Patterns classifying the CLI's stderr lines while a direct run
+    /// streams, sending matches as [`StreamEvent::Progress`] and
+    /// [`StreamEvent::Warning`] events.
+    ///
+    /// UIs can then show a live status line without mistaking the CLI's
+    /// spinner for an error. Default: `None` (stderr is only captured).
+    ///
+    /// [`StreamEvent::Progress`]: crate::StreamEvent::Progress
+    /// [`StreamEvent::Warning`]: crate::StreamEvent::Warning
+    pub stderr_events: Option<StderrPatterns>,
+
+    /// Directory Claude Code system prompts too long for the command line are
+    /// kept in, named by a hash of their contents.
+    ///
+    /// Runs with the same long preamble then pass the CLI the same
+    /// `--append-system-prompt-file` every time, which keeps the prompt
+    /// prefix stable for provider-side prompt caching and avoids writing a
+    /// temp file per run. Files are never removed. Default: `None` (a temp
+    /// file per run). Codex and `OpenCode` ignore it.
+    pub preamble_cache_dir: Option<PathBuf>,
+
+    /// Maximum number of agentic turns a Claude Code run may take before
+    /// the CLI stops it, capping runaway tool loops independently of
+    /// [`timeout`](Self::timeout).
+    ///
+    /// The turns a run took are reported in
+    /// [`CliResponse::num_turns`](crate::response::CliResponse::num_turns).
+    /// Default: `None` (uncapped). Codex and `OpenCode` ignore it.
+    pub max_turns: Option<u32>,
+
+    /// Cap on the direct CLI runs clients with this config may start.
+    ///
+    /// Default: `None` (unlimited). Cache hits are not counted.
+    pub budget: Option<RunBudget>,
+
+    /// Store the usage of CLI runs, direct and by MCP agents, is added to
+    /// and checked against, across process restarts.
+    ///
+    /// Default: `None` (untracked). Cache hits are not counted.
+    pub quota: Option<QuotaStore>,
+
+    /// Model each CLI runs, for direct runs and MCP agents alike.
+    ///
+    /// Default: [`DefaultModels::default`]; [`from_env`](Self::from_env)
+    /// applies the `RIG_CLI_*_MODEL` overrides on top.
+    pub models: DefaultModels,
+}
+
+/// Behaviour when CLI output exceeds the [`ClientConfig`] capture limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Fail the run with an output-truncated error.
+    #[default]
+    Error,
+    /// Discard the oldest lines, keeping the most recent output.
+    TruncateHead,
+    /// Stop capturing once a limit is reached, keeping the earliest output.
+    TruncateTail,
+}
+
+/// Behaviour when a completion's CLI run times out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeoutBehavior {
+    /// Fail the completion with a timeout error.
+    #[default]
+    Error,
+    /// Return the output captured before the timeout, with
+    /// [`CliResponse::truncated`](crate::response::CliResponse::truncated) set.
+    ///
+    /// Claude Code runs then use stream-JSON output so the answer text
+    /// streamed so far can be recovered. The partial text may be empty.
+    ReturnPartial,
 }
 
 impl Default for ClientConfig {
@@ -34,6 +210,24 @@ impl Default for ClientConfig {
             binary_path: None,
             timeout: Duration::from_secs(300),
             channel_capacity: 100,
+            max_output_bytes: 10 * 1024 * 1024,
+            max_output_lines: None,
+            overflow_policy: OverflowPolicy::Error,
+            cache: None,
+            backpressure: BackpressurePolicy::Block,
+            on_timeout: TimeoutBehavior::Error,
+            batch_concurrency: 4,
+            sandbox: None,
+            builtin_tools: None,
+            env_policy: EnvPolicy::Inherit,
+            extra_args: Vec::new(),
+            forward_signals: false,
+            stderr_events: None,
+            preamble_cache_dir: None,
+            max_turns: None,
+            budget: None,
+            quota: None,
+            models: DefaultModels::default(),
         }
     }
 }
@@ -46,4 +240,272 @@ impl ClientConfig {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Creates a config with the named built-in [`Profile`] applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Config` if no built-in profile has that name.
+    pub fn profile(name: &str) -> Result<Self, Error> {
+        Profile::named(name)
+            .map(|profile| Self::default().with_profile(&profile))
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "unknown profile '{name}' (expected dev, ci, or prod-locked)"
+                ))
+            })
+    }
+
+    /// Creates a config with the profile named by [`PROFILE_ENV_VAR`]
+    /// applied, or the default config when the variable is unset or empty,
+    /// and the default models overridden by the `RIG_CLI_*_MODEL` variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Config` if the variable names an unknown profile.
+    pub fn from_env() -> Result<Self, Error> {
+        let mut config = match std::env::var(PROFILE_ENV_VAR) {
+            Ok(name) if !name.is_empty() => Self::profile(&name)?,
+            _ => Self::default(),
+        };
+        config.models = config.models.with_env();
+        Ok(config)
+    }
+
+    /// Applies `profile`'s settings, keeping the rest of the config.
+    #[must_use]
+    pub fn with_profile(mut self, profile: &Profile) -> Self {
+        self.timeout = profile.timeout;
+        self.sandbox.clone_from(&profile.sandbox);
+        self.builtin_tools.clone_from(&profile.builtin_tools);
+        self.env_policy.clone_from(&profile.env_policy);
+        self.budget = profile.max_runs.map(RunBudget::new);
+        self
+    }
+
+    /// Applies the sandbox, builtin tool and model settings to an MCP agent,
+    /// and counts its runs against the [`quota`](Self::quota).
+    pub(crate) fn contain(&self, mut builder: CliAgentBuilder) -> CliAgentBuilder {
+        builder = builder.default_models(self.models.clone());
+        if let Some(ref quota) = self.quota {
+            builder = builder.middleware(QuotaMiddleware(quota.clone()));
+        }
+        if let Some(ref mode) = self.sandbox {
+            builder = builder.sandbox_mode(mode.clone());
+        }
+        if let Some(ref tools) = self.builtin_tools {
+            builder = builder.allow_builtins(tools.clone());
+        }
+        builder
+    }
+
+    /// Counts a direct CLI run of `adapter` against the
+    /// [`budget`](Self::budget) and the [`quota`](Self::quota).
+    pub(crate) fn charge_run(&self, adapter: CliAdapter) -> Result<(), CompletionError> {
+        let charge = || {
+            if let Some(ref budget) = self.budget {
+                budget.charge()?;
+            }
+            if let Some(ref quota) = self.quota {
+                quota.check(adapter)?;
+                quota.record(adapter, QuotaUsage::RUN)?;
+            }
+            Ok::<_, ProviderError>(())
+        };
+        charge().map_err(|e| CompletionError::ProviderError(e.to_string()))
+    }
+
+    /// The model direct runs of `adapter` pass to the CLI, if any.
+    pub(crate) fn model(&self, adapter: CliAdapter) -> Option<String> {
+        self.models.get(adapter).map(str::to_string)
+    }
+
+    /// Adds the tokens a finished run of `adapter` reported to the
+    /// [`quota`](Self::quota).
+    ///
+    /// The run has already happened, so a failed write is only logged.
+    pub(crate) fn record_tokens(&self, adapter: CliAdapter, usage: Option<TokenUsage>) {
+        let (Some(quota), Some(usage)) = (&self.quota, usage) else {
+            return;
+        };
+        if let Err(e) = quota.record(adapter, usage.into()) {
+            tracing::warn!(
+                path = %quota.path().display(),
+                error = %e,
+                "failed to record token usage"
+            );
+        }
+    }
+}
+
+/// A named bundle of containment settings for [`ClientConfig`].
+///
+/// The built-in profiles, from [`Profile::named`]:
+///
+/// | Profile | Timeout | Codex sandbox | MCP builtins | Environment |
+/// |---------|---------|---------------|--------------|-------------|
+/// | `dev` | 5 min | workspace-write | `Read`, `Glob`, `Grep` | inherited |
+/// | `ci` | 10 min | read-only | none | [isolated](EnvPolicy::isolated) |
+/// | `prod-locked` | 2 min | read-only | none | [isolated](EnvPolicy::isolated) |
+///
+/// None of them sets a run budget. Build a custom profile by adjusting one
+/// and apply it with [`ClientConfig::with_profile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    /// Name the profile is selected by.
+    pub name: String,
+    /// Maximum execution time for CLI operations.
+    pub timeout: Duration,
+    /// See [`ClientConfig::sandbox`].
+    pub sandbox: Option<SandboxMode>,
+    /// See [`ClientConfig::builtin_tools`].
+    pub builtin_tools: Option<Vec<String>>,
+    /// See [`ClientConfig::env_policy`].
+    pub env_policy: EnvPolicy,
+    /// Direct CLI runs allowed per applied config; `None` is unlimited.
+    pub max_runs: Option<u64>,
+}
+
+impl Profile {
+    /// Local development: the agent may edit the workspace and read code.
+    #[must_use]
+    pub fn dev() -> Self {
+        Self {
+            name: "dev".to_string(),
+            timeout: Duration::from_secs(300),
+            sandbox: Some(SandboxMode::WorkspaceWrite),
+            builtin_tools: Some(vec!["Read".into(), "Glob".into(), "Grep".into()]),
+            env_policy: EnvPolicy::Inherit,
+            max_runs: None,
+        }
+    }
+
+    /// CI jobs: read-only, no builtins, and no host secrets.
+    #[must_use]
+    pub fn ci() -> Self {
+        Self {
+            name: "ci".to_string(),
+            timeout: Duration::from_secs(600),
+            sandbox: Some(SandboxMode::ReadOnly),
+            builtin_tools: None,
+            env_policy: EnvPolicy::isolated(),
+            max_runs: None,
+        }
+    }
+
+    /// Production: as [`ci`](Self::ci), with a short timeout.
+    #[must_use]
+    pub fn prod_locked() -> Self {
+        Self {
+            name: "prod-locked".to_string(),
+            timeout: Duration::from_secs(120),
+            ..Self::ci()
+        }
+    }
+
+    /// Looks up a built-in profile by name.
+    #[must_use]
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "dev" => Some(Self::dev()),
+            "ci" => Some(Self::ci()),
+            "prod-locked" => Some(Self::prod_locked()),
+            _ => None,
+        }
+    }
+}
+
+/// A cap on the number of CLI runs.
+///
+/// Clones share one count, so a client and every model and agent made from
+/// it draw from the same budget.
+#[derive(Debug, Clone)]
+pub struct RunBudget {
+    max_runs: u64,
+    used: Arc<AtomicU64>,
+}
+
+impl RunBudget {
+    /// Creates a budget of `max_runs` runs.
+    #[must_use]
+    pub fn new(max_runs: u64) -> Self {
+        Self {
+            max_runs,
+            used: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Runs not yet used.
+    #[must_use]
+    pub fn remaining(&self) -> u64 {
+        self.max_runs
+            .saturating_sub(self.used.load(Ordering::SeqCst))
+    }
+
+    /// Counts one run.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::Budget` once every run has been used.
+    pub fn charge(&self) -> Result<(), ProviderError> {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used < self.max_runs).then_some(used + 1)
+            })
+            .map(|_| ())
+            .map_err(|_| ProviderError::Budget(format!("all {} CLI runs used", self.max_runs)))
+    }
+}
+
+// The adapters share one `OverflowPolicy` / `OutputLimits` from their common
+// process runner, so these conversions cover all three.
+impl From<OverflowPolicy> for rig_cli_claude::OverflowPolicy {
+    fn from(policy: OverflowPolicy) -> Self {
+        match policy {
+            OverflowPolicy::Error => Self::Error,
+            OverflowPolicy::TruncateHead => Self::TruncateHead,
+            OverflowPolicy::TruncateTail => Self::TruncateTail,
+        }
+    }
+}
+
+impl From<&ClientConfig> for rig_cli_claude::OutputLimits {
+    fn from(config: &ClientConfig) -> Self {
+        Self {
+            max_bytes: config.max_output_bytes,
+            max_lines: config.max_output_lines,
+            channel_capacity: config.channel_capacity,
+            overflow: config.overflow_policy.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_applies_containment_settings() {
+        let config = ClientConfig::profile("prod-locked").unwrap();
+        assert_eq!(config.timeout, Duration::from_secs(120));
+        assert_eq!(config.sandbox, Some(SandboxMode::ReadOnly));
+        assert_eq!(config.env_policy, EnvPolicy::isolated());
+        assert!(config.budget.is_none());
+
+        assert!(matches!(
+            ClientConfig::profile("staging"),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_budget_is_shared_by_clones() {
+        let budget = RunBudget::new(2);
+        let clone = budget.clone();
+        budget.charge().unwrap();
+        clone.charge().unwrap();
+        assert_eq!(budget.remaining(), 0);
+        assert!(matches!(budget.charge(), Err(ProviderError::Budget(_))));
+    }
 }
@@ -0,0 +1,138 @@
+//! Environment diagnostics: installation and login state for every adapter.
+//!
+//! [`doctor`] extends [`discover_all`] with an auth check for each installed
+//! CLI, so applications can tell users exactly which command to run before
+//! the first prompt fails.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example() {
+//! for report in rig_cli::doctor().await {
+//!     if !report.is_ready() {
+//!         match report.auth {
+//!             Some(ref auth) => println!("{}: run `{}`", report.status.adapter, auth.login_command),
+//!             None => println!("{}: not installed", report.status.adapter),
+//!         }
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::discovery::{discover_all, AdapterStatus};
+#[cfg(any(feature = "claude", feature = "codex", feature = "opencode"))]
+use rig_cli_provider::mcp_agent::CliAdapter;
+
+/// The adapters' login state types, shared by every adapter.
+pub use rig_cli_process_core::{AuthState, AuthStatus};
+
+/// Installation and login state of a single adapter.
+#[derive(Debug, Clone)]
+pub struct AdapterReport {
+    /// Discovery result (path and version).
+    pub status: AdapterStatus,
+    /// Login state, or `None` when the CLI is not installed.
+    pub auth: Option<AuthStatus>,
+}
+
+impl AdapterReport {
+    /// Returns `true` when the CLI is installed and not known to be logged out.
+    ///
+    /// An [`AuthState::Unknown`] login state counts as ready, since older CLIs
+    /// cannot report it.
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        self.status.is_installed()
+            && self
+                .auth
+                .as_ref()
+                .is_some_and(|auth| auth.state != AuthState::LoggedOut)
+    }
+}
+
+/// Discovers every enabled adapter and checks whether its CLI is logged in.
+///
+/// Like [`discover_all`], each adapter is probed independently. A failed auth
+/// probe is reported as [`AuthState::Unknown`] rather than an error.
+pub async fn doctor() -> Vec<AdapterReport> {
+    let mut reports = Vec::new();
+    for status in discover_all().await {
+        let auth = probe_auth(&status).await;
+        reports.push(AdapterReport { status, auth });
+    }
+    reports
+}
+
+/// Runs the adapter-specific auth check for an installed CLI.
+async fn probe_auth(status: &AdapterStatus) -> Option<AuthStatus> {
+    if !status.is_installed() {
+        return None;
+    }
+
+    #[cfg(feature = "claude")]
+    if status.adapter == CliAdapter::ClaudeCode {
+        let path = status.path.as_deref()?;
+        return Some(
+            rig_cli_claude::auth_status(path)
+                .await
+                .unwrap_or_else(|_| unknown(rig_cli_claude::auth::LOGIN_COMMAND)),
+        );
+    }
+
+    #[cfg(feature = "codex")]
+    if status.adapter == CliAdapter::Codex {
+        let path = status.path.as_deref()?;
+        return Some(
+            rig_cli_codex::auth_status(path)
+                .await
+                .unwrap_or_else(|_| unknown(rig_cli_codex::auth::LOGIN_COMMAND)),
+        );
+    }
+
+    #[cfg(feature = "opencode")]
+    if status.adapter == CliAdapter::OpenCode {
+        return Some(rig_cli_opencode::auth_status().await);
+    }
+
+    // Only enabled adapters are discovered, so this is not reached.
+    None
+}
+
+#[cfg(any(feature = "claude", feature = "codex"))]
+fn unknown(login_command: &str) -> AuthStatus {
+    AuthStatus {
+        state: AuthState::Unknown,
+        method: None,
+        login_command: login_command.to_string(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use rig_cli_provider::mcp_agent::CliAdapter;
+
+    fn report(installed: bool, state: AuthState) -> AdapterReport {
+        AdapterReport {
+            status: AdapterStatus {
+                adapter: CliAdapter::Codex,
+                path: installed.then(|| "/usr/local/bin/codex".into()),
+                version: None,
+            },
+            auth: installed.then(|| AuthStatus {
+                state,
+                method: None,
+                login_command: "codex login".to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_is_ready() {
+        assert!(report(true, AuthState::LoggedIn).is_ready());
+        assert!(report(true, AuthState::Unknown).is_ready());
+        assert!(!report(true, AuthState::LoggedOut).is_ready());
+        assert!(!report(false, AuthState::LoggedIn).is_ready());
+    }
+}
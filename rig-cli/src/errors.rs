@@ -46,3 +46,34 @@ pub enum Error {
     #[error("Configuration error: {0}")]
     Config(String),
 }
+
+impl Error {
+    /// Returns a stable, machine-readable code identifying the error category.
+    ///
+    /// Provider errors delegate to `ProviderError::error_code`, so the same
+    /// failure reports the same code whichever layer surfaced it.
+    #[must_use]
+    pub const fn error_code(&self) -> &'static str {
+        match self {
+            Self::ClaudeNotFound | Self::CodexNotFound | Self::OpenCodeNotFound => "discovery",
+            Self::ExecutionFailed(_) => "execution",
+            Self::Provider(e) => e.error_code(),
+            Self::Completion(_) => "completion",
+            Self::Config(_) => "config",
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use rig_cli_provider::errors::ProviderError;
+
+    #[test]
+    fn test_error_code_delegates_to_provider() {
+        let err = Error::from(ProviderError::Cancelled);
+        assert_eq!(err.error_code(), "cancelled");
+        assert_eq!(Error::ClaudeNotFound.error_code(), "discovery");
+    }
+}
@@ -0,0 +1,142 @@
+//! Rig-style extractors that run MCP-enforced agents.
+//!
+//! Rig's `client.extractor::<T>()` goes through the `CompletionModel` path,
+//! which cannot force a CLI agent to answer through a tool call.
+//! `mcp_extractor::<T>()` on each provider client is the MCP counterpart: it
+//! builds the [`JsonSchemaToolkit`] for `T` once, and each
+//! [`extract`](McpExtractor::extract) runs an MCP tool agent and returns the
+//! submission as a `T`.
+//!
+//! As with [`extract_batch`](crate::batch), the current executable must serve
+//! the same toolkit when started with `RIG_MCP_SERVER=1`:
+//!
+//! ```ignore
+//! let client = rig_cli::claude::Client::new().await?;
+//! let extractor = client
+//!     .mcp_extractor::<Invoice>("sonnet")
+//!     .preamble("Amounts are in euros.")
+//!     .build();
+//! let invoice: Invoice = extractor.extract(document).await?;
+//! ```
+
+use crate::batch::BatchRun;
+use crate::config::ClientConfig;
+use rig_cli_mcp::extraction::ExtractionError;
+use rig_cli_mcp::tools::JsonSchemaToolkit;
+use rig_cli_provider::mcp_agent::CliAdapter;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+/// Builder for an [`McpExtractor`], returned by `mcp_extractor::<T>()` on
+/// each provider client.
+pub struct McpExtractorBuilder<T> {
+    adapter: CliAdapter,
+    cli_path: PathBuf,
+    config: ClientConfig,
+    payload: Option<String>,
+    images: Vec<PathBuf>,
+    preamble: Option<String>,
+    _target: PhantomData<fn() -> T>,
+}
+
+impl<T> McpExtractorBuilder<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    pub(crate) const fn new(
+        adapter: CliAdapter,
+        cli_path: PathBuf,
+        config: ClientConfig,
+        payload: Option<String>,
+    ) -> Self {
+        Self {
+            adapter,
+            cli_path,
+            config,
+            payload,
+            images: Vec::new(),
+            preamble: None,
+            _target: PhantomData,
+        }
+    }
+
+    /// Sets the system prompt sent ahead of the MCP workflow instructions.
+    #[must_use]
+    pub fn preamble(mut self, preamble: impl Into<String>) -> Self {
+        self.preamble = Some(preamble.into());
+        self
+    }
+
+    /// Sets context data sent with every extraction, replacing the client's
+    /// payload.
+    #[must_use]
+    pub fn payload(mut self, data: impl Into<String>) -> Self {
+        self.payload = Some(data.into());
+        self
+    }
+
+    /// Sets image files shown with every extraction, replacing the
+    /// client's. Only Claude Code accepts images; with another CLI every
+    /// extraction fails with a validation error.
+    #[must_use]
+    pub fn images(mut self, images: Vec<PathBuf>) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Builds the extractor and the toolkit for `T`.
+    #[must_use]
+    pub fn build(self) -> McpExtractor<T> {
+        McpExtractor {
+            adapter: self.adapter,
+            cli_path: self.cli_path,
+            config: self.config,
+            payload: self.payload,
+            images: self.images,
+            preamble: self.preamble,
+            toolkit: JsonSchemaToolkit::<T>::builder().build(),
+        }
+    }
+}
+
+/// Extracts a `T` from text with one MCP-enforced run per call.
+pub struct McpExtractor<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    adapter: CliAdapter,
+    cli_path: PathBuf,
+    config: ClientConfig,
+    payload: Option<String>,
+    images: Vec<PathBuf>,
+    preamble: Option<String>,
+    toolkit: JsonSchemaToolkit<T>,
+}
+
+impl<T> McpExtractor<T>
+where
+    T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Runs an MCP tool agent on `text` and returns its submission as a `T`.
+    ///
+    /// # Errors
+    /// Returns [`ExtractionError::AgentError`] if the run fails or ends
+    /// without a submission, and [`ExtractionError::ParseError`] if the
+    /// submission does not deserialize into `T`.
+    #[allow(clippy::result_large_err)]
+    pub async fn extract(&self, text: impl Into<String>) -> Result<T, ExtractionError> {
+        let run = BatchRun {
+            adapter: self.adapter,
+            cli_path: &self.cli_path,
+            config: &self.config,
+            payload: self.payload.as_deref(),
+            images: &self.images,
+            preamble: self.preamble.as_deref(),
+        };
+        let (_, _, result) = run.extract_one(self.toolkit.clone(), text.into()).await;
+        result
+    }
+}
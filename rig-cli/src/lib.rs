@@ -32,7 +32,9 @@
 //! | `claude` | Yes | Enable Claude Code provider |
 //! | `codex` | Yes | Enable Codex provider |
 //! | `opencode` | Yes | Enable `OpenCode` provider |
-//! | `debug-output` | No | Include raw CLI output in error messages |
+//! | `blocking` | No | `new_blocking` / `prompt_blocking` on each client, running on a runtime of their own |
+//! | `debug-output` | No | Include raw CLI output in error messages and leave it unredacted |
+//! | `test-support` | No | Golden CLI fixtures and a fake CLI replaying them, in `testing` (unix) |
 //!
 //! Enable specific providers:
 //!
@@ -50,8 +52,12 @@
 //! | [`opencode`] | `OpenCode` provider with `CompletionModel` |
 //! | [`extraction`] | MCP extraction types (re-exported from rig-mcp-server) |
 //! | [`tools`] | MCP tool types (re-exported from rig-mcp-server) |
+//! | [`batch`] | Batch structured extraction with bounded concurrency |
+//! | [`extractor`] | `mcp_extractor::<T>()`, Rig's extractor over MCP-enforced runs |
+//! | [`mcp_child`] | `maybe_serve`, serving the toolset when started as an MCP server |
 //! | [`prelude`] | Common imports for quick start |
 //! | [`config`] | Shared client configuration |
+//! | [`quota`] | Usage quotas persisted across process restarts |
 //! | [`errors`] | Public error types |
 //! | [`response`] | Shared response type |
 //!
@@ -127,6 +133,15 @@
 //! | Streaming events | Full (ToolCall/ToolResult) | Text/Error only | Text/Error only |
 //! | Sandbox | `--tools ""` | `--sandbox` | None |
 //! | System prompt | `--system-prompt` | Prepend | Prepend |
+//! | Schema-constrained output | `--json-schema` | `--output-schema` | MCP only |
+//!
+//! ## Stream Events
+//!
+//! Each adapter's events are mapped to one provider-agnostic [`StreamEvent`],
+//! whichever path runs the CLI. `mcp_agent()` streams yield them from
+//! [`McpStreamHandle`]; `agent()` streams record them in the final
+//! response's [`events`](response::CliResponse::events). Adapter events with
+//! no typed equivalent are kept as [`StreamEvent::Raw`].
 
 #![deny(missing_docs)]
 
@@ -142,29 +157,66 @@ pub mod codex;
 #[cfg(feature = "opencode")]
 pub mod opencode;
 
+/// Batch structured extraction across many inputs.
+pub mod batch;
+
+#[cfg(all(
+    feature = "blocking",
+    any(feature = "claude", feature = "codex", feature = "opencode")
+))]
+mod blocking;
+
+/// Response caching for direct CLI completions.
+pub mod cache;
+
 /// Shared client configuration.
 pub mod config;
 
 /// Unified CLI binary discovery across all adapters.
 pub mod discovery;
 
+/// Installation and login diagnostics across all adapters.
+pub mod doctor;
+
 /// Public error types.
 pub mod errors;
 
+/// Rig-style extractors running MCP-enforced agents.
+pub mod extractor;
+
+/// Serving the toolset when started as an agent's MCP server.
+pub mod mcp_child;
+
+/// Usage quotas persisted across process restarts.
+pub mod quota;
+
 /// Shared response type.
 pub mod response;
 
+#[cfg(any(feature = "claude", feature = "codex", feature = "opencode"))]
+mod streaming;
+
 /// Commonly used types and traits.
 pub mod prelude;
 
+pub use doctor::doctor;
+
 // Re-export the Rig crate so users can access Rig types via rig_cli::rig::...
 pub use rig;
 
 // MCP-enforced agent types (from rig-provider)
+pub use rig_cli_provider::backend::{
+    BackendCapabilities, BackendHealth, BackendRequest, CliBackend,
+};
+pub use rig_cli_provider::containment::ContainmentViolation;
 pub use rig_cli_provider::mcp_agent::{
-    CliAdapter, CliAgent, CliAgentBuilder, McpStreamEvent, McpStreamHandle, McpToolAgent,
-    McpToolAgentBuilder,
+    BackpressurePolicy, CliAdapter, CliAgent, CliAgentBuilder, McpDelivery, McpStreamEvent,
+    McpStreamHandle, McpToolAgent, McpToolAgentBuilder,
 };
+pub use rig_cli_provider::preflight::PreflightReport;
+
+// Provider-agnostic stream events (from rig-provider)
+pub use rig_cli_provider::events::StreamEvent;
 
 /// Re-export of MCP extraction types for structured data extraction workflows.
 ///
@@ -172,7 +224,108 @@ pub use rig_cli_provider::mcp_agent::{
 /// schema-compliant output from CLI agents.
 pub mod extraction {
     pub use rig_cli_mcp::extraction::{
-        ExtractionConfig, ExtractionError, ExtractionMetrics, ExtractionOrchestrator,
+        AgentResponse, ExtractionConfig, ExtractionError, ExtractionMetrics,
+        ExtractionOrchestrator, TokenUsage,
+    };
+    pub use rig_cli_mcp::harvest::{ToolHarvest, WorkflowCompliance, WorkflowViolation};
+}
+
+/// Re-export of run artifact manifest types for MCP agent runs.
+///
+/// Set `McpToolAgentBuilder::artifacts_dir` to keep each run's prompts, MCP
+/// config, result and output in a directory of its own; its `index.json`
+/// deserializes into `ArtifactIndex`.
+pub mod artifacts {
+    pub use rig_cli_provider::artifacts::{ArtifactError, ArtifactFile, ArtifactIndex, INDEX_FILE};
+}
+
+/// Re-export of the golden CLI fixtures, with the `test-support` feature.
+///
+/// `FixtureCli` installs a fake CLI that prints a captured run, for
+/// regression-testing event extraction without a model.
+#[cfg(all(unix, feature = "test-support"))]
+pub mod testing {
+    pub use rig_cli_provider::testing::{
+        Fixture, FixtureCli, Replay, CLAUDE_2_0_STREAM_JSON, CODEX_0_46_EXEC, FIXTURES,
+        OPENCODE_0_15_RUN,
+    };
+}
+
+/// Re-export of credential loading types for MCP agent runs.
+///
+/// Attach credentials with `CliAgentBuilder::credentials` to load API keys
+/// from env files or the OS keychain instead of the process environment.
+pub mod credentials {
+    pub use rig_cli_provider::credentials::{adapter_vars, CredentialSource, Credentials};
+}
+
+/// Re-export of run middleware types for hooking into MCP agent runs.
+///
+/// Attach middleware with `CliAgentBuilder::middleware` to add logging,
+/// prompt redaction, caching, or metrics around every run.
+pub mod middleware {
+    pub use rig_cli_provider::middleware::{async_trait, RunMiddleware, RunRequest};
+}
+
+/// Re-export of redaction types for user-provided text in logs and errors.
+///
+/// Prompts, payloads and CLI output are truncated in error messages by
+/// default; call `RedactionPolicy::install` to hash or drop them instead.
+pub mod redaction {
+    pub use rig_cli_process_core::redact::{redact, RedactionPolicy, DEFAULT_TRUNCATE_CHARS};
+}
+
+/// Re-export of the directory tree payload builder.
+///
+/// `PayloadBuilder` renders the files under a directory into a payload for
+/// `with_payload` or `McpToolAgentBuilder::payload`, honoring `.gitignore`.
+pub mod payload {
+    pub use rig_cli_provider::payload::{
+        PayloadBuilder, PayloadFile, PayloadFileStatus, RenderedPayload, DEFAULT_MAX_FILE_BYTES,
+        DEFAULT_MAX_TOTAL_BYTES,
+    };
+}
+
+/// Re-export of binary attachment types for MCP agent runs.
+///
+/// Attach images, PDFs and other non-UTF-8 files with
+/// `CliAgentBuilder::attachment`; they are base64-encoded into the prompt or
+/// staged as files the agent reads.
+pub mod attachments {
+    pub use rig_cli_provider::attachments::{
+        AttachmentStrategy, Attachments, BinaryPayload, DEFAULT_MAX_ATTACHMENT_BYTES,
+        DEFAULT_MAX_INLINE_BYTES,
+    };
+}
+
+/// Re-export of payload scanning types for MCP agent runs.
+///
+/// Attach a scanner with `CliAgentBuilder::payload_scanner` to flag or redact
+/// prompt injection attempts in untrusted payloads.
+pub mod scanner {
+    pub use rig_cli_provider::scanner::{
+        InjectionKind, PayloadFinding, PayloadScanner, ScanAction,
+    };
+}
+
+/// Re-export of payload truncation types for MCP agent runs.
+///
+/// Attach a budget with `CliAgentBuilder::payload_budget` to keep large
+/// payloads within the model's context window.
+pub mod truncation {
+    pub use rig_cli_provider::truncation::{
+        PayloadBudget, PayloadTruncation, Retain, TokenEstimator,
+    };
+}
+
+/// Re-export of transcript types for feeding runs to evaluation harnesses.
+///
+/// `TranscriptExporter` turns the events of a `CliResponse` and an MCP tool
+/// harvest into JSONL messages with `role`, `content` and `tool_calls`.
+pub mod transcript {
+    pub use rig_cli_provider::transcript::{
+        Role, Transcript, TranscriptExporter, TranscriptFunction, TranscriptMessage,
+        TranscriptToolCall,
     };
 }
 
@@ -181,6 +334,13 @@ pub mod extraction {
 /// These types provide the building blocks for creating JSON schema-based toolkits
 /// and configuring MCP servers for structured agent execution.
 pub mod tools {
-    pub use rig_cli_mcp::server::{McpConfig, RigMcpHandler, ToolSetExt};
-    pub use rig_cli_mcp::tools::{DynamicJsonSchemaToolkit, JsonSchemaToolkit};
+    pub use rig_cli_mcp::files::FileAccess;
+    pub use rig_cli_mcp::server::{McpConfig, RigMcpHandler, ToolSetExt, SERVER_NAME_ENV};
+    pub use rig_cli_mcp::submissions::{SubmitOutcome, SubmitPolicy, SubmitRecord};
+    pub use rig_cli_mcp::tools::{
+        DynamicJsonSchemaToolkit, JsonListToolkit, JsonSchemaToolkit, ListSubmission,
+        VersionedSchema,
+    };
+    pub use rig_cli_mcp::validation::{ExternalRefs, SchemaDraft, SchemaValidation};
+    pub use rig_cli_mcp::watchdog::RestartPolicy;
 }
@@ -0,0 +1,68 @@
+//! Serving the toolset when the current executable is started as an MCP
+//! server.
+//!
+//! MCP agents launch the current executable with `RIG_MCP_SERVER=1` as their
+//! tool server, so a binary that orchestrates runs must also serve its
+//! toolset when started that way. [`maybe_serve`] does both in one line at
+//! the top of `main`:
+//!
+//! ```ignore
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     rig_cli::mcp_child::maybe_serve(build_toolset).await;
+//!
+//!     let client = rig_cli::claude::Client::new().await?;
+//!     let agent = client.mcp_agent("sonnet").toolset(build_toolset()).build()?;
+//!     // ...
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Agents with extra namespaces start one server per namespace;
+//! [`server_name`] tells the factory which toolset it is serving.
+
+use rig::tool::ToolSet;
+use rig_cli_mcp::server::{ToolSetExt, SERVER_NAME_ENV};
+
+/// Environment variable set on every MCP server an agent launches.
+pub const SERVER_ENV: &str = "RIG_MCP_SERVER";
+
+/// Whether this process was started as an agent's MCP server.
+#[must_use]
+pub fn is_server() -> bool {
+    std::env::var_os(SERVER_ENV).is_some()
+}
+
+/// The name of the MCP server this process was started as, if an agent set
+/// one.
+///
+/// The main server is named after the builder's `server_name`
+/// (`rig_mcp` by default); each extra namespace after its own name.
+#[must_use]
+pub fn server_name() -> Option<String> {
+    std::env::var(SERVER_NAME_ENV).ok()
+}
+
+/// Serves the toolset from `toolset_factory` over stdio and exits, if this
+/// process was started as an MCP server; otherwise returns at once without
+/// calling the factory.
+///
+/// The process exits with status 0 when the agent closes the session, and
+/// with status 1, after printing the error to stderr, if the server fails.
+pub async fn maybe_serve<F>(toolset_factory: F)
+where
+    F: FnOnce() -> ToolSet + Send,
+{
+    if !is_server() {
+        return;
+    }
+    let served = match toolset_factory().into_handler().await {
+        Ok(handler) => handler.serve_stdio().await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = served {
+        eprintln!("MCP server failed: {e}");
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
@@ -7,6 +7,11 @@
 //! | `client.agent("model")` | Direct CLI | Simple prompts, chat, streaming |
 //! | `client.mcp_agent("model")` | MCP Server | Structured extraction, forced tool use |
 //!
+//! By default every `agent()` prompt spawns `opencode run`. After
+//! [`Client::start_server`] or [`Client::attach_server`], prompts are instead
+//! sent over HTTP to a long-lived `opencode serve` process, which avoids the
+//! per-prompt startup cost. Completions and streaming behave the same either way.
+//!
 //! ## Example
 //!
 //! ```no_run
@@ -20,20 +25,25 @@
 //! # }
 //! ```
 
-use crate::config::ClientConfig;
+use crate::batch::{BatchExtraction, BatchRun};
+use crate::cache::CacheLayer;
+use crate::config::{ClientConfig, TimeoutBehavior};
 use crate::errors::Error;
+use crate::extractor::McpExtractorBuilder;
 use crate::response::CliResponse;
-use futures::StreamExt;
 use rig::completion::{
     message::AssistantContent, CompletionError, CompletionModel, CompletionRequest,
     CompletionResponse, Usage,
 };
-use rig::streaming::{RawStreamingChoice, StreamingCompletionResponse};
+use rig::streaming::StreamingCompletionResponse;
 use rig::OneOrMany;
-use rig_cli_opencode::{discover_opencode, OpenCodeCli, OpenCodeConfig};
+use rig_cli_opencode::{discover_opencode, OpenCodeCli, OpenCodeConfig, OpenCodeServer};
 use rig_cli_provider::mcp_agent::{CliAdapter, CliAgentBuilder};
 use rig_cli_provider::utils::format_chat_history;
-use tokio_stream::wrappers::ReceiverStream;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Instant;
 
 /// `OpenCode` CLI provider client.
 ///
@@ -44,6 +54,7 @@ pub struct Client {
     cli: OpenCodeCli,
     config: ClientConfig,
     payload: Option<String>,
+    server: Option<OpenCodeServer>,
 }
 
 impl Client {
@@ -56,18 +67,34 @@ impl Client {
     ///
     /// Returns `Error::OpenCodeNotFound` if the CLI binary cannot be found.
     /// Returns `Error::Provider` if the health check fails.
+    /// Returns `Error::Config` if [`PROFILE_ENV_VAR`](crate::config::PROFILE_ENV_VAR)
+    /// names an unknown profile.
     pub async fn new() -> Result<Self, Error> {
-        let path = discover_opencode(None).map_err(|_| Error::OpenCodeNotFound)?;
-        let cli = OpenCodeCli::new(path);
-        cli.check_health()
-            .await
-            .map_err(|e| Error::Provider(e.into()))?;
+        Self::from_config(ClientConfig::from_env()?).await
+    }
 
-        Ok(Self {
-            cli,
-            config: ClientConfig::default(),
-            payload: None,
-        })
+    /// Synchronous [`new`](Self::new), with the `blocking` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`new`](Self::new), and `Error::ExecutionFailed`
+    /// if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn new_blocking() -> Result<Self, Error> {
+        crate::blocking::block_on(Self::new())?
+    }
+
+    /// Sends `prompt` through a default agent and waits for the reply, with
+    /// the `blocking` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Completion` if the CLI run fails, and
+    /// `Error::ExecutionFailed` if called from inside a Tokio runtime.
+    #[cfg(feature = "blocking")]
+    pub fn prompt_blocking(&self, prompt: &str) -> Result<String, Error> {
+        let agent = rig::agent::AgentBuilder::new(Model::make(self, String::new())).build();
+        crate::blocking::prompt(&agent, prompt)
     }
 
     /// Creates a new `OpenCode` client from the given configuration.
@@ -79,18 +106,26 @@ impl Client {
     ///
     /// Returns `Error::OpenCodeNotFound` if the CLI binary cannot be found.
     /// Returns `Error::Provider` if the health check fails.
+    ///
+    /// The CLI's help text is probed for the flags it supports, and runs
+    /// needing a missing one fail before spawning; if the probe fails, every
+    /// flag is passed unchecked.
     pub async fn from_config(config: ClientConfig) -> Result<Self, Error> {
         let path =
             discover_opencode(config.binary_path.clone()).map_err(|_| Error::OpenCodeNotFound)?;
-        let cli = OpenCodeCli::new(path);
+        let mut cli = OpenCodeCli::new(path);
         cli.check_health()
             .await
             .map_err(|e| Error::Provider(e.into()))?;
+        if let Ok(capabilities) = cli.probe_capabilities().await {
+            cli = cli.with_capabilities(capabilities);
+        }
 
         Ok(Self {
             cli,
             config,
             payload: None,
+            server: None,
         })
     }
 
@@ -104,6 +139,45 @@ impl Client {
         self
     }
 
+    /// Starts an `opencode serve` process and routes completions through it.
+    ///
+    /// The server lives as long as the client (and any models made from it)
+    /// and is killed when the last handle is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Provider` if the server fails to start.
+    pub async fn start_server(mut self) -> Result<Self, Error> {
+        let config = OpenCodeConfig {
+            timeout: self.config.timeout,
+            ..OpenCodeConfig::default()
+        };
+        let server = OpenCodeServer::start(&self.cli.path, &config)
+            .await
+            .map_err(|e| Error::Provider(e.into()))?;
+        self.server = Some(server);
+        Ok(self)
+    }
+
+    /// Routes completions through an already running `opencode serve` at `base_url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Provider` if the server does not answer.
+    pub async fn attach_server(mut self, base_url: impl Into<String>) -> Result<Self, Error> {
+        let server = OpenCodeServer::attach(base_url)
+            .await
+            .map_err(|e| Error::Provider(e.into()))?;
+        self.server = Some(server);
+        Ok(self)
+    }
+
+    /// The server completions are routed through, if any.
+    #[must_use]
+    pub const fn server(&self) -> Option<&OpenCodeServer> {
+        self.server.as_ref()
+    }
+
     /// Access the underlying CLI handle for advanced use cases.
     ///
     /// This is an escape hatch for developers who need access to adapter-specific
@@ -124,9 +198,11 @@ impl Client {
     /// See module docs for the difference between `agent()` and `mcp_agent()`.
     #[must_use]
     pub fn mcp_agent(&self, _model: impl Into<String>) -> CliAgentBuilder {
-        let mut builder = rig_cli_provider::mcp_agent::CliAgent::builder()
-            .adapter(CliAdapter::OpenCode)
-            .timeout(self.config.timeout);
+        let mut builder = self.config.contain(
+            rig_cli_provider::mcp_agent::CliAgent::builder()
+                .adapter(CliAdapter::OpenCode)
+                .timeout(self.config.timeout),
+        );
 
         if let Some(ref payload) = self.payload {
             builder = builder.payload(payload.clone());
@@ -134,6 +210,46 @@ impl Client {
 
         builder
     }
+
+    /// Extracts a `T` from each item with MCP-enforced runs.
+    ///
+    /// Items run concurrently, up to [`ClientConfig::batch_concurrency`], and
+    /// results come back in input order. See [`crate::batch`] for the MCP
+    /// server the current executable must provide.
+    pub async fn extract_batch<T>(&self, items: Vec<String>) -> BatchExtraction<T>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        BatchRun {
+            adapter: CliAdapter::OpenCode,
+            cli_path: &self.cli.path,
+            config: &self.config,
+            payload: self.payload.as_deref(),
+            images: &[],
+            preamble: None,
+        }
+        .extract(items)
+        .await
+    }
+
+    /// Creates an extractor returning a `T` from one MCP-enforced run per
+    /// call, the MCP counterpart of Rig's `extractor::<T>()`.
+    ///
+    /// The toolkit for `T` is built once and the client's CLI binary and
+    /// payload are reused. See [`crate::extractor`] for the MCP server the
+    /// current executable must provide.
+    #[must_use]
+    pub fn mcp_extractor<T>(&self, _model: impl Into<String>) -> McpExtractorBuilder<T>
+    where
+        T: JsonSchema + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        McpExtractorBuilder::new(
+            CliAdapter::OpenCode,
+            self.cli.path.clone(),
+            self.config.clone(),
+            self.payload.clone(),
+        )
+    }
 }
 
 /// `OpenCode` completion model.
@@ -145,18 +261,24 @@ pub struct Model {
     cli: OpenCodeCli,
     config: ClientConfig,
     payload: Option<String>,
+    server: Option<OpenCodeServer>,
+    /// Model identifier; CLI agents ignore it, but it keys the response cache.
+    #[allow(clippy::struct_field_names)]
+    model_name: String,
 }
 
 impl CompletionModel for Model {
     type Response = CliResponse;
-    type StreamingResponse = ();
+    type StreamingResponse = CliResponse;
     type Client = Client;
 
-    fn make(client: &Self::Client, _model: impl Into<String>) -> Self {
+    fn make(client: &Self::Client, model: impl Into<String>) -> Self {
         Self {
             cli: client.cli.clone(),
             config: client.config.clone(),
             payload: client.payload.clone(),
+            server: client.server.clone(),
+            model_name: model.into(),
         }
     }
 
@@ -164,6 +286,17 @@ impl CompletionModel for Model {
         &self,
         request: CompletionRequest,
     ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        let cached = self.config.cache.as_ref().map(|cache| {
+            let key = CacheLayer::key(&self.model_name, &request, self.payload.as_deref());
+            (cache, key)
+        });
+        if let Some((cache, ref key)) = cached {
+            if let Some(hit) = cache.get(key) {
+                return Ok(crate::cache::completion_response(hit));
+            }
+        }
+
+        self.config.charge_run(CliAdapter::OpenCode)?;
         let prompt_text = format_chat_history(&request);
 
         // If payload is set, wrap prompt in XML context structure
@@ -182,7 +315,12 @@ impl CompletionModel for Model {
         };
 
         let mut config = OpenCodeConfig {
+            model: self.config.model(CliAdapter::OpenCode),
             timeout: self.config.timeout,
+            output_limits: (&self.config).into(),
+            env_policy: self.config.env_policy.clone(),
+            extra_args: self.config.extra_args.clone(),
+            forward_signals: self.config.forward_signals,
             ..OpenCodeConfig::default()
         };
 
@@ -191,11 +329,25 @@ impl CompletionModel for Model {
             config.prompt = Some(preamble.clone());
         }
 
-        let result = self
-            .cli
-            .run(&final_prompt, &config)
-            .await
-            .map_err(|e| {
+        let start = Instant::now();
+        let run = match self.server {
+            Some(ref server) => server.run(&final_prompt, &config).await,
+            None => self.cli.run(&final_prompt, &config).await,
+        };
+        let result = match run {
+            Err(rig_cli_opencode::OpenCodeError::Timeout { partial_stdout, .. })
+                if self.config.on_timeout == TimeoutBehavior::ReturnPartial =>
+            {
+                let duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+                return Ok(CompletionResponse {
+                    choice: OneOrMany::one(AssistantContent::text(partial_stdout.clone())),
+                    usage: Usage::default(),
+                    raw_response: CliResponse::from_partial(partial_stdout, duration_ms),
+                });
+            }
+            result => result,
+        }
+        .map_err(|e| {
                 #[cfg(feature = "debug-output")]
                 {
                     CompletionError::ProviderError(format!("{e}\n--- raw debug output ---\nError occurred during CLI execution. Enable tracing for detailed output."))
@@ -206,11 +358,21 @@ impl CompletionModel for Model {
                 }
             })?;
 
-        let cli_response = CliResponse::from_run_result(
-            result.stdout.clone(),
-            result.exit_code,
-            result.duration_ms,
-        );
+        let cli_response = CliResponse {
+            latency: Some(result.latency),
+            ..CliResponse::from_run_result(
+                result.stdout.clone(),
+                result.stderr,
+                result.exit_code,
+                result.duration_ms,
+            )
+        };
+
+        if let Some((cache, ref key)) = cached {
+            if cli_response.exit_code == 0 {
+                cache.put(key, &cli_response);
+            }
+        }
 
         Ok(CompletionResponse {
             choice: OneOrMany::one(AssistantContent::text(result.stdout)),
@@ -223,6 +385,7 @@ impl CompletionModel for Model {
         &self,
         request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        self.config.charge_run(CliAdapter::OpenCode)?;
         let prompt_text = format_chat_history(&request);
 
         // If payload is set, wrap prompt in XML context structure
@@ -242,10 +405,18 @@ impl CompletionModel for Model {
 
         let (tx, rx) = tokio::sync::mpsc::channel(self.config.channel_capacity);
         let cli = self.cli.clone();
+        let server = self.server.clone();
 
         // Spawn the CLI process in the background
         let mut config = OpenCodeConfig {
+            model: self.config.model(CliAdapter::OpenCode),
             timeout: self.config.timeout,
+            output_limits: (&self.config).into(),
+            backpressure: self.config.backpressure.into(),
+            env_policy: self.config.env_policy.clone(),
+            extra_args: self.config.extra_args.clone(),
+            forward_signals: self.config.forward_signals,
+            stderr_events: self.config.stderr_events.clone(),
             ..OpenCodeConfig::default()
         };
 
@@ -254,24 +425,27 @@ impl CompletionModel for Model {
             config.prompt = Some(preamble);
         }
 
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
         tokio::spawn(async move {
-            // Error from CLI stream is intentionally dropped here;
-            // the receiver will see the channel close and handle accordingly
-            let _ = cli.stream(&final_prompt, &config, tx).await;
+            let result = match server {
+                Some(server) => server.stream(&final_prompt, &config, tx).await,
+                None => cli.stream(&final_prompt, &config, tx).await,
+            };
+            // The receiver is gone only if the stream was dropped
+            let _ = done_tx.send(result);
         });
 
-        // Convert the receiver into a stream
-        let stream = ReceiverStream::new(rx).map(|event| match event {
-            rig_cli_opencode::StreamEvent::Text { text } => Ok(RawStreamingChoice::Message(text)),
-            rig_cli_opencode::StreamEvent::Error { message } => {
-                Err(CompletionError::ProviderError(message))
-            }
-            rig_cli_opencode::StreamEvent::Unknown(_) => {
-                Ok(RawStreamingChoice::Message(String::new()))
+        Ok(crate::streaming::assemble(rx, done_rx, false, |result| {
+            CliResponse {
+                latency: Some(result.latency),
+                ..CliResponse::from_run_result(
+                    result.stdout,
+                    result.stderr,
+                    result.exit_code,
+                    result.duration_ms,
+                )
             }
-        });
-
-        Ok(StreamingCompletionResponse::stream(Box::pin(stream)))
+        }))
     }
 }
 
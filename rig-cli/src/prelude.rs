@@ -27,4 +27,9 @@ pub use rig::completion::Prompt;
 // Re-export key MCP types for structured extraction workflows
 // These are the types users need to build ToolSets for extraction
 pub use rig_cli_mcp::extraction::{ExtractionConfig, ExtractionOrchestrator};
-pub use rig_cli_mcp::tools::{DynamicJsonSchemaToolkit, JsonSchemaToolkit};
+pub use rig_cli_mcp::tools::{
+    DynamicJsonSchemaToolkit, JsonListToolkit, JsonSchemaToolkit, ListSubmission, VersionedSchema,
+};
+
+// Directory trees as payloads, for "analyze this repo" workflows
+pub use crate::payload::PayloadBuilder;
@@ -0,0 +1,552 @@
+//! Usage quotas persisted across process restarts.
+//!
+//! A [`QuotaStore`] set on [`ClientConfig::quota`](crate::config::ClientConfig::quota)
+//! adds every CLI run, and the tokens it reported, to a JSON file kept per
+//! calendar month (UTC) and adapter. Point every process of a team
+//! sharing one CLI subscription at the same file to govern a month's usage:
+//! a [`QuotaThreshold`] logs a warning when usage crosses it, or refuses
+//! further runs with `ProviderError::Budget` once usage reaches it.
+//!
+//! Every write holds an exclusive lock on a sibling `.lock` file while it
+//! re-reads the ledger, adds its usage and replaces the file, so processes
+//! writing at the same instant take turns instead of losing each other's
+//! usage.
+//!
+//! Only Claude Code reports token usage, on direct runs, streamed or not.
+//! Codex and `OpenCode` runs, and MCP agent runs of any adapter, whose CLI
+//! prints plain text, count as runs with no tokens.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use rig_cli::config::ClientConfig;
+//! # use rig_cli::quota::{QuotaAction, QuotaMetric, QuotaStore, QuotaThreshold};
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let quota = QuotaStore::open("usage.json")?
+//!     .with_threshold(QuotaThreshold::new(
+//!         QuotaMetric::TotalTokens,
+//!         40_000_000,
+//!         QuotaAction::Warn,
+//!     ))
+//!     .with_threshold(QuotaThreshold::new(
+//!         QuotaMetric::TotalTokens,
+//!         50_000_000,
+//!         QuotaAction::Stop,
+//!     ));
+//! let config = ClientConfig {
+//!     quota: Some(quota),
+//!     ..ClientConfig::default()
+//! };
+//! # Ok(())
+//! # }
+//! ```
+
+use rig_cli_provider::errors::ProviderError;
+use rig_cli_provider::mcp_agent::{CliAdapter, McpToolAgentResult};
+use rig_cli_provider::middleware::{async_trait, RunMiddleware, RunRequest};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Usage by period (`YYYY-MM`), then by adapter.
+pub type QuotaLedger = BTreeMap<String, BTreeMap<String, QuotaUsage>>;
+
+/// Usage accumulated over a period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    /// CLI runs started.
+    pub runs: u64,
+    /// Tokens the model read, including cached prompt tokens.
+    pub input_tokens: u64,
+    /// Tokens the model generated.
+    pub output_tokens: u64,
+}
+
+impl QuotaUsage {
+    /// One run, with no tokens.
+    pub const RUN: Self = Self {
+        runs: 1,
+        input_tokens: 0,
+        output_tokens: 0,
+    };
+
+    /// Input and output tokens together.
+    #[must_use]
+    pub const fn total_tokens(&self) -> u64 {
+        self.input_tokens.saturating_add(self.output_tokens)
+    }
+
+    /// The value of `metric`.
+    #[must_use]
+    pub const fn get(&self, metric: QuotaMetric) -> u64 {
+        match metric {
+            QuotaMetric::Runs => self.runs,
+            QuotaMetric::InputTokens => self.input_tokens,
+            QuotaMetric::OutputTokens => self.output_tokens,
+            QuotaMetric::TotalTokens => self.total_tokens(),
+        }
+    }
+
+    const fn add(&mut self, other: Self) {
+        self.runs = self.runs.saturating_add(other.runs);
+        self.input_tokens = self.input_tokens.saturating_add(other.input_tokens);
+        self.output_tokens = self.output_tokens.saturating_add(other.output_tokens);
+    }
+}
+
+impl From<crate::response::TokenUsage> for QuotaUsage {
+    fn from(usage: crate::response::TokenUsage) -> Self {
+        Self {
+            runs: 0,
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        }
+    }
+}
+
+/// What a [`QuotaThreshold`] measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaMetric {
+    /// CLI runs started.
+    Runs,
+    /// Input tokens.
+    InputTokens,
+    /// Output tokens.
+    OutputTokens,
+    /// Input and output tokens together.
+    TotalTokens,
+}
+
+impl std::fmt::Display for QuotaMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Runs => "runs",
+            Self::InputTokens => "input tokens",
+            Self::OutputTokens => "output tokens",
+            Self::TotalTokens => "tokens",
+        })
+    }
+}
+
+/// What happens when usage reaches a [`QuotaThreshold`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaAction {
+    /// Log a warning when a recorded run crosses the limit.
+    Warn,
+    /// Refuse runs once the limit is reached.
+    Stop,
+}
+
+/// A monthly limit on one [`QuotaMetric`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaThreshold {
+    /// The adapter whose usage is limited, or `None` for all adapters
+    /// together.
+    pub adapter: Option<CliAdapter>,
+    /// What is limited.
+    pub metric: QuotaMetric,
+    /// The limit per calendar month.
+    pub limit: u64,
+    /// What happens at the limit.
+    pub action: QuotaAction,
+}
+
+impl QuotaThreshold {
+    /// A limit on the usage of all adapters together.
+    #[must_use]
+    pub const fn new(metric: QuotaMetric, limit: u64, action: QuotaAction) -> Self {
+        Self {
+            adapter: None,
+            metric,
+            limit,
+            action,
+        }
+    }
+
+    /// Limits the usage of `adapter` only.
+    #[must_use]
+    pub const fn for_adapter(mut self, adapter: CliAdapter) -> Self {
+        self.adapter = Some(adapter);
+        self
+    }
+
+    fn applies_to(&self, adapter: CliAdapter) -> bool {
+        self.adapter.is_none_or(|limited| limited == adapter)
+    }
+
+    fn scope(&self) -> String {
+        self.adapter
+            .map_or_else(|| "all adapters".to_string(), |adapter| adapter.to_string())
+    }
+}
+
+/// Temporary files written by this process, for unique names.
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Usage persisted in a JSON file, with thresholds checked before each run.
+#[derive(Debug, Clone)]
+pub struct QuotaStore {
+    path: PathBuf,
+    thresholds: Vec<QuotaThreshold>,
+}
+
+impl QuotaStore {
+    /// Opens the store at `path`. The file is created on the first run.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::Io` if an existing file cannot be read, and
+    /// `ProviderError::Validation` if it is not a quota ledger.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ProviderError> {
+        let store = Self {
+            path: path.into(),
+            thresholds: Vec::new(),
+        };
+        store.ledger()?;
+        Ok(store)
+    }
+
+    /// Adds a threshold checked on every run.
+    #[must_use]
+    pub fn with_threshold(mut self, threshold: QuotaThreshold) -> Self {
+        self.thresholds.push(threshold);
+        self
+    }
+
+    /// The file usage is persisted in.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The current period, as `YYYY-MM` in UTC.
+    #[must_use]
+    pub fn current_period() -> String {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        period_of(secs)
+    }
+
+    /// Everything recorded, by period and then adapter.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::Io` if the file cannot be read, and
+    /// `ProviderError::Validation` if it is not a quota ledger.
+    pub fn ledger(&self) -> Result<QuotaLedger, ProviderError> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| {
+                ProviderError::Validation(format!(
+                    "invalid quota file {}: {e}",
+                    self.path.display()
+                ))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(QuotaLedger::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Usage in `period` (`YYYY-MM`) of `adapter`, or of all adapters
+    /// together if `None`.
+    ///
+    /// # Errors
+    ///
+    /// As for [`ledger`](Self::ledger).
+    pub fn usage(
+        &self,
+        period: &str,
+        adapter: Option<CliAdapter>,
+    ) -> Result<QuotaUsage, ProviderError> {
+        Ok(usage_in(&self.ledger()?, period, adapter))
+    }
+
+    /// Checks the stop thresholds for a run of `adapter` in the current
+    /// period.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::Budget` if usage has reached a stop
+    /// threshold, or an error from [`ledger`](Self::ledger).
+    pub fn check(&self, adapter: CliAdapter) -> Result<(), ProviderError> {
+        let period = Self::current_period();
+        let ledger = self.ledger()?;
+        for threshold in self.applicable(adapter, QuotaAction::Stop) {
+            let used = usage_in(&ledger, &period, threshold.adapter).get(threshold.metric);
+            if used >= threshold.limit {
+                return Err(ProviderError::Budget(format!(
+                    "{} quota of {} {} for {period} reached ({used} used)",
+                    threshold.scope(),
+                    threshold.limit,
+                    threshold.metric
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds `usage` of `adapter` to the current period, warning about any
+    /// warn threshold it crosses.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::Io` if the file cannot be locked or written,
+    /// or an error from [`ledger`](Self::ledger).
+    pub fn record(&self, adapter: CliAdapter, usage: QuotaUsage) -> Result<(), ProviderError> {
+        let period = Self::current_period();
+        let lock = self.lock()?;
+        let mut ledger = self.ledger()?;
+        let before = ledger.clone();
+        ledger
+            .entry(period.clone())
+            .or_default()
+            .entry(adapter.to_string())
+            .or_default()
+            .add(usage);
+        self.write(&ledger)?;
+        drop(lock);
+
+        for threshold in self.applicable(adapter, QuotaAction::Warn) {
+            let was = usage_in(&before, &period, threshold.adapter).get(threshold.metric);
+            let now = usage_in(&ledger, &period, threshold.adapter).get(threshold.metric);
+            if was < threshold.limit && now >= threshold.limit {
+                tracing::warn!(
+                    scope = %threshold.scope(),
+                    metric = %threshold.metric,
+                    limit = threshold.limit,
+                    used = now,
+                    %period,
+                    "usage quota threshold crossed"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn applicable(
+        &self,
+        adapter: CliAdapter,
+        action: QuotaAction,
+    ) -> impl Iterator<Item = &QuotaThreshold> {
+        self.thresholds
+            .iter()
+            .filter(move |threshold| threshold.action == action && threshold.applies_to(adapter))
+    }
+
+    /// Takes the exclusive lock on `<path>.lock`, held until the returned
+    /// file is dropped.
+    fn lock(&self) -> Result<std::fs::File, ProviderError> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.sibling("lock"))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// Replaces the file with `ledger`, through a temporary file so readers
+    /// never see a partial write.
+    fn write(&self, ledger: &QuotaLedger) -> Result<(), ProviderError> {
+        let json = serde_json::to_string_pretty(ledger).map_err(std::io::Error::other)?;
+        let tmp = self.sibling(&format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        if let Err(e) = std::fs::write(&tmp, json).and_then(|()| std::fs::rename(&tmp, &self.path))
+        {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// `<path>.<suffix>`.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".");
+        path.push(suffix);
+        path.into()
+    }
+}
+
+/// Checks and counts the runs of MCP agents built from a
+/// [`ClientConfig`](crate::config::ClientConfig) with a quota.
+pub(crate) struct QuotaMiddleware(pub(crate) QuotaStore);
+
+#[async_trait]
+impl RunMiddleware for QuotaMiddleware {
+    async fn before_run(
+        &self,
+        request: &mut RunRequest,
+    ) -> Result<Option<McpToolAgentResult>, ProviderError> {
+        self.0.check(request.adapter())?;
+        self.0.record(request.adapter(), QuotaUsage::RUN)?;
+        Ok(None)
+    }
+}
+
+/// Usage in `period` of `adapter`, or of every adapter if `None`.
+fn usage_in(ledger: &QuotaLedger, period: &str, adapter: Option<CliAdapter>) -> QuotaUsage {
+    let mut total = QuotaUsage::default();
+    let Some(adapters) = ledger.get(period) else {
+        return total;
+    };
+    match adapter {
+        Some(adapter) => {
+            if let Some(usage) = adapters.get(&adapter.to_string()) {
+                total.add(*usage);
+            }
+        }
+        None => adapters.values().for_each(|usage| total.add(*usage)),
+    }
+    total
+}
+
+/// The UTC calendar month, as `YYYY-MM`, of `secs` since the Unix epoch.
+fn period_of(secs: u64) -> String {
+    // Civil-from-days conversion on the proleptic Gregorian calendar, with
+    // eras of 400 years starting on March 1st.
+    let days = secs / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!("{year:04}-{month:02}")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_of_calendar_months() {
+        assert_eq!(period_of(0), "1970-01");
+        assert_eq!(period_of(951_782_400), "2000-02");
+        assert_eq!(period_of(1_709_251_199), "2024-02");
+        assert_eq!(period_of(1_709_251_200), "2024-03");
+        assert_eq!(period_of(1_735_689_599), "2024-12");
+    }
+
+    #[test]
+    fn test_usage_persists_across_reopens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quota").join("usage.json");
+        let period = QuotaStore::current_period();
+
+        let store = QuotaStore::open(&path).unwrap();
+        store
+            .record(CliAdapter::ClaudeCode, QuotaUsage::RUN)
+            .unwrap();
+        store
+            .record(
+                CliAdapter::ClaudeCode,
+                QuotaUsage {
+                    runs: 0,
+                    input_tokens: 100,
+                    output_tokens: 20,
+                },
+            )
+            .unwrap();
+        store.record(CliAdapter::Codex, QuotaUsage::RUN).unwrap();
+
+        let reopened = QuotaStore::open(&path).unwrap();
+        let claude = reopened
+            .usage(&period, Some(CliAdapter::ClaudeCode))
+            .unwrap();
+        assert_eq!(
+            claude,
+            QuotaUsage {
+                runs: 1,
+                input_tokens: 100,
+                output_tokens: 20,
+            }
+        );
+        let all = reopened.usage(&period, None).unwrap();
+        assert_eq!(all.runs, 2);
+        assert_eq!(all.total_tokens(), 120);
+        assert_eq!(
+            reopened.usage("1999-01", None).unwrap(),
+            QuotaUsage::default()
+        );
+    }
+
+    #[test]
+    fn test_stop_threshold_refuses_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = QuotaStore::open(dir.path().join("usage.json"))
+            .unwrap()
+            .with_threshold(
+                QuotaThreshold::new(QuotaMetric::Runs, 1, QuotaAction::Stop)
+                    .for_adapter(CliAdapter::Codex),
+            )
+            .with_threshold(QuotaThreshold::new(QuotaMetric::Runs, 1, QuotaAction::Warn));
+
+        store.check(CliAdapter::Codex).unwrap();
+        store.record(CliAdapter::Codex, QuotaUsage::RUN).unwrap();
+
+        let err = store.check(CliAdapter::Codex).unwrap_err();
+        assert!(matches!(err, ProviderError::Budget(_)), "{err}");
+        assert!(err.to_string().contains("Codex quota of 1 runs"), "{err}");
+        store.check(CliAdapter::ClaudeCode).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_records_are_all_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usage.json");
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let store = QuotaStore::open(&path).unwrap();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        store.record(CliAdapter::Codex, QuotaUsage::RUN).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let store = QuotaStore::open(&path).unwrap();
+        let usage = store
+            .usage(&QuotaStore::current_period(), Some(CliAdapter::Codex))
+            .unwrap();
+        assert_eq!(usage.runs, 100);
+        let leftovers = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|entry| {
+                let entry = entry.as_ref().unwrap();
+                entry.file_name().to_string_lossy().ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_open_rejects_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usage.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            QuotaStore::open(&path),
+            Err(ProviderError::Validation(_))
+        ));
+    }
+}
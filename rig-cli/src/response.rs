@@ -1,11 +1,18 @@
 //! Shared response type for CLI agent execution.
 
+use rig::completion::{GetTokenUsage, Usage};
+use rig_cli_provider::events::StreamEvent;
 use serde::{Deserialize, Serialize};
 
+pub use rig_cli_process_core::LatencyBreakdown;
+
 /// Response from a CLI agent execution.
 ///
-/// This is rig-cli's response type, not an adapter internal.
-/// Access raw adapter output via `.raw_output` for debugging.
+/// This is rig-cli's response type, not an adapter internal. Rig exposes it
+/// as `response.raw_response`, and as the final response of a stream once
+/// the CLI exits; besides the answer it carries the CLI's
+/// stderr, parsed stream events, and whatever session metadata the adapter
+/// reports, for debugging.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CliResponse {
     /// The text content of the agent's response.
@@ -14,16 +21,125 @@ pub struct CliResponse {
     pub exit_code: i32,
     /// Wall-clock duration in milliseconds.
     pub duration_ms: u64,
+    /// CLI session the response belongs to, when the adapter reports one.
+    ///
+    /// For Claude Code, pass it to
+    /// [`Client::resume_agent`](crate::claude::Client::resume_agent) to
+    /// continue the conversation.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Whether the run timed out and `text` is only the output produced
+    /// before the timeout.
+    ///
+    /// Only set when [`ClientConfig::on_timeout`](crate::config::ClientConfig::on_timeout)
+    /// is [`TimeoutBehavior::ReturnPartial`](crate::config::TimeoutBehavior::ReturnPartial).
+    #[serde(default)]
+    pub truncated: bool,
+    /// Captured standard error.
+    #[serde(default)]
+    pub stderr: String,
+    /// JSON events the CLI streamed during the run, in order.
+    ///
+    /// Empty for adapters that print plain text.
+    #[serde(default)]
+    pub stream_events: Vec<serde_json::Value>,
+    /// Provider-agnostic events of a streamed run, in order, followed by the
+    /// session and usage the run reported.
+    ///
+    /// Empty for completions; see [`stream_events`](Self::stream_events)
+    /// for the CLI's raw output.
+    #[serde(default)]
+    pub events: Vec<StreamEvent>,
+    /// Model that answered, when the adapter reports one.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Token counts for the run, when the adapter reports them.
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+    /// Agentic turns the run took, when the adapter reports them.
+    #[serde(default)]
+    pub num_turns: Option<u32>,
+    /// Time to spawn, to the first text and tool call, and in total, for
+    /// performance monitoring.
+    ///
+    /// `None` for timed-out runs, which end before the adapter reports it.
+    #[serde(default)]
+    pub latency: Option<LatencyBreakdown>,
+}
+
+/// Token counts reported by a CLI for a run.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// Tokens the model read, including cached prompt tokens.
+    pub input_tokens: u64,
+    /// Tokens the model generated.
+    pub output_tokens: u64,
+}
+
+impl From<rig_cli_claude::TokenUsage> for TokenUsage {
+    fn from(usage: rig_cli_claude::TokenUsage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        }
+    }
+}
+
+impl GetTokenUsage for CliResponse {
+    fn token_usage(&self) -> Option<Usage> {
+        self.usage.map(|tokens| {
+            let mut usage = Usage::new();
+            usage.input_tokens = tokens.input_tokens;
+            usage.output_tokens = tokens.output_tokens;
+            usage.total_tokens = tokens.input_tokens + tokens.output_tokens;
+            usage
+        })
+    }
 }
 
 impl CliResponse {
     /// Creates a new `CliResponse` from adapter-internal types.
     #[must_use]
-    pub const fn from_run_result(stdout: String, exit_code: i32, duration_ms: u64) -> Self {
+    pub const fn from_run_result(
+        stdout: String,
+        stderr: String,
+        exit_code: i32,
+        duration_ms: u64,
+    ) -> Self {
         Self {
             text: stdout,
             exit_code,
             duration_ms,
+            session_id: None,
+            truncated: false,
+            stderr,
+            stream_events: Vec::new(),
+            events: Vec::new(),
+            model: None,
+            usage: None,
+            num_turns: None,
+            latency: None,
+        }
+    }
+
+    /// Creates a response from the output a timed-out run produced.
+    ///
+    /// The exit code is `-1`, as the process was killed.
+    #[must_use]
+    pub const fn from_partial(text: String, duration_ms: u64) -> Self {
+        Self {
+            text,
+            exit_code: -1,
+            duration_ms,
+            session_id: None,
+            truncated: true,
+            stderr: String::new(),
+            stream_events: Vec::new(),
+            events: Vec::new(),
+            model: None,
+            usage: None,
+            num_turns: None,
+            latency: None,
         }
     }
 }
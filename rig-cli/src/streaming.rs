@@ -0,0 +1,126 @@
+//! Provider streams assembled from adapter events and the run's outcome.
+
+use crate::response::CliResponse;
+use futures::StreamExt;
+use rig::completion::CompletionError;
+use rig::streaming::{RawStreamingChoice, RawStreamingToolCall, StreamingCompletionResponse};
+use rig_cli_provider::events::StreamEvent;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+/// Builds the stream a provider's `CompletionModel::stream` returns.
+///
+/// `events` carries the adapter's events while the CLI runs; each is mapped
+/// to a [`StreamEvent`], recorded, and converted by [`choice`]. `done`
+/// carries the run's outcome once the CLI exits: success ends the stream
+/// with a final [`CliResponse`] built by `response`, holding the recorded
+/// events in [`events`](CliResponse::events), and failure with a provider
+/// error. Events always come first, as the adapter drops its sender before
+/// returning.
+pub fn assemble<E, R, X>(
+    events: mpsc::Receiver<E>,
+    done: oneshot::Receiver<Result<R, X>>,
+    partial: bool,
+    response: fn(R) -> CliResponse,
+) -> StreamingCompletionResponse<CliResponse>
+where
+    E: Into<StreamEvent> + Send + 'static,
+    R: Send + 'static,
+    X: std::fmt::Display + Send + 'static,
+{
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let record = Arc::clone(&recorded);
+
+    let outcome = futures::stream::once(done).filter_map(move |outcome| {
+        let recorded = Arc::clone(&recorded);
+        async move {
+            match outcome {
+                Ok(Ok(result)) => {
+                    let mut response = response(result);
+                    let events = std::mem::take(
+                        &mut *recorded
+                            .lock()
+                            .unwrap_or_else(std::sync::PoisonError::into_inner),
+                    );
+                    response.events = finish_events(events, &response);
+                    Some(Ok(RawStreamingChoice::FinalResponse(response)))
+                }
+                Ok(Err(e)) => Some(Err(CompletionError::ProviderError(e.to_string()))),
+                // The run task was dropped without reporting; the events are all
+                // there is.
+                Err(_) => None,
+            }
+        }
+    });
+
+    let stream = ReceiverStream::new(events)
+        .map(move |event| {
+            let event = event.into();
+            let yielded = choice(&event, partial);
+            record
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .push(event);
+            yielded
+        })
+        .chain(outcome);
+    StreamingCompletionResponse::stream(Box::pin(stream))
+}
+
+/// Converts a stream event into the choice the stream yields.
+///
+/// With `partial` set, text streams as deltas and each complete message is
+/// reduced to a heartbeat so it isn't yielded twice. Events with no place in
+/// the assistant's answer become empty messages, which act as heartbeats;
+/// they are still recorded in [`CliResponse::events`].
+fn choice(
+    event: &StreamEvent,
+    partial: bool,
+) -> Result<RawStreamingChoice<CliResponse>, CompletionError> {
+    match event {
+        // With partial messages the text already arrived as deltas
+        StreamEvent::Text(_) if partial => Ok(RawStreamingChoice::Message(String::new())),
+        StreamEvent::Text(text) | StreamEvent::TextDelta(text) => {
+            Ok(RawStreamingChoice::Message(text.clone()))
+        }
+        StreamEvent::ToolCall { id, name, input } => {
+            let id = id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+            Ok(RawStreamingChoice::ToolCall(RawStreamingToolCall::new(
+                id,
+                name.clone(),
+                input.clone(),
+            )))
+        }
+        StreamEvent::Error(message) => Err(CompletionError::ProviderError(message.clone())),
+        // Tool results are input for the next turn, and subagent output is
+        // not part of the assistant's answer
+        StreamEvent::ToolResult { .. }
+        | StreamEvent::Usage { .. }
+        | StreamEvent::SessionInfo { .. }
+        | StreamEvent::Subagent { .. }
+        | StreamEvent::Stalled { .. }
+        | StreamEvent::Progress(_)
+        | StreamEvent::Warning(_)
+        | StreamEvent::ValidationWarning { .. }
+        | StreamEvent::Raw(_) => Ok(RawStreamingChoice::Message(String::new())),
+    }
+}
+
+/// Appends the session and usage the finished run reported to its events.
+fn finish_events(mut events: Vec<StreamEvent>, response: &CliResponse) -> Vec<StreamEvent> {
+    if response.session_id.is_some() || response.model.is_some() {
+        events.push(StreamEvent::SessionInfo {
+            session_id: response.session_id.clone(),
+            model: response.model.clone(),
+        });
+    }
+    if let Some(usage) = response.usage {
+        events.push(StreamEvent::Usage {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        });
+    }
+    events
+}
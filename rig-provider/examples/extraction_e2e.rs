@@ -3,13 +3,15 @@
 //! This example verifies the full extraction pipeline:
 //! 1. Discovers and initializes Claude Code CLI
 //! 2. Creates an `ExtractionOrchestrator` with a JSON schema
-//! 3. Wraps the Claude CLI as the `agent_fn` closure
+//! 3. Wraps the Claude CLI as the `agent_fn` closure, reporting token usage
 //! 4. Extracts structured data and validates the result
 //!
 //! Run with: `cargo run --example extraction_e2e`
 
 use rig_cli_claude::{init, ClaudeCli, OutputFormat, RunConfig};
-use rig_cli_mcp::extraction::{ExtractionConfig, ExtractionOrchestrator};
+use rig_cli_mcp::extraction::{
+    AgentResponse, ExtractionConfig, ExtractionOrchestrator, TokenUsage,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -55,17 +57,25 @@ async fn main() -> anyhow::Result<()> {
 
     let cli_clone = cli.clone();
     let result = orchestrator
-        .extract(
+        .extract_with_usage(
             |prompt_text| {
                 let cli = cli_clone.clone();
                 async move {
+                    // JSON output carries the token usage alongside the answer.
                     let config = RunConfig {
-                        output_format: Some(OutputFormat::Text),
+                        output_format: Some(OutputFormat::Json),
                         ..RunConfig::default()
                     };
                     cli.run(&prompt_text, &config)
                         .await
-                        .map(|r| r.stdout)
+                        .map(|r| AgentResponse {
+                            text: r.final_text().to_string(),
+                            usage: r.usage().map(|u| TokenUsage {
+                                input_tokens: u.input_tokens,
+                                output_tokens: u.output_tokens,
+                            }),
+                            ..AgentResponse::default()
+                        })
                         .map_err(|e| e.to_string())
                 }
             },
@@ -80,8 +90,11 @@ async fn main() -> anyhow::Result<()> {
             println!("\nMetrics:");
             println!("  Attempts: {}", metrics.total_attempts);
             println!("  Wall time: {:?}", metrics.wall_time);
-            println!("  Est. input tokens: {}", metrics.estimated_input_tokens);
-            println!("  Est. output tokens: {}", metrics.estimated_output_tokens);
+            println!(
+                "  Input tokens: {} ({} of {} attempts reported)",
+                metrics.input_tokens, metrics.reported_usage_attempts, metrics.total_attempts
+            );
+            println!("  Output tokens: {}", metrics.output_tokens);
 
             // Deserialize to the typed struct to verify
             let summary: ProjectSummary = serde_json::from_value(value)?;
@@ -97,7 +97,9 @@ impl CompletionModel for ClaudeModel {
         // Convert the receiver into a stream
         let stream = ReceiverStream::new(rx).map(|event| {
             match event {
-                StreamEvent::Text { text } => Ok(RawStreamingChoice::Message(text)),
+                StreamEvent::Text { text } | StreamEvent::TextDelta { text } => {
+                    Ok(RawStreamingChoice::Message(text))
+                }
                 StreamEvent::ToolCall { name, input } => {
                     let id = Uuid::new_v4().to_string();
                     let tool_call = RawStreamingToolCall::new(id, name, input);
@@ -109,8 +111,31 @@ impl CompletionModel for ClaudeModel {
                     // Empty message acts as a no-op heartbeat
                     Ok(RawStreamingChoice::Message(String::new()))
                 }
+                StreamEvent::SubagentStart {
+                    id,
+                    agent,
+                    description,
+                    prompt,
+                } => {
+                    let input = serde_json::json!({
+                        "subagent_type": agent,
+                        "description": description,
+                        "prompt": prompt,
+                    });
+                    Ok(RawStreamingChoice::ToolCall(RawStreamingToolCall::new(
+                        id,
+                        "Task".to_string(),
+                        input,
+                    )))
+                }
+                // Subagent output, CLI status and unknown events are not
+                // part of the assistant's answer
+                StreamEvent::Subagent { .. }
+                | StreamEvent::Stalled { .. }
+                | StreamEvent::Progress { .. }
+                | StreamEvent::Warning { .. }
+                | StreamEvent::Unknown(_) => Ok(RawStreamingChoice::Message(String::new())),
                 StreamEvent::Error { message } => Err(CompletionError::ProviderError(message)),
-                StreamEvent::Unknown(_) => Ok(RawStreamingChoice::Message(String::new())),
             }
         });
 
@@ -135,8 +160,9 @@ impl ClaudeTool {
     /// Returns a `ClaudeError` if initialization or discovery fails.
     pub async fn new(mcp_configs: Vec<String>) -> Result<Self, rig_cli_claude::ClaudeError> {
         let report = init(None).await?;
+        let version = report.cli_version();
         Ok(Self {
-            cli: ClaudeCli::new(report.claude_path, report.capabilities),
+            cli: ClaudeCli::new(report.claude_path, report.capabilities).with_version(version),
             mcp_configs,
             manager: SessionManager::new(),
         })
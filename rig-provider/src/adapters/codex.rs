@@ -94,7 +94,10 @@ impl CompletionModel for CodexModel {
         let stream = ReceiverStream::new(rx).map(|event| match event {
             StreamEvent::Text { text } => Ok(RawStreamingChoice::Message(text)),
             StreamEvent::Error { message } => Err(CompletionError::ProviderError(message)),
-            StreamEvent::Unknown(_) => Ok(RawStreamingChoice::Message(String::new())),
+            StreamEvent::Stalled { .. }
+            | StreamEvent::Progress { .. }
+            | StreamEvent::Warning { .. }
+            | StreamEvent::Unknown(_) => Ok(RawStreamingChoice::Message(String::new())),
         });
 
         Ok(StreamingCompletionResponse::stream(Box::pin(stream)))
@@ -0,0 +1,320 @@
+//! Per-run artifact directories for MCP tool agents.
+//!
+//! With [`McpToolAgentBuilder::artifacts_dir`] set, every run keeps what was
+//! produced for it in a directory of its own under the configured one, named
+//! `<unix-millis>-<random>` so a listing sorts by start time:
+//!
+//! | File | Contents |
+//! |------|----------|
+//! | `prompt.txt` | User prompt as sent to the CLI, including any payload |
+//! | `system_prompt.txt` | System prompt, including the workflow instructions |
+//! | `mcp_config.json` | The generated MCP server configs, in `mcpServers` form |
+//! | `result.json` | The submitted result, if the agent submitted one |
+//! | `stdout.log` / `stderr.log` | Captured CLI output |
+//! | `tool_calls.json` | Harvested tool calls, with [`McpToolAgentBuilder::harvest_tool_calls`] |
+//! | `index.json` | An [`ArtifactIndex`] listing the files above, the outcome and timings |
+//!
+//! The inputs are written before the CLI starts, so a run that never returns
+//! still leaves them behind. `index.json` is written last; a directory
+//! without one belongs to a run that is still going or was killed.
+//!
+//! Nothing is ever removed. The MCP config holds the server's environment,
+//! including values set with
+//! [`McpToolAgentBuilder::extra_env`]; credentials are not written.
+//!
+//! [`McpToolAgentBuilder::artifacts_dir`]: crate::mcp_agent::McpToolAgentBuilder::artifacts_dir
+//! [`McpToolAgentBuilder::harvest_tool_calls`]: crate::mcp_agent::McpToolAgentBuilder::harvest_tool_calls
+//! [`McpToolAgentBuilder::extra_env`]: crate::mcp_agent::McpToolAgentBuilder::extra_env
+
+use crate::backend::BackendRequest;
+use crate::errors::ProviderError;
+use crate::mcp_agent::{CliAdapter, McpToolAgentResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+/// Name of the manifest written into every run directory.
+pub const INDEX_FILE: &str = "index.json";
+
+/// Manifest of a run directory, written to [`INDEX_FILE`] when the run ends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactIndex {
+    /// Name of the run directory.
+    pub run_id: String,
+    /// Adapter that ran the prompt.
+    pub adapter: String,
+    /// When the run started, in milliseconds since the Unix epoch.
+    pub started_at_ms: u64,
+    /// Time from the start of the run until the index was written.
+    pub total_ms: u64,
+    /// Wall-clock time the CLI reported, when it ran to completion.
+    #[serde(default)]
+    pub cli_duration_ms: Option<u64>,
+    /// Exit code of the CLI, when it ran to completion.
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Error code and message, when the run failed.
+    #[serde(default)]
+    pub error: Option<ArtifactError>,
+    /// Files in the run directory, in the order they were written.
+    pub files: Vec<ArtifactFile>,
+}
+
+/// The error a failed run ended with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactError {
+    /// Stable code of the [`ProviderError`] variant.
+    pub code: String,
+    /// The error's message.
+    pub message: String,
+}
+
+/// One file recorded in an [`ArtifactIndex`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactFile {
+    /// File name within the run directory.
+    pub name: String,
+    /// What the file holds.
+    pub description: String,
+    /// Size in bytes.
+    pub bytes: u64,
+}
+
+/// Directory collecting one run's artifacts.
+///
+/// Writing an artifact never fails the run: errors are logged and the file
+/// is left out of the index.
+#[derive(Debug)]
+pub(crate) struct RunArtifacts {
+    dir: PathBuf,
+    run_id: String,
+    adapter: CliAdapter,
+    started: Instant,
+    started_at_ms: u64,
+    files: Vec<ArtifactFile>,
+}
+
+impl RunArtifacts {
+    /// Creates a fresh run directory under `base`, creating `base` too if
+    /// needed.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Spawn`] if the directory cannot be created.
+    pub(crate) fn create(base: &Path, adapter: CliAdapter) -> Result<Self, ProviderError> {
+        let started_at_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            });
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let run_id = format!("{started_at_ms}-{}", &suffix[..8]);
+        let dir = base.join(&run_id);
+        std::fs::create_dir_all(&dir).map_err(|source| ProviderError::Spawn {
+            stage: "create artifacts dir",
+            source,
+        })?;
+        Ok(Self {
+            dir,
+            run_id,
+            adapter,
+            started: Instant::now(),
+            started_at_ms,
+            files: Vec::new(),
+        })
+    }
+
+    /// The run directory.
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes the prompts and MCP config the CLI is about to be given.
+    pub(crate) fn record_request(&mut self, request: &BackendRequest) {
+        self.write("prompt.txt", "user prompt", request.prompt.as_bytes());
+        self.write(
+            "system_prompt.txt",
+            "system prompt",
+            request.system_prompt.as_bytes(),
+        );
+        self.write_json(
+            "mcp_config.json",
+            "MCP server config",
+            &request.mcp_servers_json(),
+        );
+    }
+
+    /// Writes the run's output and the [`ArtifactIndex`], consuming the
+    /// directory.
+    pub(crate) fn finish(mut self, outcome: &Result<McpToolAgentResult, ProviderError>) {
+        let (cli_duration_ms, exit_code, error) = match outcome {
+            Ok(result) => {
+                if let Some(ref submit) = result.submit_result {
+                    self.write("result.json", "submitted result", submit.as_bytes());
+                }
+                self.write("stdout.log", "CLI stdout", result.stdout.as_bytes());
+                self.write("stderr.log", "CLI stderr", result.stderr.as_bytes());
+                if let Some(ref harvest) = result.tool_harvest {
+                    self.write_json("tool_calls.json", "harvested tool calls", harvest);
+                }
+                (Some(result.duration_ms), Some(result.exit_code), None)
+            }
+            Err(e) => {
+                let error = ArtifactError {
+                    code: e.error_code().to_string(),
+                    message: e.to_string(),
+                };
+                (None, None, Some(error))
+            }
+        };
+
+        let index = ArtifactIndex {
+            run_id: std::mem::take(&mut self.run_id),
+            adapter: self.adapter.to_string(),
+            started_at_ms: self.started_at_ms,
+            total_ms: u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            cli_duration_ms,
+            exit_code,
+            error,
+            files: std::mem::take(&mut self.files),
+        };
+        // Not routed through `write`, which would list the index in itself.
+        let written = serde_json::to_vec_pretty(&index)
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(self.dir.join(INDEX_FILE), json));
+        if let Err(e) = written {
+            self.warn(INDEX_FILE, &e);
+        }
+    }
+
+    /// Serializes `value` as pretty JSON into `name`.
+    fn write_json(&mut self, name: &str, description: &str, value: &impl Serialize) {
+        match serde_json::to_vec_pretty(value) {
+            Ok(json) => self.write(name, description, &json),
+            Err(e) => self.warn(name, &e.into()),
+        }
+    }
+
+    /// Writes `contents` to `name` and records it for the index.
+    fn write(&mut self, name: &str, description: &str, contents: &[u8]) {
+        match std::fs::write(self.dir.join(name), contents) {
+            Ok(()) => self.files.push(ArtifactFile {
+                name: name.to_string(),
+                description: description.to_string(),
+                bytes: contents.len() as u64,
+            }),
+            Err(e) => self.warn(name, &e),
+        }
+    }
+
+    fn warn(&self, name: &str, error: &std::io::Error) {
+        tracing::warn!(
+            dir = %self.dir.display(),
+            file = name,
+            error = %error,
+            "Failed to write run artifact"
+        );
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn request() -> BackendRequest {
+        BackendRequest {
+            prompt: "Extract the invoice".to_string(),
+            system_prompt: "Use the tools".to_string(),
+            mcp_config: rig_cli_mcp::server::McpConfig {
+                name: "rig_mcp".to_string(),
+                command: "/usr/bin/agent".to_string(),
+                args: Vec::new(),
+                env: std::collections::HashMap::new(),
+            },
+            extra_mcp_configs: Vec::new(),
+            allowed_tools: vec!["mcp__rig_mcp__submit".to_string()],
+            builtin_tools: None,
+            sandbox_mode: rig_cli_codex::SandboxMode::ReadOnly,
+            timeout: std::time::Duration::from_secs(60),
+            cwd: PathBuf::from("/tmp"),
+            env: vec![("ANTHROPIC_API_KEY".to_string(), "sk-secret".to_string())],
+            cli_path: None,
+            models: crate::models::DefaultModels::default(),
+            backpressure: crate::mcp_agent::BackpressurePolicy::Block,
+            mcp_delivery: crate::mcp_agent::McpDelivery::ConfigFile,
+            isolated: false,
+            images: Vec::new(),
+            stderr_events: None,
+        }
+    }
+
+    fn read_index(dir: &Path) -> ArtifactIndex {
+        serde_json::from_slice(&std::fs::read(dir.join(INDEX_FILE)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_run_artifacts_record_inputs_outputs_and_index() {
+        let base = tempfile::tempdir().unwrap();
+        let mut artifacts = RunArtifacts::create(base.path(), CliAdapter::ClaudeCode).unwrap();
+        let dir = artifacts.dir().to_path_buf();
+        assert_eq!(dir.parent(), Some(base.path()));
+
+        artifacts.record_request(&request());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("prompt.txt")).unwrap(),
+            "Extract the invoice"
+        );
+        assert!(!dir.join(INDEX_FILE).exists());
+
+        let mut result =
+            McpToolAgentResult::from_output("progress".to_string(), "warning".to_string(), 0, 1500);
+        result.submit_result = Some(r#"{"total":42}"#.to_string());
+        artifacts.finish(&Ok(result));
+
+        let index = read_index(&dir);
+        assert_eq!(index.run_id, dir.file_name().unwrap().to_str().unwrap());
+        assert_eq!(index.adapter, CliAdapter::ClaudeCode.to_string());
+        assert_eq!(
+            (index.cli_duration_ms, index.exit_code),
+            (Some(1500), Some(0))
+        );
+        assert!(index.error.is_none());
+        let names: Vec<&str> = index.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "prompt.txt",
+                "system_prompt.txt",
+                "mcp_config.json",
+                "result.json",
+                "stdout.log",
+                "stderr.log"
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("result.json")).unwrap(),
+            r#"{"total":42}"#
+        );
+
+        let everything: String = index
+            .files
+            .iter()
+            .map(|f| std::fs::read_to_string(dir.join(&f.name)).unwrap())
+            .collect();
+        assert!(!everything.contains("sk-secret"));
+    }
+
+    #[test]
+    fn test_run_artifacts_record_failed_run() {
+        let base = tempfile::tempdir().unwrap();
+        let artifacts = RunArtifacts::create(base.path(), CliAdapter::Codex).unwrap();
+        let dir = artifacts.dir().to_path_buf();
+        artifacts.finish(&Err(ProviderError::Cancelled));
+
+        let index = read_index(&dir);
+        let error = index.error.unwrap();
+        assert_eq!(error.code, "cancelled");
+        assert!(index.exit_code.is_none());
+        assert_eq!(index.files, []);
+    }
+}
@@ -0,0 +1,438 @@
+//! Binary attachments sent alongside a payload.
+//!
+//! A [`payload`](crate::mcp_agent::McpToolAgentBuilder::payload) is text.
+//! Images, PDFs and other files that are not UTF-8 go in a
+//! [`BinaryPayload`] instead, each rendered as one `<attachment>` element
+//! after the payload in the prompt's `<context>` block. An attachment is
+//! either inlined as base64 or staged as a file the agent reads:
+//!
+//! ```text
+//! <attachment name="totals.bin" mime="application/octet-stream" bytes="12" encoding="base64">
+//! AAECAwQFBgcICQoL
+//! </attachment>
+//! <attachment name="scan.png" mime="image/png" bytes="48213" path="attachments/scan.png"/>
+//! ```
+//!
+//! With [`AttachmentStrategy::Auto`], the default, images Claude can view
+//! (PNG, JPEG, GIF and WebP) are staged when the run uses Claude Code,
+//! whose `Read` tool shows them to the model; everything else is inlined
+//! up to [`max_inline_bytes`](Attachments::max_inline_bytes) and staged
+//! beyond it. The strategy is picked for the run's main adapter.
+//!
+//! Staged files are written to an `attachments` directory in the temp
+//! working directory, or, with a caller-provided
+//! [`working_dir`](crate::mcp_agent::McpToolAgentBuilder::working_dir), to
+//! a temp directory of their own referenced by absolute path. Runs with
+//! staged attachments get the `Read` builtin opted in to.
+//!
+//! ```no_run
+//! use rig_cli_provider::attachments::{Attachments, BinaryPayload};
+//!
+//! # fn example() -> Result<(), rig_cli_provider::errors::ProviderError> {
+//! let attachments = Attachments::new()
+//!     .attachment(BinaryPayload::from_path("./invoice.pdf")?)
+//!     .attachment(BinaryPayload::new("logo.png", std::fs::read("./logo.png")?));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::ProviderError;
+use crate::mcp_agent::CliAdapter;
+use crate::payload::escape_attr;
+use base64::Engine as _;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Default cap on one attachment: 20 MiB.
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: usize = 20 * 1024 * 1024;
+
+/// Default size up to which [`AttachmentStrategy::Auto`] inlines an
+/// attachment: 48 KiB, 64 KiB once encoded.
+pub const DEFAULT_MAX_INLINE_BYTES: usize = 48 * 1024;
+
+/// MIME types of the images Claude can view.
+const CLAUDE_IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Directory staged attachments are written to.
+const STAGING_DIR: &str = "attachments";
+
+/// A named blob of bytes with its MIME type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryPayload {
+    name: String,
+    mime: String,
+    bytes: Vec<u8>,
+}
+
+impl BinaryPayload {
+    /// Wraps `bytes` under `name`, detecting the MIME type from their
+    /// contents: a known file signature, else `text/plain` for UTF-8 and
+    /// `application/octet-stream` for anything else.
+    #[must_use]
+    pub fn new(name: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        let bytes = bytes.into();
+        Self {
+            name: name.into(),
+            mime: detect_mime(&bytes).to_string(),
+            bytes,
+        }
+    }
+
+    /// Reads the file at `path`, named after its file name.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Io`] if the file cannot be read.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .map_or_else(|| "attachment".into(), |name| name.to_string_lossy());
+        Ok(Self::new(name, std::fs::read(path)?))
+    }
+
+    /// Overrides the detected MIME type.
+    #[must_use]
+    pub fn with_mime(mut self, mime: impl Into<String>) -> Self {
+        self.mime = mime.into();
+        self
+    }
+
+    /// The attachment's name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The attachment's MIME type.
+    #[must_use]
+    pub fn mime(&self) -> &str {
+        &self.mime
+    }
+
+    /// The attachment's contents.
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Whether Claude can view the attachment as an image.
+    #[must_use]
+    pub fn is_claude_image(&self) -> bool {
+        CLAUDE_IMAGE_TYPES.contains(&self.mime.as_str())
+    }
+}
+
+/// How attachments reach the agent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttachmentStrategy {
+    /// Decide per attachment from its type, size and the adapter; see
+    /// [`crate::attachments`].
+    #[default]
+    Auto,
+    /// Base64-encode every attachment into the prompt.
+    Inline,
+    /// Write every attachment to a file the agent reads.
+    Stage,
+}
+
+/// The binary attachments of a run.
+#[derive(Debug, Clone)]
+pub struct Attachments {
+    items: Vec<BinaryPayload>,
+    strategy: AttachmentStrategy,
+    max_bytes: usize,
+    max_inline_bytes: usize,
+}
+
+impl Default for Attachments {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Attachments {
+    /// An empty set with the default strategy and limits.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            strategy: AttachmentStrategy::Auto,
+            max_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
+            max_inline_bytes: DEFAULT_MAX_INLINE_BYTES,
+        }
+    }
+
+    /// Adds an attachment. Names must be plain file names, unique within
+    /// the set.
+    #[must_use]
+    pub fn attachment(mut self, attachment: BinaryPayload) -> Self {
+        self.items.push(attachment);
+        self
+    }
+
+    /// Sets how attachments reach the agent (default:
+    /// [`AttachmentStrategy::Auto`]).
+    #[must_use]
+    pub const fn strategy(mut self, strategy: AttachmentStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the largest attachment a run accepts (default:
+    /// [`DEFAULT_MAX_ATTACHMENT_BYTES`]).
+    #[must_use]
+    pub const fn max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = max;
+        self
+    }
+
+    /// Sets the largest attachment [`AttachmentStrategy::Auto`] inlines
+    /// (default: [`DEFAULT_MAX_INLINE_BYTES`]).
+    #[must_use]
+    pub const fn max_inline_bytes(mut self, max: usize) -> Self {
+        self.max_inline_bytes = max;
+        self
+    }
+
+    /// The attachments, in the order added.
+    #[must_use]
+    pub fn items(&self) -> &[BinaryPayload] {
+        &self.items
+    }
+
+    /// Returns `true` when there are no attachments.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Whether `attachment` is inlined on a run with `adapter`.
+    fn inlines(&self, attachment: &BinaryPayload, adapter: CliAdapter) -> bool {
+        match self.strategy {
+            AttachmentStrategy::Inline => true,
+            AttachmentStrategy::Stage => false,
+            AttachmentStrategy::Auto => {
+                let viewable = adapter == CliAdapter::ClaudeCode && attachment.is_claude_image();
+                !viewable && attachment.bytes.len() <= self.max_inline_bytes
+            }
+        }
+    }
+
+    /// Checks the attachments and renders them for a run with `adapter`.
+    ///
+    /// Staged files go to `cwd`'s `attachments` directory when the run owns
+    /// `cwd`, and to a new temp directory otherwise.
+    pub(crate) fn render(
+        &self,
+        adapter: CliAdapter,
+        owned_cwd: Option<&Path>,
+    ) -> Result<RenderedAttachments, ProviderError> {
+        self.check()?;
+        let mut rendered = RenderedAttachments::default();
+        for attachment in &self.items {
+            let open = format!(
+                "<attachment name=\"{}\" mime=\"{}\" bytes=\"{}\"",
+                escape_attr(&attachment.name),
+                escape_attr(&attachment.mime),
+                attachment.bytes.len()
+            );
+            if self.inlines(attachment, adapter) {
+                let encoded = base64::engine::general_purpose::STANDARD.encode(&attachment.bytes);
+                let _ = writeln!(
+                    rendered.text,
+                    "{open} encoding=\"base64\">\n{encoded}\n</attachment>"
+                );
+            } else {
+                let shown = rendered.stage(attachment, owned_cwd)?;
+                let _ = writeln!(
+                    rendered.text,
+                    "{open} path=\"{}\"/>",
+                    escape_attr(&shown.display().to_string())
+                );
+            }
+        }
+        if rendered.staged {
+            rendered.text.insert_str(
+                0,
+                "Attachments with a `path` are files; read them from there. \
+                 Images are shown to you when read.\n",
+            );
+        }
+        Ok(rendered)
+    }
+
+    /// Rejects oversized attachments and names that are not unique plain
+    /// file names.
+    fn check(&self) -> Result<(), ProviderError> {
+        for (i, attachment) in self.items.iter().enumerate() {
+            let name = &attachment.name;
+            let plain = Path::new(name)
+                .file_name()
+                .is_some_and(|file_name| file_name == name.as_str());
+            if !plain {
+                return Err(ProviderError::Validation(format!(
+                    "attachment name '{name}' must be a plain file name"
+                )));
+            }
+            if self.items[..i].iter().any(|other| &other.name == name) {
+                return Err(ProviderError::Validation(format!(
+                    "attachment name '{name}' is used more than once"
+                )));
+            }
+            if attachment.bytes.len() > self.max_bytes {
+                return Err(ProviderError::Validation(format!(
+                    "attachment '{name}' is {} bytes, over the {} byte limit",
+                    attachment.bytes.len(),
+                    self.max_bytes
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Attachments rendered for one run.
+#[derive(Debug, Default)]
+pub(crate) struct RenderedAttachments {
+    /// The `<attachment>` elements, for the `<context>` block.
+    pub(crate) text: String,
+    /// Whether any attachment was written to a file.
+    pub(crate) staged: bool,
+    /// Keeps staged files alive when the run does not own its cwd.
+    pub(crate) dir: Option<tempfile::TempDir>,
+}
+
+impl RenderedAttachments {
+    /// Writes `attachment` out and returns the path the prompt shows.
+    fn stage(
+        &mut self,
+        attachment: &BinaryPayload,
+        owned_cwd: Option<&Path>,
+    ) -> Result<PathBuf, ProviderError> {
+        let spawn_error = |source| ProviderError::Spawn {
+            stage: "stage attachment",
+            source,
+        };
+        let dir = if let Some(cwd) = owned_cwd {
+            cwd.join(STAGING_DIR)
+        } else if let Some(ref dir) = self.dir {
+            dir.path().to_path_buf()
+        } else {
+            let dir = tempfile::Builder::new()
+                .prefix("rig_attachments_")
+                .tempdir()
+                .map_err(spawn_error)?;
+            self.dir.insert(dir).path().to_path_buf()
+        };
+        std::fs::create_dir_all(&dir).map_err(spawn_error)?;
+        std::fs::write(dir.join(&attachment.name), &attachment.bytes).map_err(spawn_error)?;
+        self.staged = true;
+        Ok(if owned_cwd.is_some() {
+            Path::new(STAGING_DIR).join(&attachment.name)
+        } else {
+            dir.join(&attachment.name)
+        })
+    }
+}
+
+/// The MIME type of `bytes`.
+fn detect_mime(bytes: &[u8]) -> &'static str {
+    match infer::get(bytes) {
+        Some(kind) => kind.mime_type(),
+        None if std::str::from_utf8(bytes).is_ok() => "text/plain",
+        None => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = &[
+        0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 0x0d,
+    ];
+
+    #[test]
+    fn test_mime_is_detected_from_contents() {
+        assert_eq!(BinaryPayload::new("a", PNG).mime(), "image/png");
+        assert!(BinaryPayload::new("a", PNG).is_claude_image());
+        assert_eq!(BinaryPayload::new("a", "plain").mime(), "text/plain");
+        assert_eq!(
+            BinaryPayload::new("a", [0xff, 0xfe, 0x00]).mime(),
+            "application/octet-stream"
+        );
+        let custom = BinaryPayload::new("a", [0xff]).with_mime("application/x-custom");
+        assert_eq!(custom.mime(), "application/x-custom");
+    }
+
+    #[test]
+    fn test_auto_stages_images_for_claude_and_inlines_the_rest() {
+        let cwd = tempfile::tempdir().unwrap();
+        let attachments = Attachments::new()
+            .attachment(BinaryPayload::new("scan.png", PNG))
+            .attachment(BinaryPayload::new("data.bin", [0xff, 0x00, 0x01]));
+
+        let rendered = attachments
+            .render(CliAdapter::ClaudeCode, Some(cwd.path()))
+            .unwrap();
+        assert!(rendered.staged);
+        assert!(rendered.dir.is_none());
+        assert!(rendered
+            .text
+            .contains("<attachment name=\"scan.png\" mime=\"image/png\" bytes=\"12\" path=\"attachments/scan.png\"/>"));
+        assert!(rendered.text.contains(
+            "<attachment name=\"data.bin\" mime=\"application/octet-stream\" bytes=\"3\" encoding=\"base64\">\n/wAB\n</attachment>"
+        ));
+        assert_eq!(
+            std::fs::read(cwd.path().join("attachments/scan.png")).unwrap(),
+            PNG
+        );
+
+        let codex = attachments.render(CliAdapter::Codex, None).unwrap();
+        assert!(!codex.staged);
+        assert!(codex
+            .text
+            .contains("encoding=\"base64\">\niVBORw0KGgoAAAAN\n"));
+    }
+
+    #[test]
+    fn test_staging_outside_an_owned_cwd_uses_a_temp_dir() {
+        let attachments = Attachments::new()
+            .strategy(AttachmentStrategy::Stage)
+            .attachment(BinaryPayload::new("notes.txt", "hi"));
+        let rendered = attachments.render(CliAdapter::OpenCode, None).unwrap();
+        let dir = rendered.dir.as_ref().unwrap().path();
+        let staged = dir.join("notes.txt");
+        assert_eq!(std::fs::read_to_string(&staged).unwrap(), "hi");
+        assert!(rendered
+            .text
+            .contains(&format!("path=\"{}\"/>", staged.display())));
+    }
+
+    #[test]
+    fn test_invalid_attachments_are_rejected() {
+        let render = |attachments: Attachments| {
+            attachments
+                .render(CliAdapter::Codex, None)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(
+            render(Attachments::new().attachment(BinaryPayload::new("../x", "x")))
+                .contains("plain file name")
+        );
+        assert!(render(
+            Attachments::new()
+                .attachment(BinaryPayload::new("x", "x"))
+                .attachment(BinaryPayload::new("x", "y"))
+        )
+        .contains("more than once"));
+        assert!(render(
+            Attachments::new()
+                .max_bytes(1)
+                .attachment(BinaryPayload::new("x", "xy"))
+        )
+        .contains("over the 1 byte limit"));
+    }
+}
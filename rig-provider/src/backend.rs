@@ -0,0 +1,1178 @@
+//! Pluggable CLI backends for MCP tool agents.
+//!
+//! A [`CliBackend`] knows how to find one CLI, check that it works, and run an
+//! MCP-enforced prompt through it, either to completion or while streaming
+//! [`McpStreamEvent`]s. [`McpToolAgentBuilder`] and [`CliAgent`] only talk to
+//! this trait: [`CliAdapter::backend`] returns the built-in backends, and
+//! [`McpToolAgentBuilder::backend`] accepts any other implementation, so
+//! crates outside this workspace can drive CLIs of their own.
+//!
+//! [`McpToolAgentBuilder`]: crate::mcp_agent::McpToolAgentBuilder
+//! [`McpToolAgentBuilder::backend`]: crate::mcp_agent::McpToolAgentBuilder::backend
+//! [`CliAgent`]: crate::mcp_agent::CliAgent
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rig_cli_provider::backend::{async_trait, BackendCapabilities, BackendRequest, CliBackend};
+//! use rig_cli_provider::errors::ProviderError;
+//! use rig_cli_provider::mcp_agent::{CliAdapter, McpToolAgentResult};
+//! use std::path::PathBuf;
+//!
+//! struct EchoBackend;
+//!
+//! #[async_trait]
+//! impl CliBackend for EchoBackend {
+//!     fn adapter(&self) -> CliAdapter {
+//!         CliAdapter::Custom("echo")
+//!     }
+//!
+//!     fn capabilities(&self) -> BackendCapabilities {
+//!         BackendCapabilities::default()
+//!     }
+//!
+//!     async fn discover(&self, explicit: Option<PathBuf>) -> Result<PathBuf, ProviderError> {
+//!         Ok(explicit.unwrap_or_else(|| PathBuf::from("echo")))
+//!     }
+//!
+//!     async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+//!         Ok(McpToolAgentResult::from_output(request.prompt, String::new(), 0, 0))
+//!     }
+//! }
+//! ```
+
+use crate::errors::ProviderError;
+use crate::mcp_agent::{
+    BackpressurePolicy, CliAdapter, McpDelivery, McpStreamEvent, McpToolAgentResult,
+};
+use crate::models::DefaultModels;
+use rig_cli_mcp::server::McpConfig;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Re-exported so backends can be implemented without a direct dependency.
+pub use async_trait::async_trait;
+
+/// Resolved CLI commands, as returned by [`CliBackend::dry_run`].
+pub use rig_cli_claude::{ConfigFile, Invocation};
+
+/// A server's state as the CLI reports it, from [`CliBackend::check_mcp`].
+pub use rig_cli_claude::mcp::McpServerStatus;
+
+/// Inputs of one MCP-enforced run, resolved by the agent builder.
+#[derive(Debug, Clone)]
+pub struct BackendRequest {
+    /// User prompt, including any payload context.
+    pub prompt: String,
+    /// System prompt, including the workflow instructions and MCP tool list.
+    pub system_prompt: String,
+    /// MCP server the CLI must connect to.
+    pub mcp_config: McpConfig,
+    /// Further MCP servers the CLI connects to, one per
+    /// [namespace](crate::mcp_agent::McpToolAgentBuilder::namespace).
+    pub extra_mcp_configs: Vec<McpConfig>,
+    /// MCP tool names (`mcp__<server>__<tool>`) the CLI may call.
+    pub allowed_tools: Vec<String>,
+    /// Builtin tools opted in to; `None` disables all of them.
+    pub builtin_tools: Option<Vec<String>>,
+    /// Sandbox isolation level, for CLIs that have one.
+    pub sandbox_mode: rig_cli_codex::SandboxMode,
+    /// Maximum wall-clock time for the run.
+    pub timeout: Duration,
+    /// Working directory of the CLI process.
+    pub cwd: PathBuf,
+    /// Extra environment variables for the CLI process, such as credentials.
+    pub env: Vec<(String, String)>,
+    /// CLI binary to use instead of discovering one.
+    pub cli_path: Option<PathBuf>,
+    /// Model of each built-in adapter; a backend passes the CLI its own.
+    pub models: DefaultModels,
+    /// Delivery of stream events when the consumer falls behind.
+    pub backpressure: BackpressurePolicy,
+    /// How the MCP server reaches the CLI, for CLIs that support more than
+    /// a config file.
+    pub mcp_delivery: McpDelivery,
+    /// Whether the CLI should ignore the host user's configuration; see
+    /// [`crate::isolation`]. The agent points [`env`](Self::env) at a
+    /// scratch home; a backend adds the CLI flags that skip settings files.
+    pub isolated: bool,
+    /// Image files shown to the model with the prompt. Only sent to
+    /// backends reporting [`BackendCapabilities::images`].
+    pub images: Vec<PathBuf>,
+    /// Patterns classifying the CLI's stderr lines into
+    /// [`McpStreamEvent::Progress`] and [`McpStreamEvent::Warning`] while
+    /// streaming; `None` only captures stderr.
+    pub stderr_events: Option<rig_cli_claude::StderrPatterns>,
+}
+
+impl BackendRequest {
+    /// The main MCP server followed by the
+    /// [extra ones](Self::extra_mcp_configs).
+    pub fn mcp_configs(&self) -> impl Iterator<Item = &McpConfig> {
+        std::iter::once(&self.mcp_config).chain(&self.extra_mcp_configs)
+    }
+
+    /// Every MCP server, in the `mcpServers` form of Claude's `--mcp-config`.
+    #[must_use]
+    pub fn mcp_servers_json(&self) -> serde_json::Value {
+        let servers: serde_json::Map<String, serde_json::Value> = self
+            .mcp_configs()
+            .map(|config| {
+                let entry = config.to_claude_json()["mcpServers"][&config.name].clone();
+                (config.name.clone(), entry)
+            })
+            .collect();
+        serde_json::json!({ "mcpServers": servers })
+    }
+}
+
+/// What a [`CliBackend`] enforces beyond running a prompt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+// Independent flags, one per feature a CLI may enforce.
+#[allow(clippy::struct_excessive_bools)]
+pub struct BackendCapabilities {
+    /// Streams report [`McpStreamEvent::ToolCall`] and
+    /// [`McpStreamEvent::ToolResult`], not only text.
+    pub tool_events: bool,
+    /// [`BackendRequest::builtin_tools`] is enforced by the CLI.
+    pub builtin_tools: bool,
+    /// [`BackendRequest::sandbox_mode`] is enforced by the CLI.
+    pub sandbox: bool,
+    /// [`BackendRequest::images`] are shown to the model.
+    pub images: bool,
+}
+
+/// Outcome of [`CliBackend::health`].
+#[derive(Debug, Clone)]
+pub struct BackendHealth {
+    /// Resolved path of the CLI.
+    pub cli_path: PathBuf,
+    /// Version reported by `<cli> --version`, if it could be parsed.
+    pub version: Option<semver::Version>,
+}
+
+/// A CLI that can run MCP-enforced prompts.
+///
+/// Only [`run`](Self::run) does real work; [`health`](Self::health) and
+/// [`stream`](Self::stream) fall back to discovery and to a single text event
+/// respectively.
+#[async_trait]
+pub trait CliBackend: Send + Sync {
+    /// Names the backend in errors, reports and middleware requests, and
+    /// selects the credential variables loaded for it.
+    fn adapter(&self) -> CliAdapter;
+
+    /// Features the CLI enforces.
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Resolves the CLI binary, preferring `explicit` when set.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Discovery`] if the CLI cannot be found.
+    async fn discover(&self, explicit: Option<PathBuf>) -> Result<PathBuf, ProviderError>;
+
+    /// Resolves the CLI and reports its version, without sending a prompt.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Discovery`] if the CLI cannot be found.
+    async fn health(&self, explicit: Option<PathBuf>) -> Result<BackendHealth, ProviderError> {
+        let cli_path = self.discover(explicit).await?;
+        Ok(BackendHealth {
+            cli_path,
+            version: None,
+        })
+    }
+
+    /// Runs the prompt to completion.
+    ///
+    /// The submit result is read from the MCP server by the caller, so
+    /// [`McpToolAgentResult::submit_result`] is left empty here.
+    ///
+    /// # Errors
+    /// Returns an error if the CLI cannot be found, configured or run.
+    async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError>;
+
+    /// Runs the prompt, sending progress events to `tx` while the CLI runs.
+    ///
+    /// Returns once the CLI exits. Send errors are ignored: the consumer may
+    /// stop reading at any time.
+    ///
+    /// # Errors
+    /// Returns an error if the CLI cannot be found, configured or run.
+    async fn stream(
+        &self,
+        request: BackendRequest,
+        tx: mpsc::Sender<McpStreamEvent>,
+    ) -> Result<McpToolAgentResult, ProviderError> {
+        let result = self.run(request).await?;
+        if !result.stdout.is_empty() {
+            let _ = tx.send(McpStreamEvent::Text(result.stdout.clone())).await;
+        }
+        Ok(result)
+    }
+
+    /// Resolves the CLI and builds the command [`run`](Self::run) would
+    /// spawn, without spawning it. Temporary config files are written and
+    /// removed again; their contents are reported in the [`Invocation`].
+    ///
+    /// # Errors
+    /// Returns an error if the CLI cannot be found or configured. The default
+    /// returns [`ProviderError::Validation`], for backends that cannot report
+    /// their command.
+    async fn dry_run(&self, _request: BackendRequest) -> Result<Invocation, ProviderError> {
+        Err(ProviderError::Validation(format!(
+            "the {} backend does not support dry runs",
+            self.adapter()
+        )))
+    }
+
+    /// Has the CLI at `cli_path` load `mcp_config` in `cwd`, with `env`
+    /// added to its environment, and connect to the server, without sending
+    /// a prompt. Returns the state the CLI reports; the default returns
+    /// `None`, for CLIs without such a check.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::McpConfig`] if the CLI cannot load the config.
+    async fn check_mcp(
+        &self,
+        _cli_path: &Path,
+        _mcp_config: &McpConfig,
+        _cwd: &Path,
+        _env: &[(String, String)],
+    ) -> Result<Option<McpServerStatus>, ProviderError> {
+        Ok(None)
+    }
+}
+
+/// Version requirements for CLI adapters. Hardcoded per adapter, not configurable.
+struct VersionRequirement {
+    /// Minimum supported version (below this = unsupported, warn).
+    min_version: semver::Version,
+    /// Maximum tested version (above this = untested, warn with different message).
+    max_tested: semver::Version,
+    /// CLI name for log messages.
+    cli_name: &'static str,
+}
+
+/// Version requirement for Claude Code CLI.
+const fn claude_code_version_req() -> VersionRequirement {
+    VersionRequirement {
+        min_version: semver::Version::new(1, 0, 0),
+        max_tested: semver::Version::new(2, 99, 0),
+        cli_name: "Claude Code",
+    }
+}
+
+/// Version requirement for Codex CLI.
+const fn codex_version_req() -> VersionRequirement {
+    VersionRequirement {
+        min_version: semver::Version::new(0, 1, 0),
+        max_tested: semver::Version::new(0, 99, 0),
+        cli_name: "Codex",
+    }
+}
+
+/// Version requirement for `OpenCode` CLI.
+const fn opencode_version_req() -> VersionRequirement {
+    VersionRequirement {
+        min_version: semver::Version::new(0, 1, 0),
+        max_tested: semver::Version::new(0, 99, 0),
+        cli_name: "OpenCode",
+    }
+}
+
+/// Detects CLI version and validates against requirements.
+///
+/// Runs `<binary> --version`, parses the version string with semver,
+/// and emits structured tracing warnings for unsupported or untested versions.
+/// Never fails — version issues are warnings, never blockers — and returns
+/// the parsed version when there is one.
+async fn detect_and_validate_version(
+    binary_path: &Path,
+    requirement: &VersionRequirement,
+) -> Option<semver::Version> {
+    let output = match tokio::process::Command::new(binary_path)
+        .arg("--version")
+        .output()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) => {
+            tracing::warn!(
+                event = "version_detection_failed",
+                cli = requirement.cli_name,
+                error = %e,
+                "version_detection_failed"
+            );
+            return None;
+        }
+    };
+
+    let version_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    // Extract version substring: strip common prefixes like "v", split on whitespace
+    // to handle formats like "claude 1.2.3" or "codex v0.91.0"
+    let cleaned = extract_version_string(&version_str);
+
+    let version = match semver::Version::parse(&cleaned) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::warn!(
+                event = "version_parse_failed",
+                cli = requirement.cli_name,
+                raw_version = %version_str,
+                error = %e,
+                "version_parse_failed"
+            );
+            return None;
+        }
+    };
+
+    tracing::debug!(
+        event = "version_detected",
+        cli = requirement.cli_name,
+        version = %version,
+        "version_detected"
+    );
+
+    if version < requirement.min_version {
+        tracing::warn!(
+            event = "version_unsupported",
+            cli = requirement.cli_name,
+            detected = %version,
+            minimum = %requirement.min_version,
+            "version_unsupported: {} {} is below minimum supported version {}",
+            requirement.cli_name,
+            version,
+            requirement.min_version,
+        );
+    } else if version > requirement.max_tested {
+        tracing::warn!(
+            event = "version_untested",
+            cli = requirement.cli_name,
+            detected = %version,
+            max_tested = %requirement.max_tested,
+            "version_untested: {} {} is newer than maximum tested version {}",
+            requirement.cli_name,
+            version,
+            requirement.max_tested,
+        );
+    }
+
+    Some(version)
+}
+
+/// Extracts a semver-parseable version string from CLI version output.
+///
+/// Handles common formats:
+/// - "1.2.3" -> "1.2.3"
+/// - "v1.2.3" -> "1.2.3"
+/// - "claude 1.2.3" -> "1.2.3"
+/// - "codex v0.91.0-beta" -> "0.91.0-beta"
+fn extract_version_string(raw: &str) -> String {
+    // Split on whitespace and find the token that looks like a version
+    for token in raw.split_whitespace() {
+        let stripped = token.strip_prefix('v').unwrap_or(token);
+        if semver::Version::parse(stripped).is_ok() {
+            return stripped.to_string();
+        }
+    }
+    // Fallback: try stripping 'v' from the whole string
+    raw.strip_prefix('v').unwrap_or(raw).to_string()
+}
+
+/// Writes an MCP config to a temp file that is deleted when the guard drops.
+fn write_config_file(
+    config: &serde_json::Value,
+) -> Result<(PathBuf, tempfile::TempPath), ProviderError> {
+    let mut config_file = tempfile::NamedTempFile::new()
+        .map_err(|e| ProviderError::mcp_config("failed to create config file", e))?;
+    let json = serde_json::to_string_pretty(config)
+        .map_err(|e| ProviderError::mcp_config("failed to serialize config", e))?;
+    config_file
+        .write_all(json.as_bytes())
+        .map_err(|e| ProviderError::mcp_config("failed to write config", e))?;
+    let config_path = config_file.path().to_path_buf();
+    Ok((config_path, config_file.into_temp_path()))
+}
+
+/// Converts adapter events onto `tx` until the adapter closes its channel.
+///
+/// Runs alongside the CLI, so a long run never stalls on a full adapter channel.
+async fn forward_events<E: Into<McpStreamEvent>>(
+    mut adapter_rx: mpsc::Receiver<E>,
+    tx: &mpsc::Sender<McpStreamEvent>,
+) {
+    while let Some(event) = adapter_rx.recv().await {
+        // Send converted event (ignore if receiver dropped)
+        let _ = tx.send(event.into()).await;
+    }
+}
+
+/// The MCP server a Claude Code run was given, kept until the run ends.
+enum ClaudeMcpServer {
+    /// Config file passed with `--mcp-config`, deleted on drop.
+    File { _path: tempfile::TempPath },
+    /// Servers registered with `claude mcp add-json` in `cwd`.
+    Registered {
+        cli: rig_cli_claude::ClaudeCli,
+        names: Vec<String>,
+        cwd: PathBuf,
+        env: Vec<(String, String)>,
+    },
+    /// Nothing was set up, as for a dry run.
+    Unregistered,
+}
+
+impl ClaudeMcpServer {
+    /// Removes registered servers. A failure is only logged, so it never
+    /// hides the run's own outcome.
+    async fn release(self) {
+        if let Self::Registered {
+            cli,
+            names,
+            cwd,
+            env,
+        } = self
+        {
+            for name in names {
+                if let Err(e) = cli.mcp_remove(&name, &cwd, &env).await {
+                    tracing::warn!(
+                        server = %name,
+                        cwd = %cwd.display(),
+                        error = %e,
+                        "Failed to remove MCP server registration"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Backend for the Claude Code CLI (`claude --print`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClaudeCodeBackend;
+
+impl ClaudeCodeBackend {
+    /// Discovers the CLI, makes the MCP server available to it and builds
+    /// its run config.
+    ///
+    /// The returned server must be [released](ClaudeMcpServer::release) once
+    /// the run ends. With `register` unset, a server delivered by
+    /// [`McpDelivery::CliRegistration`] is left out instead of registered.
+    async fn prepare(
+        request: &BackendRequest,
+        output_format: rig_cli_claude::OutputFormat,
+        register: bool,
+    ) -> Result<
+        (
+            rig_cli_claude::ClaudeCli,
+            rig_cli_claude::RunConfig,
+            ClaudeMcpServer,
+        ),
+        ProviderError,
+    > {
+        let report = rig_cli_claude::init(request.cli_path.clone())
+            .await
+            .map_err(|e| ProviderError::discovery(CliAdapter::ClaudeCode, e))?;
+
+        // Detect and validate CLI version
+        let version =
+            detect_and_validate_version(&report.claude_path, &claude_code_version_req()).await;
+
+        let cli = rig_cli_claude::ClaudeCli::new(report.claude_path, report.capabilities)
+            .with_version(version);
+
+        let (mcp, server) = match request.mcp_delivery {
+            McpDelivery::ConfigFile => {
+                let (config_path, config_guard) = write_config_file(&request.mcp_servers_json())?;
+                let mcp = rig_cli_claude::McpPolicy {
+                    // Temp file paths are always valid UTF-8 (created by tempfile crate).
+                    configs: vec![config_path.to_string_lossy().to_string()],
+                    strict: true,
+                };
+                (
+                    Some(mcp),
+                    ClaudeMcpServer::File {
+                        _path: config_guard,
+                    },
+                )
+            }
+            McpDelivery::CliRegistration if register => {
+                let registered = |names| ClaudeMcpServer::Registered {
+                    cli: cli.clone(),
+                    names,
+                    cwd: request.cwd.clone(),
+                    env: request.env.clone(),
+                };
+                let servers = request.mcp_servers_json();
+                let mut names = Vec::new();
+                for (name, entry) in servers["mcpServers"].as_object().into_iter().flatten() {
+                    let added = cli
+                        .mcp_add_json(name, entry, &request.cwd, &request.env)
+                        .await;
+                    if let Err(e) = added {
+                        // Servers registered so far would outlive the run.
+                        registered(names).release().await;
+                        return Err(ProviderError::mcp_config("failed to register server", e));
+                    }
+                    names.push(name.clone());
+                }
+                (None, registered(names))
+            }
+            McpDelivery::CliRegistration => (None, ClaudeMcpServer::Unregistered),
+        };
+
+        // Apply containment: disable all builtins by default, opt-in via builtin_tools
+        let builtin_set = request
+            .builtin_tools
+            .as_ref()
+            .map_or(rig_cli_claude::BuiltinToolSet::None, |tools| {
+                rig_cli_claude::BuiltinToolSet::Explicit(tools.clone())
+            });
+
+        let config = rig_cli_claude::RunConfig {
+            model: request
+                .models
+                .get(CliAdapter::ClaudeCode)
+                .map(str::to_string),
+            output_format: Some(output_format),
+            system_prompt: rig_cli_claude::SystemPromptMode::Append(request.system_prompt.clone()),
+            mcp,
+            tools: rig_cli_claude::ToolPolicy {
+                builtin: builtin_set,
+                allowed: Some(request.allowed_tools.clone()),
+                disallowed: None,
+                disable_slash_commands: true,
+            },
+            timeout: request.timeout,
+            cwd: Some(request.cwd.clone()),
+            no_session_persistence: true,
+            setting_sources: request.isolated.then(String::new),
+            images: request.images.clone(),
+            env: request.env.clone(),
+            backpressure: request.backpressure.into(),
+            stderr_events: request.stderr_events.clone(),
+            ..rig_cli_claude::RunConfig::default()
+        };
+
+        Ok((cli, config, server))
+    }
+}
+
+#[async_trait]
+impl CliBackend for ClaudeCodeBackend {
+    fn adapter(&self) -> CliAdapter {
+        CliAdapter::ClaudeCode
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            tool_events: true,
+            builtin_tools: true,
+            sandbox: false,
+            images: true,
+        }
+    }
+
+    async fn discover(&self, explicit: Option<PathBuf>) -> Result<PathBuf, ProviderError> {
+        rig_cli_claude::init(explicit)
+            .await
+            .map(|report| report.claude_path)
+            .map_err(|e| ProviderError::discovery(CliAdapter::ClaudeCode, e))
+    }
+
+    async fn health(&self, explicit: Option<PathBuf>) -> Result<BackendHealth, ProviderError> {
+        let cli_path = self.discover(explicit).await?;
+        let version = detect_and_validate_version(&cli_path, &claude_code_version_req()).await;
+        Ok(BackendHealth { cli_path, version })
+    }
+
+    async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+        let (cli, config, server) =
+            Self::prepare(&request, rig_cli_claude::OutputFormat::Text, true).await?;
+        let result = cli.run(&request.prompt, &config).await;
+        server.release().await;
+        let result = result?;
+        Ok(McpToolAgentResult::from_output(
+            result.stdout,
+            result.stderr,
+            result.exit_code,
+            result.duration_ms,
+        ))
+    }
+
+    async fn stream(
+        &self,
+        request: BackendRequest,
+        tx: mpsc::Sender<McpStreamEvent>,
+    ) -> Result<McpToolAgentResult, ProviderError> {
+        let (cli, config, server) =
+            Self::prepare(&request, rig_cli_claude::OutputFormat::StreamJson, true).await?;
+
+        // The adapter sender is moved into the run, so the channel closes
+        // (ending the forwarder) as soon as the CLI finishes.
+        let (adapter_tx, adapter_rx) = mpsc::channel(100);
+        let (result, ()) = tokio::join!(
+            cli.stream(&request.prompt, &config, adapter_tx),
+            forward_events(adapter_rx, &tx)
+        );
+        server.release().await;
+        let result = result?;
+        let _ = tx
+            .send(McpStreamEvent::SessionInfo {
+                session_id: result.session_id.clone(),
+                model: result.model(),
+            })
+            .await;
+        if let Some(usage) = result.usage() {
+            let _ = tx
+                .send(McpStreamEvent::Usage {
+                    input_tokens: usage.input_tokens,
+                    output_tokens: usage.output_tokens,
+                })
+                .await;
+        }
+        Ok(McpToolAgentResult::from_output(
+            result.stdout,
+            result.stderr,
+            result.exit_code,
+            result.duration_ms,
+        ))
+    }
+
+    async fn dry_run(&self, request: BackendRequest) -> Result<Invocation, ProviderError> {
+        // Registering would change the CLI's config, which a dry run must not.
+        let (cli, config, _server) =
+            Self::prepare(&request, rig_cli_claude::OutputFormat::Text, false).await?;
+        Ok(cli.dry_run(&request.prompt, &config)?)
+    }
+
+    async fn check_mcp(
+        &self,
+        cli_path: &Path,
+        mcp_config: &McpConfig,
+        cwd: &Path,
+        env: &[(String, String)],
+    ) -> Result<Option<McpServerStatus>, ProviderError> {
+        let (config_path, _config_guard) = write_config_file(&mcp_config.to_claude_json())?;
+        let servers = rig_cli_claude::mcp::list(cli_path, &config_path, cwd, env)
+            .await
+            .map_err(|e| ProviderError::mcp_config("CLI failed to load the MCP config", e))?;
+        let status = servers
+            .into_iter()
+            .find(|server| server.name == mcp_config.name)
+            .unwrap_or_else(|| McpServerStatus {
+                name: mcp_config.name.clone(),
+                connected: false,
+                status: "not loaded by the CLI".to_string(),
+            });
+        Ok(Some(status))
+    }
+}
+
+/// Backend for the Codex CLI (`codex exec`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CodexBackend;
+
+impl CodexBackend {
+    /// Discovers the CLI and builds its run config.
+    async fn prepare(
+        &self,
+        request: &BackendRequest,
+    ) -> Result<(rig_cli_codex::CodexCli, rig_cli_codex::CodexConfig), ProviderError> {
+        let path = self.discover(request.cli_path.clone()).await?;
+
+        // Detect and validate CLI version
+        let cli_version = detect_and_validate_version(&path, &codex_version_req()).await;
+
+        let cli = rig_cli_codex::CodexCli::new(path);
+
+        // Codex reads MCP server config from its config.toml. Inject via -c overrides.
+        let overrides = request
+            .mcp_configs()
+            .flat_map(McpConfig::to_codex_overrides)
+            .collect();
+
+        let config = rig_cli_codex::CodexConfig {
+            model: request.models.get(CliAdapter::Codex).map(str::to_string),
+            full_auto: false,
+            sandbox: Some(request.sandbox_mode.clone()),
+            skip_git_repo_check: true,
+            cd: Some(request.cwd.clone()),
+            system_prompt: Some(request.system_prompt.clone()),
+            overrides,
+            timeout: request.timeout,
+            env_vars: request.env.clone(),
+            backpressure: request.backpressure.into(),
+            stderr_events: request.stderr_events.clone(),
+            cli_version,
+            ..rig_cli_codex::CodexConfig::default()
+        };
+
+        Ok((cli, config))
+    }
+}
+
+#[async_trait]
+impl CliBackend for CodexBackend {
+    fn adapter(&self) -> CliAdapter {
+        CliAdapter::Codex
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            tool_events: false,
+            builtin_tools: false,
+            sandbox: true,
+            images: false,
+        }
+    }
+
+    async fn discover(&self, explicit: Option<PathBuf>) -> Result<PathBuf, ProviderError> {
+        rig_cli_codex::discover_codex(explicit)
+            .map_err(|e| ProviderError::discovery(CliAdapter::Codex, e))
+    }
+
+    async fn health(&self, explicit: Option<PathBuf>) -> Result<BackendHealth, ProviderError> {
+        let cli_path = self.discover(explicit).await?;
+        let version = detect_and_validate_version(&cli_path, &codex_version_req()).await;
+        Ok(BackendHealth { cli_path, version })
+    }
+
+    async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+        let (cli, config) = self.prepare(&request).await?;
+        let result = cli.run(&request.prompt, &config).await?;
+        Ok(McpToolAgentResult::from_output(
+            result.stdout,
+            result.stderr,
+            result.exit_code,
+            result.duration_ms,
+        ))
+    }
+
+    async fn stream(
+        &self,
+        request: BackendRequest,
+        tx: mpsc::Sender<McpStreamEvent>,
+    ) -> Result<McpToolAgentResult, ProviderError> {
+        let (cli, config) = self.prepare(&request).await?;
+
+        // The adapter sender is moved into the run, so the channel closes
+        // (ending the forwarder) as soon as the CLI finishes.
+        let (adapter_tx, adapter_rx) = mpsc::channel(100);
+        let (result, ()) = tokio::join!(
+            cli.stream(&request.prompt, &config, adapter_tx),
+            forward_events(adapter_rx, &tx)
+        );
+        let result = result?;
+        Ok(McpToolAgentResult::from_output(
+            result.stdout,
+            result.stderr,
+            result.exit_code,
+            result.duration_ms,
+        ))
+    }
+
+    async fn dry_run(&self, request: BackendRequest) -> Result<Invocation, ProviderError> {
+        let (cli, config) = self.prepare(&request).await?;
+        Ok(cli.dry_run(&request.prompt, &config))
+    }
+}
+
+/// Backend for the `OpenCode` CLI (`opencode run`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenCodeBackend;
+
+impl OpenCodeBackend {
+    /// Discovers the CLI and builds its run config.
+    ///
+    /// The returned guard keeps the MCP config file alive for the run.
+    async fn prepare(
+        &self,
+        request: &BackendRequest,
+    ) -> Result<
+        (
+            rig_cli_opencode::OpenCodeCli,
+            rig_cli_opencode::OpenCodeConfig,
+            tempfile::TempPath,
+        ),
+        ProviderError,
+    > {
+        let path = self.discover(request.cli_path.clone()).await?;
+
+        // Detect and validate CLI version
+        let cli_version = detect_and_validate_version(&path, &opencode_version_req()).await;
+
+        let cli = rig_cli_opencode::OpenCodeCli::new(path);
+
+        // OpenCode config format: {"mcp": {"name": {"type":"local","command":[...],"environment":{...}}}}
+        let servers: serde_json::Map<String, serde_json::Value> = request
+            .mcp_configs()
+            .map(|mcp_config| {
+                let mut command = vec![mcp_config.command.clone()];
+                command.extend(mcp_config.args.iter().cloned());
+                let entry = serde_json::json!({
+                    "type": "local",
+                    "command": command,
+                    "environment": &mcp_config.env,
+                });
+                (mcp_config.name.clone(), entry)
+            })
+            .collect();
+
+        let opencode_cfg = serde_json::json!({
+            "$schema": "https://opencode.ai/config.json",
+            "mcp": servers,
+        });
+        let (config_path, config_guard) = write_config_file(&opencode_cfg)?;
+
+        let config = rig_cli_opencode::OpenCodeConfig {
+            model: request.models.get(CliAdapter::OpenCode).map(str::to_string),
+            prompt: Some(request.system_prompt.clone()),
+            mcp_config_path: Some(config_path),
+            cwd: Some(request.cwd.clone()),
+            timeout: request.timeout,
+            env_vars: request.env.clone(),
+            backpressure: request.backpressure.into(),
+            stderr_events: request.stderr_events.clone(),
+            cli_version,
+            ..rig_cli_opencode::OpenCodeConfig::default()
+        };
+
+        Ok((cli, config, config_guard))
+    }
+}
+
+#[async_trait]
+impl CliBackend for OpenCodeBackend {
+    fn adapter(&self) -> CliAdapter {
+        CliAdapter::OpenCode
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::default()
+    }
+
+    async fn discover(&self, explicit: Option<PathBuf>) -> Result<PathBuf, ProviderError> {
+        rig_cli_opencode::discover_opencode(explicit)
+            .map_err(|e| ProviderError::discovery(CliAdapter::OpenCode, e))
+    }
+
+    async fn health(&self, explicit: Option<PathBuf>) -> Result<BackendHealth, ProviderError> {
+        let cli_path = self.discover(explicit).await?;
+        let version = detect_and_validate_version(&cli_path, &opencode_version_req()).await;
+        Ok(BackendHealth { cli_path, version })
+    }
+
+    async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+        let (cli, config, _config_guard) = self.prepare(&request).await?;
+        let result = cli.run(&request.prompt, &config).await?;
+        Ok(McpToolAgentResult::from_output(
+            result.stdout,
+            result.stderr,
+            result.exit_code,
+            result.duration_ms,
+        ))
+    }
+
+    async fn stream(
+        &self,
+        request: BackendRequest,
+        tx: mpsc::Sender<McpStreamEvent>,
+    ) -> Result<McpToolAgentResult, ProviderError> {
+        let (cli, config, _config_guard) = self.prepare(&request).await?;
+
+        // The adapter sender is moved into the run, so the channel closes
+        // (ending the forwarder) as soon as the CLI finishes.
+        let (adapter_tx, adapter_rx) = mpsc::channel(100);
+        let (result, ()) = tokio::join!(
+            cli.stream(&request.prompt, &config, adapter_tx),
+            forward_events(adapter_rx, &tx)
+        );
+        let result = result?;
+        Ok(McpToolAgentResult::from_output(
+            result.stdout,
+            result.stderr,
+            result.exit_code,
+            result.duration_ms,
+        ))
+    }
+
+    async fn dry_run(&self, request: BackendRequest) -> Result<Invocation, ProviderError> {
+        let (cli, config, _config_guard) = self.prepare(&request).await?;
+        Ok(cli.dry_run(&request.prompt, &config))
+    }
+}
+
+/// Stand-in for [`CliAdapter::Custom`] when no backend was supplied.
+pub(crate) struct MissingBackend(pub(crate) &'static str);
+
+impl MissingBackend {
+    fn error(&self) -> ProviderError {
+        ProviderError::discovery(
+            CliAdapter::Custom(self.0),
+            format!(
+                "no backend registered for '{}'; set one with `backend()`",
+                self.0
+            ),
+        )
+    }
+}
+
+#[async_trait]
+impl CliBackend for MissingBackend {
+    fn adapter(&self) -> CliAdapter {
+        CliAdapter::Custom(self.0)
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::default()
+    }
+
+    async fn discover(&self, _explicit: Option<PathBuf>) -> Result<PathBuf, ProviderError> {
+        Err(self.error())
+    }
+
+    async fn run(&self, _request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+        Err(self.error())
+    }
+
+    async fn dry_run(&self, _request: BackendRequest) -> Result<Invocation, ProviderError> {
+        Err(self.error())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_version_string_simple() {
+        assert_eq!(extract_version_string("1.2.3"), "1.2.3");
+    }
+
+    #[test]
+    fn test_extract_version_string_with_v_prefix() {
+        assert_eq!(extract_version_string("v1.2.3"), "1.2.3");
+    }
+
+    #[test]
+    fn test_extract_version_string_with_cli_name() {
+        assert_eq!(extract_version_string("claude 1.2.3"), "1.2.3");
+        assert_eq!(extract_version_string("codex v0.91.0"), "0.91.0");
+    }
+
+    #[test]
+    fn test_extract_version_string_with_prerelease() {
+        assert_eq!(extract_version_string("v0.91.0-beta.1"), "0.91.0-beta.1");
+    }
+
+    #[test]
+    fn test_extract_version_string_unparseable_fallback() {
+        // Returns best-effort string even if not valid semver
+        let result = extract_version_string("not-a-version");
+        assert_eq!(result, "not-a-version");
+    }
+
+    #[test]
+    fn test_version_requirement_constants() {
+        let claude_req = claude_code_version_req();
+        assert!(claude_req.min_version < claude_req.max_tested);
+        assert_eq!(claude_req.cli_name, "Claude Code");
+
+        let codex_req = codex_version_req();
+        assert!(codex_req.min_version < codex_req.max_tested);
+        assert_eq!(codex_req.cli_name, "Codex");
+
+        let opencode_req = opencode_version_req();
+        assert!(opencode_req.min_version < opencode_req.max_tested);
+        assert_eq!(opencode_req.cli_name, "OpenCode");
+    }
+
+    #[test]
+    fn test_version_comparison_logic() {
+        let req = claude_code_version_req();
+        let below_min = semver::Version::new(0, 0, 1);
+        let in_range = semver::Version::new(1, 5, 0);
+        let above_max = semver::Version::new(3, 0, 0);
+
+        assert!(below_min < req.min_version);
+        assert!(in_range >= req.min_version && in_range <= req.max_tested);
+        assert!(above_max > req.max_tested);
+    }
+
+    #[test]
+    fn test_builtin_backends_report_their_adapter() {
+        for adapter in [
+            CliAdapter::ClaudeCode,
+            CliAdapter::Codex,
+            CliAdapter::OpenCode,
+        ] {
+            assert_eq!(adapter.backend().adapter(), adapter);
+        }
+        assert!(ClaudeCodeBackend.capabilities().tool_events);
+        assert!(ClaudeCodeBackend.capabilities().images);
+        assert!(CodexBackend.capabilities().sandbox);
+        assert_eq!(
+            OpenCodeBackend.capabilities(),
+            BackendCapabilities::default()
+        );
+    }
+
+    struct EchoBackend;
+
+    #[async_trait]
+    impl CliBackend for EchoBackend {
+        fn adapter(&self) -> CliAdapter {
+            CliAdapter::Custom("echo")
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            BackendCapabilities::default()
+        }
+
+        async fn discover(&self, explicit: Option<PathBuf>) -> Result<PathBuf, ProviderError> {
+            Ok(explicit.unwrap_or_else(|| PathBuf::from("echo")))
+        }
+
+        async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+            Ok(McpToolAgentResult::from_output(
+                request.prompt,
+                String::new(),
+                0,
+                1,
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_stream_sends_stdout_as_text() {
+        let request = BackendRequest {
+            prompt: "hello".to_string(),
+            system_prompt: String::new(),
+            mcp_config: McpConfig {
+                name: "rig_mcp".to_string(),
+                command: "rig".to_string(),
+                args: vec![],
+                env: std::collections::HashMap::new(),
+            },
+            extra_mcp_configs: vec![],
+            allowed_tools: vec![],
+            builtin_tools: None,
+            sandbox_mode: rig_cli_codex::SandboxMode::ReadOnly,
+            timeout: Duration::from_secs(1),
+            cwd: PathBuf::from("."),
+            env: vec![],
+            cli_path: None,
+            models: DefaultModels::default(),
+            backpressure: BackpressurePolicy::Block,
+            mcp_delivery: McpDelivery::ConfigFile,
+            isolated: false,
+            images: Vec::new(),
+            stderr_events: None,
+        };
+        let (tx, mut rx) = mpsc::channel(4);
+
+        let result = EchoBackend.stream(request.clone(), tx).await.unwrap();
+
+        assert_eq!(result.stdout, "hello");
+        assert!(matches!(rx.recv().await, Some(McpStreamEvent::Text(t)) if t == "hello"));
+        assert!(rx.recv().await.is_none());
+        let health = EchoBackend.health(None).await.unwrap();
+        assert_eq!(health.cli_path, PathBuf::from("echo"));
+        assert!(health.version.is_none());
+        assert!(matches!(
+            EchoBackend.dry_run(request).await,
+            Err(ProviderError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_mcp_servers_json_merges_every_server() {
+        let server = |name: &str| McpConfig {
+            name: name.to_string(),
+            command: "rig".to_string(),
+            args: vec![],
+            env: std::collections::HashMap::new(),
+        };
+        let request = BackendRequest {
+            prompt: String::new(),
+            system_prompt: String::new(),
+            mcp_config: server("rig_mcp"),
+            extra_mcp_configs: vec![server("domain")],
+            allowed_tools: vec![],
+            builtin_tools: None,
+            sandbox_mode: rig_cli_codex::SandboxMode::ReadOnly,
+            timeout: Duration::from_secs(1),
+            cwd: PathBuf::from("."),
+            env: vec![],
+            cli_path: None,
+            models: DefaultModels::default(),
+            backpressure: BackpressurePolicy::Block,
+            mcp_delivery: McpDelivery::ConfigFile,
+            isolated: false,
+            images: Vec::new(),
+            stderr_events: None,
+        };
+
+        let json = request.mcp_servers_json();
+        let servers = json["mcpServers"].as_object().unwrap();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers["domain"]["command"], "rig");
+        assert_eq!(
+            request
+                .mcp_configs()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            ["rig_mcp", "domain"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_claude_check_mcp_reads_mcp_list() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let cli = dir.path().join("claude");
+        std::fs::write(
+            &cli,
+            "#!/bin/sh\n[ \"$2\" = list ] && [ -f \"$4\" ] || { echo 'bad config' >&2; exit 1; }\n\
+             echo 'rig_mcp: rig --stdio - ✗ Failed to connect'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&cli, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let config = |name: &str| McpConfig {
+            name: name.to_string(),
+            command: "rig".to_string(),
+            args: vec!["--stdio".to_string()],
+            env: std::collections::HashMap::new(),
+        };
+
+        let status = ClaudeCodeBackend
+            .check_mcp(&cli, &config("rig_mcp"), dir.path(), &[])
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!status.connected);
+        assert_eq!(status.status, "✗ Failed to connect");
+
+        let status = ClaudeCodeBackend
+            .check_mcp(&cli, &config("other"), dir.path(), &[])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.status, "not loaded by the CLI");
+
+        let missing = dir.path().join("missing");
+        let err = ClaudeCodeBackend
+            .check_mcp(&missing, &config("rig_mcp"), dir.path(), &[])
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code(), "mcp_config");
+    }
+}
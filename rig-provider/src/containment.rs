@@ -0,0 +1,257 @@
+//! Pre-spawn checks of the working directory an MCP tool agent runs in.
+//!
+//! Before a run spawns its CLI, the working directory is checked against the
+//! Codex sandbox mode and, with
+//! [`McpToolAgentBuilder::require_containment`](crate::mcp_agent::McpToolAgentBuilder::require_containment),
+//! against places an agent should not be let loose in. Every failed check is
+//! collected into one [`ProviderError::ContainmentMisconfiguration`], so a
+//! misconfigured run is reported before the CLI starts instead of as a
+//! confusing failure halfway through.
+//!
+//! | Check | Applies |
+//! |-------|---------|
+//! | The directory exists and is a directory | Always |
+//! | The directory is writable | Codex with a sandbox that permits writes |
+//! | The directory is not the home directory | Containment required |
+//! | The directory is not a repository root | Containment required |
+//! | The sandbox is not `DangerFullAccess` | Codex with containment required |
+
+use crate::errors::ProviderError;
+use crate::mcp_agent::CliAdapter;
+use rig_cli_codex::SandboxMode;
+use std::path::{Path, PathBuf};
+
+/// One failed working directory check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContainmentViolation {
+    /// The working directory does not exist.
+    Missing,
+    /// The working directory is not a directory.
+    NotADirectory,
+    /// The Codex sandbox permits writes but the directory is not writable.
+    NotWritable {
+        /// The sandbox mode that permits writes.
+        sandbox: SandboxMode,
+    },
+    /// Containment was requested but the directory is the user's home.
+    HomeDirectory,
+    /// Containment was requested but the directory is the root of a
+    /// repository, holding a `.git` entry.
+    RepositoryRoot,
+    /// Containment was requested but the Codex sandbox disables isolation.
+    UnsandboxedAccess,
+}
+
+impl std::fmt::Display for ContainmentViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => f.write_str("does not exist"),
+            Self::NotADirectory => f.write_str("is not a directory"),
+            Self::NotWritable { sandbox } => {
+                write!(
+                    f,
+                    "is not writable, but the {sandbox:?} sandbox expects writes"
+                )
+            }
+            Self::HomeDirectory => f.write_str("is the home directory"),
+            Self::RepositoryRoot => f.write_str("is a repository root"),
+            Self::UnsandboxedAccess => {
+                f.write_str("runs under the DangerFullAccess sandbox, which disables isolation")
+            }
+        }
+    }
+}
+
+/// Checks `cwd` for a run of `adapter` under `sandbox`, returning every
+/// failed check as a [`ProviderError::ContainmentMisconfiguration`].
+pub(crate) fn check_working_dir(
+    cwd: &Path,
+    adapter: CliAdapter,
+    sandbox: &SandboxMode,
+    require_containment: bool,
+) -> Result<(), ProviderError> {
+    let violations = violations(cwd, adapter, sandbox, require_containment, dirs::home_dir());
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ProviderError::ContainmentMisconfiguration {
+            cwd: cwd.to_path_buf(),
+            violations,
+        })
+    }
+}
+
+fn violations(
+    cwd: &Path,
+    adapter: CliAdapter,
+    sandbox: &SandboxMode,
+    require_containment: bool,
+    home: Option<PathBuf>,
+) -> Vec<ContainmentViolation> {
+    let mut violations = Vec::new();
+    let codex = adapter == CliAdapter::Codex;
+
+    match std::fs::metadata(cwd) {
+        Err(_) => violations.push(ContainmentViolation::Missing),
+        Ok(meta) if !meta.is_dir() => violations.push(ContainmentViolation::NotADirectory),
+        Ok(_) => {
+            if codex && *sandbox != SandboxMode::ReadOnly && !is_writable(cwd) {
+                violations.push(ContainmentViolation::NotWritable {
+                    sandbox: sandbox.clone(),
+                });
+            }
+        }
+    }
+
+    if require_containment {
+        let canonical = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+        if home.is_some_and(|home| home.canonicalize().unwrap_or(home) == canonical) {
+            violations.push(ContainmentViolation::HomeDirectory);
+        }
+        if canonical.join(".git").exists() {
+            violations.push(ContainmentViolation::RepositoryRoot);
+        }
+        if codex && *sandbox == SandboxMode::DangerFullAccess {
+            violations.push(ContainmentViolation::UnsandboxedAccess);
+        }
+    }
+
+    violations
+}
+
+/// Whether a file can be created in `dir`; the probe file is removed again.
+fn is_writable(dir: &Path) -> bool {
+    tempfile::tempfile_in(dir).is_ok()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_dir_passes_every_check() {
+        let dir = tempfile::tempdir().unwrap();
+        for sandbox in [SandboxMode::ReadOnly, SandboxMode::WorkspaceWrite] {
+            assert_eq!(
+                violations(dir.path(), CliAdapter::Codex, &sandbox, true, None),
+                []
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_and_file_cwd_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        assert_eq!(
+            violations(
+                &missing,
+                CliAdapter::ClaudeCode,
+                &SandboxMode::ReadOnly,
+                false,
+                None
+            ),
+            [ContainmentViolation::Missing]
+        );
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        assert_eq!(
+            violations(
+                &file,
+                CliAdapter::ClaudeCode,
+                &SandboxMode::ReadOnly,
+                false,
+                None
+            ),
+            [ContainmentViolation::NotADirectory]
+        );
+    }
+
+    #[test]
+    fn test_home_and_repo_root_need_containment_requested() {
+        let home = tempfile::tempdir().unwrap();
+        std::fs::create_dir(home.path().join(".git")).unwrap();
+        let check = |require| {
+            violations(
+                home.path(),
+                CliAdapter::OpenCode,
+                &SandboxMode::ReadOnly,
+                require,
+                Some(home.path().to_path_buf()),
+            )
+        };
+
+        assert_eq!(check(false), []);
+        assert_eq!(
+            check(true),
+            [
+                ContainmentViolation::HomeDirectory,
+                ContainmentViolation::RepositoryRoot
+            ]
+        );
+    }
+
+    #[test]
+    fn test_codex_sandbox_must_match_cwd() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            violations(
+                dir.path(),
+                CliAdapter::Codex,
+                &SandboxMode::DangerFullAccess,
+                true,
+                None
+            ),
+            [ContainmentViolation::UnsandboxedAccess]
+        );
+        // Other adapters ignore the sandbox mode.
+        assert_eq!(
+            violations(
+                dir.path(),
+                CliAdapter::ClaudeCode,
+                &SandboxMode::DangerFullAccess,
+                true,
+                None
+            ),
+            []
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_only_cwd_conflicts_with_workspace_write() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o555)).unwrap();
+        // Root ignores directory permissions.
+        if is_writable(dir.path()) {
+            return;
+        }
+
+        let err = check_working_dir(
+            dir.path(),
+            CliAdapter::Codex,
+            &SandboxMode::WorkspaceWrite,
+            false,
+        )
+        .unwrap_err();
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(err.error_code(), "containment_misconfiguration");
+        let ProviderError::ContainmentMisconfiguration { violations, .. } = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(
+            violations,
+            [ContainmentViolation::NotWritable {
+                sandbox: SandboxMode::WorkspaceWrite
+            }]
+        );
+        assert!(
+            check_working_dir(dir.path(), CliAdapter::Codex, &SandboxMode::ReadOnly, false).is_ok()
+        );
+    }
+}
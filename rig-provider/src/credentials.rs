@@ -0,0 +1,270 @@
+//! API key loading for spawned CLIs.
+//!
+//! A [`Credentials`] set on an
+//! [`McpToolAgentBuilder`](crate::mcp_agent::McpToolAgentBuilder) or
+//! [`CliAgentBuilder`](crate::mcp_agent::CliAgentBuilder) reads API keys from
+//! env files or the OS keychain at run time, so they never need to be exported
+//! into the host process. Only the variables the target adapter's CLI reads
+//! (see [`adapter_vars`]) are looked up and set on the child process; anything
+//! else in an env file is ignored.
+//!
+//! Sources are consulted in the order they were added and the first one that
+//! has a variable wins. Variables no source provides are left unset, so a CLI
+//! that is logged in through its own OAuth flow keeps working.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rig_cli_provider::credentials::Credentials;
+//!
+//! // Prefer the project's .env, fall back to the keychain.
+//! let credentials = Credentials::new()
+//!     .env_file(".env")
+//!     .keychain("rig-cli");
+//! ```
+
+use crate::errors::ProviderError;
+use crate::mcp_agent::CliAdapter;
+use std::path::PathBuf;
+
+/// A place API keys are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// A dotenv-style file of `KEY=value` lines.
+    EnvFile(PathBuf),
+    /// The OS keychain: each variable is stored as a generic password whose
+    /// service is `service` and whose account is the variable name.
+    ///
+    /// Uses `security` on macOS and `secret-tool` (libsecret) elsewhere on
+    /// Unix. Not supported on Windows.
+    Keychain {
+        /// Keychain service name the entries are stored under.
+        service: String,
+    },
+}
+
+/// Ordered list of [`CredentialSource`]s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Credentials {
+    sources: Vec<CredentialSource>,
+}
+
+impl Credentials {
+    /// Creates an empty credential chain.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a dotenv-style file to the chain.
+    ///
+    /// The file must exist when a run starts; a missing file fails the run.
+    #[must_use]
+    pub fn env_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(CredentialSource::EnvFile(path.into()));
+        self
+    }
+
+    /// Adds the OS keychain, with entries stored under `service`, to the chain.
+    #[must_use]
+    pub fn keychain(mut self, service: impl Into<String>) -> Self {
+        self.sources.push(CredentialSource::Keychain {
+            service: service.into(),
+        });
+        self
+    }
+
+    /// The configured sources, in lookup order.
+    #[must_use]
+    pub fn sources(&self) -> &[CredentialSource] {
+        &self.sources
+    }
+
+    /// Looks up the variables `adapter`'s CLI reads and returns those found.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Credentials`] if an env file cannot be read or
+    /// the keychain tool cannot be run.
+    pub async fn resolve(
+        &self,
+        adapter: CliAdapter,
+    ) -> Result<Vec<(String, String)>, ProviderError> {
+        let mut resolved = Vec::new();
+        let mut remaining: Vec<&str> = adapter_vars(adapter).to_vec();
+
+        for source in &self.sources {
+            if remaining.is_empty() {
+                break;
+            }
+            let found = match source {
+                CredentialSource::EnvFile(path) => {
+                    let contents = tokio::fs::read_to_string(path).await.map_err(|e| {
+                        ProviderError::credentials(format!("env file {}", path.display()), e)
+                    })?;
+                    let entries = parse_env_file(&contents);
+                    remaining
+                        .iter()
+                        .filter_map(|var| {
+                            entries
+                                .iter()
+                                .find(|(key, _)| key == var)
+                                .map(|(_, value)| ((*var).to_string(), value.clone()))
+                        })
+                        .collect()
+                }
+                CredentialSource::Keychain { service } => {
+                    let mut found = Vec::new();
+                    for var in &remaining {
+                        if let Some(value) = keychain_lookup(service, var).await? {
+                            found.push(((*var).to_string(), value));
+                        }
+                    }
+                    found
+                }
+            };
+            remaining.retain(|var| !found.iter().any(|(key, _)| key == var));
+            resolved.extend(found);
+        }
+
+        Ok(resolved)
+    }
+}
+
+/// Environment variables holding credentials for `adapter`'s CLI.
+///
+/// [`CliAdapter::Custom`] backends have none.
+#[must_use]
+pub const fn adapter_vars(adapter: CliAdapter) -> &'static [&'static str] {
+    match adapter {
+        CliAdapter::ClaudeCode => &["ANTHROPIC_API_KEY", "CLAUDE_CODE_OAUTH_TOKEN"],
+        CliAdapter::Codex => &["OPENAI_API_KEY"],
+        CliAdapter::OpenCode => &[
+            "ANTHROPIC_API_KEY",
+            "OPENAI_API_KEY",
+            "OPENROUTER_API_KEY",
+            "GEMINI_API_KEY",
+        ],
+        CliAdapter::Custom(_) => &[],
+    }
+}
+
+/// Parses `KEY=value` lines, skipping blanks and `#` comments.
+///
+/// An `export ` prefix is accepted and values wrapped in matching single or
+/// double quotes are unquoted. Lines without `=` are ignored.
+fn parse_env_file(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+                .unwrap_or(value);
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the keychain entry for `account` under `service`.
+///
+/// A missing entry is `Ok(None)`; a missing or failing keychain tool is an error.
+#[cfg(unix)]
+async fn keychain_lookup(service: &str, account: &str) -> Result<Option<String>, ProviderError> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        let mut cmd = tokio::process::Command::new("security");
+        cmd.args(["find-generic-password", "-s", service, "-a", account, "-w"]);
+        cmd
+    } else {
+        let mut cmd = tokio::process::Command::new("secret-tool");
+        cmd.args(["lookup", "service", service, "account", account]);
+        cmd
+    };
+
+    let output = cmd
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .map_err(|e| ProviderError::credentials(format!("keychain service {service}"), e))?;
+
+    // Both tools exit non-zero with no output when the entry does not exist.
+    let value = String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string();
+    Ok((output.status.success() && !value.is_empty()).then_some(value))
+}
+
+/// Reads the keychain entry for `account` under `service`.
+#[cfg(not(unix))]
+#[allow(clippy::unused_async)]
+async fn keychain_lookup(service: &str, _account: &str) -> Result<Option<String>, ProviderError> {
+    Err(ProviderError::credentials(
+        format!("keychain service {service}"),
+        "OS keychain lookup is not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_file() {
+        let entries = parse_env_file(
+            "# comment\n\nexport ANTHROPIC_API_KEY=\"sk-ant\"\nOPENAI_API_KEY = 'sk-oa'\nnot a pair\nEMPTY=\n",
+        );
+        assert_eq!(
+            entries,
+            [
+                ("ANTHROPIC_API_KEY".to_string(), "sk-ant".to_string()),
+                ("OPENAI_API_KEY".to_string(), "sk-oa".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_injects_only_adapter_vars() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.env");
+        let second = dir.path().join("second.env");
+        std::fs::write(
+            &first,
+            "OPENAI_API_KEY=first\nDATABASE_URL=postgres://secret\n",
+        )
+        .unwrap();
+        std::fs::write(&second, "OPENAI_API_KEY=second\nGEMINI_API_KEY=gem\n").unwrap();
+
+        let credentials = Credentials::new().env_file(&first).env_file(&second);
+
+        let codex = credentials.resolve(CliAdapter::Codex).await.unwrap();
+        assert_eq!(codex, [("OPENAI_API_KEY".to_string(), "first".to_string())]);
+
+        let opencode = credentials.resolve(CliAdapter::OpenCode).await.unwrap();
+        assert_eq!(
+            opencode,
+            [
+                ("OPENAI_API_KEY".to_string(), "first".to_string()),
+                ("GEMINI_API_KEY".to_string(), "gem".to_string()),
+            ]
+        );
+
+        let claude = credentials.resolve(CliAdapter::ClaudeCode).await.unwrap();
+        assert_eq!(claude, Vec::<(String, String)>::new());
+    }
+
+    #[tokio::test]
+    async fn test_missing_env_file_is_an_error() {
+        let err = Credentials::new()
+            .env_file("/nonexistent/rig-cli.env")
+            .resolve(CliAdapter::Codex)
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code(), "credentials");
+    }
+}
@@ -0,0 +1,459 @@
+//! A long-running extraction service on a local unix socket.
+//!
+//! `rig-cli-provider daemon` discovers its adapters' CLIs once, then accepts
+//! connections on a unix socket. Each connection carries newline-delimited
+//! JSON: a client writes [`DaemonRequest`]s, each a prompt and the JSON
+//! Schema of the answer, and reads [`DaemonResponse`]s back. A request runs
+//! as an [`McpToolAgent`] whose submit tool enforces its schema; the agent's
+//! events stream back as they happen, followed by the submitted result or an
+//! error. Requests on one connection run concurrently and their responses
+//! interleave, tagged with the request's `id`. At most
+//! [`workers`](DaemonConfig::workers) runs are in flight across all
+//! connections; the rest wait their turn.
+//!
+//! Any process that can open a unix socket can use it:
+//!
+//! ```text
+//! $ echo '{"id":"1","prompt":"Extract the invoice","payload":"...","schema":{"type":"object"}}' \
+//!     | nc -U /tmp/rig-cli-provider.sock
+//! {"type":"event","id":"1","event":{"type":"text","data":"..."}}
+//! {"type":"result","id":"1","result":{"total":42},"exit_code":0,"duration_ms":8120}
+//! ```
+//!
+//! The agent's MCP server is this binary started again with the request's
+//! schema in [`SCHEMA_ENV`]; `main` serves the tools through
+//! [`serve_tools`] when it is set.
+
+use crate::errors::ProviderError;
+use crate::events::StreamEvent;
+use crate::mcp_agent::{CliAdapter, McpToolAgent};
+use rig::tool::ToolSet;
+use rig_cli_mcp::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, Semaphore};
+
+/// Environment variable carrying the request's JSON Schema to the MCP
+/// server the daemon's agents start.
+pub const SCHEMA_ENV: &str = "RIG_DAEMON_SCHEMA";
+
+/// Pause after a failed accept before accepting again.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Settings of the `daemon` subcommand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonConfig {
+    /// Unix socket to listen on. A stale socket file is replaced; one
+    /// another daemon still listens on is left alone.
+    pub socket: PathBuf,
+    /// Adapters whose CLIs are discovered at startup. Requests may only use
+    /// these; the first is used when a request names none.
+    pub adapters: Vec<CliAdapter>,
+    /// Maximum number of runs in flight at a time.
+    pub workers: usize,
+    /// Timeout of each run, unless the request sets its own.
+    pub timeout: Duration,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            socket: std::env::temp_dir().join("rig-cli-provider.sock"),
+            adapters: vec![CliAdapter::ClaudeCode],
+            workers: 4,
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+/// One extraction, as a line of JSON on the socket.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DaemonRequest {
+    /// Client-chosen ID that tags every response to this request.
+    pub id: String,
+    /// The task for the agent.
+    pub prompt: String,
+    /// JSON Schema the submitted result must satisfy.
+    pub schema: serde_json::Value,
+    /// Context data the agent extracts from, fenced off from the prompt.
+    #[serde(default)]
+    pub payload: Option<String>,
+    /// Adapter to run on. Default: the daemon's first adapter.
+    #[serde(default)]
+    pub adapter: Option<CliAdapter>,
+    /// Timeout of this run in seconds. Default: the daemon's timeout.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// A line of JSON the daemon writes back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonResponse {
+    /// Progress of a running request.
+    Event {
+        /// The request's ID.
+        id: String,
+        /// What the agent did.
+        event: StreamEvent,
+    },
+    /// A request finished; always its last response.
+    Result {
+        /// The request's ID.
+        id: String,
+        /// The submitted result, validated against the request's schema.
+        /// `None` if the agent never called submit.
+        result: Option<serde_json::Value>,
+        /// Exit code of the CLI.
+        exit_code: i32,
+        /// Wall-clock duration of the run in milliseconds.
+        duration_ms: u64,
+    },
+    /// A request failed; always its last response.
+    Error {
+        /// The request's ID, or `None` if the line could not be parsed.
+        id: Option<String>,
+        /// Machine-readable error category, as in
+        /// [`ProviderError::error_code`].
+        code: String,
+        /// Human-readable description.
+        message: String,
+    },
+}
+
+impl DaemonResponse {
+    fn error(id: Option<String>, code: &str, message: impl Into<String>) -> Self {
+        Self::Error {
+            id,
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// State shared by every connection.
+struct Daemon {
+    /// Discovered CLI of each enabled adapter, in configured order.
+    clis: Vec<(CliAdapter, PathBuf)>,
+    workers: Arc<Semaphore>,
+    timeout: Duration,
+}
+
+/// Discovers the configured adapters' CLIs and serves requests on the
+/// socket until interrupted with Ctrl-C.
+///
+/// # Errors
+/// Returns an error if no adapter is configured, a CLI cannot be found, or
+/// the socket cannot be bound, for example because another daemon is
+/// listening on it.
+pub async fn run(config: DaemonConfig) -> Result<(), ProviderError> {
+    if config.adapters.is_empty() {
+        return Err(ProviderError::Init(
+            "the daemon needs at least one adapter".to_string(),
+        ));
+    }
+    let mut clis = Vec::with_capacity(config.adapters.len());
+    for adapter in config.adapters {
+        if let CliAdapter::Custom(name) = adapter {
+            return Err(ProviderError::Init(format!(
+                "adapter '{name}' cannot be served"
+            )));
+        }
+        let path = adapter.backend().discover(None).await?;
+        tracing::info!(%adapter, path = %path.display(), "Adapter ready");
+        clis.push((adapter, path));
+    }
+    let daemon = Arc::new(Daemon {
+        clis,
+        workers: Arc::new(Semaphore::new(config.workers.max(1))),
+        timeout: config.timeout,
+    });
+
+    let listener = bind(&config.socket).await?;
+    tracing::info!(socket = %config.socket.display(), workers = config.workers, "Daemon listening");
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let stream = match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        // Running out of file descriptors or a client giving
+                        // up before it was accepted passes; back off briefly
+                        // so a persistent error does not spin.
+                        tracing::warn!(error = %e, "Failed to accept daemon connection");
+                        tokio::time::sleep(ACCEPT_BACKOFF).await;
+                        continue;
+                    }
+                };
+                tokio::spawn(Arc::clone(&daemon).serve_connection(stream));
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    tracing::info!("Daemon shutting down");
+    let _ = std::fs::remove_file(&config.socket);
+    Ok(())
+}
+
+/// Binds `socket`, first removing a socket file nobody listens on.
+async fn bind(socket: &std::path::Path) -> Result<UnixListener, ProviderError> {
+    if socket.exists() {
+        match UnixStream::connect(socket).await {
+            Ok(_) => {
+                return Err(ProviderError::Init(format!(
+                    "another daemon is listening on {}",
+                    socket.display()
+                )))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                std::fs::remove_file(socket)?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(UnixListener::bind(socket)?)
+}
+
+/// Serves the extraction tools for `schema` over stdio, as the MCP server of
+/// a daemon run.
+///
+/// # Errors
+/// Returns an error if `schema` is not a JSON Schema object or serving fails.
+pub async fn serve_tools(schema: &str) -> Result<(), ProviderError> {
+    let schema = serde_json::from_str(schema)
+        .map_err(|e| ProviderError::Init(format!("invalid {SCHEMA_ENV}: {e}")))?;
+    extraction_toolset(schema)
+        .map_err(ProviderError::Init)?
+        .into_handler()
+        .await
+        .map_err(|e| ProviderError::Init(e.to_string()))?
+        .serve_stdio()
+        .await
+        .map_err(|e| ProviderError::Init(e.to_string()))
+}
+
+/// The submit / validate / example tools for `schema`.
+pub(crate) fn extraction_toolset(schema: serde_json::Value) -> Result<ToolSet, String> {
+    let (submit, validate, example) = DynamicJsonSchemaToolkit::builder()
+        .schema(schema)
+        .on_success("Output successfully processed and extracted.")
+        .build()?
+        .build_tools();
+    let mut toolset = ToolSet::default();
+    toolset.add_tool(submit);
+    toolset.add_tool(validate);
+    toolset.add_tool(example);
+    Ok(toolset)
+}
+
+impl Daemon {
+    /// Reads requests from one connection and runs each concurrently,
+    /// writing their responses back as they come.
+    async fn serve_connection(self: Arc<Self>, stream: UnixStream) {
+        let (reader, mut writer) = stream.into_split();
+        let (tx, mut rx) = mpsc::channel::<DaemonResponse>(64);
+
+        let writing = tokio::spawn(async move {
+            while let Some(response) = rx.recv().await {
+                let Ok(mut line) = serde_json::to_string(&response) else {
+                    continue;
+                };
+                line.push('\n');
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<DaemonRequest>(&line) {
+                Ok(request) => {
+                    tokio::spawn(Arc::clone(&self).handle(request, tx.clone()));
+                }
+                Err(e) => {
+                    let response = DaemonResponse::error(None, "invalid_request", e.to_string());
+                    if tx.send(response).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        // In-flight requests hold their own senders; the writer finishes
+        // once the last of them is done.
+        drop(tx);
+        let _ = writing.await;
+    }
+
+    /// Runs one request and sends its responses.
+    async fn handle(self: Arc<Self>, request: DaemonRequest, tx: mpsc::Sender<DaemonResponse>) {
+        let id = request.id.clone();
+        let last = match self.execute(request, &tx).await {
+            Ok(response) => response,
+            Err(e) => DaemonResponse::error(Some(id), e.error_code(), e.to_string()),
+        };
+        let _ = tx.send(last).await;
+    }
+
+    /// Runs one request, streaming its events, and returns its result.
+    async fn execute(
+        &self,
+        request: DaemonRequest,
+        tx: &mpsc::Sender<DaemonResponse>,
+    ) -> Result<DaemonResponse, ProviderError> {
+        let (adapter, cli_path) = self.cli(request.adapter)?;
+        let toolset = extraction_toolset(request.schema.clone())
+            .map_err(|e| ProviderError::Validation(format!("invalid schema: {e}")))?;
+
+        let _permit = self
+            .workers
+            .acquire()
+            .await
+            .map_err(|_| ProviderError::Cancelled)?;
+
+        let timeout = request
+            .timeout_secs
+            .map_or(self.timeout, Duration::from_secs);
+        let mut builder = McpToolAgent::builder()
+            .toolset(toolset)
+            .adapter(adapter)
+            .cli_path(cli_path)
+            .prompt(request.prompt)
+            .timeout(timeout)
+            .extra_env(SCHEMA_ENV, request.schema.to_string());
+        if let Some(payload) = request.payload {
+            builder = builder.payload(payload);
+        }
+
+        let mut handle = builder.stream().await?;
+        while let Some(event) = handle.recv().await {
+            let response = DaemonResponse::Event {
+                id: request.id.clone(),
+                event,
+            };
+            if tx.send(response).await.is_err() {
+                // The client hung up; nobody is waiting for the answer.
+                handle.abort();
+                break;
+            }
+        }
+        let result = handle.finish().await?;
+        Ok(DaemonResponse::Result {
+            id: request.id,
+            result: result.result_json,
+            exit_code: result.exit_code,
+            duration_ms: result.duration_ms,
+        })
+    }
+
+    /// The discovered CLI for `adapter`, or for the first adapter if `None`.
+    fn cli(&self, adapter: Option<CliAdapter>) -> Result<(CliAdapter, PathBuf), ProviderError> {
+        let found = adapter.map_or_else(
+            || self.clis.first(),
+            |adapter| self.clis.iter().find(|(enabled, _)| *enabled == adapter),
+        );
+        found.cloned().ok_or_else(|| {
+            ProviderError::Validation(format!(
+                "adapter '{}' is not enabled in this daemon",
+                adapter.map_or_else(|| "default".to_string(), |a| a.to_string())
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_protocol_lines() {
+        let request: DaemonRequest = serde_json::from_value(json!({
+            "id": "7",
+            "prompt": "Extract",
+            "schema": { "type": "object" },
+            "adapter": "codex"
+        }))
+        .unwrap();
+        assert_eq!(request.adapter, Some(CliAdapter::Codex));
+        assert_eq!(request.payload, None);
+        assert_eq!(request.timeout_secs, None);
+
+        let event = DaemonResponse::Event {
+            id: "7".to_string(),
+            event: StreamEvent::Text("hi".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({ "type": "event", "id": "7", "event": { "type": "text", "data": "hi" } })
+        );
+        let result = DaemonResponse::Result {
+            id: "7".to_string(),
+            result: Some(json!({ "total": 42 })),
+            exit_code: 0,
+            duration_ms: 10,
+        };
+        assert_eq!(serde_json::to_value(&result).unwrap()["type"], "result");
+    }
+
+    #[tokio::test]
+    async fn test_bind_replaces_only_stale_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+
+        let first = bind(&socket).await.unwrap();
+        let err = bind(&socket).await.unwrap_err();
+        assert!(err.to_string().contains("another daemon"), "{err}");
+
+        drop(first);
+        assert!(socket.exists());
+        bind(&socket).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connection_rejects_bad_requests() {
+        let daemon = Arc::new(Daemon {
+            clis: vec![(CliAdapter::ClaudeCode, PathBuf::from("/bin/false"))],
+            workers: Arc::new(Semaphore::new(1)),
+            timeout: Duration::from_secs(1),
+        });
+        let (client, server) = UnixStream::pair().unwrap();
+        let serving = tokio::spawn(daemon.serve_connection(server));
+
+        let (reader, mut writer) = client.into_split();
+        writer
+            .write_all(
+                b"not json\n{\"id\":\"2\",\"prompt\":\"p\",\"schema\":{},\"adapter\":\"codex\"}\n",
+            )
+            .await
+            .unwrap();
+        writer.shutdown().await.unwrap();
+
+        let mut lines = BufReader::new(reader).lines();
+        let mut responses = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            responses.push(serde_json::from_str::<DaemonResponse>(&line).unwrap());
+        }
+        serving.await.unwrap();
+
+        assert_eq!(responses.len(), 2);
+        assert!(matches!(
+            &responses[0],
+            DaemonResponse::Error { id: None, code, .. } if code == "invalid_request"
+        ));
+        assert!(matches!(
+            &responses[1],
+            DaemonResponse::Error { id: Some(id), code, message }
+                if id == "2" && code == "validation" && message.contains("Codex")
+        ));
+    }
+}
@@ -1,19 +1,121 @@
+use crate::containment::ContainmentViolation;
+use crate::mcp_agent::CliAdapter;
+use rig_cli_claude::TimeoutStage;
+use std::time::Duration;
 use thiserror::Error;
 
+/// Boxed error used as the `source()` of variants that wrap heterogeneous causes.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
 /// Errors relating to the Rig Provider.
+///
+/// Adapter errors that have a provider-level meaning (a missing binary, a
+/// timeout, a missing login) are lifted into the typed [`Discovery`](Self::Discovery),
+/// [`Timeout`](Self::Timeout) and [`AuthRequired`](Self::AuthRequired) variants by
+/// the `From` impls below; for the first two the original adapter error stays
+/// reachable through [`std::error::Error::source`].
+/// Use [`error_code`](Self::error_code) for programmatic handling.
 #[derive(Debug, Error)]
 pub enum ProviderError {
     /// Error from the Claude Code adapter.
     #[error("Claude adapter error: {0}")]
-    Claude(#[from] rig_cli_claude::ClaudeError),
+    Claude(#[source] rig_cli_claude::ClaudeError),
 
     /// Error from the Codex adapter.
     #[error("Codex adapter error: {0}")]
-    Codex(#[from] rig_cli_codex::CodexError),
+    Codex(#[source] rig_cli_codex::CodexError),
 
     /// Error from the `OpenCode` adapter.
     #[error("OpenCode adapter error: {0}")]
-    OpenCode(#[from] rig_cli_opencode::OpenCodeError),
+    OpenCode(#[source] rig_cli_opencode::OpenCodeError),
+
+    /// The CLI binary could not be located or failed its startup checks.
+    #[error("{adapter} discovery failed: {source}")]
+    Discovery {
+        /// Adapter whose CLI could not be found.
+        adapter: CliAdapter,
+        /// Underlying adapter error.
+        #[source]
+        source: BoxError,
+    },
+
+    /// The CLI is not logged in, or rejected its credentials.
+    #[error("{adapter} is not authenticated; run `{login_command}` to log in")]
+    AuthRequired {
+        /// Adapter that needs a login.
+        adapter: CliAdapter,
+        /// Command the user should run to log in.
+        login_command: String,
+    },
+
+    /// A filesystem or process resource needed to launch the CLI could not be set up.
+    #[error("Failed to {stage}: {source}")]
+    Spawn {
+        /// Short description of the step that failed (e.g. "create temp dir").
+        stage: &'static str,
+        /// Underlying I/O error.
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The CLI run exceeded one of its timeouts.
+    #[error("{adapter} hit its {stage} timeout after {elapsed:?}")]
+    Timeout {
+        /// Adapter that timed out.
+        adapter: CliAdapter,
+        /// Which timeout fired: a CLI that never started or never printed
+        /// anything, or one that ran out of total time.
+        stage: TimeoutStage,
+        /// Wall-clock time elapsed before the process was killed.
+        elapsed: Duration,
+        /// Stdout captured before the timeout fired.
+        partial_output: String,
+        /// Underlying adapter error.
+        #[source]
+        source: BoxError,
+    },
+
+    /// The MCP server config could not be generated, serialized, or written.
+    #[error("MCP config error: {message}")]
+    McpConfig {
+        /// What was being done when the failure occurred.
+        message: String,
+        /// Underlying cause.
+        #[source]
+        source: BoxError,
+    },
+
+    /// API keys could not be loaded from a configured credential source.
+    #[error("Failed to load credentials from {location}: {source}")]
+    Credentials {
+        /// The source that failed (e.g. "env file .env").
+        location: String,
+        /// Underlying cause.
+        #[source]
+        source: BoxError,
+    },
+
+    /// The working directory failed the pre-spawn containment checks; see
+    /// [`crate::containment`].
+    #[error("Containment misconfiguration for {}: {}", cwd.display(), list_violations(.violations))]
+    ContainmentMisconfiguration {
+        /// Working directory the run would have started in.
+        cwd: std::path::PathBuf,
+        /// Every check that failed.
+        violations: Vec<ContainmentViolation>,
+    },
+
+    /// Builder or request input failed validation.
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    /// A configured budget (runs, tokens, cost) was exhausted.
+    #[error("Budget exceeded: {0}")]
+    Budget(String),
+
+    /// The run was cancelled before it completed.
+    #[error("Run cancelled")]
+    Cancelled,
 
     /// Session management error.
     #[error("Session management error: {0}")]
@@ -23,10 +125,6 @@ pub enum ProviderError {
     #[error("Initialization error: {0}")]
     Init(String),
 
-    /// Discovery error.
-    #[error("Discovery error: {0}")]
-    Discovery(String),
-
     /// I/O error.
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -34,8 +132,305 @@ pub enum ProviderError {
     /// Anyhow error.
     #[error("Anyhow error: {0}")]
     Anyhow(#[from] anyhow::Error),
+}
+
+impl ProviderError {
+    /// Returns a stable, machine-readable code identifying the error category.
+    ///
+    /// Adapter errors delegate to the adapter's own `error_code()`, so a
+    /// timeout reports `"timeout"` regardless of which layer produced it.
+    #[must_use]
+    pub const fn error_code(&self) -> &'static str {
+        match self {
+            Self::Claude(e) => e.error_code(),
+            Self::Codex(e) => e.error_code(),
+            Self::OpenCode(e) => e.error_code(),
+            Self::Discovery { .. } => "discovery",
+            Self::AuthRequired { .. } => "auth_required",
+            Self::Spawn { .. } => "spawn",
+            Self::Timeout { .. } => "timeout",
+            Self::McpConfig { .. } => "mcp_config",
+            Self::Credentials { .. } => "credentials",
+            Self::ContainmentMisconfiguration { .. } => "containment_misconfiguration",
+            Self::Validation(_) => "validation",
+            Self::Budget(_) => "budget",
+            Self::Cancelled => "cancelled",
+            Self::Session(_) => "session",
+            Self::Init(_) => "init",
+            Self::Io(_) => "io",
+            Self::Anyhow(_) => "internal",
+        }
+    }
+
+    /// Whether the run failed before the CLI produced any agent output: the
+    /// CLI could not be found, started, logged in or configured (including
+    /// its MCP server), hit its spawn or first-output timeout, or exited
+    /// with an error without printing anything.
+    ///
+    /// Such a run did no work, so it can be repeated on another CLI; see
+    /// [`McpToolAgentBuilder::fallback_adapter`](crate::mcp_agent::McpToolAgentBuilder::fallback_adapter).
+    #[must_use]
+    pub fn failed_before_output(&self) -> bool {
+        match self {
+            Self::Claude(rig_cli_claude::ClaudeError::NonZeroExit { stdout, .. })
+            | Self::Codex(rig_cli_codex::CodexError::NonZeroExit { stdout, .. })
+            | Self::OpenCode(rig_cli_opencode::OpenCodeError::NonZeroExit { stdout, .. }) => {
+                stdout.trim().is_empty()
+            }
+            Self::Timeout { stage, .. } => *stage != TimeoutStage::Total,
+            _ => matches!(
+                self.error_code(),
+                "discovery"
+                    | "auth_required"
+                    | "spawn"
+                    | "init"
+                    | "mcp_config"
+                    | "credentials"
+                    | "unsupported_feature"
+            ),
+        }
+    }
+
+    /// Builds an [`McpConfig`](Self::McpConfig) error from a message and its cause.
+    pub(crate) fn mcp_config(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self::McpConfig {
+            message: message.into(),
+            source: source.into(),
+        }
+    }
+
+    /// Builds a [`Credentials`](Self::Credentials) error for the source at `location`.
+    pub(crate) fn credentials(location: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self::Credentials {
+            location: location.into(),
+            source: source.into(),
+        }
+    }
+
+    /// Builds a [`Discovery`](Self::Discovery) error for `adapter`.
+    pub(crate) fn discovery(adapter: CliAdapter, source: impl Into<BoxError>) -> Self {
+        Self::Discovery {
+            adapter,
+            source: source.into(),
+        }
+    }
+}
+
+/// Joins containment violations into one clause for the error message.
+fn list_violations(violations: &[ContainmentViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl From<rig_cli_claude::ClaudeError> for ProviderError {
+    fn from(err: rig_cli_claude::ClaudeError) -> Self {
+        use rig_cli_claude::ClaudeError;
+        match err {
+            ClaudeError::Timeout {
+                stage,
+                elapsed,
+                ref partial_stdout,
+                ..
+            } => Self::Timeout {
+                adapter: CliAdapter::ClaudeCode,
+                stage,
+                elapsed,
+                partial_output: partial_stdout.clone(),
+                source: Box::new(err),
+            },
+            ClaudeError::AuthRequired { login_command } => Self::AuthRequired {
+                adapter: CliAdapter::ClaudeCode,
+                login_command,
+            },
+            ClaudeError::ExecutableNotFound(_) => Self::discovery(CliAdapter::ClaudeCode, err),
+            other => Self::Claude(other),
+        }
+    }
+}
+
+impl From<rig_cli_codex::CodexError> for ProviderError {
+    fn from(err: rig_cli_codex::CodexError) -> Self {
+        use rig_cli_codex::CodexError;
+        match err {
+            CodexError::Timeout {
+                stage,
+                elapsed,
+                ref partial_stdout,
+                ..
+            } => Self::Timeout {
+                adapter: CliAdapter::Codex,
+                stage,
+                elapsed,
+                partial_output: partial_stdout.clone(),
+                source: Box::new(err),
+            },
+            CodexError::AuthRequired { login_command } => Self::AuthRequired {
+                adapter: CliAdapter::Codex,
+                login_command,
+            },
+            CodexError::ExecutableNotFound(_) | CodexError::WhichError(_) => {
+                Self::discovery(CliAdapter::Codex, err)
+            }
+            other => Self::Codex(other),
+        }
+    }
+}
+
+impl From<rig_cli_opencode::OpenCodeError> for ProviderError {
+    fn from(err: rig_cli_opencode::OpenCodeError) -> Self {
+        use rig_cli_opencode::OpenCodeError;
+        match err {
+            OpenCodeError::Timeout {
+                stage,
+                elapsed,
+                ref partial_stdout,
+                ..
+            } => Self::Timeout {
+                adapter: CliAdapter::OpenCode,
+                stage,
+                elapsed,
+                partial_output: partial_stdout.clone(),
+                source: Box::new(err),
+            },
+            OpenCodeError::AuthRequired { login_command } => Self::AuthRequired {
+                adapter: CliAdapter::OpenCode,
+                login_command,
+            },
+            OpenCodeError::ExecutableNotFound(_) | OpenCodeError::WhichError(_) => {
+                Self::discovery(CliAdapter::OpenCode, err)
+            }
+            other => Self::OpenCode(other),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_claude_timeout_lifts_to_typed_timeout() {
+        let err = ProviderError::from(rig_cli_claude::ClaudeError::Timeout {
+            stage: TimeoutStage::Total,
+            elapsed: Duration::from_secs(5),
+            pid: 42,
+            partial_stdout: "partial".to_string(),
+            partial_stderr: String::new(),
+        });
+
+        match &err {
+            ProviderError::Timeout {
+                adapter,
+                partial_output,
+                ..
+            } => {
+                assert_eq!(*adapter, CliAdapter::ClaudeCode);
+                assert_eq!(partial_output, "partial");
+            }
+            other => panic!("expected Timeout, got {other:?}"),
+        }
+        assert_eq!(err.error_code(), "timeout");
+
+        let source = err
+            .source()
+            .expect("timeout should carry its adapter error");
+        assert!(source
+            .downcast_ref::<rig_cli_claude::ClaudeError>()
+            .is_some());
+    }
+
+    #[test]
+    fn test_codex_not_found_lifts_to_discovery() {
+        let err = ProviderError::from(rig_cli_codex::CodexError::ExecutableNotFound(
+            "codex".to_string(),
+        ));
+        assert!(matches!(
+            err,
+            ProviderError::Discovery {
+                adapter: CliAdapter::Codex,
+                ..
+            }
+        ));
+        assert_eq!(err.error_code(), "discovery");
+    }
+
+    #[test]
+    fn test_other_adapter_errors_keep_adapter_variant_and_code() {
+        let err = ProviderError::from(rig_cli_opencode::OpenCodeError::OutputTruncated {
+            captured_bytes: 11,
+            limit_bytes: 10,
+        });
+        assert!(matches!(err, ProviderError::OpenCode(_)));
+        assert_eq!(err.error_code(), "output_truncated");
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_auth_required_lifts_with_login_command() {
+        let err = ProviderError::from(rig_cli_claude::ClaudeError::AuthRequired {
+            login_command: "claude auth login".to_string(),
+        });
+        match &err {
+            ProviderError::AuthRequired {
+                adapter,
+                login_command,
+            } => {
+                assert_eq!(*adapter, CliAdapter::ClaudeCode);
+                assert_eq!(login_command, "claude auth login");
+            }
+            other => panic!("expected AuthRequired, got {other:?}"),
+        }
+        assert_eq!(err.error_code(), "auth_required");
+    }
+
+    #[test]
+    fn test_mcp_config_error_chains_source() {
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        let err = ProviderError::mcp_config("failed to write config", io);
+        assert_eq!(err.error_code(), "mcp_config");
+        assert_eq!(err.source().unwrap().to_string(), "denied");
+    }
+
+    #[test]
+    fn test_failed_before_output() {
+        let exit = |stdout: &str| {
+            ProviderError::from(rig_cli_codex::CodexError::NonZeroExit {
+                exit_code: 1,
+                pid: 42,
+                elapsed: Duration::from_secs(1),
+                stdout: stdout.to_string(),
+                stderr: "MCP client failed to start".to_string(),
+            })
+        };
+        assert!(exit("\n").failed_before_output());
+        assert!(!exit("partial answer").failed_before_output());
+
+        let auth = ProviderError::from(rig_cli_claude::ClaudeError::AuthRequired {
+            login_command: "claude auth login".to_string(),
+        });
+        assert!(auth.failed_before_output());
+        assert!(
+            ProviderError::mcp_config("failed to register server", "exit 1").failed_before_output()
+        );
 
-    /// Error from the MCP tool agent builder.
-    #[error("MCP tool agent error: {0}")]
-    McpToolAgent(String),
+        let timeout = |stage| {
+            ProviderError::from(rig_cli_claude::ClaudeError::Timeout {
+                stage,
+                elapsed: Duration::from_secs(5),
+                pid: 42,
+                partial_stdout: String::new(),
+                partial_stderr: String::new(),
+            })
+        };
+        assert!(!timeout(TimeoutStage::Total).failed_before_output());
+        assert!(timeout(TimeoutStage::Spawn).failed_before_output());
+        assert!(timeout(TimeoutStage::FirstOutput).failed_before_output());
+        assert!(!ProviderError::Cancelled.failed_before_output());
+        assert!(!ProviderError::Validation("bad".to_string()).failed_before_output());
+    }
 }
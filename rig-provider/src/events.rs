@@ -0,0 +1,222 @@
+//! Provider-agnostic stream events.
+//!
+//! Each adapter reports its CLI's output with its own `StreamEvent` type.
+//! [`StreamEvent`] is the common form both execution paths use: MCP tool
+//! agents stream it from [`McpStreamHandle`](crate::mcp_agent::McpStreamHandle),
+//! and `rig-cli` records it for direct CLI streams. Every adapter event maps
+//! to exactly one event here; anything without a typed equivalent is kept
+//! as [`StreamEvent::Raw`] rather than dropped.
+
+use serde::{Deserialize, Serialize};
+
+/// An event from a CLI run, in a form shared by all adapters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// Text content from the agent.
+    Text(String),
+    /// A fragment of text still being generated. The complete
+    /// [`Text`](Self::Text) follows once the message is finished.
+    TextDelta(String),
+    /// Tool call initiated by the agent.
+    ToolCall {
+        /// The tool use ID, when the CLI reports one.
+        id: Option<String>,
+        /// The tool name.
+        name: String,
+        /// The tool input.
+        input: serde_json::Value,
+    },
+    /// Result from a tool execution.
+    ToolResult {
+        /// The tool use ID or name.
+        tool_use_id: String,
+        /// The tool output content.
+        content: String,
+    },
+    /// Token counts for the run, sent once it finishes.
+    Usage {
+        /// Tokens the model read, including cached prompt tokens.
+        input_tokens: u64,
+        /// Tokens the model generated.
+        output_tokens: u64,
+    },
+    /// The CLI session and model of the run, sent once it finishes.
+    SessionInfo {
+        /// CLI session the run belongs to.
+        session_id: Option<String>,
+        /// Model that answered.
+        model: Option<String>,
+    },
+    /// An event produced inside a subagent's run. Its text is not part of
+    /// the agent's answer.
+    Subagent {
+        /// Tool use ID of the delegating `Task` [`ToolCall`](Self::ToolCall).
+        parent_tool_use_id: String,
+        /// The subagent's event.
+        event: Box<Self>,
+    },
+    /// The CLI has printed nothing for its stall timeout. It may still
+    /// recover.
+    Stalled {
+        /// Milliseconds since the CLI's last output line.
+        idle_ms: u64,
+    },
+    /// A stderr line the CLI printed to report its progress, e.g.
+    /// `Thinking…`. Only sent when the run classifies stderr.
+    Progress(String),
+    /// A stderr line the CLI printed as a warning. The run carries on.
+    Warning(String),
+    /// A submission that does not match the submit tool's schema, found
+    /// while the run is still streaming. See [`crate::stream_validation`].
+    ValidationWarning {
+        /// JSON pointer to the offending value within the submission.
+        path: String,
+        /// What is wrong with it.
+        message: String,
+    },
+    /// Error during execution.
+    Error(String),
+    /// An adapter event with no typed equivalent, as JSON.
+    Raw(serde_json::Value),
+}
+
+impl From<rig_cli_claude::StreamEvent> for StreamEvent {
+    fn from(event: rig_cli_claude::StreamEvent) -> Self {
+        match event {
+            rig_cli_claude::StreamEvent::Text { text } => Self::Text(text),
+            rig_cli_claude::StreamEvent::TextDelta { text } => Self::TextDelta(text),
+            rig_cli_claude::StreamEvent::ToolCall { name, input } => Self::ToolCall {
+                id: None,
+                name,
+                input,
+            },
+            rig_cli_claude::StreamEvent::ToolResult { name, output } => Self::ToolResult {
+                tool_use_id: name,
+                content: output,
+            },
+            // A delegation is a `Task` tool call; report it as the CLI made it.
+            rig_cli_claude::StreamEvent::SubagentStart {
+                id,
+                agent,
+                description,
+                prompt,
+            } => Self::ToolCall {
+                id: Some(id),
+                name: "Task".to_string(),
+                input: serde_json::json!({
+                    "subagent_type": agent,
+                    "description": description,
+                    "prompt": prompt,
+                }),
+            },
+            rig_cli_claude::StreamEvent::Subagent {
+                parent_tool_use_id,
+                event,
+            } => Self::Subagent {
+                parent_tool_use_id,
+                event: Box::new((*event).into()),
+            },
+            rig_cli_claude::StreamEvent::Stalled { idle_ms } => Self::Stalled { idle_ms },
+            rig_cli_claude::StreamEvent::Progress { message } => Self::Progress(message),
+            rig_cli_claude::StreamEvent::Warning { message } => Self::Warning(message),
+            rig_cli_claude::StreamEvent::Error { message } => Self::Error(message),
+            rig_cli_claude::StreamEvent::Unknown(value) => Self::Raw(value),
+        }
+    }
+}
+
+impl From<rig_cli_codex::StreamEvent> for StreamEvent {
+    fn from(event: rig_cli_codex::StreamEvent) -> Self {
+        match event {
+            rig_cli_codex::StreamEvent::Text { text } => Self::Text(text),
+            rig_cli_codex::StreamEvent::Stalled { idle_ms } => Self::Stalled { idle_ms },
+            rig_cli_codex::StreamEvent::Progress { message } => Self::Progress(message),
+            rig_cli_codex::StreamEvent::Warning { message } => Self::Warning(message),
+            rig_cli_codex::StreamEvent::Error { message } => Self::Error(message),
+            rig_cli_codex::StreamEvent::Unknown(value) => Self::Raw(value),
+        }
+    }
+}
+
+impl From<rig_cli_opencode::StreamEvent> for StreamEvent {
+    fn from(event: rig_cli_opencode::StreamEvent) -> Self {
+        match event {
+            rig_cli_opencode::StreamEvent::Text { text } => Self::Text(text),
+            rig_cli_opencode::StreamEvent::Stalled { idle_ms } => Self::Stalled { idle_ms },
+            rig_cli_opencode::StreamEvent::Progress { message } => Self::Progress(message),
+            rig_cli_opencode::StreamEvent::Warning { message } => Self::Warning(message),
+            rig_cli_opencode::StreamEvent::Error { message } => Self::Error(message),
+            rig_cli_opencode::StreamEvent::Unknown(value) => Self::Raw(value),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_claude_events_map_losslessly() {
+        let delegation = StreamEvent::from(rig_cli_claude::StreamEvent::SubagentStart {
+            id: "toolu_1".to_string(),
+            agent: "reviewer".to_string(),
+            description: "Review".to_string(),
+            prompt: "Check the diff".to_string(),
+        });
+        assert_eq!(
+            delegation,
+            StreamEvent::ToolCall {
+                id: Some("toolu_1".to_string()),
+                name: "Task".to_string(),
+                input: json!({
+                    "subagent_type": "reviewer",
+                    "description": "Review",
+                    "prompt": "Check the diff",
+                }),
+            }
+        );
+
+        let nested = StreamEvent::from(rig_cli_claude::StreamEvent::Subagent {
+            parent_tool_use_id: "toolu_1".to_string(),
+            event: Box::new(rig_cli_claude::StreamEvent::ToolResult {
+                name: "toolu_2".to_string(),
+                output: "3 files".to_string(),
+            }),
+        });
+        assert_eq!(
+            nested,
+            StreamEvent::Subagent {
+                parent_tool_use_id: "toolu_1".to_string(),
+                event: Box::new(StreamEvent::ToolResult {
+                    tool_use_id: "toolu_2".to_string(),
+                    content: "3 files".to_string(),
+                }),
+            }
+        );
+
+        let raw = json!({ "type": "system", "subtype": "init" });
+        assert_eq!(
+            StreamEvent::from(rig_cli_claude::StreamEvent::Unknown(raw.clone())),
+            StreamEvent::Raw(raw)
+        );
+        assert_eq!(
+            StreamEvent::from(rig_cli_codex::StreamEvent::Text {
+                text: "hi".to_string()
+            }),
+            StreamEvent::Text("hi".to_string())
+        );
+        assert_eq!(
+            StreamEvent::from(rig_cli_opencode::StreamEvent::Progress {
+                message: "Thinking…".to_string()
+            }),
+            StreamEvent::Progress("Thinking…".to_string())
+        );
+
+        let json = serde_json::to_value(&nested).unwrap();
+        assert_eq!(json["type"], "subagent");
+        assert_eq!(serde_json::from_value::<StreamEvent>(json).unwrap(), nested);
+    }
+}
@@ -0,0 +1,390 @@
+//! A C interface to MCP-enforced extraction, with the `ffi` feature.
+//!
+//! Non-Rust services (Python through `ctypes` or `cffi`, Node through
+//! `ffi-napi`) drive an [`McpToolAgent`] through four calls instead of
+//! reimplementing the subprocess and MCP plumbing. The `rig-cli-ffi` crate
+//! links these functions into a shared and a static library:
+//!
+//! ```text
+//! cargo build -p rig-cli-ffi --release
+//! ```
+//!
+//! builds `target/release/librig_cli_ffi.so` (`.dylib` on macOS) and
+//! `librig_cli_ffi.a`. Declare:
+//!
+//! ```c
+//! typedef struct RigCliClient RigCliClient;
+//!
+//! RigCliClient *rig_cli_client_new(const char *adapter, const char *server_exe,
+//!                                  const char *cli_path);
+//! char *rig_cli_run(const RigCliClient *client, const char *prompt,
+//!                   const char *schema_json, const char *payload);
+//! void rig_cli_string_free(char *s);
+//! void rig_cli_client_free(RigCliClient *client);
+//! const char *rig_cli_last_error(void);
+//! ```
+//!
+//! The agent's MCP server cannot be the host process, so `server_exe` names
+//! the `rig-cli-provider` binary, which serves the submit / validate /
+//! example tools for the schema handed to it in
+//! [`SCHEMA_ENV`](crate::daemon::SCHEMA_ENV), as daemon runs do.
+//!
+//! `rig_cli_run` blocks until the run finishes and returns one JSON object,
+//! shaped like the daemon's final response without the `id`:
+//!
+//! ```text
+//! {"type":"result","result":{"total":42},"exit_code":0,"duration_ms":8120}
+//! {"type":"error","code":"validation","message":"invalid schema: ..."}
+//! ```
+//!
+//! Strings passed in must be NUL-terminated UTF-8; strings returned must be
+//! released with `rig_cli_string_free`. A client may be shared across
+//! threads, and each call runs on the client's own Tokio runtime. A panic
+//! never unwinds into the caller: the call returns null and
+//! `rig_cli_last_error` describes the panic.
+
+use crate::daemon::{extraction_toolset, SCHEMA_ENV};
+use crate::errors::ProviderError;
+use crate::mcp_agent::{CliAdapter, McpToolAgent};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Timeout of each run started through [`rig_cli_run`].
+const RUN_TIMEOUT: Duration = Duration::from_secs(300);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A resolved CLI and the runtime its runs are driven on.
+///
+/// Opaque to C callers; created by [`rig_cli_client_new`] and released with
+/// [`rig_cli_client_free`].
+pub struct RigCliClient {
+    runtime: tokio::runtime::Runtime,
+    adapter: CliAdapter,
+    cli_path: PathBuf,
+    server_exe: PathBuf,
+}
+
+/// The JSON object returned by [`rig_cli_run`].
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FfiResponse {
+    Result {
+        result: Option<serde_json::Value>,
+        exit_code: i32,
+        duration_ms: u64,
+    },
+    Error {
+        code: String,
+        message: String,
+    },
+}
+
+impl RigCliClient {
+    fn new(adapter: &str, server_exe: &str, cli_path: Option<&str>) -> Result<Self, ProviderError> {
+        let adapter: CliAdapter = adapter.parse().map_err(ProviderError::Validation)?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|source| ProviderError::Spawn {
+                stage: "build ffi runtime",
+                source,
+            })?;
+        let cli_path = match cli_path {
+            Some(path) => PathBuf::from(path),
+            None => runtime.block_on(adapter.backend().discover(None))?,
+        };
+        Ok(Self {
+            runtime,
+            adapter,
+            cli_path,
+            server_exe: PathBuf::from(server_exe),
+        })
+    }
+
+    fn run(
+        &self,
+        prompt: &str,
+        schema_json: &str,
+        payload: Option<&str>,
+    ) -> Result<FfiResponse, ProviderError> {
+        let schema: serde_json::Value = serde_json::from_str(schema_json)
+            .map_err(|e| ProviderError::Validation(format!("invalid schema JSON: {e}")))?;
+        let toolset = extraction_toolset(schema.clone())
+            .map_err(|e| ProviderError::Validation(format!("invalid schema: {e}")))?;
+
+        let mut builder = McpToolAgent::builder()
+            .toolset(toolset)
+            .adapter(self.adapter)
+            .cli_path(&self.cli_path)
+            .mcp_server_exe(&self.server_exe)
+            .prompt(prompt)
+            .timeout(RUN_TIMEOUT)
+            .extra_env(SCHEMA_ENV, schema.to_string());
+        if let Some(payload) = payload {
+            builder = builder.payload(payload);
+        }
+
+        let result = self.runtime.block_on(builder.run())?;
+        Ok(FfiResponse::Result {
+            result: result.result_json,
+            exit_code: result.exit_code,
+            duration_ms: result.duration_ms,
+        })
+    }
+}
+
+/// Creates a client for `adapter` (`claude`, `codex` or `opencode`).
+///
+/// `server_exe` is the path of the `rig-cli-provider` binary started as each
+/// run's MCP server. `cli_path` may be null, in which case the CLI is
+/// discovered once here.
+///
+/// Returns null on failure; [`rig_cli_last_error`] then describes it.
+///
+/// # Safety
+/// `adapter` and `server_exe` must be valid NUL-terminated strings, and
+/// `cli_path` one or null.
+#[allow(unsafe_code)]
+#[no_mangle]
+pub unsafe extern "C" fn rig_cli_client_new(
+    adapter: *const c_char,
+    server_exe: *const c_char,
+    cli_path: *const c_char,
+) -> *mut RigCliClient {
+    catch_panic(std::ptr::null_mut(), || {
+        let client = (|| {
+            let adapter = str_arg(adapter, "adapter")?.ok_or_else(|| required("adapter"))?;
+            let server_exe =
+                str_arg(server_exe, "server_exe")?.ok_or_else(|| required("server_exe"))?;
+            RigCliClient::new(adapter, server_exe, str_arg(cli_path, "cli_path")?)
+        })();
+        match client {
+            Ok(client) => Box::into_raw(Box::new(client)),
+            Err(e) => {
+                set_last_error(&e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Runs `prompt` until the agent submits a result matching `schema_json`,
+/// and returns the response JSON. `payload` may be null.
+///
+/// Returns null only if `client` is null or the run panicked; every other
+/// failure is reported in the returned JSON.
+///
+/// # Safety
+/// `client` must come from [`rig_cli_client_new`] and not yet be freed;
+/// `prompt` and `schema_json` must be valid NUL-terminated strings, and
+/// `payload` one or null.
+#[allow(unsafe_code)]
+#[no_mangle]
+pub unsafe extern "C" fn rig_cli_run(
+    client: *const RigCliClient,
+    prompt: *const c_char,
+    schema_json: *const c_char,
+    payload: *const c_char,
+) -> *mut c_char {
+    catch_panic(std::ptr::null_mut(), || {
+        let Some(client) = client.as_ref() else {
+            set_last_error("client is null");
+            return std::ptr::null_mut();
+        };
+        let response = (|| {
+            let prompt = str_arg(prompt, "prompt")?.ok_or_else(|| required("prompt"))?;
+            let schema_json =
+                str_arg(schema_json, "schema_json")?.ok_or_else(|| required("schema_json"))?;
+            client.run(prompt, schema_json, str_arg(payload, "payload")?)
+        })()
+        .unwrap_or_else(|e| FfiResponse::Error {
+            code: e.error_code().to_string(),
+            message: e.to_string(),
+        });
+        let json = serde_json::to_string(&response)
+            .unwrap_or_else(|e| format!(r#"{{"type":"error","code":"internal","message":"{e}"}}"#));
+        c_string(json).into_raw()
+    })
+}
+
+/// Releases a string returned by [`rig_cli_run`]. Null is ignored.
+///
+/// # Safety
+/// `s` must come from [`rig_cli_run`] and not yet be freed.
+#[allow(unsafe_code)]
+#[no_mangle]
+pub unsafe extern "C" fn rig_cli_string_free(s: *mut c_char) {
+    catch_panic((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    });
+}
+
+/// Releases a client, shutting down its runtime. Null is ignored.
+///
+/// # Safety
+/// `client` must come from [`rig_cli_client_new`], not yet be freed, and
+/// not be in use by another thread.
+#[allow(unsafe_code)]
+#[no_mangle]
+pub unsafe extern "C" fn rig_cli_client_free(client: *mut RigCliClient) {
+    catch_panic((), || {
+        if !client.is_null() {
+            drop(Box::from_raw(client));
+        }
+    });
+}
+
+/// The last error reported on this thread by a call returning null, or null
+/// if there was none.
+///
+/// The string stays valid until the next failing call on this thread.
+#[allow(unsafe_code)]
+#[no_mangle]
+pub extern "C" fn rig_cli_last_error() -> *const c_char {
+    catch_panic(std::ptr::null(), || {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(std::ptr::null(), |e| e.as_ptr())
+        })
+    })
+}
+
+/// Runs the body of an entry point, returning `fallback` and recording the
+/// panic as the last error if it panics.
+fn catch_panic<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    std::panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        set_last_error(&format!("panicked: {message}"));
+        fallback
+    })
+}
+
+/// Reads a string argument, with null as `None`.
+///
+/// # Safety
+/// `ptr` must be null or a valid NUL-terminated string outliving `'a`.
+#[allow(unsafe_code)]
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<Option<&'a str>, ProviderError> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(Some)
+        .map_err(|e| ProviderError::Validation(format!("{name} is not valid UTF-8: {e}")))
+}
+
+fn required(name: &str) -> ProviderError {
+    ProviderError::Validation(format!("{name} must not be null"))
+}
+
+fn set_last_error(message: &str) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(c_string(message.to_string())));
+}
+
+/// Converts `s` to a C string, dropping any interior NUL bytes.
+fn c_string(s: String) -> CString {
+    CString::new(s).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic, unsafe_code)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        let ptr = rig_cli_last_error();
+        assert!(!ptr.is_null());
+        unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string()
+    }
+
+    fn run(client: *const RigCliClient, schema: &str) -> serde_json::Value {
+        let prompt = CString::new("Extract").unwrap();
+        let schema = CString::new(schema).unwrap();
+        unsafe {
+            let response = rig_cli_run(client, prompt.as_ptr(), schema.as_ptr(), std::ptr::null());
+            let json = CStr::from_ptr(response).to_str().unwrap().to_string();
+            rig_cli_string_free(response);
+            serde_json::from_str(&json).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_client_new_reports_errors_through_last_error() {
+        let adapter = CString::new("gemini").unwrap();
+        let server = CString::new("/usr/bin/rig-cli-provider").unwrap();
+        let client =
+            unsafe { rig_cli_client_new(adapter.as_ptr(), server.as_ptr(), std::ptr::null()) };
+        assert!(client.is_null());
+        assert!(
+            last_error().contains("unknown adapter 'gemini'"),
+            "{}",
+            last_error()
+        );
+
+        let client =
+            unsafe { rig_cli_client_new(std::ptr::null(), server.as_ptr(), std::ptr::null()) };
+        assert!(client.is_null());
+        assert!(last_error().contains("adapter must not be null"));
+    }
+
+    #[test]
+    fn test_run_reports_errors_as_json() {
+        let adapter = CString::new("claude").unwrap();
+        let server = CString::new("/usr/bin/rig-cli-provider").unwrap();
+        let cli = CString::new("/bin/false").unwrap();
+        let client = unsafe { rig_cli_client_new(adapter.as_ptr(), server.as_ptr(), cli.as_ptr()) };
+        assert!(!client.is_null());
+
+        let response = run(client, "not json");
+        assert_eq!(response["type"], "error");
+        assert_eq!(response["code"], "validation");
+        assert!(response["message"]
+            .as_str()
+            .unwrap()
+            .contains("invalid schema JSON"));
+
+        unsafe { rig_cli_client_free(client) };
+        let prompt = CString::new("Extract").unwrap();
+        let response = unsafe {
+            rig_cli_run(
+                std::ptr::null(),
+                prompt.as_ptr(),
+                prompt.as_ptr(),
+                std::ptr::null(),
+            )
+        };
+        assert!(response.is_null());
+        assert_eq!(last_error(), "client is null");
+    }
+
+    #[test]
+    fn test_panics_become_the_last_error() {
+        let result = catch_panic(std::ptr::null::<c_char>(), || panic!("boom"));
+        assert!(result.is_null());
+        assert_eq!(last_error(), "panicked: boom");
+    }
+
+    #[test]
+    fn test_c_string_drops_interior_nul_bytes() {
+        assert_eq!(c_string("a\0b".to_string()).to_str().unwrap(), "ab");
+    }
+}
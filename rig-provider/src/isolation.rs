@@ -0,0 +1,203 @@
+//! Keeping the host user's Claude Code configuration out of MCP runs.
+//!
+//! Left alone, a Claude Code run loads the `CLAUDE.md` files, hooks,
+//! settings and globally registered MCP servers of whoever runs the host,
+//! so the same extraction behaves differently on every machine. An
+//! [isolated](crate::mcp_agent::McpToolAgentBuilder::isolated) run, the
+//! default, instead:
+//!
+//! - passes `--setting-sources ""`, so no settings file is loaded;
+//! - passes `--strict-mcp-config`, so only the run's own MCP servers are
+//!   connected;
+//! - points `HOME`, `CLAUDE_CONFIG_DIR` and the XDG base directories at a
+//!   [`ScratchHome`] that is deleted after the run.
+//!
+//! The stored login, `.credentials.json` in the user's Claude config
+//! directory, is copied into the scratch home so the CLI stays signed in,
+//! and copied back when the run refreshed it, so the user's refresh token
+//! stays valid. Logins kept in the macOS keychain and API keys passed in
+//! the environment are not affected by the scratch home.
+
+use crate::errors::ProviderError;
+use std::path::{Path, PathBuf};
+
+/// Name of the file the Claude CLI stores its login in.
+const CREDENTIALS_FILE: &str = ".credentials.json";
+
+/// A temporary home directory for one isolated run, deleted on drop.
+#[derive(Debug)]
+pub struct ScratchHome {
+    dir: tempfile::TempDir,
+    /// The user's login copied in, if any.
+    login: Option<CopiedLogin>,
+}
+
+/// A login file copied into the scratch home.
+#[derive(Debug)]
+struct CopiedLogin {
+    /// The user's file, written back if the run changes the copy.
+    source: PathBuf,
+    /// The contents copied.
+    contents: Vec<u8>,
+}
+
+impl ScratchHome {
+    /// Creates an empty home with a copy of the user's Claude login, if
+    /// there is one. A login the run refreshes is copied back on drop.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Spawn`] if the directory cannot be created
+    /// or the login cannot be copied.
+    pub fn new() -> Result<Self, ProviderError> {
+        Self::with_config_dir(user_config_dir().as_deref())
+    }
+
+    /// [`new`](Self::new), with the login taken from `config_dir`.
+    fn with_config_dir(config_dir: Option<&Path>) -> Result<Self, ProviderError> {
+        let dir = tempfile::Builder::new()
+            .prefix("rig_home_")
+            .tempdir()
+            .map_err(|source| ProviderError::Spawn {
+                stage: "create scratch home",
+                source,
+            })?;
+        let mut home = Self { dir, login: None };
+        if let Some(credentials) = config_dir.map(|dir| dir.join(CREDENTIALS_FILE)) {
+            home.copy_login(credentials)
+                .map_err(|source| ProviderError::Spawn {
+                    stage: "copy login into scratch home",
+                    source,
+                })?;
+        }
+        Ok(home)
+    }
+
+    /// The scratch home directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Environment variables pointing the CLI at the scratch home.
+    #[must_use]
+    pub fn env(&self) -> Vec<(String, String)> {
+        let home = self.path();
+        [
+            ("HOME", home.to_path_buf()),
+            ("USERPROFILE", home.to_path_buf()),
+            ("CLAUDE_CONFIG_DIR", home.join(".claude")),
+            ("XDG_CONFIG_HOME", home.join(".config")),
+            ("XDG_DATA_HOME", home.join(".local/share")),
+            ("XDG_STATE_HOME", home.join(".local/state")),
+            ("XDG_CACHE_HOME", home.join(".cache")),
+        ]
+        .into_iter()
+        .map(|(name, path)| (name.to_string(), path.to_string_lossy().into_owned()))
+        .collect()
+    }
+
+    /// The login file inside the scratch home.
+    fn scratch_login(&self) -> PathBuf {
+        self.path().join(".claude").join(CREDENTIALS_FILE)
+    }
+
+    /// Copies the login file at `credentials` into the scratch config
+    /// directory; a missing file is not an error.
+    fn copy_login(&mut self, credentials: PathBuf) -> std::io::Result<()> {
+        if !credentials.is_file() {
+            return Ok(());
+        }
+        let contents = std::fs::read(&credentials)?;
+        let copy = self.scratch_login();
+        if let Some(config_dir) = copy.parent() {
+            std::fs::create_dir_all(config_dir)?;
+        }
+        std::fs::copy(&credentials, copy)?;
+        self.login = Some(CopiedLogin {
+            source: credentials,
+            contents,
+        });
+        Ok(())
+    }
+
+    /// Copies the login back to the user's file if the run changed it, as
+    /// the CLI does when it refreshes an expired token.
+    fn write_back_login(&self) -> std::io::Result<()> {
+        let Some(login) = &self.login else {
+            return Ok(());
+        };
+        match std::fs::read(self.scratch_login()) {
+            Ok(contents) if !contents.is_empty() && contents != login.contents => {
+                std::fs::write(&login.source, contents)
+            }
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for ScratchHome {
+    fn drop(&mut self) {
+        if let Err(e) = self.write_back_login() {
+            tracing::warn!(error = %e, "Failed to copy the refreshed Claude login back");
+        }
+    }
+}
+
+/// The user's Claude config directory: `CLAUDE_CONFIG_DIR`, or `~/.claude`.
+fn user_config_dir() -> Option<PathBuf> {
+    std::env::var_os("CLAUDE_CONFIG_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".claude")))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_home_carries_the_login_and_is_removed() {
+        let user = tempfile::tempdir().unwrap();
+        let credentials = user.path().join(CREDENTIALS_FILE);
+        std::fs::write(&credentials, r#"{"token":"t"}"#).unwrap();
+
+        let home = ScratchHome::with_config_dir(Some(user.path())).unwrap();
+        let copied = home.scratch_login();
+        assert_eq!(std::fs::read_to_string(copied).unwrap(), r#"{"token":"t"}"#);
+
+        let env = home.env();
+        let home_var = env.iter().find(|(name, _)| name == "HOME").unwrap();
+        assert_eq!(Path::new(&home_var.1), home.path());
+
+        let path = home.path().to_path_buf();
+        drop(home);
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(&credentials).unwrap(),
+            r#"{"token":"t"}"#
+        );
+
+        let empty = tempfile::tempdir().unwrap();
+        let home = ScratchHome::with_config_dir(Some(empty.path())).unwrap();
+        assert!(!home.scratch_login().exists());
+    }
+
+    #[test]
+    fn test_refreshed_login_is_written_back() {
+        let user = tempfile::tempdir().unwrap();
+        let credentials = user.path().join(CREDENTIALS_FILE);
+        std::fs::write(&credentials, r#"{"refresh":"old"}"#).unwrap();
+
+        let home = ScratchHome::with_config_dir(Some(user.path())).unwrap();
+        std::fs::write(home.scratch_login(), r#"{"refresh":"new"}"#).unwrap();
+        drop(home);
+
+        assert_eq!(
+            std::fs::read_to_string(&credentials).unwrap(),
+            r#"{"refresh":"new"}"#
+        );
+    }
+}
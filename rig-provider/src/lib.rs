@@ -6,8 +6,30 @@
 
 /// Adapter implementations for various AI providers.
 pub mod adapters;
+/// Per-run artifact directories for MCP tool agents.
+pub mod artifacts;
+/// Binary attachments sent alongside a payload.
+pub mod attachments;
+/// Pluggable CLI backends for MCP tool agents.
+pub mod backend;
+/// Pre-spawn checks of MCP tool agent working directories.
+pub mod containment;
+/// API key loading for spawned CLIs.
+pub mod credentials;
+/// Long-running extraction service on a local unix socket.
+#[cfg(unix)]
+pub mod daemon;
 /// Error types for the provider.
 pub mod errors;
+/// Provider-agnostic stream events.
+pub mod events;
+/// C interface to MCP-enforced extraction.
+#[cfg(all(unix, feature = "ffi"))]
+pub mod ffi;
+/// Keeping the host user's Claude Code configuration out of MCP runs.
+pub mod isolation;
+/// Runtime-adjustable log verbosity.
+pub mod logging;
 /// Session management for isolated execution environments.
 pub mod sessions;
 /// Setup and configuration logic.
@@ -19,10 +41,44 @@ pub use adapters::codex::CodexModel;
 pub use adapters::opencode::OpenCodeModel;
 /// MCP tool agent builder for transparent CLI orchestration.
 pub mod mcp_agent;
+/// Composable hooks around MCP tool agent runs.
+pub mod middleware;
+/// Default models of the built-in adapters.
+pub mod models;
+/// Rendering directory trees into payloads.
+pub mod payload;
+/// Warm, interactive Claude Code processes leased per request.
+pub mod pool;
+/// Preflight checks for MCP tool agents.
+pub mod preflight;
+/// Prompt injection scanning for payload context.
+pub mod scanner;
+/// Settings for the `serve` MCP bridge.
+pub mod serve;
+/// Large context written once and shared by many runs.
+pub mod shared_context;
+/// Checking submissions against the submit schema while a run streams.
+pub mod stream_validation;
+/// Captured CLI transcripts and a fake CLI that replays them.
+#[cfg(all(unix, any(test, feature = "test-support")))]
+pub mod testing;
+/// JSONL conversation transcripts of runs.
+pub mod transcript;
+/// Token-aware truncation of payload context.
+pub mod truncation;
 /// Utility functions.
 pub mod utils;
 
+pub use backend::CliBackend;
+pub use credentials::Credentials;
+pub use events::StreamEvent;
 pub use mcp_agent::{
-    CliAdapter, CliAgent, CliAgentBuilder, McpStreamEvent, McpStreamHandle, McpToolAgent,
-    McpToolAgentBuilder, McpToolAgentResult, DEFAULT_WORKFLOW_TEMPLATE,
+    CliAdapter, CliAgent, CliAgentBuilder, FailedAttempt, McpStreamEvent, McpStreamHandle,
+    McpToolAgent, McpToolAgentBuilder, McpToolAgentResult, DEFAULT_WORKFLOW_TEMPLATE,
 };
+pub use middleware::{RunMiddleware, RunRequest};
+pub use models::DefaultModels;
+pub use payload::PayloadBuilder;
+pub use preflight::PreflightReport;
+pub use scanner::PayloadScanner;
+pub use truncation::PayloadBudget;
@@ -0,0 +1,169 @@
+//! Log verbosity that can be changed while the process runs.
+//!
+//! The active filter is a list of `target=level` directives, e.g.
+//! `info,rig_cli_provider=debug,rig_cli_claude=trace`: a bare level applies
+//! to every target without a directive of its own. [`filter`] returns a
+//! per-layer filter that follows it, so an embedder keeps its own
+//! subscriber and only attaches the filter to one of its layers:
+//!
+//! ```ignore
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry()
+//!     .with(tracing_subscriber::fmt::layer().with_filter(logging::filter()))
+//!     .init();
+//! logging::set_module_level("rig_cli_claude", tracing::Level::TRACE);
+//! ```
+//!
+//! [`set_filter`] and [`set_module_level`] take effect on the next event.
+//! The `rig-cli-provider` binary installs such a subscriber with [`init`],
+//! applying the directives in [`LOG_ENV`] after its `--log-level`.
+
+use crate::errors::ProviderError;
+use std::sync::{LazyLock, PoisonError, RwLock};
+use tracing::Metadata;
+use tracing_subscriber::filter::{filter_fn, FilterFn, LevelFilter, Targets};
+use tracing_subscriber::prelude::*;
+
+/// Environment variable with directives applied after the `--log-level`,
+/// e.g. `rig_cli_provider=debug,rig_cli_claude=trace`.
+pub const LOG_ENV: &str = "RIG_LOG";
+
+/// The active directives; `info` until changed.
+static FILTER: LazyLock<RwLock<Targets>> =
+    LazyLock::new(|| RwLock::new(Targets::new().with_default(LevelFilter::INFO)));
+
+/// How [`init`] writes log lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Pretty,
+    /// One JSON object per line, with the event's target.
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "unknown log format '{s}' (expected pretty or json)"
+            )),
+        }
+    }
+}
+
+/// Replaces the active filter with `directives`, e.g. `warn` or
+/// `info,rig_cli_claude=trace`.
+///
+/// # Errors
+/// Returns [`ProviderError::Init`] if a directive does not parse; the
+/// active filter is then left unchanged.
+pub fn set_filter(directives: &str) -> Result<(), ProviderError> {
+    let targets = parse(directives)?;
+    *FILTER.write().unwrap_or_else(PoisonError::into_inner) = targets;
+    tracing::callsite::rebuild_interest_cache();
+    Ok(())
+}
+
+/// Sets the level of `module` and the targets below it, keeping every
+/// other directive.
+pub fn set_module_level(module: &str, level: impl Into<LevelFilter>) {
+    {
+        let mut targets = FILTER.write().unwrap_or_else(PoisonError::into_inner);
+        *targets = targets.clone().with_target(module, level);
+    }
+    tracing::callsite::rebuild_interest_cache();
+}
+
+/// The active directives, in the syntax [`set_filter`] accepts.
+#[must_use]
+pub fn directives() -> String {
+    FILTER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .to_string()
+}
+
+/// A per-layer filter enabling what the active directives enable.
+#[must_use]
+pub fn filter() -> FilterFn<fn(&Metadata<'_>) -> bool> {
+    filter_fn(enabled as fn(&Metadata<'_>) -> bool)
+}
+
+/// Installs a global subscriber writing to stderr in `format`, filtered by
+/// `level` and then the directives in [`LOG_ENV`]. Stdout is left alone:
+/// it carries the MCP protocol when serving.
+///
+/// # Errors
+/// Returns [`ProviderError::Init`] if a directive does not parse or a
+/// global subscriber is already installed.
+pub fn init(level: &str, format: LogFormat) -> Result<(), ProviderError> {
+    let env = std::env::var(LOG_ENV).unwrap_or_default();
+    set_filter(&format!("{level},{env}"))?;
+    let stderr = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    let layer = match format {
+        LogFormat::Pretty => stderr.with_target(false).with_filter(filter()).boxed(),
+        LogFormat::Json => stderr.json().with_filter(filter()).boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(layer)
+        .try_init()
+        .map_err(|e| ProviderError::Init(format!("failed to install logger: {e}")))
+}
+
+/// Parses comma-separated directives, skipping empty ones.
+fn parse(directives: &str) -> Result<Targets, ProviderError> {
+    directives
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .collect::<Vec<_>>()
+        .join(",")
+        .parse()
+        .map_err(|e| ProviderError::Init(format!("invalid log level '{directives}': {e}")))
+}
+
+fn enabled(metadata: &Metadata<'_>) -> bool {
+    FILTER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .would_enable(metadata.target(), metadata.level())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use tracing::Level;
+
+    #[test]
+    fn test_filters_change_at_runtime() {
+        set_filter("warn, rig_cli_provider=debug,").unwrap();
+        let active = parse(&directives()).unwrap();
+        assert!(active.would_enable("rig_cli_provider::pool", &Level::DEBUG));
+        assert!(!active.would_enable("rig_cli_claude", &Level::INFO));
+
+        set_module_level("rig_cli_claude", Level::TRACE);
+        let active = parse(&directives()).unwrap();
+        assert!(active.would_enable("rig_cli_claude::process", &Level::TRACE));
+        assert!(active.would_enable("rig_cli_provider", &Level::DEBUG));
+
+        assert!(set_filter("rig=loud").is_err());
+        assert!(parse(&directives())
+            .unwrap()
+            .would_enable("rig_cli_claude", &Level::TRACE));
+    }
+
+    #[test]
+    fn test_log_format_parses_case_insensitively() {
+        assert_eq!("JSON".parse(), Ok(LogFormat::Json));
+        assert_eq!("pretty".parse(), Ok(LogFormat::Pretty));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
@@ -1,14 +1,20 @@
 //! The Rig Provider binary serves as an MCP server bridging AI CLI adapters.
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use rig::tool::ToolSet;
 use rig_cli_mcp::prelude::*;
 use rig_cli_provider::adapters::claude::ClaudeTool;
 use rig_cli_provider::adapters::codex::CodexTool;
 use rig_cli_provider::adapters::opencode::OpenCodeTool;
+#[cfg(unix)]
+use rig_cli_provider::daemon::{self, DaemonConfig};
+use rig_cli_provider::logging::{self, LogFormat};
+use rig_cli_provider::mcp_agent::CliAdapter;
+use rig_cli_provider::serve::ServeConfig;
 use rig_cli_provider::setup::{run_setup, SetupConfig};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -20,13 +26,123 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Starts the MCP server (default)
-    Serve,
+    Serve(ServeArgs),
     /// Automatically register this provider in Claude/Codex/OpenCode configs
     Setup {
         /// Show what would be done without modifying files
         #[arg(long)]
         dry_run: bool,
     },
+    /// Serves extraction requests on a unix socket, keeping adapters warm
+    #[cfg(unix)]
+    Daemon(DaemonArgs),
+}
+
+/// Flags for the `daemon` subcommand.
+#[cfg(unix)]
+#[derive(Args)]
+struct DaemonArgs {
+    /// Unix socket to listen on [default: rig-cli-provider.sock in the temp dir]
+    #[arg(long)]
+    socket: Option<PathBuf>,
+    /// Adapter requests may run on, the first being the default (repeatable)
+    #[arg(long = "adapter", default_value = "claude")]
+    adapters: Vec<CliAdapter>,
+    /// Maximum number of runs in flight at a time
+    #[arg(long, default_value_t = 4)]
+    workers: usize,
+    /// Timeout of each run in seconds, unless the request sets its own
+    #[arg(long, default_value_t = 300)]
+    timeout_secs: u64,
+    /// Log filter, e.g. `info` or `rig_cli_provider=debug,warn`
+    #[arg(long, default_value = "info")]
+    log_level: String,
+    /// Log line format: pretty or json
+    #[arg(long, default_value = "pretty")]
+    log_format: LogFormat,
+}
+
+#[cfg(unix)]
+impl DaemonArgs {
+    fn resolve(self) -> DaemonConfig {
+        let defaults = DaemonConfig::default();
+        DaemonConfig {
+            socket: self.socket.unwrap_or(defaults.socket),
+            adapters: self.adapters,
+            workers: self.workers,
+            timeout: std::time::Duration::from_secs(self.timeout_secs),
+        }
+    }
+}
+
+/// Flags for the `serve` subcommand; each one overrides the config file.
+#[derive(Args, Default)]
+struct ServeArgs {
+    /// TOML or JSON file with server settings
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Adapter to register as a tool: claude, codex, or opencode (repeatable)
+    #[arg(long = "adapter")]
+    adapters: Vec<CliAdapter>,
+    /// JSON Schema file enforced by the submit/validate/example tools
+    #[arg(long)]
+    schema: Option<PathBuf>,
+    /// Server name reported to MCP clients
+    #[arg(long)]
+    server_name: Option<String>,
+    /// Log filter, e.g. `info` or `rig_cli_provider=debug,warn`
+    #[arg(long)]
+    log_level: Option<String>,
+    /// Log line format: pretty or json
+    #[arg(long)]
+    log_format: Option<LogFormat>,
+    /// MCP config file passed to Claude Code (repeatable)
+    #[arg(long = "claude-mcp-config")]
+    claude_mcp_configs: Vec<String>,
+    /// Times a failed MCP session is restarted before the server exits
+    #[arg(long)]
+    max_restarts: Option<u32>,
+    /// Do not print the startup banner with client config snippets
+    #[arg(long)]
+    quiet: bool,
+    /// Print the client config snippets in this format and exit
+    #[arg(long, value_name = "FORMAT", value_parser = ["json"])]
+    print_config: Option<String>,
+}
+
+impl ServeArgs {
+    /// Loads the config file, if any, and applies the flags on top.
+    fn resolve(self) -> Result<ServeConfig, ProviderError> {
+        let mut config = match self.config {
+            Some(ref path) => ServeConfig::load(path)?,
+            None => ServeConfig::default(),
+        };
+        if !self.adapters.is_empty() {
+            config.adapters = self.adapters;
+        }
+        if self.schema.is_some() {
+            config.schema = self.schema;
+        }
+        if let Some(name) = self.server_name {
+            config.server_name = name;
+        }
+        if let Some(level) = self.log_level {
+            config.log_level = level;
+        }
+        if let Some(format) = self.log_format {
+            config.log_format = format;
+        }
+        if !self.claude_mcp_configs.is_empty() {
+            config.claude_mcp_configs = self.claude_mcp_configs;
+        }
+        if let Some(max_restarts) = self.max_restarts {
+            config.max_restarts = max_restarts;
+        }
+        if self.quiet {
+            config.quiet = true;
+        }
+        Ok(config)
+    }
 }
 
 /// Structured output from the provider containing the AI result and metadata.
@@ -42,61 +158,171 @@ use rig_cli_provider::errors::ProviderError;
 
 #[tokio::main]
 async fn main() -> Result<(), ProviderError> {
-    let cli = Cli::parse();
+    // Started again by a daemon run's agent as its MCP server.
+    #[cfg(unix)]
+    if let Ok(schema) = std::env::var(daemon::SCHEMA_ENV) {
+        logging::init("warn", LogFormat::Pretty)?;
+        return daemon::serve_tools(&schema).await;
+    }
 
-    // Initialize tracing aligned with Rig's style
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .with_target(false)
-        .init();
+    let cli = Cli::parse();
 
     match cli.command {
         Some(Commands::Setup { dry_run }) => {
-            run_setup(&SetupConfig { dry_run })?;
+            logging::init("info", LogFormat::Pretty)?;
+            let report = run_setup(&SetupConfig {
+                dry_run,
+                home: None,
+            })?;
+            print!("{report}");
+            if !report.is_success() {
+                return Err(anyhow::anyhow!(
+                    "setup failed for {} config file(s)",
+                    report.errors.len()
+                )
+                .into());
+            }
+        }
+        #[cfg(unix)]
+        Some(Commands::Daemon(args)) => {
+            logging::init(&args.log_level, args.log_format)?;
+            daemon::run(args.resolve()).await?;
         }
-        Some(Commands::Serve) | None => {
-            run_serve().await?;
+        Some(Commands::Serve(args)) => {
+            let print_config = args.print_config.is_some();
+            let config = args.resolve()?;
+            let args = launch_args(std::env::args().skip(1));
+            if print_config {
+                let mut mcp_config = McpConfig::for_current_exe(config.server_name)?;
+                mcp_config.args = args;
+                let json = serde_json::to_string_pretty(&mcp_config.to_client_configs_json())
+                    .map_err(|e| ProviderError::Init(e.to_string()))?;
+                println!("{json}");
+                return Ok(());
+            }
+            logging::init(&config.log_level, config.log_format)?;
+            run_serve(&config, args).await?;
+        }
+        None => {
+            let config = ServeArgs::default().resolve()?;
+            logging::init(&config.log_level, config.log_format)?;
+            run_serve(&config, Vec::new()).await?;
         }
     }
 
     Ok(())
 }
 
-async fn run_serve() -> Result<(), ProviderError> {
-    let mut toolset = ToolSet::default();
-
-    // In a real scenario, these configs would be detected or passed via env
-    let mcp_configs = vec!["~/.claude.json".to_string()];
-
-    tracing::info!("Initializing Claude Code adapter...");
-    let claude = ClaudeTool::new(mcp_configs).await?;
-    toolset.add_tool(claude);
-
-    tracing::info!("Initializing Codex adapter...");
-    let codex = CodexTool::new().await?;
-    toolset.add_tool(codex);
+/// The arguments this run was started with, minus `--print-config`, so
+/// the printed client configs start the server the same way.
+fn launch_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut args = args.into_iter();
+    let mut kept = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--print-config" {
+            args.next();
+        } else if !arg.starts_with("--print-config=") {
+            kept.push(arg);
+        }
+    }
+    kept
+}
 
-    tracing::info!("Initializing OpenCode adapter...");
-    let opencode = OpenCodeTool::new().await?;
-    toolset.add_tool(opencode);
+async fn run_serve(config: &ServeConfig, args: Vec<String>) -> Result<(), ProviderError> {
+    let mut toolset = ToolSet::default();
 
-    // 2. Add the 3 default MCP tools for seamless extraction
-    let toolkit = JsonSchemaToolkit::<ProviderOutput>::builder()
-        .on_success("Output successfully processed and extracted.")
-        .build();
+    for adapter in &config.adapters {
+        match adapter {
+            CliAdapter::ClaudeCode => {
+                tracing::info!("Initializing Claude Code adapter...");
+                let claude = ClaudeTool::new(config.claude_mcp_configs.clone()).await?;
+                toolset.add_tool(claude);
+            }
+            CliAdapter::Codex => {
+                tracing::info!("Initializing Codex adapter...");
+                let codex = CodexTool::new().await?;
+                toolset.add_tool(codex);
+            }
+            CliAdapter::OpenCode => {
+                tracing::info!("Initializing OpenCode adapter...");
+                let opencode = OpenCodeTool::new().await?;
+                toolset.add_tool(opencode);
+            }
+            CliAdapter::Custom(name) => {
+                return Err(ProviderError::Init(format!(
+                    "adapter '{name}' cannot be served"
+                )));
+            }
+        }
+    }
 
-    let (submit, validate, example) = toolkit.build_tools();
-    toolset.add_tool(submit);
-    toolset.add_tool(validate);
-    toolset.add_tool(example);
+    // 2. Add the 3 MCP extraction tools, enforcing the configured schema
+    if let Some(schema) = config.load_schema()? {
+        let (submit, validate, example) = DynamicJsonSchemaToolkit::builder()
+            .schema(schema)
+            .on_success("Output successfully processed and extracted.")
+            .build()
+            .map_err(ProviderError::Init)?
+            .build_tools();
+        toolset.add_tool(submit);
+        toolset.add_tool(validate);
+        toolset.add_tool(example);
+    } else {
+        let (submit, validate, example) = JsonSchemaToolkit::<ProviderOutput>::builder()
+            .on_success("Output successfully processed and extracted.")
+            .build()
+            .build_tools();
+        toolset.add_tool(submit);
+        toolset.add_tool(validate);
+        toolset.add_tool(example);
+    }
 
-    tracing::info!("Rig Provider MCP Server starting over stdio...");
+    tracing::info!(
+        server_name = %config.server_name,
+        "Rig Provider MCP Server starting over stdio..."
+    );
 
     // Start the MCP Server
-    toolset
+    RigMcpHandler::builder()
+        .name(&config.server_name)
+        .toolset(toolset)
+        .restart_policy(RestartPolicy {
+            max_restarts: config.max_restarts,
+            ..RestartPolicy::default()
+        })
+        .quiet(config.quiet)
+        .launch_args(args)
+        .build()
+        .await
+        .map_err(|e| ProviderError::Init(e.to_string()))?
         .run_stdio()
         .await
         .map_err(|e| ProviderError::Init(e.to_string()))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_args_drop_print_config() {
+        let args = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            launch_args(args(&[
+                "serve",
+                "--print-config",
+                "json",
+                "--schema",
+                "s.json"
+            ])),
+            ["serve", "--schema", "s.json"]
+        );
+        assert_eq!(
+            launch_args(args(&["serve", "--print-config=json", "--quiet"])),
+            ["serve", "--quiet"]
+        );
+    }
+}
@@ -1,147 +1,25 @@
 //! MCP tool agent builder for transparent CLI orchestration.
 //!
 //! Provides [`McpToolAgent`] and its builder, which handle MCP config generation,
-//! CLI discovery, tool name computation, and execution across all three supported
-//! CLI adapters (Claude Code, Codex, OpenCode).
-
+//! CLI discovery, tool name computation, and execution through a
+//! [`CliBackend`]: one of the three built-in CLI adapters (Claude Code, Codex,
+//! OpenCode) or a custom backend.
+
+use crate::artifacts::RunArtifacts;
+use crate::attachments::{Attachments, BinaryPayload};
+use crate::backend::{BackendRequest, CliBackend};
+use crate::credentials::Credentials;
 use crate::errors::ProviderError;
-use std::io::Write as _;
+use crate::isolation::ScratchHome;
+use crate::middleware::{MiddlewareStack, RunMiddleware, RunRequest};
+use crate::models::DefaultModels;
+use crate::preflight::PreflightReport;
+use crate::scanner::{PayloadFinding, PayloadScanner};
+use crate::shared_context::SharedContext;
+use crate::stream_validation::SubmitValidator;
+use crate::truncation::PayloadBudget;
 use std::time::Duration;
 
-/// Version requirements for CLI adapters. Hardcoded per adapter, not configurable.
-struct VersionRequirement {
-    /// Minimum supported version (below this = unsupported, warn).
-    min_version: semver::Version,
-    /// Maximum tested version (above this = untested, warn with different message).
-    max_tested: semver::Version,
-    /// CLI name for log messages.
-    cli_name: &'static str,
-}
-
-/// Version requirement for Claude Code CLI.
-const fn claude_code_version_req() -> VersionRequirement {
-    VersionRequirement {
-        min_version: semver::Version::new(1, 0, 0),
-        max_tested: semver::Version::new(2, 99, 0),
-        cli_name: "Claude Code",
-    }
-}
-
-/// Version requirement for Codex CLI.
-const fn codex_version_req() -> VersionRequirement {
-    VersionRequirement {
-        min_version: semver::Version::new(0, 1, 0),
-        max_tested: semver::Version::new(0, 99, 0),
-        cli_name: "Codex",
-    }
-}
-
-/// Version requirement for `OpenCode` CLI.
-const fn opencode_version_req() -> VersionRequirement {
-    VersionRequirement {
-        min_version: semver::Version::new(0, 1, 0),
-        max_tested: semver::Version::new(0, 99, 0),
-        cli_name: "OpenCode",
-    }
-}
-
-/// Detects CLI version and validates against requirements.
-///
-/// Runs `<binary> --version`, parses the version string with semver,
-/// and emits structured tracing warnings for unsupported or untested versions.
-/// Always returns Ok — version issues are warnings, never blockers.
-async fn detect_and_validate_version(
-    binary_path: &std::path::Path,
-    requirement: &VersionRequirement,
-) {
-    let output = match tokio::process::Command::new(binary_path)
-        .arg("--version")
-        .output()
-        .await
-    {
-        Ok(output) => output,
-        Err(e) => {
-            tracing::warn!(
-                event = "version_detection_failed",
-                cli = requirement.cli_name,
-                error = %e,
-                "version_detection_failed"
-            );
-            return;
-        }
-    };
-
-    let version_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-
-    // Extract version substring: strip common prefixes like "v", split on whitespace
-    // to handle formats like "claude 1.2.3" or "codex v0.91.0"
-    let cleaned = extract_version_string(&version_str);
-
-    let version = match semver::Version::parse(&cleaned) {
-        Ok(v) => v,
-        Err(e) => {
-            tracing::warn!(
-                event = "version_parse_failed",
-                cli = requirement.cli_name,
-                raw_version = %version_str,
-                error = %e,
-                "version_parse_failed"
-            );
-            return;
-        }
-    };
-
-    tracing::debug!(
-        event = "version_detected",
-        cli = requirement.cli_name,
-        version = %version,
-        "version_detected"
-    );
-
-    if version < requirement.min_version {
-        tracing::warn!(
-            event = "version_unsupported",
-            cli = requirement.cli_name,
-            detected = %version,
-            minimum = %requirement.min_version,
-            "version_unsupported: {} {} is below minimum supported version {}",
-            requirement.cli_name,
-            version,
-            requirement.min_version,
-        );
-    } else if version > requirement.max_tested {
-        tracing::warn!(
-            event = "version_untested",
-            cli = requirement.cli_name,
-            detected = %version,
-            max_tested = %requirement.max_tested,
-            "version_untested: {} {} is newer than maximum tested version {}",
-            requirement.cli_name,
-            version,
-            requirement.max_tested,
-        );
-    }
-}
-
-/// Extracts a semver-parseable version string from CLI version output.
-///
-/// Handles common formats:
-/// - "1.2.3" -> "1.2.3"
-/// - "v1.2.3" -> "1.2.3"
-/// - "claude 1.2.3" -> "1.2.3"
-/// - "codex v0.91.0-beta" -> "0.91.0-beta"
-fn extract_version_string(raw: &str) -> String {
-    // Split on whitespace and find the token that looks like a version
-    for token in raw.split_whitespace() {
-        let stripped = token.strip_prefix('v').unwrap_or(token);
-        if semver::Version::parse(stripped).is_ok() {
-            return stripped.to_string();
-        }
-    }
-    // Fallback: try stripping 'v' from the whole string
-    raw.strip_prefix('v').unwrap_or(raw).to_string()
-}
-
 /// Default instruction template enforcing the three-tool workflow.
 ///
 /// This template requires agents to follow the example -> validate -> submit
@@ -163,30 +41,64 @@ RULES:
 - ONLY the 'submit' tool call marks task completion
 - The task is NOT complete until you call 'submit'";
 
-/// Stream event from MCP-enforced CLI execution.
-#[derive(Debug, Clone)]
-pub enum McpStreamEvent {
-    /// Text content from the agent.
-    Text(String),
-    /// Tool call initiated by the agent.
-    ToolCall {
-        /// The tool name.
-        name: String,
-        /// The tool input as a JSON string.
-        input: String,
-    },
-    /// Result from a tool execution.
-    ToolResult {
-        /// The tool use ID or name.
-        tool_use_id: String,
-        /// The tool output content.
-        content: String,
-    },
-    /// Error during execution.
-    Error(String),
+/// Stream event from MCP-enforced CLI execution: the provider-agnostic
+/// [`StreamEvent`](crate::events::StreamEvent).
+pub type McpStreamEvent = crate::events::StreamEvent;
+
+/// How [`McpStreamEvent`]s are delivered when the [`McpStreamHandle`]
+/// consumer falls behind.
+///
+/// Events are forwarded to the handle as fast as it accepts them. Once its
+/// channel is full, the adapter's own event channel fills up and the policy
+/// is applied there by the CLI output reader.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Wait for the consumer, pausing the CLI output reader. Nothing is lost.
+    #[default]
+    Block,
+    /// Discard the oldest queued events, keeping the most recent ones.
+    DropOldest,
+    /// Discard new events while the channels are full.
+    DropNewest,
+    /// Merge consecutive queued text chunks into one event; other events
+    /// wait for the consumer as with [`Block`](Self::Block).
+    CoalesceText,
+}
+
+// All three adapters re-export the same `BackpressurePolicy`.
+impl From<BackpressurePolicy> for rig_cli_claude::BackpressurePolicy {
+    fn from(policy: BackpressurePolicy) -> Self {
+        match policy {
+            BackpressurePolicy::Block => Self::Block,
+            BackpressurePolicy::DropOldest => Self::DropOldest,
+            BackpressurePolicy::DropNewest => Self::DropNewest,
+            BackpressurePolicy::CoalesceText => Self::CoalesceText,
+        }
+    }
+}
+
+/// How the MCP server is made available to the CLI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum McpDelivery {
+    /// Write the server to a temp file passed with `--mcp-config`, and
+    /// ignore every other configured server.
+    #[default]
+    ConfigFile,
+    /// Register the server with `claude mcp add-json --scope local` in the
+    /// run's working directory, and remove it once the run ends.
+    ///
+    /// Use this when user-level settings such as `disabledMcpServers`
+    /// conflict with `--mcp-config`. Other servers configured for the
+    /// directory are not excluded. A run that is cancelled before it ends
+    /// leaves the registration behind. Only Claude Code supports it; other
+    /// adapters always use a config file.
+    CliRegistration,
 }
 
 /// Which CLI adapter to use for MCP tool agent execution.
+///
+/// Parses from `claude` (or `claude-code`), `codex`, and `opencode`, ignoring
+/// case, so it can be read from config files and command-line flags.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CliAdapter {
     /// Use the Claude Code CLI (`claude --print`).
@@ -195,6 +107,37 @@ pub enum CliAdapter {
     Codex,
     /// Use the `OpenCode` CLI (`opencode run`).
     OpenCode,
+    /// A [`CliBackend`] implemented outside this crate, by name.
+    ///
+    /// Never parsed; set with [`McpToolAgentBuilder::backend`].
+    Custom(&'static str),
+}
+
+impl CliAdapter {
+    /// Returns the built-in backend for this adapter.
+    ///
+    /// [`Custom`](Self::Custom) has no built-in backend; its backend reports
+    /// every CLI as missing.
+    #[must_use]
+    pub fn backend(self) -> std::sync::Arc<dyn CliBackend> {
+        match self {
+            Self::ClaudeCode => std::sync::Arc::new(crate::backend::ClaudeCodeBackend),
+            Self::Codex => std::sync::Arc::new(crate::backend::CodexBackend),
+            Self::OpenCode => std::sync::Arc::new(crate::backend::OpenCodeBackend),
+            Self::Custom(name) => std::sync::Arc::new(crate::backend::MissingBackend(name)),
+        }
+    }
+
+    /// The name this adapter's CLI shows the model for MCP tool `tool` on
+    /// `server`. It must satisfy [`rig_cli_mcp::naming`], or the CLI
+    /// truncates or drops the tool.
+    #[must_use]
+    pub fn model_tool_name(self, server: &str, tool: &str) -> String {
+        match self {
+            Self::OpenCode => format!("{server}_{tool}"),
+            Self::ClaudeCode | Self::Codex | Self::Custom(_) => format!("mcp__{server}__{tool}"),
+        }
+    }
 }
 
 impl std::fmt::Display for CliAdapter {
@@ -203,12 +146,45 @@ impl std::fmt::Display for CliAdapter {
             Self::ClaudeCode => write!(f, "ClaudeCode"),
             Self::Codex => write!(f, "Codex"),
             Self::OpenCode => write!(f, "OpenCode"),
+            Self::Custom(name) => f.write_str(name),
+        }
+    }
+}
+
+impl std::str::FromStr for CliAdapter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "claude" | "claude-code" | "claudecode" => Ok(Self::ClaudeCode),
+            "codex" => Ok(Self::Codex),
+            "opencode" => Ok(Self::OpenCode),
+            _ => Err(format!(
+                "unknown adapter '{s}' (expected claude, codex, or opencode)"
+            )),
         }
     }
 }
 
+impl TryFrom<String> for CliAdapter {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+// Written by hand: a derive would require `'de: 'static` for `Custom`.
+impl<'de> serde::Deserialize<'de> for CliAdapter {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 /// Result of an [`McpToolAgent`] execution.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct McpToolAgentResult {
     /// The raw stdout output from the CLI.
     pub stdout: String,
@@ -222,13 +198,177 @@ pub struct McpToolAgentResult {
     /// This is the primary result channel — populated from the result file
     /// that the MCP server writes via `RIG_MCP_RESULT_PATH`.
     pub submit_result: Option<String>,
+    /// [`submit_result`](Self::submit_result) parsed as JSON and validated
+    /// against the submit tool's schema.
+    pub result_json: Option<serde_json::Value>,
+    /// Suspected prompt injections the [`PayloadScanner`] found in the
+    /// payload. Empty when no scanner was set.
+    pub payload_findings: Vec<PayloadFinding>,
+    /// Every tool call the MCP server served during the run, with full
+    /// arguments and results. `None` unless enabled with
+    /// [`McpToolAgentBuilder::harvest_tool_calls`].
+    pub tool_harvest: Option<rig_cli_mcp::harvest::ToolHarvest>,
+    /// Whether the agent followed the example, validate and submit workflow,
+    /// judged from [`tool_harvest`](Self::tool_harvest). `None` unless
+    /// [`McpToolAgentBuilder::harvest_tool_calls`] is enabled.
+    pub compliance: Option<rig_cli_mcp::harvest::WorkflowCompliance>,
+    /// Runs that failed before their CLI produced any output and were
+    /// handed to the [fallback adapter](McpToolAgentBuilder::fallback_adapter),
+    /// which produced this result. Empty when the first CLI ran.
+    pub failed_attempts: Vec<FailedAttempt>,
+    /// What the submit tools of the [namespaced](McpToolAgentBuilder::namespace)
+    /// servers wrote, by server name. Namespaces whose submit tool was not
+    /// called, or that have none, are left out.
+    pub namespace_results: std::collections::BTreeMap<String, String>,
+    /// Every call of the main server's submit tool, in order, with what the
+    /// toolkit's [`SubmitPolicy`](rig_cli_mcp::submissions::SubmitPolicy)
+    /// made of it. [`submit_result`](Self::submit_result) is the one that
+    /// counted.
+    pub submissions: Vec<rig_cli_mcp::submissions::SubmitRecord>,
+}
+
+impl McpToolAgentResult {
+    /// Builds a result from raw CLI output, with no submit result, payload
+    /// findings or tool harvest yet.
+    ///
+    /// Used by [`CliBackend`] implementations; the agent fills in the rest.
+    #[must_use]
+    pub const fn from_output(
+        stdout: String,
+        stderr: String,
+        exit_code: i32,
+        duration_ms: u64,
+    ) -> Self {
+        Self {
+            stdout,
+            stderr,
+            exit_code,
+            duration_ms,
+            submit_result: None,
+            result_json: None,
+            payload_findings: Vec::new(),
+            tool_harvest: None,
+            compliance: None,
+            failed_attempts: Vec::new(),
+            namespace_results: std::collections::BTreeMap::new(),
+            submissions: Vec::new(),
+        }
+    }
+
+    /// Deserializes the submitted result into `T`.
+    ///
+    /// Returns `Ok(None)` if the agent never called the submit tool.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Validation`] if the submitted JSON does not
+    /// deserialize into `T`.
+    pub fn typed_result<T: serde::de::DeserializeOwned>(&self) -> Result<Option<T>, ProviderError> {
+        self.result_json
+            .as_ref()
+            .map(|json| serde_json::from_value(json.clone()))
+            .transpose()
+            .map_err(|e| {
+                ProviderError::Validation(format!(
+                    "submitted result does not match {}: {e}",
+                    std::any::type_name::<T>()
+                ))
+            })
+    }
+}
+
+/// A run attempt that failed before its CLI produced any output.
+#[derive(Debug, Clone)]
+pub struct FailedAttempt {
+    /// Adapter whose CLI failed.
+    pub adapter: CliAdapter,
+    /// [`ProviderError::error_code`] of the failure.
+    pub error_code: &'static str,
+    /// The failure's message.
+    pub error: String,
+    /// Wall-clock time the attempt took, in milliseconds.
+    pub duration_ms: u64,
 }
 
+/// Name of the toolkit tool whose call marks task completion.
+const SUBMIT_TOOL_NAME: &str = "submit";
+
+/// Parses a raw submit result and validates it against the submit tool schema.
+///
+/// # Errors
+/// Returns [`ProviderError::Validation`] if the result is not JSON or does not
+/// satisfy `schema`.
+fn parse_submit_result(
+    raw: Option<&str>,
+    schema: Option<&serde_json::Value>,
+) -> Result<Option<serde_json::Value>, ProviderError> {
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+    let json: serde_json::Value = serde_json::from_str(raw).map_err(|e| {
+        ProviderError::Validation(format!("submitted result is not valid JSON: {e}"))
+    })?;
+
+    if let Some(schema) = schema {
+        let validator = jsonschema::Validator::new(schema).map_err(|e| {
+            ProviderError::Validation(format!("submit tool schema is invalid: {e}"))
+        })?;
+        let errors: Vec<String> = validator
+            .iter_errors(&json)
+            .map(|e| format!("at '{}': {e}", e.instance_path))
+            .collect();
+        if !errors.is_empty() {
+            return Err(ProviderError::Validation(format!(
+                "submitted result does not match the submit tool schema: {}",
+                errors.join("; ")
+            )));
+        }
+    }
+
+    Ok(Some(json))
+}
+
+/// Forwards `events` to `tx`, each followed by the validation warnings it
+/// gives rise to.
+async fn relay_validated(
+    mut events: tokio::sync::mpsc::Receiver<McpStreamEvent>,
+    tx: &tokio::sync::mpsc::Sender<McpStreamEvent>,
+    mut validator: SubmitValidator,
+) {
+    while let Some(event) = events.recv().await {
+        let warnings = validator.observe(&event);
+        // Keep draining after the receiver is gone so the CLI is not blocked.
+        let _ = tx.send(event).await;
+        for warning in warnings {
+            let _ = tx.send(warning).await;
+        }
+    }
+}
+
+/// Background task driving a streaming run to its final result.
+type StreamTask = tokio::task::JoinHandle<Result<McpToolAgentResult, ProviderError>>;
+
 /// Handle returned by [`McpToolAgentBuilder::stream`].
 ///
-/// Bundles the event receiver with the result file path. After draining all
-/// events from the receiver, call [`read_result`](Self::read_result) to get
-/// the structured JSON that the MCP server's submit tool wrote.
+/// Yields progress events through [`recv`](Self::recv) (or the public `rx`)
+/// while the CLI runs, then resolves to the final [`McpToolAgentResult`]
+/// through [`finish`](Self::finish). [`abort`](Self::abort) stops the run early.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rig_cli_provider::mcp_agent::{McpStreamEvent, McpToolAgentBuilder};
+/// # async fn example(builder: McpToolAgentBuilder) -> Result<(), rig_cli_provider::errors::ProviderError> {
+/// let mut handle = builder.stream().await?;
+/// while let Some(event) = handle.recv().await {
+///     if let McpStreamEvent::Text(text) = event {
+///         print!("{text}");
+///     }
+/// }
+/// let result = handle.finish().await?;
+/// println!("exit {} in {}ms: {:?}", result.exit_code, result.duration_ms, result.submit_result);
+/// # Ok(())
+/// # }
+/// ```
 pub struct McpStreamHandle {
     /// Receiver for streaming progress events.
     pub rx: tokio::sync::mpsc::Receiver<McpStreamEvent>,
@@ -236,9 +376,81 @@ pub struct McpStreamHandle {
     result_path: std::path::PathBuf,
     /// Keep the temp file alive until this handle is dropped.
     _result_file: tempfile::NamedTempFile,
+    /// Schema of the submit tool, used to validate the submitted result.
+    submit_schema: Option<serde_json::Value>,
+    /// Task running the CLI and forwarding its events.
+    task: StreamTask,
+    /// Payload scan findings, copied onto the final result.
+    payload_findings: Vec<PayloadFinding>,
+    /// File the MCP server appends harvested tool calls to, if enabled.
+    harvest_file: Option<tempfile::NamedTempFile>,
+    /// Result files of the namespaced MCP servers.
+    namespace_sinks: Vec<NamespaceSink>,
+    /// File the MCP server appends its submit calls to.
+    submissions_file: tempfile::NamedTempFile,
+    /// Server and tools the workflow compliance verdict is judged on.
+    workflow: WorkflowScope,
+    /// Directory the run's output is recorded in, if enabled.
+    artifacts: Option<RunArtifacts>,
 }
 
 impl McpStreamHandle {
+    /// Receives the next progress event, or `None` once the run has finished.
+    pub async fn recv(&mut self) -> Option<McpStreamEvent> {
+        self.rx.recv().await
+    }
+
+    /// Waits for the run to finish and returns its result, with the submit
+    /// result harvested from the MCP server's result file and parsed into
+    /// [`McpToolAgentResult::result_json`], and the server's tool calls in
+    /// [`McpToolAgentResult::tool_harvest`] when harvesting is enabled.
+    ///
+    /// Events not yet received are discarded.
+    ///
+    /// # Errors
+    /// Returns the CLI execution error, [`ProviderError::Cancelled`] if the
+    /// run was aborted, an I/O error if the result or harvest file cannot be
+    /// read, or [`ProviderError::Validation`] if the submitted result fails
+    /// the submit tool schema.
+    pub async fn finish(mut self) -> Result<McpToolAgentResult, ProviderError> {
+        let artifacts = self.artifacts.take();
+        finish_artifacts(artifacts, self.collect().await)
+    }
+
+    /// Waits for the task and assembles the final result for
+    /// [`finish`](Self::finish).
+    async fn collect(self) -> Result<McpToolAgentResult, ProviderError> {
+        // Dropping the receiver lets the forwarding task run to completion
+        // even if the caller stopped reading events.
+        drop(self.rx);
+        let mut result = match self.task.await {
+            Ok(result) => result?,
+            Err(e) if e.is_cancelled() => return Err(ProviderError::Cancelled),
+            Err(e) => return Err(anyhow::anyhow!("stream task panicked: {e}").into()),
+        };
+        result.submit_result = read_result_file(&self.result_path)?;
+        result.result_json =
+            parse_submit_result(result.submit_result.as_deref(), self.submit_schema.as_ref())?;
+        result.payload_findings = self.payload_findings;
+        if let (None, Some(file)) = (&result.tool_harvest, &self.harvest_file) {
+            result.tool_harvest = Some(rig_cli_mcp::harvest::ToolHarvest::read(file.path())?);
+        }
+        result.compliance = result
+            .tool_harvest
+            .as_ref()
+            .map(|harvest| self.workflow.judge(harvest));
+        result.namespace_results = read_namespace_results(&self.namespace_sinks)?;
+        result.submissions =
+            rig_cli_mcp::submissions::SubmitRecord::read_all(self.submissions_file.path())?;
+        Ok(result)
+    }
+
+    /// Stops the run: the CLI process is killed and [`finish`](Self::finish)
+    /// returns [`ProviderError::Cancelled`].
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+
     /// Reads the submit result from the MCP server's result file.
     ///
     /// Call this after the stream receiver is fully drained (returns `None`).
@@ -247,13 +459,67 @@ impl McpStreamHandle {
     /// # Errors
     /// Returns an error if the file exists but cannot be read or parsed.
     pub fn read_result(&self) -> Result<Option<String>, std::io::Error> {
-        match std::fs::read_to_string(&self.result_path) {
-            Ok(content) if content.is_empty() => Ok(None),
-            Ok(content) => Ok(Some(content)),
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(e),
+        read_result_file(&self.result_path)
+    }
+}
+
+/// Records `result` in the run's artifact directory, if there is one, and
+/// passes it on.
+fn finish_artifacts(
+    artifacts: Option<RunArtifacts>,
+    result: Result<McpToolAgentResult, ProviderError>,
+) -> Result<McpToolAgentResult, ProviderError> {
+    if let Some(artifacts) = artifacts {
+        artifacts.finish(&result);
+    }
+    result
+}
+
+/// Reads the submit result file, treating a missing or empty file as no result.
+fn read_result_file(path: &std::path::Path) -> Result<Option<String>, std::io::Error> {
+    match std::fs::read_to_string(path) {
+        Ok(content) if content.is_empty() => Ok(None),
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Result file of an MCP server added with [`McpToolAgentBuilder::namespace`].
+struct NamespaceSink {
+    name: String,
+    result_file: tempfile::NamedTempFile,
+}
+
+/// The main MCP server and the tools it serves, for judging a run's
+/// workflow compliance.
+struct WorkflowScope {
+    server: String,
+    tools: Vec<String>,
+}
+
+impl WorkflowScope {
+    /// The compliance verdict on the tool calls in `harvest`.
+    fn judge(
+        &self,
+        harvest: &rig_cli_mcp::harvest::ToolHarvest,
+    ) -> rig_cli_mcp::harvest::WorkflowCompliance {
+        harvest.workflow_compliance(&self.server, &self.tools)
+    }
+}
+
+/// Reads what each namespaced server's submit tool wrote, skipping servers
+/// that wrote nothing.
+fn read_namespace_results(
+    sinks: &[NamespaceSink],
+) -> Result<std::collections::BTreeMap<String, String>, std::io::Error> {
+    let mut results = std::collections::BTreeMap::new();
+    for sink in sinks {
+        if let Some(content) = read_result_file(sink.result_file.path())? {
+            results.insert(sink.name.clone(), content);
         }
     }
+    Ok(results)
 }
 
 /// MCP-backed CLI agent that transparently handles MCP config generation,
@@ -273,10 +539,14 @@ impl McpToolAgent {
 /// Accepts a Rig [`ToolSet`](rig::tool::ToolSet), a prompt, and a CLI adapter
 /// choice. Handles all MCP plumbing transparently: config generation, temp file
 /// management, CLI discovery, tool name computation, and execution.
+// Independent opt-ins, each set by a builder method of its own.
+#[allow(clippy::struct_excessive_bools)]
 pub struct McpToolAgentBuilder {
     toolset: Option<rig::tool::ToolSet>,
+    namespaces: Vec<(String, rig::tool::ToolSet)>,
     prompt: Option<String>,
-    adapter: Option<CliAdapter>,
+    backend: Option<std::sync::Arc<dyn CliBackend>>,
+    fallback: Option<std::sync::Arc<dyn CliBackend>>,
     server_name: String,
     system_prompt: Option<String>,
     timeout: Duration,
@@ -285,32 +555,76 @@ pub struct McpToolAgentBuilder {
     builtin_tools: Option<Vec<String>>,
     sandbox_mode: Option<rig_cli_codex::SandboxMode>,
     working_dir: Option<std::path::PathBuf>,
+    require_containment: bool,
+    isolated: bool,
     extra_env: std::collections::HashMap<String, String>,
+    middleware: MiddlewareStack,
+    backpressure: BackpressurePolicy,
+    mcp_delivery: McpDelivery,
+    credentials: Option<Credentials>,
+    cli_path: Option<std::path::PathBuf>,
+    payload_scanner: Option<PayloadScanner>,
+    payload_budget: Option<PayloadBudget>,
+    harvest_tool_calls: bool,
+    stream_validation: bool,
+    artifacts_dir: Option<std::path::PathBuf>,
+    shared_context: Option<SharedContext>,
+    attachments: Attachments,
+    images: Vec<std::path::PathBuf>,
+    stderr_events: Option<rig_cli_claude::StderrPatterns>,
+    mcp_server_command: Option<(std::path::PathBuf, Vec<String>)>,
+    default_models: DefaultModels,
 }
 
 /// Validated and resolved state shared by [`McpToolAgentBuilder::stream`] and
 /// [`McpToolAgentBuilder::run`]. Built by [`McpToolAgentBuilder::prepare`].
 struct PreparedAgent {
-    adapter: CliAdapter,
+    backend: std::sync::Arc<dyn CliBackend>,
+    fallback: Option<std::sync::Arc<dyn CliBackend>>,
+    fallback_env: Vec<(String, String)>,
     timeout: Duration,
     builtin_tools: Option<Vec<String>>,
     sandbox_mode: rig_cli_codex::SandboxMode,
+    require_containment: bool,
     temp_dir_guard: Option<tempfile::TempDir>,
+    isolated: bool,
+    scratch_home: Option<ScratchHome>,
     effective_cwd: std::path::PathBuf,
     result_file: tempfile::NamedTempFile,
     result_path: std::path::PathBuf,
+    submit_schema: Option<serde_json::Value>,
     mcp_config: rig_cli_mcp::server::McpConfig,
+    extra_mcp_configs: Vec<rig_cli_mcp::server::McpConfig>,
+    namespace_sinks: Vec<NamespaceSink>,
     allowed_tools: Vec<String>,
     full_system_prompt: String,
     final_prompt: String,
+    middleware: MiddlewareStack,
+    backpressure: BackpressurePolicy,
+    mcp_delivery: McpDelivery,
+    cli_env: Vec<(String, String)>,
+    cli_path: Option<std::path::PathBuf>,
+    default_models: DefaultModels,
+    payload_findings: Vec<PayloadFinding>,
+    harvest_file: Option<tempfile::NamedTempFile>,
+    submissions_file: tempfile::NamedTempFile,
+    workflow: WorkflowScope,
+    stream_validator: Option<SubmitValidator>,
+    artifacts_dir: Option<std::path::PathBuf>,
+    shared_context: Option<SharedContext>,
+    attachments_dir: Option<tempfile::TempDir>,
+    images: Vec<std::path::PathBuf>,
+    stderr_events: Option<rig_cli_claude::StderrPatterns>,
 }
 
 impl McpToolAgentBuilder {
     fn new() -> Self {
         Self {
             toolset: None,
+            namespaces: Vec::new(),
             prompt: None,
-            adapter: None,
+            backend: None,
+            fallback: None,
             server_name: "rig_mcp".to_string(),
             system_prompt: None,
             timeout: Duration::from_secs(300),
@@ -319,7 +633,25 @@ impl McpToolAgentBuilder {
             builtin_tools: None,
             sandbox_mode: Some(rig_cli_codex::SandboxMode::ReadOnly),
             working_dir: None,
+            require_containment: false,
+            isolated: true,
             extra_env: std::collections::HashMap::new(),
+            middleware: MiddlewareStack::default(),
+            backpressure: BackpressurePolicy::Block,
+            mcp_delivery: McpDelivery::ConfigFile,
+            credentials: None,
+            cli_path: None,
+            payload_scanner: None,
+            payload_budget: None,
+            harvest_tool_calls: false,
+            stream_validation: false,
+            artifacts_dir: None,
+            shared_context: None,
+            attachments: Attachments::new(),
+            images: Vec::new(),
+            stderr_events: None,
+            mcp_server_command: None,
+            default_models: DefaultModels::from_env(),
         }
     }
 
@@ -338,9 +670,58 @@ impl McpToolAgentBuilder {
     }
 
     /// Sets which CLI adapter to use for execution.
+    ///
+    /// Shorthand for [`backend`](Self::backend) with the adapter's built-in
+    /// backend.
+    #[must_use]
+    pub fn adapter(mut self, adapter: CliAdapter) -> Self {
+        self.backend = Some(adapter.backend());
+        self
+    }
+
+    /// Runs through `backend` instead of a built-in adapter.
+    ///
+    /// See [`crate::backend`] for implementing a backend for another CLI.
     #[must_use]
-    pub const fn adapter(mut self, adapter: CliAdapter) -> Self {
-        self.adapter = Some(adapter);
+    pub fn backend(mut self, backend: impl CliBackend + 'static) -> Self {
+        self.backend = Some(std::sync::Arc::new(backend));
+        self
+    }
+
+    /// Re-runs the prompt through `adapter`, within the same
+    /// [`run`](Self::run), if the chosen CLI fails before producing any
+    /// output, e.g. because it is not installed, not logged in or could not
+    /// set up the MCP server; see [`ProviderError::failed_before_output`].
+    ///
+    /// The first failure is recorded in
+    /// [`McpToolAgentResult::failed_attempts`] instead of being returned.
+    /// Any other failure, and any failure of the fallback, is returned as
+    /// usual. The fallback discovers its own CLI and loads its own
+    /// [`credentials`](Self::credentials); [`cli_path`](Self::cli_path)
+    /// only applies to the first adapter. [`stream`](Self::stream) does not
+    /// fail over. Default: none.
+    #[must_use]
+    pub fn fallback_adapter(mut self, adapter: CliAdapter) -> Self {
+        self.fallback = Some(adapter.backend());
+        self
+    }
+
+    /// Fails over to `backend` instead of a built-in adapter; see
+    /// [`fallback_adapter`](Self::fallback_adapter).
+    #[must_use]
+    pub fn fallback_backend(mut self, backend: impl CliBackend + 'static) -> Self {
+        self.fallback = Some(std::sync::Arc::new(backend));
+        self
+    }
+
+    /// Sets the model each built-in adapter runs, including the
+    /// [fallback](Self::fallback_adapter).
+    ///
+    /// Default: [`DefaultModels::from_env`], the built-in defaults with the
+    /// `RIG_CLI_*_MODEL` overrides applied.
+    #[must_use]
+    pub fn default_models(mut self, models: DefaultModels) -> Self {
+        self.default_models = models;
         self
     }
 
@@ -353,6 +734,25 @@ impl McpToolAgentBuilder {
         self
     }
 
+    /// Runs a second MCP server named `name`, serving `toolset`, next to
+    /// the main one, so the agent can call domain tools while it extracts.
+    ///
+    /// Its tools are allowed as `mcp__<name>__<tool>`. Every server runs the
+    /// current executable with `RIG_MCP_SERVER=1` and
+    /// [`SERVER_NAME_ENV`](rig_cli_mcp::server::SERVER_NAME_ENV) set to its
+    /// name, which picks the toolset to serve. Each server gets a result file
+    /// of its own: the main one's fills
+    /// [`submit_result`](McpToolAgentResult::submit_result) and the others
+    /// [`namespace_results`](McpToolAgentResult::namespace_results).
+    ///
+    /// Server names must be distinct; a repeated name fails the run with a
+    /// validation error.
+    #[must_use]
+    pub fn namespace(mut self, name: impl Into<String>, toolset: rig::tool::ToolSet) -> Self {
+        self.namespaces.push((name.into(), toolset));
+        self
+    }
+
     /// Sets an optional system prompt to prepend to the MCP instructions.
     #[must_use]
     pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
@@ -422,12 +822,38 @@ impl McpToolAgentBuilder {
     /// By default, agents execute in an auto-created temp directory (CONT-04).
     /// Call this to use a specific directory instead. The caller is responsible
     /// for the lifetime of the provided directory.
+    ///
+    /// The directory must exist, and be writable when the Codex sandbox
+    /// permits writes; see [`crate::containment`].
     #[must_use]
     pub fn working_dir(mut self, path: impl Into<std::path::PathBuf>) -> Self {
         self.working_dir = Some(path.into());
         self
     }
 
+    /// Refuses to run in the home directory or a repository root, or under
+    /// the Codex `DangerFullAccess` sandbox.
+    ///
+    /// Violations fail the run before the CLI is spawned with
+    /// [`ProviderError::ContainmentMisconfiguration`]. Default: `false`.
+    #[must_use]
+    pub const fn require_containment(mut self, require: bool) -> Self {
+        self.require_containment = require;
+        self
+    }
+
+    /// Keeps the host user's Claude Code configuration out of the run:
+    /// settings files, `CLAUDE.md`, hooks and globally registered MCP
+    /// servers are not loaded, and the CLI gets a scratch home directory.
+    ///
+    /// The stored login is carried over; see [`crate::isolation`]. Other
+    /// adapters are unaffected. Default: `true`.
+    #[must_use]
+    pub const fn isolated(mut self, isolated: bool) -> Self {
+        self.isolated = isolated;
+        self
+    }
+
     /// Adds an environment variable to the MCP server subprocess config.
     ///
     /// These are written into the MCP config JSON and explicitly set by the
@@ -439,91 +865,435 @@ impl McpToolAgentBuilder {
         self
     }
 
+    /// Adds a [`RunMiddleware`] layer around the run.
+    ///
+    /// Layers run in the order they are added; see [`crate::middleware`].
+    #[must_use]
+    pub fn middleware(mut self, middleware: impl RunMiddleware + 'static) -> Self {
+        self.middleware.push(std::sync::Arc::new(middleware));
+        self
+    }
+
+    /// Sets how [`stream`](Self::stream) events are delivered when the
+    /// consumer falls behind.
+    ///
+    /// Defaults to [`BackpressurePolicy::Block`]. Has no effect on
+    /// [`run`](Self::run).
+    #[must_use]
+    pub const fn backpressure(mut self, policy: BackpressurePolicy) -> Self {
+        self.backpressure = policy;
+        self
+    }
+
+    /// Streams the CLI's stderr lines matching `patterns` as
+    /// [`McpStreamEvent::Progress`] and [`McpStreamEvent::Warning`], so a UI
+    /// can show what the CLI is doing without treating stderr as errors.
+    ///
+    /// Defaults to `None`: stderr is only captured into the result. Has no
+    /// effect on [`run`](Self::run).
+    #[must_use]
+    pub fn stderr_events(mut self, patterns: rig_cli_claude::StderrPatterns) -> Self {
+        self.stderr_events = Some(patterns);
+        self
+    }
+
+    /// Sets how the MCP server is made available to the CLI.
+    ///
+    /// Defaults to [`McpDelivery::ConfigFile`].
+    #[must_use]
+    pub const fn mcp_delivery(mut self, delivery: McpDelivery) -> Self {
+        self.mcp_delivery = delivery;
+        self
+    }
+
+    /// Loads the CLI's API keys from `credentials` when the run starts.
+    ///
+    /// Only the variables the selected adapter's CLI reads are set on its
+    /// process; see [`crate::credentials`].
+    #[must_use]
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Uses the CLI binary at `path` instead of discovering it on every run.
+    ///
+    /// Callers that launch many runs against the same CLI can resolve it once
+    /// and pass the path here; the version check still runs per run.
+    #[must_use]
+    pub fn cli_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.cli_path = Some(path.into());
+        self
+    }
+
+    /// Scans the payload for prompt injection patterns before the run.
+    ///
+    /// Findings are recorded on [`McpToolAgentResult::payload_findings`];
+    /// with [`ScanAction::Redact`](crate::scanner::ScanAction::Redact) the
+    /// matches are also removed from the prompt. See [`crate::scanner`].
+    #[must_use]
+    pub const fn payload_scanner(mut self, scanner: PayloadScanner) -> Self {
+        self.payload_scanner = Some(scanner);
+        self
+    }
+
+    /// Caps the payload at a number of estimated tokens.
+    ///
+    /// An oversized payload is cut after scanning, keeping the part the
+    /// budget's [`Retain`](crate::truncation::Retain) strategy names, with a
+    /// notice in the `<context>` block where text was omitted. See
+    /// [`crate::truncation`]. Default: no limit.
+    #[must_use]
+    pub const fn payload_budget(mut self, budget: PayloadBudget) -> Self {
+        self.payload_budget = Some(budget);
+        self
+    }
+
+    /// Records every tool call the MCP server serves, with its full
+    /// arguments and result, in [`McpToolAgentResult::tool_harvest`].
+    ///
+    /// Use it to evaluate a workflow, e.g. how many validate calls an agent
+    /// needed before submitting; the verdict on the example, validate and
+    /// submit workflow is in [`McpToolAgentResult::compliance`]. Results are
+    /// not redacted; see [`rig_cli_mcp::harvest`]. Default: off.
+    #[must_use]
+    pub const fn harvest_tool_calls(mut self, enabled: bool) -> Self {
+        self.harvest_tool_calls = enabled;
+        self
+    }
+
+    /// Checks submissions against the submit tool's schema while
+    /// [`stream`](Self::stream) runs, sending an
+    /// [`McpStreamEvent::ValidationWarning`] for each problem as soon as it
+    /// shows; see [`crate::stream_validation`].
+    ///
+    /// The run goes on regardless; call [`McpStreamHandle::abort`] to stop
+    /// it early. Has no effect on [`run`](Self::run) or without a submit
+    /// tool. Default: off.
+    #[must_use]
+    pub const fn stream_validation(mut self, enabled: bool) -> Self {
+        self.stream_validation = enabled;
+        self
+    }
+
+    /// Keeps everything produced for each run in a directory of its own
+    /// under `dir`: the prompts, the generated MCP config, the submitted
+    /// result, the CLI's output and an `index.json` manifest with timings.
+    ///
+    /// Use it to debug a run after the fact; nothing in the directory is
+    /// removed. See [`crate::artifacts`] for the layout. Default: none.
+    #[must_use]
+    pub fn artifacts_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.artifacts_dir = Some(dir.into());
+        self
+    }
+
+    /// Gives the run a [`SharedContext`] to read from disk instead of
+    /// sending it in the prompt.
+    ///
+    /// The file is linked into the temp working directory, or referenced by
+    /// its absolute path with a [`working_dir`](Self::working_dir), and the
+    /// prompt's `<context>` block points the agent at it ahead of any
+    /// [`payload`](Self::payload). The `Read` builtin is opted in to. See
+    /// [`crate::shared_context`].
+    #[must_use]
+    pub fn shared_context(mut self, context: SharedContext) -> Self {
+        self.shared_context = Some(context);
+        self
+    }
+
+    /// Sends a binary attachment, such as an image or a PDF, after the
+    /// payload. May be called repeatedly.
+    ///
+    /// Each attachment is inlined as base64 or staged as a file the agent
+    /// reads, as the [`attachments`](Self::attachments) strategy decides.
+    /// See [`crate::attachments`].
+    #[must_use]
+    pub fn attachment(mut self, attachment: BinaryPayload) -> Self {
+        self.attachments = self.attachments.attachment(attachment);
+        self
+    }
+
+    /// Replaces the run's attachments, with their strategy and size limits.
+    #[must_use]
+    pub fn attachments(mut self, attachments: Attachments) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    /// Shows the model an image file with the prompt, e.g. a screenshot to
+    /// extract data from. May be called repeatedly.
+    ///
+    /// Only backends reporting
+    /// [`BackendCapabilities::images`](crate::backend::BackendCapabilities::images),
+    /// i.e. Claude Code, accept images; running with another backend or
+    /// fallback fails with [`ProviderError::Validation`]. Send images to
+    /// other CLIs as [`attachment`](Self::attachment)s instead.
+    #[must_use]
+    pub fn image(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.images.push(path.into());
+        self
+    }
+
+    /// Launches `path` as the MCP server instead of the current executable.
+    ///
+    /// For hosts that cannot serve the toolset themselves, such as non-Rust
+    /// processes driving the agent through the C interface. The executable
+    /// must serve the same tools when started with the run's
+    /// [`extra_env`](Self::extra_env). Default: the current executable.
+    ///
+    /// Shorthand for [`mcp_server_command`](Self::mcp_server_command) with no
+    /// arguments.
+    #[must_use]
+    pub fn mcp_server_exe(self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.mcp_server_command(path, Vec::<String>::new())
+    }
+
+    /// Launches `command` with `args` as the MCP server instead of the
+    /// current executable.
+    ///
+    /// Points the agent at an external MCP server, or at a packaged
+    /// `rig-cli-provider` binary, so the orchestrating process does not have
+    /// to double as the server. The server still receives the run's result
+    /// path and [`extra_env`](Self::extra_env) through its environment, and
+    /// is expected to expose the toolset's tools under the same names.
+    #[must_use]
+    pub fn mcp_server_command(
+        mut self,
+        command: impl Into<std::path::PathBuf>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.mcp_server_command =
+            Some((command.into(), args.into_iter().map(Into::into).collect()));
+        self
+    }
+
     /// Computes the MCP tool names that will be passed to the CLI's allowed-tools list.
     ///
     /// Tool names follow the pattern `mcp__<server_name>__<tool_name>`.
     ///
     /// # Errors
-    /// Returns an error if the toolset is not set or if fetching tool definitions fails.
+    /// Returns an error if the toolset is not set or if fetching tool definitions fails,
+    /// and a validation error if the server or a tool name would be rejected or
+    /// truncated by the adapter's CLI (Claude Code when no adapter is set).
     pub async fn compute_tool_names(&self) -> Result<Vec<String>, ProviderError> {
         let toolset = self
             .toolset
             .as_ref()
-            .ok_or_else(|| ProviderError::McpToolAgent("toolset is required".to_string()))?;
+            .ok_or_else(|| ProviderError::Validation("toolset is required".to_string()))?;
         let definitions = toolset.get_tool_definitions().await.map_err(|e| {
-            ProviderError::McpToolAgent(format!("Failed to get tool definitions: {e}"))
+            ProviderError::mcp_config("failed to get tool definitions", e.to_string())
         })?;
-        Ok(definitions
-            .iter()
-            .map(|def| format!("mcp__{}__{}", self.server_name, def.name))
-            .collect())
+        let adapter = self
+            .backend
+            .as_ref()
+            .map_or(CliAdapter::ClaudeCode, |backend| backend.adapter());
+        allowed_tool_names(adapter, &self.server_name, &definitions)
+    }
+
+    /// Checks that a run would start, without sending a prompt.
+    ///
+    /// Resolves the adapter's CLI, starts the generated MCP server with the
+    /// same command and environment a run uses, and performs the MCP
+    /// `initialize` / `tools/list` handshake. Backends with a
+    /// [CLI-side check](crate::backend::CliBackend::check_mcp) then have the
+    /// CLI load the same server config and connect to it. The report lists
+    /// any toolset tools the server did not advertise and the state the CLI
+    /// reported. This costs no tokens and finishes in seconds, so
+    /// misconfigurations surface before a long agent run.
+    ///
+    /// # Errors
+    /// Returns a validation error if the toolset or adapter is missing, a
+    /// discovery error if the CLI cannot be found, or an MCP config error if
+    /// the server does not complete the handshake within 30 seconds (or the
+    /// agent timeout, if shorter) or the CLI cannot load its config.
+    pub async fn preflight(&self) -> Result<PreflightReport, ProviderError> {
+        let start = std::time::Instant::now();
+        let toolset = self
+            .toolset
+            .as_ref()
+            .ok_or_else(|| ProviderError::Validation("toolset is required".to_string()))?;
+        let backend = self
+            .backend
+            .as_ref()
+            .ok_or_else(|| ProviderError::Validation("adapter is required".to_string()))?;
+        let definitions = toolset.get_tool_definitions().await.map_err(|e| {
+            ProviderError::mcp_config("failed to get tool definitions", e.to_string())
+        })?;
+
+        let cli_path = backend.discover(self.cli_path.clone()).await?;
+
+        let result_file =
+            tempfile::NamedTempFile::new().map_err(|source| ProviderError::Spawn {
+                stage: "create result file",
+                source,
+            })?;
+        let mcp_config = mcp_server_config(
+            &self.server_name,
+            self.mcp_server_command.as_ref(),
+            &self.extra_env,
+            result_file.path(),
+            None,
+            None,
+        )?;
+        let handshake = crate::preflight::handshake(
+            &mcp_config,
+            self.timeout.min(crate::preflight::HANDSHAKE_TIMEOUT),
+        )
+        .await?;
+
+        // Check from the directory and home a run would use.
+        let scratch_cwd;
+        let cwd = if let Some(dir) = &self.working_dir {
+            dir.as_path()
+        } else {
+            scratch_cwd = tempfile::TempDir::new().map_err(|source| ProviderError::Spawn {
+                stage: "create temp dir",
+                source,
+            })?;
+            scratch_cwd.path()
+        };
+        let scratch_home = if self.isolated && backend.adapter() == CliAdapter::ClaudeCode {
+            Some(ScratchHome::new()?)
+        } else {
+            None
+        };
+        let env = scratch_home
+            .as_ref()
+            .map(ScratchHome::env)
+            .unwrap_or_default();
+        let cli_status = backend.check_mcp(&cli_path, &mcp_config, cwd, &env).await?;
+
+        let missing_tools = definitions
+            .into_iter()
+            .map(|def| def.name)
+            .filter(|name| !handshake.tools.contains(name))
+            .collect();
+
+        Ok(PreflightReport {
+            adapter: backend.adapter(),
+            cli_path,
+            server_info: handshake.server_info,
+            server_tools: handshake.tools,
+            missing_tools,
+            cli_status,
+            duration_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+        })
     }
 
     /// Executes the MCP tool agent with streaming output.
     ///
-    /// Similar to `run()`, but returns a receiver that yields `McpStreamEvent`
-    /// as the CLI produces output. The agent spawns in a background task.
+    /// Similar to `run()`, but returns an [`McpStreamHandle`] that yields
+    /// `McpStreamEvent`s as the CLI produces output and then resolves to the
+    /// final result. The agent spawns in a background task.
+    ///
+    /// `before_run` middleware hooks apply; `after_run` hooks do not, since the
+    /// output is delivered incrementally.
     ///
     /// # Errors
-    /// Returns error if validation fails before spawning.
+    /// Returns error if validation fails, the working directory fails its
+    /// containment checks, or a middleware rejects the run before spawning.
+    /// CLI discovery and execution errors happen in the background task: they
+    /// are sent as an [`McpStreamEvent::Error`] and returned from
+    /// [`McpStreamHandle::finish`].
     pub async fn stream(self) -> Result<McpStreamHandle, ProviderError> {
-        let prepared = self.prepare().await?;
+        let mut prepared = self.prepare().await?;
+        let middleware = std::mem::take(&mut prepared.middleware);
+
+        let mut request = prepared.request();
+        let answered = middleware.before(&mut request).await.1?;
+        prepared.apply(request);
+
+        let mut artifacts = prepared.artifacts()?;
+        if let Some(ref mut artifacts) = artifacts {
+            artifacts.record_request(&prepared.backend_request());
+        }
 
         // Create channel for streaming events
         let (tx, rx) = tokio::sync::mpsc::channel::<McpStreamEvent>(100);
 
-        // Execute per adapter
-        // NOTE: temp_dir_guard MUST be moved into the adapter function so it stays alive
-        // for the duration of the spawned task. If dropped here, the cwd is deleted
-        // before the CLI process starts, causing ENOENT on spawn.
-        match prepared.adapter {
-            CliAdapter::ClaudeCode => {
-                let ctx = StreamRunCtx {
-                    prompt: &prepared.final_prompt,
-                    mcp_config: &prepared.mcp_config,
-                    system_prompt: &prepared.full_system_prompt,
-                    timeout: prepared.timeout,
-                    cwd: &prepared.effective_cwd,
-                    temp_dir_guard: prepared.temp_dir_guard,
-                    tx,
-                };
-                run_claude_code_stream(
-                    ctx,
-                    &prepared.allowed_tools,
-                    prepared.builtin_tools.as_ref(),
-                )
-                .await?;
-            }
-            CliAdapter::Codex => {
-                let ctx = StreamRunCtx {
-                    prompt: &prepared.final_prompt,
-                    mcp_config: &prepared.mcp_config,
-                    system_prompt: &prepared.full_system_prompt,
-                    timeout: prepared.timeout,
-                    cwd: &prepared.effective_cwd,
-                    temp_dir_guard: prepared.temp_dir_guard,
-                    tx,
-                };
-                run_codex_stream(ctx, &prepared.sandbox_mode).await?;
+        // A middleware answered the run: replay its result instead of spawning.
+        if let Some(result) = answered {
+            if let Some(ref submit) = result.submit_result {
+                std::fs::write(&prepared.result_path, submit).map_err(|source| {
+                    ProviderError::Spawn {
+                        stage: "write result file",
+                        source,
+                    }
+                })?;
             }
-            CliAdapter::OpenCode => {
-                let ctx = StreamRunCtx {
-                    prompt: &prepared.final_prompt,
-                    mcp_config: &prepared.mcp_config,
-                    system_prompt: &prepared.full_system_prompt,
-                    timeout: prepared.timeout,
-                    cwd: &prepared.effective_cwd,
-                    temp_dir_guard: prepared.temp_dir_guard,
-                    tx,
-                };
-                run_opencode_stream(ctx).await?;
+            if !result.stdout.is_empty() {
+                // Fresh channel with spare capacity; this cannot fail.
+                let _ = tx.try_send(McpStreamEvent::Text(result.stdout.clone()));
             }
+            return Ok(McpStreamHandle {
+                rx,
+                result_path: prepared.result_path,
+                _result_file: prepared.result_file,
+                submit_schema: prepared.submit_schema,
+                task: tokio::spawn(async move { Ok(result) }),
+                payload_findings: prepared.payload_findings,
+                harvest_file: prepared.harvest_file,
+                namespace_sinks: prepared.namespace_sinks,
+                submissions_file: prepared.submissions_file,
+                workflow: prepared.workflow,
+                artifacts,
+            });
         }
 
+        prepared.check_containment()?;
+
+        // NOTE: temp_dir_guard MUST be moved into the spawned task so it stays alive
+        // for the duration of the run. If dropped here, the cwd is deleted before
+        // the CLI process starts, causing ENOENT on spawn.
+        let backend = std::sync::Arc::clone(&prepared.backend);
+        let temp_dir_guard = prepared.temp_dir_guard.take();
+        let scratch_home = prepared.scratch_home.take();
+        let shared_context = prepared.shared_context.take();
+        let attachments_dir = prepared.attachments_dir.take();
+        let stream_validator = prepared.stream_validator.take();
+        let request = prepared.backend_request();
+        let task = tokio::spawn(async move {
+            let _keep_cwd = temp_dir_guard;
+            let _keep_home = scratch_home;
+            let _keep_context = shared_context;
+            let _keep_attachments = attachments_dir;
+            let result = match stream_validator {
+                Some(validator) => {
+                    let (events_tx, events_rx) = tokio::sync::mpsc::channel(100);
+                    let relay = relay_validated(events_rx, &tx, validator);
+                    tokio::join!(backend.stream(request, events_tx), relay).0
+                }
+                None => backend.stream(request, tx.clone()).await,
+            };
+
+            // Propagate CLI execution errors as McpStreamEvent::Error
+            if let Err(ref e) = result {
+                tracing::error!(
+                    event = "cli_stream_failed",
+                    adapter = %backend.adapter(),
+                    error = %e,
+                    "CLI stream execution failed"
+                );
+                let _ = tx
+                    .send(McpStreamEvent::Error(format!("CLI stream failed: {e}")))
+                    .await;
+            }
+            result
+        });
+
         Ok(McpStreamHandle {
             rx,
             result_path: prepared.result_path,
             _result_file: prepared.result_file,
+            submit_schema: prepared.submit_schema,
+            task,
+            payload_findings: prepared.payload_findings,
+            harvest_file: prepared.harvest_file,
+            namespace_sinks: prepared.namespace_sinks,
+            submissions_file: prepared.submissions_file,
+            workflow: prepared.workflow,
+            artifacts,
         })
     }
 
@@ -535,149 +1305,563 @@ impl McpToolAgentBuilder {
     /// 3. Builds an [`McpConfig`](rig_cli_mcp::server::McpConfig) for the target adapter
     /// 4. Computes allowed tool names as `mcp__<server>__<tool>`
     /// 5. Writes the config to a temp file in the adapter's format
-    /// 6. Discovers and launches the CLI with correct flags
-    /// 7. Returns the result; temp files are cleaned via RAII
+    /// 6. Runs middleware `before_run` hooks, which may rewrite or answer the run
+    /// 7. Checks the working directory (see [`crate::containment`]), then
+    ///    discovers and launches the CLI with correct flags
+    /// 8. Runs middleware `after_run` hooks and returns the result; temp files
+    ///    are cleaned via RAII, after the run is recorded in the
+    ///    [artifacts directory](Self::artifacts_dir) if one is set
     ///
     /// # Errors
     /// Returns [`ProviderError`] if any step fails (missing fields, CLI discovery,
-    /// config generation, or CLI execution).
+    /// config generation, or CLI execution), and
+    /// [`ProviderError::ContainmentMisconfiguration`] if the working directory
+    /// fails its checks.
     pub async fn run(self) -> Result<McpToolAgentResult, ProviderError> {
-        let prepared = self.prepare().await?;
+        let mut prepared = self.prepare().await?;
+        let middleware = std::mem::take(&mut prepared.middleware);
 
-        // Execute per adapter
-        let mut result = match prepared.adapter {
-            CliAdapter::ClaudeCode => {
-                run_claude_code(
-                    &prepared.final_prompt,
-                    &prepared.mcp_config,
-                    &prepared.allowed_tools,
-                    &prepared.full_system_prompt,
-                    prepared.timeout,
-                    prepared.builtin_tools.as_ref(),
-                    &prepared.effective_cwd,
-                )
-                .await?
-            }
-            CliAdapter::Codex => {
-                run_codex(
-                    &prepared.final_prompt,
-                    &prepared.mcp_config,
-                    &prepared.full_system_prompt,
-                    prepared.timeout,
-                    &prepared.sandbox_mode,
-                    &prepared.effective_cwd,
-                )
-                .await?
-            }
-            CliAdapter::OpenCode => {
-                run_opencode(
-                    &prepared.final_prompt,
-                    &prepared.mcp_config,
-                    &prepared.full_system_prompt,
-                    prepared.timeout,
-                    &prepared.effective_cwd,
-                )
-                .await?
+        let payload_findings = std::mem::take(&mut prepared.payload_findings);
+        let mut artifacts = prepared.artifacts()?;
+
+        let mut request = prepared.request();
+        let (entered, answered) = middleware.before(&mut request).await;
+        let mut result = match answered {
+            Err(e) => Err(e),
+            Ok(answered) => {
+                prepared.apply(request.clone());
+                if let Some(ref mut artifacts) = artifacts {
+                    artifacts.record_request(&prepared.backend_request());
+                }
+                match answered {
+                    Some(result) => Ok(result),
+                    None => match prepared.check_containment() {
+                        Ok(()) => prepared.execute().await,
+                        Err(e) => Err(e),
+                    },
+                }
             }
         };
+        if let Ok(ref mut result) = result {
+            result.payload_findings = payload_findings;
+        }
+        middleware.after(entered, &request, &mut result).await;
+        finish_artifacts(artifacts, result)
+    }
 
-        // Read the structured result from the MCP server's result file.
-        // This is the primary result path — stdout is a progress channel.
-        result.submit_result = std::fs::read_to_string(&prepared.result_path)
-            .ok()
-            .filter(|s| !s.is_empty());
-
-        // Explicitly drop temp dir guard after CLI completes
-        drop(prepared.temp_dir_guard);
-
-        Ok(result)
+    /// Resolves everything [`run`](Self::run) would, but returns the CLI
+    /// command instead of spawning it: the binary, arguments, environment,
+    /// working directory and generated config files.
+    ///
+    /// Middleware is not consulted, so the command reflects the builder as
+    /// configured. When no working directory was set, the reported one is a
+    /// temporary directory that no longer exists, as is the scratch home of
+    /// an [isolated](Self::isolated) run. The environment includes resolved
+    /// credentials.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError`] if validation, the containment checks, CLI
+    /// discovery or config generation fails, or the backend does not support
+    /// dry runs.
+    pub async fn dry_run(self) -> Result<crate::backend::Invocation, ProviderError> {
+        let prepared = self.prepare().await?;
+        prepared.check_containment()?;
+        prepared.backend.dry_run(prepared.backend_request()).await
     }
 
     /// Validates required fields and builds the common state shared by
     /// [`stream`](Self::stream) and [`run`](Self::run).
+    #[allow(clippy::too_many_lines)]
     async fn prepare(self) -> Result<PreparedAgent, ProviderError> {
         let toolset = self
             .toolset
-            .ok_or_else(|| ProviderError::McpToolAgent("toolset is required".to_string()))?;
+            .ok_or_else(|| ProviderError::Validation("toolset is required".to_string()))?;
         let prompt = self
             .prompt
-            .ok_or_else(|| ProviderError::McpToolAgent("prompt is required".to_string()))?;
-        let adapter = self
-            .adapter
-            .ok_or_else(|| ProviderError::McpToolAgent("adapter is required".to_string()))?;
+            .ok_or_else(|| ProviderError::Validation("prompt is required".to_string()))?;
+        let backend = self
+            .backend
+            .ok_or_else(|| ProviderError::Validation("adapter is required".to_string()))?;
         let sandbox_mode = self
             .sandbox_mode
             .unwrap_or(rig_cli_codex::SandboxMode::ReadOnly);
+        let mut cli_env = match self.credentials {
+            Some(ref credentials) => credentials.resolve(backend.adapter()).await?,
+            None => Vec::new(),
+        };
+        let mut fallback_env = match (&self.credentials, &self.fallback) {
+            (Some(credentials), Some(fallback)) => credentials.resolve(fallback.adapter()).await?,
+            _ => Vec::new(),
+        };
+        // Only Claude Code reads the user configuration isolation hides.
+        let main_claude = backend.adapter() == CliAdapter::ClaudeCode;
+        let fallback_claude = self
+            .fallback
+            .as_ref()
+            .is_some_and(|fallback| fallback.adapter() == CliAdapter::ClaudeCode);
+        let scratch_home = if self.isolated && (main_claude || fallback_claude) {
+            Some(ScratchHome::new()?)
+        } else {
+            None
+        };
+        if let Some(ref home) = scratch_home {
+            if main_claude {
+                cli_env.extend(home.env());
+            }
+            if fallback_claude {
+                fallback_env.extend(home.env());
+            }
+        }
+        if !self.images.is_empty() {
+            let blind = std::iter::once(&backend)
+                .chain(self.fallback.as_ref())
+                .find(|backend| !backend.capabilities().images);
+            if let Some(blind) = blind {
+                return Err(ProviderError::Validation(format!(
+                    "{} does not accept images; send them as attachments instead",
+                    blind.adapter()
+                )));
+            }
+        }
+        let (payload, payload_findings) = match (self.payload, &self.payload_scanner) {
+            (Some(payload), Some(scanner)) => {
+                let (payload, findings) = scanner.apply(payload);
+                if !findings.is_empty() {
+                    tracing::warn!(
+                        event = "payload_injection_suspected",
+                        findings = findings.len(),
+                        action = ?scanner.scan_action(),
+                        "payload scanner matched suspected prompt injection"
+                    );
+                }
+                (Some(payload), findings)
+            }
+            (payload, _) => (payload, Vec::new()),
+        };
+        let payload = match (payload, &self.payload_budget) {
+            (Some(payload), Some(budget)) => {
+                let (payload, truncation) = budget.apply(payload, backend.adapter());
+                if let Some(truncation) = truncation {
+                    tracing::info!(
+                        event = "payload_truncated",
+                        original_tokens = truncation.original_tokens,
+                        kept_tokens = truncation.kept_tokens,
+                        max_tokens = budget.max_tokens(),
+                        "payload truncated to fit its token budget"
+                    );
+                }
+                Some(payload)
+            }
+            (payload, _) => payload,
+        };
 
         // Create temp dir if working_dir not provided (CONT-04).
         // Guard must live until CLI process completes to keep the directory alive.
         let (temp_dir_guard, effective_cwd) = if let Some(dir) = self.working_dir {
             (None, dir)
         } else {
-            let td = tempfile::TempDir::new().map_err(|e| {
-                ProviderError::McpToolAgent(format!("Failed to create temp dir: {e}"))
+            let td = tempfile::TempDir::new().map_err(|source| ProviderError::Spawn {
+                stage: "create temp dir",
+                source,
             })?;
             let path = td.path().to_path_buf();
             (Some(td), path)
         };
 
+        // Point the agent at the shared context instead of inlining it.
+        let context_reference = match self.shared_context {
+            Some(ref context) if temp_dir_guard.is_some() => {
+                context.link_into(&effective_cwd)?;
+                Some(context.reference(std::path::Path::new(context.file_name())))
+            }
+            Some(ref context) => Some(context.reference(context.path())),
+            None => None,
+        };
+        let payload = match (context_reference, payload) {
+            (Some(reference), Some(payload)) => Some(format!("{reference}\n\n{payload}")),
+            (reference, payload) => reference.or(payload),
+        };
+        // Attachments follow the payload, past the scanner and the budget,
+        // which would corrupt their encoding.
+        let (payload, attachments_staged, attachments_dir) = if self.attachments.is_empty() {
+            (payload, false, None)
+        } else {
+            let owned_cwd = temp_dir_guard.as_ref().map(|_| effective_cwd.as_path());
+            let rendered = self.attachments.render(backend.adapter(), owned_cwd)?;
+            let payload = match payload {
+                Some(payload) => format!("{payload}\n\n{}", rendered.text),
+                None => rendered.text,
+            };
+            (Some(payload), rendered.staged, rendered.dir)
+        };
+        let mut builtin_tools = self.builtin_tools;
+        if self.shared_context.is_some() || attachments_staged {
+            let tools = builtin_tools.get_or_insert_with(Vec::new);
+            if !tools.iter().any(|tool| tool == "Read") {
+                tools.push("Read".to_string());
+            }
+        }
+
         let definitions = toolset.get_tool_definitions().await.map_err(|e| {
-            ProviderError::McpToolAgent(format!("Failed to get tool definitions: {e}"))
+            ProviderError::mcp_config("failed to get tool definitions", e.to_string())
         })?;
 
-        let result_file = tempfile::NamedTempFile::new().map_err(|e| {
-            ProviderError::McpToolAgent(format!("Failed to create result file: {e}"))
-        })?;
+        let result_file =
+            tempfile::NamedTempFile::new().map_err(|source| ProviderError::Spawn {
+                stage: "create result file",
+                source,
+            })?;
         let result_path = result_file.path().to_path_buf();
+        let harvest_file = if self.harvest_tool_calls {
+            Some(
+                tempfile::NamedTempFile::new().map_err(|source| ProviderError::Spawn {
+                    stage: "create harvest file",
+                    source,
+                })?,
+            )
+        } else {
+            None
+        };
+        let submissions_file =
+            tempfile::NamedTempFile::new().map_err(|source| ProviderError::Spawn {
+                stage: "create submissions file",
+                source,
+            })?;
 
-        let exe = std::env::current_exe()
-            .map_err(|e| ProviderError::McpToolAgent(format!("Failed to get current exe: {e}")))?;
-        let mcp_config = rig_cli_mcp::server::McpConfig {
-            name: self.server_name.clone(),
-            command: exe.to_string_lossy().to_string(),
-            args: vec![],
-            env: {
-                let mut env = std::collections::HashMap::new();
-                env.insert("RIG_MCP_SERVER".to_string(), "1".to_string());
-                env.insert(
-                    "RIG_MCP_RESULT_PATH".to_string(),
-                    result_path.to_string_lossy().to_string(),
-                );
-                env.extend(self.extra_env);
-                env
-            },
+        let mcp_config = mcp_server_config(
+            &self.server_name,
+            self.mcp_server_command.as_ref(),
+            &self.extra_env,
+            &result_path,
+            harvest_file.as_ref().map(tempfile::NamedTempFile::path),
+            Some(submissions_file.path()),
+        )?;
+
+        let workflow = WorkflowScope {
+            server: self.server_name.clone(),
+            tools: definitions.iter().map(|def| def.name.clone()).collect(),
         };
 
-        let allowed_tools: Vec<String> = definitions
+        let mut allowed_tools =
+            allowed_tool_names(backend.adapter(), &self.server_name, &definitions)?;
+        if let Some(ref fallback) = self.fallback {
+            allowed_tool_names(fallback.adapter(), &self.server_name, &definitions)?;
+        }
+
+        let mut extra_mcp_configs = Vec::new();
+        let mut namespace_sinks: Vec<NamespaceSink> = Vec::new();
+        for (name, tools) in self.namespaces {
+            if name == self.server_name || namespace_sinks.iter().any(|sink| sink.name == name) {
+                return Err(ProviderError::Validation(format!(
+                    "MCP server name '{name}' is used more than once"
+                )));
+            }
+            let tool_defs = tools.get_tool_definitions().await.map_err(|e| {
+                ProviderError::mcp_config(
+                    format!("failed to get tool definitions for '{name}'"),
+                    e.to_string(),
+                )
+            })?;
+            allowed_tools.extend(allowed_tool_names(backend.adapter(), &name, &tool_defs)?);
+            if let Some(ref fallback) = self.fallback {
+                allowed_tool_names(fallback.adapter(), &name, &tool_defs)?;
+            }
+            let result_file =
+                tempfile::NamedTempFile::new().map_err(|source| ProviderError::Spawn {
+                    stage: "create result file",
+                    source,
+                })?;
+            extra_mcp_configs.push(mcp_server_config(
+                &name,
+                self.mcp_server_command.as_ref(),
+                &self.extra_env,
+                result_file.path(),
+                harvest_file.as_ref().map(tempfile::NamedTempFile::path),
+                None,
+            )?);
+            namespace_sinks.push(NamespaceSink { name, result_file });
+        }
+        let submit_schema = definitions
             .iter()
-            .map(|def| format!("mcp__{}__{}", self.server_name, def.name))
-            .collect();
+            .find(|def| def.name == SUBMIT_TOOL_NAME)
+            .map(|def| def.parameters.clone());
+        let stream_validator = match submit_schema {
+            Some(ref schema) if self.stream_validation => Some(SubmitValidator::new(
+                backend
+                    .adapter()
+                    .model_tool_name(&self.server_name, SUBMIT_TOOL_NAME),
+                schema,
+            )?),
+            _ => None,
+        };
 
         let (full_system_prompt, final_prompt) = assemble_prompts(
             self.instruction_template.as_deref(),
             self.system_prompt.as_deref(),
             &allowed_tools,
             &prompt,
-            self.payload.as_deref(),
+            payload.as_deref(),
         );
 
         Ok(PreparedAgent {
-            adapter,
+            backend,
+            fallback: self.fallback,
+            fallback_env,
             timeout: self.timeout,
-            builtin_tools: self.builtin_tools,
+            builtin_tools,
             sandbox_mode,
+            require_containment: self.require_containment,
             temp_dir_guard,
+            isolated: self.isolated,
+            scratch_home,
             effective_cwd,
             result_file,
             result_path,
+            submit_schema,
             mcp_config,
+            extra_mcp_configs,
+            namespace_sinks,
             allowed_tools,
             full_system_prompt,
             final_prompt,
+            middleware: self.middleware,
+            backpressure: self.backpressure,
+            mcp_delivery: self.mcp_delivery,
+            cli_env,
+            cli_path: self.cli_path,
+            default_models: self.default_models,
+            payload_findings,
+            harvest_file,
+            submissions_file,
+            workflow,
+            stream_validator,
+            artifacts_dir: self.artifacts_dir,
+            shared_context: self.shared_context,
+            attachments_dir,
+            images: self.images,
+            stderr_events: self.stderr_events,
         })
     }
 }
 
+/// Checks the server and tool names against `adapter`'s naming rules and
+/// returns the `mcp__<server>__<tool>` names for the allowed-tools list.
+fn allowed_tool_names(
+    adapter: CliAdapter,
+    server_name: &str,
+    definitions: &[rig::completion::ToolDefinition],
+) -> Result<Vec<String>, ProviderError> {
+    let invalid = |e: rig_cli_mcp::naming::NameError| {
+        ProviderError::Validation(format!("{adapter} MCP tool naming: {e}"))
+    };
+    rig_cli_mcp::naming::check_server_name(server_name).map_err(invalid)?;
+    definitions
+        .iter()
+        .map(|def| {
+            rig_cli_mcp::naming::check_tool_name(&def.name).map_err(invalid)?;
+            rig_cli_mcp::naming::check_qualified_len(
+                &adapter.model_tool_name(server_name, &def.name),
+                &def.name,
+                rig_cli_mcp::naming::MAX_TOOL_NAME_LEN,
+            )
+            .map_err(invalid)?;
+            Ok(format!("mcp__{server_name}__{}", def.name))
+        })
+        .collect()
+}
+
+/// Builds the config that launches the current executable, or `command`
+/// and its arguments if set, as the MCP server.
+///
+/// The server is told where to write the submit result via `RIG_MCP_RESULT_PATH`,
+/// where to harvest tool calls, if at all, via
+/// [`HARVEST_PATH_ENV`](rig_cli_mcp::harvest::HARVEST_PATH_ENV), and where to
+/// record submit calls, if at all, via
+/// [`SUBMISSIONS_PATH_ENV`](rig_cli_mcp::submissions::SUBMISSIONS_PATH_ENV).
+fn mcp_server_config(
+    server_name: &str,
+    command: Option<&(std::path::PathBuf, Vec<String>)>,
+    extra_env: &std::collections::HashMap<String, String>,
+    result_path: &std::path::Path,
+    harvest_path: Option<&std::path::Path>,
+    submissions_path: Option<&std::path::Path>,
+) -> Result<rig_cli_mcp::server::McpConfig, ProviderError> {
+    let (exe, args) = match command {
+        Some((exe, args)) => (exe.clone(), args.clone()),
+        None => (
+            std::env::current_exe().map_err(|e| {
+                ProviderError::mcp_config("failed to resolve current executable", e)
+            })?,
+            Vec::new(),
+        ),
+    };
+
+    let path_string = |path: &std::path::Path| {
+        rig_cli_mcp::server::config_path_string(path)
+            .map_err(|e| ProviderError::mcp_config("failed to encode path for MCP config", e))
+    };
+
+    let mut env = std::collections::HashMap::new();
+    env.insert("RIG_MCP_SERVER".to_string(), "1".to_string());
+    env.insert(
+        rig_cli_mcp::server::SERVER_NAME_ENV.to_string(),
+        server_name.to_string(),
+    );
+    env.insert("RIG_MCP_RESULT_PATH".to_string(), path_string(result_path)?);
+    if let Some(harvest_path) = harvest_path {
+        env.insert(
+            rig_cli_mcp::harvest::HARVEST_PATH_ENV.to_string(),
+            path_string(harvest_path)?,
+        );
+    }
+    if let Some(submissions_path) = submissions_path {
+        env.insert(
+            rig_cli_mcp::submissions::SUBMISSIONS_PATH_ENV.to_string(),
+            path_string(submissions_path)?,
+        );
+    }
+    env.extend(extra_env.clone());
+
+    Ok(rig_cli_mcp::server::McpConfig {
+        name: server_name.to_string(),
+        command: path_string(&exe)?,
+        args,
+        env,
+    })
+}
+
+impl PreparedAgent {
+    /// Snapshot of the run inputs handed to middleware.
+    fn request(&self) -> RunRequest {
+        RunRequest::new(
+            self.backend.adapter(),
+            self.final_prompt.clone(),
+            self.full_system_prompt.clone(),
+            self.timeout,
+            self.effective_cwd.clone(),
+            self.allowed_tools.clone(),
+        )
+    }
+
+    /// Applies middleware edits back onto the prepared run.
+    fn apply(&mut self, request: RunRequest) {
+        self.final_prompt = request.prompt;
+        self.full_system_prompt = request.system_prompt;
+        self.timeout = request.timeout;
+        self.effective_cwd = request.working_dir;
+    }
+
+    /// Checks the working directory, after middleware had its say, before
+    /// anything is spawned.
+    fn check_containment(&self) -> Result<(), ProviderError> {
+        crate::containment::check_working_dir(
+            &self.effective_cwd,
+            self.backend.adapter(),
+            &self.sandbox_mode,
+            self.require_containment,
+        )
+    }
+
+    /// Creates the run's artifact directory, if one was requested.
+    fn artifacts(&mut self) -> Result<Option<RunArtifacts>, ProviderError> {
+        let Some(dir) = self.artifacts_dir.take() else {
+            return Ok(None);
+        };
+        let artifacts = RunArtifacts::create(&dir, self.backend.adapter())?;
+        tracing::debug!(
+            event = "run_artifacts",
+            dir = %artifacts.dir().display(),
+            "Recording run artifacts"
+        );
+        Ok(Some(artifacts))
+    }
+
+    /// Inputs handed to the backend.
+    fn backend_request(&self) -> BackendRequest {
+        BackendRequest {
+            prompt: self.final_prompt.clone(),
+            system_prompt: self.full_system_prompt.clone(),
+            mcp_config: self.mcp_config.clone(),
+            extra_mcp_configs: self.extra_mcp_configs.clone(),
+            allowed_tools: self.allowed_tools.clone(),
+            builtin_tools: self.builtin_tools.clone(),
+            sandbox_mode: self.sandbox_mode.clone(),
+            timeout: self.timeout,
+            cwd: self.effective_cwd.clone(),
+            env: self.cli_env.clone(),
+            cli_path: self.cli_path.clone(),
+            models: self.default_models.clone(),
+            backpressure: self.backpressure,
+            mcp_delivery: self.mcp_delivery,
+            isolated: self.isolated,
+            images: self.images.clone(),
+            stderr_events: self.stderr_events.clone(),
+        }
+    }
+
+    /// Spawns the CLI for the prepared run and collects its result, failing
+    /// over to the fallback backend if the CLI produced no output.
+    async fn execute(self) -> Result<McpToolAgentResult, ProviderError> {
+        let start = std::time::Instant::now();
+        let mut result = match self.backend.run(self.backend_request()).await {
+            Ok(result) => result,
+            Err(e) => {
+                let Some(fallback) = self.fallback.as_ref().filter(|_| e.failed_before_output())
+                else {
+                    return Err(e);
+                };
+                tracing::warn!(
+                    event = "cli_failover",
+                    adapter = %self.backend.adapter(),
+                    fallback = %fallback.adapter(),
+                    error = %e,
+                    "CLI failed before producing output; running the fallback adapter"
+                );
+                let attempt = FailedAttempt {
+                    adapter: self.backend.adapter(),
+                    error_code: e.error_code(),
+                    error: e.to_string(),
+                    duration_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+                };
+                let request = BackendRequest {
+                    env: self.fallback_env.clone(),
+                    cli_path: None,
+                    ..self.backend_request()
+                };
+                let mut result = fallback.run(request).await?;
+                result.failed_attempts.push(attempt);
+                result
+            }
+        };
+
+        // Read the structured result from the MCP server's result file.
+        // This is the primary result path — stdout is a progress channel.
+        result.submit_result = std::fs::read_to_string(&self.result_path)
+            .ok()
+            .filter(|s| !s.is_empty());
+        result.result_json =
+            parse_submit_result(result.submit_result.as_deref(), self.submit_schema.as_ref())?;
+        if let Some(file) = &self.harvest_file {
+            result.tool_harvest = Some(rig_cli_mcp::harvest::ToolHarvest::read(file.path())?);
+        }
+        result.compliance = result
+            .tool_harvest
+            .as_ref()
+            .map(|harvest| self.workflow.judge(harvest));
+        result.namespace_results = read_namespace_results(&self.namespace_sinks)?;
+        result.submissions =
+            rig_cli_mcp::submissions::SubmitRecord::read_all(self.submissions_file.path())?;
+
+        // Explicitly drop temp dir guard after CLI completes
+        drop(self.temp_dir_guard);
+        drop(self.scratch_home);
+        drop(self.shared_context);
+        drop(self.attachments_dir);
+
+        Ok(result)
+    }
+}
+
 /// MCP-enforced CLI agent that implements Rig's Prompt and Chat traits.
 ///
 /// Unlike `CompletionModel` (which receives `ToolDefinitions`), `CliAgent` holds
@@ -704,7 +1888,8 @@ impl McpToolAgentBuilder {
 /// ```
 pub struct CliAgent {
     toolset: rig::tool::ToolSet,
-    adapter: CliAdapter,
+    namespaces: Vec<(String, rig::tool::ToolSet)>,
+    backend: std::sync::Arc<dyn CliBackend>,
     preamble: Option<String>,
     timeout: Duration,
     payload: Option<String>,
@@ -712,14 +1897,24 @@ pub struct CliAgent {
     builtin_tools: Option<Vec<String>>,
     sandbox_mode: Option<rig_cli_codex::SandboxMode>,
     working_dir: Option<std::path::PathBuf>,
+    require_containment: bool,
+    isolated: bool,
     server_name: String,
     extra_env: std::collections::HashMap<String, String>,
+    middleware: MiddlewareStack,
+    credentials: Option<Credentials>,
+    payload_scanner: Option<PayloadScanner>,
+    payload_budget: Option<PayloadBudget>,
+    attachments: Attachments,
+    images: Vec<std::path::PathBuf>,
+    default_models: DefaultModels,
 }
 
 /// Builder for `CliAgent`.
 pub struct CliAgentBuilder {
     toolset: Option<rig::tool::ToolSet>,
-    adapter: Option<CliAdapter>,
+    namespaces: Vec<(String, rig::tool::ToolSet)>,
+    backend: Option<std::sync::Arc<dyn CliBackend>>,
     preamble: Option<String>,
     timeout: Duration,
     payload: Option<String>,
@@ -727,15 +1922,25 @@ pub struct CliAgentBuilder {
     builtin_tools: Option<Vec<String>>,
     sandbox_mode: Option<rig_cli_codex::SandboxMode>,
     working_dir: Option<std::path::PathBuf>,
+    require_containment: bool,
+    isolated: bool,
     server_name: String,
     extra_env: std::collections::HashMap<String, String>,
+    middleware: MiddlewareStack,
+    credentials: Option<Credentials>,
+    payload_scanner: Option<PayloadScanner>,
+    payload_budget: Option<PayloadBudget>,
+    attachments: Attachments,
+    images: Vec<std::path::PathBuf>,
+    default_models: DefaultModels,
 }
 
 impl CliAgentBuilder {
     fn new() -> Self {
         Self {
             toolset: None,
-            adapter: None,
+            namespaces: Vec::new(),
+            backend: None,
             preamble: None,
             timeout: Duration::from_secs(300),
             payload: None,
@@ -743,8 +1948,17 @@ impl CliAgentBuilder {
             builtin_tools: None,
             sandbox_mode: Some(rig_cli_codex::SandboxMode::ReadOnly),
             working_dir: None,
+            require_containment: false,
+            isolated: true,
             server_name: "rig_mcp".to_string(),
             extra_env: std::collections::HashMap::new(),
+            middleware: MiddlewareStack::default(),
+            credentials: None,
+            payload_scanner: None,
+            payload_budget: None,
+            attachments: Attachments::new(),
+            images: Vec::new(),
+            default_models: DefaultModels::from_env(),
         }
     }
 
@@ -757,8 +1971,17 @@ impl CliAgentBuilder {
 
     /// Sets which CLI adapter to use for execution.
     #[must_use]
-    pub const fn adapter(mut self, adapter: CliAdapter) -> Self {
-        self.adapter = Some(adapter);
+    pub fn adapter(mut self, adapter: CliAdapter) -> Self {
+        self.backend = Some(adapter.backend());
+        self
+    }
+
+    /// Runs through `backend` instead of a built-in adapter.
+    ///
+    /// See [`McpToolAgentBuilder::backend`].
+    #[must_use]
+    pub fn backend(mut self, backend: impl CliBackend + 'static) -> Self {
+        self.backend = Some(std::sync::Arc::new(backend));
         self
     }
 
@@ -815,6 +2038,23 @@ impl CliAgentBuilder {
         self
     }
 
+    /// Refuses to run in the home directory or a repository root, or under
+    /// the Codex `DangerFullAccess` sandbox. Default: `false`.
+    #[must_use]
+    pub const fn require_containment(mut self, require: bool) -> Self {
+        self.require_containment = require;
+        self
+    }
+
+    /// Keeps the host user's Claude Code configuration out of every run.
+    ///
+    /// See [`McpToolAgentBuilder::isolated`].
+    #[must_use]
+    pub const fn isolated(mut self, isolated: bool) -> Self {
+        self.isolated = isolated;
+        self
+    }
+
     /// Sets the MCP server name used in config and tool name prefixes.
     ///
     /// Defaults to `"rig_mcp"`.
@@ -824,6 +2064,14 @@ impl CliAgentBuilder {
         self
     }
 
+    /// Runs a second MCP server named `name`, serving `toolset`; see
+    /// [`McpToolAgentBuilder::namespace`].
+    #[must_use]
+    pub fn namespace(mut self, name: impl Into<String>, toolset: rig::tool::ToolSet) -> Self {
+        self.namespaces.push((name.into(), toolset));
+        self
+    }
+
     /// Adds an environment variable to the MCP server subprocess config.
     ///
     /// These are written into the MCP config JSON and explicitly set by the
@@ -835,6 +2083,78 @@ impl CliAgentBuilder {
         self
     }
 
+    /// Adds a [`RunMiddleware`] layer around the run.
+    ///
+    /// Layers run in the order they are added; see [`crate::middleware`].
+    #[must_use]
+    pub fn middleware(mut self, middleware: impl RunMiddleware + 'static) -> Self {
+        self.middleware.push(std::sync::Arc::new(middleware));
+        self
+    }
+
+    /// Loads the CLI's API keys from `credentials` on every prompt.
+    ///
+    /// See [`McpToolAgentBuilder::credentials`].
+    #[must_use]
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Scans the payload for prompt injection patterns on every prompt.
+    ///
+    /// See [`McpToolAgentBuilder::payload_scanner`].
+    #[must_use]
+    pub const fn payload_scanner(mut self, scanner: PayloadScanner) -> Self {
+        self.payload_scanner = Some(scanner);
+        self
+    }
+
+    /// Caps the payload at a number of estimated tokens on every prompt.
+    ///
+    /// See [`McpToolAgentBuilder::payload_budget`].
+    #[must_use]
+    pub const fn payload_budget(mut self, budget: PayloadBudget) -> Self {
+        self.payload_budget = Some(budget);
+        self
+    }
+
+    /// Sends a binary attachment with every prompt.
+    ///
+    /// See [`McpToolAgentBuilder::attachment`].
+    #[must_use]
+    pub fn attachment(mut self, attachment: BinaryPayload) -> Self {
+        self.attachments = self.attachments.attachment(attachment);
+        self
+    }
+
+    /// Replaces the attachments sent with every prompt.
+    ///
+    /// See [`McpToolAgentBuilder::attachments`].
+    #[must_use]
+    pub fn attachments(mut self, attachments: Attachments) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    /// Shows the model an image file with every prompt.
+    ///
+    /// See [`McpToolAgentBuilder::image`].
+    #[must_use]
+    pub fn image(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.images.push(path.into());
+        self
+    }
+
+    /// Sets the model each built-in adapter runs.
+    ///
+    /// See [`McpToolAgentBuilder::default_models`].
+    #[must_use]
+    pub fn default_models(mut self, models: DefaultModels) -> Self {
+        self.default_models = models;
+        self
+    }
+
     /// Builds the `CliAgent`.
     ///
     /// # Errors
@@ -842,14 +2162,15 @@ impl CliAgentBuilder {
     pub fn build(self) -> Result<CliAgent, ProviderError> {
         let toolset = self
             .toolset
-            .ok_or_else(|| ProviderError::McpToolAgent("toolset is required".to_string()))?;
-        let adapter = self
-            .adapter
-            .ok_or_else(|| ProviderError::McpToolAgent("adapter is required".to_string()))?;
+            .ok_or_else(|| ProviderError::Validation("toolset is required".to_string()))?;
+        let backend = self
+            .backend
+            .ok_or_else(|| ProviderError::Validation("adapter is required".to_string()))?;
 
         Ok(CliAgent {
             toolset,
-            adapter,
+            namespaces: self.namespaces,
+            backend,
             preamble: self.preamble,
             timeout: self.timeout,
             payload: self.payload,
@@ -857,8 +2178,17 @@ impl CliAgentBuilder {
             builtin_tools: self.builtin_tools,
             sandbox_mode: self.sandbox_mode,
             working_dir: self.working_dir,
+            require_containment: self.require_containment,
+            isolated: self.isolated,
             server_name: self.server_name,
             extra_env: self.extra_env,
+            middleware: self.middleware,
+            credentials: self.credentials,
+            payload_scanner: self.payload_scanner,
+            payload_budget: self.payload_budget,
+            attachments: self.attachments,
+            images: self.images,
+            default_models: self.default_models,
         })
     }
 }
@@ -880,7 +2210,6 @@ impl CliAgent {
     pub async fn prompt(self, prompt: &str) -> Result<String, ProviderError> {
         let mut builder = McpToolAgent::builder()
             .toolset(self.toolset)
-            .adapter(self.adapter)
             .prompt(prompt)
             .timeout(self.timeout)
             .server_name(&self.server_name);
@@ -904,9 +2233,21 @@ impl CliAgent {
         if let Some(ref dir) = self.working_dir {
             builder = builder.working_dir(dir);
         }
+        builder = builder
+            .require_containment(self.require_containment)
+            .isolated(self.isolated);
         for (k, v) in &self.extra_env {
             builder = builder.extra_env(k, v);
         }
+        builder.namespaces = self.namespaces;
+        builder.backend = Some(self.backend);
+        builder.middleware = self.middleware;
+        builder.credentials = self.credentials;
+        builder.payload_scanner = self.payload_scanner;
+        builder.payload_budget = self.payload_budget;
+        builder.attachments = self.attachments;
+        builder.images = self.images;
+        builder.default_models = self.default_models;
 
         let result = builder.run().await?;
 
@@ -972,547 +2313,436 @@ fn assemble_prompts(
     (full_system_prompt, final_prompt)
 }
 
-/// Shared parameters for stream-based adapter execution.
-struct StreamRunCtx<'a> {
-    prompt: &'a str,
-    mcp_config: &'a rig_cli_mcp::server::McpConfig,
-    system_prompt: &'a str,
-    timeout: Duration,
-    cwd: &'a std::path::Path,
-    temp_dir_guard: Option<tempfile::TempDir>,
-    tx: tokio::sync::mpsc::Sender<McpStreamEvent>,
-}
-
-async fn run_claude_code(
-    prompt: &str,
-    mcp_config: &rig_cli_mcp::server::McpConfig,
-    allowed_tools: &[String],
-    system_prompt: &str,
-    timeout: Duration,
-    builtin_tools: Option<&Vec<String>>,
-    cwd: &std::path::Path,
-) -> Result<McpToolAgentResult, ProviderError> {
-    // Write Claude Code MCP config JSON to temp file
-    let mut config_file = tempfile::NamedTempFile::new()
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to create temp file: {e}")))?;
-    let json = serde_json::to_string_pretty(&mcp_config.to_claude_json())
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to serialize config: {e}")))?;
-    config_file
-        .write_all(json.as_bytes())
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to write config: {e}")))?;
-    let config_path = config_file.path().to_path_buf();
-    let _config_guard = config_file.into_temp_path();
-
-    let report = rig_cli_claude::init(None)
-        .await
-        .map_err(|e| ProviderError::McpToolAgent(format!("Claude init failed: {e}")))?;
-
-    // Detect and validate CLI version
-    detect_and_validate_version(&report.claude_path, &claude_code_version_req()).await;
-
-    let cli = rig_cli_claude::ClaudeCli::new(report.claude_path, report.capabilities);
-
-    // Apply containment: disable all builtins by default, opt-in via builtin_tools
-    let builtin_set = builtin_tools.map_or(rig_cli_claude::BuiltinToolSet::None, |tools| {
-        rig_cli_claude::BuiltinToolSet::Explicit(tools.clone())
-    });
-
-    let config = rig_cli_claude::RunConfig {
-        output_format: Some(rig_cli_claude::OutputFormat::Text),
-        system_prompt: rig_cli_claude::SystemPromptMode::Append(system_prompt.to_string()),
-        mcp: Some(rig_cli_claude::McpPolicy {
-            // Temp file paths are always valid UTF-8 (created by tempfile crate).
-            configs: vec![config_path.to_string_lossy().to_string()],
-            strict: true,
-        }),
-        tools: rig_cli_claude::ToolPolicy {
-            builtin: builtin_set,
-            allowed: Some(allowed_tools.to_vec()),
-            disallowed: None,
-            disable_slash_commands: true,
-        },
-        timeout,
-        cwd: Some(cwd.to_path_buf()),
-        no_session_persistence: true,
-        ..rig_cli_claude::RunConfig::default()
-    };
-
-    let result = cli
-        .run(prompt, &config)
-        .await
-        .map_err(ProviderError::Claude)?;
-
-    Ok(McpToolAgentResult {
-        stdout: result.stdout,
-        stderr: result.stderr,
-        exit_code: result.exit_code,
-        duration_ms: result.duration_ms,
-        submit_result: None,
-    })
-}
-
-async fn run_codex(
-    prompt: &str,
-    mcp_config: &rig_cli_mcp::server::McpConfig,
-    system_prompt: &str,
-    timeout: Duration,
-    sandbox_mode: &rig_cli_codex::SandboxMode,
-    cwd: &std::path::Path,
-) -> Result<McpToolAgentResult, ProviderError> {
-    let path = rig_cli_codex::discover_codex(None)
-        .map_err(|e| ProviderError::McpToolAgent(format!("Codex discovery failed: {e}")))?;
-
-    // Detect and validate CLI version
-    detect_and_validate_version(&path, &codex_version_req()).await;
-
-    let cli = rig_cli_codex::CodexCli::new(path);
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
 
-    // Codex reads MCP server config from its config.toml. Inject via -c overrides.
-    let server_name = &mcp_config.name;
-    let mut overrides = vec![
-        (
-            format!("mcp_servers.{server_name}.command"),
-            format!("\"{}\"", mcp_config.command),
-        ),
-        (
-            format!("mcp_servers.{server_name}.args"),
-            format!("{:?}", mcp_config.args),
-        ),
-    ];
-    for (k, v) in &mcp_config.env {
-        overrides.push((
-            format!("mcp_servers.{server_name}.env.{k}"),
-            format!("\"{v}\""),
-        ));
+    fn stream_handle(
+        task: StreamTask,
+    ) -> (McpStreamHandle, tokio::sync::mpsc::Sender<McpStreamEvent>) {
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        let result_file = tempfile::NamedTempFile::new().unwrap();
+        let handle = McpStreamHandle {
+            rx,
+            result_path: result_file.path().to_path_buf(),
+            _result_file: result_file,
+            submit_schema: None,
+            task,
+            payload_findings: Vec::new(),
+            harvest_file: None,
+            namespace_sinks: Vec::new(),
+            submissions_file: tempfile::NamedTempFile::new().unwrap(),
+            workflow: WorkflowScope {
+                server: "rig_mcp".to_string(),
+                tools: Vec::new(),
+            },
+            artifacts: None,
+        };
+        (handle, tx)
     }
 
-    let config = rig_cli_codex::CodexConfig {
-        full_auto: false,
-        sandbox: Some(sandbox_mode.clone()),
-        skip_git_repo_check: true,
-        cd: Some(cwd.to_path_buf()),
-        system_prompt: Some(system_prompt.to_string()),
-        overrides,
-        timeout,
-        ..rig_cli_codex::CodexConfig::default()
-    };
-
-    let result = cli
-        .run(prompt, &config)
-        .await
-        .map_err(ProviderError::Codex)?;
-
-    Ok(McpToolAgentResult {
-        stdout: result.stdout,
-        stderr: result.stderr,
-        exit_code: result.exit_code,
-        duration_ms: result.duration_ms,
-        submit_result: None,
-    })
-}
-
-async fn run_opencode(
-    prompt: &str,
-    mcp_config: &rig_cli_mcp::server::McpConfig,
-    system_prompt: &str,
-    timeout: Duration,
-    cwd: &std::path::Path,
-) -> Result<McpToolAgentResult, ProviderError> {
-    let path = rig_cli_opencode::discover_opencode(None)
-        .map_err(|e| ProviderError::McpToolAgent(format!("OpenCode discovery failed: {e}")))?;
+    #[tokio::test]
+    async fn test_stream_handle_finish_harvests_submit_result() {
+        let (mut handle, tx) = stream_handle(tokio::spawn(async {
+            Ok(McpToolAgentResult {
+                stdout: "done".to_string(),
+                stderr: String::new(),
+                exit_code: 0,
+                duration_ms: 42,
+                submit_result: None,
+                result_json: None,
+                payload_findings: Vec::new(),
+                tool_harvest: None,
+                compliance: None,
+                failed_attempts: Vec::new(),
+                namespace_results: std::collections::BTreeMap::new(),
+                submissions: Vec::new(),
+            })
+        }));
+        tx.send(McpStreamEvent::Text("working".to_string()))
+            .await
+            .unwrap();
+        drop(tx);
+        std::fs::write(&handle.result_path, r#"{"ok":true}"#).unwrap();
+
+        assert!(matches!(handle.recv().await, Some(McpStreamEvent::Text(t)) if t == "working"));
+        assert!(handle.recv().await.is_none());
+
+        let result = handle.finish().await.unwrap();
+        assert_eq!(result.duration_ms, 42);
+        assert_eq!(result.submit_result.as_deref(), Some(r#"{"ok":true}"#));
+        assert_eq!(result.result_json, Some(serde_json::json!({"ok": true})));
+    }
 
-    // Detect and validate CLI version
-    detect_and_validate_version(&path, &opencode_version_req()).await;
+    #[tokio::test]
+    async fn test_stream_handle_finish_reads_tool_harvest() {
+        let (mut handle, tx) = stream_handle(tokio::spawn(async {
+            Ok(McpToolAgentResult::from_output(
+                String::new(),
+                String::new(),
+                0,
+                0,
+            ))
+        }));
+        drop(tx);
+        let harvest_file = tempfile::NamedTempFile::new().unwrap();
+        let record = serde_json::json!({
+            "timestamp_ms": 0,
+            "server": "rig_mcp",
+            "tool": "validate_json",
+            "arguments": { "json": {} },
+            "result": "JSON validation failed.",
+            "is_error": false,
+            "duration_ms": 1,
+            "client_name": null,
+            "client_version": null
+        });
+        std::fs::write(harvest_file.path(), format!("{record}\n")).unwrap();
+        handle.harvest_file = Some(harvest_file);
+
+        let harvest = handle.finish().await.unwrap().tool_harvest.unwrap();
+        assert_eq!(harvest.calls_to("validate_json"), 1);
+        assert_eq!(harvest.validation_failures(), 1);
+    }
 
-    let cli = rig_cli_opencode::OpenCodeCli::new(path);
+    #[tokio::test]
+    async fn test_stream_handle_finish_records_artifacts() {
+        let (mut handle, tx) = stream_handle(tokio::spawn(async {
+            Ok(McpToolAgentResult::from_output(
+                "done".to_string(),
+                String::new(),
+                0,
+                42,
+            ))
+        }));
+        drop(tx);
+        let base = tempfile::tempdir().unwrap();
+        let artifacts = RunArtifacts::create(base.path(), CliAdapter::ClaudeCode).unwrap();
+        let dir = artifacts.dir().to_path_buf();
+        handle.artifacts = Some(artifacts);
+        std::fs::write(&handle.result_path, r#"{"ok":true}"#).unwrap();
+
+        handle.finish().await.unwrap();
+
+        let index: crate::artifacts::ArtifactIndex =
+            serde_json::from_slice(&std::fs::read(dir.join(crate::artifacts::INDEX_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(index.exit_code, Some(0));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("result.json")).unwrap(),
+            r#"{"ok":true}"#
+        );
+    }
 
-    // OpenCode config format: {"mcp": {"name": {"type":"local","command":[...],"environment":{...}}}}
-    let mut command = vec![mcp_config.command.clone()];
-    command.extend(mcp_config.args.iter().cloned());
+    struct MissingCli;
 
-    let opencode_cfg = serde_json::json!({
-        "$schema": "https://opencode.ai/config.json",
-        "mcp": {
-            &mcp_config.name: {
-                "type": "local",
-                "command": command,
-                "environment": &mcp_config.env,
-            }
+    #[crate::backend::async_trait]
+    impl CliBackend for MissingCli {
+        fn adapter(&self) -> CliAdapter {
+            CliAdapter::Custom("missing")
         }
-    });
-
-    let mut config_file = tempfile::NamedTempFile::new()
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to create temp file: {e}")))?;
-    let json = serde_json::to_string_pretty(&opencode_cfg)
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to serialize config: {e}")))?;
-    config_file
-        .write_all(json.as_bytes())
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to write config: {e}")))?;
-
-    let config_path = config_file.path().to_path_buf();
-    let _config_guard = config_file.into_temp_path();
-
-    let config = rig_cli_opencode::OpenCodeConfig {
-        model: Some("opencode/big-pickle".to_string()),
-        prompt: Some(system_prompt.to_string()),
-        mcp_config_path: Some(config_path),
-        cwd: Some(cwd.to_path_buf()),
-        timeout,
-        ..rig_cli_opencode::OpenCodeConfig::default()
-    };
-
-    let result = cli
-        .run(prompt, &config)
-        .await
-        .map_err(ProviderError::OpenCode)?;
-
-    Ok(McpToolAgentResult {
-        stdout: result.stdout,
-        stderr: result.stderr,
-        exit_code: result.exit_code,
-        duration_ms: result.duration_ms,
-        submit_result: None,
-    })
-}
-
-async fn run_claude_code_stream(
-    ctx: StreamRunCtx<'_>,
-    allowed_tools: &[String],
-    builtin_tools: Option<&Vec<String>>,
-) -> Result<(), ProviderError> {
-    // Write Claude Code MCP config JSON to temp file
-    let mut config_file = tempfile::NamedTempFile::new()
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to create temp file: {e}")))?;
-    let json = serde_json::to_string_pretty(&ctx.mcp_config.to_claude_json())
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to serialize config: {e}")))?;
-    config_file
-        .write_all(json.as_bytes())
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to write config: {e}")))?;
-    let config_path = config_file.path().to_path_buf();
-    let config_guard = config_file.into_temp_path();
-
-    let report = rig_cli_claude::init(None)
-        .await
-        .map_err(|e| ProviderError::McpToolAgent(format!("Claude init failed: {e}")))?;
-
-    // Detect and validate CLI version
-    detect_and_validate_version(&report.claude_path, &claude_code_version_req()).await;
-
-    let cli = rig_cli_claude::ClaudeCli::new(report.claude_path, report.capabilities);
-
-    // Apply containment: disable all builtins by default, opt-in via builtin_tools
-    let builtin_set = builtin_tools.map_or(rig_cli_claude::BuiltinToolSet::None, |tools| {
-        rig_cli_claude::BuiltinToolSet::Explicit(tools.clone())
-    });
-
-    let config = rig_cli_claude::RunConfig {
-        output_format: Some(rig_cli_claude::OutputFormat::StreamJson),
-        system_prompt: rig_cli_claude::SystemPromptMode::Append(ctx.system_prompt.to_string()),
-        mcp: Some(rig_cli_claude::McpPolicy {
-            configs: vec![config_path.to_string_lossy().to_string()],
-            strict: true,
-        }),
-        tools: rig_cli_claude::ToolPolicy {
-            builtin: builtin_set,
-            allowed: Some(allowed_tools.to_vec()),
-            disallowed: None,
-            disable_slash_commands: true,
-        },
-        timeout: ctx.timeout,
-        cwd: Some(ctx.cwd.to_path_buf()),
-        no_session_persistence: true,
-        ..rig_cli_claude::RunConfig::default()
-    };
 
-    // Create internal channel for adapter's native StreamEvent
-    let (adapter_tx, mut adapter_rx) =
-        tokio::sync::mpsc::channel::<rig_cli_claude::StreamEvent>(100);
-
-    // Clone prompt for 'static lifetime in spawned task
-    let prompt_owned = ctx.prompt.to_string();
-    let tx = ctx.tx;
-
-    // Spawn task to run CLI and convert events.
-    // Move temp file guards into the task to keep them alive for the CLI's duration.
-    tokio::spawn(async move {
-        let _keep_cwd = ctx.temp_dir_guard;
-        let _keep_config = config_guard;
-
-        // Run the CLI with streaming
-        let result = cli.stream(&prompt_owned, &config, adapter_tx.clone()).await;
-
-        // Drop the sender to close the channel - this signals to the receiver
-        // that no more events will arrive. Without this, recv() never returns None.
-        drop(adapter_tx);
-
-        // Propagate CLI execution errors as McpStreamEvent::Error
-        if let Err(e) = result {
-            tracing::error!(event = "claude_stream_failed", error = %e, "Claude Code stream execution failed");
-            let _ = tx
-                .send(McpStreamEvent::Error(format!("CLI stream failed: {e}")))
-                .await;
-            return;
+        fn capabilities(&self) -> crate::backend::BackendCapabilities {
+            crate::backend::BackendCapabilities::default()
         }
 
-        // Convert adapter events to McpStreamEvent
-        while let Some(event) = adapter_rx.recv().await {
-            let mcp_event = match event {
-                rig_cli_claude::StreamEvent::Text { text } => McpStreamEvent::Text(text),
-                rig_cli_claude::StreamEvent::ToolCall { name, input } => McpStreamEvent::ToolCall {
-                    name,
-                    input: input.to_string(),
-                },
-                rig_cli_claude::StreamEvent::ToolResult { name, output } => {
-                    McpStreamEvent::ToolResult {
-                        tool_use_id: name,
-                        content: output,
-                    }
-                }
-                rig_cli_claude::StreamEvent::Error { message } => McpStreamEvent::Error(message),
-                rig_cli_claude::StreamEvent::Unknown(_) => continue, // skip unknowns
-            };
+        async fn discover(
+            &self,
+            _explicit: Option<std::path::PathBuf>,
+        ) -> Result<std::path::PathBuf, ProviderError> {
+            Err(ProviderError::discovery(self.adapter(), "not on PATH"))
+        }
 
-            // Send converted event (ignore if receiver dropped)
-            let _ = tx.send(mcp_event).await;
+        async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+            self.discover(request.cli_path).await?;
+            unreachable!("discovery always fails")
         }
-    });
+    }
 
-    Ok(())
-}
+    struct EchoCli;
 
-async fn run_codex_stream(
-    ctx: StreamRunCtx<'_>,
-    sandbox_mode: &rig_cli_codex::SandboxMode,
-) -> Result<(), ProviderError> {
-    let path = rig_cli_codex::discover_codex(None)
-        .map_err(|e| ProviderError::McpToolAgent(format!("Codex discovery failed: {e}")))?;
+    #[crate::backend::async_trait]
+    impl CliBackend for EchoCli {
+        fn adapter(&self) -> CliAdapter {
+            CliAdapter::Custom("echo")
+        }
 
-    // Detect and validate CLI version
-    detect_and_validate_version(&path, &codex_version_req()).await;
+        fn capabilities(&self) -> crate::backend::BackendCapabilities {
+            crate::backend::BackendCapabilities::default()
+        }
 
-    let cli = rig_cli_codex::CodexCli::new(path);
+        async fn discover(
+            &self,
+            explicit: Option<std::path::PathBuf>,
+        ) -> Result<std::path::PathBuf, ProviderError> {
+            Ok(explicit.unwrap_or_else(|| std::path::PathBuf::from("echo")))
+        }
 
-    // Codex reads MCP server config from its config.toml. Inject via -c overrides.
-    let server_name = &ctx.mcp_config.name;
-    let mut overrides = vec![
-        (
-            format!("mcp_servers.{server_name}.command"),
-            format!("\"{}\"", ctx.mcp_config.command),
-        ),
-        (
-            format!("mcp_servers.{server_name}.args"),
-            format!("{:?}", ctx.mcp_config.args),
-        ),
-    ];
-    for (k, v) in &ctx.mcp_config.env {
-        overrides.push((
-            format!("mcp_servers.{server_name}.env.{k}"),
-            format!("\"{v}\""),
-        ));
+        async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+            assert!(request.cli_path.is_none());
+            Ok(McpToolAgentResult::from_output(
+                request.prompt,
+                String::new(),
+                0,
+                1,
+            ))
+        }
     }
 
-    let config = rig_cli_codex::CodexConfig {
-        full_auto: false,
-        sandbox: Some(sandbox_mode.clone()),
-        skip_git_repo_check: true,
-        cd: Some(ctx.cwd.to_path_buf()),
-        system_prompt: Some(ctx.system_prompt.to_string()),
-        overrides,
-        timeout: ctx.timeout,
-        ..rig_cli_codex::CodexConfig::default()
-    };
+    #[tokio::test]
+    async fn test_run_fails_over_when_cli_produces_no_output() {
+        let builder = || {
+            McpToolAgent::builder()
+                .toolset(rig::tool::ToolSet::builder().build())
+                .prompt("hello")
+                .backend(MissingCli)
+                .cli_path("/opt/missing")
+        };
 
-    // Create internal channel for adapter's native StreamEvent
-    let (adapter_tx, mut adapter_rx) =
-        tokio::sync::mpsc::channel::<rig_cli_codex::StreamEvent>(100);
-
-    // Clone prompt for 'static lifetime in spawned task
-    let prompt_owned = ctx.prompt.to_string();
-    let tx = ctx.tx;
-
-    // Spawn task to run CLI and convert events.
-    // Move temp dir guard into the task to keep cwd alive.
-    tokio::spawn(async move {
-        let _keep_cwd = ctx.temp_dir_guard;
-
-        // Run the CLI with streaming
-        let result = cli.stream(&prompt_owned, &config, adapter_tx.clone()).await;
-
-        // Drop the sender to close the channel - this signals to the receiver
-        // that no more events will arrive. Without this, recv() never returns None.
-        drop(adapter_tx);
-
-        // Propagate CLI execution errors as McpStreamEvent::Error
-        if let Err(e) = result {
-            tracing::error!(event = "codex_stream_failed", error = %e, "Codex stream execution failed");
-            let _ = tx
-                .send(McpStreamEvent::Error(format!("CLI stream failed: {e}")))
-                .await;
-            return;
-        }
+        let result = builder().fallback_backend(EchoCli).run().await.unwrap();
+        assert_eq!(result.stdout, "hello");
+        let [attempt] = result.failed_attempts.as_slice() else {
+            panic!("expected one failed attempt: {:?}", result.failed_attempts);
+        };
+        assert_eq!(attempt.adapter, CliAdapter::Custom("missing"));
+        assert_eq!(attempt.error_code, "discovery");
 
-        // Convert adapter events to McpStreamEvent
-        while let Some(event) = adapter_rx.recv().await {
-            let mcp_event = match event {
-                rig_cli_codex::StreamEvent::Text { text } => McpStreamEvent::Text(text),
-                rig_cli_codex::StreamEvent::Error { message } => McpStreamEvent::Error(message),
-                rig_cli_codex::StreamEvent::Unknown(_) => continue, // skip unknowns
-            };
+        let err = builder().run().await.unwrap_err();
+        assert_eq!(err.error_code(), "discovery");
+    }
 
-            // Send converted event (ignore if receiver dropped)
-            let _ = tx.send(mcp_event).await;
-        }
-    });
+    #[tokio::test]
+    async fn test_images_are_refused_by_backends_without_image_support() {
+        let err = McpToolAgent::builder()
+            .toolset(rig::tool::ToolSet::builder().build())
+            .prompt("hello")
+            .backend(EchoCli)
+            .image("screenshot.png")
+            .run()
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code(), "validation");
+        assert!(err.to_string().contains("echo does not accept images"));
+    }
 
-    Ok(())
-}
+    #[tokio::test]
+    async fn test_run_checks_working_dir_before_spawning() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        let builder = |cwd: std::path::PathBuf| {
+            McpToolAgent::builder()
+                .toolset(rig::tool::ToolSet::builder().build())
+                .prompt("hello")
+                .backend(EchoCli)
+                .working_dir(cwd)
+        };
 
-async fn run_opencode_stream(ctx: StreamRunCtx<'_>) -> Result<(), ProviderError> {
-    let path = rig_cli_opencode::discover_opencode(None)
-        .map_err(|e| ProviderError::McpToolAgent(format!("OpenCode discovery failed: {e}")))?;
+        assert!(builder(dir.path().to_path_buf()).run().await.is_ok());
 
-    // Detect and validate CLI version
-    detect_and_validate_version(&path, &opencode_version_req()).await;
+        let err = builder(dir.path().to_path_buf())
+            .require_containment(true)
+            .run()
+            .await
+            .unwrap_err();
+        let ProviderError::ContainmentMisconfiguration { violations, .. } = &err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(
+            violations,
+            &[crate::containment::ContainmentViolation::RepositoryRoot]
+        );
 
-    let cli = rig_cli_opencode::OpenCodeCli::new(path);
+        let err = builder(dir.path().join("missing"))
+            .stream()
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(err.error_code(), "containment_misconfiguration");
+        assert!(err.to_string().contains("does not exist"), "{err}");
+    }
 
-    // OpenCode config format: {"mcp": {"name": {"type":"local","command":[...],"environment":{...}}}}
-    let mut command = vec![ctx.mcp_config.command.clone()];
-    command.extend(ctx.mcp_config.args.iter().cloned());
+    /// Writes a submission into every namespaced server's result file.
+    struct NamespaceSubmitCli;
 
-    let opencode_cfg = serde_json::json!({
-        "$schema": "https://opencode.ai/config.json",
-        "mcp": {
-            &ctx.mcp_config.name: {
-                "type": "local",
-                "command": command,
-                "environment": &ctx.mcp_config.env,
-            }
+    #[crate::backend::async_trait]
+    impl CliBackend for NamespaceSubmitCli {
+        fn adapter(&self) -> CliAdapter {
+            CliAdapter::Custom("namespaces")
         }
-    });
-
-    let mut config_file = tempfile::NamedTempFile::new()
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to create temp file: {e}")))?;
-    let json = serde_json::to_string_pretty(&opencode_cfg)
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to serialize config: {e}")))?;
-    config_file
-        .write_all(json.as_bytes())
-        .map_err(|e| ProviderError::McpToolAgent(format!("Failed to write config: {e}")))?;
-
-    let config_path = config_file.path().to_path_buf();
-    let config_guard = config_file.into_temp_path();
-
-    let config = rig_cli_opencode::OpenCodeConfig {
-        model: Some("opencode/big-pickle".to_string()),
-        prompt: Some(ctx.system_prompt.to_string()),
-        mcp_config_path: Some(config_path),
-        cwd: Some(ctx.cwd.to_path_buf()),
-        timeout: ctx.timeout,
-        ..rig_cli_opencode::OpenCodeConfig::default()
-    };
 
-    // Create internal channel for adapter's native StreamEvent
-    let (adapter_tx, mut adapter_rx) =
-        tokio::sync::mpsc::channel::<rig_cli_opencode::StreamEvent>(100);
-
-    // Clone prompt for 'static lifetime in spawned task
-    let prompt_owned = ctx.prompt.to_string();
-    let tx = ctx.tx;
-
-    // Spawn task to run CLI and convert events.
-    // Move temp file guards into the task to keep them alive for the CLI's duration.
-    tokio::spawn(async move {
-        let _keep_cwd = ctx.temp_dir_guard;
-        let _keep_config = config_guard;
-
-        // Run the CLI with streaming
-        let result = cli.stream(&prompt_owned, &config, adapter_tx.clone()).await;
-
-        // Drop the sender to close the channel - this signals to the receiver
-        // that no more events will arrive. Without this, recv() never returns None.
-        drop(adapter_tx);
-
-        // Propagate CLI execution errors as McpStreamEvent::Error
-        if let Err(e) = result {
-            tracing::error!(event = "opencode_stream_failed", error = %e, "OpenCode stream execution failed");
-            let _ = tx
-                .send(McpStreamEvent::Error(format!("CLI stream failed: {e}")))
-                .await;
-            return;
+        fn capabilities(&self) -> crate::backend::BackendCapabilities {
+            crate::backend::BackendCapabilities::default()
         }
 
-        // Convert adapter events to McpStreamEvent
-        while let Some(event) = adapter_rx.recv().await {
-            let mcp_event = match event {
-                rig_cli_opencode::StreamEvent::Text { text } => McpStreamEvent::Text(text),
-                rig_cli_opencode::StreamEvent::Error { message } => McpStreamEvent::Error(message),
-                rig_cli_opencode::StreamEvent::Unknown(_) => continue, // skip unknowns
-            };
-
-            // Send converted event (ignore if receiver dropped)
-            let _ = tx.send(mcp_event).await;
+        async fn discover(
+            &self,
+            _explicit: Option<std::path::PathBuf>,
+        ) -> Result<std::path::PathBuf, ProviderError> {
+            Ok(std::path::PathBuf::from("namespaces"))
         }
-    });
 
-    Ok(())
-}
+        async fn run(&self, request: BackendRequest) -> Result<McpToolAgentResult, ProviderError> {
+            let names: Vec<&str> = request.mcp_configs().map(|c| c.name.as_str()).collect();
+            assert_eq!(names, ["rig_mcp", "domain"]);
+            let domain = &request.extra_mcp_configs[0];
+            assert_eq!(domain.env[rig_cli_mcp::server::SERVER_NAME_ENV], "domain");
+            std::fs::write(&domain.env["RIG_MCP_RESULT_PATH"], r#"{"done":true}"#).unwrap();
+            Ok(McpToolAgentResult::from_output(
+                String::new(),
+                String::new(),
+                0,
+                1,
+            ))
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_namespaces_get_their_own_server_and_result_file() {
+        let builder = || {
+            McpToolAgent::builder()
+                .toolset(rig::tool::ToolSet::builder().build())
+                .prompt("hello")
+                .backend(NamespaceSubmitCli)
+        };
 
-    #[test]
-    fn test_extract_version_string_simple() {
-        assert_eq!(extract_version_string("1.2.3"), "1.2.3");
+        let result = builder()
+            .namespace("domain", rig::tool::ToolSet::builder().build())
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(result.submit_result, None);
+        assert_eq!(result.namespace_results["domain"], r#"{"done":true}"#);
+
+        let err = builder()
+            .namespace("rig_mcp", rig::tool::ToolSet::builder().build())
+            .run()
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code(), "validation");
     }
 
     #[test]
-    fn test_extract_version_string_with_v_prefix() {
-        assert_eq!(extract_version_string("v1.2.3"), "1.2.3");
+    fn test_mcp_server_command_overrides_current_exe() {
+        let result_path = std::path::Path::new("/tmp/result.json");
+        let env = std::collections::HashMap::new();
+
+        let config = mcp_server_config("rig_mcp", None, &env, result_path, None, None).unwrap();
+        assert!(config.args.is_empty());
+        assert!(!config
+            .env
+            .contains_key(rig_cli_mcp::submissions::SUBMISSIONS_PATH_ENV));
+
+        let command = (
+            std::path::PathBuf::from("/usr/bin/rig-cli-provider"),
+            vec!["--stdio".to_string()],
+        );
+        let submissions_path = std::path::Path::new("/tmp/submissions.jsonl");
+        let config = mcp_server_config(
+            "rig_mcp",
+            Some(&command),
+            &env,
+            result_path,
+            None,
+            Some(submissions_path),
+        )
+        .unwrap();
+        assert_eq!(config.command, "/usr/bin/rig-cli-provider");
+        assert_eq!(config.args, ["--stdio"]);
+        assert_eq!(config.env["RIG_MCP_RESULT_PATH"], "/tmp/result.json");
+        assert_eq!(
+            config.env[rig_cli_mcp::submissions::SUBMISSIONS_PATH_ENV],
+            "/tmp/submissions.jsonl"
+        );
     }
 
-    #[test]
-    fn test_extract_version_string_with_cli_name() {
-        assert_eq!(extract_version_string("claude 1.2.3"), "1.2.3");
-        assert_eq!(extract_version_string("codex v0.91.0"), "0.91.0");
+    #[tokio::test]
+    async fn test_stream_handle_abort_cancels_run() {
+        let (handle, _tx) = stream_handle(tokio::spawn(async {
+            std::future::pending::<()>().await;
+            Err(ProviderError::Cancelled)
+        }));
+        handle.abort();
+        assert!(matches!(
+            handle.finish().await,
+            Err(ProviderError::Cancelled)
+        ));
     }
 
     #[test]
-    fn test_extract_version_string_with_prerelease() {
-        assert_eq!(extract_version_string("v0.91.0-beta.1"), "0.91.0-beta.1");
-    }
+    fn test_parse_submit_result_validates_against_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" } },
+            "required": ["name"]
+        });
+
+        assert!(parse_submit_result(None, Some(&schema)).unwrap().is_none());
+        assert_eq!(
+            parse_submit_result(Some(r#"{"name":"Ada"}"#), Some(&schema)).unwrap(),
+            Some(serde_json::json!({"name": "Ada"}))
+        );
 
-    #[test]
-    fn test_extract_version_string_unparseable_fallback() {
-        // Returns best-effort string even if not valid semver
-        let result = extract_version_string("not-a-version");
-        assert_eq!(result, "not-a-version");
+        let err = parse_submit_result(Some(r#"{"name":7}"#), Some(&schema)).unwrap_err();
+        assert_eq!(err.error_code(), "validation");
+        let err = parse_submit_result(Some("not json"), None).unwrap_err();
+        assert_eq!(err.error_code(), "validation");
     }
 
     #[test]
-    fn test_version_requirement_constants() {
-        let claude_req = claude_code_version_req();
-        assert!(claude_req.min_version < claude_req.max_tested);
-        assert_eq!(claude_req.cli_name, "Claude Code");
+    fn test_typed_result() {
+        #[derive(serde::Deserialize)]
+        struct Person {
+            name: String,
+        }
 
-        let codex_req = codex_version_req();
-        assert!(codex_req.min_version < codex_req.max_tested);
-        assert_eq!(codex_req.cli_name, "Codex");
+        let mut result = McpToolAgentResult {
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: 0,
+            duration_ms: 0,
+            submit_result: None,
+            result_json: None,
+            payload_findings: Vec::new(),
+            tool_harvest: None,
+            compliance: None,
+            failed_attempts: Vec::new(),
+            namespace_results: std::collections::BTreeMap::new(),
+            submissions: Vec::new(),
+        };
+        assert!(result.typed_result::<Person>().unwrap().is_none());
+
+        result.result_json = Some(serde_json::json!({"name": "Ada"}));
+        assert_eq!(
+            result.typed_result::<Person>().unwrap().unwrap().name,
+            "Ada"
+        );
 
-        let opencode_req = opencode_version_req();
-        assert!(opencode_req.min_version < opencode_req.max_tested);
-        assert_eq!(opencode_req.cli_name, "OpenCode");
+        result.result_json = Some(serde_json::json!({"age": 36}));
+        assert!(result.typed_result::<Person>().is_err());
     }
 
     #[test]
-    fn test_version_comparison_logic() {
-        let req = claude_code_version_req();
-        let below_min = semver::Version::new(0, 0, 1);
-        let in_range = semver::Version::new(1, 5, 0);
-        let above_max = semver::Version::new(3, 0, 0);
-
-        assert!(below_min < req.min_version);
-        assert!(in_range >= req.min_version && in_range <= req.max_tested);
-        assert!(above_max > req.max_tested);
+    fn test_allowed_tool_names_checks_adapter_naming() {
+        let tool = |name: &str| rig::completion::ToolDefinition {
+            name: name.to_string(),
+            description: String::new(),
+            parameters: serde_json::json!({}),
+        };
+
+        assert_eq!(
+            allowed_tool_names(CliAdapter::ClaudeCode, "rig_mcp", &[tool("submit")]).unwrap(),
+            ["mcp__rig_mcp__submit"]
+        );
+        assert!(allowed_tool_names(CliAdapter::Codex, "rig mcp", &[tool("submit")]).is_err());
+
+        // 52 characters: fits OpenCode's `rig_mcp_<tool>`, not `mcp__rig_mcp__<tool>`.
+        let long = tool(&"x".repeat(52));
+        assert!(
+            allowed_tool_names(CliAdapter::OpenCode, "rig_mcp", std::slice::from_ref(&long))
+                .is_ok()
+        );
+        let err = allowed_tool_names(CliAdapter::ClaudeCode, "rig_mcp", &[long]).unwrap_err();
+        assert!(err.to_string().contains("over the 64 character limit"));
     }
 }
@@ -0,0 +1,312 @@
+//! Composable hooks around MCP tool agent runs.
+//!
+//! A [`RunMiddleware`] sees every run made by an [`McpToolAgentBuilder`] or
+//! [`CliAgent`]: it can rewrite the prompt and run settings before the CLI is
+//! spawned, answer the run itself (e.g. from a cache), and inspect or replace
+//! the result afterwards. Logging, prompt redaction, caching, and metrics can
+//! all be layered this way without touching the per-adapter run functions.
+//!
+//! Middleware registered first is outermost: `before_run` hooks are called in
+//! registration order and `after_run` hooks in reverse.
+//!
+//! [`McpToolAgentBuilder`]: crate::mcp_agent::McpToolAgentBuilder
+//! [`CliAgent`]: crate::mcp_agent::CliAgent
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rig_cli_provider::errors::ProviderError;
+//! use rig_cli_provider::mcp_agent::McpToolAgentResult;
+//! use rig_cli_provider::middleware::{async_trait, RunMiddleware, RunRequest};
+//!
+//! struct LogDuration;
+//!
+//! #[async_trait]
+//! impl RunMiddleware for LogDuration {
+//!     async fn after_run(
+//!         &self,
+//!         request: &RunRequest,
+//!         result: &mut Result<McpToolAgentResult, ProviderError>,
+//!     ) {
+//!         if let Ok(r) = result {
+//!             tracing::info!(adapter = %request.adapter(), duration_ms = r.duration_ms, "run finished");
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::errors::ProviderError;
+use crate::mcp_agent::{CliAdapter, McpToolAgentResult};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Re-exported so middleware can be implemented without a direct dependency.
+pub use async_trait::async_trait;
+
+/// The resolved inputs of a single run, as seen by [`RunMiddleware`] hooks.
+///
+/// The prompts already include the workflow instructions, tool list, and
+/// payload. Edits made in [`RunMiddleware::before_run`] are used for the spawn.
+#[derive(Debug, Clone)]
+pub struct RunRequest {
+    /// User prompt sent to the CLI.
+    pub prompt: String,
+    /// System prompt sent to the CLI.
+    pub system_prompt: String,
+    /// Maximum wall-clock time for the run.
+    pub timeout: Duration,
+    /// Working directory of the CLI process.
+    pub working_dir: PathBuf,
+    adapter: CliAdapter,
+    allowed_tools: Vec<String>,
+}
+
+impl RunRequest {
+    pub(crate) const fn new(
+        adapter: CliAdapter,
+        prompt: String,
+        system_prompt: String,
+        timeout: Duration,
+        working_dir: PathBuf,
+        allowed_tools: Vec<String>,
+    ) -> Self {
+        Self {
+            prompt,
+            system_prompt,
+            timeout,
+            working_dir,
+            adapter,
+            allowed_tools,
+        }
+    }
+
+    /// The CLI adapter the run targets.
+    #[must_use]
+    pub const fn adapter(&self) -> CliAdapter {
+        self.adapter
+    }
+
+    /// MCP tool names (`mcp__<server>__<tool>`) the CLI may call.
+    #[must_use]
+    pub fn allowed_tools(&self) -> &[String] {
+        &self.allowed_tools
+    }
+}
+
+/// Hooks invoked around every MCP tool agent run.
+///
+/// Both hooks default to no-ops, so implementors only override what they need.
+#[async_trait]
+pub trait RunMiddleware: Send + Sync {
+    /// Called before the CLI is spawned.
+    ///
+    /// Return `Ok(Some(result))` to answer the run without spawning the CLI;
+    /// later middleware and the CLI itself are skipped. Returning an error
+    /// aborts the run; the layers before this one still see it in
+    /// [`after_run`](Self::after_run).
+    ///
+    /// # Errors
+    /// Any error returned here is handed to the `after_run` hooks of the
+    /// earlier layers, then returned from the run.
+    async fn before_run(
+        &self,
+        request: &mut RunRequest,
+    ) -> Result<Option<McpToolAgentResult>, ProviderError> {
+        let _ = request;
+        Ok(None)
+    }
+
+    /// Called once the run has finished, successfully or not, including when
+    /// a later layer's [`before_run`](Self::before_run) failed.
+    ///
+    /// The result may be replaced, e.g. to redact output or to turn a
+    /// failure into a fallback answer. Not called for streaming runs,
+    /// whose output is delivered incrementally.
+    async fn after_run(
+        &self,
+        request: &RunRequest,
+        result: &mut Result<McpToolAgentResult, ProviderError>,
+    ) {
+        let _ = (request, result);
+    }
+}
+
+/// Ordered list of middleware attached to a builder.
+#[derive(Clone, Default)]
+pub(crate) struct MiddlewareStack {
+    layers: Vec<Arc<dyn RunMiddleware>>,
+}
+
+impl MiddlewareStack {
+    pub(crate) fn push(&mut self, middleware: Arc<dyn RunMiddleware>) {
+        self.layers.push(middleware);
+    }
+
+    /// Runs `before_run` hooks in order.
+    ///
+    /// Returns the number of layers whose hook succeeded (and so must see
+    /// `after_run`), along with the answer of the layer that answered the
+    /// run, if any, or the error of the layer that failed.
+    pub(crate) async fn before(
+        &self,
+        request: &mut RunRequest,
+    ) -> (usize, Result<Option<McpToolAgentResult>, ProviderError>) {
+        for (i, layer) in self.layers.iter().enumerate() {
+            match layer.before_run(request).await {
+                Ok(None) => {}
+                Ok(Some(result)) => return (i + 1, Ok(Some(result))),
+                Err(e) => return (i, Err(e)),
+            }
+        }
+        (self.layers.len(), Ok(None))
+    }
+
+    /// Runs `after_run` hooks for the first `entered` layers, innermost first.
+    pub(crate) async fn after(
+        &self,
+        entered: usize,
+        request: &RunRequest,
+        result: &mut Result<McpToolAgentResult, ProviderError>,
+    ) {
+        for layer in self.layers[..entered].iter().rev() {
+            layer.after_run(request, result).await;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Recorder {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+        answer: bool,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl RunMiddleware for Recorder {
+        async fn before_run(
+            &self,
+            request: &mut RunRequest,
+        ) -> Result<Option<McpToolAgentResult>, ProviderError> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("before {}", self.name));
+            request.prompt.push_str(self.name);
+            if self.fail {
+                return Err(ProviderError::Cancelled);
+            }
+            Ok(self.answer.then(|| McpToolAgentResult {
+                stdout: request.prompt.clone(),
+                stderr: String::new(),
+                exit_code: 0,
+                duration_ms: 0,
+                submit_result: None,
+                result_json: None,
+                payload_findings: Vec::new(),
+                tool_harvest: None,
+                compliance: None,
+                failed_attempts: Vec::new(),
+                namespace_results: std::collections::BTreeMap::new(),
+                submissions: Vec::new(),
+            }))
+        }
+
+        async fn after_run(
+            &self,
+            _request: &RunRequest,
+            _result: &mut Result<McpToolAgentResult, ProviderError>,
+        ) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("after {}", self.name));
+        }
+    }
+
+    fn request() -> RunRequest {
+        RunRequest::new(
+            CliAdapter::ClaudeCode,
+            String::new(),
+            String::new(),
+            Duration::from_secs(1),
+            PathBuf::from("."),
+            Vec::new(),
+        )
+    }
+
+    fn stack(
+        log: &Arc<Mutex<Vec<String>>>,
+        answer_at: Option<&str>,
+        fail_at: Option<&str>,
+    ) -> MiddlewareStack {
+        let mut stack = MiddlewareStack::default();
+        for name in ["a", "b", "c"] {
+            stack.push(Arc::new(Recorder {
+                name,
+                log: Arc::clone(log),
+                answer: answer_at == Some(name),
+                fail: fail_at == Some(name),
+            }));
+        }
+        stack
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_as_onion() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let stack = stack(&log, None, None);
+        let mut req = request();
+
+        let (entered, answered) = stack.before(&mut req).await;
+        let answered = answered.unwrap();
+        assert_eq!(entered, 3);
+        assert!(answered.is_none());
+        assert_eq!(req.prompt, "abc");
+
+        let mut result = Err(ProviderError::Cancelled);
+        stack.after(entered, &req, &mut result).await;
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["before a", "before b", "before c", "after c", "after b", "after a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_short_circuit_skips_inner_layers() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let stack = stack(&log, Some("b"), None);
+        let mut req = request();
+
+        let (entered, answered) = stack.before(&mut req).await;
+        assert_eq!(entered, 2);
+        assert_eq!(answered.unwrap().unwrap().stdout, "ab");
+
+        let mut result = Err(ProviderError::Cancelled);
+        stack.after(entered, &req, &mut result).await;
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["before a", "before b", "after b", "after a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_before_unwinds_outer_layers() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let stack = stack(&log, None, Some("b"));
+        let mut req = request();
+
+        let (entered, answered) = stack.before(&mut req).await;
+        assert_eq!(entered, 1);
+        let mut result = answered.map(|_| unreachable!());
+        stack.after(entered, &req, &mut result).await;
+        assert!(matches!(result, Err(ProviderError::Cancelled)));
+        assert_eq!(*log.lock().unwrap(), ["before a", "before b", "after a"]);
+    }
+}
@@ -0,0 +1,173 @@
+//! The model each adapter runs when the caller does not pick one.
+//!
+//! [`DefaultModels`] can be built in code, read from a config file with
+//! [`DefaultModels::load`], and overridden per adapter from the
+//! environment with [`DefaultModels::with_env`], so a deployment can switch
+//! models without a code change:
+//!
+//! ```toml
+//! claude = "sonnet"
+//! codex = "gpt-5-codex"
+//! opencode = "opencode/big-pickle"
+//! ```
+//!
+//! An adapter without a default model runs whatever its CLI is configured
+//! to use.
+
+use crate::errors::ProviderError;
+use crate::mcp_agent::CliAdapter;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Environment variable overriding the Claude Code default model.
+pub const CLAUDE_MODEL_ENV: &str = "RIG_CLI_CLAUDE_MODEL";
+/// Environment variable overriding the Codex default model.
+pub const CODEX_MODEL_ENV: &str = "RIG_CLI_CODEX_MODEL";
+/// Environment variable overriding the `OpenCode` default model.
+pub const OPENCODE_MODEL_ENV: &str = "RIG_CLI_OPENCODE_MODEL";
+
+/// Default model of each built-in adapter.
+///
+/// Missing fields keep their [`Default`] values; unknown fields are rejected
+/// so typos do not silently fall back to defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DefaultModels {
+    /// Model passed to Claude Code. Default: `None` (the CLI's own default).
+    pub claude: Option<String>,
+    /// Model passed to Codex. Default: `None` (the CLI's own default).
+    pub codex: Option<String>,
+    /// Model passed to `OpenCode`. Default: `opencode/big-pickle`.
+    pub opencode: Option<String>,
+}
+
+impl Default for DefaultModels {
+    fn default() -> Self {
+        Self {
+            claude: None,
+            codex: None,
+            opencode: Some("opencode/big-pickle".to_string()),
+        }
+    }
+}
+
+impl DefaultModels {
+    /// The built-in defaults with the environment overrides applied.
+    #[must_use]
+    pub fn from_env() -> Self {
+        Self::default().with_env()
+    }
+
+    /// Reads a config file, parsing it as JSON if its extension is `.json`
+    /// and as TOML otherwise.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::McpConfig`] if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, ProviderError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ProviderError::mcp_config(format!("failed to read {}", path.display()), e)
+        })?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        Self::parse(&contents, is_json).map_err(|e| {
+            ProviderError::mcp_config(format!("failed to parse {}", path.display()), e)
+        })
+    }
+
+    /// Parses config file contents.
+    fn parse(contents: &str, is_json: bool) -> Result<Self, String> {
+        if is_json {
+            serde_json::from_str(contents).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(contents).map_err(|e| e.to_string())
+        }
+    }
+
+    /// Replaces the default model of every adapter whose `RIG_CLI_*_MODEL`
+    /// variable is set and not empty.
+    #[must_use]
+    pub fn with_env(self) -> Self {
+        self.with_overrides(|name| std::env::var(name).ok())
+    }
+
+    /// Applies the overrides `lookup` returns for each adapter's variable.
+    fn with_overrides(mut self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        for (adapter, name) in [
+            (CliAdapter::ClaudeCode, CLAUDE_MODEL_ENV),
+            (CliAdapter::Codex, CODEX_MODEL_ENV),
+            (CliAdapter::OpenCode, OPENCODE_MODEL_ENV),
+        ] {
+            if let Some(model) = lookup(name).filter(|model| !model.is_empty()) {
+                self = self.with_model(adapter, model);
+            }
+        }
+        self
+    }
+
+    /// Sets the default model of `adapter`.
+    ///
+    /// [`CliAdapter::Custom`] backends pick their own model, so it is ignored.
+    #[must_use]
+    pub fn with_model(mut self, adapter: CliAdapter, model: impl Into<String>) -> Self {
+        let slot = match adapter {
+            CliAdapter::ClaudeCode => &mut self.claude,
+            CliAdapter::Codex => &mut self.codex,
+            CliAdapter::OpenCode => &mut self.opencode,
+            CliAdapter::Custom(_) => return self,
+        };
+        *slot = Some(model.into());
+        self
+    }
+
+    /// The default model of `adapter`, if it has one.
+    #[must_use]
+    pub fn get(&self, adapter: CliAdapter) -> Option<&str> {
+        match adapter {
+            CliAdapter::ClaudeCode => self.claude.as_deref(),
+            CliAdapter::Codex => self.codex.as_deref(),
+            CliAdapter::OpenCode => self.opencode.as_deref(),
+            CliAdapter::Custom(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_file_keeps_unset_defaults() {
+        let models = DefaultModels::parse("claude = \"sonnet\"\n", false).unwrap();
+        assert_eq!(models.get(CliAdapter::ClaudeCode), Some("sonnet"));
+        assert_eq!(models.get(CliAdapter::Codex), None);
+        assert_eq!(
+            models.get(CliAdapter::OpenCode),
+            Some("opencode/big-pickle")
+        );
+
+        let models = DefaultModels::parse(r#"{"codex": "o3"}"#, true).unwrap();
+        assert_eq!(models.get(CliAdapter::Codex), Some("o3"));
+
+        assert!(DefaultModels::parse("clade = \"sonnet\"\n", false).is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_replace_non_empty_values() {
+        let models = DefaultModels::default()
+            .with_model(CliAdapter::Codex, "o3")
+            .with_overrides(|name| match name {
+                OPENCODE_MODEL_ENV => Some("anthropic/claude-sonnet-4".to_string()),
+                CODEX_MODEL_ENV => Some(String::new()),
+                _ => None,
+            });
+        assert_eq!(models.get(CliAdapter::ClaudeCode), None);
+        assert_eq!(models.get(CliAdapter::Codex), Some("o3"));
+        assert_eq!(
+            models.get(CliAdapter::OpenCode),
+            Some("anthropic/claude-sonnet-4")
+        );
+        assert_eq!(models.get(CliAdapter::Custom("mock")), None);
+    }
+}
@@ -0,0 +1,440 @@
+//! Rendering a directory tree into a payload.
+//!
+//! [`PayloadBuilder`] walks a directory and renders the text files it finds
+//! as one `<file>` element each, ready for `with_payload` on any client or
+//! [`McpToolAgentBuilder::payload`], which wrap it in `<context>` tags:
+//!
+//! ```text
+//! <file path="src/lib.rs" bytes="1532">
+//! ...
+//! </file>
+//! <file path="data/large.csv" bytes="900000" truncated="65536">
+//! ...
+//! </file>
+//! <file path="assets/logo.png" bytes="20480" omitted="binary"/>
+//! ```
+//!
+//! Files are visited in path order. `.gitignore`, `.ignore` and
+//! `.git/info/exclude` rules are honored, hidden files are skipped, and
+//! include/exclude globs narrow the walk further. Each file is cut to
+//! [`max_file_bytes`](PayloadBuilder::max_file_bytes) and the whole payload
+//! to [`max_total_bytes`](PayloadBuilder::max_total_bytes); files that are
+//! not UTF-8 text, or that no longer fit, are listed without contents so
+//! the agent still knows they exist.
+//!
+//! ```no_run
+//! use rig_cli_provider::payload::PayloadBuilder;
+//!
+//! # fn example() -> Result<(), rig_cli_provider::errors::ProviderError> {
+//! let payload = PayloadBuilder::new("./my-repo")
+//!     .include("*.rs")
+//!     .include("Cargo.toml")
+//!     .exclude("target/**")
+//!     .build()?;
+//! println!("{} files", payload.files.len());
+//! let context: String = payload.into();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`McpToolAgentBuilder::payload`]: crate::mcp_agent::McpToolAgentBuilder::payload
+
+use crate::errors::ProviderError;
+use std::fmt::Write as _;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Default per-file cap: 64 KiB.
+pub const DEFAULT_MAX_FILE_BYTES: usize = 64 * 1024;
+
+/// Default cap on the contents of all files together: 512 KiB.
+pub const DEFAULT_MAX_TOTAL_BYTES: usize = 512 * 1024;
+
+/// Bytes inspected for a NUL byte before a file is treated as binary.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+/// Builder rendering a directory tree into a payload.
+#[derive(Debug, Clone)]
+pub struct PayloadBuilder {
+    root: PathBuf,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    gitignore: bool,
+    hidden: bool,
+    max_file_bytes: usize,
+    max_total_bytes: usize,
+}
+
+impl PayloadBuilder {
+    /// Starts a payload of the files under `root`.
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            gitignore: true,
+            hidden: false,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            max_total_bytes: DEFAULT_MAX_TOTAL_BYTES,
+        }
+    }
+
+    /// Only renders files matching `glob`, relative to the root. May be
+    /// called repeatedly; a file matching any include glob is rendered.
+    #[must_use]
+    pub fn include(mut self, glob: impl Into<String>) -> Self {
+        self.include.push(glob.into());
+        self
+    }
+
+    /// Skips files and directories matching `glob`, relative to the root.
+    /// Exclusions win over inclusions.
+    #[must_use]
+    pub fn exclude(mut self, glob: impl Into<String>) -> Self {
+        self.exclude.push(glob.into());
+        self
+    }
+
+    /// Sets whether `.gitignore`, `.ignore` and `.git/info/exclude` rules
+    /// are honored (default: true).
+    #[must_use]
+    pub const fn gitignore(mut self, respect: bool) -> Self {
+        self.gitignore = respect;
+        self
+    }
+
+    /// Sets whether hidden files and directories are rendered (default:
+    /// false).
+    #[must_use]
+    pub const fn hidden(mut self, include: bool) -> Self {
+        self.hidden = include;
+        self
+    }
+
+    /// Sets the most bytes of any one file that are rendered (default:
+    /// [`DEFAULT_MAX_FILE_BYTES`]).
+    #[must_use]
+    pub const fn max_file_bytes(mut self, max: usize) -> Self {
+        self.max_file_bytes = max;
+        self
+    }
+
+    /// Sets the most file bytes the whole payload renders (default:
+    /// [`DEFAULT_MAX_TOTAL_BYTES`]).
+    #[must_use]
+    pub const fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = max;
+        self
+    }
+
+    /// Walks the tree and renders it.
+    ///
+    /// Files that cannot be read are listed as
+    /// [`PayloadFileStatus::Unreadable`] rather than failing the build.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Validation`] for an invalid glob and
+    /// [`ProviderError::Io`] if the root does not exist.
+    pub fn build(&self) -> Result<RenderedPayload, ProviderError> {
+        std::fs::metadata(&self.root)?;
+
+        let mut overrides = ignore::overrides::OverrideBuilder::new(&self.root);
+        let globs = self
+            .include
+            .iter()
+            .cloned()
+            .chain(self.exclude.iter().map(|glob| format!("!{glob}")));
+        for glob in globs {
+            overrides
+                .add(&glob)
+                .map_err(|e| ProviderError::Validation(format!("invalid payload glob: {e}")))?;
+        }
+        let overrides = overrides
+            .build()
+            .map_err(|e| ProviderError::Validation(format!("invalid payload glob: {e}")))?;
+
+        let walk = ignore::WalkBuilder::new(&self.root)
+            .hidden(!self.hidden)
+            .git_ignore(self.gitignore)
+            .git_exclude(self.gitignore)
+            .ignore(self.gitignore)
+            .git_global(false)
+            .parents(self.gitignore)
+            .require_git(false)
+            .overrides(overrides)
+            .sort_by_file_name(std::cmp::Ord::cmp)
+            .build();
+
+        let mut payload = RenderedPayload::default();
+        let mut budget = self.max_total_bytes;
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Skipping unreadable payload entry");
+                    continue;
+                }
+            };
+            if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+                continue;
+            }
+            let path = entry.path();
+            let relative = display_path(path.strip_prefix(&self.root).unwrap_or(path));
+            let bytes = entry.metadata().map_or(0, |meta| meta.len());
+
+            let (status, text) = if budget == 0 {
+                (PayloadFileStatus::OverBudget, None)
+            } else {
+                match read_text(path, self.max_file_bytes.min(budget)) {
+                    Ok(Some(text)) => {
+                        budget -= text.len();
+                        let status = if (text.len() as u64) < bytes {
+                            PayloadFileStatus::Truncated
+                        } else {
+                            PayloadFileStatus::Included
+                        };
+                        (status, Some(text))
+                    }
+                    Ok(None) => (PayloadFileStatus::Binary, None),
+                    Err(e) => {
+                        tracing::warn!(path = %path.display(), error = %e, "Failed to read payload file");
+                        (PayloadFileStatus::Unreadable, None)
+                    }
+                }
+            };
+            match text {
+                Some(text) => payload.push_contents(&relative, bytes, status, &text),
+                None => payload.push_omitted(&relative, bytes, status),
+            }
+            payload.files.push(PayloadFile {
+                path: relative,
+                bytes,
+                status,
+            });
+        }
+        Ok(payload)
+    }
+}
+
+/// A rendered directory tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderedPayload {
+    /// The `<file>` elements, ready to pass as a payload.
+    pub text: String,
+    /// Every file visited, in the order rendered.
+    pub files: Vec<PayloadFile>,
+}
+
+impl From<RenderedPayload> for String {
+    fn from(payload: RenderedPayload) -> Self {
+        payload.text
+    }
+}
+
+impl RenderedPayload {
+    fn push_contents(&mut self, path: &str, bytes: u64, status: PayloadFileStatus, text: &str) {
+        let _ = write!(
+            self.text,
+            "<file path=\"{}\" bytes=\"{bytes}\"",
+            escape_attr(path)
+        );
+        if status == PayloadFileStatus::Truncated {
+            let _ = write!(self.text, " truncated=\"{}\"", text.len());
+        }
+        self.text.push_str(">\n");
+        self.text.push_str(text);
+        if !text.ends_with('\n') {
+            self.text.push('\n');
+        }
+        self.text.push_str("</file>\n");
+    }
+
+    fn push_omitted(&mut self, path: &str, bytes: u64, status: PayloadFileStatus) {
+        let _ = writeln!(
+            self.text,
+            "<file path=\"{}\" bytes=\"{bytes}\" omitted=\"{}\"/>",
+            escape_attr(path),
+            status.as_str()
+        );
+    }
+}
+
+/// One file of a [`RenderedPayload`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadFile {
+    /// Path relative to the root, with `/` separators.
+    pub path: String,
+    /// Size of the file on disk.
+    pub bytes: u64,
+    /// How much of the file was rendered.
+    pub status: PayloadFileStatus,
+}
+
+/// How much of a file a [`RenderedPayload`] contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadFileStatus {
+    /// Rendered in full.
+    Included,
+    /// Rendered up to the per-file or total cap.
+    Truncated,
+    /// Listed without contents: not UTF-8 text.
+    Binary,
+    /// Listed without contents: the total cap was already reached.
+    OverBudget,
+    /// Listed without contents: reading it failed.
+    Unreadable,
+}
+
+impl PayloadFileStatus {
+    /// The value of the `omitted` attribute for a file listed without
+    /// contents.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Included => "included",
+            Self::Truncated => "truncated",
+            Self::Binary => "binary",
+            Self::OverBudget => "over_budget",
+            Self::Unreadable => "unreadable",
+        }
+    }
+}
+
+/// Reads up to `limit` bytes of `path` as text, cut back to a character
+/// boundary. `None` if the file looks binary.
+fn read_text(path: &Path, limit: usize) -> std::io::Result<Option<String>> {
+    let mut buf = Vec::new();
+    std::fs::File::open(path)?
+        .take(limit as u64)
+        .read_to_end(&mut buf)?;
+    if buf[..buf.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return Ok(None);
+    }
+    match String::from_utf8(buf) {
+        Ok(text) => Ok(Some(text)),
+        // Only the cut split a character; keep what precedes it.
+        Err(e) if e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut buf = e.into_bytes();
+            buf.truncate(valid);
+            Ok(String::from_utf8(buf).ok())
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+fn display_path(path: &Path) -> String {
+    path.components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub(crate) fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub fn demo() {}\n").unwrap();
+        std::fs::write(root.join("src/logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+        std::fs::write(root.join("target/out.rs"), "built").unwrap();
+        dir
+    }
+
+    fn paths(payload: &RenderedPayload) -> Vec<&str> {
+        payload.files.iter().map(|f| f.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_build_honors_gitignore_and_hidden() {
+        let dir = tree();
+        let payload = PayloadBuilder::new(dir.path()).build().unwrap();
+
+        assert_eq!(
+            paths(&payload),
+            ["Cargo.toml", "src/lib.rs", "src/logo.png"]
+        );
+        assert!(payload
+            .text
+            .contains("<file path=\"src/lib.rs\" bytes=\"17\">\npub fn demo() {}\n</file>\n"));
+        assert!(payload
+            .text
+            .contains("<file path=\"src/logo.png\" bytes=\"6\" omitted=\"binary\"/>"));
+
+        let all = PayloadBuilder::new(dir.path())
+            .gitignore(false)
+            .hidden(true)
+            .build()
+            .unwrap();
+        assert!(paths(&all).contains(&".gitignore"));
+        assert!(paths(&all).contains(&"target/out.rs"));
+    }
+
+    #[test]
+    fn test_build_applies_globs() {
+        let dir = tree();
+        let payload = PayloadBuilder::new(dir.path())
+            .include("*.rs")
+            .include("*.toml")
+            .exclude("Cargo.toml")
+            .build()
+            .unwrap();
+        assert_eq!(paths(&payload), ["src/lib.rs"]);
+
+        let err = PayloadBuilder::new(dir.path())
+            .include("[")
+            .build()
+            .unwrap_err();
+        assert_eq!(err.error_code(), "validation");
+    }
+
+    #[test]
+    fn test_build_caps_file_and_total_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "é".repeat(10)).unwrap();
+        std::fs::write(dir.path().join("b.txt"), "bbbb").unwrap();
+        std::fs::write(dir.path().join("c.txt"), "cccc").unwrap();
+
+        let payload = PayloadBuilder::new(dir.path())
+            .max_file_bytes(5)
+            .max_total_bytes(8)
+            .build()
+            .unwrap();
+        let statuses: Vec<_> = payload.files.iter().map(|f| f.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                PayloadFileStatus::Truncated,
+                PayloadFileStatus::Included,
+                PayloadFileStatus::OverBudget
+            ]
+        );
+        assert!(payload
+            .text
+            .contains("<file path=\"a.txt\" bytes=\"20\" truncated=\"4\">\néé\n</file>"));
+        assert!(payload.text.contains("omitted=\"over_budget\""));
+    }
+
+    #[test]
+    fn test_missing_root_is_an_error() {
+        let err = PayloadBuilder::new("/nonexistent/payload/root")
+            .build()
+            .unwrap_err();
+        assert_eq!(err.error_code(), "io");
+    }
+}
@@ -0,0 +1,582 @@
+//! A pool of warm, interactive Claude Code processes.
+//!
+//! Every [`McpToolAgent`](crate::mcp_agent::McpToolAgent) run starts a fresh
+//! CLI, and booting node, checking auth and handshaking with MCP servers
+//! adds seconds to each call. A [`WarmPool`] starts its processes ahead of
+//! time in stream-json input mode (`--input-format stream-json`), where one
+//! process answers prompts written to its stdin for as long as it lives.
+//!
+//! [`WarmPool::lease`] hands out an idle process for one request; prompts
+//! sent through the same [`Lease`] continue one conversation. When the lease
+//! is dropped the process is reset with `/clear` in the background and
+//! returned to the pool. A process that failed mid-prompt, could not be
+//! reset, reached [`max_leases`](WarmPoolConfig::max_leases) or sat idle for
+//! [`idle_timeout`](WarmPoolConfig::idle_timeout) is killed and replaced by
+//! a fresh one, so the pool stays warm:
+//!
+//! ```no_run
+//! use rig_cli_provider::pool::{WarmPool, WarmPoolConfig};
+//!
+//! # async fn example() -> Result<(), rig_cli_provider::errors::ProviderError> {
+//! let pool = WarmPool::start(WarmPoolConfig {
+//!     size: 4,
+//!     args: vec!["--model".to_string(), "sonnet".to_string()],
+//!     ..WarmPoolConfig::default()
+//! })?;
+//! let mut lease = pool.lease().await?;
+//! let answer = lease.prompt("Summarize the release notes").await?;
+//! println!("{}", answer.result);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::ProviderError;
+use crate::events::StreamEvent;
+use crate::mcp_agent::CliAdapter;
+use rig_cli_claude::TimeoutStage;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Message sent to a returned process to drop its conversation.
+const RESET_COMMAND: &str = "/clear";
+
+/// Settings of a [`WarmPool`].
+#[derive(Debug, Clone)]
+pub struct WarmPoolConfig {
+    /// Number of processes kept warm, and the most leased at once.
+    pub size: usize,
+    /// Claude CLI to run. Default: discovered as for other runs.
+    pub cli_path: Option<PathBuf>,
+    /// Extra arguments for every process, such as `--model` or
+    /// `--mcp-config`, added after the stream-json flags.
+    pub args: Vec<String>,
+    /// Extra environment variables for every process.
+    pub env: Vec<(String, String)>,
+    /// Working directory of every process. Default: the current one.
+    pub working_dir: Option<PathBuf>,
+    /// Longest a prompt may take to produce its result.
+    pub prompt_timeout: Duration,
+    /// Longest a returned process may take to reset before it is replaced.
+    pub reset_timeout: Duration,
+    /// Idle processes older than this are replaced.
+    pub idle_timeout: Duration,
+    /// Leases a process serves before it is replaced.
+    pub max_leases: u32,
+}
+
+impl Default for WarmPoolConfig {
+    fn default() -> Self {
+        Self {
+            size: 2,
+            cli_path: None,
+            args: Vec::new(),
+            env: Vec::new(),
+            working_dir: None,
+            prompt_timeout: Duration::from_secs(300),
+            reset_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(600),
+            max_leases: 50,
+        }
+    }
+}
+
+/// The outcome of one prompt sent through a [`Lease`].
+#[derive(Debug, Clone)]
+pub struct WarmResponse {
+    /// Text of the CLI's `result` message.
+    pub result: String,
+    /// Whether the CLI reported the turn as failed.
+    pub is_error: bool,
+    /// Session ID reported by the CLI, if any.
+    pub session_id: Option<String>,
+    /// Events of the turn, in order.
+    pub events: Vec<StreamEvent>,
+    /// Wall-clock duration of the prompt in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// Warm Claude Code processes leased out one request at a time.
+///
+/// Clones share the pool. Its processes are killed when the last clone and
+/// the last [`Lease`] are dropped.
+#[derive(Clone)]
+pub struct WarmPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    config: WarmPoolConfig,
+    cli_path: PathBuf,
+    idle: Mutex<Vec<WarmProcess>>,
+    /// One permit per process that is leased or being returned.
+    permits: Arc<Semaphore>,
+}
+
+impl WarmPool {
+    /// Discovers the CLI and starts [`size`](WarmPoolConfig::size) processes.
+    ///
+    /// Must be called within a Tokio runtime: it also starts a background
+    /// task there that replaces idle processes once they exceed the idle
+    /// timeout or exit, and tops the pool back up to its size.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Validation`] for a zero size,
+    /// [`ProviderError::Discovery`] if the CLI cannot be found, and
+    /// [`ProviderError::Spawn`] if a process cannot be started.
+    pub fn start(config: WarmPoolConfig) -> Result<Self, ProviderError> {
+        if config.size == 0 {
+            return Err(ProviderError::Validation(
+                "a warm pool needs at least one process".to_string(),
+            ));
+        }
+        let cli_path = rig_cli_claude::discover_claude(config.cli_path.clone())
+            .map_err(|e| ProviderError::discovery(CliAdapter::ClaudeCode, e))?;
+        let inner = Arc::new(PoolInner {
+            permits: Arc::new(Semaphore::new(config.size)),
+            idle: Mutex::new(Vec::with_capacity(config.size)),
+            cli_path,
+            config,
+        });
+        let processes = (0..inner.config.size)
+            .map(|_| inner.spawn())
+            .collect::<Result<Vec<_>, _>>()?;
+        inner.lock_idle().extend(processes);
+
+        let interval = (inner.config.idle_timeout / 2).max(Duration::from_secs(1));
+        tokio::spawn(maintain(Arc::downgrade(&inner), interval));
+        tracing::debug!(
+            event = "warm_pool_started",
+            size = inner.config.size,
+            cli = %inner.cli_path.display(),
+            "Warm pool started"
+        );
+        Ok(Self { inner })
+    }
+
+    /// Leases an idle process, waiting while all of them are leased.
+    ///
+    /// A process is started on the spot if none is idle, which happens only
+    /// while returned processes are still being replaced.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Spawn`] if a process has to be started and
+    /// cannot be.
+    pub async fn lease(&self) -> Result<Lease, ProviderError> {
+        let permit = Arc::clone(&self.inner.permits)
+            .acquire_owned()
+            .await
+            .map_err(|_| ProviderError::Cancelled)?;
+        let process = loop {
+            let popped = self.inner.lock_idle().pop();
+            match popped {
+                Some(mut process) => {
+                    if process.is_alive() {
+                        break process;
+                    }
+                }
+                None => break self.inner.spawn()?,
+            }
+        };
+        Ok(Lease {
+            process: Some(process),
+            healthy: true,
+            pool: Arc::clone(&self.inner),
+            permit: Some(permit),
+        })
+    }
+
+    /// Number of processes idle in the pool.
+    #[must_use]
+    pub fn idle(&self) -> usize {
+        self.inner.lock_idle().len()
+    }
+}
+
+impl PoolInner {
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, Vec<WarmProcess>> {
+        self.idle.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn spawn(&self) -> Result<WarmProcess, ProviderError> {
+        let mut command = Command::new(&self.cli_path);
+        command
+            .args([
+                "--print",
+                "--input-format",
+                "stream-json",
+                "--output-format",
+                "stream-json",
+                "--verbose",
+            ])
+            .args(&self.config.args)
+            .envs(self.config.env.iter().cloned())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        if let Some(dir) = &self.config.working_dir {
+            command.current_dir(dir);
+        }
+        let mut child = command.spawn().map_err(|source| ProviderError::Spawn {
+            stage: "start warm Claude process",
+            source,
+        })?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(ProviderError::Spawn {
+                stage: "start warm Claude process",
+                source: std::io::Error::other("stdio was not piped"),
+            });
+        };
+        Ok(WarmProcess {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            leases: 0,
+            idle_since: Instant::now(),
+        })
+    }
+
+    /// Resets a returned process and puts it back, or replaces it.
+    async fn give_back(
+        self: Arc<Self>,
+        mut process: WarmProcess,
+        healthy: bool,
+        _permit: OwnedSemaphorePermit,
+    ) {
+        process.leases += 1;
+        let reusable = healthy
+            && process.leases < self.config.max_leases
+            && process.reset(self.config.reset_timeout).await.is_ok();
+        let process = if reusable {
+            process
+        } else {
+            tracing::debug!(
+                event = "warm_process_recycled",
+                pid = process.child.id(),
+                leases = process.leases,
+                healthy,
+                "Replacing warm Claude process"
+            );
+            drop(process);
+            match self.spawn() {
+                Ok(process) => process,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Could not replace warm Claude process");
+                    return;
+                }
+            }
+        };
+        self.lock_idle().push(process);
+    }
+
+    /// Replaces idle processes that exited or exceeded the idle timeout,
+    /// and starts any missing ones.
+    fn recycle_idle(&self) {
+        let idle_timeout = self.config.idle_timeout;
+        let missing = {
+            let mut idle = self.lock_idle();
+            idle.retain_mut(|process| {
+                process.is_alive() && process.idle_since.elapsed() < idle_timeout
+            });
+            self.permits.available_permits().saturating_sub(idle.len())
+        };
+        for _ in 0..missing {
+            match self.spawn() {
+                Ok(process) => self.lock_idle().push(process),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Could not replace idle warm Claude process");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Replaces stale idle processes every `interval` until the pool is gone.
+async fn maintain(pool: Weak<PoolInner>, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.tick().await;
+    loop {
+        ticks.tick().await;
+        let Some(pool) = pool.upgrade() else {
+            return;
+        };
+        pool.recycle_idle();
+    }
+}
+
+/// One warm process, with the ends of its pipes.
+struct WarmProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    leases: u32,
+    idle_since: Instant,
+}
+
+impl WarmProcess {
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Writes `text` as a user message and reads the turn up to its
+    /// `result` message, which is returned.
+    async fn turn(
+        &mut self,
+        text: &str,
+        events: &mut Vec<StreamEvent>,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let message = serde_json::json!({
+            "type": "user",
+            "message": { "role": "user", "content": [{ "type": "text", "text": text }] },
+        });
+        let mut line = message.to_string();
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|source| ProviderError::Spawn {
+                stage: "write to warm Claude process",
+                source,
+            })?;
+        self.stdin
+            .flush()
+            .await
+            .map_err(|source| ProviderError::Spawn {
+                stage: "write to warm Claude process",
+                source,
+            })?;
+
+        loop {
+            let line = self
+                .stdout
+                .next_line()
+                .await
+                .map_err(|source| ProviderError::Spawn {
+                    stage: "read from warm Claude process",
+                    source,
+                })?
+                .ok_or_else(|| ProviderError::Spawn {
+                    stage: "read from warm Claude process",
+                    source: std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "the process exited before its result",
+                    ),
+                })?;
+            let Ok(value) = serde_json::from_str::<serde_json::Value>(&line) else {
+                continue;
+            };
+            if value.get("type").and_then(serde_json::Value::as_str) == Some("result") {
+                return Ok(value);
+            }
+            events.extend(
+                rig_cli_claude::extract_v2_events(&value)
+                    .into_iter()
+                    .map(StreamEvent::from),
+            );
+        }
+    }
+
+    /// Drops the conversation so the next lease starts fresh.
+    async fn reset(&mut self, timeout: Duration) -> Result<(), ProviderError> {
+        let mut discarded = Vec::new();
+        tokio::time::timeout(timeout, self.turn(RESET_COMMAND, &mut discarded))
+            .await
+            .map_err(|_| ProviderError::Cancelled)??;
+        self.idle_since = Instant::now();
+        Ok(())
+    }
+}
+
+/// A warm process leased from a [`WarmPool`].
+///
+/// Dropping the lease returns the process; it is reset and put back in the
+/// background, on the current Tokio runtime.
+pub struct Lease {
+    process: Option<WarmProcess>,
+    /// Cleared while a prompt is in flight, so a prompt that fails or is
+    /// cancelled midway gets the process replaced instead of reused.
+    healthy: bool,
+    pool: Arc<PoolInner>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Lease {
+    /// OS process ID of the leased process.
+    #[must_use]
+    pub fn pid(&self) -> Option<u32> {
+        self.process.as_ref().and_then(|process| process.child.id())
+    }
+
+    /// Sends `prompt` and waits for its result. Later prompts on the same
+    /// lease continue the conversation.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Timeout`] if no result arrives within the
+    /// prompt timeout, and [`ProviderError::Spawn`] if the process cannot be
+    /// written to or exits. The process is replaced after either.
+    pub async fn prompt(&mut self, prompt: &str) -> Result<WarmResponse, ProviderError> {
+        if !self.healthy {
+            return Err(ProviderError::Validation(
+                "the leased process failed an earlier prompt".to_string(),
+            ));
+        }
+        let Some(process) = self.process.as_mut() else {
+            return Err(ProviderError::Cancelled);
+        };
+        self.healthy = false;
+        let start = Instant::now();
+        let timeout = self.pool.config.prompt_timeout;
+        let mut events = Vec::new();
+        let result = tokio::time::timeout(timeout, process.turn(prompt, &mut events))
+            .await
+            .map_err(|_| ProviderError::Timeout {
+                adapter: CliAdapter::ClaudeCode,
+                stage: TimeoutStage::Total,
+                elapsed: start.elapsed(),
+                partial_output: String::new(),
+                source: "no result from the warm Claude process".into(),
+            })??;
+        self.healthy = true;
+
+        let text = |key: &str| {
+            result
+                .get(key)
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        };
+        Ok(WarmResponse {
+            result: text("result").unwrap_or_default(),
+            is_error: result
+                .get("is_error")
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or(false),
+            session_id: text("session_id"),
+            events,
+            duration_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+        })
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let (Some(process), Some(permit)) = (self.process.take(), self.permit.take()) else {
+            return;
+        };
+        // Outside a runtime the process is killed on drop instead.
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(Arc::clone(&self.pool).give_back(process, self.healthy, permit));
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A fake CLI answering each user message with a result, and `/clear`
+    /// with an empty one. Exits on a message containing `crash`.
+    const FAKE_CLI: &str = r#"#!/bin/sh
+while IFS= read -r line; do
+  case "$line" in
+    *crash*) exit 1 ;;
+    *'"/clear"'*) echo '{"type":"result","subtype":"success","is_error":false,"result":""}' ;;
+    *)
+      echo '{"type":"system","subtype":"init","session_id":"s1"}'
+      echo '{"type":"assistant","message":{"content":[{"type":"text","text":"pong"}]}}'
+      echo '{"type":"result","subtype":"success","is_error":false,"result":"pong","session_id":"s1"}'
+      ;;
+  esac
+done
+"#;
+
+    fn fake_cli(dir: &tempfile::TempDir) -> PathBuf {
+        let path = dir.path().join("claude");
+        std::fs::write(&path, FAKE_CLI).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn pool(dir: &tempfile::TempDir, size: usize, max_leases: u32) -> WarmPool {
+        WarmPool::start(WarmPoolConfig {
+            size,
+            cli_path: Some(fake_cli(dir)),
+            max_leases,
+            ..WarmPoolConfig::default()
+        })
+        .unwrap()
+    }
+
+    /// Waits for a returned process to be reset and put back.
+    async fn settle(pool: &WarmPool, idle: usize) {
+        for _ in 0..200 {
+            if pool.idle() == idle {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("pool never reached {idle} idle processes");
+    }
+
+    #[tokio::test]
+    async fn test_lease_answers_prompts_and_reuses_the_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = pool(&dir, 1, 10);
+        assert_eq!(pool.idle(), 1);
+
+        let mut lease = pool.lease().await.unwrap();
+        let pid = lease.pid();
+        let response = lease.prompt("ping").await.unwrap();
+        assert_eq!(response.result, "pong");
+        assert_eq!(response.session_id.as_deref(), Some("s1"));
+        assert!(!response.is_error);
+        assert!(matches!(&response.events[..], [StreamEvent::Text(text)] if text == "pong"));
+        drop(lease);
+
+        settle(&pool, 1).await;
+        let lease = pool.lease().await.unwrap();
+        assert_eq!(lease.pid(), pid);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_and_broken_processes_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = pool(&dir, 1, 1);
+
+        let lease = pool.lease().await.unwrap();
+        let first = lease.pid();
+        drop(lease);
+        settle(&pool, 1).await;
+
+        let mut lease = pool.lease().await.unwrap();
+        let second = lease.pid();
+        assert_ne!(second, first);
+        let err = lease.prompt("crash").await.unwrap_err();
+        assert_eq!(err.error_code(), "spawn");
+        assert!(lease.prompt("ping").await.is_err());
+        drop(lease);
+        settle(&pool, 1).await;
+
+        let mut lease = pool.lease().await.unwrap();
+        assert_ne!(lease.pid(), second);
+        assert_eq!(lease.prompt("ping").await.unwrap().result, "pong");
+    }
+
+    #[test]
+    fn test_zero_size_is_rejected() {
+        let err = WarmPool::start(WarmPoolConfig {
+            size: 0,
+            ..WarmPoolConfig::default()
+        })
+        .err()
+        .unwrap();
+        assert_eq!(err.error_code(), "validation");
+    }
+}
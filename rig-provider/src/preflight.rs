@@ -0,0 +1,278 @@
+//! Preflight checks for MCP tool agents.
+//!
+//! [`McpToolAgentBuilder::preflight`](crate::mcp_agent::McpToolAgentBuilder::preflight)
+//! resolves the CLI and starts the generated MCP server exactly as a run
+//! would, then performs the MCP `initialize` / `tools/list` handshake itself.
+//! A binary that never enters server mode, a server that crashes on start, or
+//! a toolset whose tools are not advertised is reported in seconds instead of
+//! after a full agent run that never calls `submit`.
+//!
+//! Backends that implement [`CliBackend::check_mcp`](crate::backend::CliBackend::check_mcp)
+//! also have the CLI load the server config itself, as with
+//! `claude mcp list --mcp-config <file>`, so a config the CLI rejects or a
+//! server it cannot connect to is caught too.
+
+use crate::backend::McpServerStatus;
+use crate::errors::ProviderError;
+use crate::mcp_agent::CliAdapter;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{ChildStdin, Command};
+
+/// Upper bound on the handshake; the agent's own timeout applies if shorter.
+pub(crate) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// MCP protocol revision sent in the `initialize` request.
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Outcome of [`McpToolAgentBuilder::preflight`](crate::mcp_agent::McpToolAgentBuilder::preflight).
+#[derive(Debug, Clone)]
+pub struct PreflightReport {
+    /// Adapter the check ran for.
+    pub adapter: CliAdapter,
+    /// Resolved path of the adapter's CLI.
+    pub cli_path: PathBuf,
+    /// Server name and version from the `initialize` response, if reported.
+    pub server_info: Option<String>,
+    /// Tool names the MCP server advertised.
+    pub server_tools: Vec<String>,
+    /// Toolset tools the server did not advertise.
+    pub missing_tools: Vec<String>,
+    /// The server's state as the CLI reported it, or `None` when the
+    /// backend has no CLI-side check.
+    pub cli_status: Option<McpServerStatus>,
+    /// Wall-clock duration of the check in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl PreflightReport {
+    /// Returns `true` when every toolset tool is visible through the server
+    /// and the CLI, if it was asked, connected to it.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.missing_tools.is_empty()
+            && self
+                .cli_status
+                .as_ref()
+                .is_none_or(|status| status.connected)
+    }
+}
+
+/// Server identity and advertised tool names from an MCP handshake.
+pub(crate) struct Handshake {
+    pub(crate) server_info: Option<String>,
+    pub(crate) tools: Vec<String>,
+}
+
+/// Starts the MCP server described by `config` and lists its tools over stdio.
+///
+/// The server is killed once the handshake completes or `timeout` expires.
+pub(crate) async fn handshake(
+    config: &rig_cli_mcp::server::McpConfig,
+    timeout: Duration,
+) -> Result<Handshake, ProviderError> {
+    let mut child = Command::new(&config.command)
+        .args(&config.args)
+        .envs(&config.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|source| ProviderError::Spawn {
+            stage: "start MCP server",
+            source,
+        })?;
+
+    let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Err(ProviderError::mcp_config(
+            "failed to start MCP server",
+            "stdio pipes unavailable",
+        ));
+    };
+
+    let exchange = async {
+        let mut lines = BufReader::new(stdout).lines();
+
+        send(
+            &mut stdin,
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": {
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "rig-cli-preflight",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                },
+            }),
+        )
+        .await?;
+        let init = read_response(&mut lines, 1).await?;
+
+        send(
+            &mut stdin,
+            &json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        )
+        .await?;
+        send(
+            &mut stdin,
+            &json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+        )
+        .await?;
+        let list = read_response(&mut lines, 2).await?;
+
+        Ok(Handshake {
+            server_info: server_info(&init),
+            tools: tool_names(&list),
+        })
+    };
+
+    let result = match tokio::time::timeout(timeout, exchange).await {
+        Ok(result) => result,
+        Err(e) => Err(ProviderError::mcp_config(
+            format!("MCP server did not complete the handshake within {timeout:?}"),
+            e,
+        )),
+    };
+
+    let _ = child.kill().await;
+    result
+}
+
+/// Writes one JSON-RPC message to the server.
+async fn send(stdin: &mut ChildStdin, message: &Value) -> Result<(), ProviderError> {
+    let mut line = message.to_string();
+    line.push('\n');
+    stdin
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|source| ProviderError::Spawn {
+            stage: "write to MCP server",
+            source,
+        })
+}
+
+/// Reads server output until the response to request `id` arrives.
+///
+/// Notifications and non-JSON lines are skipped.
+async fn read_response<R: AsyncBufRead + Unpin>(
+    lines: &mut Lines<R>,
+    id: u64,
+) -> Result<Value, ProviderError> {
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|source| ProviderError::Spawn {
+            stage: "read from MCP server",
+            source,
+        })?
+    {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        if message.get("id").and_then(Value::as_u64) != Some(id) {
+            continue;
+        }
+        if let Some(error) = message.get("error") {
+            return Err(ProviderError::mcp_config(
+                format!("MCP server rejected request {id}"),
+                error.to_string(),
+            ));
+        }
+        return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+    }
+
+    Err(ProviderError::mcp_config(
+        "MCP server exited during the handshake",
+        "stdout closed before a response arrived",
+    ))
+}
+
+/// Formats `serverInfo` from an `initialize` result as `name version`.
+fn server_info(init: &Value) -> Option<String> {
+    let info = init.get("serverInfo")?;
+    let name = info.get("name").and_then(Value::as_str)?;
+    Some(
+        info.get("version")
+            .and_then(Value::as_str)
+            .map_or_else(|| name.to_string(), |version| format!("{name} {version}")),
+    )
+}
+
+/// Extracts tool names from a `tools/list` result.
+fn tool_names(list: &Value) -> Vec<String> {
+    list.get("tools")
+        .and_then(Value::as_array)
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|tool| tool.get("name").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_handshake_results() {
+        let init = json!({ "serverInfo": { "name": "rig", "version": "1.0.0" } });
+        assert_eq!(server_info(&init).as_deref(), Some("rig 1.0.0"));
+        assert!(server_info(&json!({})).is_none());
+
+        let list = json!({ "tools": [{ "name": "submit" }, { "name": "validate_json" }] });
+        assert_eq!(tool_names(&list), ["submit", "validate_json"]);
+        assert_eq!(tool_names(&Value::Null), Vec::<String>::new());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_handshake_with_stub_server() {
+        let script = r#"
+            read -r init
+            echo '{"jsonrpc":"2.0","method":"notifications/message"}'
+            echo '{"jsonrpc":"2.0","id":1,"result":{"serverInfo":{"name":"stub"}}}'
+            read -r initialized
+            read -r list
+            echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"submit"}]}}'
+        "#;
+        let config = rig_cli_mcp::server::McpConfig {
+            name: "stub".to_string(),
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: std::collections::HashMap::new(),
+        };
+
+        let handshake = handshake(&config, Duration::from_secs(10)).await.unwrap();
+        assert_eq!(handshake.server_info.as_deref(), Some("stub"));
+        assert_eq!(handshake.tools, ["submit"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_handshake_reports_silent_server() {
+        let config = rig_cli_mcp::server::McpConfig {
+            name: "silent".to_string(),
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "cat > /dev/null".to_string()],
+            env: std::collections::HashMap::new(),
+        };
+
+        let err = handshake(&config, Duration::from_millis(200))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.error_code(), "mcp_config");
+    }
+}
@@ -0,0 +1,204 @@
+//! Settings for the `rig-cli-provider serve` MCP bridge.
+//!
+//! A [`ServeConfig`] decides which adapters the server registers as tools,
+//! which JSON Schema its `submit` / `validate_json` / `json_example` tools
+//! enforce, the server name reported to clients, the log level and format,
+//! how often a failed MCP session is restarted, and whether the startup
+//! banner is printed. It is read from an
+//! optional `--config` file (TOML, or JSON when the file name ends in
+//! `.json`); command-line flags then override individual fields.
+//!
+//! # Example
+//!
+//! ```toml
+//! adapters = ["claude", "codex"]
+//! schema = "schemas/invoice.json"
+//! server_name = "invoice-bridge"
+//! log_level = "debug"
+//! log_format = "json"
+//! claude_mcp_configs = ["~/.claude.json"]
+//! max_restarts = 3
+//! quiet = true
+//! ```
+
+use crate::errors::ProviderError;
+use crate::logging::LogFormat;
+use crate::mcp_agent::CliAdapter;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Configuration of the `serve` subcommand.
+///
+/// Missing fields take their [`Default`] values; unknown fields are rejected
+/// so typos do not silently fall back to defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServeConfig {
+    /// Adapters registered as MCP tools. Default: all three.
+    pub adapters: Vec<CliAdapter>,
+    /// JSON Schema file served through a
+    /// [`DynamicJsonSchemaToolkit`](rig_cli_mcp::tools::DynamicJsonSchemaToolkit).
+    ///
+    /// Default: `None`, which serves the built-in `ProviderOutput` schema.
+    pub schema: Option<PathBuf>,
+    /// Server name reported in the MCP `initialize` response.
+    ///
+    /// Default: `rig-mcp-server`.
+    pub server_name: String,
+    /// Log filter of `target=level` directives, e.g. `info` or
+    /// `rig_cli_provider=debug,warn`; see [`logging`](crate::logging).
+    /// Default: `info`.
+    pub log_level: String,
+    /// How log lines are written. Default: [`LogFormat::Pretty`].
+    pub log_format: LogFormat,
+    /// MCP config files handed to the Claude Code adapter.
+    ///
+    /// Default: `["~/.claude.json"]`.
+    pub claude_mcp_configs: Vec<String>,
+    /// Times a failed MCP session is served again before the server exits,
+    /// keeping the adapters it already initialized. A session the client
+    /// closes is never restarted. Default: 0.
+    pub max_restarts: u32,
+    /// Skips the banner with client config snippets printed to stderr at
+    /// startup, which log processors cannot parse. Default: false.
+    pub quiet: bool,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self {
+            adapters: vec![
+                CliAdapter::ClaudeCode,
+                CliAdapter::Codex,
+                CliAdapter::OpenCode,
+            ],
+            schema: None,
+            server_name: "rig-mcp-server".to_string(),
+            log_level: "info".to_string(),
+            log_format: LogFormat::Pretty,
+            claude_mcp_configs: vec!["~/.claude.json".to_string()],
+            max_restarts: 0,
+            quiet: false,
+        }
+    }
+}
+
+impl ServeConfig {
+    /// Reads a config file, parsing it as JSON if its extension is `.json`
+    /// and as TOML otherwise.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::McpConfig`] if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, ProviderError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ProviderError::mcp_config(format!("failed to read {}", path.display()), e)
+        })?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        Self::parse(&contents, is_json).map_err(|e| {
+            ProviderError::mcp_config(format!("failed to parse {}", path.display()), e)
+        })
+    }
+
+    /// Parses config file contents.
+    fn parse(contents: &str, is_json: bool) -> Result<Self, String> {
+        if is_json {
+            serde_json::from_str(contents).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(contents).map_err(|e| e.to_string())
+        }
+    }
+
+    /// Reads the configured schema file, if any.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::McpConfig`] if the file cannot be read or is
+    /// not a JSON object.
+    pub fn load_schema(&self) -> Result<Option<serde_json::Value>, ProviderError> {
+        let Some(path) = &self.schema else {
+            return Ok(None);
+        };
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ProviderError::mcp_config(format!("failed to read schema {}", path.display()), e)
+        })?;
+        let schema: serde_json::Value = serde_json::from_str(&contents).map_err(|e| {
+            ProviderError::mcp_config(format!("failed to parse schema {}", path.display()), e)
+        })?;
+        if !schema.is_object() {
+            return Err(ProviderError::mcp_config(
+                format!("invalid schema {}", path.display()),
+                "a JSON Schema must be a JSON object",
+            ));
+        }
+        Ok(Some(schema))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml_and_json() {
+        let toml = r#"
+            adapters = ["claude", "OpenCode"]
+            server_name = "bridge"
+        "#;
+        let config = ServeConfig::parse(toml, false).unwrap();
+        assert_eq!(
+            config.adapters,
+            [CliAdapter::ClaudeCode, CliAdapter::OpenCode]
+        );
+        assert_eq!(config.server_name, "bridge");
+        assert_eq!(config.log_level, "info");
+        assert_eq!(config.log_format, LogFormat::Pretty);
+        assert_eq!(config.claude_mcp_configs, ["~/.claude.json"]);
+
+        assert_eq!(config.max_restarts, 0);
+        assert!(!config.quiet);
+
+        let json = r#"{ "schema": "s.json", "log_level": "debug", "log_format": "json", "claude_mcp_configs": [], "max_restarts": 2, "quiet": true }"#;
+        let config = ServeConfig::parse(json, true).unwrap();
+        assert_eq!(config.schema, Some(PathBuf::from("s.json")));
+        assert_eq!(config.log_level, "debug");
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.adapters.len(), 3);
+        assert_eq!(config.claude_mcp_configs, Vec::<String>::new());
+        assert_eq!(config.max_restarts, 2);
+        assert!(config.quiet);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_adapters_and_fields() {
+        let err = ServeConfig::parse(r#"adapters = ["gemini"]"#, false).unwrap_err();
+        assert!(err.contains("unknown adapter 'gemini'"), "{err}");
+
+        assert!(ServeConfig::parse(r#"{ "server": "x" }"#, true).is_err());
+    }
+
+    #[test]
+    fn test_load_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema_path = dir.path().join("schema.json");
+        std::fs::write(&schema_path, r#"{ "type": "object" }"#).unwrap();
+        let config_path = dir.path().join("serve.toml");
+        std::fs::write(
+            &config_path,
+            format!("schema = {:?}\n", schema_path.display().to_string()),
+        )
+        .unwrap();
+
+        let config = ServeConfig::load(&config_path).unwrap();
+        assert_eq!(
+            config.load_schema().unwrap(),
+            Some(serde_json::json!({ "type": "object" }))
+        );
+        assert_eq!(ServeConfig::default().load_schema().unwrap(), None);
+
+        std::fs::write(&schema_path, "[1, 2]").unwrap();
+        let err = config.load_schema().unwrap_err();
+        assert_eq!(err.error_code(), "mcp_config");
+    }
+}
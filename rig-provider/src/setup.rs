@@ -1,151 +1,594 @@
+//! Registration of the provider as an MCP server in each CLI's config.
+//!
+//! [`run_setup`] adds a `rig-provider` entry to the Claude Code and
+//! `OpenCode` JSON configs and the Codex TOML config in the home directory,
+//! and returns a [`SetupReport`] instead of printing: every file touched with
+//! the lines it gained or lost, the CLIs found with their versions, and an
+//! actionable [`SetupError`] for every file that could not be updated. A
+//! config that fails to parse is left as it is rather than overwritten.
+//!
+//! ```no_run
+//! use rig_cli_provider::setup::{run_setup, SetupConfig};
+//!
+//! # fn example() -> anyhow::Result<()> {
+//! let report = run_setup(&SetupConfig {
+//!     dry_run: true,
+//!     home: None,
+//! })?;
+//! for change in &report.files {
+//!     println!("{} {}:\n{}", change.action, change.path.display(), change.diff);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::mcp_agent::CliAdapter;
 use anyhow::Context;
 use serde_json::Value;
+use std::fmt::Write as _;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Name of the provider's entry in every CLI config.
+const PROVIDER_NAME: &str = "rig-provider";
 
 /// Configuration for the setup process.
+#[derive(Debug, Clone, Default)]
 pub struct SetupConfig {
     /// Whether to run in dry-run mode (no changes applied).
     pub dry_run: bool,
+    /// Directory holding the CLI configs; `None` uses the user's home.
+    pub home: Option<PathBuf>,
+}
+
+/// Format of a CLI config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// JSON, with servers under `mcpServers`.
+    Json,
+    /// TOML, with servers under `mcp_servers`.
+    Toml,
+}
+
+impl std::fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Json => "JSON",
+            Self::Toml => "TOML",
+        })
+    }
+}
+
+/// What setup did, or in dry-run mode would do, to a config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigAction {
+    /// The file did not exist and was created.
+    Created,
+    /// The entry was added or replaced.
+    Updated,
+    /// The file already held the entry.
+    Unchanged,
+}
+
+impl std::fmt::Display for ConfigAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Unchanged => "unchanged",
+        })
+    }
+}
+
+/// One config file setup registered the provider in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigChange {
+    /// CLI the file configures.
+    pub adapter: CliAdapter,
+    /// Path of the file.
+    pub path: PathBuf,
+    /// What was done to it.
+    pub action: ConfigAction,
+    /// Lines removed (`-`) and added (`+`); empty when unchanged.
+    pub diff: String,
+}
+
+/// A CLI looked for during setup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedCli {
+    /// The CLI.
+    pub adapter: CliAdapter,
+    /// Path of its binary, or `None` if it is not installed.
+    pub path: Option<PathBuf>,
+    /// First line of `<binary> --version`, if it ran.
+    pub version: Option<String>,
+}
+
+/// Why a config file could not be updated.
+#[derive(Debug, thiserror::Error)]
+pub enum SetupError {
+    /// The file is locked by another program or not writable.
+    #[error(
+        "{adapter} config {} is locked or read-only; close programs using it or fix its permissions, then re-run setup: {source}",
+        path.display()
+    )]
+    Locked {
+        /// CLI the file configures.
+        adapter: CliAdapter,
+        /// Path of the file.
+        path: PathBuf,
+        /// Underlying I/O error.
+        source: std::io::Error,
+    },
+    /// The existing file does not parse, so it was left untouched.
+    #[error(
+        "{adapter} config {} is not valid {format}; fix it or move it aside, then re-run setup: {message}",
+        path.display()
+    )]
+    Parse {
+        /// CLI the file configures.
+        adapter: CliAdapter,
+        /// Path of the file.
+        path: PathBuf,
+        /// Format the file should be in.
+        format: ConfigFormat,
+        /// Parser error message.
+        message: String,
+    },
+    /// The file parses but does not have the expected structure.
+    #[error("{adapter} config {} is not usable: {message}", path.display())]
+    Invalid {
+        /// CLI the file configures.
+        adapter: CliAdapter,
+        /// Path of the file.
+        path: PathBuf,
+        /// What is wrong with it.
+        message: String,
+    },
+    /// Any other failure to read or write the file.
+    #[error("{adapter} config {} could not be accessed: {source}", path.display())]
+    Io {
+        /// CLI the file configures.
+        adapter: CliAdapter,
+        /// Path of the file.
+        path: PathBuf,
+        /// Underlying I/O error.
+        source: std::io::Error,
+    },
+}
+
+/// Everything setup did and found.
+#[derive(Debug, Default)]
+pub struct SetupReport {
+    /// Whether nothing was written.
+    pub dry_run: bool,
+    /// Config files registered in, in the order they were handled.
+    pub files: Vec<ConfigChange>,
+    /// Every supported CLI, found or not.
+    pub clis: Vec<DetectedCli>,
+    /// Config files that could not be updated.
+    pub errors: Vec<SetupError>,
+}
+
+impl SetupReport {
+    /// Returns `true` when every config file was updated.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl std::fmt::Display for SetupReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for cli in &self.clis {
+            match (&cli.path, &cli.version) {
+                (Some(path), Some(version)) => {
+                    writeln!(f, "{}: {version} at {}", cli.adapter, path.display())?;
+                }
+                (Some(path), None) => writeln!(f, "{}: found at {}", cli.adapter, path.display())?,
+                (None, _) => writeln!(f, "{}: not installed", cli.adapter)?,
+            }
+        }
+        let prefix = if self.dry_run { "[DRY RUN] " } else { "" };
+        for change in &self.files {
+            writeln!(
+                f,
+                "{prefix}{} config {}: {}",
+                change.adapter,
+                change.path.display(),
+                change.action
+            )?;
+            f.write_str(&change.diff)?;
+        }
+        for error in &self.errors {
+            writeln!(f, "[ERROR] {error}")?;
+        }
+        Ok(())
+    }
 }
 
 /// Runs the setup process to register the provider in CLI configs.
 ///
+/// Per-file failures are collected in [`SetupReport::errors`] rather than
+/// stopping setup, so one broken config does not keep the others from being
+/// updated.
+///
 /// # Errors
-/// Returns an error if environment variables are missing or if config files cannot be read/written.
-pub fn run_setup(config: &SetupConfig) -> anyhow::Result<()> {
+/// Returns an error if the current executable or the home directory cannot
+/// be determined.
+pub fn run_setup(config: &SetupConfig) -> anyhow::Result<SetupReport> {
     tracing::info!("Starting Zero-Config self-registration...");
 
     let exe_path = std::env::current_exe()?;
-    let home = dirs::home_dir().context("Could not determine home directory")?;
-
-    // 1. JSON-based configurations (Claude Code, OpenCode)
-    let claude_path = home.join(".claude.json");
-    setup_json_mcp(
-        "Claude Code",
-        &claude_path,
-        &exe_path,
-        "rig-provider",
-        config,
-    )?;
-
-    let opencode_path = home.join(".opencode.json");
-    setup_json_mcp(
-        "OpenCode",
-        &opencode_path,
-        &exe_path,
-        "rig-provider",
-        config,
-    )?;
-
-    // 2. TOML-based configurations (Codex)
-    let codex_path = home.join(".codex/config.toml");
-    setup_codex(&codex_path, &exe_path, "rig-provider", config)?;
-
-    if config.dry_run {
-        println!("\n[DRY RUN] Setup complete. No files were modified.");
-    } else {
-        println!("\n[SUCCESS] Rig Provider successfully registered for all supported CLIs.");
-    }
-
-    Ok(())
-}
-
-fn setup_json_mcp(
-    name: &str,
-    path: &Path,
-    exe_path: &Path,
-    provider_name: &str,
-    config: &SetupConfig,
-) -> anyhow::Result<()> {
-    println!("Checking {name} config at: {}", path.display());
-
-    let mut data = if path.exists() {
-        let content = fs::read_to_string(path)?;
-        serde_json::from_str::<Value>(&content)
-            .unwrap_or_else(|_| serde_json::json!({"mcpServers": {}}))
-    } else {
-        serde_json::json!({"mcpServers": {}})
+    let command = rig_cli_mcp::server::config_path_string(&exe_path)?;
+    let home = match config.home {
+        Some(ref home) => home.clone(),
+        None => dirs::home_dir().context("Could not determine home directory")?,
     };
 
-    // Ensure mcpServers is an object
-    if data.get("mcpServers").is_none() {
-        if let Some(obj) = data.as_object_mut() {
-            obj.insert("mcpServers".to_string(), serde_json::json!({}));
+    let mut report = SetupReport {
+        dry_run: config.dry_run,
+        clis: detect_clis(),
+        ..SetupReport::default()
+    };
+    let targets = [
+        (
+            CliAdapter::ClaudeCode,
+            home.join(".claude.json"),
+            ConfigFormat::Json,
+        ),
+        (
+            CliAdapter::OpenCode,
+            home.join(".opencode.json"),
+            ConfigFormat::Json,
+        ),
+        (
+            CliAdapter::Codex,
+            home.join(".codex/config.toml"),
+            ConfigFormat::Toml,
+        ),
+    ];
+    for (adapter, path, format) in targets {
+        match register(adapter, &path, format, &command, config.dry_run) {
+            Ok(change) => {
+                tracing::info!(
+                    adapter = %adapter,
+                    path = %path.display(),
+                    action = %change.action,
+                    dry_run = config.dry_run,
+                    "Checked CLI config"
+                );
+                report.files.push(change);
+            }
+            Err(error) => {
+                tracing::warn!(adapter = %adapter, error = %error, "Failed to update CLI config");
+                report.errors.push(error);
+            }
         }
     }
 
-    let servers = data
-        .get_mut("mcpServers")
-        .and_then(|v| v.as_object_mut())
-        .context(format!(
-            "Invalid {name} config: mcpServers must be an object"
-        ))?;
-
-    // Convert exe_path to string only for JSON serialization
-    let exe_str = exe_path.display().to_string();
-
-    servers.insert(
-        provider_name.to_string(),
-        serde_json::json!({
-            "command": exe_str,
-            "args": [],
-            "env": {}
-        }),
-    );
-
-    if config.dry_run {
-        println!(
-            "[DRY RUN] Would update {} with {provider_name}",
-            path.display()
-        );
-    } else {
+    Ok(report)
+}
+
+/// Looks for every supported CLI and asks the ones found for their version.
+fn detect_clis() -> Vec<DetectedCli> {
+    [
+        (
+            CliAdapter::ClaudeCode,
+            rig_cli_claude::discover_claude(None).ok(),
+        ),
+        (CliAdapter::Codex, rig_cli_codex::discover_codex(None).ok()),
+        (
+            CliAdapter::OpenCode,
+            rig_cli_opencode::discover_opencode(None).ok(),
+        ),
+    ]
+    .into_iter()
+    .map(|(adapter, path)| DetectedCli {
+        adapter,
+        version: path.as_deref().and_then(cli_version),
+        path,
+    })
+    .collect()
+}
+
+/// First line of `<binary> --version`, if it succeeds.
+fn cli_version(binary: &Path) -> Option<String> {
+    let output = std::process::Command::new(binary)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+}
+
+/// Registers the provider in the config at `path`, writing it unless
+/// `dry_run` is set.
+fn register(
+    adapter: CliAdapter,
+    path: &Path,
+    format: ConfigFormat,
+    command: &str,
+    dry_run: bool,
+) -> Result<ConfigChange, SetupError> {
+    let io_error = |source: std::io::Error| {
+        let path = path.to_path_buf();
+        if is_locked(&source) {
+            SetupError::Locked {
+                adapter,
+                path,
+                source,
+            }
+        } else {
+            SetupError::Io {
+                adapter,
+                path,
+                source,
+            }
+        }
+    };
+
+    let before = match fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(io_error(e)),
+    };
+    let after = match format {
+        ConfigFormat::Json => with_json_entry(before.as_deref(), command),
+        ConfigFormat::Toml => with_toml_entry(before.as_deref(), command),
+    }
+    .map_err(|error| match error {
+        EntryError::Parse(message) => SetupError::Parse {
+            adapter,
+            path: path.to_path_buf(),
+            format,
+            message,
+        },
+        EntryError::Invalid(message) => SetupError::Invalid {
+            adapter,
+            path: path.to_path_buf(),
+            message,
+        },
+    })?;
+
+    let action = match before {
+        None => ConfigAction::Created,
+        Some(ref before) if *before == after => ConfigAction::Unchanged,
+        Some(_) => ConfigAction::Updated,
+    };
+    if action != ConfigAction::Unchanged && !dry_run {
         if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+            fs::create_dir_all(parent).map_err(io_error)?;
         }
-        fs::write(path, serde_json::to_string_pretty(&data)?)?;
-        println!("[OK] Registered in {name}.");
+        fs::write(path, &after).map_err(io_error)?;
     }
 
-    Ok(())
+    Ok(ConfigChange {
+        adapter,
+        path: path.to_path_buf(),
+        action,
+        diff: line_diff(before.as_deref().unwrap_or_default(), &after),
+    })
 }
 
-fn setup_codex(
-    path: &Path,
-    exe_path: &Path,
-    provider_name: &str,
-    config: &SetupConfig,
-) -> anyhow::Result<()> {
-    println!("Checking Codex config at: {}", path.display());
-
-    let mut content = if path.exists() {
-        fs::read_to_string(path)?
-    } else {
-        String::new()
+/// Whether `error` means another program holds the file or it is read-only.
+fn is_locked(error: &std::io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION on Windows.
+    error.kind() == std::io::ErrorKind::PermissionDenied
+        || (cfg!(windows) && matches!(error.raw_os_error(), Some(32 | 33)))
+}
+
+/// Why an entry could not be added to a config's contents.
+#[derive(Debug, PartialEq, Eq)]
+enum EntryError {
+    Parse(String),
+    Invalid(String),
+}
+
+/// `existing` JSON with the provider under `mcpServers`. Returned unchanged
+/// if the entry is already there.
+fn with_json_entry(existing: Option<&str>, command: &str) -> Result<String, EntryError> {
+    let mut data = match existing.filter(|content| !content.trim().is_empty()) {
+        Some(content) => {
+            serde_json::from_str::<Value>(content).map_err(|e| EntryError::Parse(e.to_string()))?
+        }
+        None => serde_json::json!({}),
     };
+    let root = data
+        .as_object_mut()
+        .ok_or_else(|| EntryError::Invalid("the top level must be an object".to_string()))?;
+    let servers = root
+        .entry("mcpServers")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| EntryError::Invalid("mcpServers must be an object".to_string()))?;
 
-    let section_header = format!("[mcp_servers.{provider_name}]");
-    if content.contains(&section_header) {
-        println!("[SKIP] {provider_name} already exists in Codex config.");
-    } else {
-        // Convert exe_path to string only for TOML serialization
-        let exe_str = exe_path.display().to_string();
-        let entry = format!("\n{section_header}\ncommand = \"{exe_str}\"\nargs = []\n");
-        content.push_str(&entry);
-
-        if config.dry_run {
-            println!(
-                "[DRY RUN] Would update {} with {provider_name}",
-                path.display()
-            );
-        } else {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::write(path, content)?;
-            println!("[OK] Registered in Codex.");
+    let entry = serde_json::json!({
+        "command": command,
+        "args": [],
+        "env": {}
+    });
+    if let (Some(existing), Some(current)) = (existing, servers.get(PROVIDER_NAME)) {
+        if *current == entry {
+            return Ok(existing.to_string());
+        }
+    }
+    servers.insert(PROVIDER_NAME.to_string(), entry);
+    serde_json::to_string_pretty(&data).map_err(|e| EntryError::Invalid(e.to_string()))
+}
+
+/// `existing` TOML with the provider appended under `mcp_servers`. Returned
+/// unchanged if a provider entry is already there, so hand edits survive.
+fn with_toml_entry(existing: Option<&str>, command: &str) -> Result<String, EntryError> {
+    let mut content = existing.unwrap_or_default().to_string();
+    let table: toml::Table =
+        toml::from_str(&content).map_err(|e| EntryError::Parse(e.to_string()))?;
+    let registered = match table.get("mcp_servers") {
+        None => false,
+        Some(toml::Value::Table(servers)) => servers.contains_key(PROVIDER_NAME),
+        Some(_) => {
+            return Err(EntryError::Invalid(
+                "mcp_servers must be a table".to_string(),
+            ))
         }
+    };
+    if registered {
+        return Ok(content);
+    }
+
+    let entry = rig_cli_mcp::server::McpConfig {
+        name: PROVIDER_NAME.to_string(),
+        command: command.to_string(),
+        args: vec![],
+        env: std::collections::HashMap::new(),
+    };
+    content.push('\n');
+    content.push_str(&entry.to_codex_toml());
+    Ok(content)
+}
+
+/// The lines between the common head and tail of `before` and `after`,
+/// prefixed with `-` and `+`.
+fn line_diff(before: &str, after: &str) -> String {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    let head = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let tail = old[head..]
+        .iter()
+        .rev()
+        .zip(new[head..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut diff = String::new();
+    for line in &old[head..old.len() - tail] {
+        let _ = writeln!(diff, "-{line}");
+    }
+    for line in &new[head..new.len() - tail] {
+        let _ = writeln!(diff, "+{line}");
+    }
+    diff
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_entry_is_added_and_kept() {
+        let updated = with_json_entry(
+            Some(r#"{"theme": "dark", "mcpServers": {"other": {}}}"#),
+            "/bin/rig",
+        )
+        .unwrap();
+        let data: Value = serde_json::from_str(&updated).unwrap();
+        assert_eq!(data["theme"], "dark");
+        assert_eq!(data["mcpServers"]["other"], serde_json::json!({}));
+        assert_eq!(data["mcpServers"][PROVIDER_NAME]["command"], "/bin/rig");
+
+        assert_eq!(
+            with_json_entry(Some(&updated), "/bin/rig").unwrap(),
+            updated
+        );
+    }
+
+    #[test]
+    fn test_broken_configs_are_reported_not_replaced() {
+        assert!(matches!(
+            with_json_entry(Some("{ not json"), "/bin/rig"),
+            Err(EntryError::Parse(_))
+        ));
+        assert!(matches!(
+            with_json_entry(Some(r#"{"mcpServers": []}"#), "/bin/rig"),
+            Err(EntryError::Invalid(_))
+        ));
+        assert!(matches!(
+            with_toml_entry(Some("model = "), "/bin/rig"),
+            Err(EntryError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_toml_entry_is_appended_once() {
+        let updated = with_toml_entry(Some("model = \"o3\"\n"), "/bin/rig").unwrap();
+        assert!(updated.starts_with("model = \"o3\"\n"));
+        let table: toml::Table = toml::from_str(&updated).unwrap();
+        assert_eq!(
+            table["mcp_servers"][PROVIDER_NAME]["command"].as_str(),
+            Some("/bin/rig")
+        );
+        assert_eq!(
+            with_toml_entry(Some(&updated), "/bin/rig").unwrap(),
+            updated
+        );
+    }
+
+    #[test]
+    fn test_register_reports_actions_and_diffs() {
+        let home = tempfile::tempdir().unwrap();
+        let path = home.path().join(".codex/config.toml");
+        let run = |dry_run| {
+            register(
+                CliAdapter::Codex,
+                &path,
+                ConfigFormat::Toml,
+                "/bin/rig",
+                dry_run,
+            )
+            .unwrap()
+        };
+
+        let planned = run(true);
+        assert_eq!(planned.action, ConfigAction::Created);
+        assert!(!path.exists());
+
+        let created = run(false);
+        assert_eq!(created.diff, planned.diff);
+        assert!(
+            created.diff.contains("+command = \"/bin/rig\""),
+            "{}",
+            created.diff
+        );
+        assert!(created.diff.lines().all(|line| line.starts_with('+')));
+
+        let again = run(false);
+        assert_eq!(again.action, ConfigAction::Unchanged);
+        assert_eq!(again.diff, "");
+    }
+
+    #[test]
+    fn test_register_leaves_unparsable_file_alone() {
+        let home = tempfile::tempdir().unwrap();
+        let path = home.path().join(".claude.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let err = register(
+            CliAdapter::ClaudeCode,
+            &path,
+            ConfigFormat::Json,
+            "/bin/rig",
+            false,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SetupError::Parse {
+                format: ConfigFormat::Json,
+                ..
+            }
+        ));
+        assert!(err.to_string().contains("re-run setup"), "{err}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{ not json");
     }
 
-    Ok(())
+    #[test]
+    fn test_line_diff_shows_only_changed_lines() {
+        assert_eq!(line_diff("a\nb\nc\n", "a\nx\ny\nc\n"), "-b\n+x\n+y\n");
+        assert_eq!(line_diff("a\n", "a\n"), "");
+        assert_eq!(line_diff("", "a\n"), "+a\n");
+    }
 }
@@ -0,0 +1,308 @@
+//! Large context shared by many MCP tool agent runs.
+//!
+//! A [`payload`](crate::mcp_agent::McpToolAgentBuilder::payload) is pasted
+//! into every prompt, so extracting from a product catalog with a hundred
+//! different prompts sends the catalog a hundred times. A [`SharedContext`]
+//! is written to disk once instead. Each run given it with
+//! [`McpToolAgentBuilder::shared_context`] gets the file hard-linked (or
+//! copied, where linking fails) into its temp working directory, and its
+//! prompt tells the agent to read the file from there. A caller-provided
+//! [`working_dir`](crate::mcp_agent::McpToolAgentBuilder::working_dir) is
+//! left untouched; the prompt points at the file's absolute path instead.
+//!
+//! The file is written read-only into a temp directory of its own, which is
+//! removed when the last clone of the context is dropped, so it outlives
+//! every run still holding one. A [`SharedContextCache`] hands out one
+//! context per name and writes a new one only when the contents' hash
+//! changes:
+//!
+//! ```no_run
+//! use rig_cli_provider::shared_context::SharedContextCache;
+//!
+//! # fn example(catalog: &str) -> Result<(), rig_cli_provider::errors::ProviderError> {
+//! let cache = SharedContextCache::new();
+//! let context = cache.get_or_write("catalog.json", catalog)?;
+//! // Same contents: the file already written is reused.
+//! assert_eq!(cache.get_or_write("catalog.json", catalog)?.path(), context.path());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Runs need a builtin file reading tool; `Read` is opted in to for them.
+//!
+//! [`McpToolAgentBuilder::shared_context`]: crate::mcp_agent::McpToolAgentBuilder::shared_context
+
+use crate::errors::ProviderError;
+use rig_cli_process_core::hash::fnv1a;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Context written to disk once and read by many runs.
+///
+/// Clones share the file; it is deleted when the last clone is dropped.
+#[derive(Debug, Clone)]
+pub struct SharedContext {
+    inner: Arc<SharedContextFile>,
+}
+
+#[derive(Debug)]
+struct SharedContextFile {
+    /// Keeps the directory holding the file alive.
+    _dir: tempfile::TempDir,
+    path: PathBuf,
+    file_name: String,
+    content_hash: u64,
+    bytes: u64,
+}
+
+impl SharedContext {
+    /// Writes `contents` to a new read-only file named `file_name`.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Validation`] if `file_name` is not a plain
+    /// file name and [`ProviderError::Spawn`] if the file cannot be written.
+    pub fn new(
+        file_name: impl Into<String>,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<Self, ProviderError> {
+        let file_name = file_name.into();
+        check_file_name(&file_name)?;
+        let contents = contents.as_ref();
+
+        let dir = tempfile::Builder::new()
+            .prefix("rig_shared_context_")
+            .tempdir()
+            .map_err(|source| ProviderError::Spawn {
+                stage: "create shared context dir",
+                source,
+            })?;
+        let path = dir.path().join(&file_name);
+        let write = || {
+            std::fs::write(&path, contents)?;
+            let mut permissions = std::fs::metadata(&path)?.permissions();
+            permissions.set_readonly(true);
+            std::fs::set_permissions(&path, permissions)
+        };
+        write().map_err(|source| ProviderError::Spawn {
+            stage: "write shared context",
+            source,
+        })?;
+
+        Ok(Self {
+            inner: Arc::new(SharedContextFile {
+                _dir: dir,
+                path,
+                file_name,
+                content_hash: fnv1a(contents),
+                bytes: contents.len() as u64,
+            }),
+        })
+    }
+
+    /// Absolute path of the written file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Name of the file, as it appears in run working directories.
+    #[must_use]
+    pub fn file_name(&self) -> &str {
+        &self.inner.file_name
+    }
+
+    /// 64-bit FNV-1a hash of the contents.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        self.inner.content_hash
+    }
+
+    /// Size of the contents in bytes.
+    #[must_use]
+    pub fn bytes(&self) -> u64 {
+        self.inner.bytes
+    }
+
+    /// Places the file in `dir`, by hard link where possible, and returns
+    /// its path there.
+    pub(crate) fn link_into(&self, dir: &Path) -> Result<PathBuf, ProviderError> {
+        let target = dir.join(self.file_name());
+        if std::fs::hard_link(self.path(), &target).is_err() {
+            std::fs::copy(self.path(), &target).map_err(|source| ProviderError::Spawn {
+                stage: "link shared context",
+                source,
+            })?;
+        }
+        Ok(target)
+    }
+
+    /// The prompt context telling the agent to read the file at `shown_path`.
+    pub(crate) fn reference(&self, shown_path: &Path) -> String {
+        format!(
+            "The shared context for this task is in the file `{}` ({} bytes, content hash {:016x}). \
+             Read it from there; it is not repeated in this prompt.",
+            shown_path.display(),
+            self.bytes(),
+            self.content_hash()
+        )
+    }
+}
+
+/// Shared contexts by name, rewritten only when their contents change.
+///
+/// Clones share the cache, so one can be handed to many concurrent tasks.
+#[derive(Debug, Clone, Default)]
+pub struct SharedContextCache {
+    entries: Arc<Mutex<HashMap<String, SharedContext>>>,
+}
+
+impl SharedContextCache {
+    /// Returns a new, empty cache.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the context cached as `file_name` if its contents hash the
+    /// same as `contents`, and otherwise writes and caches a new one.
+    ///
+    /// A replaced context stays on disk until the runs holding it finish.
+    ///
+    /// # Errors
+    /// Returns the errors of [`SharedContext::new`].
+    pub fn get_or_write(
+        &self,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<SharedContext, ProviderError> {
+        let contents = contents.as_ref();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(context) = entries.get(file_name) {
+            if context.content_hash() == fnv1a(contents) && context.bytes() == contents.len() as u64
+            {
+                return Ok(context.clone());
+            }
+            tracing::debug!(
+                event = "shared_context_invalidated",
+                file_name,
+                "Shared context contents changed; writing a new file"
+            );
+        }
+        let context = SharedContext::new(file_name, contents)?;
+        entries.insert(file_name.to_string(), context.clone());
+        drop(entries);
+        Ok(context)
+    }
+
+    /// Drops the context cached as `file_name`, returning whether there was
+    /// one.
+    pub fn invalidate(&self, file_name: &str) -> bool {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(file_name)
+            .is_some()
+    }
+
+    /// Number of cached contexts.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Returns `true` when no context is cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Accepts names that stay inside the directory they are joined to.
+fn check_file_name(file_name: &str) -> Result<(), ProviderError> {
+    let plain = Path::new(file_name)
+        .file_name()
+        .is_some_and(|name| name == file_name);
+    if plain {
+        Ok(())
+    } else {
+        Err(ProviderError::Validation(format!(
+            "shared context file name '{file_name}' must be a plain file name"
+        )))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_is_written_read_only() {
+        let context = SharedContext::new("catalog.json", "[1, 2, 3]").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(context.path()).unwrap(),
+            "[1, 2, 3]"
+        );
+        assert!(std::fs::metadata(context.path())
+            .unwrap()
+            .permissions()
+            .readonly());
+        assert_eq!(context.file_name(), "catalog.json");
+        assert_eq!(context.bytes(), 9);
+        assert_eq!(context.content_hash(), fnv1a(b"[1, 2, 3]"));
+    }
+
+    #[test]
+    fn test_file_is_removed_with_the_last_clone() {
+        let context = SharedContext::new("catalog.json", "data").unwrap();
+        let path = context.path().to_path_buf();
+        let clone = context.clone();
+        drop(context);
+        assert!(path.exists());
+        drop(clone);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_file_names_must_be_plain() {
+        for name in ["", "..", "a/b.txt", "/etc/passwd"] {
+            let err = SharedContext::new(name, "data").unwrap_err();
+            assert_eq!(err.error_code(), "validation", "{name}");
+        }
+    }
+
+    #[test]
+    fn test_cache_rewrites_only_changed_contents() {
+        let cache = SharedContextCache::new();
+        let first = cache.get_or_write("catalog.json", "v1").unwrap();
+        let again = cache.get_or_write("catalog.json", "v1").unwrap();
+        assert_eq!(again.path(), first.path());
+        assert_eq!(cache.len(), 1);
+
+        let changed = cache.get_or_write("catalog.json", "v2").unwrap();
+        assert_ne!(changed.path(), first.path());
+        assert_eq!(std::fs::read_to_string(changed.path()).unwrap(), "v2");
+        // The replaced file stays while a run still holds it.
+        assert_eq!(std::fs::read_to_string(first.path()).unwrap(), "v1");
+
+        assert!(cache.invalidate("catalog.json"));
+        assert!(!cache.invalidate("catalog.json"));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_link_into_places_the_file_in_the_run_dir() {
+        let context = SharedContext::new("catalog.json", "data").unwrap();
+        let run_dir = tempfile::tempdir().unwrap();
+        let linked = context.link_into(run_dir.path()).unwrap();
+        assert_eq!(linked, run_dir.path().join("catalog.json"));
+        assert_eq!(std::fs::read_to_string(&linked).unwrap(), "data");
+
+        let reference = context.reference(Path::new("catalog.json"));
+        assert!(reference.contains("`catalog.json` (4 bytes"), "{reference}");
+    }
+}
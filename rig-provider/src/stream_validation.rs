@@ -0,0 +1,341 @@
+//! Checking submissions against the submit tool's schema while a run streams.
+//!
+//! A [`SubmitValidator`] watches the events of a streamed run and reports
+//! what does not match the schema as
+//! [`StreamEvent::ValidationWarning`]s, long before the run ends:
+//!
+//! - a [`ToolCall`](StreamEvent::ToolCall) of the submit tool is checked as a
+//!   whole, one warning per error;
+//! - a JSON object arriving as [`TextDelta`](StreamEvent::TextDelta)s is
+//!   checked one top-level member at a time, as soon as each member's value
+//!   is complete, and against the whole schema once the object closes.
+//!
+//! Enable it with
+//! [`McpToolAgentBuilder::stream_validation`](crate::mcp_agent::McpToolAgentBuilder::stream_validation);
+//! a consumer that sees a warning can stop the run with
+//! [`McpStreamHandle::abort`](crate::mcp_agent::McpStreamHandle::abort)
+//! instead of waiting for an answer that will be rejected.
+
+use crate::errors::ProviderError;
+use crate::events::StreamEvent;
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::ValidationError;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Validates submissions seen in a stream of events against a schema.
+pub struct SubmitValidator {
+    /// Name the CLI reports for the submit tool.
+    tool_name: String,
+    /// Validator of the whole submission.
+    schema: jsonschema::Validator,
+    /// Validators of the top-level properties, by name.
+    properties: BTreeMap<String, jsonschema::Validator>,
+    /// Whether properties not in `properties` are rejected.
+    closed: bool,
+    /// JSON text received as deltas since the last complete message.
+    partial: PartialObject,
+}
+
+impl std::fmt::Debug for SubmitValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubmitValidator")
+            .field("tool_name", &self.tool_name)
+            .field("properties", &self.properties.keys().collect::<Vec<_>>())
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SubmitValidator {
+    /// Creates a validator for calls of `tool_name`, the submit tool as the
+    /// CLI names it, against `schema`.
+    ///
+    /// Properties whose schema cannot be compiled on its own are only
+    /// checked once the whole submission is known.
+    ///
+    /// # Errors
+    /// Returns [`ProviderError::Validation`] if `schema` is not a valid JSON
+    /// Schema.
+    pub fn new(tool_name: impl Into<String>, schema: &Value) -> Result<Self, ProviderError> {
+        let compiled = jsonschema::Validator::new(schema).map_err(|e| {
+            ProviderError::Validation(format!("submit tool schema is invalid: {e}"))
+        })?;
+        let properties = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, property)| {
+                let validator = jsonschema::Validator::new(&with_definitions(property, schema));
+                validator.ok().map(|validator| (name.clone(), validator))
+            })
+            .collect();
+        Ok(Self {
+            tool_name: tool_name.into(),
+            schema: compiled,
+            properties,
+            closed: schema.get("additionalProperties") == Some(&Value::Bool(false)),
+            partial: PartialObject::default(),
+        })
+    }
+
+    /// The warnings `event` gives rise to, in the order they were found.
+    pub fn observe(&mut self, event: &StreamEvent) -> Vec<StreamEvent> {
+        match event {
+            StreamEvent::ToolCall { name, input, .. } if *name == self.tool_name => {
+                warnings("", &self.schema, input, |_| true)
+            }
+            StreamEvent::TextDelta(text) => {
+                let mut found = Vec::new();
+                for piece in self.partial.push(text) {
+                    match piece {
+                        Piece::Member(key, value) => found.extend(self.check_member(&key, &value)),
+                        Piece::Object(value) => found.extend(self.check_object(&value)),
+                    }
+                }
+                found
+            }
+            StreamEvent::Text(_) => {
+                self.partial = PartialObject::default();
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Warnings for a whole submission whose members were already checked:
+    /// only errors about the object itself, such as missing required
+    /// members, are new.
+    fn check_object(&self, value: &Value) -> Vec<StreamEvent> {
+        warnings("", &self.schema, value, |e| {
+            e.instance_path.to_string().is_empty()
+                && !matches!(e.kind, ValidationErrorKind::AdditionalProperties { .. })
+        })
+    }
+
+    /// Warnings for the top-level member `key` of a submission.
+    fn check_member(&self, key: &str, value: &Value) -> Vec<StreamEvent> {
+        let path = format!("/{}", key.replace('~', "~0").replace('/', "~1"));
+        match self.properties.get(key) {
+            Some(validator) => warnings(&path, validator, value, |_| true),
+            None if self.closed => vec![StreamEvent::ValidationWarning {
+                path,
+                message: format!("additional property '{key}' is not allowed"),
+            }],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Warnings for the errors of `value`, found at `path`, against `validator`
+/// that `keep` selects.
+fn warnings(
+    path: &str,
+    validator: &jsonschema::Validator,
+    value: &Value,
+    keep: impl Fn(&ValidationError<'_>) -> bool,
+) -> Vec<StreamEvent> {
+    validator
+        .iter_errors(value)
+        .filter(|e| keep(e))
+        .map(|e| StreamEvent::ValidationWarning {
+            path: format!("{path}{}", e.instance_path),
+            message: e.to_string(),
+        })
+        .collect()
+}
+
+/// `property`'s schema with the root schema's definitions, so its `$ref`s
+/// resolve when it is compiled on its own.
+fn with_definitions(property: &Value, root: &Value) -> Value {
+    let mut property = property.clone();
+    if let Some(object) = property.as_object_mut() {
+        for key in ["$defs", "definitions"] {
+            if let Some(definitions) = root.get(key) {
+                object.entry(key).or_insert_with(|| definitions.clone());
+            }
+        }
+    }
+    property
+}
+
+/// What [`PartialObject::push`] completed.
+enum Piece {
+    /// A top-level member, once its value is complete.
+    Member(String, Value),
+    /// The whole object, once it closes.
+    Object(Value),
+}
+
+/// Incremental scanner of a JSON object arriving in fragments.
+///
+/// It only tracks nesting and strings, so each fragment is scanned once;
+/// members are parsed when the comma or brace ending them arrives. Text
+/// that does not start with `{`, or stops being valid JSON, is ignored.
+#[derive(Debug, Default)]
+struct PartialObject {
+    text: String,
+    /// Bytes of `text` already scanned.
+    scanned: usize,
+    /// Byte offset of the opening brace, once seen.
+    start: Option<usize>,
+    /// Byte offset the member being scanned starts at.
+    member_start: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// Set once the text closed its object or turned out not to be one.
+    done: bool,
+}
+
+impl PartialObject {
+    /// Appends `fragment` and returns what it completed.
+    fn push(&mut self, fragment: &str) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+        if self.done {
+            return pieces;
+        }
+        self.text.push_str(fragment);
+        while self.scanned < self.text.len() && !self.done {
+            let at = self.scanned;
+            let byte = self.text.as_bytes()[at];
+            self.scanned += 1;
+            if self.start.is_none() {
+                match byte {
+                    b'{' => {
+                        self.start = Some(at);
+                        self.member_start = at + 1;
+                        self.depth = 1;
+                    }
+                    b if b.is_ascii_whitespace() => {}
+                    _ => self.done = true,
+                }
+                continue;
+            }
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b',' if self.depth == 1 => {
+                    pieces.extend(self.member(at));
+                    self.member_start = at + 1;
+                }
+                b'}' | b']' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 0 {
+                        pieces.extend(self.member(at));
+                        let start = self.start.unwrap_or_default();
+                        if let Ok(object) = serde_json::from_str(&self.text[start..=at]) {
+                            pieces.push(Piece::Object(object));
+                        }
+                        self.done = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        pieces
+    }
+
+    /// Parses the member ending at byte `end`; stops scanning if it is not
+    /// valid JSON.
+    fn member(&mut self, end: usize) -> Option<Piece> {
+        let member = self.text[self.member_start..end].trim();
+        if member.is_empty() {
+            return None;
+        }
+        let parsed: Result<serde_json::Map<String, Value>, _> =
+            serde_json::from_str(&format!("{{{member}}}"));
+        let Ok(object) = parsed else {
+            self.done = true;
+            return None;
+        };
+        object
+            .into_iter()
+            .next()
+            .map(|(key, value)| Piece::Member(key, value))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" } }
+            },
+            "required": ["name"],
+            "additionalProperties": false,
+            "$defs": { "tag": { "type": "string" } }
+        })
+    }
+
+    fn paths(warnings: &[StreamEvent]) -> Vec<&str> {
+        warnings
+            .iter()
+            .map(|warning| match warning {
+                StreamEvent::ValidationWarning { path, .. } => path.as_str(),
+                other => panic!("not a warning: {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_submit_tool_call_is_checked_whole() {
+        let mut validator = SubmitValidator::new("mcp__rig_mcp__submit", &schema()).unwrap();
+        let call = |name: &str| StreamEvent::ToolCall {
+            id: None,
+            name: name.to_string(),
+            input: json!({ "name": 3, "tags": ["a", 1] }),
+        };
+
+        assert!(validator
+            .observe(&call("mcp__rig_mcp__validate_json"))
+            .is_empty());
+        let warnings = validator.observe(&call("mcp__rig_mcp__submit"));
+        let mut found = paths(&warnings);
+        found.sort_unstable();
+        assert_eq!(found, ["/name", "/tags/1"]);
+    }
+
+    #[test]
+    fn test_members_are_checked_as_they_complete() {
+        let mut validator = SubmitValidator::new("submit", &schema()).unwrap();
+        let mut delta = |text: &str| validator.observe(&StreamEvent::TextDelta(text.to_string()));
+
+        assert!(delta("{\"tags\": [\"a\", ").is_empty());
+        assert_eq!(paths(&delta("2], \"extra\": {\"a\": \"}")), ["/tags/1"]);
+        assert_eq!(paths(&delta("\"}, \"na")), ["/extra"]);
+        // The closing brace reports the missing "name" once the object is
+        // known; "extra" is not reported again.
+        assert_eq!(paths(&delta("mes\": 1}")), ["/names", ""]);
+        assert!(delta("{\"extra\": 1}").is_empty());
+    }
+
+    #[test]
+    fn test_prose_is_ignored() {
+        let mut validator = SubmitValidator::new("submit", &schema()).unwrap();
+        let warnings =
+            validator.observe(&StreamEvent::TextDelta("Here is {\"name\": 3}".to_string()));
+        assert!(warnings.is_empty());
+
+        validator.observe(&StreamEvent::Text("Here is {\"name\": 3}".to_string()));
+        let warnings = validator.observe(&StreamEvent::TextDelta("{\"name\": 3,".to_string()));
+        assert_eq!(paths(&warnings), ["/name"]);
+    }
+}
@@ -0,0 +1,410 @@
+//! Behaviors every [`CliBackend`] shares, checked against a fake CLI.
+//!
+//! [`check`] runs a backend once per [`Behavior`], each time pointing it at
+//! a `/bin/sh` script that misbehaves in one way: never exits, prints more
+//! than a pipe buffer holds, fails, streams many lines, or reports the
+//! directory it was started in. A [`CliDialect`] tells the script how the
+//! CLI prints model text, so the backend's own parser reads it. A backend
+//! for a new CLI passes when it behaves like the built-in three:
+//!
+//! ```no_run
+//! use rig_cli_provider::testing::contract::{self, CliDialect};
+//! # use rig_cli_provider::backend::CliBackend;
+//!
+//! # async fn check(backend: &dyn CliBackend) -> std::io::Result<()> {
+//! let dialect = CliDialect::plain("gemini 0.9.0", "Usage: gemini [OPTIONS]");
+//! let report = contract::check(backend, &dialect).await?;
+//! assert!(report.is_success(), "{report}");
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{request, shell_quote, shell_quote_path, Fixture};
+use crate::backend::CliBackend;
+use crate::events::StreamEvent;
+use crate::mcp_agent::CliAdapter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Timeout of the [`Behavior::Timeout`] run.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long after [`TIMEOUT`] the timed out run may take to return.
+const TIMEOUT_GRACE: Duration = Duration::from_secs(10);
+
+/// Lines printed for [`Behavior::LargeOutput`]; well over a pipe buffer.
+const LARGE_OUTPUT_LINES: usize = 10_000;
+
+/// Lines printed one at a time for [`Behavior::StreamOrdering`].
+const ORDERED_LINES: usize = 50;
+
+/// Exit code of the [`Behavior::NonZeroExit`] run.
+const EXIT_CODE: i32 = 3;
+
+/// How a CLI prints, so the fake CLI is read by the backend's own parser.
+#[derive(Debug, Clone, Copy)]
+pub struct CliDialect {
+    /// What the CLI prints for `--version`.
+    pub version: &'static str,
+    /// What the CLI prints for `--help`.
+    pub help: &'static str,
+    /// The stdout line carrying one line of model text.
+    pub text_line: fn(&str) -> String,
+}
+
+impl CliDialect {
+    /// A CLI printing model text as plain lines.
+    #[must_use]
+    pub const fn plain(version: &'static str, help: &'static str) -> Self {
+        Self {
+            version,
+            help,
+            text_line: str::to_string,
+        }
+    }
+
+    /// The dialect of a captured CLI: `stream-json` events for Claude Code,
+    /// plain lines for the others.
+    #[must_use]
+    pub fn of(fixture: &Fixture) -> Self {
+        let dialect = Self::plain(fixture.version, fixture.help);
+        match fixture.adapter {
+            CliAdapter::ClaudeCode => Self {
+                text_line: claude_text_line,
+                ..dialect
+            },
+            CliAdapter::Codex | CliAdapter::OpenCode | CliAdapter::Custom(_) => dialect,
+        }
+    }
+}
+
+/// One behavior [`check`] tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behavior {
+    /// A CLI that never exits is stopped at the request's timeout with a
+    /// `timeout` error.
+    Timeout,
+    /// Output larger than a pipe buffer is returned whole.
+    LargeOutput,
+    /// A CLI exiting with an error is reported: as a result carrying its
+    /// exit code and stderr, or as an error that, since output was printed,
+    /// is not
+    /// [failing before output](crate::errors::ProviderError::failed_before_output),
+    /// so the run is never repeated on a fallback CLI.
+    NonZeroExit,
+    /// Streamed text arrives in the order it was printed.
+    StreamOrdering,
+    /// The CLI is started in the request's working directory.
+    CwdContainment,
+}
+
+impl Behavior {
+    /// Every behavior, in the order [`check`] runs them.
+    pub const ALL: [Self; 5] = [
+        Self::Timeout,
+        Self::LargeOutput,
+        Self::NonZeroExit,
+        Self::StreamOrdering,
+        Self::CwdContainment,
+    ];
+
+    /// Short name, used in reports.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::LargeOutput => "large_output",
+            Self::NonZeroExit => "non_zero_exit",
+            Self::StreamOrdering => "stream_ordering",
+            Self::CwdContainment => "cwd_containment",
+        }
+    }
+}
+
+/// Outcome of [`check`].
+#[derive(Debug, Clone)]
+pub struct ContractReport {
+    /// Adapter of the checked backend.
+    pub adapter: CliAdapter,
+    /// Each behavior, with why it failed.
+    pub results: Vec<(Behavior, Result<(), String>)>,
+}
+
+impl ContractReport {
+    /// Whether every behavior passed.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// The behaviors that failed, with why.
+    pub fn failures(&self) -> impl Iterator<Item = (Behavior, &str)> {
+        self.results
+            .iter()
+            .filter_map(|(behavior, result)| result.as_ref().err().map(|e| (*behavior, e.as_str())))
+    }
+}
+
+impl std::fmt::Display for ContractReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let failed = self.failures().count();
+        write!(
+            f,
+            "{}: {} of {} behaviors pass",
+            self.adapter,
+            self.results.len() - failed,
+            self.results.len()
+        )?;
+        for (behavior, error) in self.failures() {
+            write!(f, "\n  {}: {error}", behavior.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs every [`Behavior`] against `backend`, with a fake CLI speaking
+/// `dialect`.
+///
+/// # Errors
+/// Returns an error if the fake CLI cannot be written. A backend that
+/// misbehaves is reported in the [`ContractReport`] instead.
+pub async fn check(
+    backend: &dyn CliBackend,
+    dialect: &CliDialect,
+) -> std::io::Result<ContractReport> {
+    let mut results = Vec::with_capacity(Behavior::ALL.len());
+    for behavior in Behavior::ALL {
+        results.push((behavior, check_one(backend, dialect, behavior).await?));
+    }
+    Ok(ContractReport {
+        adapter: backend.adapter(),
+        results,
+    })
+}
+
+/// Runs `backend` against a fake CLI showing `behavior`.
+async fn check_one(
+    backend: &dyn CliBackend,
+    dialect: &CliDialect,
+    behavior: Behavior,
+) -> std::io::Result<Result<(), String>> {
+    let dir = tempfile::tempdir()?;
+    let workdir = dir.path().join("work");
+    std::fs::create_dir(&workdir)?;
+    let body = script(dir.path(), dialect, behavior)?;
+    let cli = install(dir.path(), dialect, &body)?;
+    let mut request = request(&cli, &workdir);
+
+    let outcome = match behavior {
+        Behavior::Timeout => {
+            request.timeout = TIMEOUT;
+            let started = Instant::now();
+            let result = backend.run(request).await;
+            let elapsed = started.elapsed();
+            match result {
+                Ok(_) => Err("the run succeeded although the CLI never exited".to_string()),
+                Err(e) if e.error_code() != "timeout" => {
+                    Err(format!("expected a timeout error, got: {e}"))
+                }
+                Err(_) if elapsed > TIMEOUT + TIMEOUT_GRACE => Err(format!(
+                    "the run returned after {elapsed:?}, with a timeout of {TIMEOUT:?}"
+                )),
+                Err(_) => Ok(()),
+            }
+        }
+        Behavior::LargeOutput => match backend.run(request).await {
+            Ok(result) => {
+                let printed = result.stdout.matches(LARGE_OUTPUT_PREFIX).count();
+                let last = large_output_line(LARGE_OUTPUT_LINES);
+                if printed == LARGE_OUTPUT_LINES && result.stdout.contains(&last) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "stdout holds {printed} of the {LARGE_OUTPUT_LINES} lines printed"
+                    ))
+                }
+            }
+            Err(e) => Err(format!("the run failed: {e}")),
+        },
+        Behavior::NonZeroExit => match backend.run(request).await {
+            Ok(result) if result.exit_code != EXIT_CODE => Err(format!(
+                "the run reported exit code {}, not {EXIT_CODE}",
+                result.exit_code
+            )),
+            Ok(result) if !result.stderr.contains("boom") => {
+                Err("the run dropped the CLI's stderr".to_string())
+            }
+            Err(e) if e.failed_before_output() => Err(format!(
+                "the error claims no output was printed, so a fallback would repeat the run: {e}"
+            )),
+            Ok(_) | Err(_) => Ok(()),
+        },
+        Behavior::StreamOrdering => {
+            let (tx, mut rx) = mpsc::channel(100);
+            let collect = async {
+                let mut streamed = String::new();
+                while let Some(event) = rx.recv().await {
+                    if let StreamEvent::Text(chunk) | StreamEvent::TextDelta(chunk) = event {
+                        streamed.push_str(&chunk);
+                    }
+                }
+                streamed
+            };
+            let (result, streamed) = tokio::join!(backend.stream(request, tx), collect);
+            match result {
+                Ok(_) => in_order(&streamed),
+                Err(e) => Err(format!("the run failed: {e}")),
+            }
+        }
+        Behavior::CwdContainment => match backend.run(request).await {
+            Ok(_) => {
+                let expected = workdir.canonicalize()?;
+                match std::fs::read_to_string(dir.path().join("cwd")) {
+                    Ok(started_in) if Path::new(started_in.trim()) == expected => Ok(()),
+                    Ok(started_in) => Err(format!(
+                        "the CLI was started in {}, not in {}",
+                        started_in.trim(),
+                        expected.display()
+                    )),
+                    Err(_) => Err("the run succeeded without starting the CLI".to_string()),
+                }
+            }
+            Err(e) => Err(format!("the run failed: {e}")),
+        },
+    };
+    Ok(outcome)
+}
+
+/// Writes what the fake CLI prints for `behavior` into `dir` and returns
+/// the script body printing it.
+fn script(dir: &Path, dialect: &CliDialect, behavior: Behavior) -> std::io::Result<String> {
+    let text = dir.join("text");
+    let write_text = |lines: Vec<String>| {
+        let stdout: String = lines
+            .iter()
+            .map(|line| (dialect.text_line)(line) + "\n")
+            .collect();
+        std::fs::write(&text, stdout)
+    };
+
+    Ok(match behavior {
+        Behavior::Timeout => "exec sleep 30".to_string(),
+        Behavior::LargeOutput => {
+            write_text((1..=LARGE_OUTPUT_LINES).map(large_output_line).collect())?;
+            format!("cat {}", shell_quote_path(&text))
+        }
+        Behavior::NonZeroExit => {
+            write_text(vec!["partial answer".to_string()])?;
+            format!(
+                "cat {}\necho boom >&2\nexit {EXIT_CODE}",
+                shell_quote_path(&text)
+            )
+        }
+        Behavior::StreamOrdering => {
+            write_text((1..=ORDERED_LINES).map(ordered_line).collect())?;
+            format!(
+                "while IFS= read -r line; do printf '%s\\n' \"$line\"; done < {}",
+                shell_quote_path(&text)
+            )
+        }
+        Behavior::CwdContainment => {
+            write_text(vec!["done".to_string()])?;
+            format!(
+                "pwd -P > {}\ncat {}",
+                shell_quote_path(&dir.join("cwd")),
+                shell_quote_path(&text)
+            )
+        }
+    })
+}
+
+/// Writes the fake CLI into `dir`: it answers `--version`, `--help` and a
+/// bare invocation like a healthy CLI, and runs `body` for anything else.
+fn install(dir: &Path, dialect: &CliDialect, body: &str) -> std::io::Result<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let help = dir.join("help");
+    std::fs::write(&help, dialect.help)?;
+    let path = dir.join("cli");
+    let script = format!(
+        "#!/bin/sh\n\
+         case \"$1\" in\n\
+         --version) echo {version}; exit 0 ;;\n\
+         --help) cat {help}; exit 0 ;;\n\
+         '') echo ok; exit 0 ;;\n\
+         esac\n\
+         {body}\n",
+        version = shell_quote(dialect.version),
+        help = shell_quote_path(&help),
+    );
+    std::fs::write(&path, script)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(path)
+}
+
+/// Start of every [`Behavior::LargeOutput`] line.
+const LARGE_OUTPUT_PREFIX: &str = "large output line ";
+
+fn large_output_line(n: usize) -> String {
+    format!("{LARGE_OUTPUT_PREFIX}{n:05}")
+}
+
+fn ordered_line(n: usize) -> String {
+    format!("ordered line {n:03}")
+}
+
+/// Checks that every [`ordered_line`] appears in `text`, in order.
+fn in_order(text: &str) -> Result<(), String> {
+    let mut from = 0;
+    for n in 1..=ORDERED_LINES {
+        let line = ordered_line(n);
+        match text[from..].find(&line) {
+            Some(at) => from += at + line.len(),
+            None if text.contains(&line) => return Err(format!("'{line}' arrived out of order")),
+            None => return Err(format!("'{line}' never arrived")),
+        }
+    }
+    Ok(())
+}
+
+/// A Claude Code `stream-json` assistant message holding `text`.
+fn claude_text_line(text: &str) -> String {
+    serde_json::json!({
+        "type": "assistant",
+        "message": { "content": [{ "type": "text", "text": text }] },
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::testing::FIXTURES;
+
+    #[tokio::test]
+    async fn test_built_in_backends_keep_the_contract() {
+        for fixture in FIXTURES {
+            let backend = fixture.adapter.backend();
+            let report = check(&*backend, &CliDialect::of(fixture)).await.unwrap();
+            assert_eq!(report.results.len(), Behavior::ALL.len());
+            assert!(report.is_success(), "{report}");
+        }
+    }
+
+    #[test]
+    fn test_in_order_reports_missing_and_reordered_lines() {
+        let lines: Vec<String> = (1..=ORDERED_LINES).map(ordered_line).collect();
+        assert_eq!(in_order(&lines.concat()), Ok(()));
+
+        let mut swapped = lines.clone();
+        swapped.swap(0, 1);
+        assert!(in_order(&swapped.concat())
+            .unwrap_err()
+            .contains("out of order"));
+        assert!(in_order(&lines[1..].concat())
+            .unwrap_err()
+            .contains("never arrived"));
+    }
+}
@@ -0,0 +1,339 @@
+//! Captured CLI transcripts and a fake CLI that replays them.
+//!
+//! Enabled with the `test-support` feature. Each [`Fixture`] is the output
+//! of one real run of a specific CLI version: what it printed for
+//! `--version` and `--help`, and the stdout and stderr of a prompt that
+//! validated and submitted an invoice through the `rig_mcp` server.
+//!
+//! [`FixtureCli::install`] writes a fixture out as an executable that
+//! prints the same bytes whatever it is asked, so a run goes through the
+//! real discovery, capability probing and stream parsing of its adapter
+//! without a model behind it. Point an agent at it with
+//! [`McpToolAgentBuilder::cli_path`], or call [`FixtureCli::replay`] to
+//! collect the events the built-in backend extracts:
+//!
+//! ```no_run
+//! use rig_cli_provider::testing::{FixtureCli, CLAUDE_2_0_STREAM_JSON};
+//! use rig_cli_provider::StreamEvent;
+//!
+//! # async fn check() -> Result<(), Box<dyn std::error::Error>> {
+//! let cli = FixtureCli::install(&CLAUDE_2_0_STREAM_JSON)?;
+//! let replay = cli.replay().await?;
+//! assert!(replay
+//!     .events
+//!     .iter()
+//!     .any(|e| matches!(e, StreamEvent::ToolCall { name, .. } if name == "mcp__rig_mcp__submit")));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`contract`] runs any [`CliBackend`](crate::backend::CliBackend) through
+//! the behaviors the built-in backends share, so a new adapter can show it
+//! is a drop-in replacement.
+//!
+//! The fake CLI is a `/bin/sh` script, so this module is only built on unix.
+//!
+//! [`McpToolAgentBuilder::cli_path`]: crate::mcp_agent::McpToolAgentBuilder::cli_path
+
+use crate::backend::BackendRequest;
+use crate::errors::ProviderError;
+use crate::events::StreamEvent;
+use crate::mcp_agent::{CliAdapter, McpToolAgentResult};
+use crate::models::DefaultModels;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+pub mod contract;
+
+/// Output of one captured CLI run.
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    /// Short name, used for the files [`FixtureCli`] writes.
+    pub name: &'static str,
+    /// Adapter whose CLI was captured.
+    pub adapter: CliAdapter,
+    /// What the CLI printed for `--version`.
+    pub version: &'static str,
+    /// What the CLI printed for `--help`.
+    pub help: &'static str,
+    /// Stdout of the run.
+    pub stdout: &'static str,
+    /// Stderr of the run.
+    pub stderr: &'static str,
+    /// Exit code of the run.
+    pub exit_code: i32,
+}
+
+/// Claude Code 2.0.14 with `--output-format stream-json`: an init event,
+/// text, a `validate_json` and a `submit` tool call with their results, and
+/// a `result` envelope carrying the session, turn count and usage.
+pub const CLAUDE_2_0_STREAM_JSON: Fixture = Fixture {
+    name: "claude-2.0.14-stream-json",
+    adapter: CliAdapter::ClaudeCode,
+    version: "2.0.14 (Claude Code)",
+    help: include_str!("../../fixtures/claude-2.0.14-help.txt"),
+    stdout: include_str!("../../fixtures/claude-2.0.14-stream-json.jsonl"),
+    stderr: "",
+    exit_code: 0,
+};
+
+/// Codex 0.46.0 `exec`: the final message on stdout, the session header,
+/// tool calls and token count on stderr.
+pub const CODEX_0_46_EXEC: Fixture = Fixture {
+    name: "codex-0.46.0-exec",
+    adapter: CliAdapter::Codex,
+    version: "codex-cli 0.46.0",
+    help: "Codex CLI\n\nUsage: codex [OPTIONS] [PROMPT]\n       codex [OPTIONS] <COMMAND> [ARGS]\n",
+    stdout: include_str!("../../fixtures/codex-0.46.0-exec.stdout.txt"),
+    stderr: include_str!("../../fixtures/codex-0.46.0-exec.stderr.txt"),
+    exit_code: 0,
+};
+
+/// `OpenCode` 0.15.0 `run`: text interleaved with one line per tool call.
+pub const OPENCODE_0_15_RUN: Fixture = Fixture {
+    name: "opencode-0.15.0-run",
+    adapter: CliAdapter::OpenCode,
+    version: "0.15.0",
+    help: "opencode [project]\n\nstart opencode tui\n\nCommands:\n  opencode run [message..]  run opencode with a message\n",
+    stdout: include_str!("../../fixtures/opencode-0.15.0-run.stdout.txt"),
+    stderr: "",
+    exit_code: 0,
+};
+
+/// Every fixture shipped with the crate.
+pub const FIXTURES: &[Fixture] = &[CLAUDE_2_0_STREAM_JSON, CODEX_0_46_EXEC, OPENCODE_0_15_RUN];
+
+/// Events and result of a [`FixtureCli::replay`].
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// Events the backend streamed, in order.
+    pub events: Vec<StreamEvent>,
+    /// The backend's result.
+    pub result: McpToolAgentResult,
+}
+
+/// A fake CLI executable that prints a [`Fixture`].
+///
+/// The executable and its data live in a temporary directory that is
+/// removed when this is dropped.
+#[derive(Debug)]
+pub struct FixtureCli {
+    fixture: Fixture,
+    dir: tempfile::TempDir,
+    path: PathBuf,
+}
+
+impl FixtureCli {
+    /// Writes `fixture` out as an executable in a new temporary directory.
+    ///
+    /// # Errors
+    /// Returns an error if the directory or its files cannot be written.
+    pub fn install(fixture: &Fixture) -> std::io::Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let data = |suffix: &str| dir.path().join(format!("{}.{suffix}", fixture.name));
+        for (suffix, contents) in [
+            ("version", fixture.version),
+            ("help", fixture.help),
+            ("stdout", fixture.stdout),
+            ("stderr", fixture.stderr),
+        ] {
+            std::fs::write(data(suffix), contents)?;
+        }
+
+        let path = dir.path().join(fixture.name);
+        let script = format!(
+            "#!/bin/sh\n\
+             case \"$1\" in\n\
+             --version) echo {version}; exit 0 ;;\n\
+             --help) cat {help}; exit 0 ;;\n\
+             esac\n\
+             cat {stdout}\n\
+             cat {stderr} >&2\n\
+             exit {exit_code}\n",
+            version = shell_quote(fixture.version),
+            help = shell_quote_path(&data("help")),
+            stdout = shell_quote_path(&data("stdout")),
+            stderr = shell_quote_path(&data("stderr")),
+            exit_code = fixture.exit_code,
+        );
+        std::fs::write(&path, script)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+
+        Ok(Self {
+            fixture: *fixture,
+            dir,
+            path,
+        })
+    }
+
+    /// Path of the executable.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The fixture this CLI prints.
+    #[must_use]
+    pub const fn fixture(&self) -> &Fixture {
+        &self.fixture
+    }
+
+    /// Streams a run through the fixture adapter's built-in backend,
+    /// collecting every event it sends.
+    ///
+    /// # Errors
+    /// Returns the backend's error, including the adapter's non-zero exit
+    /// error for a fixture that does not exit with 0.
+    pub async fn replay(&self) -> Result<Replay, ProviderError> {
+        let backend = self.fixture.adapter.backend();
+        let request = request(&self.path, self.dir.path());
+        let (tx, mut rx) = mpsc::channel(100);
+        let collect = async {
+            let mut events = Vec::new();
+            while let Some(event) = rx.recv().await {
+                events.push(event);
+            }
+            events
+        };
+        let (result, events) = tokio::join!(backend.stream(request, tx), collect);
+        Ok(Replay {
+            events,
+            result: result?,
+        })
+    }
+}
+
+/// A request running `cli_path` in `cwd`, with a 30 second timeout.
+fn request(cli_path: &Path, cwd: &Path) -> BackendRequest {
+    BackendRequest {
+        prompt: "Extract the invoice in the context and submit it.".to_string(),
+        system_prompt: String::new(),
+        mcp_config: rig_cli_mcp::server::McpConfig {
+            name: "rig_mcp".to_string(),
+            command: cli_path.display().to_string(),
+            args: Vec::new(),
+            env: std::collections::HashMap::new(),
+        },
+        extra_mcp_configs: Vec::new(),
+        allowed_tools: Vec::new(),
+        builtin_tools: None,
+        sandbox_mode: rig_cli_codex::SandboxMode::ReadOnly,
+        timeout: std::time::Duration::from_secs(30),
+        cwd: cwd.to_path_buf(),
+        env: Vec::new(),
+        cli_path: Some(cli_path.to_path_buf()),
+        models: DefaultModels::default(),
+        backpressure: crate::mcp_agent::BackpressurePolicy::Block,
+        mcp_delivery: crate::mcp_agent::McpDelivery::ConfigFile,
+        isolated: false,
+        images: Vec::new(),
+        stderr_events: None,
+    }
+}
+
+/// Quotes `s` for the shell, as a single-quoted word.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn shell_quote_path(path: &Path) -> String {
+    shell_quote(&path.display().to_string())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn texts(events: &[StreamEvent]) -> Vec<&str> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn tool_calls(events: &[StreamEvent]) -> Vec<&str> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::ToolCall { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_claude_fixture_replays_stream_json_events() {
+        let cli = FixtureCli::install(&CLAUDE_2_0_STREAM_JSON).unwrap();
+        let replay = cli.replay().await.unwrap();
+
+        assert_eq!(
+            texts(&replay.events),
+            [
+                "I'll validate the extracted invoice before submitting it.",
+                "Submitted invoice INV-1042 from Acme Tooling with a total of 129.50."
+            ]
+        );
+        assert_eq!(
+            tool_calls(&replay.events),
+            ["mcp__rig_mcp__validate_json", "mcp__rig_mcp__submit"]
+        );
+        assert!(replay.events.contains(&StreamEvent::SessionInfo {
+            session_id: Some("3f1c9a52-7d4e-4b8a-9c61-0e2f5a7b8d13".to_string()),
+            model: Some("claude-sonnet-4-5-20250929".to_string()),
+        }));
+        assert!(replay.events.iter().any(|e| matches!(
+            e,
+            StreamEvent::Usage {
+                output_tokens: 237,
+                ..
+            }
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_codex_fixture_replays_final_message() {
+        let cli = FixtureCli::install(&CODEX_0_46_EXEC).unwrap();
+        let replay = cli.replay().await.unwrap();
+
+        assert_eq!(
+            texts(&replay.events).concat().trim(),
+            "Submitted invoice INV-1042 from Acme Tooling with a total of 129.50."
+        );
+        assert!(replay.result.stderr.contains("rig_mcp.submit"));
+    }
+
+    #[tokio::test]
+    async fn test_opencode_fixture_replays_text_lines() {
+        let cli = FixtureCli::install(&OPENCODE_0_15_RUN).unwrap();
+        let replay = cli.replay().await.unwrap();
+
+        let text = texts(&replay.events).concat();
+        assert!(text.contains("rig_mcp_submit"));
+        assert!(text.contains("Submitted invoice INV-1042"));
+    }
+
+    #[tokio::test]
+    async fn test_non_zero_fixture_is_an_error() {
+        let fixture = Fixture {
+            name: "failing",
+            stdout: "",
+            stderr: "boom",
+            exit_code: 3,
+            ..OPENCODE_0_15_RUN
+        };
+        let cli = FixtureCli::install(&fixture).unwrap();
+        let err = cli.replay().await.unwrap_err();
+        assert!(err.failed_before_output(), "{err}");
+    }
+
+    #[test]
+    fn test_shell_quote_escapes_single_quotes() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
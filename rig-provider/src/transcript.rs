@@ -0,0 +1,453 @@
+//! Conversation transcripts of CLI runs, for evaluation harnesses.
+//!
+//! A [`TranscriptExporter`] turns the [`StreamEvent`]s of a run, and the MCP
+//! tool calls harvested from it, into a [`Transcript`]: chat messages with
+//! `role`, `content`, `tool_calls` and `tool_call_id` in the shape chat
+//! completion APIs use. Written as JSONL, one message per line, it can be fed
+//! to tools such as promptfoo or to custom scoring scripts without knowing
+//! which CLI produced it.
+//!
+//! ```
+//! use rig_cli_provider::events::StreamEvent;
+//! use rig_cli_provider::transcript::TranscriptExporter;
+//!
+//! let events = vec![
+//!     StreamEvent::ToolCall {
+//!         id: Some("toolu_1".to_string()),
+//!         name: "mcp__rig__submit".to_string(),
+//!         input: serde_json::json!({ "total": 42 }),
+//!     },
+//!     StreamEvent::ToolResult {
+//!         tool_use_id: "toolu_1".to_string(),
+//!         content: "Submitted".to_string(),
+//!     },
+//!     StreamEvent::Text("Done.".to_string()),
+//! ];
+//! let transcript = TranscriptExporter::new()
+//!     .prompt("Extract the invoice total")
+//!     .export(&events, None);
+//! let jsonl = transcript.to_jsonl();
+//! assert_eq!(jsonl.lines().count(), 4);
+//! assert!(jsonl.starts_with(r#"{"role":"user","content":"Extract the invoice total"}"#));
+//! ```
+
+use crate::events::StreamEvent;
+use rig_cli_mcp::harvest::ToolHarvest;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Who a [`TranscriptMessage`] is from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// The system prompt.
+    System,
+    /// The prompt the run was given.
+    User,
+    /// The agent's text and tool calls.
+    Assistant,
+    /// The result of one tool call.
+    Tool,
+}
+
+/// One message of a [`Transcript`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    /// Who the message is from.
+    pub role: Role,
+    /// The text of the message; for a tool message, the tool's output.
+    #[serde(default)]
+    pub content: String,
+    /// Tools an assistant message calls.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<TranscriptToolCall>,
+    /// The call a tool message answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl TranscriptMessage {
+    fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+}
+
+/// A tool call of an assistant message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptToolCall {
+    /// ID the answering tool message refers to.
+    pub id: String,
+    /// Always `"function"`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The tool and its arguments.
+    pub function: TranscriptFunction,
+}
+
+/// The tool a [`TranscriptToolCall`] calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptFunction {
+    /// Tool name as the model saw it, e.g. `mcp__rig__submit`.
+    pub name: String,
+    /// The arguments, as a JSON string.
+    pub arguments: String,
+}
+
+/// The messages of one run, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transcript {
+    /// The messages.
+    pub messages: Vec<TranscriptMessage>,
+}
+
+impl Transcript {
+    /// The transcript as JSONL, one message per line, each line ending in a
+    /// newline.
+    #[must_use]
+    pub fn to_jsonl(&self) -> String {
+        self.messages
+            .iter()
+            .filter_map(|message| serde_json::to_string(message).ok())
+            .map(|line| line + "\n")
+            .collect()
+    }
+
+    /// Writes the transcript as JSONL.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn write_jsonl(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(self.to_jsonl().as_bytes())
+    }
+}
+
+/// Builds [`Transcript`]s from run events.
+///
+/// Text deltas are dropped in favour of the complete text that follows
+/// them; usage, session, stall, error, raw and subagent events have no place
+/// in the conversation and are dropped too. Claude Code reports tool calls
+/// without their IDs, so calls without one get `call_<n>` and a tool result
+/// that names no known call answers the oldest unanswered one.
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptExporter {
+    system_prompt: Option<String>,
+    prompt: Option<String>,
+}
+
+impl TranscriptExporter {
+    /// Creates an exporter with no system or user message.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts transcripts with this system message.
+    #[must_use]
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Starts transcripts with this user message, after any system message.
+    #[must_use]
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    /// Builds the transcript of a run.
+    ///
+    /// When `events` report no tool calls, as with Codex and `OpenCode`,
+    /// which do not stream them, the calls in `harvest` are added as
+    /// tool-call and tool-result messages before the agent's final text.
+    #[must_use]
+    pub fn export(&self, events: &[StreamEvent], harvest: Option<&ToolHarvest>) -> Transcript {
+        let mut builder = Builder::default();
+        if let Some(prompt) = &self.system_prompt {
+            builder.push(TranscriptMessage::new(Role::System, prompt.clone()));
+        }
+        if let Some(prompt) = &self.prompt {
+            builder.push(TranscriptMessage::new(Role::User, prompt.clone()));
+        }
+        let conversation_start = builder.messages.len();
+
+        let mut saw_tool_calls = false;
+        for event in events {
+            match event {
+                StreamEvent::Text(text) => builder.text(text),
+                StreamEvent::ToolCall { id, name, input } => {
+                    saw_tool_calls = true;
+                    builder.tool_call(id.clone(), name, input);
+                }
+                StreamEvent::ToolResult {
+                    tool_use_id,
+                    content,
+                } => builder.tool_result(tool_use_id, content),
+                StreamEvent::TextDelta(_)
+                | StreamEvent::Usage { .. }
+                | StreamEvent::SessionInfo { .. }
+                | StreamEvent::Subagent { .. }
+                | StreamEvent::Stalled { .. }
+                | StreamEvent::Progress(_)
+                | StreamEvent::Warning(_)
+                | StreamEvent::ValidationWarning { .. }
+                | StreamEvent::Error(_)
+                | StreamEvent::Raw(_) => {}
+            }
+        }
+
+        match harvest {
+            Some(harvest) if !saw_tool_calls && !harvest.calls.is_empty() => {
+                builder.insert_harvest(conversation_start, harvest);
+            }
+            _ => {}
+        }
+        Transcript {
+            messages: builder.messages,
+        }
+    }
+
+    /// Builds the transcript of a Claude Code run from the raw JSON events in
+    /// its `RunResult::stream_events`.
+    ///
+    /// Events the built-in parsers do not recognise are dropped.
+    #[must_use]
+    pub fn export_claude(
+        &self,
+        stream_events: &[serde_json::Value],
+        harvest: Option<&ToolHarvest>,
+    ) -> Transcript {
+        let parsers = rig_cli_claude::builtin_parsers();
+        let events: Vec<StreamEvent> = stream_events
+            .iter()
+            .filter_map(|value| parsers.parse(None, value))
+            .flatten()
+            .map(StreamEvent::from)
+            .collect();
+        self.export(&events, harvest)
+    }
+}
+
+/// Transcript under construction.
+#[derive(Default)]
+struct Builder {
+    messages: Vec<TranscriptMessage>,
+    /// Calls not answered yet, oldest first.
+    pending: VecDeque<String>,
+    next_id: usize,
+}
+
+impl Builder {
+    fn push(&mut self, message: TranscriptMessage) {
+        self.messages.push(message);
+    }
+
+    /// The assistant message being written, started if the last message is
+    /// not one.
+    fn assistant(&mut self) -> &mut TranscriptMessage {
+        if self
+            .messages
+            .last()
+            .is_none_or(|m| m.role != Role::Assistant)
+        {
+            self.messages
+                .push(TranscriptMessage::new(Role::Assistant, String::new()));
+        }
+        let last = self.messages.len() - 1;
+        &mut self.messages[last]
+    }
+
+    fn text(&mut self, text: &str) {
+        let message = self.assistant();
+        if !message.content.is_empty() {
+            message.content.push('\n');
+        }
+        message.content.push_str(text);
+    }
+
+    fn tool_call(&mut self, id: Option<String>, name: &str, input: &serde_json::Value) {
+        let id = id.unwrap_or_else(|| {
+            self.next_id += 1;
+            format!("call_{}", self.next_id)
+        });
+        self.pending.push_back(id.clone());
+        self.assistant().tool_calls.push(TranscriptToolCall {
+            id,
+            kind: "function".to_string(),
+            function: TranscriptFunction {
+                name: name.to_string(),
+                arguments: input.to_string(),
+            },
+        });
+    }
+
+    fn tool_result(&mut self, tool_use_id: &str, content: &str) {
+        let id = match self.pending.iter().position(|id| id == tool_use_id) {
+            Some(index) => self.pending.remove(index),
+            None => self.pending.pop_front(),
+        }
+        .unwrap_or_else(|| tool_use_id.to_string());
+        let mut message = TranscriptMessage::new(Role::Tool, content);
+        message.tool_call_id = Some(id);
+        self.push(message);
+    }
+
+    /// Adds the harvested calls before the final assistant text, or at the
+    /// end if there is none.
+    fn insert_harvest(&mut self, conversation_start: usize, harvest: &ToolHarvest) {
+        let tail = match self.messages.last() {
+            Some(last)
+                if self.messages.len() > conversation_start && last.role == Role::Assistant =>
+            {
+                self.messages.pop()
+            }
+            _ => None,
+        };
+        for call in &harvest.calls {
+            self.tool_call(None, &call.tool, &call.arguments);
+            let id = self.pending.back().cloned().unwrap_or_default();
+            self.tool_result(&id, &call.result);
+        }
+        if let Some(tail) = tail {
+            self.push(tail);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use rig_cli_mcp::audit::ToolCallRecord;
+    use serde_json::json;
+
+    #[test]
+    fn test_claude_tool_results_pair_with_unnamed_calls() {
+        let events = vec![
+            StreamEvent::TextDelta("Let me".to_string()),
+            StreamEvent::Text("Let me check.".to_string()),
+            StreamEvent::ToolCall {
+                id: None,
+                name: "mcp__rig__validate_json".to_string(),
+                input: json!({ "json": { "total": 42 } }),
+            },
+            StreamEvent::ToolResult {
+                tool_use_id: "toolu_abc".to_string(),
+                content: "JSON is valid.".to_string(),
+            },
+            StreamEvent::Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+            },
+            StreamEvent::Text("Submitted.".to_string()),
+        ];
+        let transcript = TranscriptExporter::new()
+            .system_prompt("Be precise.")
+            .prompt("Extract")
+            .export(&events, None);
+
+        let lines: Vec<serde_json::Value> = transcript
+            .to_jsonl()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                json!({ "role": "system", "content": "Be precise." }),
+                json!({ "role": "user", "content": "Extract" }),
+                json!({
+                    "role": "assistant",
+                    "content": "Let me check.",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {
+                            "name": "mcp__rig__validate_json",
+                            "arguments": "{\"json\":{\"total\":42}}"
+                        }
+                    }]
+                }),
+                json!({ "role": "tool", "content": "JSON is valid.", "tool_call_id": "call_1" }),
+                json!({ "role": "assistant", "content": "Submitted." }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_claude_parses_stream_json() {
+        let stream_events = vec![
+            json!({ "type": "system", "subtype": "init", "session_id": "s1" }),
+            json!({ "type": "assistant", "message": { "content": [
+                { "type": "tool_use", "id": "toolu_1", "name": "mcp__rig__submit", "input": { "total": 42 } }
+            ] } }),
+            json!({ "type": "assistant", "message": { "content": [
+                { "type": "tool_result", "tool_use_id": "toolu_1", "content": "Submitted" },
+                { "type": "text", "text": "Done." }
+            ] } }),
+            json!({ "type": "result", "result": "Done.", "is_error": false }),
+        ];
+        let transcript = TranscriptExporter::new().export_claude(&stream_events, None);
+
+        let roles: Vec<Role> = transcript.messages.iter().map(|m| m.role).collect();
+        assert_eq!(roles, [Role::Assistant, Role::Tool, Role::Assistant]);
+        assert_eq!(
+            transcript.messages[0].tool_calls[0].function.arguments,
+            r#"{"total":42}"#
+        );
+        assert_eq!(transcript.messages[1].content, "Submitted");
+        assert_eq!(transcript.messages[2].content, "Done.");
+    }
+
+    #[test]
+    fn test_harvest_fills_in_unstreamed_tool_calls() {
+        let record = |tool: &str, result: &str| ToolCallRecord {
+            timestamp_ms: 0,
+            server: "rig_mcp".to_string(),
+            tool: tool.to_string(),
+            arguments: json!({ "total": 42 }),
+            result: result.to_string(),
+            is_error: false,
+            duration_ms: 1,
+            client_name: None,
+            client_version: None,
+        };
+        let harvest = ToolHarvest {
+            calls: vec![
+                record("validate_json", "JSON is valid."),
+                record("submit", "Submitted"),
+            ],
+        };
+        let events = vec![StreamEvent::Text("Done.".to_string())];
+        let transcript = TranscriptExporter::new()
+            .prompt("Extract")
+            .export(&events, Some(&harvest));
+
+        let roles: Vec<Role> = transcript.messages.iter().map(|m| m.role).collect();
+        assert_eq!(
+            roles,
+            [
+                Role::User,
+                Role::Assistant,
+                Role::Tool,
+                Role::Assistant,
+                Role::Tool,
+                Role::Assistant
+            ]
+        );
+        assert_eq!(
+            transcript.messages[1].tool_calls[0].function.name,
+            "validate_json"
+        );
+        assert_eq!(
+            transcript.messages[4].tool_call_id.as_deref(),
+            Some("call_2")
+        );
+        assert_eq!(transcript.messages[5].content, "Done.");
+    }
+}
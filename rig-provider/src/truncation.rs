@@ -0,0 +1,360 @@
+//! Token-aware truncation of payload context.
+//!
+//! A payload is inlined into the prompt's `<context>` block as is, so one
+//! large enough pushes the prompt past the model's context window. A
+//! [`PayloadBudget`] set on an
+//! [`McpToolAgentBuilder`](crate::mcp_agent::McpToolAgentBuilder) or
+//! [`CliAgentBuilder`](crate::mcp_agent::CliAgentBuilder) caps the payload
+//! at a number of tokens, keeping the part its [`Retain`] strategy names,
+//! and puts a notice where text was cut so the agent knows the context is
+//! incomplete:
+//!
+//! ```text
+//! fn main() {
+//! [truncated: about 18250 of 20000 estimated tokens omitted here]
+//! ```
+//!
+//! Tokens are estimated, not counted: each adapter's [`TokenEstimator`]
+//! weighs ASCII text, CJK text and other scripts differently, which is
+//! close enough to keep prompts predictably within limits without shipping
+//! a tokenizer. Cuts fall on line boundaries where one is close.
+//!
+//! # Example
+//!
+//! ```
+//! use rig_cli_provider::mcp_agent::CliAdapter;
+//! use rig_cli_provider::truncation::{PayloadBudget, Retain};
+//!
+//! let budget = PayloadBudget::new(40).retain(Retain::Tail);
+//! let log = "starting\n".repeat(100) + "error: disk full\n";
+//! let (payload, truncation) = budget.apply(log, CliAdapter::Codex);
+//! assert!(truncation.is_some());
+//! assert!(payload.ends_with("error: disk full\n"));
+//! ```
+
+use crate::mcp_agent::CliAdapter;
+use std::fmt::Write as _;
+
+/// Which part of an oversized payload is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Retain {
+    /// The beginning, e.g. of a document whose summary comes first.
+    #[default]
+    Head,
+    /// The middle, dropping equal amounts from both ends.
+    Middle,
+    /// The end, e.g. of a log whose latest lines matter most.
+    Tail,
+    /// The beginning and the end, dropping the middle.
+    HeadAndTail,
+}
+
+/// Approximates how many tokens a model's tokenizer makes of a text.
+///
+/// Each character costs a number of thousandths of a token depending on its
+/// script: English text and code average about four characters per token,
+/// while CJK characters are usually a token each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenEstimator {
+    /// Thousandths of a token per ASCII character.
+    pub ascii_millitokens: u32,
+    /// Thousandths of a token per CJK, kana, hangul or emoji character.
+    pub wide_millitokens: u32,
+    /// Thousandths of a token per other non-ASCII character.
+    pub other_millitokens: u32,
+}
+
+impl Default for TokenEstimator {
+    fn default() -> Self {
+        Self {
+            ascii_millitokens: 250,
+            wide_millitokens: 1000,
+            other_millitokens: 500,
+        }
+    }
+}
+
+impl TokenEstimator {
+    /// The estimator matching `adapter`'s models.
+    ///
+    /// Claude's tokenizer splits text a little finer, at about 3.5 ASCII
+    /// characters per token; the others use the default of four.
+    #[must_use]
+    pub fn for_adapter(adapter: CliAdapter) -> Self {
+        match adapter {
+            CliAdapter::ClaudeCode => Self {
+                ascii_millitokens: 286,
+                ..Self::default()
+            },
+            CliAdapter::Codex | CliAdapter::OpenCode | CliAdapter::Custom(_) => Self::default(),
+        }
+    }
+
+    /// Estimated number of tokens in `text`.
+    #[must_use]
+    pub fn estimate(&self, text: &str) -> usize {
+        let millitokens: u64 = text.chars().map(|c| self.cost(c)).sum();
+        usize::try_from(millitokens.div_ceil(1000)).unwrap_or(usize::MAX)
+    }
+
+    /// Thousandths of a token `c` costs.
+    fn cost(&self, c: char) -> u64 {
+        let millitokens = if c.is_ascii() {
+            self.ascii_millitokens
+        } else if is_wide(c) {
+            self.wide_millitokens
+        } else {
+            self.other_millitokens
+        };
+        u64::from(millitokens)
+    }
+}
+
+/// Whether `c` belongs to a script tokenizers give about a token per
+/// character.
+fn is_wide(c: char) -> bool {
+    matches!(u32::from(c),
+        0x1100..=0x11FF // Hangul Jamo
+        | 0x2E80..=0x9FFF // CJK radicals, punctuation, kana, unified ideographs
+        | 0xAC00..=0xD7AF // Hangul syllables
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+        | 0xFF00..=0xFFEF // Fullwidth forms
+        | 0x1F000..=0x1FAFF // Emoji and pictographs
+        | 0x20000..=0x3FFFF // CJK extensions
+    )
+}
+
+/// Tokens set aside for each truncation notice.
+const NOTICE_TOKENS: usize = 24;
+
+/// A limit on the estimated tokens of a payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadBudget {
+    max_tokens: usize,
+    retain: Retain,
+    estimator: Option<TokenEstimator>,
+}
+
+/// What [`PayloadBudget::apply`] cut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadTruncation {
+    /// Estimated tokens of the payload as given.
+    pub original_tokens: usize,
+    /// Estimated tokens of the payload text kept, without the notices.
+    pub kept_tokens: usize,
+}
+
+impl PayloadBudget {
+    /// Caps payloads at `max_tokens` estimated tokens, notices included,
+    /// keeping their beginning.
+    #[must_use]
+    pub const fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            retain: Retain::Head,
+            estimator: None,
+        }
+    }
+
+    /// Sets which part of an oversized payload is kept (default:
+    /// [`Retain::Head`]).
+    #[must_use]
+    pub const fn retain(mut self, retain: Retain) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    /// Estimates tokens with `estimator` instead of the adapter's own
+    /// ([`TokenEstimator::for_adapter`]).
+    #[must_use]
+    pub const fn estimator(mut self, estimator: TokenEstimator) -> Self {
+        self.estimator = Some(estimator);
+        self
+    }
+
+    /// The maximum number of estimated tokens.
+    #[must_use]
+    pub const fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    /// Truncates `payload` for a run of `adapter` if it is over budget.
+    ///
+    /// Returns the payload, with a notice at every cut, and what was cut;
+    /// `None` when the payload fits and is returned unchanged.
+    #[must_use]
+    pub fn apply(
+        &self,
+        payload: String,
+        adapter: CliAdapter,
+    ) -> (String, Option<PayloadTruncation>) {
+        let estimator = self
+            .estimator
+            .unwrap_or_else(|| TokenEstimator::for_adapter(adapter));
+        let original_tokens = estimator.estimate(&payload);
+        if original_tokens <= self.max_tokens {
+            return (payload, None);
+        }
+
+        let kept = self.kept_ranges(&payload, &estimator, original_tokens);
+        let mut truncated = String::new();
+        let mut kept_tokens = 0;
+        let mut at = 0;
+        let end = payload.len();
+        for (start, end) in kept.into_iter().chain(std::iter::once((end, end))) {
+            if start > at {
+                let omitted = estimator.estimate(&payload[at..start]);
+                if !truncated.is_empty() && !truncated.ends_with('\n') {
+                    truncated.push('\n');
+                }
+                let _ = writeln!(
+                    truncated,
+                    "[truncated: about {omitted} of {original_tokens} estimated tokens omitted here]"
+                );
+            }
+            kept_tokens += estimator.estimate(&payload[start..end]);
+            truncated.push_str(&payload[start..end]);
+            at = end;
+        }
+        (
+            truncated,
+            Some(PayloadTruncation {
+                original_tokens,
+                kept_tokens,
+            }),
+        )
+    }
+
+    /// Start and end bytes of the parts of `payload` to keep, in order.
+    fn kept_ranges(
+        &self,
+        payload: &str,
+        estimator: &TokenEstimator,
+        original_tokens: usize,
+    ) -> Vec<(usize, usize)> {
+        let len = payload.len();
+        let notices = match self.retain {
+            Retain::Head | Retain::Tail | Retain::HeadAndTail => 1,
+            Retain::Middle => 2,
+        };
+        let budget = self.max_tokens.saturating_sub(notices * NOTICE_TOKENS);
+        match self.retain {
+            Retain::Head => vec![(0, head_end(payload, estimator, budget))],
+            Retain::Tail => vec![(tail_start(payload, estimator, budget), len)],
+            Retain::HeadAndTail => {
+                let head = head_end(payload, estimator, budget / 2);
+                let tail = tail_start(payload, estimator, budget - budget / 2).max(head);
+                vec![(0, head), (tail, len)]
+            }
+            Retain::Middle => {
+                let skip = (original_tokens - budget.min(original_tokens)) / 2;
+                let start = tail_start(payload, estimator, original_tokens - skip);
+                let end = start + head_end(&payload[start..], estimator, budget);
+                vec![(start, end)]
+            }
+        }
+    }
+}
+
+/// End of the longest prefix of `text` within `tokens`, moved back to the
+/// last line break if that keeps at least half of it.
+fn head_end(text: &str, estimator: &TokenEstimator, tokens: usize) -> usize {
+    let budget = u64::try_from(tokens)
+        .unwrap_or(u64::MAX)
+        .saturating_mul(1000);
+    let mut spent = 0;
+    let mut end = text.len();
+    for (at, c) in text.char_indices() {
+        spent += estimator.cost(c);
+        if spent > budget {
+            end = at;
+            break;
+        }
+    }
+    match text[..end].rfind('\n') {
+        Some(line_end) if end < text.len() && line_end + 1 >= end / 2 => line_end + 1,
+        _ => end,
+    }
+}
+
+/// Start of the longest suffix of `text` within `tokens`, moved forward to
+/// the next line start if that keeps at least half of it.
+fn tail_start(text: &str, estimator: &TokenEstimator, tokens: usize) -> usize {
+    let budget = u64::try_from(tokens)
+        .unwrap_or(u64::MAX)
+        .saturating_mul(1000);
+    let mut spent = 0;
+    let mut start = 0;
+    for (at, c) in text.char_indices().rev() {
+        spent += estimator.cost(c);
+        if spent > budget {
+            start = at + c.len_utf8();
+            break;
+        }
+    }
+    match text[start..].find('\n') {
+        Some(line_end) if start > 0 && line_end < (text.len() - start) / 2 => start + line_end + 1,
+        _ => start,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_depend_on_script() {
+        let estimator = TokenEstimator::default();
+        assert_eq!(estimator.estimate("abcdefgh"), 2);
+        assert_eq!(estimator.estimate("日本語です"), 5);
+        assert_eq!(estimator.estimate("привет"), 3);
+        assert!(
+            TokenEstimator::for_adapter(CliAdapter::ClaudeCode).estimate(&"a".repeat(700))
+                > estimator.estimate(&"a".repeat(700))
+        );
+    }
+
+    #[test]
+    fn test_payload_within_budget_is_unchanged() {
+        let (payload, truncation) =
+            PayloadBudget::new(100).apply("short".to_string(), CliAdapter::Codex);
+        assert_eq!(payload, "short");
+        assert_eq!(truncation, None);
+    }
+
+    #[test]
+    fn test_retention_strategies_cut_on_lines() {
+        let mut text = String::new();
+        for i in 0..200 {
+            let _ = writeln!(text, "line {i:03}");
+        }
+        let apply = |retain| {
+            PayloadBudget::new(100)
+                .retain(retain)
+                .apply(text.clone(), CliAdapter::Codex)
+        };
+
+        let (head, truncation) = apply(Retain::Head);
+        let truncation = truncation.unwrap();
+        assert_eq!(truncation.original_tokens, 450);
+        assert!(truncation.kept_tokens <= 100 - NOTICE_TOKENS);
+        assert!(head.starts_with("line 000\n"));
+        assert!(head.ends_with("estimated tokens omitted here]\n"));
+        assert!(head
+            .lines()
+            .all(|line| line.starts_with("line ") || line.starts_with("[truncated")));
+
+        let (tail, _) = apply(Retain::Tail);
+        assert!(tail.starts_with("[truncated: about "));
+        assert!(tail.ends_with("line 199\n"));
+
+        let (ends, _) = apply(Retain::HeadAndTail);
+        assert!(ends.starts_with("line 000\n") && ends.ends_with("line 199\n"));
+        assert_eq!(ends.matches("[truncated").count(), 1);
+
+        let (middle, _) = apply(Retain::Middle);
+        assert_eq!(middle.matches("[truncated").count(), 2);
+        assert!(middle.contains("line 100\n"));
+    }
+}